@@ -19,7 +19,8 @@ use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot, watch};
 use tracing::{error, info, warn};
 
-use crate::ai::claude::{ClaudeClient, ClaudeConfig};
+use crate::ai::{AiConfig, AiFeature, AiProvider};
+use crate::ai::config::ProviderKind;
 use crate::coordinator::CoordinatorClient;
 use dashmap::DashMap;
 
@@ -42,13 +43,13 @@ pub struct UniversalAgent {
     next_session_id: Cell<u64>,
     /// MCP coordinator client for enhanced capabilities
     coordinator_client: Option<CoordinatorClient>,
-    /// Claude API client for AI-powered responses
-    claude_client: Option<Arc<ClaudeClient>>,
+    /// AI provider answering conversations
+    ai_provider: Option<Arc<dyn AiProvider>>,
     /// Conversation history per session (session_id -> messages)
     sessions: Arc<DashMap<String, Vec<ConversationMessage>>>,
     /// Workspace root directory
     workspace_root: PathBuf,
-    /// Tool registry for Claude-executable actions
+    /// Tool registry for model-executable actions
     tools: Arc<ToolRegistry>,
     /// Context provider for workspace awareness
     context: Arc<ContextProvider>,
@@ -66,7 +67,7 @@ struct ConversationMessage {
     timestamp: std::time::SystemTime,
 }
 
-/// System prompt for the Claude-powered development assistant
+/// System prompt for the AI-powered development assistant
 const SYSTEM_PROMPT: &str = r#"You are an expert software development assistant integrated into Universal LSP.
 
 ## Your Capabilities
@@ -105,6 +106,15 @@ Use markdown for formatting:
 
 Let's write great code together!"#;
 
+/// AI configuration of the default configuration file, or from the environment
+/// when the file is unreadable
+fn load_ai_config() -> AiConfig {
+    AiConfig::load(None).unwrap_or_else(|e| {
+        warn!("{:#}; using API keys from the environment", e);
+        AiConfig::from_env()
+    })
+}
+
 impl UniversalAgent {
     /// Create a new Universal ACP Agent
     pub fn new(
@@ -118,8 +128,8 @@ impl UniversalAgent {
         session_update_tx: mpsc::UnboundedSender<(acp::SessionNotification, oneshot::Sender<()>)>,
         workspace_root: PathBuf,
     ) -> Self {
-        // Initialize Claude client if API key is available
-        let claude_client = Self::init_claude_client();
+        // Initialize the chat provider if one is configured
+        let ai_provider = Self::init_ai_provider(&load_ai_config());
 
         // Initialize tool registry with workspace
         let tools = Arc::new(ToolRegistry::new(workspace_root.clone()));
@@ -135,8 +145,8 @@ impl UniversalAgent {
             });
 
         info!(
-            "UniversalAgent created (Claude: {}, tools: {}, workspace: {})",
-            if claude_client.is_some() { "enabled" } else { "disabled" },
+            "UniversalAgent created (AI: {}, tools: {}, workspace: {})",
+            ai_provider.as_ref().map_or("disabled", |provider| provider.name()),
             tools.count(),
             workspace_root.display()
         );
@@ -145,7 +155,7 @@ impl UniversalAgent {
             session_update_tx,
             next_session_id: Cell::new(1),
             coordinator_client: None,
-            claude_client,
+            ai_provider,
             sessions: Arc::new(DashMap::new()),
             workspace_root,
             tools,
@@ -166,6 +176,16 @@ impl UniversalAgent {
     pub async fn with_coordinator_and_workspace(
         session_update_tx: mpsc::UnboundedSender<(acp::SessionNotification, oneshot::Sender<()>)>,
         workspace_root: PathBuf,
+    ) -> Self {
+        Self::with_coordinator_and_ai_config(session_update_tx, workspace_root, &load_ai_config()).await
+    }
+
+    /// Create a new Universal ACP Agent with MCP coordinator integration, answering
+    /// with the chat provider of `ai`
+    pub async fn with_coordinator_and_ai_config(
+        session_update_tx: mpsc::UnboundedSender<(acp::SessionNotification, oneshot::Sender<()>)>,
+        workspace_root: PathBuf,
+        ai: &AiConfig,
     ) -> Self {
         let coordinator_client = match CoordinatorClient::connect().await {
             Ok(client) => {
@@ -179,7 +199,7 @@ impl UniversalAgent {
             }
         };
 
-        let claude_client = Self::init_claude_client();
+        let ai_provider = Self::init_ai_provider(ai);
 
         // Initialize tool registry with workspace
         let tools = Arc::new(ToolRegistry::new(workspace_root.clone()));
@@ -195,8 +215,8 @@ impl UniversalAgent {
             });
 
         info!(
-            "UniversalAgent created (Claude: {}, MCP: {}, tools: {}, workspace: {})",
-            if claude_client.is_some() { "enabled" } else { "disabled" },
+            "UniversalAgent created (AI: {}, MCP: {}, tools: {}, workspace: {})",
+            ai_provider.as_ref().map_or("disabled", |provider| provider.name()),
             if coordinator_client.is_some() { "enabled" } else { "disabled" },
             tools.count(),
             workspace_root.display()
@@ -206,7 +226,7 @@ impl UniversalAgent {
             session_update_tx,
             next_session_id: Cell::new(1),
             coordinator_client,
-            claude_client,
+            ai_provider,
             sessions: Arc::new(DashMap::new()),
             workspace_root,
             tools,
@@ -216,36 +236,29 @@ impl UniversalAgent {
         }
     }
 
-    /// Initialize Claude API client from environment variable
-    fn init_claude_client() -> Option<Arc<ClaudeClient>> {
-        match std::env::var("ANTHROPIC_API_KEY") {
-            Ok(api_key) if !api_key.is_empty() => {
-                let config = ClaudeConfig {
-                    api_key,
-                    model: "claude-sonnet-4-20250514".to_string(),
-                    max_tokens: 4096,
-                    temperature: 0.7,  // Higher for more creative responses
-                    timeout_ms: 30000,  // 30s timeout for longer responses
-                };
-
-                match ClaudeClient::new(config) {
-                    Ok(client) => {
-                        info!("Claude API client initialized successfully");
-                        Some(Arc::new(client))
-                    }
-                    Err(e) => {
-                        error!("Failed to initialize Claude client: {}", e);
-                        None
-                    }
-                }
+    /// Initialize the provider of the chat feature
+    ///
+    /// Conversations get longer, more creative replies than the editor features
+    /// unless the provider's configuration says otherwise.
+    fn init_ai_provider(config: &AiConfig) -> Option<Arc<dyn AiProvider>> {
+        let mut config = config.clone();
+        for provider in config.providers.values_mut() {
+            provider.max_tokens.get_or_insert(4096);
+            provider.temperature.get_or_insert(0.7);
+            // Local models keep their own, longer timeout
+            if provider.kind != ProviderKind::Ollama {
+                provider.timeout_ms.get_or_insert(30000);
             }
-            Ok(_) => {
-                warn!("ANTHROPIC_API_KEY is empty");
-                None
+        }
+
+        match config.build().get(AiFeature::Chat) {
+            Some(provider) => {
+                info!("Chat provider initialized: {}", provider.name());
+                Some(provider)
             }
-            Err(_) => {
-                warn!("ANTHROPIC_API_KEY not set - Claude integration disabled");
-                info!("Set ANTHROPIC_API_KEY environment variable to enable Claude");
+            None => {
+                warn!("No AI provider configured for chat - AI integration disabled");
+                info!("Set ANTHROPIC_API_KEY or configure [ai] providers to enable AI responses");
                 None
             }
         }
@@ -294,7 +307,7 @@ impl UniversalAgent {
         Ok(messages.join("\n"))
     }
 
-    /// Generate fallback response when Claude is not available
+    /// Generate fallback response when no AI provider is available
     fn generate_fallback_response(&self, user_message: &str) -> String {
         format!(
             "I'm the Universal LSP ACP Agent, but Claude API integration is not available.\n\n\
@@ -308,6 +321,8 @@ impl UniversalAgent {
              To enable Claude AI responses:\n\
              1. Set ANTHROPIC_API_KEY environment variable\n\
              2. Restart the ACP agent\n\n\
+             To use another provider, such as a local Ollama model, configure it\n\
+             under [ai] in ~/.universal-lsp/config.toml.\n\n\
              MCP Integration: {}\n\
              Workspace: {}",
             user_message,
@@ -316,10 +331,10 @@ impl UniversalAgent {
         )
     }
 
-    /// Call Claude API with tool support and handle tool execution loop
-    async fn call_claude_with_tools(
+    /// Call the AI provider with tool support and handle tool execution loop
+    async fn call_ai_with_tools(
         &self,
-        client: &crate::ai::claude::ClaudeClient,
+        client: &dyn AiProvider,
         mut messages: Vec<crate::ai::claude::Message>,
         tool_definitions: Vec<serde_json::Value>,
         system_prompt: String,
@@ -337,7 +352,7 @@ impl UniversalAgent {
                 break;
             }
 
-            // Call Claude with tools
+            // Call the model with tools
             let response = client
                 .send_message_with_tools(
                     &messages,
@@ -351,14 +366,14 @@ impl UniversalAgent {
                 accumulated_text.extend(response.text_blocks.clone());
             }
 
-            // Check if Claude wants to use tools
+            // Check if the model wants to use tools
             if response.tool_uses.is_empty() {
                 // No tools to execute, we're done
-                info!("Claude response complete after {} iterations", iteration);
+                info!("{} response complete after {} iterations", client.name(), iteration);
                 break;
             }
 
-            info!("Claude requested {} tool(s)", response.tool_uses.len());
+            info!("{} requested {} tool(s)", client.name(), response.tool_uses.len());
 
             // Execute tools and collect results
             let mut tool_results = Vec::new();
@@ -412,13 +427,13 @@ impl UniversalAgent {
         Ok(accumulated_text.join("\n\n"))
     }
 
-    /// Call Claude with tools using streaming for real-time updates
+    /// Call the AI provider with tools using streaming for real-time updates
     ///
-    /// This method is similar to call_claude_with_tools but streams responses
+    /// This method is similar to call_ai_with_tools but streams responses
     /// incrementally via session notifications, providing a better UX.
-    async fn call_claude_with_tools_streaming(
+    async fn call_ai_with_tools_streaming(
         &self,
-        client: &crate::ai::claude::ClaudeClient,
+        client: &dyn AiProvider,
         mut messages: Vec<crate::ai::claude::Message>,
         tool_definitions: Vec<serde_json::Value>,
         system_prompt: String,
@@ -454,7 +469,7 @@ impl UniversalAgent {
             let session_update_tx = self.session_update_tx.clone();
             let session_id_clone = session_id.clone();
 
-            let callback = move |event: StreamEvent| -> Result<(), anyhow::Error> {
+            let mut callback = move |event: StreamEvent| -> Result<(), anyhow::Error> {
                 match event {
                     StreamEvent::ContentBlockDelta { delta, .. } => {
                         if let ContentDelta::TextDelta { text } = delta {
@@ -485,13 +500,13 @@ impl UniversalAgent {
                 Ok(())
             };
 
-            // Call Claude with streaming
+            // Call the model with streaming
             let response = client
                 .send_message_with_tools_streaming(
                     &messages,
                     Some(tool_definitions.clone()),
                     Some(system_prompt.clone()),
-                    callback,
+                    &mut callback,
                 )
                 .await?;
 
@@ -500,13 +515,13 @@ impl UniversalAgent {
                 accumulated_text.extend(response.text_blocks.clone());
             }
 
-            // Check if Claude wants to use tools
+            // Check if the model wants to use tools
             if response.tool_uses.is_empty() {
                 info!("Streaming response complete after {} iterations", iteration);
                 break;
             }
 
-            info!("Claude requested {} tool(s) in streaming mode", response.tool_uses.len());
+            info!("{} requested {} tool(s) in streaming mode", client.name(), response.tool_uses.len());
 
             // Send notification about tool execution
             let (tx, _rx) = oneshot::channel();
@@ -739,9 +754,9 @@ impl acp::Agent for UniversalAgent {
             .entry(session_id_str.clone())
             .or_insert_with(Vec::new);
 
-        // 3. Generate response (Claude or fallback)
-        let response_text = if let Some(client) = &self.claude_client {
-            // Build messages for Claude API
+        // 3. Generate response (AI provider or fallback)
+        let response_text = if let Some(client) = &self.ai_provider {
+            // Build messages for the AI provider
             let mut messages = Vec::new();
 
             // Add conversation history
@@ -762,37 +777,43 @@ impl acp::Agent for UniversalAgent {
             let tool_definitions = self.tools.get_tool_definitions();
             let system_prompt = self.build_system_prompt().await;
 
-            // Call Claude API with streaming for real-time updates
-            info!("Calling Claude API with streaming ({} messages, {} tools)", history.len(), tool_definitions.len());
+            // Call the AI provider with streaming for real-time updates
+            info!(
+                "Calling {} with streaming ({} messages, {} tools)",
+                client.name(),
+                history.len(),
+                tool_definitions.len()
+            );
 
-            match self.call_claude_with_tools_streaming(
-                client,
+            match self.call_ai_with_tools_streaming(
+                client.as_ref(),
                 messages,
                 tool_definitions,
                 system_prompt,
                 arguments.session_id.clone()
             ).await {
                 Ok(response) => {
-                    info!("Claude streaming response complete ({} chars)", response.len());
+                    info!("{} streaming response complete ({} chars)", client.name(), response.len());
                     response
                 }
                 Err(e) => {
-                    error!("Claude API error: {}", e);
+                    error!("{} API error: {}", client.name(), e);
                     format!(
-                        "I encountered an error communicating with Claude API: {}\n\n\
+                        "I encountered an error communicating with {} API: {}\n\n\
                          Please check:\n\
                          - Your API key is valid\n\
                          - You have available credits\n\
                          - Network connectivity is working\n\n\
                          Error details: {}",
+                        client.name(),
                         e,
                         e
                     )
                 }
             }
         } else {
-            // Fallback when Claude is not available
-            warn!("Claude client not available, using fallback response");
+            // Fallback when no AI provider is available
+            warn!("AI provider not available, using fallback response");
             self.generate_fallback_response(&user_message)
         };
 
@@ -981,6 +1002,11 @@ pub async fn run_agent() -> Result<()> {
 
 /// Run the ACP agent server on stdio with specified workspace
 pub async fn run_agent_with_workspace(workspace_root: PathBuf) -> Result<()> {
+    run_agent_with_config(workspace_root, load_ai_config()).await
+}
+
+/// Run the ACP agent server on stdio, answering with the chat provider of `ai`
+pub async fn run_agent_with_config(workspace_root: PathBuf, ai: AiConfig) -> Result<()> {
     use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
     info!(
@@ -999,15 +1025,15 @@ pub async fn run_agent_with_workspace(workspace_root: PathBuf) -> Result<()> {
             let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
 
             // Create agent with MCP coordinator integration and workspace
-            let agent = UniversalAgent::with_coordinator_and_workspace(tx, workspace_root.clone())
+            let agent = UniversalAgent::with_coordinator_and_ai_config(tx, workspace_root.clone(), &ai)
                 .await;
 
             let has_mcp = agent.coordinator_client.is_some();
-            let has_claude = agent.claude_client.is_some();
+            let ai_name = agent.ai_provider.as_ref().map(|provider| provider.name().to_string());
 
             info!(
-                "ACP agent initialized (Claude: {}, MCP: {}, workspace: {})",
-                if has_claude { "✅" } else { "❌" },
+                "ACP agent initialized (AI: {}, MCP: {}, workspace: {})",
+                ai_name.as_deref().map_or("❌".to_string(), |name| format!("✅ {}", name)),
                 if has_mcp { "✅" } else { "❌" },
                 workspace_root.display()
             );
@@ -1320,45 +1346,72 @@ mod tests {
     // ========================================================================
 
     #[test]
-    fn test_init_claude_client_with_api_key() {
+    fn test_init_ai_provider_with_api_key() {
         // Set API key
         std::env::set_var("ANTHROPIC_API_KEY", "sk-ant-test-key-123");
 
-        let client = UniversalAgent::init_claude_client();
+        let client = UniversalAgent::init_ai_provider(&AiConfig::from_env());
 
         assert!(client.is_some(), "Claude client should be initialized with API key");
+        assert_eq!(client.unwrap().name(), "Claude");
 
         // Cleanup
         std::env::remove_var("ANTHROPIC_API_KEY");
     }
 
     #[test]
-    fn test_init_claude_client_without_api_key() {
-        // Remove API key
+    fn test_init_ai_provider_without_api_key() {
+        // Remove API keys
         let original = std::env::var("ANTHROPIC_API_KEY").ok();
+        let github_token = std::env::var("GITHUB_TOKEN").ok();
         std::env::remove_var("ANTHROPIC_API_KEY");
+        std::env::remove_var("GITHUB_TOKEN");
 
-        let client = UniversalAgent::init_claude_client();
+        let client = UniversalAgent::init_ai_provider(&AiConfig::from_env());
 
-        assert!(client.is_none(), "Claude client should be None without API key");
+        assert!(client.is_none(), "AI provider should be None without API keys");
 
         // Restore original
         if let Some(key) = original {
             std::env::set_var("ANTHROPIC_API_KEY", key);
         }
+        if let Some(token) = github_token {
+            std::env::set_var("GITHUB_TOKEN", token);
+        }
     }
 
     #[test]
-    fn test_init_claude_client_with_empty_api_key() {
+    fn test_init_ai_provider_with_empty_api_key() {
         // Set empty API key
+        let github_token = std::env::var("GITHUB_TOKEN").ok();
+        std::env::remove_var("GITHUB_TOKEN");
         std::env::set_var("ANTHROPIC_API_KEY", "");
 
-        let client = UniversalAgent::init_claude_client();
+        let client = UniversalAgent::init_ai_provider(&AiConfig::from_env());
 
-        assert!(client.is_none(), "Claude client should be None with empty API key");
+        assert!(client.is_none(), "AI provider should be None with empty API key");
 
         // Cleanup
         std::env::remove_var("ANTHROPIC_API_KEY");
+        if let Some(token) = github_token {
+            std::env::set_var("GITHUB_TOKEN", token);
+        }
+    }
+
+    #[test]
+    fn test_init_ai_provider_for_local_model() {
+        let config: AiConfig = serde_json::from_value(serde_json::json!({
+            "providers": {
+                "claude": { "kind": "anthropic", "api_key": "test-key" },
+                "local": { "kind": "ollama", "model": "codellama" }
+            },
+            "default": "claude",
+            "features": { "chat": "local" }
+        }))
+        .unwrap();
+
+        let client = UniversalAgent::init_ai_provider(&config);
+        assert_eq!(client.unwrap().name(), "Ollama");
     }
 
     #[tokio::test]
@@ -1552,11 +1605,11 @@ mod tests {
     fn test_claude_config_values() {
         // Verify Claude configuration values are sensible
         std::env::set_var("ANTHROPIC_API_KEY", "test-key");
-        let client = UniversalAgent::init_claude_client();
+        let client = UniversalAgent::init_ai_provider(&AiConfig::from_env());
 
         assert!(client.is_some(), "Client should initialize with test key");
 
-        // The config values are set in init_claude_client():
+        // Unless configured, init_ai_provider() uses for chat:
         // - model: "claude-sonnet-4-20250514"
         // - max_tokens: 4096
         // - temperature: 0.7
@@ -250,7 +250,7 @@ pub fn should_summarize(messages: &[ConversationMessage]) -> bool {
 /// Create a summary of old messages to compress context
 pub async fn summarize_conversation(
     messages: &[ConversationMessage],
-    claude_client: &crate::ai::claude::ClaudeClient,
+    ai_provider: &dyn crate::ai::AiProvider,
 ) -> Result<String> {
     // Take the first 80% of messages for summarization
     let cutoff = (messages.len() as f32 * 0.8) as usize;
@@ -262,7 +262,7 @@ pub async fn summarize_conversation(
         conversation_text.push_str(&format!("{}: {}\n\n", msg.role, msg.content));
     }
 
-    // Ask Claude to summarize
+    // Ask the AI provider to summarize
     let prompt = format!(
         "Please provide a concise summary of this conversation history. \
          Focus on key points, decisions made, and important context. \
@@ -275,7 +275,7 @@ pub async fn summarize_conversation(
         content: prompt,
     }];
 
-    let summary = claude_client.send_message(&messages).await
+    let summary = ai_provider.send_message(&messages).await
         .context("Failed to generate conversation summary")?;
 
     info!("Summarized {} messages into {} chars", to_summarize.len(), summary.len());
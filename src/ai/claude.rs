@@ -4,11 +4,17 @@
 //! intelligent, context-aware code completions.
 
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::time::Duration;
 
+use super::provider::{completion_prompt, suggestions_from_reply, AiProvider, StreamSink};
+
+/// Anthropic Messages API endpoint
+const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+
 /// Claude API configuration
 #[derive(Debug, Clone)]
 pub struct ClaudeConfig {
@@ -36,6 +42,8 @@ impl Default for ClaudeConfig {
 pub struct ClaudeClient {
     config: ClaudeConfig,
     http_client: reqwest::Client,
+    /// Messages API URL
+    endpoint: String,
 }
 
 /// Claude Messages API request structure
@@ -198,7 +206,14 @@ impl ClaudeClient {
             .build()
             .context("Failed to build HTTP client")?;
 
-        Ok(Self { config, http_client })
+        Ok(Self { config, http_client, endpoint: MESSAGES_URL.to_string() })
+    }
+
+    /// Send requests to another Messages API URL, such as a proxy or a local
+    /// stand-in
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
     }
 
     /// Send a multi-turn conversation to Claude and get response
@@ -226,7 +241,7 @@ impl ClaudeClient {
 
         let response = self
             .http_client
-            .post("https://api.anthropic.com/v1/messages")
+            .post(&self.endpoint)
             .json(&request)
             .send()
             .await
@@ -296,7 +311,7 @@ impl ClaudeClient {
 
         let response = self
             .http_client
-            .post("https://api.anthropic.com/v1/messages")
+            .post(&self.endpoint)
             .json(&request)
             .send()
             .await
@@ -316,6 +331,8 @@ impl ClaudeClient {
         let mut text_blocks: Vec<String> = Vec::new();
         let mut tool_uses: Vec<ToolUse> = Vec::new();
         let mut current_text = String::new();
+        // Tool use being streamed: id, name and input JSON so far
+        let mut current_tool: Option<(String, String, String)> = None;
         let mut stop_reason: Option<String> = None;
 
         // Read response body as stream
@@ -339,16 +356,33 @@ impl ClaudeClient {
 
                     // Accumulate response
                     match event {
-                        StreamEvent::ContentBlockDelta { delta, .. } => {
-                            if let ContentDelta::TextDelta { text } = delta {
-                                current_text.push_str(&text);
-                            }
+                        StreamEvent::ContentBlockStart { content_block, .. }
+                            if content_block.get("type").and_then(|t| t.as_str()) == Some("tool_use") =>
+                        {
+                            let field = |name: &str| content_block.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                            current_tool = Some((field("id"), field("name"), String::new()));
                         }
+                        StreamEvent::ContentBlockDelta { delta, .. } => match delta {
+                            ContentDelta::TextDelta { text } => current_text.push_str(&text),
+                            ContentDelta::InputJsonDelta { partial_json } => {
+                                if let Some((_, _, input)) = current_tool.as_mut() {
+                                    input.push_str(&partial_json);
+                                }
+                            }
+                        },
                         StreamEvent::ContentBlockStop { .. } => {
                             if !current_text.is_empty() {
                                 text_blocks.push(current_text.clone());
                                 current_text.clear();
                             }
+                            if let Some((id, name, input)) = current_tool.take() {
+                                let input = if input.trim().is_empty() {
+                                    Value::Object(Default::default())
+                                } else {
+                                    serde_json::from_str(&input).context("Invalid tool input in stream")?
+                                };
+                                tool_uses.push(ToolUse { id, name, input });
+                            }
                         }
                         StreamEvent::MessageDelta { delta } => {
                             if let Some(reason) = delta.get("stop_reason").and_then(|v| v.as_str()) {
@@ -423,31 +457,7 @@ impl ClaudeClient {
 
     /// Build a prompt for code completion
     fn build_completion_prompt(&self, ctx: &CompletionContext) -> String {
-        let mut prompt = format!(
-            "You are an expert {} programmer. ",
-            ctx.language
-        );
-
-        if let Some(context) = &ctx.context {
-            prompt.push_str(&format!("Context:\n{}\n\n", context));
-        }
-
-        prompt.push_str(&format!(
-            "Complete the following {} code. Provide ONLY the completion text, no explanations.\n\n",
-            ctx.language
-        ));
-
-        prompt.push_str("Code to complete:\n");
-        prompt.push_str(&ctx.prefix);
-        prompt.push_str("<CURSOR>");
-
-        if let Some(suffix) = &ctx.suffix {
-            prompt.push_str(suffix);
-        }
-
-        prompt.push_str("\n\nProvide the completion that should replace <CURSOR>. Return only the code, nothing else.");
-
-        prompt
+        completion_prompt(ctx)
     }
 
     /// Query Claude's Messages API
@@ -462,31 +472,8 @@ impl ClaudeClient {
 
     /// Parse Claude's response into completion suggestions
     fn parse_completion_response(&self, response: &str, _ctx: &CompletionContext) -> Result<Vec<CompletionSuggestion>> {
-        // Clean up the response
-        let cleaned = response.trim();
-
-        // Split into multiple suggestions if Claude provides alternatives
-        let suggestions: Vec<CompletionSuggestion> = cleaned
-            .lines()
-            .filter(|line| !line.is_empty())
-            .take(5) // Limit to 5 suggestions
-            .enumerate()
-            .map(|(idx, text)| CompletionSuggestion {
-                text: text.trim().to_string(),
-                confidence: 1.0 - (idx as f32 * 0.1), // Decreasing confidence
-                detail: Some(format!("AI-generated completion (Claude {})", self.config.model)),
-            })
-            .collect();
-
-        if suggestions.is_empty() {
-            return Ok(vec![CompletionSuggestion {
-                text: cleaned.to_string(),
-                confidence: 0.8,
-                detail: Some("AI-generated completion".to_string()),
-            }]);
-        }
-
-        Ok(suggestions)
+        let detail = format!("AI-generated completion (Claude {})", self.config.model);
+        Ok(suggestions_from_reply(response, &detail))
     }
 
     /// Check if Claude API is available and configured
@@ -495,6 +482,36 @@ impl ClaudeClient {
     }
 }
 
+#[async_trait]
+impl AiProvider for ClaudeClient {
+    fn name(&self) -> &str {
+        "Claude"
+    }
+
+    async fn get_completions(&self, ctx: &CompletionContext) -> Result<Vec<CompletionSuggestion>> {
+        ClaudeClient::get_completions(self, ctx).await
+    }
+
+    async fn send_message_with_tools(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+    ) -> Result<ClaudeToolResponse> {
+        ClaudeClient::send_message_with_tools(self, messages, tools, system).await
+    }
+
+    async fn send_message_with_tools_streaming(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+        callback: StreamSink<'_>,
+    ) -> Result<ClaudeToolResponse> {
+        ClaudeClient::send_message_with_tools_streaming(self, messages, tools, system, callback).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -505,6 +522,7 @@ mod tests {
         let client = ClaudeClient {
             config: config.clone(),
             http_client: reqwest::Client::new(),
+            endpoint: MESSAGES_URL.to_string(),
         };
 
         let ctx = CompletionContext {
@@ -0,0 +1,377 @@
+//! Choice of AI provider per feature
+//!
+//! Providers are named in the `[ai]` table of the configuration file, and each
+//! feature lists the providers it asks, in order:
+//!
+//! ```toml
+//! [ai.providers.claude]
+//! kind = "anthropic"
+//!
+//! [ai.providers.local]
+//! kind = "ollama"
+//! model = "qwen2.5-coder:7b"
+//!
+//! [ai]
+//! default = "claude"
+//!
+//! [ai.features]
+//! completion = ["local", "claude"]
+//! lint = "local"
+//! ```
+//!
+//! Features without an entry use `default`. Without a configuration file, Claude
+//! and Copilot are used when `ANTHROPIC_API_KEY` or `GITHUB_TOKEN` is set.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::claude::{ClaudeClient, ClaudeConfig};
+use super::copilot::{CopilotClient, CopilotConfig};
+use super::ollama::{OllamaClient, OllamaConfig};
+use super::openai::{OpenAiClient, OpenAiConfig};
+use super::provider::AiProvider;
+
+/// Feature asking an AI provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AiFeature {
+    /// Inline code completions
+    Completion,
+    /// Explanations added to hovers
+    Hover,
+    /// Explain, optimize and generate-tests code actions
+    CodeActions,
+    /// AI diagnostics
+    Lint,
+    /// Summaries documenting completion items
+    Summaries,
+    /// ACP agent conversations
+    Chat,
+}
+
+/// Kind of backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Anthropic,
+    Copilot,
+    /// Any server with the OpenAI chat completions API
+    #[serde(rename = "openai")]
+    OpenAi,
+    Ollama,
+}
+
+/// A configured provider; unset settings keep the backend's defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
+    /// API URL: the messages URL for Anthropic, the completions URL for Copilot,
+    /// the base URL for OpenAI-compatible servers and Ollama
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// The key, or `$NAME` to read it from an environment variable; defaults to
+    /// the kind's usual variable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// One provider name or several, in order of preference
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ProviderNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl ProviderNames {
+    fn names(&self) -> &[String] {
+        match self {
+            Self::One(name) => std::slice::from_ref(name),
+            Self::Many(names) => names,
+        }
+    }
+}
+
+/// `[ai]` configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AiConfig {
+    /// Providers by name
+    #[serde(default)]
+    pub providers: BTreeMap<String, ProviderConfig>,
+    /// Providers of features without an entry in `features`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<ProviderNames>,
+    #[serde(default)]
+    pub features: HashMap<AiFeature, ProviderNames>,
+}
+
+impl ProviderConfig {
+    fn new(kind: ProviderKind) -> Self {
+        Self { kind, endpoint: None, model: None, api_key: None, max_tokens: None, temperature: None, timeout_ms: None }
+    }
+
+    /// The API key, read from the environment when configured as `$NAME`
+    fn resolve_api_key(&self) -> String {
+        let default_var = match self.kind {
+            ProviderKind::Anthropic => "ANTHROPIC_API_KEY",
+            ProviderKind::Copilot => "GITHUB_TOKEN",
+            ProviderKind::OpenAi => "OPENAI_API_KEY",
+            ProviderKind::Ollama => return String::new(),
+        };
+        match self.api_key.as_deref() {
+            Some(key) => match key.strip_prefix('$') {
+                Some(var) => std::env::var(var).unwrap_or_default(),
+                None => key.to_string(),
+            },
+            None => std::env::var(default_var).unwrap_or_default(),
+        }
+    }
+
+    /// Client for this provider
+    pub fn build(&self) -> Result<Arc<dyn AiProvider>> {
+        let api_key = self.resolve_api_key();
+        let provider: Arc<dyn AiProvider> = match self.kind {
+            ProviderKind::Anthropic => {
+                let defaults = ClaudeConfig::default();
+                let client = ClaudeClient::new(ClaudeConfig {
+                    api_key,
+                    model: self.model.clone().unwrap_or(defaults.model),
+                    max_tokens: self.max_tokens.unwrap_or(defaults.max_tokens),
+                    temperature: self.temperature.unwrap_or(defaults.temperature),
+                    timeout_ms: self.timeout_ms.unwrap_or(defaults.timeout_ms),
+                })?;
+                match &self.endpoint {
+                    Some(endpoint) => Arc::new(client.with_endpoint(endpoint.clone())),
+                    None => Arc::new(client),
+                }
+            }
+            ProviderKind::Copilot => {
+                let defaults = CopilotConfig::default();
+                Arc::new(CopilotClient::new(CopilotConfig {
+                    api_key,
+                    endpoint: self.endpoint.clone().unwrap_or(defaults.endpoint),
+                    chat_model: self.model.clone().unwrap_or(defaults.chat_model),
+                    max_tokens: self.max_tokens.unwrap_or(defaults.max_tokens),
+                    temperature: self.temperature.unwrap_or(defaults.temperature),
+                    timeout_ms: self.timeout_ms.unwrap_or(defaults.timeout_ms),
+                })?)
+            }
+            ProviderKind::OpenAi => {
+                let defaults = OpenAiConfig::default();
+                Arc::new(OpenAiClient::new(OpenAiConfig {
+                    api_key,
+                    base_url: self.endpoint.clone().unwrap_or(defaults.base_url),
+                    model: self.model.clone().unwrap_or(defaults.model),
+                    max_tokens: self.max_tokens.unwrap_or(defaults.max_tokens),
+                    temperature: self.temperature.unwrap_or(defaults.temperature),
+                    timeout_ms: self.timeout_ms.unwrap_or(defaults.timeout_ms),
+                })?)
+            }
+            ProviderKind::Ollama => {
+                let defaults = OllamaConfig::default();
+                Arc::new(OllamaClient::new(OllamaConfig {
+                    base_url: self.endpoint.clone().unwrap_or(defaults.base_url),
+                    model: self.model.clone().unwrap_or(defaults.model),
+                    max_tokens: self.max_tokens.unwrap_or(defaults.max_tokens),
+                    temperature: self.temperature.unwrap_or(defaults.temperature),
+                    timeout_ms: self.timeout_ms.unwrap_or(defaults.timeout_ms),
+                })?)
+            }
+        };
+        Ok(provider)
+    }
+}
+
+impl AiConfig {
+    /// Claude and Copilot, for the API keys set in the environment
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        let has_var = |var: &str| std::env::var(var).map(|value| !value.is_empty()).unwrap_or(false);
+        if has_var("ANTHROPIC_API_KEY") {
+            config.providers.insert("claude".to_string(), ProviderConfig::new(ProviderKind::Anthropic));
+        }
+        if has_var("GITHUB_TOKEN") {
+            config.providers.insert("copilot".to_string(), ProviderConfig::new(ProviderKind::Copilot));
+        }
+        let names: Vec<String> = ["claude", "copilot"]
+            .into_iter()
+            .filter(|name| config.providers.contains_key(*name))
+            .map(str::to_string)
+            .collect();
+        if !names.is_empty() {
+            config.default = Some(ProviderNames::Many(names));
+        }
+        config
+    }
+
+    /// The `[ai]` table of `path`, or of `~/.universal-lsp/config.toml`; falls
+    /// back to [`AiConfig::from_env`] when no provider is configured
+    ///
+    /// Files ending in `.json` are read as JSON, others as TOML.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let default_path = dirs::home_dir().map(|home| home.join(".universal-lsp").join("config.toml"));
+        let path: Option<PathBuf> = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => default_path.filter(|path| path.exists()),
+        };
+        let Some(path) = path else {
+            return Ok(Self::from_env());
+        };
+
+        let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let config = Self::parse(&text, path.extension().is_some_and(|ext| ext == "json"))
+            .with_context(|| format!("Invalid AI configuration in {}", path.display()))?;
+        if config.providers.is_empty() {
+            return Ok(Self::from_env());
+        }
+        Ok(config)
+    }
+
+    /// The `[ai]` table of a configuration file
+    fn parse(text: &str, json: bool) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct File {
+            #[serde(default)]
+            ai: AiConfig,
+        }
+        let file: File = if json { serde_json::from_str(text)? } else { toml::from_str(text)? };
+        Ok(file.ai)
+    }
+
+    /// Clients of the configured providers; providers that fail to start are
+    /// logged and left out
+    pub fn build(&self) -> AiProviders {
+        let mut providers = HashMap::new();
+        for (name, config) in &self.providers {
+            match config.build() {
+                Ok(provider) => {
+                    tracing::info!("AI provider '{}' ({}) initialized", name, provider.name());
+                    providers.insert(name.clone(), provider);
+                }
+                Err(e) => tracing::warn!("Failed to initialize AI provider '{}': {}", name, e),
+            }
+        }
+
+        let resolve = |names: &ProviderNames| -> Vec<Arc<dyn AiProvider>> {
+            names
+                .names()
+                .iter()
+                .filter_map(|name| {
+                    let provider = providers.get(name).cloned();
+                    if provider.is_none() && !self.providers.contains_key(name) {
+                        tracing::warn!("Unknown AI provider '{}'", name);
+                    }
+                    provider
+                })
+                .collect()
+        };
+        AiProviders {
+            default: self.default.as_ref().map(&resolve).unwrap_or_default(),
+            features: self.features.iter().map(|(feature, names)| (*feature, resolve(names))).collect(),
+        }
+    }
+}
+
+/// Providers of each feature
+#[derive(Debug, Clone, Default)]
+pub struct AiProviders {
+    default: Vec<Arc<dyn AiProvider>>,
+    features: HashMap<AiFeature, Vec<Arc<dyn AiProvider>>>,
+}
+
+impl AiProviders {
+    /// Providers of `feature`, in order of preference
+    pub fn for_feature(&self, feature: AiFeature) -> &[Arc<dyn AiProvider>] {
+        self.features.get(&feature).unwrap_or(&self.default)
+    }
+
+    /// Preferred provider of `feature`
+    pub fn get(&self, feature: AiFeature) -> Option<Arc<dyn AiProvider>> {
+        self.for_feature(feature).first().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = AiConfig::parse(
+            r#"
+            [ai]
+            default = "claude"
+
+            [ai.providers.claude]
+            kind = "anthropic"
+            api_key = "$MY_KEY"
+
+            [ai.providers.local]
+            kind = "ollama"
+            model = "codellama"
+
+            [ai.features]
+            completion = ["local", "claude"]
+            chat = "local"
+            "#,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(config.providers["claude"].kind, ProviderKind::Anthropic);
+        assert_eq!(config.providers["claude"].api_key.as_deref(), Some("$MY_KEY"));
+        assert_eq!(config.providers["local"].model.as_deref(), Some("codellama"));
+        assert_eq!(config.default, Some(ProviderNames::One("claude".to_string())));
+        assert_eq!(config.features[&AiFeature::Completion].names(), ["local", "claude"]);
+
+        let json = r#"{ "ai": { "providers": { "lm": { "kind": "openai", "endpoint": "http://localhost:1234/v1" } } } }"#;
+        let config = AiConfig::parse(json, true).unwrap();
+        assert_eq!(config.providers["lm"].kind, ProviderKind::OpenAi);
+
+        assert!(AiConfig::parse("[ai.providers.x]\nkind = \"unknown\"\n", false).is_err());
+    }
+
+    #[test]
+    fn test_providers_per_feature() {
+        let mut config = AiConfig::default();
+        let mut local = ProviderConfig::new(ProviderKind::Ollama);
+        local.model = Some("codellama".to_string());
+        let mut lm = ProviderConfig::new(ProviderKind::OpenAi);
+        lm.endpoint = Some("http://localhost:1234/v1".to_string());
+        lm.api_key = Some(String::new());
+        config.providers.insert("local".to_string(), local);
+        config.providers.insert("lm".to_string(), lm);
+        config.default = Some(ProviderNames::One("local".to_string()));
+        config.features.insert(AiFeature::Chat, ProviderNames::Many(vec!["lm".to_string(), "missing".to_string()]));
+        config.features.insert(AiFeature::Lint, ProviderNames::Many(Vec::new()));
+
+        let providers = config.build();
+        assert_eq!(providers.get(AiFeature::Completion).unwrap().name(), "Ollama");
+        let chat: Vec<&str> = providers.for_feature(AiFeature::Chat).iter().map(|p| p.name()).collect();
+        assert_eq!(chat, ["OpenAI"]);
+        // An empty list turns the feature off
+        assert!(providers.get(AiFeature::Lint).is_none());
+        assert!(AiProviders::default().get(AiFeature::Hover).is_none());
+    }
+
+    #[test]
+    fn test_load_without_providers_uses_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[server]\nlog_level = \"info\"\n").unwrap();
+        assert_eq!(AiConfig::load(Some(&path)).unwrap(), AiConfig::from_env());
+
+        assert!(AiConfig::load(Some(&dir.path().join("missing.toml"))).is_err());
+    }
+}
@@ -4,14 +4,22 @@
 //! intelligent, context-aware code completions.
 
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::time::Duration;
 
+use super::openai::{OpenAiClient, OpenAiConfig};
+use super::provider::{AiProvider, StreamSink};
+
 /// GitHub Copilot API configuration
 #[derive(Debug, Clone)]
 pub struct CopilotConfig {
     pub api_key: String,
     pub endpoint: String,
+    /// Model answering conversations, through the OpenAI-compatible chat API next
+    /// to `endpoint`
+    pub chat_model: String,
     pub max_tokens: usize,
     pub temperature: f32,
     pub timeout_ms: u64,
@@ -22,6 +30,7 @@ impl Default for CopilotConfig {
         Self {
             api_key: String::new(),
             endpoint: "https://api.githubcopilot.com/completions".to_string(),
+            chat_model: "gpt-4o".to_string(),
             max_tokens: 1024,
             temperature: 0.3,
             timeout_ms: 10000,
@@ -64,7 +73,7 @@ struct Choice {
 }
 
 // Reuse shared types from claude module
-use super::claude::{CompletionContext, CompletionSuggestion};
+use super::claude::{ClaudeToolResponse, CompletionContext, CompletionSuggestion, Message};
 
 impl CopilotClient {
     /// Create a new GitHub Copilot API client
@@ -188,6 +197,49 @@ impl CopilotClient {
     pub fn is_available(&self) -> bool {
         !self.config.api_key.is_empty()
     }
+
+    /// Client for the chat API, sharing this client's headers
+    fn chat(&self) -> OpenAiClient {
+        let config = OpenAiConfig {
+            api_key: self.config.api_key.clone(),
+            base_url: self.config.endpoint.trim_end_matches("/completions").to_string(),
+            model: self.config.chat_model.clone(),
+            max_tokens: self.config.max_tokens,
+            temperature: self.config.temperature,
+            timeout_ms: self.config.timeout_ms,
+        };
+        OpenAiClient::with_http_client(config, self.http_client.clone(), "GitHub Copilot")
+    }
+}
+
+#[async_trait]
+impl AiProvider for CopilotClient {
+    fn name(&self) -> &str {
+        "GitHub Copilot"
+    }
+
+    async fn get_completions(&self, ctx: &CompletionContext) -> Result<Vec<CompletionSuggestion>> {
+        CopilotClient::get_completions(self, ctx).await
+    }
+
+    async fn send_message_with_tools(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+    ) -> Result<ClaudeToolResponse> {
+        self.chat().send_message_with_tools(messages, tools, system).await
+    }
+
+    async fn send_message_with_tools_streaming(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+        callback: StreamSink<'_>,
+    ) -> Result<ClaudeToolResponse> {
+        self.chat().send_message_with_tools_streaming(messages, tools, system, callback).await
+    }
 }
 
 #[cfg(test)]
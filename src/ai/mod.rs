@@ -2,13 +2,24 @@
 //!
 //! This module provides integration with various AI providers for intelligent
 //! code completion, hover information, and other AI-enhanced features.
+//! Features talk to them through the [`AiProvider`] trait, and [`config`]
+//! chooses which provider each feature uses.
 
 pub mod claude;
+pub mod config;
 pub mod copilot;
+pub mod ollama;
+pub mod openai;
+pub mod provider;
 
 // Re-export provider-specific types
 pub use claude::{ClaudeClient, ClaudeConfig};
 pub use copilot::{CopilotClient, CopilotConfig};
+pub use ollama::{OllamaClient, OllamaConfig};
+pub use openai::{OpenAiClient, OpenAiConfig};
 
-// Shared types (from claude module, but used by both providers)
+pub use config::{AiConfig, AiFeature, AiProviders};
+pub use provider::AiProvider;
+
+// Shared types (from claude module, but used by all providers)
 pub use claude::{CompletionContext, CompletionSuggestion};
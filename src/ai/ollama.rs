@@ -0,0 +1,352 @@
+//! Ollama API client for local models
+//!
+//! Conversations go to `/api/chat`, with tools given as function tools, and code
+//! completions to `/api/generate` with the text after the cursor as the suffix, so
+//! that fill-in-the-middle models complete between the two.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::claude::{ClaudeToolResponse, CompletionContext, CompletionSuggestion, Message, ToolUse};
+use super::openai::{function_tool, stop_reason};
+use super::provider::{for_each_line, AiProvider, StreamSink, StreamedReply};
+
+/// Ollama configuration
+#[derive(Debug, Clone)]
+pub struct OllamaConfig {
+    pub base_url: String,
+    pub model: String,
+    /// Most tokens generated (`num_predict`)
+    pub max_tokens: usize,
+    pub temperature: f32,
+    pub timeout_ms: u64,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:11434".to_string(),
+            model: "qwen2.5-coder".to_string(),
+            max_tokens: 1024,
+            temperature: 0.3,
+            // Local models may have to be loaded first
+            timeout_ms: 60000,
+        }
+    }
+}
+
+/// Ollama API client
+#[derive(Debug)]
+pub struct OllamaClient {
+    config: OllamaConfig,
+    http_client: reqwest::Client,
+}
+
+/// Reply of `/api/chat`, or one line of it when streaming
+#[derive(Debug, Deserialize)]
+struct ChatReply {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    message: Option<ChatMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCall {
+    function: FunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+/// Reply of `/api/generate`
+#[derive(Debug, Deserialize)]
+struct GenerateReply {
+    #[serde(default)]
+    response: String,
+}
+
+impl OllamaClient {
+    /// Create a new Ollama API client
+    pub fn new(config: OllamaConfig) -> Result<Self> {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        Ok(Self { config, http_client })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    fn options(&self) -> Value {
+        json!({ "num_predict": self.config.max_tokens, "temperature": self.config.temperature })
+    }
+
+    fn chat_body(&self, messages: &[Message], tools: Option<Vec<Value>>, system: Option<String>, stream: bool) -> Value {
+        let mut chat = Vec::new();
+        if let Some(system) = system {
+            chat.push(json!({ "role": "system", "content": system }));
+        }
+        chat.extend(messages.iter().map(|message| json!({ "role": message.role, "content": message.content })));
+
+        let mut body = json!({
+            "model": self.config.model,
+            "messages": chat,
+            "stream": stream,
+            "options": self.options(),
+        });
+        if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+            body["tools"] = tools.iter().map(function_tool).collect();
+        }
+        body
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<reqwest::Response> {
+        let response = self
+            .http_client
+            .post(self.url(path))
+            .json(body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach Ollama at {}", self.config.base_url))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Ollama returned error status {}: {}", status, error_body));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AiProvider for OllamaClient {
+    fn name(&self) -> &str {
+        "Ollama"
+    }
+
+    async fn get_completions(&self, ctx: &CompletionContext) -> Result<Vec<CompletionSuggestion>> {
+        let mut prompt = ctx.prefix.clone();
+        if let Some(context) = &ctx.context {
+            prompt = format!("{}\n\n{}", context, prompt);
+        }
+        let body = json!({
+            "model": self.config.model,
+            "prompt": prompt,
+            "suffix": ctx.suffix.clone().unwrap_or_default(),
+            "stream": false,
+            "options": self.options(),
+        });
+        let reply: GenerateReply =
+            self.post("generate", &body).await?.json().await.context("Failed to parse Ollama response")?;
+
+        let text = reply.response.trim_end();
+        if text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![CompletionSuggestion {
+            text: text.to_string(),
+            confidence: 0.9,
+            detail: Some(format!("AI-generated completion (Ollama {})", self.config.model)),
+        }])
+    }
+
+    async fn send_message_with_tools(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+    ) -> Result<ClaudeToolResponse> {
+        let body = self.chat_body(messages, tools, system, false);
+        let reply: ChatReply = self.post("chat", &body).await?.json().await.context("Failed to parse Ollama response")?;
+
+        let message = reply.message.ok_or_else(|| anyhow::anyhow!("Ollama reply has no message"))?;
+        let tool_uses = tool_uses(message.tool_calls, 0);
+        let text_blocks = Some(message.content).filter(|text| !text.is_empty()).into_iter().collect();
+        Ok(ClaudeToolResponse { text_blocks, stop_reason: reply_stop_reason(reply.done_reason, &tool_uses), tool_uses })
+    }
+
+    async fn send_message_with_tools_streaming(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+        callback: StreamSink<'_>,
+    ) -> Result<ClaudeToolResponse> {
+        let body = self.chat_body(messages, tools, system, true);
+        let response = self.post("chat", &body).await?;
+
+        let mut reply = StreamedReply::default();
+        let mut done_reason = None;
+        // Each line is a JSON object; the last one has `done` set
+        for_each_line(response, |line| {
+            if line.is_empty() {
+                return Ok(true);
+            }
+            let chunk: ChatReply = serde_json::from_str(line).context("Failed to parse Ollama stream")?;
+            reply.start("", &chunk.model, &mut *callback)?;
+            if let Some(message) = chunk.message {
+                reply.push_text(&message.content, &mut *callback)?;
+                let first = reply.tool_uses.len();
+                reply.tool_uses.extend(tool_uses(message.tool_calls, first));
+            }
+            if chunk.done {
+                done_reason = chunk.done_reason;
+            }
+            Ok(!chunk.done)
+        })
+        .await?;
+
+        reply.stop_reason = reply_stop_reason(done_reason, &reply.tool_uses);
+        reply.finish(callback)
+    }
+}
+
+/// Tool uses of Ollama tool calls, which have no ids; they are numbered from
+/// `first`
+fn tool_uses(calls: Vec<ToolCall>, first: usize) -> Vec<ToolUse> {
+    calls
+        .into_iter()
+        .enumerate()
+        .map(|(i, call)| {
+            let input = match call.function.arguments {
+                Value::Null => json!({}),
+                // Some models send the arguments as JSON text
+                Value::String(text) => serde_json::from_str(&text).unwrap_or(Value::String(text)),
+                arguments => arguments,
+            };
+            ToolUse { id: format!("ollama_tool_{}", first + i), name: call.function.name, input }
+        })
+        .collect()
+}
+
+/// Anthropic stop reason of a reply; Ollama reports "stop" for tool calls too
+fn reply_stop_reason(done_reason: Option<String>, tool_uses: &[ToolUse]) -> Option<String> {
+    if !tool_uses.is_empty() {
+        return Some("tool_use".to_string());
+    }
+    done_reason.map(|reason| stop_reason(&reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use warp::Filter;
+
+    /// Local stand-in for an Ollama server
+    async fn stand_in() -> SocketAddr {
+        let chat = warp::post().and(warp::path!("api" / "chat")).and(warp::body::json()).map(|body: Value| {
+            assert_eq!(body["model"], "local-model");
+            if body["stream"] == true {
+                let lines = [
+                    json!({ "model": "local-model", "message": { "role": "assistant", "content": "Hel" }, "done": false }),
+                    json!({ "model": "local-model", "message": { "role": "assistant", "content": "lo" }, "done": false }),
+                    json!({ "model": "local-model", "message": { "role": "assistant", "content": "" }, "done": true, "done_reason": "stop" }),
+                ];
+                let body: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+                return warp::http::Response::builder().header("content-type", "application/x-ndjson").body(body);
+            }
+            let reply = if body.get("tools").is_some() {
+                json!({
+                    "model": "local-model",
+                    "message": {
+                        "role": "assistant",
+                        "content": "",
+                        "tool_calls": [{ "function": { "name": "list_files", "arguments": { "path": "src" } } }]
+                    },
+                    "done": true,
+                    "done_reason": "stop"
+                })
+            } else {
+                json!({ "model": "local-model", "message": { "role": "assistant", "content": "Hello" }, "done": true, "done_reason": "stop" })
+            };
+            warp::http::Response::builder().header("content-type", "application/json").body(reply.to_string())
+        });
+        let generate = warp::post().and(warp::path!("api" / "generate")).and(warp::body::json()).map(|body: Value| {
+            assert_eq!(body["prompt"], "fn add(a: i32, b: i32) -> i32 {\n    ");
+            assert_eq!(body["suffix"], "\n}");
+            warp::reply::json(&json!({ "model": "local-model", "response": "a + b", "done": true }))
+        });
+        let (addr, server) = warp::serve(chat.or(generate)).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        addr
+    }
+
+    fn client(addr: SocketAddr) -> OllamaClient {
+        OllamaClient::new(OllamaConfig {
+            base_url: format!("http://{}", addr),
+            model: "local-model".to_string(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_chat() {
+        let client = client(stand_in().await);
+        let messages = [Message { role: "user".to_string(), content: "Hi".to_string() }];
+
+        assert_eq!(client.send_message(&messages).await.unwrap(), "Hello");
+
+        let tools = vec![json!({ "name": "list_files", "description": "List files", "input_schema": { "type": "object" } })];
+        let response = client.send_message_with_tools(&messages, Some(tools), None).await.unwrap();
+        assert_eq!(response.tool_uses[0].name, "list_files");
+        assert_eq!(response.tool_uses[0].input, json!({ "path": "src" }));
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+    }
+
+    #[tokio::test]
+    async fn test_streaming() {
+        let client = client(stand_in().await);
+        let messages = [Message { role: "user".to_string(), content: "Hi".to_string() }];
+
+        let mut events = 0;
+        let mut on_event = |_| {
+            events += 1;
+            Ok(())
+        };
+        let response = client.send_message_with_tools_streaming(&messages, None, None, &mut on_event).await.unwrap();
+
+        assert_eq!(response.text_blocks, ["Hello"]);
+        assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+        // Start, two deltas, block stop, message delta, message stop
+        assert_eq!(events, 6);
+    }
+
+    #[tokio::test]
+    async fn test_fill_in_the_middle() {
+        let client = client(stand_in().await);
+        let ctx = CompletionContext {
+            language: "rust".to_string(),
+            file_path: "src/lib.rs".to_string(),
+            prefix: "fn add(a: i32, b: i32) -> i32 {\n    ".to_string(),
+            suffix: Some("\n}".to_string()),
+            context: None,
+        };
+        let suggestions = client.get_completions(&ctx).await.unwrap();
+        assert_eq!(suggestions[0].text, "a + b");
+    }
+}
@@ -0,0 +1,445 @@
+//! OpenAI-compatible chat API client
+//!
+//! Talks to the `/chat/completions` endpoint of OpenAI or of any server mimicking
+//! it (vLLM, llama.cpp, LM Studio, ...). Anthropic tool definitions are sent as
+//! function tools, and tool calls and streamed deltas come back in the Anthropic
+//! shapes of [`super::claude`].
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::claude::{ClaudeToolResponse, CompletionContext, CompletionSuggestion, Message, ToolUse};
+use super::provider::{
+    completion_prompt, for_each_line, suggestions_from_reply, AiProvider, StreamSink, StreamedReply,
+};
+
+/// OpenAI-compatible API configuration
+#[derive(Debug, Clone)]
+pub struct OpenAiConfig {
+    /// Bearer token; local servers usually need none
+    pub api_key: String,
+    /// URL the `/chat/completions` path is appended to
+    pub base_url: String,
+    pub model: String,
+    pub max_tokens: usize,
+    pub temperature: f32,
+    pub timeout_ms: u64,
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            base_url: "https://api.openai.com/v1".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            max_tokens: 1024,
+            temperature: 0.3,
+            timeout_ms: 10000,
+        }
+    }
+}
+
+/// OpenAI-compatible API client
+#[derive(Debug)]
+pub struct OpenAiClient {
+    config: OpenAiConfig,
+    http_client: reqwest::Client,
+    name: String,
+}
+
+/// Chat completion response
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    #[serde(default)]
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<ToolCall>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCall {
+    #[serde(default)]
+    id: String,
+    function: FunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionCall {
+    name: String,
+    /// JSON text of the arguments
+    #[serde(default)]
+    arguments: String,
+}
+
+/// Chunk of a streamed chat completion
+#[derive(Debug, Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+/// Part of a tool call; the arguments arrive in pieces
+#[derive(Debug, Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+impl OpenAiClient {
+    /// Create a new OpenAI-compatible API client
+    pub fn new(config: OpenAiConfig) -> Result<Self> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            "application/json".parse().context("Invalid content type")?,
+        );
+        if !config.api_key.is_empty() {
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", config.api_key).parse().context("Invalid API key format")?,
+            );
+        }
+
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .default_headers(headers)
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        Ok(Self::with_http_client(config, http_client, "OpenAI"))
+    }
+
+    /// Client sending requests through `http_client`, which carries the
+    /// authentication headers
+    pub(super) fn with_http_client(config: OpenAiConfig, http_client: reqwest::Client, name: &str) -> Self {
+        Self { config, http_client, name: name.to_string() }
+    }
+
+    fn url(&self) -> String {
+        format!("{}/chat/completions", self.config.base_url.trim_end_matches('/'))
+    }
+
+    fn request_body(&self, messages: &[Message], tools: Option<Vec<Value>>, system: Option<String>, stream: bool) -> Value {
+        let mut chat = Vec::new();
+        if let Some(system) = system {
+            chat.push(json!({ "role": "system", "content": system }));
+        }
+        chat.extend(messages.iter().map(|message| json!({ "role": message.role, "content": message.content })));
+
+        let mut body = json!({
+            "model": self.config.model,
+            "messages": chat,
+            "max_tokens": self.config.max_tokens,
+            "temperature": self.config.temperature,
+            "stream": stream,
+        });
+        if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+            body["tools"] = tools.iter().map(function_tool).collect();
+        }
+        body
+    }
+
+    async fn post(&self, body: &Value) -> Result<reqwest::Response> {
+        let response = self
+            .http_client
+            .post(self.url())
+            .json(body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send request to {} API", self.name))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("{} API returned error status {}: {}", self.name, status, error_body));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AiProvider for OpenAiClient {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn get_completions(&self, ctx: &CompletionContext) -> Result<Vec<CompletionSuggestion>> {
+        let messages = [Message { role: "user".to_string(), content: completion_prompt(ctx) }];
+        let reply = self.send_message(&messages).await?;
+        let detail = format!("AI-generated completion ({} {})", self.name, self.config.model);
+        Ok(suggestions_from_reply(&reply, &detail))
+    }
+
+    async fn send_message_with_tools(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+    ) -> Result<ClaudeToolResponse> {
+        let body = self.request_body(messages, tools, system, false);
+        let response: ChatResponse = self
+            .post(&body)
+            .await?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {} API response", self.name))?;
+
+        let choice = response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} API returned no choices", self.name))?;
+        let text_blocks = choice.message.content.filter(|text| !text.is_empty()).into_iter().collect();
+        let tool_uses = choice
+            .message
+            .tool_calls
+            .unwrap_or_default()
+            .into_iter()
+            .map(|call| ToolUse { id: call.id, name: call.function.name, input: tool_input(&call.function.arguments) })
+            .collect();
+        Ok(ClaudeToolResponse { text_blocks, tool_uses, stop_reason: choice.finish_reason.map(|reason| stop_reason(&reason)) })
+    }
+
+    async fn send_message_with_tools_streaming(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+        callback: StreamSink<'_>,
+    ) -> Result<ClaudeToolResponse> {
+        let body = self.request_body(messages, tools, system, true);
+        let response = self.post(&body).await?;
+
+        let mut reply = StreamedReply::default();
+        // Tool calls by index: id, name and arguments so far
+        let mut calls: Vec<(String, String, String)> = Vec::new();
+        for_each_line(response, |line| {
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                return Ok(true);
+            };
+            if data == "[DONE]" {
+                return Ok(false);
+            }
+            let Ok(chunk) = serde_json::from_str::<ChatChunk>(data) else {
+                return Ok(true);
+            };
+            reply.start(&chunk.id, &chunk.model, &mut *callback)?;
+            for choice in chunk.choices {
+                if let Some(text) = &choice.delta.content {
+                    reply.push_text(text, &mut *callback)?;
+                }
+                for delta in choice.delta.tool_calls.unwrap_or_default() {
+                    if calls.len() <= delta.index {
+                        calls.resize_with(delta.index + 1, Default::default);
+                    }
+                    let call = &mut calls[delta.index];
+                    if let Some(id) = delta.id {
+                        call.0 = id;
+                    }
+                    if let Some(function) = delta.function {
+                        call.1.push_str(&function.name.unwrap_or_default());
+                        call.2.push_str(&function.arguments.unwrap_or_default());
+                    }
+                }
+                if let Some(reason) = choice.finish_reason {
+                    reply.stop_reason = Some(stop_reason(&reason));
+                }
+            }
+            Ok(true)
+        })
+        .await?;
+
+        reply.tool_uses = calls
+            .into_iter()
+            .filter(|(_, name, _)| !name.is_empty())
+            .map(|(id, name, arguments)| ToolUse { id, name, input: tool_input(&arguments) })
+            .collect();
+        reply.finish(callback)
+    }
+}
+
+/// OpenAI function tool for an Anthropic tool definition
+pub(super) fn function_tool(tool: &Value) -> Value {
+    let parameters = tool.get("input_schema").cloned().unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+    json!({
+        "type": "function",
+        "function": {
+            "name": tool.get("name").cloned().unwrap_or(Value::Null),
+            "description": tool.get("description").cloned().unwrap_or(Value::Null),
+            "parameters": parameters,
+        }
+    })
+}
+
+/// Input of a tool call from its JSON arguments, kept as a string when they don't
+/// parse
+fn tool_input(arguments: &str) -> Value {
+    if arguments.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(arguments).unwrap_or_else(|_| Value::String(arguments.to_string()))
+}
+
+/// Anthropic stop reason of an OpenAI finish reason
+pub(super) fn stop_reason(finish_reason: &str) -> String {
+    match finish_reason {
+        "stop" => "end_turn",
+        "length" => "max_tokens",
+        "tool_calls" | "function_call" => "tool_use",
+        other => other,
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use warp::Filter;
+
+    /// Local stand-in for an OpenAI-compatible server, answering every chat request
+    /// with `reply` (as an event stream when the request asks for one)
+    async fn stand_in(reply: Value, stream: &'static str) -> SocketAddr {
+        let route = warp::post().and(warp::path!("v1" / "chat" / "completions")).and(warp::body::json()).map(
+            move |body: Value| {
+                assert_eq!(body["model"], "local-model");
+                if body["stream"] == true {
+                    warp::http::Response::builder().header("content-type", "text/event-stream").body(stream.to_string())
+                } else {
+                    warp::http::Response::builder().header("content-type", "application/json").body(reply.to_string())
+                }
+            },
+        );
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        addr
+    }
+
+    fn client(addr: SocketAddr) -> OpenAiClient {
+        OpenAiClient::new(OpenAiConfig {
+            base_url: format!("http://{}/v1", addr),
+            model: "local-model".to_string(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_request_body() {
+        let client = OpenAiClient::new(OpenAiConfig::default()).unwrap();
+        let tools = vec![json!({ "name": "read_file", "description": "Read a file", "input_schema": { "type": "object" } })];
+        let messages = [Message { role: "user".to_string(), content: "hi".to_string() }];
+        let body = client.request_body(&messages, Some(tools), Some("Be brief".to_string()), false);
+
+        assert_eq!(body["messages"][0], json!({ "role": "system", "content": "Be brief" }));
+        assert_eq!(body["messages"][1]["content"], "hi");
+        assert_eq!(body["tools"][0]["function"]["name"], "read_file");
+        assert_eq!(body["tools"][0]["function"]["parameters"], json!({ "type": "object" }));
+    }
+
+    #[tokio::test]
+    async fn test_chat_with_tool_calls() {
+        let reply = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "Reading it.",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "read_file", "arguments": "{\"path\":\"src/main.rs\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        });
+        let client = client(stand_in(reply, "").await);
+        let messages = [Message { role: "user".to_string(), content: "Open main".to_string() }];
+        let response = client.send_message_with_tools(&messages, None, None).await.unwrap();
+
+        assert_eq!(response.text_blocks, ["Reading it."]);
+        assert_eq!(response.tool_uses[0].name, "read_file");
+        assert_eq!(response.tool_uses[0].input, json!({ "path": "src/main.rs" }));
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+    }
+
+    #[tokio::test]
+    async fn test_streaming() {
+        let stream = concat!(
+            "data: {\"id\":\"c1\",\"model\":\"local-model\",\"choices\":[{\"delta\":{\"content\":\"fn \"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"model\":\"local-model\",\"choices\":[{\"delta\":{\"content\":\"main\"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"t1\",\"function\":{\"name\":\"run\",\"arguments\":\"{\\\"cmd\\\":\"}}]}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"ls\\\"}\"}}]},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let client = client(stand_in(Value::Null, stream).await);
+        let messages = [Message { role: "user".to_string(), content: "go".to_string() }];
+
+        let mut streamed = String::new();
+        let mut on_event = |event: super::super::claude::StreamEvent| {
+            if let super::super::claude::StreamEvent::ContentBlockDelta {
+                delta: super::super::claude::ContentDelta::TextDelta { text },
+                ..
+            } = event
+            {
+                streamed.push_str(&text);
+            }
+            Ok(())
+        };
+        let response = client.send_message_with_tools_streaming(&messages, None, None, &mut on_event).await.unwrap();
+
+        assert_eq!(streamed, "fn main");
+        assert_eq!(response.text_blocks, ["fn main"]);
+        assert_eq!(response.tool_uses[0].id, "t1");
+        assert_eq!(response.tool_uses[0].input, json!({ "cmd": "ls" }));
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Common interface of the AI backends
+//!
+//! Features hold an `Arc<dyn AiProvider>` and don't know which backend answers.
+//! Conversations, tool definitions and stream events use the Anthropic shapes from
+//! [`super::claude`]; the other backends translate to and from them.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use super::claude::{
+    ClaudeToolResponse, CompletionContext, CompletionSuggestion, ContentDelta, Message, StreamEvent, StreamMessage,
+    ToolUse,
+};
+
+/// Most suggestions taken from one reply
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Receives the events of a streamed reply
+pub type StreamSink<'a> = &'a mut (dyn FnMut(StreamEvent) -> Result<()> + Send);
+
+/// An AI backend answering completions and conversations
+#[async_trait]
+pub trait AiProvider: Send + Sync + std::fmt::Debug {
+    /// Name of the backend, for logs and item details
+    fn name(&self) -> &str;
+
+    /// Code completions for the text around a cursor
+    async fn get_completions(&self, ctx: &CompletionContext) -> Result<Vec<CompletionSuggestion>>;
+
+    /// Reply to a conversation; the model may ask to call `tools`, given as
+    /// Anthropic tool definitions (`name`, `description`, `input_schema`)
+    async fn send_message_with_tools(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+    ) -> Result<ClaudeToolResponse>;
+
+    /// Like [`AiProvider::send_message_with_tools`], reporting the reply to
+    /// `callback` as it arrives
+    async fn send_message_with_tools_streaming(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Value>>,
+        system: Option<String>,
+        callback: StreamSink<'_>,
+    ) -> Result<ClaudeToolResponse>;
+
+    /// Text of the reply to a conversation
+    async fn send_message(&self, messages: &[Message]) -> Result<String> {
+        let response = self.send_message_with_tools(messages, None, None).await?;
+        Ok(response.text_blocks.join("\n"))
+    }
+}
+
+/// Prompt asking a chat model to complete the code at `<CURSOR>`
+pub fn completion_prompt(ctx: &CompletionContext) -> String {
+    let mut prompt = format!("You are an expert {} programmer. ", ctx.language);
+
+    if let Some(context) = &ctx.context {
+        prompt.push_str(&format!("Context:\n{}\n\n", context));
+    }
+
+    prompt.push_str(&format!(
+        "Complete the following {} code. Provide ONLY the completion text, no explanations.\n\n",
+        ctx.language
+    ));
+
+    prompt.push_str("Code to complete:\n");
+    prompt.push_str(&ctx.prefix);
+    prompt.push_str("<CURSOR>");
+
+    if let Some(suffix) = &ctx.suffix {
+        prompt.push_str(suffix);
+    }
+
+    prompt.push_str("\n\nProvide the completion that should replace <CURSOR>. Return only the code, nothing else.");
+
+    prompt
+}
+
+/// Suggestions from a chat model's reply to [`completion_prompt`]: one per line,
+/// or the whole reply when it has no non-empty line
+pub fn suggestions_from_reply(reply: &str, detail: &str) -> Vec<CompletionSuggestion> {
+    let cleaned = reply.trim();
+    let suggestions: Vec<CompletionSuggestion> = cleaned
+        .lines()
+        .filter(|line| !line.is_empty())
+        .take(MAX_SUGGESTIONS)
+        .enumerate()
+        .map(|(idx, text)| CompletionSuggestion {
+            text: text.trim().to_string(),
+            confidence: 1.0 - (idx as f32 * 0.1), // Decreasing confidence
+            detail: Some(detail.to_string()),
+        })
+        .collect();
+
+    if suggestions.is_empty() {
+        return vec![CompletionSuggestion {
+            text: cleaned.to_string(),
+            confidence: 0.8,
+            detail: Some("AI-generated completion".to_string()),
+        }];
+    }
+    suggestions
+}
+
+/// A reply streamed by a backend without Anthropic stream events, reported to the
+/// callback as the events an Anthropic stream would have sent
+#[derive(Debug, Default)]
+pub(super) struct StreamedReply {
+    started: bool,
+    text: String,
+    pub tool_uses: Vec<ToolUse>,
+    pub stop_reason: Option<String>,
+}
+
+impl StreamedReply {
+    /// Report the start of the message, once
+    pub fn start(&mut self, id: &str, model: &str, callback: StreamSink<'_>) -> Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        let message = StreamMessage { id: id.to_string(), model: model.to_string(), role: "assistant".to_string() };
+        callback(StreamEvent::MessageStart { message })
+    }
+
+    pub fn push_text(&mut self, text: &str, callback: StreamSink<'_>) -> Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.text.push_str(text);
+        callback(StreamEvent::ContentBlockDelta { index: 0, delta: ContentDelta::TextDelta { text: text.to_string() } })
+    }
+
+    /// Report the tool uses and the end of the message, and return the reply
+    pub fn finish(self, callback: StreamSink<'_>) -> Result<ClaudeToolResponse> {
+        let mut text_blocks = Vec::new();
+        if !self.text.is_empty() {
+            callback(StreamEvent::ContentBlockStop { index: 0 })?;
+            text_blocks.push(self.text);
+        }
+        for (i, tool_use) in self.tool_uses.iter().enumerate() {
+            let index = text_blocks.len() + i;
+            let content_block = json!({ "type": "tool_use", "id": tool_use.id, "name": tool_use.name, "input": {} });
+            callback(StreamEvent::ContentBlockStart { index, content_block })?;
+            let partial_json = tool_use.input.to_string();
+            callback(StreamEvent::ContentBlockDelta { index, delta: ContentDelta::InputJsonDelta { partial_json } })?;
+            callback(StreamEvent::ContentBlockStop { index })?;
+        }
+        if let Some(stop_reason) = &self.stop_reason {
+            callback(StreamEvent::MessageDelta { delta: json!({ "stop_reason": stop_reason }) })?;
+        }
+        callback(StreamEvent::MessageStop)?;
+        Ok(ClaudeToolResponse { text_blocks, tool_uses: self.tool_uses, stop_reason: self.stop_reason })
+    }
+}
+
+/// Call `on_line` with each line of a streamed response body, until it returns
+/// `false`
+pub(super) async fn for_each_line<F>(response: reqwest::Response, mut on_line: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<bool>,
+{
+    // Bytes, so that characters split across chunks are decoded whole
+    let mut stream = response.bytes_stream();
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk.context("Failed to read streaming response")?);
+        while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            if !on_line(String::from_utf8_lossy(&line).trim())? {
+                return Ok(());
+            }
+        }
+    }
+    let rest = String::from_utf8_lossy(&buffer);
+    if !rest.trim().is_empty() {
+        on_line(rest.trim())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions_from_reply() {
+        let suggestions = suggestions_from_reply("a + b\n\nb + a\n", "test");
+        let texts: Vec<&str> = suggestions.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["a + b", "b + a"]);
+        assert!(suggestions[0].confidence > suggestions[1].confidence);
+        assert_eq!(suggestions[0].detail.as_deref(), Some("test"));
+    }
+}
@@ -0,0 +1,557 @@
+//! Call Hierarchy Module
+//!
+//! Builds call graphs from tree-sitter call-expression captures. Each file is reduced
+//! to its callable definitions and the call sites inside them; the workspace index
+//! stores these per file so incoming calls can be answered across the workspace.
+
+use anyhow::Result;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use tower_lsp::lsp_types::*;
+use tree_sitter::{Query, QueryCursor};
+use crate::tree_sitter::{byte_to_position, TreeSitterParser};
+use crate::workspace_index::WorkspaceIndex;
+
+/// Per-language queries capturing the called name as `@callee`
+const CALL_QUERIES: &[(&str, &str)] = &[
+    ("python", r#"
+        (call function: (identifier) @callee)
+        (call function: (attribute attribute: (identifier) @callee))
+    "#),
+    ("javascript", r#"
+        (call_expression function: (identifier) @callee)
+        (call_expression function: (member_expression property: (property_identifier) @callee))
+        (new_expression constructor: (identifier) @callee)
+    "#),
+    ("typescript", r#"
+        (call_expression function: (identifier) @callee)
+        (call_expression function: (member_expression property: (property_identifier) @callee))
+        (new_expression constructor: (identifier) @callee)
+    "#),
+    ("tsx", r#"
+        (call_expression function: (identifier) @callee)
+        (call_expression function: (member_expression property: (property_identifier) @callee))
+        (new_expression constructor: (identifier) @callee)
+    "#),
+    ("rust", r#"
+        (call_expression function: (identifier) @callee)
+        (call_expression function: (scoped_identifier name: (identifier) @callee))
+        (call_expression function: (field_expression field: (field_identifier) @callee))
+        (call_expression function: (generic_function function: (identifier) @callee))
+        (call_expression function: (generic_function function: (scoped_identifier name: (identifier) @callee)))
+        (call_expression function: (generic_function function: (field_expression field: (field_identifier) @callee)))
+    "#),
+    ("go", r#"
+        (call_expression function: (identifier) @callee)
+        (call_expression function: (selector_expression field: (field_identifier) @callee))
+    "#),
+    ("java", r#"
+        (method_invocation name: (identifier) @callee)
+        (object_creation_expression type: (type_identifier) @callee)
+    "#),
+    ("c", r#"
+        (call_expression function: (identifier) @callee)
+        (call_expression function: (field_expression field: (field_identifier) @callee))
+    "#),
+    ("cpp", r#"
+        (call_expression function: (identifier) @callee)
+        (call_expression function: (field_expression field: (field_identifier) @callee))
+        (call_expression function: (qualified_identifier name: (identifier) @callee))
+    "#),
+];
+
+/// Compiled call queries, keyed by language (`None` if the query failed to compile)
+static QUERY_CACHE: Lazy<DashMap<String, Option<Arc<Query>>>> = Lazy::new(DashMap::new);
+
+/// A function, method or constructor definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallableDefinition {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Whole definition
+    pub range: Range,
+    /// Name of the definition
+    pub selection_range: Range,
+    /// Enclosing class, impl or receiver type
+    pub container: Option<String>,
+}
+
+/// A call expression and the callable it appears in
+#[derive(Debug, Clone)]
+pub struct CallSite {
+    /// Name being called (last path segment, e.g. `new` for `Foo::new()`)
+    pub callee: String,
+    /// Range of the called name
+    pub range: Range,
+    /// Enclosing callable; `None` for top-level code
+    pub caller: Option<CallableDefinition>,
+}
+
+/// Callables and call sites of a single file
+#[derive(Debug, Clone, Default)]
+pub struct FileCalls {
+    pub callables: Vec<CallableDefinition>,
+    pub calls: Vec<CallSite>,
+}
+
+/// Extract callable definitions and call sites from a document
+pub fn extract_calls(content: &str, lang: &str) -> Result<FileCalls> {
+    let mut parser = TreeSitterParser::new()?;
+    if parser.set_language(lang).is_err() {
+        return Ok(FileCalls::default());
+    }
+
+    let tree = parser.parse(content, "temp")?;
+    let root = tree.root_node();
+
+    let mut callables = Vec::new();
+    collect_callables(root, content, lang, &mut callables);
+
+    let mut calls = Vec::new();
+    if let Some(query) = query_for(&parser, lang) {
+        let mut cursor = QueryCursor::new();
+        for m in cursor.matches(&query, root, content.as_bytes()) {
+            for capture in m.captures {
+                let node = capture.node;
+                calls.push(CallSite {
+                    callee: content[node.byte_range()].to_string(),
+                    range: node_range(content, &node),
+                    caller: enclosing_callable(node, content, lang),
+                });
+            }
+        }
+    }
+    calls.sort_by_key(|call| (call.range.start.line, call.range.start.character));
+
+    Ok(FileCalls { callables, calls })
+}
+
+fn query_for(parser: &TreeSitterParser, lang: &str) -> Option<Arc<Query>> {
+    if let Some(cached) = QUERY_CACHE.get(lang) {
+        return cached.clone();
+    }
+
+    let source = CALL_QUERIES.iter().find(|(name, _)| *name == lang).map(|(_, q)| *q)?;
+    let language = parser.language()?;
+
+    let compiled = match Query::new(language, source) {
+        Ok(query) => Some(Arc::new(query)),
+        Err(e) => {
+            tracing::warn!("Invalid call query for {}: {:?}", lang, e);
+            None
+        }
+    };
+
+    QUERY_CACHE.insert(lang.to_string(), compiled.clone());
+    compiled
+}
+
+fn collect_callables(node: tree_sitter::Node, content: &str, lang: &str, callables: &mut Vec<CallableDefinition>) {
+    if let Some(callable) = callable_definition(node, content, lang) {
+        callables.push(callable);
+    }
+
+    for child in node.children(&mut node.walk()) {
+        collect_callables(child, content, lang, callables);
+    }
+}
+
+/// Innermost named callable containing `node`
+fn enclosing_callable(node: tree_sitter::Node, content: &str, lang: &str) -> Option<CallableDefinition> {
+    let mut current = node.parent();
+    while let Some(candidate) = current {
+        if let Some(callable) = callable_definition(candidate, content, lang) {
+            return Some(callable);
+        }
+        current = candidate.parent();
+    }
+    None
+}
+
+/// Describe `node` if it is a named function, method or constructor definition
+fn callable_definition(node: tree_sitter::Node, content: &str, lang: &str) -> Option<CallableDefinition> {
+    let (name_node, kind) = match (lang, node.kind()) {
+        ("python", "function_definition") => (node.child_by_field_name("name")?, SymbolKind::FUNCTION),
+        (
+            "javascript" | "typescript" | "tsx",
+            "function_declaration" | "generator_function_declaration",
+        ) => (node.child_by_field_name("name")?, SymbolKind::FUNCTION),
+        ("javascript" | "typescript" | "tsx", "method_definition") => {
+            (node.child_by_field_name("name")?, SymbolKind::METHOD)
+        }
+        // `const handler = () => {}`
+        ("javascript" | "typescript" | "tsx", "arrow_function" | "function" | "function_expression") => {
+            let declarator = node.parent().filter(|p| p.kind() == "variable_declarator")?;
+            (declarator.child_by_field_name("name")?, SymbolKind::FUNCTION)
+        }
+        ("rust", "function_item") => (node.child_by_field_name("name")?, SymbolKind::FUNCTION),
+        ("go", "function_declaration") => (node.child_by_field_name("name")?, SymbolKind::FUNCTION),
+        ("go", "method_declaration") => (node.child_by_field_name("name")?, SymbolKind::METHOD),
+        ("java", "method_declaration") => (node.child_by_field_name("name")?, SymbolKind::METHOD),
+        ("java", "constructor_declaration") => (node.child_by_field_name("name")?, SymbolKind::CONSTRUCTOR),
+        ("c" | "cpp", "function_definition") => (c_function_name(node.child_by_field_name("declarator")?)?, SymbolKind::FUNCTION),
+        _ => return None,
+    };
+
+    if !matches!(
+        name_node.kind(),
+        "identifier" | "property_identifier" | "field_identifier" | "type_identifier"
+    ) {
+        return None;
+    }
+
+    let container = container_name(node, content, lang);
+    let kind = if kind == SymbolKind::FUNCTION && container.is_some() {
+        SymbolKind::METHOD
+    } else {
+        kind
+    };
+
+    Some(CallableDefinition {
+        name: content[name_node.byte_range()].to_string(),
+        kind,
+        range: node_range(content, &node),
+        selection_range: node_range(content, &name_node),
+        container,
+    })
+}
+
+/// Name node of a C/C++ function declarator (`foo`, `Class::foo`)
+fn c_function_name(declarator: tree_sitter::Node) -> Option<tree_sitter::Node> {
+    match declarator.kind() {
+        "function_declarator" | "pointer_declarator" | "reference_declarator" => {
+            let inner = declarator
+                .child_by_field_name("declarator")
+                .or_else(|| declarator.named_child(0))?;
+            c_function_name(inner)
+        }
+        "qualified_identifier" => c_function_name(declarator.child_by_field_name("name")?),
+        "identifier" | "field_identifier" => Some(declarator),
+        _ => None,
+    }
+}
+
+/// Class, impl or receiver type a definition belongs to
+fn container_name(node: tree_sitter::Node, content: &str, lang: &str) -> Option<String> {
+    if lang == "go" && node.kind() == "method_declaration" {
+        let receiver = node.child_by_field_name("receiver")?;
+        let text = &content[receiver.byte_range()];
+        let ty = text
+            .trim_matches(|c| c == '(' || c == ')')
+            .split_whitespace()
+            .last()?
+            .trim_start_matches('*');
+        return Some(ty.to_string());
+    }
+
+    if matches!(lang, "c" | "cpp") {
+        let declarator = node.child_by_field_name("declarator")?;
+        let inner = declarator.child_by_field_name("declarator")?;
+        if inner.kind() == "qualified_identifier" {
+            let scope = inner.child_by_field_name("scope")?;
+            return Some(content[scope.byte_range()].to_string());
+        }
+    }
+
+    let mut current = node.parent();
+    while let Some(candidate) = current {
+        let name = match (lang, candidate.kind()) {
+            ("python", "class_definition") => candidate.child_by_field_name("name"),
+            ("javascript" | "typescript" | "tsx", "class_declaration" | "class") => candidate.child_by_field_name("name"),
+            ("java", "class_declaration" | "interface_declaration" | "enum_declaration" | "record_declaration") => {
+                candidate.child_by_field_name("name")
+            }
+            ("rust", "impl_item") => candidate.child_by_field_name("type"),
+            ("rust", "trait_item") => candidate.child_by_field_name("name"),
+            ("cpp", "class_specifier" | "struct_specifier") => candidate.child_by_field_name("name"),
+            // Nested functions don't inherit the outer container
+            ("python", "function_definition") | ("rust", "function_item") => return None,
+            _ => None,
+        };
+        if let Some(name) = name {
+            return Some(content[name.byte_range()].to_string());
+        }
+        current = candidate.parent();
+    }
+
+    None
+}
+
+/// Call hierarchy provider
+#[derive(Debug)]
+pub struct CallHierarchyProvider {}
+
+impl CallHierarchyProvider {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Resolve the callable at position: its definition, or the definitions of a called name
+    pub fn prepare(
+        &self,
+        content: &str,
+        uri: &Url,
+        position: Position,
+        lang: &str,
+        index: &WorkspaceIndex,
+    ) -> Result<Vec<CallHierarchyItem>> {
+        let file = extract_calls(content, lang)?;
+
+        // Cursor on a definition name
+        if let Some(callable) = file
+            .callables
+            .iter()
+            .find(|c| contains(&c.selection_range, position))
+        {
+            return Ok(vec![self.to_item(uri, callable)]);
+        }
+
+        // Cursor on a call: jump to the callee's definitions
+        let Some(call) = file.calls.iter().find(|c| contains(&c.range, position)) else {
+            return Ok(Vec::new());
+        };
+
+        let mut items: Vec<CallHierarchyItem> = file
+            .callables
+            .iter()
+            .filter(|c| c.name == call.callee)
+            .map(|c| self.to_item(uri, c))
+            .collect();
+
+        if items.is_empty() {
+            items = index
+                .callables_named(&call.callee)
+                .iter()
+                .map(|(callable_uri, c)| self.to_item(callable_uri, c))
+                .collect();
+        }
+
+        Ok(items)
+    }
+
+    /// Callers of `item` across the workspace, grouped by calling function
+    pub fn incoming_calls(&self, item: &CallHierarchyItem, index: &WorkspaceIndex) -> Vec<CallHierarchyIncomingCall> {
+        if item.kind == SymbolKind::MODULE {
+            return Vec::new();
+        }
+
+        let mut grouped: Vec<(CallHierarchyItem, Vec<Range>)> = Vec::new();
+        for (uri, call) in index.calls_to(&item.name) {
+            let from = match &call.caller {
+                Some(caller) => self.to_item(&uri, caller),
+                None => self.module_item(&uri),
+            };
+
+            match grouped
+                .iter_mut()
+                .find(|(existing, _)| existing.uri == from.uri && existing.selection_range == from.selection_range)
+            {
+                Some((_, ranges)) => ranges.push(call.range),
+                None => grouped.push((from, vec![call.range])),
+            }
+        }
+
+        grouped
+            .into_iter()
+            .map(|(from, from_ranges)| CallHierarchyIncomingCall { from, from_ranges })
+            .collect()
+    }
+
+    /// Callables invoked from `item`, grouped by callee
+    ///
+    /// Calls to names without a definition in the file or the workspace index
+    /// (library functions, builtins) are left out.
+    pub fn outgoing_calls(&self, item: &CallHierarchyItem, index: &WorkspaceIndex) -> Vec<CallHierarchyOutgoingCall> {
+        let Some(file) = index.file_calls(item.uri.as_str()) else {
+            return Vec::new();
+        };
+
+        let calls = file.calls.iter().filter(|call| match &call.caller {
+            Some(caller) => item.kind != SymbolKind::MODULE && caller.selection_range == item.selection_range,
+            None => item.kind == SymbolKind::MODULE,
+        });
+
+        let mut by_callee: Vec<(String, Vec<Range>)> = Vec::new();
+        for call in calls {
+            match by_callee.iter_mut().find(|(name, _)| *name == call.callee) {
+                Some((_, ranges)) => ranges.push(call.range),
+                None => by_callee.push((call.callee.clone(), vec![call.range])),
+            }
+        }
+
+        let mut outgoing = Vec::new();
+        for (callee, from_ranges) in by_callee {
+            // Prefer a definition in the same file over same-named ones elsewhere
+            let local: Vec<CallHierarchyItem> = file
+                .callables
+                .iter()
+                .filter(|c| c.name == callee)
+                .map(|c| self.to_item(&item.uri, c))
+                .collect();
+            let targets = if local.is_empty() {
+                index
+                    .callables_named(&callee)
+                    .iter()
+                    .map(|(uri, c)| self.to_item(uri, c))
+                    .collect()
+            } else {
+                local
+            };
+
+            for to in targets {
+                outgoing.push(CallHierarchyOutgoingCall {
+                    to,
+                    from_ranges: from_ranges.clone(),
+                });
+            }
+        }
+
+        outgoing
+    }
+
+    fn to_item(&self, uri: &Url, callable: &CallableDefinition) -> CallHierarchyItem {
+        CallHierarchyItem {
+            name: callable.name.clone(),
+            kind: callable.kind,
+            tags: None,
+            detail: callable.container.clone(),
+            uri: uri.clone(),
+            range: callable.range,
+            selection_range: callable.selection_range,
+            data: None,
+        }
+    }
+
+    /// Stand-in caller for top-level code
+    fn module_item(&self, uri: &Url) -> CallHierarchyItem {
+        let name = uri
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or("<module>")
+            .to_string();
+
+        CallHierarchyItem {
+            name,
+            kind: SymbolKind::MODULE,
+            tags: None,
+            detail: None,
+            uri: uri.clone(),
+            range: Range::default(),
+            selection_range: Range::default(),
+            data: None,
+        }
+    }
+}
+
+impl Default for CallHierarchyProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn contains(range: &Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+fn node_range(content: &str, node: &tree_sitter::Node) -> Range {
+    Range {
+        start: byte_to_position(content, node.start_byte()),
+        end: byte_to_position(content, node.end_byte()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    #[test]
+    fn test_extract_python_calls() {
+        let content = "def helper():\n    return 1\n\nclass Service:\n    def run(self):\n        return helper() + self.load()\n\nhelper()\n";
+        let file = extract_calls(content, "python").unwrap();
+
+        let names: Vec<_> = file.callables.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+        assert_eq!(names, vec![("helper", SymbolKind::FUNCTION), ("run", SymbolKind::METHOD)]);
+        assert_eq!(file.callables[1].container.as_deref(), Some("Service"));
+
+        let calls: Vec<_> = file
+            .calls
+            .iter()
+            .map(|c| (c.callee.as_str(), c.caller.as_ref().map(|caller| caller.name.as_str())))
+            .collect();
+        assert_eq!(calls, vec![("helper", Some("run")), ("load", Some("run")), ("helper", None)]);
+    }
+
+    #[test]
+    fn test_extract_rust_calls() {
+        let content = "impl Store {\n    fn open() -> Self { Store::new() }\n    fn save(&self) { self.flush(); write::<u8>(); }\n}\n";
+        let file = extract_calls(content, "rust").unwrap();
+
+        assert!(file.callables.iter().all(|c| c.kind == SymbolKind::METHOD));
+        let callees: Vec<_> = file.calls.iter().map(|c| c.callee.as_str()).collect();
+        assert_eq!(callees, vec!["new", "flush", "write"]);
+    }
+
+    #[test]
+    fn test_incoming_and_outgoing_across_files() {
+        let index = WorkspaceIndex::new();
+        let util = Url::parse("file:///ws/util.js").unwrap();
+        let app = Url::parse("file:///ws/app.js").unwrap();
+
+        let util_src = "function format(x) { return String(x); }\n";
+        let app_src = "const render = () => format(1);\nfunction main() {\n  render();\n  format(2);\n}\n";
+        index.index_content(util.as_str(), util_src).unwrap();
+        index.index_content(app.as_str(), app_src).unwrap();
+
+        let provider = CallHierarchyProvider::new();
+
+        // Prepare on the call to `format` in main resolves to util.js
+        let items = provider.prepare(app_src, &app, position(3, 3), "javascript", &index).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].uri, util);
+        assert_eq!(items[0].name, "format");
+
+        let incoming = provider.incoming_calls(&items[0], &index);
+        let mut callers: Vec<_> = incoming.iter().map(|c| (c.from.name.as_str(), c.from_ranges.len())).collect();
+        callers.sort();
+        assert_eq!(callers, vec![("main", 1), ("render", 1)]);
+
+        // Outgoing from `main`: both calls resolve within the workspace; `String` doesn't
+        let main_item = provider.prepare(app_src, &app, position(1, 10), "javascript", &index).unwrap();
+        let outgoing = provider.outgoing_calls(&main_item[0], &index);
+        let mut callees: Vec<_> = outgoing.iter().map(|c| (c.to.name.as_str(), c.to.uri.clone())).collect();
+        callees.sort();
+        assert_eq!(callees, vec![("format", util.clone()), ("render", app.clone())]);
+    }
+
+    #[test]
+    fn test_top_level_callers_use_module_item() {
+        let index = WorkspaceIndex::new();
+        let uri = Url::parse("file:///ws/script.py").unwrap();
+        let content = "def setup():\n    pass\n\nsetup()\n";
+        index.index_content(uri.as_str(), content).unwrap();
+
+        let provider = CallHierarchyProvider::new();
+        let items = provider.prepare(content, &uri, position(0, 5), "python", &index).unwrap();
+        let incoming = provider.incoming_calls(&items[0], &index);
+
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(incoming[0].from.kind, SymbolKind::MODULE);
+        assert_eq!(incoming[0].from.name, "script.py");
+    }
+
+    #[test]
+    fn test_all_queries_compile() {
+        for (lang, _) in CALL_QUERIES {
+            let mut parser = TreeSitterParser::new().unwrap();
+            if parser.set_language(lang).is_err() {
+                continue;
+            }
+            assert!(query_for(&parser, lang).is_some(), "query for {} failed to compile", lang);
+        }
+    }
+}
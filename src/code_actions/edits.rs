@@ -0,0 +1,260 @@
+//! Applying, validating and annotating generated edits
+//!
+//! Edits that are not derived from the syntax tree (AI output, refactorings that
+//! move code around) are applied to a copy of the document and reparsed before they
+//! are offered; an edit that adds syntax errors is rejected.
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use tower_lsp::lsp_types::*;
+use tree_sitter::Node;
+
+use crate::tree_sitter::{position_to_byte, TreeSitterParser};
+
+/// Change annotation id of edits generated by the AI actions
+pub const AI_EDIT_ANNOTATION: &str = "universal-lsp.ai";
+
+/// Apply `(start byte, end byte, new text)` edits to `source`, as the client would
+pub fn apply_byte_edits(source: &str, edits: &[(usize, usize, String)]) -> String {
+    let mut edits: Vec<_> = edits.iter().map(|(start, end, new_text)| (*start, (*end).max(*start), new_text)).collect();
+    // Later edits first so earlier offsets stay valid; inserts at the same
+    // position keep their order
+    edits.sort_by_key(|(start, end, _)| (*start, *end));
+
+    let mut result = source.to_string();
+    for (start, end, new_text) in edits.into_iter().rev() {
+        result.replace_range(start..end, new_text);
+    }
+    result
+}
+
+/// Apply LSP `edits` to `source`, as the client would
+pub fn apply_text_edits(source: &str, edits: &[TextEdit]) -> String {
+    apply_byte_edits(source, &to_byte_edits(source, edits))
+}
+
+/// Single edit turning `old` into `new` that replaces only the lines in between
+/// their common leading and trailing lines, `None` when they are equal
+pub fn changed_lines_edit(old: &str, new: &str) -> Option<TextEdit> {
+    if old == new {
+        return None;
+    }
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+
+    let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let end_line = old_lines.len() - suffix;
+    // A removed or replaced last line without a newline ends at the end of the text
+    let end = if end_line == old_lines.len() && !old.ends_with('\n') && end_line > prefix {
+        end_position(old)
+    } else {
+        Position { line: end_line as u32, character: 0 }
+    };
+    Some(TextEdit {
+        range: Range { start: Position { line: prefix as u32, character: 0 }, end },
+        new_text: new_lines[prefix..new_lines.len() - suffix].concat(),
+    })
+}
+
+fn to_byte_edits(source: &str, edits: &[TextEdit]) -> Vec<(usize, usize, String)> {
+    edits
+        .iter()
+        .map(|edit| {
+            (
+                position_to_byte(source, edit.range.start),
+                position_to_byte(source, edit.range.end),
+                edit.new_text.clone(),
+            )
+        })
+        .collect()
+}
+
+/// Number of ERROR and MISSING nodes in `source`, `None` without a grammar for `lang`
+pub fn syntax_error_count(source: &str, lang: &str) -> Option<usize> {
+    let mut parser = TreeSitterParser::new().ok()?;
+    parser.set_language(lang).ok()?;
+    let tree = parser.parse(source, "").ok()?;
+
+    fn count(node: Node) -> usize {
+        if !node.has_error() {
+            return 0;
+        }
+        let own = usize::from(node.is_error() || node.is_missing());
+        let mut cursor = node.walk();
+        own + node.children(&mut cursor).map(count).sum::<usize>()
+    }
+    Some(count(tree.root_node()))
+}
+
+/// Reject `edits` if the edited document has more syntax errors than `source`
+///
+/// Languages without a grammar cannot be checked and are accepted.
+pub fn validate_edits(source: &str, edits: &[TextEdit], lang: &str) -> Result<()> {
+    validate_byte_edits(source, &to_byte_edits(source, edits), lang)
+}
+
+/// [`validate_edits`] for `(start byte, end byte, new text)` edits
+pub fn validate_byte_edits(source: &str, edits: &[(usize, usize, String)], lang: &str) -> Result<()> {
+    let Some(before) = syntax_error_count(source, lang) else {
+        return Ok(());
+    };
+    let edited = apply_byte_edits(source, edits);
+    let after = syntax_error_count(&edited, lang).unwrap_or(before);
+    if after > before {
+        bail!("Generated code introduces {} syntax error(s)", after - before);
+    }
+    Ok(())
+}
+
+/// Edits of one document, created first when `create` is set
+#[derive(Debug, Clone)]
+pub struct DocumentEdits {
+    pub uri: Url,
+    pub create: bool,
+    pub edits: Vec<TextEdit>,
+}
+
+/// Workspace edit whose changes are annotated for confirmation, so clients show
+/// them as a diff preview before applying
+pub fn annotated_workspace_edit(documents: Vec<DocumentEdits>, label: &str, description: &str) -> WorkspaceEdit {
+    let annotation_id: ChangeAnnotationIdentifier = AI_EDIT_ANNOTATION.to_string();
+    let mut operations = Vec::new();
+
+    for document in documents {
+        if document.create {
+            operations.push(DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                uri: document.uri.clone(),
+                options: Some(CreateFileOptions {
+                    overwrite: Some(false),
+                    ignore_if_exists: Some(true),
+                }),
+                annotation_id: Some(annotation_id.clone()),
+            })));
+        }
+        operations.push(DocumentChangeOperation::Edit(TextDocumentEdit {
+            text_document: OptionalVersionedTextDocumentIdentifier {
+                uri: document.uri,
+                version: None,
+            },
+            edits: document
+                .edits
+                .into_iter()
+                .map(|text_edit| {
+                    OneOf::Right(AnnotatedTextEdit {
+                        text_edit,
+                        annotation_id: annotation_id.clone(),
+                    })
+                })
+                .collect(),
+        }));
+    }
+
+    WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(operations)),
+        change_annotations: Some(HashMap::from([(
+            annotation_id,
+            ChangeAnnotation {
+                label: label.to_string(),
+                needs_confirmation: Some(true),
+                description: Some(description.to_string()),
+            },
+        )])),
+        ..Default::default()
+    }
+}
+
+/// Position just past the end of `source`
+pub fn end_position(source: &str) -> Position {
+    let line = source.matches('\n').count() as u32;
+    let last_line = source.rsplit('\n').next().unwrap_or("");
+    Position {
+        line,
+        character: last_line.chars().count() as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> TextEdit {
+        TextEdit {
+            range: Range {
+                start: Position { line: start.0, character: start.1 },
+                end: Position { line: end.0, character: end.1 },
+            },
+            new_text: new_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let source = "def f():\n    return 1\n";
+        let edits = [edit((1, 11), (1, 12), "2"), edit((0, 0), (0, 0), "# doc\n")];
+        let edited = apply_byte_edits(source, &to_byte_edits(source, &edits));
+        assert_eq!(edited, "# doc\ndef f():\n    return 2\n");
+    }
+
+    #[test]
+    fn test_validate_rejects_new_syntax_errors() {
+        let source = "def f():\n    return 1\n";
+        assert!(validate_edits(source, &[edit((1, 11), (1, 12), "a + b")], "python").is_ok());
+        assert!(validate_edits(source, &[edit((1, 11), (1, 12), "(a +")], "python").is_err());
+
+        // Errors already in the document are not held against the edit
+        let broken = "def f(:\n    return 1\n";
+        assert!(validate_edits(broken, &[edit((1, 11), (1, 12), "2")], "python").is_ok());
+
+        // No grammar, nothing to check
+        assert!(validate_edits("x", &[edit((0, 0), (0, 1), "(")], "unknown").is_ok());
+    }
+
+    #[test]
+    fn test_annotated_workspace_edit() {
+        let uri = Url::parse("file:///tmp/test_mod.py").unwrap();
+        let edit = annotated_workspace_edit(
+            vec![DocumentEdits { uri, create: true, edits: vec![edit((0, 0), (0, 0), "x = 1\n")] }],
+            "Generate tests",
+            "Tests generated by Claude",
+        );
+
+        let Some(DocumentChanges::Operations(operations)) = edit.document_changes else {
+            panic!("expected document change operations");
+        };
+        assert!(matches!(operations[0], DocumentChangeOperation::Op(ResourceOp::Create(_))));
+        let DocumentChangeOperation::Edit(document_edit) = &operations[1] else {
+            panic!("expected a text document edit");
+        };
+        assert!(matches!(&document_edit.edits[0], OneOf::Right(e) if e.annotation_id == AI_EDIT_ANNOTATION));
+
+        let annotation = &edit.change_annotations.unwrap()[AI_EDIT_ANNOTATION];
+        assert_eq!(annotation.needs_confirmation, Some(true));
+    }
+
+    #[test]
+    fn test_changed_lines_edit() {
+        let old = "import b\nimport a\n\nx = 1\n";
+        let new = "import a\nimport b\n\nx = 1\n";
+        let change = changed_lines_edit(old, new).unwrap();
+        assert_eq!(change.range, edit((0, 0), (2, 0), "").range);
+        assert_eq!(change.new_text, "import a\nimport b\n");
+        assert_eq!(apply_text_edits(old, &[change]), new);
+
+        let change = changed_lines_edit("a\nb", "a\nc").unwrap();
+        assert_eq!(apply_text_edits("a\nb", &[change]), "a\nc");
+        assert!(changed_lines_edit(old, old).is_none());
+    }
+
+    #[test]
+    fn test_end_position() {
+        assert_eq!(end_position("a\nbc"), Position { line: 1, character: 2 });
+        assert_eq!(end_position("a\n"), Position { line: 1, character: 0 });
+    }
+}
@@ -0,0 +1,504 @@
+//! Organize imports
+//!
+//! Removes the imports the usage checks report as unused, then sorts and groups the
+//! leading block of imports: standard library first, then third-party packages, then
+//! local modules, with a blank line between groups. Blocks interleaved with comments
+//! are only cleaned of unused imports, since reordering would detach the comments.
+
+use tree_sitter::{Node, Tree};
+
+use super::edits::apply_text_edits;
+use crate::diagnostics::usage::{analyze_usage, usage_fix, UNUSED_IMPORT};
+use crate::tree_sitter::TreeSitterParser;
+
+/// Longest Python import line before its names are wrapped in parentheses
+const PYTHON_LINE_LENGTH: usize = 79;
+
+/// Top-level modules of the Python standard library
+const PYTHON_STDLIB: &[&str] = &[
+    "__future__", "abc", "argparse", "array", "ast", "asyncio", "atexit", "base64", "bisect",
+    "builtins", "bz2", "calendar", "cmath", "codecs", "collections", "colorsys", "concurrent",
+    "configparser", "contextlib", "contextvars", "copy", "csv", "ctypes", "dataclasses",
+    "datetime", "decimal", "difflib", "dis", "email", "enum", "errno", "faulthandler",
+    "fcntl", "filecmp", "fnmatch", "fractions", "ftplib", "functools", "gc", "getpass",
+    "gettext", "glob", "graphlib", "gzip", "hashlib", "heapq", "hmac", "html", "http",
+    "imaplib", "importlib", "inspect", "io", "ipaddress", "itertools", "json", "keyword",
+    "linecache", "locale", "logging", "lzma", "mailbox", "marshal", "math", "mimetypes",
+    "mmap", "multiprocessing", "numbers", "operator", "os", "pathlib", "pdb", "pickle",
+    "pkgutil", "platform", "plistlib", "pprint", "profile", "pstats", "pty", "queue",
+    "random", "re", "readline", "reprlib", "resource", "sched", "secrets", "select",
+    "selectors", "shelve", "shlex", "shutil", "signal", "site", "smtplib", "socket",
+    "socketserver", "sqlite3", "ssl", "stat", "statistics", "string", "struct", "subprocess",
+    "sys", "sysconfig", "syslog", "tarfile", "tempfile", "termios", "textwrap", "threading",
+    "time", "timeit", "tkinter", "token", "tokenize", "tomllib", "trace", "traceback",
+    "tracemalloc", "tty", "types", "typing", "unicodedata", "unittest", "urllib", "uuid",
+    "venv", "warnings", "wave", "weakref", "webbrowser", "xml", "xmlrpc", "zipapp",
+    "zipfile", "zlib", "zoneinfo",
+];
+
+/// Node.js built-in modules importable without the `node:` prefix
+const NODE_BUILTINS: &[&str] = &[
+    "assert", "buffer", "child_process", "cluster", "crypto", "dgram", "dns", "events", "fs",
+    "fs/promises", "http", "http2", "https", "net", "os", "path", "perf_hooks", "process",
+    "querystring", "readline", "stream", "string_decoder", "timers", "tls", "tty", "url",
+    "util", "v8", "vm", "worker_threads", "zlib",
+];
+
+/// One import of a block, ready to be sorted
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Import {
+    group: u8,
+    key: String,
+    text: String,
+}
+
+/// `source` with unused imports removed and the leading import block sorted and
+/// grouped, `None` for languages without an organizer
+pub fn organize_imports(tree: &Tree, source: &str, lang: &str) -> Option<String> {
+    if !matches!(lang, "python" | "javascript" | "typescript" | "tsx" | "go" | "rust" | "java") {
+        return None;
+    }
+
+    // Remove unused imports first, then reparse to sort what is left
+    let removals: Vec<_> = analyze_usage(tree, source, lang)
+        .iter()
+        .filter(|d| matches!(&d.code, Some(tower_lsp::lsp_types::NumberOrString::String(code)) if code == UNUSED_IMPORT))
+        .filter_map(usage_fix)
+        .flat_map(|(_, edits)| edits)
+        .collect();
+    let cleaned = apply_text_edits(source, &removals);
+
+    let mut parser = TreeSitterParser::new().ok()?;
+    parser.set_language(lang).ok()?;
+    let tree = parser.parse(&cleaned, "").ok()?;
+
+    let Some(block) = import_block(tree.root_node(), lang) else {
+        return Some(cleaned);
+    };
+    let (start, end) = (block[0].start_byte(), block[block.len() - 1].end_byte());
+    if has_comment_between(tree.root_node(), start, end) {
+        return Some(cleaned);
+    }
+
+    let organized = match lang {
+        "python" => python_imports(&block, &cleaned),
+        "go" => go_imports(&block, &cleaned),
+        "rust" => rust_imports(&block, &cleaned),
+        "java" => java_imports(&block, &cleaned),
+        _ => js_imports(&block, &cleaned),
+    }?;
+
+    Some(format!("{}{}{}", &cleaned[..start], organized, &cleaned[end..]))
+}
+
+fn import_kinds(lang: &str) -> &'static [&'static str] {
+    match lang {
+        "python" => &["import_statement", "import_from_statement", "future_import_statement"],
+        "go" | "java" => &["import_declaration"],
+        "rust" => &["use_declaration"],
+        _ => &["import_statement"],
+    }
+}
+
+/// First run of consecutive top-level imports
+pub(crate) fn import_block<'t>(root: Node<'t>, lang: &str) -> Option<Vec<Node<'t>>> {
+    let kinds = import_kinds(lang);
+    let mut cursor = root.walk();
+    let children: Vec<Node> = root.named_children(&mut cursor).collect();
+
+    // Imports carrying attributes (`#[cfg(test)] use ...`) stay where they are
+    let attributed = |i: usize| i > 0 && children[i - 1].kind() == "attribute_item";
+    let first = (0..children.len()).find(|&i| kinds.contains(&children[i].kind()) && !attributed(i))?;
+    let block: Vec<Node> = children[first..]
+        .iter()
+        .enumerate()
+        .take_while(|(offset, node)| kinds.contains(&node.kind()) && !attributed(first + offset))
+        .map(|(_, node)| *node)
+        .collect();
+    Some(block)
+}
+
+fn has_comment_between(root: Node, start: usize, end: usize) -> bool {
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.end_byte() <= start || node.start_byte() >= end {
+            continue;
+        }
+        if node.kind().contains("comment") {
+            return true;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.children(&mut cursor));
+    }
+    false
+}
+
+fn text<'s>(node: Node, source: &'s str) -> &'s str {
+    &source[node.start_byte()..node.end_byte()]
+}
+
+/// Sorted imports, one per line, with a blank line between groups
+fn join_groups(mut imports: Vec<Import>) -> String {
+    imports.sort();
+    imports.dedup_by(|a, b| a.text == b.text);
+
+    let mut out = String::new();
+    for (i, import) in imports.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+            if imports[i - 1].group != import.group {
+                out.push('\n');
+            }
+        }
+        out.push_str(&import.text);
+    }
+    out
+}
+
+// ------------------------------------------------------------------------
+// Python
+// ------------------------------------------------------------------------
+
+fn python_imports(block: &[Node], source: &str) -> Option<String> {
+    let mut imports = Vec::new();
+    // `from` imports of the same module are merged
+    let mut from_imports: Vec<(String, Vec<String>)> = Vec::new();
+
+    for node in block {
+        match node.kind() {
+            "future_import_statement" => imports.push(Import {
+                group: 0,
+                key: String::new(),
+                text: text(*node, source).to_string(),
+            }),
+            "import_statement" => {
+                let mut cursor = node.walk();
+                for name in node.children_by_field_name("name", &mut cursor) {
+                    let module = name.child_by_field_name("name").unwrap_or(name);
+                    let module = text(module, source);
+                    imports.push(Import {
+                        group: python_group(module),
+                        key: format!("0 {}", module.to_lowercase()),
+                        text: format!("import {}", text(name, source)),
+                    });
+                }
+            }
+            "import_from_statement" => {
+                let module = text(node.child_by_field_name("module_name")?, source).to_string();
+                let mut cursor = node.walk();
+                let mut names: Vec<String> = node
+                    .children_by_field_name("name", &mut cursor)
+                    .map(|name| text(name, source).to_string())
+                    .collect();
+                let mut cursor = node.walk();
+                if node.named_children(&mut cursor).any(|c| c.kind() == "wildcard_import") {
+                    names = vec!["*".to_string()];
+                }
+                match from_imports.iter_mut().find(|(m, existing)| *m == module && existing != &["*"] && names != ["*"]) {
+                    Some((_, existing)) => existing.extend(names),
+                    None => from_imports.push((module, names)),
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    for (module, mut names) in from_imports {
+        // isort's default order: CONSTANTS, Classes, then functions and modules
+        names.sort_by_key(|name| {
+            let kind = if name.len() > 1 && name.chars().all(|c| !c.is_lowercase()) {
+                0
+            } else if name.starts_with(char::is_uppercase) {
+                1
+            } else {
+                2
+            };
+            (kind, name.to_lowercase())
+        });
+        names.dedup();
+
+        let line = format!("from {} import {}", module, names.join(", "));
+        let text = if line.len() > PYTHON_LINE_LENGTH {
+            let wrapped: String = names.iter().map(|name| format!("    {},\n", name)).collect();
+            format!("from {} import (\n{})", module, wrapped)
+        } else {
+            line
+        };
+        imports.push(Import {
+            group: python_group(&module),
+            key: format!("1 {}", module.to_lowercase()),
+            text,
+        });
+    }
+
+    Some(join_groups(imports))
+}
+
+fn python_group(module: &str) -> u8 {
+    let root = module.split('.').next().unwrap_or(module);
+    if module == "__future__" {
+        0
+    } else if module.starts_with('.') {
+        3
+    } else if PYTHON_STDLIB.contains(&root) {
+        1
+    } else {
+        2
+    }
+}
+
+// ------------------------------------------------------------------------
+// JavaScript / TypeScript
+// ------------------------------------------------------------------------
+
+fn js_imports(block: &[Node], source: &str) -> Option<String> {
+    // Side-effect imports (`import "./polyfill"`) may depend on their position, so
+    // they split the block into runs sorted on their own
+    let mut runs: Vec<String> = Vec::new();
+    let mut run: Vec<Import> = Vec::new();
+
+    for node in block {
+        let specifier = node.child_by_field_name("source")?;
+        let module = text(specifier, source).trim_matches(|c| c == '"' || c == '\'' || c == '`');
+        let mut cursor = node.walk();
+        let Some(clause) = node.named_children(&mut cursor).find(|c| c.kind() == "import_clause") else {
+            if !run.is_empty() {
+                runs.push(join_groups(std::mem::take(&mut run)));
+            }
+            runs.push(text(*node, source).to_string());
+            continue;
+        };
+
+        let statement = text(*node, source);
+        let sorted = {
+            let mut cursor = clause.walk();
+            let named = clause.named_children(&mut cursor).find(|c| c.kind() == "named_imports");
+            match named {
+                Some(named) => {
+                    let offset = node.start_byte();
+                    let replacement = sorted_named_imports(named, source);
+                    format!(
+                        "{}{}{}",
+                        &statement[..named.start_byte() - offset],
+                        replacement,
+                        &statement[named.end_byte() - offset..]
+                    )
+                }
+                None => statement.to_string(),
+            }
+        };
+
+        let group = if module.starts_with('.') {
+            2
+        } else if module.starts_with("node:") || NODE_BUILTINS.contains(&module) {
+            0
+        } else {
+            1
+        };
+        run.push(Import { group, key: module.to_lowercase(), text: sorted });
+    }
+    if !run.is_empty() {
+        runs.push(join_groups(run));
+    }
+
+    Some(runs.join("\n"))
+}
+
+/// `{ b, a as c }` with its specifiers sorted, keeping the original layout
+fn sorted_named_imports(named: Node, source: &str) -> String {
+    let mut cursor = named.walk();
+    let mut specifiers: Vec<&str> = named
+        .named_children(&mut cursor)
+        .filter(|c| c.kind() == "import_specifier")
+        .map(|c| text(c, source))
+        .collect();
+    specifiers.sort_by_key(|s| s.trim_start_matches("type ").to_lowercase());
+
+    let original = text(named, source);
+    if original.contains('\n') {
+        let indent = original
+            .lines()
+            .nth(1)
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .unwrap_or("  ");
+        let items: String = specifiers.iter().map(|s| format!("{}{},\n", indent, s)).collect();
+        format!("{{\n{}}}", items)
+    } else if original.starts_with("{ ") {
+        format!("{{ {} }}", specifiers.join(", "))
+    } else {
+        format!("{{{}}}", specifiers.join(", "))
+    }
+}
+
+// ------------------------------------------------------------------------
+// Go
+// ------------------------------------------------------------------------
+
+fn go_imports(block: &[Node], source: &str) -> Option<String> {
+    let mut imports = Vec::new();
+    for declaration in block {
+        let mut stack = vec![*declaration];
+        while let Some(node) = stack.pop() {
+            if node.kind() == "import_spec" {
+                let path = text(node.child_by_field_name("path")?, source).trim_matches(|c| c == '"' || c == '`');
+                let first = path.split('/').next().unwrap_or(path);
+                imports.push(Import {
+                    group: if first.contains('.') { 1 } else { 0 },
+                    key: path.to_string(),
+                    text: text(node, source).to_string(),
+                });
+                continue;
+            }
+            let mut cursor = node.walk();
+            stack.extend(node.named_children(&mut cursor));
+        }
+    }
+
+    let single_line = block.len() == 1 && !text(block[0], source).contains('(');
+    if imports.len() == 1 && single_line {
+        return Some(format!("import {}", imports[0].text));
+    }
+    let body = join_groups(imports);
+    let indented: Vec<String> = body
+        .lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("\t{}", line) })
+        .collect();
+    Some(format!("import (\n{}\n)", indented.join("\n")))
+}
+
+// ------------------------------------------------------------------------
+// Rust
+// ------------------------------------------------------------------------
+
+fn rust_imports(block: &[Node], source: &str) -> Option<String> {
+    let mut imports = Vec::new();
+    for node in block {
+        let argument = node.child_by_field_name("argument")?;
+        let statement = text(*node, source);
+        let offset = node.start_byte();
+        let path = rust_sorted_argument(argument, source);
+        let text = format!(
+            "{}{}{}",
+            &statement[..argument.start_byte() - offset],
+            path,
+            &statement[argument.end_byte() - offset..]
+        );
+
+        let root = path.split("::").next().unwrap_or("").trim_start_matches("::");
+        let group = match root {
+            "std" | "core" | "alloc" | "proc_macro" | "test" => 0,
+            "crate" | "self" | "super" => 2,
+            _ => 1,
+        };
+        imports.push(Import { group, key: path.clone(), text });
+    }
+    Some(join_groups(imports))
+}
+
+/// Use path with the items of its top-level `{...}` list sorted (`self` first) and
+/// single-item lists unwrapped
+fn rust_sorted_argument(argument: Node, source: &str) -> String {
+    if argument.kind() != "scoped_use_list" {
+        return text(argument, source).to_string();
+    }
+    let (Some(path), Some(list)) = (argument.child_by_field_name("path"), argument.child_by_field_name("list")) else {
+        return text(argument, source).to_string();
+    };
+    if text(list, source).contains('\n') {
+        return text(argument, source).to_string();
+    }
+
+    let mut cursor = list.walk();
+    let mut items: Vec<&str> = list.named_children(&mut cursor).map(|c| text(c, source)).collect();
+    items.sort_by_key(|item| (*item != "self", item.to_string()));
+    items.dedup();
+
+    let path = text(path, source);
+    match items.as_slice() {
+        [single] if *single != "self" && !single.contains('{') && !single.contains('*') => format!("{}::{}", path, single),
+        _ => format!("{}::{{{}}}", path, items.join(", ")),
+    }
+}
+
+// ------------------------------------------------------------------------
+// Java
+// ------------------------------------------------------------------------
+
+fn java_imports(block: &[Node], source: &str) -> Option<String> {
+    let imports = block
+        .iter()
+        .map(|node| {
+            let text = text(*node, source).to_string();
+            let is_static = text.starts_with("import static ");
+            let key = text
+                .trim_start_matches("import ")
+                .trim_start_matches("static ")
+                .trim_end_matches(';')
+                .trim()
+                .to_string();
+            // Static imports first, as in Google Java Style
+            Import { group: if is_static { 0 } else { 1 }, key, text }
+        })
+        .collect();
+    Some(join_groups(imports))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn organize(source: &str, lang: &str) -> String {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        organize_imports(&tree, source, lang).unwrap()
+    }
+
+    #[test]
+    fn test_organize_python_imports() {
+        let source = "import sys, os\nfrom .models import User\nimport requests\nfrom typing import List, Dict, Any\nfrom typing import Optional\n\nprint(os, sys, User, requests, List, Dict, Optional)\n";
+        assert_eq!(
+            organize(source, "python"),
+            "import os\nimport sys\nfrom typing import Dict, List, Optional\n\nimport requests\n\nfrom .models import User\n\nprint(os, sys, User, requests, List, Dict, Optional)\n"
+        );
+    }
+
+    #[test]
+    fn test_organize_typescript_imports() {
+        let source = "import { z, a } from \"./local\";\nimport fs from \"fs\";\nimport React, { useState } from \"react\";\nimport { unused } from \"lodash\";\n\nfs.readFileSync(a + z);\nReact.render(useState);\n";
+        assert_eq!(
+            organize(source, "typescript"),
+            "import fs from \"fs\";\n\nimport React, { useState } from \"react\";\n\nimport { a, z } from \"./local\";\n\nfs.readFileSync(a + z);\nReact.render(useState);\n"
+        );
+    }
+
+    #[test]
+    fn test_organize_go_imports() {
+        let source = "package main\n\nimport (\n\t\"github.com/x/y\"\n\t\"os\"\n\t\"fmt\"\n)\n\nfunc main() { fmt.Println(os.Args, y.Z) }\n";
+        assert_eq!(
+            organize(source, "go"),
+            "package main\n\nimport (\n\t\"fmt\"\n\t\"os\"\n\n\t\"github.com/x/y\"\n)\n\nfunc main() { fmt.Println(os.Args, y.Z) }\n"
+        );
+    }
+
+    #[test]
+    fn test_organize_rust_and_java_imports() {
+        let source = "use crate::config::Config;\nuse serde::Serialize;\nuse std::{fmt, collections::HashMap};\n\nfn f(_: Config, _: HashMap<u8, u8>) -> fmt::Result { Serialize::x() }\n";
+        assert_eq!(
+            organize(source, "rust"),
+            "use std::{collections::HashMap, fmt};\n\nuse serde::Serialize;\n\nuse crate::config::Config;\n\nfn f(_: Config, _: HashMap<u8, u8>) -> fmt::Result { Serialize::x() }\n"
+        );
+
+        let source = "import java.util.List;\nimport static org.junit.Assert.assertEquals;\nimport java.io.File;\n\nclass T { List<File> f; void t() { assertEquals(1, 1); } }\n";
+        assert_eq!(
+            organize(source, "java"),
+            "import static org.junit.Assert.assertEquals;\n\nimport java.io.File;\nimport java.util.List;\n\nclass T { List<File> f; void t() { assertEquals(1, 1); } }\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_keep_import_order() {
+        let source = "import sys\n# needed for paths\nimport os\nimport json\n\nprint(sys, os)\n";
+        assert_eq!(organize(source, "python"), "import sys\n# needed for paths\nimport os\n\nprint(sys, os)\n");
+    }
+}
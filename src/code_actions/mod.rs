@@ -1,31 +1,101 @@
 //! Code Actions and Refactoring Module
 //!
 //! Provides quick fixes, refactorings, and code transformations
+//!
+//! When the client supports `codeAction/resolve`, actions whose edits are expensive
+//! (extract variable) or come from the AI provider are listed without an edit and carry
+//! [`ResolveData`]; the edit is computed by [`CodeActionProvider::resolve`] once the
+//! user picks the action.
+//!
+//! AI actions never edit blindly: the model's output is turned into edits that are
+//! reparsed before they are offered (see [`edits`]) and annotated for confirmation so
+//! the client previews them as a diff.
+//!
+//! `source.organizeImports` and `source.fixAll` are only listed when the request's
+//! `only` filter asks for them, so editors can run them on save.
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::*;
-use crate::tree_sitter::TreeSitterParser;
-use crate::ai::claude::ClaudeClient;
+use crate::tree_sitter::{byte_to_position, position_to_byte, TreeSitterParser};
+use crate::ai::AiProvider;
+use crate::diagnostics::ai_lint::suggested_fix;
+use crate::diagnostics::linter::autofix;
+use crate::diagnostics::usage::{analyze_usage, usage_fix, UNUSED_PARAMETER};
+use crate::pipeline::mcp_fixes;
+use edits::{annotated_workspace_edit, changed_lines_edit, end_position, validate_byte_edits, validate_edits, DocumentEdits};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+pub mod edits;
+pub mod imports;
+pub mod refactor;
+pub mod rust;
+pub mod test_file;
+
+/// A refactoring ready to be turned into a code action
+#[derive(Debug, Clone)]
+pub struct Refactoring {
+    pub title: String,
+    pub kind: CodeActionKind,
+    /// `(start byte, end byte, new text)`
+    pub edits: Vec<(usize, usize, String)>,
+}
+
+/// Edits computed by `codeAction/resolve` rather than when actions are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeferredEdit {
+    ExtractVariable,
+    AiOptimize,
+    AiDocumentation,
+    AiTests,
+}
+
+/// `data` of a code action whose edit is deferred
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolveData {
+    pub uri: Url,
+    pub range: Range,
+    pub lang: String,
+    pub edit: DeferredEdit,
+}
+
 /// Code action provider for refactoring and quick fixes
 #[derive(Debug)]
 pub struct CodeActionProvider {
-    claude_client: Option<Arc<ClaudeClient>>,
+    ai_provider: Option<Arc<dyn AiProvider>>,
+    /// Client resolves the `edit` of code actions lazily
+    resolve_support: AtomicBool,
 }
 
 impl CodeActionProvider {
     pub fn new() -> Self {
+        Self::with_ai(None)
+    }
+
+    pub fn with_ai(ai_provider: Option<Arc<dyn AiProvider>>) -> Self {
         Self {
-            claude_client: None,
+            ai_provider,
+            resolve_support: AtomicBool::new(false),
         }
     }
 
-    pub fn with_claude(claude_client: Option<Arc<ClaudeClient>>) -> Self {
-        Self { claude_client }
+    /// Defer expensive and AI edits to `codeAction/resolve`
+    pub fn set_resolve_support(&self, enabled: bool) {
+        self.resolve_support.store(enabled, Ordering::Relaxed);
+    }
+
+    fn defers_edits(&self) -> bool {
+        self.resolve_support.load(Ordering::Relaxed)
     }
 
     /// Generate code actions for a given range
+    ///
+    /// `only` is the `CodeActionContext.only` filter of the request; commands carry no
+    /// kind and are only listed when no filter is given. Generated code is indented
+    /// with `indent`, one level of the document's indentation.
+    #[allow(clippy::too_many_arguments)]
     pub fn get_actions(
         &self,
         uri: &Url,
@@ -33,32 +103,299 @@ impl CodeActionProvider {
         content: &str,
         diagnostics: Vec<Diagnostic>,
         lang: &str,
+        only: Option<&[CodeActionKind]>,
+        indent: &str,
     ) -> Result<Vec<CodeActionOrCommand>> {
         let mut actions = Vec::new();
+        let wants = |family: &CodeActionKind| {
+            only.is_none_or(|only| only.iter().any(|kind| kind_matches(family, kind) || kind_matches(kind, family)))
+        };
+
+        // Source actions that rewrite the whole file are only listed when asked for,
+        // typically by an editor running them on save
+        let requested = |kind: &CodeActionKind| only.is_some_and(|only| only.iter().any(|wanted| kind_matches(kind, wanted)));
+        if requested(&CodeActionKind::SOURCE_ORGANIZE_IMPORTS) || requested(&CodeActionKind::SOURCE_FIX_ALL) {
+            let mut parser = TreeSitterParser::new()?;
+            if parser.set_language(lang).is_ok() {
+                if let Ok(tree) = parser.parse(content, uri.as_str()) {
+                    if requested(&CodeActionKind::SOURCE_ORGANIZE_IMPORTS) {
+                        actions.extend(self.organize_imports_action(&tree, content, uri, lang));
+                    }
+                    if requested(&CodeActionKind::SOURCE_FIX_ALL) {
+                        actions.extend(self.fix_all_action(&tree, content, &diagnostics, uri, lang));
+                    }
+                }
+            }
+        }
 
         // Add quick fixes for diagnostics
-        for diagnostic in diagnostics {
-            if let Some(action) = self.diagnostic_to_quick_fix(&diagnostic, uri, lang, content) {
-                actions.push(action);
+        if wants(&CodeActionKind::QUICKFIX) {
+            for diagnostic in &diagnostics {
+                if let Some(action) = self.diagnostic_to_quick_fix(diagnostic, uri, lang, content, indent) {
+                    actions.push(action);
+                }
+                actions.extend(self.mcp_quick_fixes(diagnostic, uri));
             }
         }
 
         // Add refactoring actions
-        let mut parser = TreeSitterParser::new()?;
-        if parser.set_language(lang).is_ok() {
-            if let Ok(tree) = parser.parse(content, uri.as_str()) {
-                actions.extend(self.get_refactoring_actions(&tree, content, range, uri, lang)?);
+        if wants(&CodeActionKind::REFACTOR) || wants(&CodeActionKind::SOURCE) {
+            let mut parser = TreeSitterParser::new()?;
+            if parser.set_language(lang).is_ok() {
+                if let Ok(tree) = parser.parse(content, uri.as_str()) {
+                    actions.extend(self.get_refactoring_actions(&tree, content, range, uri, lang, indent)?);
+                }
             }
         }
 
-        // Add AI-powered actions if Claude is available
-        if self.claude_client.is_some() {
+        // Add AI-powered actions if a provider is configured
+        if self.ai_provider.is_some() {
             actions.extend(self.get_ai_actions(content, range, uri, lang)?);
         }
 
+        if let Some(only) = only {
+            actions.retain(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action
+                    .kind
+                    .as_ref()
+                    .is_some_and(|kind| only.iter().any(|wanted| kind_matches(kind, wanted))),
+                CodeActionOrCommand::Command(_) => false,
+            });
+        }
+
         Ok(actions)
     }
 
+    /// Compute the deferred edit of a code action picked by the user
+    pub async fn resolve(&self, mut action: CodeAction, content: &str) -> Result<CodeAction> {
+        let Some(data) = action.data.clone() else {
+            return Ok(action);
+        };
+        let Ok(data) = serde_json::from_value::<ResolveData>(data) else {
+            return Ok(action);
+        };
+
+        action.edit = Some(match data.edit {
+            DeferredEdit::ExtractVariable => {
+                let edits = extract_variable_edits(content, data.range, &data.lang)
+                    .ok_or_else(|| anyhow!("Selection can no longer be extracted"))?;
+                WorkspaceEdit {
+                    changes: Some(std::collections::HashMap::from([(data.uri, edits)])),
+                    ..Default::default()
+                }
+            }
+            ai => self.ai_edit(ai, &data.uri, data.range, &data.lang, content).await?,
+        });
+        Ok(action)
+    }
+
+    /// Ask the AI provider about the selection and turn the answer into a validated,
+    /// annotated workspace edit
+    pub async fn ai_edit(
+        &self,
+        kind: DeferredEdit,
+        uri: &Url,
+        range: Range,
+        lang: &str,
+        content: &str,
+    ) -> Result<WorkspaceEdit> {
+        let selected = text_in_range(content, range);
+        if selected.trim().is_empty() {
+            return Err(anyhow!("No code selected"));
+        }
+
+        let (label, edits) = match kind {
+            DeferredEdit::ExtractVariable => return Err(anyhow!("Extract variable is not an AI action")),
+            DeferredEdit::AiOptimize => {
+                let prompt = format!(
+                    "Optimize this {} code for performance, readability, and best practices. Reply with only the optimized code, without explanations:\n\n```{}\n{}\n```",
+                    lang, lang, selected
+                );
+                let optimized = self.ask_ai(&prompt).await?;
+                ("Optimize code", vec![TextEdit { range, new_text: optimized }])
+            }
+            DeferredEdit::AiDocumentation => {
+                let prompt = format!(
+                    "Write the documentation comment for this {} code using the language's doc comment syntax. Reply with only the comment:\n\n```{}\n{}\n```",
+                    lang, lang, selected
+                );
+                let docs = self.ask_ai(&prompt).await?;
+                ("Generate documentation", vec![insert_above(content, range, &docs)])
+            }
+            DeferredEdit::AiTests => {
+                let document = self.generate_tests(uri, selected, lang, content).await?;
+                let description = format!("Tests generated by {}", self.ai_name());
+                let edit = annotated_workspace_edit(vec![document], "Generate tests", &description);
+                return Ok(edit);
+            }
+        };
+
+        validate_edits(content, &edits, lang)?;
+        Ok(annotated_workspace_edit(
+            vec![DocumentEdits { uri: uri.clone(), create: false, edits }],
+            label,
+            &format!("Generated by {}", self.ai_name()),
+        ))
+    }
+
+    /// Explanation of the code in `range`, shown to the user rather than written into
+    /// the document
+    pub async fn explain(&self, range: Range, lang: &str, content: &str) -> Result<String> {
+        let selected = text_in_range(content, range);
+        if selected.trim().is_empty() {
+            return Err(anyhow!("No code selected"));
+        }
+        let prompt = format!(
+            "Explain this {} code concisely: what it does, how it works, and any gotchas. Reply in plain text:\n\n```{}\n{}\n```",
+            lang, lang, selected
+        );
+        self.ask_ai(&prompt).await
+    }
+
+    /// Edits adding tests for `selected` to its conventional test file
+    async fn generate_tests(&self, uri: &Url, selected: &str, lang: &str, content: &str) -> Result<DocumentEdits> {
+        let path = uri.to_file_path().ok();
+        let module = path
+            .as_deref()
+            .and_then(|p| p.file_name())
+            .map_or_else(|| uri.to_string(), |name| name.to_string_lossy().into_owned());
+        let target = path.as_deref().and_then(|p| test_file::test_file_for(p, lang));
+
+        let Some(target) = target else {
+            // Tests live next to the code, in the same file
+            let prompt = format!(
+                "Generate unit tests for this {} code from `{}`, covering edge and error cases. Reply with only the test functions, without imports or an enclosing module:\n\n```{}\n{}\n```",
+                lang, module, lang, selected
+            );
+            let tests = self.ask_ai(&prompt).await?;
+            let (position, new_text) = if lang == "rust" {
+                test_file::rust_tests_insertion(content, &tests)
+            } else {
+                (end_position(content), format!("\n\n{}\n", tests))
+            };
+            let edits = vec![TextEdit { range: Range { start: position, end: position }, new_text }];
+            validate_edits(content, &edits, lang)?;
+            return Ok(DocumentEdits { uri: uri.clone(), create: false, edits });
+        };
+
+        let test_uri = Url::from_file_path(&target).map_err(|_| anyhow!("Invalid test file path"))?;
+        let existing = std::fs::read_to_string(&target).ok();
+        let prompt = match &existing {
+            Some(tests) => format!(
+                "Extend this {} test file `{}` with tests for the code below from `{}`, covering edge and error cases. Reply with only the code to append, without repeating existing imports or tests.\n\nTest file:\n```{}\n{}\n```\n\nCode:\n```{}\n{}\n```",
+                lang, target.display(), module, lang, tests, lang, selected
+            ),
+            None => format!(
+                "Write the {} test file `{}` with unit tests for this code from `{}`, covering edge and error cases. Reply with only the complete file, including imports:\n\n```{}\n{}\n```",
+                lang, target.display(), module, lang, selected
+            ),
+        };
+        let tests = self.ask_ai(&prompt).await?;
+
+        let existing_text = existing.as_deref().unwrap_or("");
+        let (position, new_text) = match &existing {
+            Some(text) if !text.is_empty() => {
+                let separator = if text.ends_with('\n') { "\n" } else { "\n\n" };
+                (end_position(text), format!("{}{}\n", separator, tests))
+            }
+            _ => (Position::default(), format!("{}\n", tests)),
+        };
+        let edits = vec![TextEdit { range: Range { start: position, end: position }, new_text }];
+        validate_edits(existing_text, &edits, lang)?;
+        Ok(DocumentEdits { uri: test_uri, create: existing.is_none(), edits })
+    }
+
+    /// Name of the AI provider, for titles and edit descriptions
+    fn ai_name(&self) -> &str {
+        self.ai_provider.as_ref().map_or("AI", |provider| provider.name())
+    }
+
+    /// Code suggested by the AI provider, without surrounding code fences
+    async fn ask_ai(&self, prompt: &str) -> Result<String> {
+        let client = self.ai_provider.as_ref().ok_or_else(|| {
+            anyhow!("No AI provider configured. Set ANTHROPIC_API_KEY or configure [ai] providers to enable AI features.")
+        })?;
+        let response = client
+            .send_message(&[crate::ai::claude::Message {
+                role: "user".to_string(),
+                content: prompt.to_string(),
+            }])
+            .await?;
+        Ok(strip_code_fence(&response).to_string())
+    }
+
+    /// `source.organizeImports`: sort, group and prune the imports of the file
+    fn organize_imports_action(&self, tree: &tree_sitter::Tree, content: &str, uri: &Url, lang: &str) -> Option<CodeActionOrCommand> {
+        let organized = imports::organize_imports(tree, content, lang)?;
+        let edit = changed_lines_edit(content, &organized)?;
+        validate_edits(content, std::slice::from_ref(&edit), lang).ok()?;
+
+        Some(CodeActionOrCommand::CodeAction(CodeAction {
+            title: "Organize imports".to_string(),
+            kind: Some(CodeActionKind::SOURCE_ORGANIZE_IMPORTS),
+            edit: Some(WorkspaceEdit {
+                changes: Some(std::collections::HashMap::from([(uri.clone(), vec![edit])])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+    }
+
+    /// `source.fixAll`: every safe quick fix of the file in one edit
+    ///
+    /// Safe fixes are the preferred quick fixes of [`Self::diagnostic_to_quick_fix`]:
+    /// linter autofixes from the client's diagnostics, and from the usage checks,
+    /// which are rerun so the whole file is covered, removing unused imports and
+    /// renaming unused variables to `_name`. Renaming unused parameters is left out
+    /// since it breaks callers passing them by keyword. Fixes overlapping an earlier
+    /// one are skipped.
+    fn fix_all_action(
+        &self,
+        tree: &tree_sitter::Tree,
+        content: &str,
+        diagnostics: &[Diagnostic],
+        uri: &Url,
+        lang: &str,
+    ) -> Option<CodeActionOrCommand> {
+        let usage = analyze_usage(tree, content, lang);
+        let mut fixed = Vec::new();
+        let mut edits: Vec<TextEdit> = Vec::new();
+
+        let linter_fixes = diagnostics.iter().filter_map(|diagnostic| Some((diagnostic, autofix(diagnostic)?.1)));
+        let usage_fixes = usage.iter().filter_map(|diagnostic| {
+            let parameter = matches!(&diagnostic.code, Some(NumberOrString::String(code)) if code == UNUSED_PARAMETER);
+            Some((diagnostic, usage_fix(diagnostic).filter(|_| !parameter)?.1))
+        });
+
+        for (diagnostic, fix) in linter_fixes.chain(usage_fixes) {
+            let overlaps = fix.iter().any(|new| {
+                edits.iter().any(|old| {
+                    (new.range == old.range && new.new_text == old.new_text)
+                        || (new.range.start < old.range.end && old.range.start < new.range.end)
+                })
+            });
+            if overlaps {
+                continue;
+            }
+            edits.extend(fix);
+            fixed.push(diagnostic.clone());
+        }
+
+        if edits.is_empty() || validate_edits(content, &edits, lang).is_err() {
+            return None;
+        }
+        Some(CodeActionOrCommand::CodeAction(CodeAction {
+            title: "Fix all auto-fixable problems".to_string(),
+            kind: Some(CodeActionKind::SOURCE_FIX_ALL),
+            diagnostics: Some(fixed),
+            edit: Some(WorkspaceEdit {
+                changes: Some(std::collections::HashMap::from([(uri.clone(), edits)])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+    }
+
     /// Convert diagnostic to quick fix action
     fn diagnostic_to_quick_fix(
         &self,
@@ -66,10 +403,11 @@ impl CodeActionProvider {
         uri: &Url,
         lang: &str,
         content: &str,
+        indent: &str,
     ) -> Option<CodeActionOrCommand> {
         // Handle "Undefined name" warnings
         if diagnostic.message.starts_with("Undefined name '") {
-            return self.create_undefined_name_fix(diagnostic, uri, lang, content);
+            return self.create_undefined_name_fix(diagnostic, uri, lang, content, indent);
         }
 
         // Handle syntax errors
@@ -77,9 +415,66 @@ impl CodeActionProvider {
             return self.create_syntax_error_fix(diagnostic, uri, lang);
         }
 
+        // Autofixes reported by external linters, and removing or renaming unused symbols
+        if let Some((title, edits)) = autofix(diagnostic).or_else(|| usage_fix(diagnostic)) {
+            let mut changes = std::collections::HashMap::new();
+            changes.insert(uri.clone(), edits);
+
+            return Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(changes),
+                    ..Default::default()
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            }));
+        }
+
+        // Suggested fixes of AI lint findings
+        if let Some(edit) = suggested_fix(diagnostic) {
+            let mut changes = std::collections::HashMap::new();
+            changes.insert(uri.clone(), vec![edit]);
+
+            return Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Apply suggested fix: {}", diagnostic.message),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(changes),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }));
+        }
+
         None
     }
 
+    /// Quick fixes supplied by MCP servers along with their diagnostics
+    fn mcp_quick_fixes(&self, diagnostic: &Diagnostic, uri: &Url) -> Vec<CodeActionOrCommand> {
+        mcp_fixes(diagnostic)
+            .into_iter()
+            .map(|(title, edits)| {
+                let mut changes = std::collections::HashMap::new();
+                changes.insert(uri.clone(), edits);
+
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title,
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(changes),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
     /// Create quick fix for undefined name warnings
     fn create_undefined_name_fix(
         &self,
@@ -87,6 +482,7 @@ impl CodeActionProvider {
         uri: &Url,
         lang: &str,
         content: &str,
+        indent: &str,
     ) -> Option<CodeActionOrCommand> {
         // Extract variable name from message: "Undefined name 'xxx'"
         let var_name = diagnostic.message
@@ -104,8 +500,8 @@ impl CodeActionProvider {
             line: diagnostic.range.start.line + 1,
             character: 0,
         };
-        let line_start_byte = self.position_to_byte(content, line_start);
-        let line_end_byte = self.position_to_byte(content, line_end).min(content.len());
+        let line_start_byte = position_to_byte(content, line_start);
+        let line_end_byte = position_to_byte(content, line_end).min(content.len());
         let line_content = &content[line_start_byte..line_end_byte];
 
         match lang {
@@ -114,7 +510,7 @@ impl CodeActionProvider {
                 if line_content.contains(&format!("{}(", var_name)) {
                     // Looks like a function call - suggest defining a function
                     let insert_pos = Position { line: 0, character: 0 };
-                    let new_text = format!("def {}():\n    pass\n\n", var_name);
+                    let new_text = format!("def {}():\n{}pass\n\n", var_name, indent);
 
                     let mut changes = std::collections::HashMap::new();
                     changes.insert(uri.clone(), vec![TextEdit {
@@ -167,7 +563,7 @@ impl CodeActionProvider {
                 if line_content.contains(&format!("{}(", var_name)) {
                     // Suggest defining a function
                     let insert_pos = Position { line: 0, character: 0 };
-                    let new_text = format!("function {}() {{\n  // TODO: Implement\n}}\n\n", var_name);
+                    let new_text = format!("function {}() {{\n{}// TODO: Implement\n}}\n\n", var_name, indent);
 
                     let mut changes = std::collections::HashMap::new();
                     changes.insert(uri.clone(), vec![TextEdit {
@@ -220,7 +616,7 @@ impl CodeActionProvider {
                 if line_content.contains(&format!("{}(", var_name)) || line_content.contains(&format!("{}!", var_name)) {
                     // Suggest defining a function
                     let insert_pos = Position { line: 0, character: 0 };
-                    let new_text = format!("fn {}() {{\n    todo!()\n}}\n\n", var_name);
+                    let new_text = format!("fn {}() {{\n{}todo!()\n}}\n\n", var_name, indent);
 
                     let mut changes = std::collections::HashMap::new();
                     changes.insert(uri.clone(), vec![TextEdit {
@@ -313,13 +709,13 @@ impl CodeActionProvider {
         content: &str,
         range: Range,
         uri: &Url,
-        _lang: &str,
+        lang: &str,
     ) -> Result<Vec<CodeActionOrCommand>> {
         let mut actions = Vec::new();
 
         // Extract selected text
-        let start_byte = self.position_to_byte(content, range.start);
-        let end_byte = self.position_to_byte(content, range.end);
+        let start_byte = position_to_byte(content, range.start);
+        let end_byte = position_to_byte(content, range.end);
         let selected_text = if start_byte < end_byte && end_byte <= content.len() {
             &content[start_byte..end_byte]
         } else {
@@ -328,9 +724,10 @@ impl CodeActionProvider {
 
         // Only show AI actions if there's selected text
         if !selected_text.trim().is_empty() {
-            // "Explain code" action
+            let name = self.ai_name();
+            // Explaining edits nothing; the command shows the explanation
             actions.push(CodeActionOrCommand::Command(Command {
-                title: "🤖 Explain code with Claude".to_string(),
+                title: format!("🤖 Explain code with {}", name),
                 command: "universal-lsp.explainCode".to_string(),
                 arguments: Some(vec![
                     serde_json::to_value(uri.to_string()).unwrap(),
@@ -338,40 +735,57 @@ impl CodeActionProvider {
                 ]),
             }));
 
-            // "Optimize code" action
-            actions.push(CodeActionOrCommand::Command(Command {
-                title: "🤖 Optimize code with Claude".to_string(),
-                command: "universal-lsp.optimizeCode".to_string(),
-                arguments: Some(vec![
-                    serde_json::to_value(uri.to_string()).unwrap(),
-                    serde_json::to_value(range).unwrap(),
-                ]),
-            }));
-
-            // "Add tests" action
-            actions.push(CodeActionOrCommand::Command(Command {
-                title: "🤖 Generate tests with Claude".to_string(),
-                command: "universal-lsp.generateTests".to_string(),
-                arguments: Some(vec![
-                    serde_json::to_value(uri.to_string()).unwrap(),
-                    serde_json::to_value(range).unwrap(),
-                ]),
-            }));
+            let ai_actions = [
+                (format!("🤖 Optimize code with {}", name), "universal-lsp.optimizeCode", DeferredEdit::AiOptimize),
+                (format!("🤖 Generate tests with {}", name), "universal-lsp.generateTests", DeferredEdit::AiTests),
+                ("🤖 Generate documentation".to_string(), "universal-lsp.generateDocs", DeferredEdit::AiDocumentation),
+            ];
 
-            // "Generate documentation" action
-            actions.push(CodeActionOrCommand::Command(Command {
-                title: "🤖 Generate documentation".to_string(),
-                command: "universal-lsp.generateDocs".to_string(),
-                arguments: Some(vec![
-                    serde_json::to_value(uri.to_string()).unwrap(),
-                    serde_json::to_value(range).unwrap(),
-                ]),
-            }));
+            for (title, command, edit) in ai_actions {
+                if self.defers_edits() {
+                    actions.push(self.deferred_action(&title, CodeActionKind::REFACTOR_REWRITE, uri, range, lang, edit));
+                } else {
+                    // Executing the command asks the AI provider and applies the resulting edit
+                    actions.push(CodeActionOrCommand::Command(Command {
+                        title,
+                        command: command.to_string(),
+                        arguments: Some(vec![
+                            serde_json::to_value(uri.to_string()).unwrap(),
+                            serde_json::to_value(range).unwrap(),
+                        ]),
+                    }));
+                }
+            }
         }
 
         Ok(actions)
     }
 
+    /// Code action whose edit is computed on `codeAction/resolve`
+    fn deferred_action(
+        &self,
+        title: &str,
+        kind: CodeActionKind,
+        uri: &Url,
+        range: Range,
+        lang: &str,
+        edit: DeferredEdit,
+    ) -> CodeActionOrCommand {
+        let data = ResolveData {
+            uri: uri.clone(),
+            range,
+            lang: lang.to_string(),
+            edit,
+        };
+
+        CodeActionOrCommand::CodeAction(CodeAction {
+            title: title.to_string(),
+            kind: Some(kind),
+            data: serde_json::to_value(data).ok(),
+            ..Default::default()
+        })
+    }
+
     /// Get refactoring actions for a range
     fn get_refactoring_actions(
         &self,
@@ -380,12 +794,13 @@ impl CodeActionProvider {
         range: Range,
         uri: &Url,
         lang: &str,
+        indent: &str,
     ) -> Result<Vec<CodeActionOrCommand>> {
         let mut actions = Vec::new();
 
         // Find node at range
-        let start_byte = self.position_to_byte(source, range.start);
-        let end_byte = self.position_to_byte(source, range.end);
+        let start_byte = position_to_byte(source, range.start);
+        let end_byte = position_to_byte(source, range.end);
 
         // Generic refactorings available when text is selected
         if start_byte < end_byte {
@@ -399,15 +814,19 @@ impl CodeActionProvider {
                     actions.extend(self.js_ts_refactorings(node, source, range, uri)?);
                 }
                 "python" => {
-                    actions.extend(self.python_refactorings(node, source, range, uri)?);
+                    actions.extend(self.python_refactorings(node, source, range, uri, indent)?);
                 }
                 "rust" => {
-                    actions.extend(self.rust_refactorings(node, source, range, uri)?);
+                    actions.extend(self.refactoring_actions(rust::refactorings(node, source, indent), source, uri, lang));
                 }
                 _ => {}
             }
         }
 
+        // Extract function, inline variable and introduce parameter
+        let refactorings = refactor::refactorings(tree, source, start_byte, end_byte, lang, indent);
+        actions.extend(self.refactoring_actions(refactorings, source, uri, lang));
+
         Ok(actions)
     }
 
@@ -438,8 +857,8 @@ impl CodeActionProvider {
                     let mut changes = std::collections::HashMap::new();
                     changes.insert(uri.clone(), vec![TextEdit {
                         range: Range {
-                            start: self.byte_to_position(source, node.start_byte()),
-                            end: self.byte_to_position(source, node.end_byte()),
+                            start: byte_to_position(source, node.start_byte()),
+                            end: byte_to_position(source, node.end_byte()),
                         },
                         new_text,
                     }]);
@@ -468,6 +887,7 @@ impl CodeActionProvider {
         source: &str,
         _range: Range,
         uri: &Url,
+        indent: &str,
     ) -> Result<Vec<CodeActionOrCommand>> {
         let mut actions = Vec::new();
 
@@ -479,14 +899,15 @@ impl CodeActionProvider {
                     // Find the position after the colon on the first line
                     if let Some(first_line_end) = text.find('\n') {
                         let insert_offset = node.start_byte() + first_line_end + 1;
-                        let indent = "    "; // Basic indentation
-                        let docstring = format!("{}\"\"\"TODO: Add description.\"\"\"\n", indent);
+                        let line_start = source[..node.start_byte()].rfind('\n').map_or(0, |i| i + 1);
+                        let outer = &source[line_start..node.start_byte()];
+                        let docstring = format!("{}{}\"\"\"TODO: Add description.\"\"\"\n", outer, indent);
 
                         let mut changes = std::collections::HashMap::new();
                         changes.insert(uri.clone(), vec![TextEdit {
                             range: Range {
-                                start: self.byte_to_position(source, insert_offset),
-                                end: self.byte_to_position(source, insert_offset),
+                                start: byte_to_position(source, insert_offset),
+                                end: byte_to_position(source, insert_offset),
                             },
                             new_text: docstring,
                         }]);
@@ -509,37 +930,44 @@ impl CodeActionProvider {
         Ok(actions)
     }
 
-    /// Rust refactorings
-    fn rust_refactorings(
+    /// Code actions for refactorings whose edits keep the document free of new syntax errors
+    fn refactoring_actions(
         &self,
-        node: tree_sitter::Node,
-        _source: &str,
-        _range: Range,
-        _uri: &Url,
-    ) -> Result<Vec<CodeActionOrCommand>> {
-        let mut actions = Vec::new();
+        refactorings: Vec<Refactoring>,
+        source: &str,
+        uri: &Url,
+        lang: &str,
+    ) -> Vec<CodeActionOrCommand> {
+        refactorings
+            .into_iter()
+            .filter(|refactoring| validate_byte_edits(source, &refactoring.edits, lang).is_ok())
+            .map(|refactoring| {
+                let edits = refactoring
+                    .edits
+                    .into_iter()
+                    .map(|(start, end, new_text)| TextEdit {
+                        range: Range {
+                            start: byte_to_position(source, start),
+                            end: byte_to_position(source, end),
+                        },
+                        new_text,
+                    })
+                    .collect();
 
-        match node.kind() {
-            "function_item" => {
-                // Suggest: Add error handling (placeholder for now)
-                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
-                    title: "Add error handling (Result<T, E>)".to_string(),
-                    kind: Some(CodeActionKind::REFACTOR),
-                    ..Default::default()
-                }));
-            }
-            "struct_item" => {
-                // Suggest: Derive common traits (placeholder for now)
-                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
-                    title: "Add #[derive(Debug, Clone)]".to_string(),
-                    kind: Some(CodeActionKind::REFACTOR),
-                    ..Default::default()
-                }));
-            }
-            _ => {}
-        }
+                let mut changes = std::collections::HashMap::new();
+                changes.insert(uri.clone(), edits);
 
-        Ok(actions)
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title: refactoring.title,
+                    kind: Some(refactoring.kind),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(changes),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+            })
+            .collect()
     }
 
     /// Generic refactorings applicable to all languages
@@ -553,126 +981,125 @@ impl CodeActionProvider {
     ) -> Result<Vec<CodeActionOrCommand>> {
         let mut actions = Vec::new();
 
-        let start_byte = self.position_to_byte(source, range.start);
-        let end_byte = self.position_to_byte(source, range.end);
-
-        // Only offer extract variable if there's a selection
-        if start_byte < end_byte && end_byte <= source.len() {
-            let selected_text = &source[start_byte..end_byte];
-
-            // Extract variable refactoring
-            if !selected_text.trim().is_empty() && !selected_text.contains('\n') {
-                let variable_name = "extracted_value";
-
-                let (declaration, assignment) = match lang {
-                    "python" => (
-                        format!("{} = {}\n", variable_name, selected_text.trim()),
-                        variable_name.to_string(),
-                    ),
-                    "javascript" | "typescript" | "tsx" => (
-                        format!("const {} = {};\n", variable_name, selected_text.trim()),
-                        variable_name.to_string(),
-                    ),
-                    "rust" => (
-                        format!("let {} = {};\n", variable_name, selected_text.trim()),
-                        variable_name.to_string(),
-                    ),
-                    _ => (
-                        format!("{} = {}\n", variable_name, selected_text.trim()),
-                        variable_name.to_string(),
-                    ),
-                };
-
-                // Find the start of the line
-                let line_start = Position {
-                    line: range.start.line,
-                    character: 0,
-                };
-                let line_start_byte = self.position_to_byte(source, line_start);
-                let line_text = &source[line_start_byte..start_byte];
-                let indent = " ".repeat(line_text.len() - line_text.trim_start().len());
-
-                let mut changes = std::collections::HashMap::new();
-                changes.insert(uri.clone(), vec![
-                    // Insert variable declaration at the start of the line
-                    TextEdit {
-                        range: Range {
-                            start: line_start,
-                            end: line_start,
-                        },
-                        new_text: format!("{}{}", indent, declaration),
-                    },
-                    // Replace selected text with variable name
-                    TextEdit {
-                        range,
-                        new_text: assignment,
-                    },
-                ]);
+        // Extract variable refactoring
+        let title = format!("Extract to variable '{}'", EXTRACTED_VARIABLE);
+        if self.defers_edits() {
+            if extractable_selection(source, range).is_some() {
+                actions.push(self.deferred_action(
+                    &title,
+                    CodeActionKind::REFACTOR_EXTRACT,
+                    uri,
+                    range,
+                    lang,
+                    DeferredEdit::ExtractVariable,
+                ));
+            }
+        } else if let Some(edits) = extract_variable_edits(source, range, lang) {
+            let mut changes = std::collections::HashMap::new();
+            changes.insert(uri.clone(), edits);
 
-                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
-                    title: format!("Extract to variable '{}'", variable_name),
-                    kind: Some(CodeActionKind::REFACTOR_EXTRACT),
-                    edit: Some(WorkspaceEdit {
-                        changes: Some(changes),
-                        ..Default::default()
-                    }),
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(changes),
                     ..Default::default()
-                }));
-            }
+                }),
+                ..Default::default()
+            }));
         }
 
         Ok(actions)
     }
 
-    /// Helper: Convert byte offset to LSP position
-    fn byte_to_position(&self, source: &str, byte_offset: usize) -> Position {
-        let mut line = 0;
-        let mut character = 0;
-        let mut current_offset = 0;
-
-        for ch in source.chars() {
-            if current_offset >= byte_offset {
-                break;
-            }
+}
 
-            if ch == '\n' {
-                line += 1;
-                character = 0;
-            } else {
-                character += 1;
-            }
+/// Name of the variable introduced by extract variable
+const EXTRACTED_VARIABLE: &str = "extracted_value";
 
-            current_offset += ch.len_utf8();
-        }
+/// Whether `kind` is `family` or one of its sub-kinds (`refactor.extract` is a `refactor`)
+fn kind_matches(kind: &CodeActionKind, family: &CodeActionKind) -> bool {
+    let (kind, family) = (kind.as_str(), family.as_str());
+    kind == family || kind.strip_prefix(family).is_some_and(|rest| rest.starts_with('.'))
+}
 
-        Position { line, character }
+fn text_in_range(source: &str, range: Range) -> &str {
+    let start = position_to_byte(source, range.start);
+    let end = position_to_byte(source, range.end);
+    if start < end && end <= source.len() {
+        &source[start..end]
+    } else {
+        ""
     }
+}
 
-    /// Helper: Convert LSP position to byte offset
-    fn position_to_byte(&self, source: &str, position: Position) -> usize {
-        let mut byte_offset = 0;
-        let mut current_line = 0;
-        let mut current_char = 0;
-
-        for ch in source.chars() {
-            if current_line == position.line && current_char == position.character {
-                return byte_offset;
-            }
-
-            if ch == '\n' {
-                current_line += 1;
-                current_char = 0;
-            } else {
-                current_char += 1;
-            }
+/// Single-line, non-blank selection extract variable applies to
+fn extractable_selection(source: &str, range: Range) -> Option<&str> {
+    let selected = text_in_range(source, range);
+    (!selected.trim().is_empty() && !selected.contains('\n')).then_some(selected)
+}
 
-            byte_offset += ch.len_utf8();
-        }
+/// Edits declaring the selection as a variable on the line above and using it instead
+fn extract_variable_edits(source: &str, range: Range, lang: &str) -> Option<Vec<TextEdit>> {
+    let selected_text = extractable_selection(source, range)?;
+    let variable_name = EXTRACTED_VARIABLE;
+
+    let declaration = match lang {
+        "python" => format!("{} = {}\n", variable_name, selected_text.trim()),
+        "javascript" | "typescript" | "tsx" => format!("const {} = {};\n", variable_name, selected_text.trim()),
+        "rust" => format!("let {} = {};\n", variable_name, selected_text.trim()),
+        _ => format!("{} = {}\n", variable_name, selected_text.trim()),
+    };
+
+    // Find the start of the line
+    let line_start = Position {
+        line: range.start.line,
+        character: 0,
+    };
+    let line_start_byte = position_to_byte(source, line_start);
+    let line_text = &source[line_start_byte..position_to_byte(source, range.start)];
+    let indent = " ".repeat(line_text.len() - line_text.trim_start().len());
+
+    Some(vec![
+        // Insert variable declaration at the start of the line
+        TextEdit {
+            range: Range {
+                start: line_start,
+                end: line_start,
+            },
+            new_text: format!("{}{}", indent, declaration),
+        },
+        // Replace selected text with variable name
+        TextEdit {
+            range,
+            new_text: variable_name.to_string(),
+        },
+    ])
+}
 
-        byte_offset
+/// Contents of the first fenced code block of a reply, or the whole reply without one
+fn strip_code_fence(response: &str) -> &str {
+    let Some(open) = response.find("```") else {
+        return response.trim();
+    };
+    let body = &response[open + 3..];
+    // Skip the language tag on the opening fence
+    let body = body.split_once('\n').map_or(body, |(_, rest)| rest);
+    match body.find("```") {
+        Some(close) => body[..close].trim_end(),
+        None => body.trim_end(),
     }
 }
 
+/// Edit inserting `text` on its own lines above the first line of `range`, at its indentation
+fn insert_above(source: &str, range: Range, text: &str) -> TextEdit {
+    let line = source.lines().nth(range.start.line as usize).unwrap_or("");
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let new_text: String = text.lines().map(|l| format!("{}{}\n", indent, l)).collect();
+    let start = Position { line: range.start.line, character: 0 };
+    TextEdit { range: Range { start, end: start }, new_text }
+}
+
 impl Default for CodeActionProvider {
     fn default() -> Self {
         Self::new()
@@ -714,7 +1141,7 @@ mod tests {
             data: None,
         };
 
-        let action = provider.create_undefined_name_fix(&diagnostic, &uri, "python", content);
+        let action = provider.create_undefined_name_fix(&diagnostic, &uri, "python", content, "    ");
         assert!(action.is_some());
 
         if let Some(CodeActionOrCommand::CodeAction(action)) = action {
@@ -747,7 +1174,7 @@ mod tests {
             data: None,
         };
 
-        let action = provider.create_undefined_name_fix(&diagnostic, &uri, "python", content);
+        let action = provider.create_undefined_name_fix(&diagnostic, &uri, "python", content, "    ");
         assert!(action.is_some());
 
         if let Some(CodeActionOrCommand::CodeAction(action)) = action {
@@ -780,7 +1207,7 @@ mod tests {
             data: None,
         };
 
-        let action = provider.create_undefined_name_fix(&diagnostic, &uri, "javascript", content);
+        let action = provider.create_undefined_name_fix(&diagnostic, &uri, "javascript", content, "  ");
         assert!(action.is_some());
 
         if let Some(CodeActionOrCommand::CodeAction(action)) = action {
@@ -813,7 +1240,7 @@ mod tests {
             data: None,
         };
 
-        let action = provider.create_undefined_name_fix(&diagnostic, &uri, "rust", content);
+        let action = provider.create_undefined_name_fix(&diagnostic, &uri, "rust", content, "    ");
         assert!(action.is_some());
 
         if let Some(CodeActionOrCommand::CodeAction(action)) = action {
@@ -960,24 +1387,22 @@ mod tests {
 
     #[test]
     fn test_position_to_byte_conversion() {
-        let provider = CodeActionProvider::new();
         let source = "hello\nworld\n";
 
-        assert_eq!(provider.position_to_byte(source, Position { line: 0, character: 0 }), 0);
-        assert_eq!(provider.position_to_byte(source, Position { line: 0, character: 5 }), 5);
-        assert_eq!(provider.position_to_byte(source, Position { line: 1, character: 0 }), 6);
-        assert_eq!(provider.position_to_byte(source, Position { line: 1, character: 5 }), 11);
+        assert_eq!(position_to_byte(source, Position { line: 0, character: 0 }), 0);
+        assert_eq!(position_to_byte(source, Position { line: 0, character: 5 }), 5);
+        assert_eq!(position_to_byte(source, Position { line: 1, character: 0 }), 6);
+        assert_eq!(position_to_byte(source, Position { line: 1, character: 5 }), 11);
     }
 
     #[test]
     fn test_byte_to_position_conversion() {
-        let provider = CodeActionProvider::new();
         let source = "hello\nworld\n";
 
-        assert_eq!(provider.byte_to_position(source, 0), Position { line: 0, character: 0 });
-        assert_eq!(provider.byte_to_position(source, 5), Position { line: 0, character: 5 });
-        assert_eq!(provider.byte_to_position(source, 6), Position { line: 1, character: 0 });
-        assert_eq!(provider.byte_to_position(source, 11), Position { line: 1, character: 5 });
+        assert_eq!(byte_to_position(source, 0), Position { line: 0, character: 0 });
+        assert_eq!(byte_to_position(source, 5), Position { line: 0, character: 5 });
+        assert_eq!(byte_to_position(source, 6), Position { line: 1, character: 0 });
+        assert_eq!(byte_to_position(source, 11), Position { line: 1, character: 5 });
     }
 
     #[test]
@@ -989,8 +1414,8 @@ mod tests {
             api_key: "test-key".to_string(),
             ..Default::default()
         };
-        let claude_client = ClaudeClient::new(config).ok().map(Arc::new);
-        let provider = CodeActionProvider::with_claude(claude_client);
+        let claude_client = ClaudeClient::new(config).ok().map(|client| Arc::new(client) as Arc<dyn AiProvider>);
+        let provider = CodeActionProvider::with_ai(claude_client);
 
         let uri = create_uri("/test.py");
         let content = "def hello():\n    print('Hello')\n";
@@ -1028,8 +1453,8 @@ mod tests {
             api_key: "test-key".to_string(),
             ..Default::default()
         };
-        let claude_client = ClaudeClient::new(config).ok().map(Arc::new);
-        let provider = CodeActionProvider::with_claude(claude_client);
+        let claude_client = ClaudeClient::new(config).ok().map(|client| Arc::new(client) as Arc<dyn AiProvider>);
+        let provider = CodeActionProvider::with_ai(claude_client);
 
         let uri = create_uri("/test.py");
         let content = "def hello():\n    print('Hello')\n";
@@ -1044,4 +1469,188 @@ mod tests {
         // Should have no AI actions when there's no selection
         assert_eq!(actions.len(), 0);
     }
+
+    #[test]
+    fn test_only_filters_by_kind() {
+        let provider = CodeActionProvider::new();
+        let uri = create_uri("/test.py");
+        let content = "x = compute(1) + 2\n";
+        let range = Range {
+            start: Position { line: 0, character: 4 },
+            end: Position { line: 0, character: 14 },
+        };
+
+        let quickfix = [CodeActionKind::QUICKFIX];
+        let actions = provider.get_actions(&uri, range, content, vec![], "python", Some(&quickfix), "    ").unwrap();
+        assert!(actions.is_empty());
+
+        // `refactor` also matches its sub-kind `refactor.extract`
+        let refactor = [CodeActionKind::REFACTOR];
+        let actions = provider.get_actions(&uri, range, content, vec![], "python", Some(&refactor), "    ").unwrap();
+        assert!(actions.iter().any(|a| matches!(a,
+            CodeActionOrCommand::CodeAction(action) if action.kind == Some(CodeActionKind::REFACTOR_EXTRACT))));
+
+        assert!(kind_matches(&CodeActionKind::REFACTOR_EXTRACT, &CodeActionKind::REFACTOR));
+        assert!(!kind_matches(&CodeActionKind::REFACTOR, &CodeActionKind::REFACTOR_EXTRACT));
+        assert!(!kind_matches(&CodeActionKind::new("refactorx"), &CodeActionKind::REFACTOR));
+    }
+
+    #[test]
+    fn test_source_actions() {
+        let provider = CodeActionProvider::new();
+        let uri = create_uri("/test.py");
+        let content = "import sys\nimport os\nimport json\n\ndef f(unused):\n    x = 1\n    return os.getcwd() + sys.argv[0]  \n";
+        let cursor = Range::default();
+
+        // Source actions are not listed without being asked for
+        let actions = provider.get_actions(&uri, cursor, content, vec![], "python", None, "    ").unwrap();
+        assert!(!actions.iter().any(|a| matches!(a, CodeActionOrCommand::CodeAction(action) if action.title == "Organize imports")));
+
+        let only = [CodeActionKind::SOURCE_ORGANIZE_IMPORTS];
+        let actions = provider.get_actions(&uri, cursor, content, vec![], "python", Some(&only), "    ").unwrap();
+        let [CodeActionOrCommand::CodeAction(action)] = actions.as_slice() else {
+            panic!("expected only the organize imports action: {:?}", actions);
+        };
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(edits[0].new_text, "import os\nimport sys\n");
+
+        // Linter autofix, unused import and unused variable; the parameter is kept
+        let trailing = Range {
+            start: Position { line: 6, character: 36 },
+            end: Position { line: 6, character: 38 },
+        };
+        let linter = Diagnostic {
+            range: trailing,
+            message: "Trailing whitespace".to_string(),
+            data: Some(serde_json::json!({
+                "linter": "ruff",
+                "fix": {"title": "Remove trailing whitespace", "edits": [{"range": trailing, "newText": ""}]},
+            })),
+            ..Default::default()
+        };
+        // Usage findings the client sent back may be stale; only recomputed ones are fixed
+        let stale = Diagnostic {
+            range: Range {
+                start: Position { line: 4, character: 6 },
+                end: Position { line: 4, character: 12 },
+            },
+            code: Some(NumberOrString::String(crate::diagnostics::usage::UNUSED_VARIABLE.to_string())),
+            data: Some(serde_json::json!({ "rename": "_unused" })),
+            ..Default::default()
+        };
+        let only = [CodeActionKind::SOURCE];
+        let actions = provider.get_actions(&uri, cursor, content, vec![linter, stale], "python", Some(&only), "    ").unwrap();
+        let action = actions
+            .iter()
+            .find_map(|a| match a {
+                CodeActionOrCommand::CodeAction(action) if action.kind == Some(CodeActionKind::SOURCE_FIX_ALL) => Some(action),
+                _ => None,
+            })
+            .expect("fix all action");
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+        assert_eq!(
+            edits::apply_text_edits(content, edits),
+            "import sys\nimport os\n\ndef f(unused):\n    _x = 1\n    return os.getcwd() + sys.argv[0]\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extract_variable_resolves_lazily() {
+        let provider = CodeActionProvider::new();
+        provider.set_resolve_support(true);
+        let uri = create_uri("/test.py");
+        let content = "def f():\n    return compute(1) + 2\n";
+        let range = Range {
+            start: Position { line: 1, character: 11 },
+            end: Position { line: 1, character: 21 },
+        };
+
+        let actions = provider.get_actions(&uri, range, content, vec![], "python", None, "    ").unwrap();
+        let action = actions
+            .into_iter()
+            .find_map(|a| match a {
+                CodeActionOrCommand::CodeAction(action) if action.title.starts_with("Extract to variable") => Some(action),
+                _ => None,
+            })
+            .expect("extract variable action");
+        assert!(action.edit.is_none());
+        assert!(action.data.is_some());
+
+        let resolved = provider.resolve(action, content).await.unwrap();
+        let edits = &resolved.edit.unwrap().changes.unwrap()[&uri];
+        assert_eq!(edits[0].new_text, "    extracted_value = compute(1)\n");
+        assert_eq!(edits[1].new_text, "extracted_value");
+    }
+
+    #[test]
+    fn test_strip_code_fence() {
+        assert_eq!(strip_code_fence("```python\nx = 1\n```\nDone."), "x = 1");
+        assert_eq!(strip_code_fence("  x = 1\n"), "x = 1");
+        assert_eq!(strip_code_fence("Here:\n```\nfoo()\n"), "foo()");
+    }
+
+    #[test]
+    fn test_ai_actions_deferred_with_resolve_support() {
+        use crate::ai::claude::{ClaudeClient, ClaudeConfig};
+
+        let config = ClaudeConfig {
+            api_key: "test-key".to_string(),
+            ..Default::default()
+        };
+        let provider =
+            CodeActionProvider::with_ai(ClaudeClient::new(config).ok().map(|client| Arc::new(client) as Arc<dyn AiProvider>));
+        provider.set_resolve_support(true);
+
+        let uri = create_uri("/test.py");
+        let content = "def hello():\n    print('Hello')\n";
+        let range = Range {
+            start: Position { line: 0, character: 0 },
+            end: Position { line: 1, character: 19 },
+        };
+
+        let actions = provider.get_ai_actions(content, range, &uri, "python").unwrap();
+        let edits: Vec<DeferredEdit> = actions
+            .iter()
+            .filter_map(|a| match a {
+                CodeActionOrCommand::CodeAction(action) => {
+                    serde_json::from_value::<ResolveData>(action.data.clone()?).ok().map(|d| d.edit)
+                }
+                CodeActionOrCommand::Command(_) => None,
+            })
+            .collect();
+        assert_eq!(edits, [DeferredEdit::AiOptimize, DeferredEdit::AiTests, DeferredEdit::AiDocumentation]);
+
+        // Explaining never edits the document
+        assert!(actions.iter().any(|a| matches!(a,
+            CodeActionOrCommand::Command(command) if command.command == "universal-lsp.explainCode")));
+    }
+
+    #[tokio::test]
+    async fn test_ai_edit_requires_provider() {
+        let provider = CodeActionProvider::new();
+        let uri = create_uri("/test.py");
+        let content = "x = 1\n";
+        let range = Range {
+            start: Position { line: 0, character: 0 },
+            end: Position { line: 0, character: 5 },
+        };
+
+        let err = provider
+            .ai_edit(DeferredEdit::AiOptimize, &uri, range, "python", content)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("No AI provider configured"));
+    }
+
+    #[test]
+    fn test_insert_above_keeps_indentation() {
+        let content = "class A:\n    def f(self):\n        pass\n";
+        let range = Range {
+            start: Position { line: 1, character: 4 },
+            end: Position { line: 2, character: 12 },
+        };
+        let edit = insert_above(content, range, "# Does nothing\n#");
+        assert_eq!(edit.range.start, Position { line: 1, character: 0 });
+        assert_eq!(edit.new_text, "    # Does nothing\n    #\n");
+    }
 }
@@ -0,0 +1,1384 @@
+//! Extract function, inline variable and introduce parameter
+//!
+//! Refactorings for Python, JavaScript/TypeScript, Rust and Go computed from the syntax
+//! tree. Names are resolved with the scope analysis of the usage checks
+//! ([`resolve_names`]): the parameters of an extracted function are the locals of the
+//! enclosing function the selection reads, and its return values the locals the
+//! selection writes that are read after it. The provider reparses the edited buffer
+//! and drops refactorings that would introduce syntax errors.
+
+use std::collections::HashSet;
+use tower_lsp::lsp_types::CodeActionKind;
+use tree_sitter::{Node, Tree};
+
+use super::Refactoring;
+use crate::diagnostics::usage::{resolve_names, Definition, NameReference, Names};
+
+/// Name of the function introduced by extract function, in snake and camel case
+const EXTRACTED_FUNCTION: (&str, &str) = ("extracted_function", "extractedFunction");
+
+/// Name of the parameter introduced for an expression without a better name
+const INTRODUCED_PARAMETER: &str = "value";
+
+/// Refactorings for the selection `start..end` (a cursor when empty); generated
+/// code is indented with `unit`
+pub fn refactorings(tree: &Tree, source: &str, start: usize, end: usize, lang: &str, unit: &str) -> Vec<Refactoring> {
+    if !matches!(lang, "python" | "javascript" | "typescript" | "tsx" | "rust" | "go") {
+        return Vec::new();
+    }
+
+    let context = Context {
+        source,
+        lang,
+        unit,
+        root: tree.root_node(),
+        names: resolve_names(tree, source, lang),
+    };
+    let (start, end) = trim(source, start, end.max(start));
+
+    let mut refactorings = Vec::new();
+    if start < end {
+        refactorings.extend(context.extract_function(start, end));
+        refactorings.extend(context.introduce_parameter(start, end));
+    }
+    refactorings.extend(context.inline_variable(start, end));
+    refactorings
+}
+
+struct Context<'a, 't> {
+    source: &'a str,
+    lang: &'a str,
+    /// One level of indentation
+    unit: &'a str,
+    root: Node<'t>,
+    names: Names,
+}
+
+/// What extract function moves into the new function
+enum Extracted<'t> {
+    /// Whole statements of one block; `tail` when the last is a Rust tail expression
+    Statements { nodes: Vec<Node<'t>>, tail: bool },
+    Expression(Node<'t>),
+}
+
+/// How the extracted function is attached to its surroundings
+struct Placement<'t> {
+    /// Node the new function is inserted after (before, for module-level Python)
+    anchor: Node<'t>,
+    before: bool,
+    /// Receiver parameter of a method (`self`, `&mut self`, `(s *Server)`)
+    receiver: Option<String>,
+    /// Prefix of the call (`self.`, `this.`, `Self::`)
+    call_prefix: String,
+    /// Prefix of the declaration (`static ` for JavaScript static methods)
+    modifiers: String,
+    method: bool,
+}
+
+impl<'a, 't> Context<'a, 't> {
+    fn text(&self, node: Node) -> &'a str {
+        &self.source[node.start_byte()..node.end_byte()]
+    }
+
+    fn node_at(&self, start: usize, end: usize) -> Option<Node<'t>> {
+        self.root.descendant_for_byte_range(start, end)
+    }
+
+    fn reference_node(&self, reference: &NameReference) -> Option<Node<'t>> {
+        self.node_at(reference.start, reference.end)
+            .filter(|node| node.start_byte() == reference.start && node.end_byte() == reference.end)
+            .filter(|node| node.kind().ends_with("identifier"))
+    }
+
+    fn definition_node(&self, definition: &Definition) -> Option<Node<'t>> {
+        self.node_at(definition.start, definition.end)
+    }
+
+    /// Outermost named function around `byte`
+    fn host_function(&self, byte: usize) -> Option<Node<'t>> {
+        let mut current = self.node_at(byte, byte);
+        let mut host = None;
+        while let Some(node) = current {
+            if is_named_function(node.kind()) {
+                host = Some(node);
+            }
+            current = node.parent();
+        }
+        host
+    }
+
+    // --------------------------------------------------------------------
+    // Extract function
+    // --------------------------------------------------------------------
+
+    fn extract_function(&self, start: usize, end: usize) -> Option<Refactoring> {
+        let host = self.host_function(start);
+        if host.is_none() && matches!(self.lang, "rust" | "go") {
+            return None;
+        }
+
+        let extracted = match self.selected_statements(start, end) {
+            Some(nodes) => {
+                let last = *nodes.last()?;
+                let tail = self.lang == "rust" && is_rust_tail(last, self.source);
+                Extracted::Statements { nodes, tail }
+            }
+            None => Extracted::Expression(self.selected_expression(start, end)?),
+        };
+        let nodes: Vec<Node> = match &extracted {
+            Extracted::Statements { nodes, .. } => nodes.clone(),
+            Extracted::Expression(node) => vec![*node],
+        };
+        if nodes.iter().any(|node| self.escapes(*node, start, end)) {
+            return None;
+        }
+        let is_async = nodes.iter().any(|node| contains_kind(*node, &["await", "await_expression"]));
+
+        let host_range = host.map_or(0..self.source.len(), |h| h.byte_range());
+        let host_name = host.and_then(|h| h.child_by_field_name("name")).map(|n| n.start_byte());
+        let inside = |d: &Definition| d.start >= start && d.end <= end;
+        let local = |d: &Definition| {
+            host.is_some() && !d.import && host_range.contains(&d.start) && Some(d.start) != host_name
+        };
+
+        // Locals of the enclosing function the selection reads become parameters
+        let selection_refs: Vec<&NameReference> = self
+            .names
+            .references
+            .iter()
+            .filter(|r| r.start >= start && r.end <= end)
+            .collect();
+        let mut params: Vec<usize> = Vec::new();
+        for reference in &selection_refs {
+            let Some(index) = reference.definition else { continue };
+            let definition = &self.names.definitions[index];
+            if local(definition) && !inside(definition) && definition.start < start
+                && !params.iter().any(|&p| self.names.definitions[p].name == definition.name)
+            {
+                params.push(index);
+            }
+        }
+
+        // Locals the selection writes that are read after it become return values
+        let written: Vec<usize> = self
+            .names
+            .definitions
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| inside(d) && !d.import)
+            .map(|(i, _)| i)
+            .chain(selection_refs.iter().filter_map(|r| {
+                let node = self.reference_node(r)?;
+                self.is_assignment_target(node).then_some(r.definition?)
+            }))
+            .collect();
+        let read_after = |index: usize| {
+            self.names.references.iter().any(|r| {
+                r.definition == Some(index) && r.start >= end && (host.is_none() || host_range.contains(&r.start))
+            })
+        };
+        let mut returns: Vec<usize> = Vec::new();
+        for &index in &written {
+            let name = &self.names.definitions[index].name;
+            let definitions: Vec<usize> = written
+                .iter()
+                .copied()
+                .filter(|&i| &self.names.definitions[i].name == name)
+                .collect();
+            if definitions.iter().any(|&i| read_after(i))
+                && !returns.iter().any(|&r| &self.names.definitions[r].name == name)
+            {
+                returns.push(index);
+            }
+        }
+
+        let tail = matches!(extracted, Extracted::Statements { tail: true, .. });
+        if (tail || matches!(extracted, Extracted::Expression(_))) && !returns.is_empty() {
+            return None;
+        }
+
+        let placement = self.placement(host, &nodes, &mut params)?;
+        let name = self.unique_name(if matches!(self.lang, "python" | "rust") {
+            EXTRACTED_FUNCTION.0
+        } else {
+            EXTRACTED_FUNCTION.1
+        });
+
+        let function = self.function_text(&name, &extracted, &params, &returns, &placement, is_async, end);
+        let call = self.call_text(&name, &extracted, &params, &returns, &placement, is_async, start, end)?;
+
+        let (replace_start, replace_end) = match &extracted {
+            Extracted::Statements { nodes, .. } => (nodes[0].start_byte(), nodes[nodes.len() - 1].end_byte()),
+            Extracted::Expression(node) => (node.start_byte(), node.end_byte()),
+        };
+
+        let anchor = placement.anchor;
+        let fn_indent = line_indent(self.source, anchor.start_byte());
+        let blank_lines = if self.lang == "python" && fn_indent.is_empty() { "\n\n\n" } else { "\n\n" };
+        let insertion = if placement.before {
+            let at = line_start(self.source, anchor.start_byte());
+            // Separate from the code above unless a blank line already does
+            let separated = at == 0 || self.source[..at].ends_with("\n\n");
+            let separator = if separated { "" } else { &blank_lines[1..] };
+            (at, at, format!("{}{}{}", separator, function, blank_lines))
+        } else {
+            (anchor.end_byte(), anchor.end_byte(), format!("{}{}", blank_lines, function))
+        };
+
+        let kind = if placement.method { "method" } else { "function" };
+        Some(Refactoring {
+            title: format!("Extract to {} '{}'", kind, name),
+            kind: CodeActionKind::REFACTOR_EXTRACT,
+            edits: vec![(replace_start, replace_end, call), insertion],
+        })
+    }
+
+    /// Complete statements of one block covered by the selection
+    fn selected_statements(&self, start: usize, end: usize) -> Option<Vec<Node<'t>>> {
+        let containers = statement_containers(self.lang);
+        let mut node = self.node_at(start, end)?;
+        while !containers.contains(&node.kind()) {
+            node = node.parent()?;
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node
+            .named_children(&mut cursor)
+            .filter(|child| !child.kind().contains("comment"))
+            .collect();
+        let selected: Vec<Node> = children
+            .iter()
+            .copied()
+            .filter(|child| child.start_byte() >= start && child.end_byte() <= end)
+            .collect();
+        let partial = children.iter().any(|child| {
+            child.end_byte() > start
+                && child.start_byte() < end
+                && !(child.start_byte() >= start && child.end_byte() <= end)
+        });
+        if selected.is_empty() || partial {
+            return None;
+        }
+
+        let only_separators = |text: &str| text.chars().all(|c| c.is_whitespace() || c == ';');
+        let first = selected[0].start_byte();
+        let last = selected[selected.len() - 1].end_byte();
+        (only_separators(&self.source[start..first]) && only_separators(&self.source[last..end])).then_some(selected)
+    }
+
+    /// Expression spanning exactly the selection
+    fn selected_expression(&self, start: usize, end: usize) -> Option<Node<'t>> {
+        let mut node = self.node_at(start, end)?;
+        while let Some(parent) = node.parent() {
+            if parent.start_byte() != start || parent.end_byte() != end || !is_expression(parent.kind()) {
+                break;
+            }
+            node = parent;
+        }
+        let exact = node.start_byte() == start && node.end_byte() == end;
+        let is_target = self.is_assignment_target(node);
+        (exact && node.is_named() && is_expression(node.kind()) && !is_target && !self.in_pattern(node)).then_some(node)
+    }
+
+    /// Whether `node` is in declaration position (a pattern, parameter or type)
+    fn in_pattern(&self, node: Node) -> bool {
+        self.names.definitions.iter().any(|d| d.start == node.start_byte() && d.end == node.end_byte())
+            || node.parent().is_some_and(|p| p.kind().contains("pattern") || p.kind().contains("parameter"))
+    }
+
+    /// Whether `node` contains control flow leaving the selection `start..end`
+    fn escapes(&self, node: Node, start: usize, end: usize) -> bool {
+        let mut stack = vec![node];
+        while let Some(current) = stack.pop() {
+            let kind = current.kind();
+            if matches!(
+                kind,
+                "return_statement" | "return_expression" | "yield" | "yield_expression"
+                    | "try_expression" | "global_statement" | "nonlocal_statement" | "goto_statement"
+                    | "labeled_statement"
+            ) {
+                return true;
+            }
+            if matches!(
+                kind,
+                "break_statement" | "continue_statement" | "break_expression" | "continue_expression"
+            ) {
+                let mut ancestor = current.parent();
+                let mut enclosed = false;
+                while let Some(a) = ancestor {
+                    if a.start_byte() < start || a.end_byte() > end {
+                        break;
+                    }
+                    if is_loop(a.kind()) {
+                        enclosed = true;
+                        break;
+                    }
+                    ancestor = a.parent();
+                }
+                if !enclosed {
+                    return true;
+                }
+            }
+            // Nested functions may return on their own
+            if is_named_function(kind) || matches!(kind, "lambda" | "arrow_function" | "function_expression" | "closure_expression" | "func_literal") {
+                continue;
+            }
+            let mut cursor = current.walk();
+            stack.extend(current.children(&mut cursor));
+        }
+        false
+    }
+
+    /// Whether the identifier `node` is written by an assignment
+    fn is_assignment_target(&self, node: Node) -> bool {
+        let Some(parent) = node.parent() else { return false };
+        let is_left = |p: Node| p.child_by_field_name("left").is_some_and(|left| left.id() == node.id());
+        match parent.kind() {
+            "augmented_assignment" | "assignment" | "assignment_expression" | "augmented_assignment_expression"
+            | "compound_assignment_expr" => is_left(parent),
+            "update_expression" | "inc_statement" | "dec_statement" => true,
+            "expression_list" => parent.parent().is_some_and(|p| {
+                matches!(p.kind(), "assignment_statement" | "short_var_declaration")
+                    && p.child_by_field_name("left").is_some_and(|left| left.id() == parent.id())
+            }),
+            _ => false,
+        }
+    }
+
+    /// Where the new function goes and how it is called; drops the receiver from `params`
+    fn placement(&self, host: Option<Node<'t>>, nodes: &[Node<'t>], params: &mut Vec<usize>) -> Option<Placement<'t>> {
+        let top_level = |node: Node<'t>| {
+            let mut current = node;
+            while let Some(parent) = current.parent() {
+                if parent.parent().is_none() {
+                    break;
+                }
+                current = parent;
+            }
+            current
+        };
+        let free = |anchor: Node<'t>, before: bool| Placement {
+            anchor,
+            before,
+            receiver: None,
+            call_prefix: String::new(),
+            modifiers: String::new(),
+            method: false,
+        };
+        let uses = |kinds: &[&str]| nodes.iter().any(|node| contains_kind(*node, kinds));
+
+        let Some(host) = host else {
+            // Module-level code: Python needs the function defined before it is called
+            let anchor = top_level(nodes[0]);
+            return Some(if self.lang == "python" { free(anchor, true) } else { free(top_level(nodes[nodes.len() - 1]), false) });
+        };
+
+        match self.lang {
+            "python" => {
+                let in_class = host.parent().and_then(|p| p.parent()).is_some_and(|p| p.kind() == "class_definition")
+                    || host.parent().is_some_and(|p| p.kind() == "decorated_definition"
+                        && p.parent().and_then(|b| b.parent()).is_some_and(|c| c.kind() == "class_definition"));
+                let decorated = host.parent().filter(|p| p.kind() == "decorated_definition");
+                let is_static = decorated.is_some_and(|d| self.text(d).contains("@staticmethod"));
+                let anchor = decorated.unwrap_or(host);
+                if !in_class {
+                    return Some(free(anchor, false));
+                }
+                let first_param = host
+                    .child_by_field_name("parameters")
+                    .and_then(|p| p.named_child(0))
+                    .filter(|p| p.kind() == "identifier");
+                match first_param {
+                    Some(receiver) if !is_static => {
+                        let receiver_name = self.text(receiver);
+                        params.retain(|&p| self.names.definitions[p].name != receiver_name);
+                        Some(Placement {
+                            anchor,
+                            before: false,
+                            receiver: Some(receiver_name.to_string()),
+                            call_prefix: format!("{}.", receiver_name),
+                            modifiers: String::new(),
+                            method: true,
+                        })
+                    }
+                    // Static methods get a module-level helper after the class
+                    _ => Some(free(top_level(host), false)),
+                }
+            }
+            "javascript" | "typescript" | "tsx" => {
+                if host.kind() == "method_definition" && uses(&["this", "super"]) {
+                    let is_static = self.text(host).starts_with("static");
+                    return Some(Placement {
+                        anchor: host,
+                        before: false,
+                        receiver: None,
+                        call_prefix: "this.".to_string(),
+                        modifiers: if is_static { "static ".to_string() } else { String::new() },
+                        method: true,
+                    });
+                }
+                Some(free(top_level(host), false))
+            }
+            "rust" => {
+                let impl_item = host.parent().and_then(|p| p.parent()).filter(|p| p.kind() == "impl_item");
+                let uses_self = uses(&["self"]);
+                match impl_item {
+                    Some(item) if item.child_by_field_name("trait").is_none() => {
+                        let receiver = uses_self.then(|| {
+                            let mut cursor = host.walk();
+                            host.child_by_field_name("parameters")
+                                .and_then(|p| p.named_children(&mut cursor).find(|c| c.kind() == "self_parameter"))
+                                .map(|p| self.text(p))
+                                .filter(|p| p.starts_with('&'))
+                                .unwrap_or("&self")
+                                .to_string()
+                        });
+                        let method = receiver.is_some();
+                        Some(Placement {
+                            anchor: host,
+                            before: false,
+                            receiver,
+                            call_prefix: if method { "self.".to_string() } else { "Self::".to_string() },
+                            modifiers: String::new(),
+                            method,
+                        })
+                    }
+                    // Trait impls cannot take extra items, so the helper goes after the impl
+                    Some(item) if !uses_self => Some(free(item, false)),
+                    Some(_) => None,
+                    None if uses_self => None,
+                    None => Some(free(host, false)),
+                }
+            }
+            "go" => {
+                let receiver = host
+                    .child_by_field_name("receiver")
+                    .and_then(|r| r.named_child(0))
+                    .filter(|r| r.kind() == "parameter_declaration");
+                let receiver_name = receiver.and_then(|r| r.child_by_field_name("name")).map(|n| self.text(n));
+                match (receiver, receiver_name) {
+                    (Some(receiver), Some(name)) if params.iter().any(|&p| self.names.definitions[p].name == name) => {
+                        params.retain(|&p| self.names.definitions[p].name != name);
+                        Some(Placement {
+                            anchor: host,
+                            before: false,
+                            receiver: Some(format!("({})", self.text(receiver))),
+                            call_prefix: format!("{}.", name),
+                            modifiers: String::new(),
+                            method: true,
+                        })
+                    }
+                    _ => Some(free(host, false)),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Source of the extracted function
+    #[allow(clippy::too_many_arguments)]
+    fn function_text(
+        &self,
+        name: &str,
+        extracted: &Extracted,
+        params: &[usize],
+        returns: &[usize],
+        placement: &Placement,
+        is_async: bool,
+        end: usize,
+    ) -> String {
+        let fn_indent = line_indent(self.source, placement.anchor.start_byte()).to_string();
+        let body_indent = format!("{}{}", fn_indent, self.unit);
+        let definitions = &self.names.definitions;
+        let names: Vec<&str> = returns.iter().map(|&r| definitions[r].name.as_str()).collect();
+
+        let mut body = match extracted {
+            Extracted::Statements { nodes, .. } => {
+                let first = nodes[0].start_byte();
+                let text = &self.source[line_start(self.source, first)..nodes[nodes.len() - 1].end_byte()];
+                reindent(text, line_indent(self.source, first), &body_indent)
+            }
+            Extracted::Expression(node) => {
+                let expr = self.text(*node);
+                match self.lang {
+                    "rust" => format!("{}{}", body_indent, expr),
+                    "python" | "go" => format!("{}return {}", body_indent, expr),
+                    _ => format!("{}return {};", body_indent, expr),
+                }
+            }
+        };
+        if !names.is_empty() {
+            let value = match self.lang {
+                "python" | "go" => format!("return {}", names.join(", ")),
+                "rust" if names.len() == 1 => names[0].to_string(),
+                "rust" => format!("({})", names.join(", ")),
+                _ if names.len() == 1 => format!("return {};", names[0]),
+                _ => format!("return {{ {} }};", names.join(", ")),
+            };
+            body = format!("{}\n{}{}", body, body_indent, value);
+        }
+
+        // Types of parameters and results, where the language needs them
+        let param_list: Vec<String> = params
+            .iter()
+            .map(|&p| {
+                let definition = &definitions[p];
+                let ty = self.definition_type(definition, 0);
+                match self.lang {
+                    "rust" => {
+                        let ty = ty.unwrap_or_else(|| "_".to_string());
+                        if self.passed_by_reference(p, returns, end, &ty) {
+                            format!("{}: &{}", definition.name, ty)
+                        } else if self.written_in(p, extracted) {
+                            format!("mut {}: {}", definition.name, ty)
+                        } else {
+                            format!("{}: {}", definition.name, ty)
+                        }
+                    }
+                    "go" => format!("{} {}", definition.name, ty.unwrap_or_else(|| "any".to_string())),
+                    "typescript" | "tsx" => match ty {
+                        Some(ty) => format!("{}: {}", definition.name, ty),
+                        None => definition.name.clone(),
+                    },
+                    _ => definition.name.clone(),
+                }
+            })
+            .collect();
+        let mut all_params: Vec<String> = placement.receiver.iter().filter(|_| self.lang != "go").cloned().collect();
+        all_params.extend(param_list);
+        let params_text = all_params.join(", ");
+
+        let result_types: Vec<String> = match extracted {
+            Extracted::Expression(node) => vec![self.expression_type(*node, 0).unwrap_or_else(|| self.unknown_type())],
+            Extracted::Statements { nodes, tail: true } => {
+                vec![self.expression_type(nodes[nodes.len() - 1], 0).unwrap_or_else(|| self.unknown_type())]
+            }
+            Extracted::Statements { .. } => returns
+                .iter()
+                .map(|&r| self.definition_type(&definitions[r], 0).unwrap_or_else(|| self.unknown_type()))
+                .collect(),
+        };
+        let result = match (self.lang, result_types.len()) {
+            ("rust" | "go", 0) => String::new(),
+            ("rust", 1) => format!(" -> {}", result_types[0]),
+            ("rust", _) => format!(" -> ({})", result_types.join(", ")),
+            ("go", 1) => format!(" {}", result_types[0]),
+            ("go", _) => format!(" ({})", result_types.join(", ")),
+            _ => String::new(),
+        };
+
+        let async_kw = if is_async { "async " } else { "" };
+        match self.lang {
+            "python" => format!("{}{}def {}({}):\n{}", fn_indent, async_kw, name, params_text, body),
+            "rust" => format!("{}{}fn {}({}){} {{\n{}\n{}}}", fn_indent, async_kw, name, params_text, result, body, fn_indent),
+            "go" => {
+                let receiver = placement.receiver.as_ref().map_or(String::new(), |r| format!("{} ", r));
+                format!("{}func {}{}({}){} {{\n{}\n{}}}", fn_indent, receiver, name, params_text, result, body, fn_indent)
+            }
+            _ if placement.method => format!(
+                "{}{}{}{}({}) {{\n{}\n{}}}",
+                fn_indent, placement.modifiers, async_kw, name, params_text, body, fn_indent
+            ),
+            _ => format!("{}{}function {}({}) {{\n{}\n{}}}", fn_indent, async_kw, name, params_text, body, fn_indent),
+        }
+    }
+
+    /// Source replacing the selection: the call, binding the returned values
+    #[allow(clippy::too_many_arguments)]
+    fn call_text(
+        &self,
+        name: &str,
+        extracted: &Extracted,
+        params: &[usize],
+        returns: &[usize],
+        placement: &Placement,
+        is_async: bool,
+        start: usize,
+        end: usize,
+    ) -> Option<String> {
+        let definitions = &self.names.definitions;
+        let args: Vec<String> = params
+            .iter()
+            .map(|&p| {
+                let definition = &definitions[p];
+                let ty = self.definition_type(definition, 0).unwrap_or_else(|| "_".to_string());
+                if self.lang == "rust" && self.passed_by_reference(p, returns, end, &ty) {
+                    format!("&{}", definition.name)
+                } else {
+                    definition.name.clone()
+                }
+            })
+            .collect();
+        let mut call = format!("{}{}({})", placement.call_prefix, name, args.join(", "));
+        if is_async {
+            call = if self.lang == "rust" { format!("{}.await", call) } else { format!("await {}", call) };
+        }
+
+        let statements = match extracted {
+            Extracted::Expression(_) => return Some(call),
+            Extracted::Statements { tail: true, .. } => return Some(call),
+            Extracted::Statements { nodes, .. } => nodes,
+        };
+        let terminator = if matches!(self.lang, "python" | "go") { "" } else { ";" };
+        if returns.is_empty() {
+            return Some(format!("{}{}", call, terminator));
+        }
+
+        let names: Vec<&str> = returns.iter().map(|&r| definitions[r].name.as_str()).collect();
+        let declared_inside = |r: usize| definitions[r].start >= start && definitions[r].end <= end;
+        Some(match self.lang {
+            "python" => format!("{} = {}", names.join(", "), call),
+            "go" => {
+                let op = if returns.iter().any(|&r| declared_inside(r)) { ":=" } else { "=" };
+                format!("{} {} {}", names.join(", "), op, call)
+            }
+            "rust" => {
+                let bindings: Vec<String> = returns
+                    .iter()
+                    .map(|&r| {
+                        let mutable = self.definition_node(&definitions[r])
+                            .and_then(|n| n.parent())
+                            .is_some_and(|p| p.kind() == "mut_pattern" || contains_kind_shallow(p, "mutable_specifier"));
+                        if mutable { format!("mut {}", definitions[r].name) } else { definitions[r].name.clone() }
+                    })
+                    .collect();
+                let pattern = if names.len() == 1 { names[0].to_string() } else { format!("({})", names.join(", ")) };
+                if returns.iter().all(|&r| !declared_inside(r)) {
+                    format!("{} = {};", pattern, call)
+                } else if bindings.len() == 1 {
+                    format!("let {} = {};", bindings[0], call)
+                } else {
+                    // Values declared before the selection are shadowed
+                    format!("let ({}) = {};", bindings.join(", "), call)
+                }
+            }
+            _ => {
+                let new: Vec<bool> = returns.iter().map(|&r| declared_inside(r)).collect();
+                let pattern = if names.len() == 1 { names[0].to_string() } else { format!("{{ {} }}", names.join(", ")) };
+                if new.iter().all(|&n| n) {
+                    let keyword = statements
+                        .iter()
+                        .find_map(|s| matches!(s.kind(), "lexical_declaration" | "variable_declaration").then(|| self.text(*s)))
+                        .and_then(|text| text.split_whitespace().next())
+                        .unwrap_or("const");
+                    format!("{} {} = {};", keyword, pattern, call)
+                } else if new.iter().all(|&n| !n) {
+                    if names.len() == 1 { format!("{} = {};", pattern, call) } else { format!("({} = {});", pattern, call) }
+                } else {
+                    return None;
+                }
+            }
+        })
+    }
+
+    /// Whether a Rust parameter is borrowed: it is read after the selection, not
+    /// returned, and not obviously `Copy`
+    fn passed_by_reference(&self, param: usize, returns: &[usize], end: usize, ty: &str) -> bool {
+        let read_after = self.names.references.iter().any(|r| r.definition == Some(param) && r.start >= end);
+        read_after && !returns.contains(&param) && !is_copy_type(ty)
+    }
+
+    fn written_in(&self, param: usize, extracted: &Extracted) -> bool {
+        let (start, end) = match extracted {
+            Extracted::Statements { nodes, .. } => (nodes[0].start_byte(), nodes[nodes.len() - 1].end_byte()),
+            Extracted::Expression(node) => (node.start_byte(), node.end_byte()),
+        };
+        self.names.references.iter().any(|r| {
+            r.definition == Some(param)
+                && r.start >= start
+                && r.end <= end
+                && self.reference_node(r).is_some_and(|node| self.is_assignment_target(node))
+        })
+    }
+
+    fn unknown_type(&self) -> String {
+        if self.lang == "go" { "any".to_string() } else { "_".to_string() }
+    }
+
+    /// Identifier not yet used in the document, starting from `base`
+    fn unique_name(&self, base: &str) -> String {
+        let used: HashSet<&str> = self.names.definitions.iter().map(|d| d.name.as_str()).collect();
+        let mut name = base.to_string();
+        let mut n = 2;
+        while used.contains(name.as_str()) || contains_word(self.source, &name) {
+            name = format!("{}{}", base, n);
+            n += 1;
+        }
+        name
+    }
+
+    // --------------------------------------------------------------------
+    // Types
+    // --------------------------------------------------------------------
+
+    /// Declared or inferred type of a local, in the language's syntax
+    fn definition_type(&self, definition: &Definition, depth: usize) -> Option<String> {
+        if depth > 4 || matches!(self.lang, "python" | "javascript") {
+            return None;
+        }
+        let ident = self.definition_node(definition)?;
+        let parent = ident.parent()?;
+        let is_field = |node: Node, field: &str| node.child_by_field_name(field).is_some_and(|f| f.id() == ident.id());
+        let annotation = |node: Node| {
+            node.child_by_field_name("type")
+                .map(|t| self.text(t).trim_start_matches(':').trim().to_string())
+        };
+
+        match (self.lang, parent.kind()) {
+            ("rust", "parameter") if is_field(parent, "pattern") => annotation(parent),
+            ("rust", "let_declaration") if is_field(parent, "pattern") => annotation(parent).or_else(|| {
+                self.expression_type(parent.child_by_field_name("value")?, depth + 1)
+            }),
+            ("go", "parameter_declaration") => annotation(parent),
+            ("go", "var_spec") => annotation(parent).or_else(|| {
+                let value = parent.child_by_field_name("value")?.named_child(0)?;
+                self.expression_type(value, depth + 1)
+            }),
+            ("go", "expression_list") => {
+                let declaration = parent.parent().filter(|p| p.kind() == "short_var_declaration")?;
+                let mut cursor = parent.walk();
+                let position = parent.named_children(&mut cursor).position(|c| c.id() == ident.id())?;
+                let value = declaration.child_by_field_name("right")?.named_child(position)?;
+                self.expression_type(value, depth + 1)
+            }
+            (_, "required_parameter" | "optional_parameter") if is_field(parent, "pattern") => annotation(parent),
+            (_, "variable_declarator") if is_field(parent, "name") => annotation(parent).or_else(|| {
+                self.expression_type(parent.child_by_field_name("value")?, depth + 1)
+            }),
+            _ => None,
+        }
+    }
+
+    /// Type of simple expressions: literals, comparisons, arithmetic on typed locals
+    fn expression_type(&self, node: Node, depth: usize) -> Option<String> {
+        if depth > 4 {
+            return None;
+        }
+        let kind = node.kind();
+        let text = self.text(node);
+        let recurse = |child: Option<Node>| child.and_then(|c| self.expression_type(c, depth + 1));
+
+        match self.lang {
+            "rust" => match kind {
+                "integer_literal" => Some(rust_literal_suffix(text).unwrap_or("i32").to_string()),
+                "float_literal" => Some(rust_literal_suffix(text).unwrap_or("f64").to_string()),
+                "string_literal" | "raw_string_literal" => Some("&str".to_string()),
+                "boolean_literal" => Some("bool".to_string()),
+                "char_literal" => Some("char".to_string()),
+                "macro_invocation" if text.starts_with("format!") => Some("String".to_string()),
+                "struct_expression" => node.child_by_field_name("name").map(|n| self.text(n).to_string()),
+                "reference_expression" => {
+                    let inner = recurse(node.child_by_field_name("value"))?;
+                    Some(format!("&{}", inner))
+                }
+                "call_expression" => {
+                    let function = node.child_by_field_name("function")?;
+                    match function.kind() {
+                        "scoped_identifier" if function.child_by_field_name("name").is_some_and(|n| self.text(n) == "new") => {
+                            function.child_by_field_name("path").map(|p| self.text(p).to_string())
+                        }
+                        "field_expression" if function.child_by_field_name("field").is_some_and(|f| self.text(f) == "to_string") => {
+                            Some("String".to_string())
+                        }
+                        _ => None,
+                    }
+                }
+                _ => self.common_expression_type(node, depth, "bool"),
+            },
+            "go" => match kind {
+                "int_literal" => Some("int".to_string()),
+                "float_literal" => Some("float64".to_string()),
+                "interpreted_string_literal" | "raw_string_literal" => Some("string".to_string()),
+                "true" | "false" => Some("bool".to_string()),
+                "rune_literal" => Some("rune".to_string()),
+                "composite_literal" => node.child_by_field_name("type").map(|t| self.text(t).to_string()),
+                "unary_expression" if text.starts_with('&') => {
+                    let inner = recurse(node.child_by_field_name("operand"))?;
+                    Some(format!("*{}", inner))
+                }
+                _ => self.common_expression_type(node, depth, "bool"),
+            },
+            "typescript" | "tsx" => match kind {
+                "number" => Some("number".to_string()),
+                "string" | "template_string" => Some("string".to_string()),
+                "true" | "false" => Some("boolean".to_string()),
+                _ => self.common_expression_type(node, depth, "boolean"),
+            },
+            _ => None,
+        }
+    }
+
+    fn common_expression_type(&self, node: Node, depth: usize, boolean: &str) -> Option<String> {
+        match node.kind() {
+            "parenthesized_expression" => self.expression_type(node.named_child(0)?, depth + 1),
+            "binary_expression" => {
+                let operator = node.child_by_field_name("operator").map(|o| self.text(o))?;
+                if matches!(operator, "==" | "!=" | "<" | ">" | "<=" | ">=" | "&&" | "||" | "===" | "!==") {
+                    Some(boolean.to_string())
+                } else {
+                    self.expression_type(node.child_by_field_name("left")?, depth + 1)
+                        .or_else(|| self.expression_type(node.child_by_field_name("right")?, depth + 1))
+                }
+            }
+            "unary_expression" => {
+                let operand = node.child_by_field_name("operand").or_else(|| node.child_by_field_name("argument"))?;
+                if self.text(node).starts_with('!') {
+                    Some(boolean.to_string())
+                } else {
+                    self.expression_type(operand, depth + 1)
+                }
+            }
+            "identifier" => {
+                let reference = self.names.references.iter().find(|r| r.start == node.start_byte())?;
+                let definition = &self.names.definitions[reference.definition?];
+                self.definition_type(definition, depth + 1)
+            }
+            _ => None,
+        }
+    }
+
+    // --------------------------------------------------------------------
+    // Inline variable
+    // --------------------------------------------------------------------
+
+    fn inline_variable(&self, start: usize, end: usize) -> Option<Refactoring> {
+        // The variable under the cursor, at its declaration or at a use
+        let index = self
+            .names
+            .definitions
+            .iter()
+            .position(|d| d.start <= start && end <= d.end && !d.import && !d.parameter)
+            .or_else(|| {
+                self.names.references.iter().find(|r| r.start <= start && end <= r.end)?.definition
+            })?;
+        let definition = &self.names.definitions[index];
+        if definition.import || definition.parameter {
+            return None;
+        }
+        let (statement, value) = self.simple_declaration(definition)?;
+
+        // Rebinding or assigning the variable changes which value a use sees
+        let rebound = self.names.definitions.iter().enumerate().any(|(i, d)| {
+            i != index && d.name == definition.name && d.scope == definition.scope
+        });
+        if rebound {
+            return None;
+        }
+        let references: Vec<&NameReference> = self
+            .names
+            .references
+            .iter()
+            .filter(|r| r.definition == Some(index) && r.start >= statement.end_byte())
+            .collect();
+        if references.is_empty() {
+            return None;
+        }
+
+        let value_text = self.text(value);
+        let mut edits = Vec::new();
+        for reference in &references {
+            // Format-string captures cannot take an expression
+            let node = self.reference_node(reference)?;
+            if self.is_assignment_target(node) {
+                return None;
+            }
+            let new_text = if node.kind() == "shorthand_property_identifier" {
+                format!("{}: {}", definition.name, value_text)
+            } else if needs_parentheses(value, node) {
+                format!("({})", value_text)
+            } else {
+                value_text.to_string()
+            };
+            edits.push((reference.start, reference.end, new_text));
+        }
+
+        let (remove_start, remove_end) = line_extent(self.source, statement.start_byte(), statement.end_byte());
+        edits.push((remove_start, remove_end, String::new()));
+
+        Some(Refactoring {
+            title: format!("Inline variable '{}'", definition.name),
+            kind: CodeActionKind::REFACTOR_INLINE,
+            edits,
+        })
+    }
+
+    /// Statement declaring only `definition`, and its initializer
+    fn simple_declaration(&self, definition: &Definition) -> Option<(Node<'t>, Node<'t>)> {
+        let ident = self.definition_node(definition)?;
+        let parent = ident.parent()?;
+        let is_field = |node: Node, field: &str| node.child_by_field_name(field).is_some_and(|f| f.id() == ident.id());
+
+        match (self.lang, parent.kind()) {
+            ("python", "assignment") if is_field(parent, "left") => {
+                let statement = parent.parent().filter(|p| p.kind() == "expression_statement")?;
+                let value = parent.child_by_field_name("right")?;
+                (value.kind() != "assignment").then_some((statement, value))
+            }
+            ("rust", "let_declaration") if is_field(parent, "pattern") => {
+                if parent.child_by_field_name("alternative").is_some() {
+                    return None;
+                }
+                Some((parent, parent.child_by_field_name("value")?))
+            }
+            ("go", "expression_list") => {
+                let statement = parent.parent().filter(|p| p.kind() == "short_var_declaration")?;
+                let right = statement.child_by_field_name("right")?;
+                (parent.named_child_count() == 1 && right.named_child_count() == 1)
+                    .then_some((statement, right.named_child(0)?))
+            }
+            ("go", "var_spec") if is_field(parent, "name") => {
+                let declaration = parent.parent().filter(|p| p.kind() == "var_declaration")?;
+                let values = parent.child_by_field_name("value")?;
+                (declaration.named_child_count() == 1 && values.named_child_count() == 1)
+                    .then_some((declaration, values.named_child(0)?))
+            }
+            (_, "variable_declarator") if is_field(parent, "name") => {
+                let statement = parent
+                    .parent()
+                    .filter(|p| matches!(p.kind(), "lexical_declaration" | "variable_declaration"))?;
+                (statement.named_children(&mut statement.walk()).filter(|c| c.kind() == "variable_declarator").count() == 1)
+                    .then_some((statement, parent.child_by_field_name("value")?))
+            }
+            _ => None,
+        }
+    }
+
+    // --------------------------------------------------------------------
+    // Introduce parameter
+    // --------------------------------------------------------------------
+
+    /// Turn the selected expression into a parameter of the enclosing function;
+    /// calls in this document pass the expression instead
+    fn introduce_parameter(&self, start: usize, end: usize) -> Option<Refactoring> {
+        let expression = self.selected_expression(start, end)?;
+        let function = {
+            let mut current = expression.parent();
+            loop {
+                let node = current?;
+                if is_named_function(node.kind()) {
+                    break node;
+                }
+                current = node.parent();
+            }
+        };
+        if contains_kind(expression, &["this", "self", "super", "await", "await_expression", "yield"]) {
+            return None;
+        }
+
+        // The expression may only use names that are also visible at the call sites
+        let depends_on_locals = self.names.references.iter().any(|r| {
+            r.start >= start
+                && r.end <= end
+                && r.definition.is_some_and(|d| function.byte_range().contains(&self.names.definitions[d].start))
+        });
+        if depends_on_locals {
+            return None;
+        }
+
+        let parameters = function.child_by_field_name("parameters")?;
+        let mut cursor = parameters.walk();
+        let existing: Vec<Node> = parameters.named_children(&mut cursor).filter(|p| !p.kind().contains("comment")).collect();
+        let unsupported = existing.iter().any(|p| {
+            matches!(
+                p.kind(),
+                "default_parameter" | "typed_default_parameter" | "list_splat_pattern" | "dictionary_splat_pattern"
+                    | "keyword_separator" | "positional_separator" | "assignment_pattern" | "rest_pattern"
+                    | "optional_parameter" | "variadic_parameter_declaration" | "variadic_parameter"
+            ) || (p.kind() == "required_parameter" && p.child_by_field_name("value").is_some())
+        });
+        if unsupported {
+            return None;
+        }
+
+        let name = self.parameter_name(expression, function);
+        let ty = self.expression_type(expression, 0);
+        let declaration = match self.lang {
+            "rust" => format!("{}: {}", name, ty.unwrap_or_else(|| "_".to_string())),
+            "go" => format!("{} {}", name, ty.unwrap_or_else(|| "any".to_string())),
+            "typescript" | "tsx" => match ty {
+                Some(ty) => format!("{}: {}", name, ty),
+                None => name.clone(),
+            },
+            _ => name.clone(),
+        };
+
+        let mut edits = vec![(start, end, name.clone())];
+        edits.push(append_to_list(parameters, &existing, &declaration));
+
+        // Update the calls of the function in this document
+        let function_name = self.text(function.child_by_field_name("name")?);
+        let argument = self.text(expression);
+        let mut stack = vec![self.root];
+        while let Some(node) = stack.pop() {
+            if matches!(node.kind(), "call" | "call_expression") && self.calls(node, function_name) {
+                let arguments = node.child_by_field_name("arguments")?;
+                let mut cursor = arguments.walk();
+                let args: Vec<Node> = arguments.named_children(&mut cursor).filter(|a| !a.kind().contains("comment")).collect();
+                let keyword = self.lang == "python"
+                    && args.iter().any(|a| matches!(a.kind(), "keyword_argument" | "list_splat" | "dictionary_splat"));
+                let passed = if keyword { format!("{}={}", name, argument) } else { argument.to_string() };
+                edits.push(append_to_list(arguments, &args, &passed));
+            }
+            let mut cursor = node.walk();
+            stack.extend(node.named_children(&mut cursor));
+        }
+
+        Some(Refactoring {
+            title: format!("Introduce parameter '{}'", name),
+            kind: CodeActionKind::REFACTOR_REWRITE,
+            edits,
+        })
+    }
+
+    /// Whether `call` calls a function or method named `name`
+    fn calls(&self, call: Node, name: &str) -> bool {
+        let Some(function) = call.child_by_field_name("function") else { return false };
+        let callee = match function.kind() {
+            "identifier" => Some(function),
+            "attribute" => function.child_by_field_name("attribute"),
+            "member_expression" => function.child_by_field_name("property"),
+            "field_expression" | "selector_expression" => function.child_by_field_name("field"),
+            "scoped_identifier" => function.child_by_field_name("name"),
+            _ => None,
+        };
+        callee.is_some_and(|c| self.text(c) == name)
+    }
+
+    /// Name for a parameter holding `expression`, unused in `function`
+    fn parameter_name(&self, expression: Node, function: Node) -> String {
+        let named = match expression.kind() {
+            "call" | "call_expression" => expression.child_by_field_name("function").and_then(|f| {
+                ["attribute", "property", "field", "name"].iter().find_map(|field| f.child_by_field_name(field)).or(Some(f))
+            }),
+            "attribute" => expression.child_by_field_name("attribute"),
+            "member_expression" => expression.child_by_field_name("property"),
+            "field_expression" | "selector_expression" => expression.child_by_field_name("field"),
+            _ => None,
+        };
+        let base = named
+            .map(|n| self.text(n))
+            .filter(|n| n.chars().all(|c| c.is_alphanumeric() || c == '_') && !n.is_empty())
+            .map(|n| n.trim_start_matches("get_").trim_start_matches("get").to_string())
+            .filter(|n| !n.is_empty() && n.starts_with(|c: char| c.is_alphabetic()))
+            .map(|n| {
+                let mut chars = n.chars();
+                chars.next().map_or(String::new(), |c| c.to_lowercase().chain(chars).collect())
+            })
+            .unwrap_or_else(|| INTRODUCED_PARAMETER.to_string());
+
+        let body = self.text(function);
+        let mut name = base.clone();
+        let mut n = 2;
+        while contains_word(body, &name) {
+            name = format!("{}{}", base, n);
+            n += 1;
+        }
+        name
+    }
+}
+
+/// Named functions and methods, the hosts of extracted functions
+fn is_named_function(kind: &str) -> bool {
+    matches!(
+        kind,
+        "function_definition"
+            | "function_declaration"
+            | "generator_function_declaration"
+            | "method_definition"
+            | "function_item"
+            | "method_declaration"
+    )
+}
+
+fn is_loop(kind: &str) -> bool {
+    matches!(
+        kind,
+        "for_statement"
+            | "while_statement"
+            | "for_in_statement"
+            | "do_statement"
+            | "switch_statement"
+            | "loop_expression"
+            | "for_expression"
+            | "while_expression"
+            | "expression_switch_statement"
+            | "type_switch_statement"
+            | "select_statement"
+    )
+}
+
+/// Nodes whose named children are statements
+fn statement_containers(lang: &str) -> &'static [&'static str] {
+    match lang {
+        "python" => &["block", "module"],
+        "javascript" | "typescript" | "tsx" => &["statement_block", "program"],
+        "rust" | "go" => &["block"],
+        _ => &[],
+    }
+}
+
+/// Whether nodes of `kind` are expressions (rather than statements, patterns or names)
+fn is_expression(kind: &str) -> bool {
+    !(kind.ends_with("statement")
+        || kind.ends_with("declaration")
+        || kind.ends_with("definition")
+        || kind.ends_with("_item")
+        || kind.ends_with("identifier")
+        || kind.ends_with("_type")
+        || kind.ends_with("_clause")
+        || kind.contains("pattern")
+        || kind.contains("parameter")
+        || kind.contains("comment")
+        || matches!(
+            kind,
+            "block" | "statement_block" | "module" | "program" | "source_file" | "argument_list" | "arguments"
+                | "expression_list" | "keyword_argument" | "pair" | "string_content" | "escape_sequence"
+                | "type_annotation" | "token_tree" | "let_declaration" | "match_arm" | "match_block"
+                | "field_initializer" | "shorthand_field_initializer" | "decorator" | "self" | "this" | "super"
+        ))
+}
+
+/// Rust tail expression: the last child of a block, not terminated by `;`
+fn is_rust_tail(node: Node, source: &str) -> bool {
+    let is_last = node.parent().is_some_and(|p| p.named_child(p.named_child_count().saturating_sub(1)).is_some_and(|l| l.id() == node.id()));
+    let terminated = source[node.end_byte()..].trim_start().starts_with(';') || source[..node.end_byte()].ends_with(';');
+    is_last
+        && !terminated
+        && !matches!(node.kind(), "expression_statement" | "let_declaration")
+        && !node.kind().ends_with("_item")
+        && node.parent().is_some_and(|p| p.parent().is_some_and(|f| f.kind() == "function_item"))
+}
+
+/// Whether a value must be parenthesized when it replaces `reference`
+fn needs_parentheses(value: Node, reference: Node) -> bool {
+    let atomic = value.kind().ends_with("identifier")
+        || value.kind().contains("literal")
+        || matches!(
+            value.kind(),
+            "integer" | "float" | "string" | "number" | "true" | "false" | "none" | "call" | "call_expression"
+                | "attribute" | "member_expression" | "field_expression" | "selector_expression" | "subscript"
+                | "subscript_expression" | "index_expression" | "parenthesized_expression" | "list" | "array"
+                | "dictionary" | "object" | "tuple" | "tuple_expression" | "array_expression" | "macro_invocation"
+                | "template_string" | "composite_literal" | "struct_expression"
+        );
+    let safe_context = reference.parent().is_some_and(|p| {
+        matches!(
+            p.kind(),
+            "argument_list" | "arguments" | "expression_list" | "return_statement" | "expression_statement"
+                | "parenthesized_expression" | "list" | "array" | "tuple" | "keyword_argument" | "pair"
+                | "variable_declarator" | "let_declaration" | "block" | "interpolation" | "literal_value"
+                | "array_expression" | "tuple_expression" | "template_substitution"
+        ) || (p.kind() == "assignment" && p.child_by_field_name("right").is_some_and(|r| r.id() == reference.id()))
+    });
+    !atomic && !safe_context
+}
+
+/// Edit appending `item` to the parenthesized list `list` with the given items
+fn append_to_list(list: Node, items: &[Node], item: &str) -> (usize, usize, String) {
+    match items.last() {
+        Some(last) => (last.end_byte(), last.end_byte(), format!(", {}", item)),
+        None => {
+            let open = list.start_byte() + 1;
+            (open, open, item.to_string())
+        }
+    }
+}
+
+fn is_copy_type(ty: &str) -> bool {
+    ty.starts_with('&')
+        || matches!(
+            ty,
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize"
+                | "f32" | "f64" | "bool" | "char"
+        )
+}
+
+fn rust_literal_suffix(text: &str) -> Option<&'static str> {
+    ["i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32", "f64"]
+        .into_iter()
+        .find(|suffix| text.ends_with(suffix) && !text.starts_with("0x"))
+}
+
+fn contains_kind(node: Node, kinds: &[&str]) -> bool {
+    let mut stack = vec![node];
+    while let Some(current) = stack.pop() {
+        if kinds.contains(&current.kind()) {
+            return true;
+        }
+        let mut cursor = current.walk();
+        stack.extend(current.children(&mut cursor));
+    }
+    false
+}
+
+fn contains_kind_shallow(node: Node, kind: &str) -> bool {
+    let mut cursor = node.walk();
+    let found = node.children(&mut cursor).any(|child| child.kind() == kind);
+    found
+}
+
+/// Whether `word` occurs in `text` as a whole identifier
+fn contains_word(text: &str, word: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).any(|(i, _)| {
+        !text[..i].ends_with(is_ident) && !text[i + word.len()..].starts_with(is_ident)
+    })
+}
+
+/// `start..end` without surrounding whitespace
+fn trim(source: &str, start: usize, end: usize) -> (usize, usize) {
+    let end = end.min(source.len());
+    let text = &source[start..end];
+    let trimmed_start = start + (text.len() - text.trim_start().len());
+    let trimmed_end = end - (text.len() - text.trim_end().len());
+    (trimmed_start.min(trimmed_end), trimmed_end)
+}
+
+fn line_start(source: &str, byte: usize) -> usize {
+    source[..byte].rfind('\n').map_or(0, |nl| nl + 1)
+}
+
+/// Leading whitespace of the line containing `byte`
+fn line_indent(source: &str, byte: usize) -> &str {
+    let line = &source[line_start(source, byte)..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// `text` with the indentation `from` of each line replaced by `to`
+fn reindent(text: &str, from: &str, to: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", to, line.strip_prefix(from).unwrap_or(line.trim_start()))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Byte range removing the statement `start..end` together with its line when it
+/// stands alone on it
+fn line_extent(source: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_begin = line_start(source, start);
+    let rest = &source[end..];
+    let line_end = rest.find('\n').map_or(source.len(), |nl| end + nl + 1);
+    let alone = source[line_begin..start].trim().is_empty() && source[end..line_end].trim().is_empty();
+    if alone {
+        (line_begin, line_end)
+    } else {
+        (start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_actions::edits::{apply_byte_edits, syntax_error_count};
+    use crate::tree_sitter::TreeSitterParser;
+
+    /// Apply the refactoring titled `title_prefix...` for the selection between the
+    /// `«` and `»` markers (a cursor when they are adjacent)
+    fn refactor(source: &str, lang: &str, title_prefix: &str) -> Option<String> {
+        let start = source.find('«').unwrap();
+        let source = source.replacen('«', "", 1);
+        let end = source.find('»').unwrap();
+        let source = source.replacen('»', "", 1);
+
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(&source, "test").unwrap();
+        let unit = match lang {
+            "go" => "\t",
+            "typescript" | "javascript" => "  ",
+            _ => "    ",
+        };
+        let refactoring = refactorings(&tree, &source, start, end, lang, unit)
+            .into_iter()
+            .find(|r| r.title.starts_with(title_prefix))?;
+
+        let result = apply_byte_edits(&source, &refactoring.edits);
+        assert_eq!(syntax_error_count(&result, lang), Some(0), "{}", result);
+        Some(result)
+    }
+
+    #[test]
+    fn test_extract_function_python() {
+        let source = "def total(items, tax):\n    «subtotal = sum(items)\n    amount = subtotal * tax»\n    return amount\n";
+        let result = refactor(source, "python", "Extract to function").unwrap();
+        assert_eq!(
+            result,
+            "def total(items, tax):\n    amount = extracted_function(items, tax)\n    return amount\n\n\n\
+             def extracted_function(items, tax):\n    subtotal = sum(items)\n    amount = subtotal * tax\n    return amount\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_method_python() {
+        let source = "class Cart:\n    def total(self):\n        «print(self.items)»\n        return 0\n";
+        let result = refactor(source, "python", "Extract to method").unwrap();
+        assert!(result.contains("        self.extracted_function()\n"));
+        assert!(result.contains("\n\n    def extracted_function(self):\n        print(self.items)"));
+    }
+
+    #[test]
+    fn test_extract_function_rust_infers_types() {
+        let source = "fn area(w: u32) -> u32 {\n    let h: u32 = 2;\n    «let a = w * h;»\n    a\n}\n";
+        let result = refactor(source, "rust", "Extract to function").unwrap();
+        assert!(result.contains("    let a = extracted_function(w, h);\n"), "{}", result);
+        assert!(result.contains("fn extracted_function(w: u32, h: u32) -> u32 {\n    let a = w * h;\n    a\n}"), "{}", result);
+    }
+
+    #[test]
+    fn test_extract_expression_go_and_typescript() {
+        let source = "package main\n\nfunc f(a int) int {\n\treturn «a + 1»\n}\n";
+        let result = refactor(source, "go", "Extract to function").unwrap();
+        assert!(result.contains("return extractedFunction(a)"));
+        assert!(result.contains("func extractedFunction(a int) int {\n\treturn a + 1\n}"));
+
+        let source = "function f(a: number) {\n  const b = «a * 2»;\n  return b;\n}\n";
+        let result = refactor(source, "typescript", "Extract to function").unwrap();
+        assert!(result.contains("function extractedFunction(a: number) {\n  return a * 2;\n}"));
+    }
+
+    #[test]
+    fn test_extract_rejects_control_flow() {
+        let source = "def f(x):\n    «if x:\n        return 1»\n    return 2\n";
+        assert!(refactor(source, "python", "Extract to function").is_none());
+    }
+
+    #[test]
+    fn test_inline_variable() {
+        let source = "def f(a, b):\n    «»total = a + b\n    return total * 2\n";
+        let result = refactor(source, "python", "Inline variable").unwrap();
+        assert_eq!(result, "def f(a, b):\n    return (a + b) * 2\n");
+
+        let source = "function f() {\n  const x = compute();\n  return { «»x };\n}\n";
+        let result = refactor(source, "javascript", "Inline variable").unwrap();
+        assert_eq!(result, "function f() {\n  return { x: compute() };\n}\n");
+
+        // Reassigned variables are left alone
+        let source = "fn f() -> i32 {\n    let mut «»x = 1;\n    x += 1;\n    x\n}\n";
+        assert!(refactor(source, "rust", "Inline variable").is_none());
+    }
+
+    #[test]
+    fn test_introduce_parameter() {
+        let source = "def greet(name):\n    return «\"Hello\"» + name\n\ngreet(\"Ann\")\ngreet(name=\"Bo\")\n";
+        let result = refactor(source, "python", "Introduce parameter").unwrap();
+        assert_eq!(
+            result,
+            "def greet(name, value):\n    return value + name\n\ngreet(\"Ann\", \"Hello\")\ngreet(name=\"Bo\", value=\"Hello\")\n"
+        );
+
+        let source = "fn scale(x: f64) -> f64 {\n    x * «2.5»\n}\n\nfn main() {\n    scale(1.0);\n}\n";
+        let result = refactor(source, "rust", "Introduce parameter").unwrap();
+        assert!(result.contains("fn scale(x: f64, value: f64) -> f64 {\n    x * value\n}"));
+        assert!(result.contains("scale(1.0, 2.5);"));
+
+        // Expressions reading locals cannot be passed by callers
+        let source = "def f(a):\n    return «a + 1»\n";
+        assert!(refactor(source, "python", "Introduce parameter").is_none());
+    }
+}
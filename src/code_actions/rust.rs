@@ -0,0 +1,713 @@
+//! Rust refactorings
+//!
+//! Each refactoring is computed from the syntax tree around the cursor and returned as
+//! byte-range edits; edits of one refactoring are insertions or disjoint replacements,
+//! so they can be applied together.
+
+use tower_lsp::lsp_types::CodeActionKind;
+use tree_sitter::Node;
+
+use super::Refactoring;
+
+/// Traits added by the derive refactoring
+const DERIVED_TRAITS: &[&str] = &["Debug", "Clone"];
+
+/// Refactorings available at `node`, the node under the cursor or selection;
+/// generated code is indented with `unit`
+pub fn refactorings(node: Node, source: &str, unit: &str) -> Vec<Refactoring> {
+    let mut refactorings = Vec::new();
+
+    if let Some(call) = ancestor(node, &["call_expression"], |call| unwrap_call(*call, source).is_some()) {
+        refactorings.extend(unwrap_to_question_mark(call, source));
+    }
+    if let Some(match_expr) = ancestor(node, &["match_expression"], |_| true) {
+        refactorings.extend(fill_match_arms(match_expr, source, unit));
+    }
+    if let Some(function) = ancestor(node, &["function_item"], |_| true) {
+        refactorings.extend(add_error_handling(function, source, unit));
+    }
+    if let Some(item) = ancestor(node, &["struct_item", "enum_item"], |_| true) {
+        refactorings.extend(add_derive(item, source));
+        refactorings.extend(generate_impl(item, source, unit));
+    }
+
+    refactorings
+}
+
+/// Innermost ancestor (or `node` itself) of one of `kinds` accepted by `accept`
+fn ancestor<'t>(node: Node<'t>, kinds: &[&str], accept: impl Fn(&Node<'t>) -> bool) -> Option<Node<'t>> {
+    let mut current = Some(node);
+    while let Some(candidate) = current {
+        if kinds.contains(&candidate.kind()) && accept(&candidate) {
+            return Some(candidate);
+        }
+        current = candidate.parent();
+    }
+    None
+}
+
+fn text<'s>(node: Node, source: &'s str) -> &'s str {
+    &source[node.start_byte()..node.end_byte()]
+}
+
+/// Leading whitespace of the line containing `byte`
+fn line_indent(source: &str, byte: usize) -> &str {
+    let line_start = source[..byte].rfind('\n').map_or(0, |i| i + 1);
+    let line = &source[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Root of the file containing `node`
+fn root(node: Node) -> Node {
+    let mut root = node;
+    while let Some(parent) = root.parent() {
+        root = parent;
+    }
+    root
+}
+
+/// Whether the file uses anyhow, by a `use anyhow...` or an `anyhow::` path
+fn uses_anyhow(node: Node, source: &str) -> bool {
+    let mut stack = vec![root(node)];
+    while let Some(current) = stack.pop() {
+        let found = match current.kind() {
+            "use_declaration" => current
+                .child_by_field_name("argument")
+                .is_some_and(|arg| text(arg, source).trim_start_matches("::").starts_with("anyhow")),
+            "scoped_identifier" | "scoped_type_identifier" => {
+                current.child_by_field_name("path").is_some_and(|path| text(path, source) == "anyhow")
+            }
+            _ => false,
+        };
+        if found {
+            return true;
+        }
+        let mut cursor = current.walk();
+        stack.extend(current.named_children(&mut cursor));
+    }
+    false
+}
+
+/// Last path segment of the outermost type (`Result` for `io::Result<T>`)
+fn outer_type_name<'s>(ty: Node, source: &'s str) -> &'s str {
+    let ty = match ty.kind() {
+        "generic_type" => ty.child_by_field_name("type").unwrap_or(ty),
+        _ => ty,
+    };
+    match ty.kind() {
+        "scoped_type_identifier" => ty.child_by_field_name("name").map_or("", |name| text(name, source)),
+        _ => text(ty, source),
+    }
+}
+
+/// Wrap the function's return type in `Result` and its return values in `Ok`
+fn add_error_handling(function: Node, source: &str, unit: &str) -> Option<Refactoring> {
+    let body = function.child_by_field_name("body")?;
+    let return_type = function.child_by_field_name("return_type");
+    if return_type.is_some_and(|ty| outer_type_name(ty, source) == "Result") {
+        return None;
+    }
+
+    let anyhow = uses_anyhow(function, source);
+    let error_result = |ok: &str| {
+        if anyhow {
+            format!("anyhow::Result<{}>", ok)
+        } else {
+            format!("Result<{}, Box<dyn std::error::Error>>", ok)
+        }
+    };
+
+    let mut edits = Vec::new();
+    match return_type {
+        Some(ty) => edits.push((ty.start_byte(), ty.end_byte(), error_result(text(ty, source)))),
+        None => {
+            let params = function.child_by_field_name("parameters")?;
+            edits.push((params.end_byte(), params.end_byte(), format!(" -> {}", error_result("()"))));
+        }
+    }
+
+    // Explicit returns of this function (not of nested closures or items)
+    let mut stack = vec![body];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "closure_expression" | "function_item" | "async_block" => continue,
+            "return_expression" => match node.named_child(0) {
+                Some(value) => {
+                    edits.push((value.start_byte(), value.start_byte(), "Ok(".to_string()));
+                    edits.push((value.end_byte(), value.end_byte(), ")".to_string()));
+                }
+                None => edits.push((node.end_byte(), node.end_byte(), " Ok(())".to_string())),
+            },
+            _ => {}
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+
+    let tail = tail_expression(body);
+    match (return_type, tail) {
+        (Some(_), Some(tail)) if tail.kind() != "return_expression" => {
+            edits.push((tail.start_byte(), tail.start_byte(), "Ok(".to_string()));
+            edits.push((tail.end_byte(), tail.end_byte(), ")".to_string()));
+        }
+        (Some(_), _) => {}
+        (None, tail) => {
+            // A unit tail expression becomes a statement before the new `Ok(())`
+            if let Some(tail) = tail.filter(|t| t.kind() != "expression_statement") {
+                edits.push((tail.end_byte(), tail.end_byte(), ";".to_string()));
+            }
+            let close = body.end_byte() - 1;
+            let close_line = source[..close].rfind('\n').map_or(0, |i| i + 1);
+            if source[close_line..close].trim().is_empty() && close_line > body.start_byte() {
+                let indent = format!("{}{}", line_indent(source, close), unit);
+                edits.push((close_line, close_line, format!("{}Ok(())\n", indent)));
+            } else {
+                edits.push((close, close, " Ok(()) ".to_string()));
+            }
+        }
+    }
+
+    Some(Refactoring {
+        title: "Add error handling (Result<T, E>)".to_string(),
+        kind: CodeActionKind::REFACTOR_REWRITE,
+        edits,
+    })
+}
+
+/// The expression a block evaluates to, if it ends in one
+fn tail_expression(block: Node) -> Option<Node> {
+    let mut cursor = block.walk();
+    let last = block
+        .named_children(&mut cursor)
+        .filter(|child| !matches!(child.kind(), "line_comment" | "block_comment"))
+        .last()?;
+
+    let is_statement = matches!(last.kind(), "let_declaration" | "empty_statement" | "use_declaration" | "attribute_item")
+        || last.kind().ends_with("_item");
+    if is_statement {
+        return None;
+    }
+    if last.kind() == "expression_statement" {
+        // Block-like expressions (`if`, `match`) end a block without a semicolon
+        let terminated = last.child(last.child_count().saturating_sub(1)).is_some_and(|c| c.kind() == ";");
+        return (!terminated).then_some(last);
+    }
+    Some(last)
+}
+
+/// Add `Debug` and `Clone` to the item's derives, merging into an existing `#[derive]`
+fn add_derive(item: Node, source: &str) -> Option<Refactoring> {
+    let mut derive_args = None;
+    let mut sibling = item.prev_named_sibling();
+    while let Some(attribute_item) = sibling {
+        match attribute_item.kind() {
+            "attribute_item" => {
+                let attribute = attribute_item.named_child(0);
+                let is_derive = attribute
+                    .and_then(|a| a.named_child(0))
+                    .is_some_and(|name| text(name, source) == "derive");
+                if is_derive {
+                    derive_args = attribute.and_then(|a| a.child_by_field_name("arguments"));
+                    break;
+                }
+            }
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = attribute_item.prev_named_sibling();
+    }
+
+    let Some(args) = derive_args else {
+        let indent = line_indent(source, item.start_byte());
+        return Some(Refactoring {
+            title: format!("Add #[derive({})]", DERIVED_TRAITS.join(", ")),
+            kind: CodeActionKind::REFACTOR,
+            edits: vec![(
+                item.start_byte(),
+                item.start_byte(),
+                format!("#[derive({})]\n{}", DERIVED_TRAITS.join(", "), indent),
+            )],
+        });
+    };
+
+    // Inside the parentheses of `derive(...)`
+    let inner_start = args.start_byte() + 1;
+    let inner = &source[inner_start..args.end_byte() - 1];
+    let existing: Vec<&str> = inner
+        .split(',')
+        .map(|path| path.trim().rsplit("::").next().unwrap_or(""))
+        .collect();
+    let missing: Vec<&str> = DERIVED_TRAITS.iter().copied().filter(|t| !existing.contains(t)).collect();
+    if missing.is_empty() {
+        return None;
+    }
+
+    let content = inner.trim_end();
+    let separator = if content.trim().is_empty() {
+        ""
+    } else if content.ends_with(',') {
+        " "
+    } else {
+        ", "
+    };
+    let insert_at = inner_start + content.len();
+
+    Some(Refactoring {
+        title: format!("Add {} to #[derive]", missing.join(", ")),
+        kind: CodeActionKind::REFACTOR,
+        edits: vec![(insert_at, insert_at, format!("{}{}", separator, missing.join(", ")))],
+    })
+}
+
+/// Generate an inherent `impl` block, with a `new` constructor for structs with named fields
+fn generate_impl(item: Node, source: &str, unit: &str) -> Option<Refactoring> {
+    let name = text(item.child_by_field_name("name")?, source);
+
+    // Only offered while the type has no inherent impl yet
+    let mut stack = vec![root(item)];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "impl_item" && node.child_by_field_name("trait").is_none() {
+            let self_type = node.child_by_field_name("type").map(|t| text(t, source)).unwrap_or("");
+            if self_type.split('<').next() == Some(name) {
+                return None;
+            }
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+
+    let (impl_generics, type_args) = match item.child_by_field_name("type_parameters") {
+        Some(params) => {
+            let mut declared = Vec::new();
+            let mut args = Vec::new();
+            let mut cursor = params.walk();
+            for param in params.named_children(&mut cursor) {
+                let (decl, arg) = match param.kind() {
+                    "constrained_type_parameter" => (
+                        text(param, source),
+                        param.child_by_field_name("left").map_or("", |l| text(l, source)),
+                    ),
+                    // Defaults are not allowed on impl generics
+                    "optional_type_parameter" => {
+                        let name = param.child_by_field_name("name").map_or("", |n| text(n, source));
+                        (name, name)
+                    }
+                    "const_parameter" => (
+                        text(param, source),
+                        param.child_by_field_name("name").map_or("", |n| text(n, source)),
+                    ),
+                    _ => (text(param, source), text(param, source)),
+                };
+                declared.push(decl);
+                args.push(arg);
+            }
+            (format!("<{}>", declared.join(", ")), format!("<{}>", args.join(", ")))
+        }
+        None => (String::new(), String::new()),
+    };
+
+    let indent = line_indent(source, item.start_byte());
+    let fields: Vec<(&str, &str)> = match item.child_by_field_name("body") {
+        Some(body) if item.kind() == "struct_item" && body.kind() == "field_declaration_list" => {
+            let mut cursor = body.walk();
+            body.named_children(&mut cursor)
+                .filter(|field| field.kind() == "field_declaration")
+                .filter_map(|field| {
+                    Some((
+                        text(field.child_by_field_name("name")?, source),
+                        text(field.child_by_field_name("type")?, source),
+                    ))
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let mut block = format!("\n\n{indent}impl{impl_generics} {name}{type_args} {{\n");
+    if !fields.is_empty() {
+        let params: Vec<String> = fields.iter().map(|(field, ty)| format!("{}: {}", field, ty)).collect();
+        let names: Vec<&str> = fields.iter().map(|(field, _)| *field).collect();
+        block.push_str(&format!(
+            "{indent}{unit}pub fn new({}) -> Self {{\n{indent}{unit}{unit}Self {{ {} }}\n{indent}{unit}}}\n",
+            params.join(", "),
+            names.join(", "),
+        ));
+    }
+    block.push_str(&format!("{indent}}}"));
+
+    Some(Refactoring {
+        title: format!("Generate impl block for '{}'", name),
+        kind: CodeActionKind::REFACTOR,
+        edits: vec![(item.end_byte(), item.end_byte(), block)],
+    })
+}
+
+/// Add arms for the variants of a same-file enum the match does not cover yet
+fn fill_match_arms(match_expr: Node, source: &str, unit: &str) -> Option<Refactoring> {
+    let block = match_expr.child_by_field_name("body")?;
+    let mut cursor = block.walk();
+    let arms: Vec<Node> = block.named_children(&mut cursor).filter(|n| n.kind() == "match_arm").collect();
+
+    let mut enum_path = None;
+    let mut covered = Vec::new();
+    let mut wildcard = None;
+    for arm in &arms {
+        let Some(pattern) = arm.child_by_field_name("pattern") else { continue };
+        if text(pattern, source).trim() == "_" {
+            wildcard = Some(*arm);
+        }
+        for (path, variant) in variant_paths(pattern, source) {
+            enum_path.get_or_insert(path);
+            covered.push(variant);
+        }
+    }
+
+    // Without arms to go by, use the declared type of the matched parameter
+    let enum_path = match enum_path {
+        Some(path) => path,
+        None => matched_parameter_type(match_expr, source)?,
+    };
+    let enum_name = enum_path.rsplit("::").next()?;
+    let variants = enum_variants(match_expr, enum_name, source)?;
+
+    let missing: Vec<String> = variants
+        .into_iter()
+        .filter(|(variant, _)| !covered.contains(&variant.as_str()))
+        .map(|(variant, body)| {
+            let fields = match body.as_deref() {
+                Some("ordered_field_declaration_list") => "(..)",
+                Some("field_declaration_list") => " { .. }",
+                _ => "",
+            };
+            format!("{}::{}{} => todo!(),", enum_path, variant, fields)
+        })
+        .collect();
+    if missing.is_empty() {
+        return None;
+    }
+
+    let arm_indent = match arms.first() {
+        Some(arm) => line_indent(source, arm.start_byte()).to_string(),
+        None => format!("{}{}", line_indent(source, match_expr.start_byte()), unit),
+    };
+
+    // New arms go before a catch-all arm, or after the last arm
+    let edit = match wildcard {
+        Some(arm) => {
+            let new_arms: String = missing.iter().map(|m| format!("{}\n{}", m, arm_indent)).collect();
+            (arm.start_byte(), arm.start_byte(), new_arms)
+        }
+        None => {
+            let insert_at = arms.last().map_or(block.start_byte() + 1, |arm| arm.end_byte());
+            let mut new_arms = String::new();
+            let needs_comma = arms.last().is_some_and(|arm| {
+                let arm_text = text(*arm, source).trim_end();
+                !arm_text.ends_with(',') && !arm_text.ends_with('}')
+            });
+            if needs_comma {
+                new_arms.push(',');
+            }
+            for arm in &missing {
+                new_arms.push_str(&format!("\n{}{}", arm_indent, arm));
+            }
+            // Keep the closing brace on a line of its own
+            if !source[insert_at..block.end_byte()].contains('\n') {
+                new_arms.push('\n');
+                new_arms.push_str(line_indent(source, match_expr.start_byte()));
+            }
+            (insert_at, insert_at, new_arms)
+        }
+    };
+
+    Some(Refactoring {
+        title: "Fill match arms".to_string(),
+        kind: CodeActionKind::REFACTOR_REWRITE,
+        edits: vec![edit],
+    })
+}
+
+/// `(enum path, variant)` pairs named by a match pattern
+fn variant_paths<'s>(pattern: Node, source: &'s str) -> Vec<(String, &'s str)> {
+    let mut paths = Vec::new();
+    let mut stack = vec![pattern];
+    while let Some(node) = stack.pop() {
+        let path_node = match node.kind() {
+            "scoped_identifier" | "scoped_type_identifier" => Some(node),
+            "tuple_struct_pattern" | "struct_pattern" => node.child_by_field_name("type"),
+            "match_pattern" | "or_pattern" => {
+                let mut cursor = node.walk();
+                stack.extend(node.named_children(&mut cursor));
+                None
+            }
+            _ => None,
+        };
+        let Some(path_node) = path_node else { continue };
+        if let (Some(path), Some(name)) = (path_node.child_by_field_name("path"), path_node.child_by_field_name("name")) {
+            paths.push((text(path, source).to_string(), text(name, source)));
+        }
+    }
+    paths
+}
+
+/// Type name of the parameter a match expression scrutinizes (`fn f(s: &Shape)`)
+fn matched_parameter_type(match_expr: Node, source: &str) -> Option<String> {
+    let value = text(match_expr.child_by_field_name("value")?, source).trim_start_matches(['&', '*']);
+    let function = ancestor(match_expr, &["function_item"], |_| true)?;
+    let params = function.child_by_field_name("parameters")?;
+    let mut cursor = params.walk();
+    let param = params
+        .named_children(&mut cursor)
+        .find(|p| p.child_by_field_name("pattern").is_some_and(|pat| text(pat, source) == value))?;
+    let ty = text(param.child_by_field_name("type")?, source);
+    Some(ty.trim_start_matches('&').trim_start_matches("mut ").trim().to_string())
+}
+
+/// Variants of the enum `name` declared in the same file, with the kind of their fields
+fn enum_variants(node: Node, name: &str, source: &str) -> Option<Vec<(String, Option<String>)>> {
+    let mut stack = vec![root(node)];
+    while let Some(current) = stack.pop() {
+        let is_target = current.kind() == "enum_item"
+            && current.child_by_field_name("name").is_some_and(|n| text(n, source) == name);
+        if is_target {
+            let body = current.child_by_field_name("body")?;
+            let mut cursor = body.walk();
+            let variants = body
+                .named_children(&mut cursor)
+                .filter(|v| v.kind() == "enum_variant")
+                .filter_map(|v| {
+                    let variant = text(v.child_by_field_name("name")?, source).to_string();
+                    let fields = v.child_by_field_name("body").map(|b| b.kind().to_string());
+                    Some((variant, fields))
+                })
+                .collect();
+            return Some(variants);
+        }
+        let mut cursor = current.walk();
+        stack.extend(current.named_children(&mut cursor));
+    }
+    None
+}
+
+/// Receiver of an `x.unwrap()` / `x.expect(..)` call
+fn unwrap_call<'t>(call: Node<'t>, source: &str) -> Option<(Node<'t>, &'static str)> {
+    let function = call.child_by_field_name("function")?;
+    if function.kind() != "field_expression" {
+        return None;
+    }
+    let method = match text(function.child_by_field_name("field")?, source) {
+        "unwrap" => "unwrap",
+        "expect" => "expect",
+        _ => return None,
+    };
+    Some((function.child_by_field_name("value")?, method))
+}
+
+/// Replace `x.unwrap()` with `x?` inside functions returning `Result` or `Option`
+///
+/// Only offered when the receiver is of the same kind as the return type, or when its
+/// kind isn't evident from the code.
+fn unwrap_to_question_mark(call: Node, source: &str) -> Option<Refactoring> {
+    let (receiver, method) = unwrap_call(call, source)?;
+
+    let function = ancestor(call, &["function_item", "closure_expression"], |_| true)?;
+    let returns = outer_type_name(function.child_by_field_name("return_type")?, source);
+    if returns != "Result" && returns != "Option" {
+        return None;
+    }
+    if receiver_kind(receiver, source).is_some_and(|kind| kind != returns) {
+        return None;
+    }
+
+    Some(Refactoring {
+        title: format!("Replace {}() with ?", method),
+        kind: CodeActionKind::REFACTOR_REWRITE,
+        edits: vec![(receiver.end_byte(), call.end_byte(), "?".to_string())],
+    })
+}
+
+/// `"Result"` or `"Option"` when the kind of `value` is evident: constructors, common
+/// std methods and functions of the same file
+fn receiver_kind(value: Node, source: &str) -> Option<&'static str> {
+    match value.kind() {
+        "identifier" if text(value, source) == "None" => Some("Option"),
+        "call_expression" => {
+            let mut function = value.child_by_field_name("function")?;
+            // `s.parse::<T>()`
+            if function.kind() == "generic_function" {
+                function = function.child_by_field_name("function")?;
+            }
+            let name = match function.kind() {
+                "field_expression" => return method_kind(text(function.child_by_field_name("field")?, source)),
+                "identifier" => text(function, source),
+                "scoped_identifier" => text(function.child_by_field_name("name")?, source),
+                _ => return None,
+            };
+            match name {
+                "Some" => Some("Option"),
+                "Ok" | "Err" => Some("Result"),
+                _ => local_function_kind(value, name, source),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Kind returned by a std method returning `Result` or `Option`
+fn method_kind(method: &str) -> Option<&'static str> {
+    match method {
+        "ok" | "err" | "get" | "get_mut" | "first" | "last" | "next" | "pop" | "find" | "position"
+        | "strip_prefix" | "strip_suffix" | "split_once" | "checked_add" | "checked_sub" | "checked_mul"
+        | "checked_div" | "parent" | "file_name" | "extension" | "to_str" => Some("Option"),
+        "ok_or" | "ok_or_else" | "parse" | "try_into" => Some("Result"),
+        _ => None,
+    }
+}
+
+/// Kind returned by the function `name` declared in the same file
+fn local_function_kind(node: Node, name: &str, source: &str) -> Option<&'static str> {
+    let mut stack = vec![root(node)];
+    while let Some(current) = stack.pop() {
+        let is_target = current.kind() == "function_item"
+            && current.child_by_field_name("name").is_some_and(|n| text(n, source) == name);
+        if is_target {
+            return match outer_type_name(current.child_by_field_name("return_type")?, source) {
+                "Result" => Some("Result"),
+                "Option" => Some("Option"),
+                _ => None,
+            };
+        }
+        let mut cursor = current.walk();
+        stack.extend(current.named_children(&mut cursor));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_sitter::TreeSitterParser;
+
+    /// Refactorings at the first occurrence of `marker`, applied one at a time
+    fn apply(source: &str, marker: &str, title_prefix: &str) -> Option<String> {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language("rust").unwrap();
+        let tree = parser.parse(source, "test.rs").unwrap();
+
+        let offset = source.find(marker).unwrap();
+        let node = tree.root_node().descendant_for_byte_range(offset, offset).unwrap();
+        let refactoring = refactorings(node, source, "    ")
+            .into_iter()
+            .find(|r| r.title.starts_with(title_prefix))?;
+
+        let mut edits = refactoring.edits;
+        edits.sort_by_key(|(start, end, _)| (*start, *end));
+        let mut result = source.to_string();
+        for (start, end, new_text) in edits.into_iter().rev() {
+            result.replace_range(start..end, &new_text);
+        }
+
+        // The refactored code still parses
+        let tree = parser.parse(&result, "test.rs").unwrap();
+        assert!(!tree.root_node().has_error(), "invalid result:\n{}", result);
+        Some(result)
+    }
+
+    #[test]
+    fn test_add_error_handling() {
+        let source = "fn parse(s: &str) -> i32 {\n    if s.is_empty() {\n        return 0;\n    }\n    s.len() as i32\n}\n";
+        let result = apply(source, "parse", "Add error handling").unwrap();
+        assert_eq!(
+            result,
+            "fn parse(s: &str) -> Result<i32, Box<dyn std::error::Error>> {\n    if s.is_empty() {\n        return Ok(0);\n    }\n    Ok(s.len() as i32)\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_add_error_handling_ignores_anyhow_in_comments() {
+        let source = "// Errors are reported like anyhow does\nfn run() {\n    println!(\"anyhow\")\n}\n";
+        let result = apply(source, "run", "Add error handling").unwrap();
+        assert!(result.contains("fn run() -> Result<(), Box<dyn std::error::Error>> {"));
+
+        let source = "fn run() {\n    anyhow::ensure!(true);\n}\n";
+        let result = apply(source, "run", "Add error handling").unwrap();
+        assert!(result.contains("fn run() -> anyhow::Result<()> {"));
+    }
+
+    #[test]
+    fn test_add_error_handling_to_unit_function() {
+        let source = "use anyhow::Context;\n\nfn run() {\n    println!(\"hi\")\n}\n";
+        let result = apply(source, "run", "Add error handling").unwrap();
+        assert_eq!(result, "use anyhow::Context;\n\nfn run() -> anyhow::Result<()> {\n    println!(\"hi\");\n    Ok(())\n}\n");
+    }
+
+    #[test]
+    fn test_derive_added_and_merged() {
+        let source = "struct Point {\n    x: i32,\n}\n";
+        let result = apply(source, "Point", "Add #[derive").unwrap();
+        assert_eq!(result, "#[derive(Debug, Clone)]\nstruct Point {\n    x: i32,\n}\n");
+
+        let source = "#[derive(PartialEq, Debug)]\nstruct Point;\n";
+        let result = apply(source, "Point", "Add Clone").unwrap();
+        assert_eq!(result, "#[derive(PartialEq, Debug, Clone)]\nstruct Point;\n");
+
+        let source = "#[derive(Clone, Debug)]\nstruct Point;\n";
+        assert!(apply(source, "Point", "Add").is_none());
+    }
+
+    #[test]
+    fn test_fill_match_arms() {
+        let source = "enum Shape { Circle(f64), Square { side: f64 }, Empty }\n\nfn area(s: &Shape) -> f64 {\n    match s {\n        Shape::Circle(r) => r * r,\n    }\n}\n";
+        let result = apply(source, "match", "Fill match arms").unwrap();
+        assert!(result.contains("        Shape::Circle(r) => r * r,\n        Shape::Square { .. } => todo!(),\n        Shape::Empty => todo!(),\n    }"));
+
+        // From the parameter type when there are no arms yet
+        let source = "enum Shape { Circle(f64), Empty }\n\nfn area(s: &Shape) -> f64 {\n    match s {\n    }\n}\n";
+        let result = apply(source, "match", "Fill match arms").unwrap();
+        assert!(result.contains("Shape::Circle(..) => todo!(),\n        Shape::Empty => todo!(),"));
+    }
+
+    #[test]
+    fn test_fill_match_arms_before_wildcard() {
+        let source = "enum E { A, B, C }\n\nfn f(e: E) -> u8 {\n    match e {\n        E::A => 1,\n        _ => 0,\n    }\n}\n";
+        let result = apply(source, "match", "Fill match arms").unwrap();
+        assert!(result.contains("E::A => 1,\n        E::B => todo!(),\n        E::C => todo!(),\n        _ => 0,"));
+    }
+
+    #[test]
+    fn test_generate_impl_with_constructor() {
+        let source = "pub struct Pair<T: Clone> {\n    left: T,\n    right: T,\n}\n";
+        let result = apply(source, "Pair", "Generate impl block").unwrap();
+        assert!(result.ends_with(
+            "impl<T: Clone> Pair<T> {\n    pub fn new(left: T, right: T) -> Self {\n        Self { left, right }\n    }\n}\n"
+        ));
+
+        let with_impl = format!("{}impl<T: Clone> Pair<T> {{}}\n", source);
+        assert!(apply(&with_impl, "Pair", "Generate impl block").is_none());
+    }
+
+    #[test]
+    fn test_unwrap_to_question_mark() {
+        let source = "fn read() -> std::io::Result<String> {\n    let s = std::fs::read_to_string(\"a\").unwrap();\n    Ok(s)\n}\n";
+        let result = apply(source, "unwrap", "Replace unwrap() with ?").unwrap();
+        assert!(result.contains("let s = std::fs::read_to_string(\"a\")?;"));
+
+        // Not offered where `?` cannot propagate
+        let source = "fn main() {\n    let s = std::fs::read_to_string(\"a\").unwrap();\n}\n";
+        assert!(apply(source, "unwrap", "Replace unwrap() with ?").is_none());
+        let source = "fn rows() -> MyResultSet {\n    let n = \"1\".parse::<i32>().unwrap();\n    MyResultSet::new(n)\n}\n";
+        assert!(apply(source, "unwrap", "Replace unwrap() with ?").is_none());
+    }
+
+    #[test]
+    fn test_unwrap_to_question_mark_needs_matching_receiver() {
+        let source = "fn first(v: &[i32]) -> Option<i32> {\n    let x = v.first().unwrap();\n    Some(*x)\n}\n";
+        let result = apply(source, "unwrap", "Replace unwrap() with ?").unwrap();
+        assert!(result.contains("let x = v.first()?;"));
+
+        // An `Option` can't be propagated from a function returning `Result`
+        let source = "fn first(v: &[i32]) -> Result<i32, String> {\n    let x = v.first().unwrap();\n    Ok(*x)\n}\n";
+        assert!(apply(source, "unwrap", "Replace unwrap() with ?").is_none());
+        let source = "fn lookup() -> Option<u8> {\n    None\n}\n\nfn run() -> anyhow::Result<u8> {\n    let x = lookup().unwrap();\n    Ok(x)\n}\n";
+        assert!(apply(source, "unwrap", "Replace unwrap() with ?").is_none());
+    }
+}
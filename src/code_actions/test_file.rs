@@ -0,0 +1,135 @@
+//! Where generated tests go
+//!
+//! Follows each ecosystem's convention: Rust tests live in a `#[cfg(test)] mod tests`
+//! of the same file, Go in `<name>_test.go`, Python in `test_<name>.py` (inside an
+//! existing `tests/` directory when there is one), JavaScript and TypeScript in
+//! `<name>.test.<ext>` (or `__tests__/`), and Java under `src/test/java`.
+
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::*;
+
+use crate::tree_sitter::TreeSitterParser;
+
+/// Test file for the source file at `path`, `None` when tests go in the file itself
+pub fn test_file_for(path: &Path, lang: &str) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    let stem = path.file_stem()?.to_str()?;
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    match lang {
+        "python" => {
+            if stem.starts_with("test_") || stem.ends_with("_test") {
+                return None;
+            }
+            let file = format!("test_{}.py", stem);
+            let tests_dir = [dir.join("tests"), dir.parent()?.join("tests")]
+                .into_iter()
+                .find(|d| d.is_dir());
+            Some(tests_dir.unwrap_or_else(|| dir.to_path_buf()).join(file))
+        }
+        "javascript" | "typescript" | "tsx" => {
+            if stem.ends_with(".test") || stem.ends_with(".spec") {
+                return None;
+            }
+            let file = format!("{}.test.{}", stem, ext);
+            let tests_dir = dir.join("__tests__");
+            Some(if tests_dir.is_dir() { tests_dir.join(file) } else { dir.join(file) })
+        }
+        "go" => (!stem.ends_with("_test")).then(|| dir.join(format!("{}_test.go", stem))),
+        "java" => {
+            if stem.ends_with("Test") {
+                return None;
+            }
+            let file = format!("{}Test.java", stem);
+            let dir_str = dir.to_string_lossy();
+            let test_dir = match dir_str.find("/src/main/java") {
+                Some(idx) => PathBuf::from(format!("{}/src/test/java{}", &dir_str[..idx], &dir_str[idx + 14..])),
+                None => dir.to_path_buf(),
+            };
+            Some(test_dir.join(file))
+        }
+        _ => None,
+    }
+}
+
+/// Insertion point and text adding generated `tests` to Rust `source`
+///
+/// Extends an existing `mod tests`, or appends a new `#[cfg(test)]` module.
+pub fn rust_tests_insertion(source: &str, tests: &str) -> (Position, String) {
+    let indented: String = tests
+        .lines()
+        .map(|line| if line.is_empty() { "\n".to_string() } else { format!("    {}\n", line) })
+        .collect();
+
+    if let Some(close) = tests_module_close(source) {
+        let line = source[..close].matches('\n').count() as u32;
+        return (Position { line, character: 0 }, format!("\n{}", indented));
+    }
+
+    let separator = if source.ends_with('\n') { "\n" } else { "\n\n" };
+    (
+        super::edits::end_position(source),
+        format!("{}#[cfg(test)]\nmod tests {{\n    use super::*;\n\n{}}}\n", separator, indented),
+    )
+}
+
+/// Byte offset of the line holding the closing brace of the top-level `mod tests`
+fn tests_module_close(source: &str) -> Option<usize> {
+    let mut parser = TreeSitterParser::new().ok()?;
+    parser.set_language("rust").ok()?;
+    let tree = parser.parse(source, "").ok()?;
+    let root = tree.root_node();
+
+    let mut cursor = root.walk();
+    let module = root.children(&mut cursor).find(|node| {
+        node.kind() == "mod_item"
+            && node
+                .child_by_field_name("name")
+                .is_some_and(|name| &source[name.byte_range()] == "tests")
+    })?;
+    let body = module.child_by_field_name("body")?;
+    let close = body.end_byte().checked_sub(1)?;
+    Some(source[..close].rfind('\n').map_or(0, |nl| nl + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conventional_test_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("pkg")).unwrap();
+
+        assert_eq!(test_file_for(&dir.path().join("pkg/util.go"), "go"), Some(dir.path().join("pkg/util_test.go")));
+        assert_eq!(test_file_for(&dir.path().join("pkg/util.ts"), "typescript"), Some(dir.path().join("pkg/util.test.ts")));
+        assert_eq!(test_file_for(&dir.path().join("pkg/util.py"), "python"), Some(dir.path().join("pkg/test_util.py")));
+        assert_eq!(test_file_for(&dir.path().join("pkg/util.rs"), "rust"), None);
+        assert_eq!(test_file_for(&dir.path().join("pkg/util_test.go"), "go"), None);
+
+        // An existing tests/ directory next to the package is preferred
+        std::fs::create_dir_all(dir.path().join("tests")).unwrap();
+        assert_eq!(test_file_for(&dir.path().join("pkg/util.py"), "python"), Some(dir.path().join("tests/test_util.py")));
+
+        assert_eq!(
+            test_file_for(Path::new("/p/src/main/java/com/x/Util.java"), "java"),
+            Some(PathBuf::from("/p/src/test/java/com/x/UtilTest.java"))
+        );
+    }
+
+    #[test]
+    fn test_rust_tests_insertion() {
+        let tests = "#[test]\nfn adds() {\n    assert_eq!(add(1, 2), 3);\n}";
+
+        let source = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        let (position, text) = rust_tests_insertion(source, tests);
+        assert_eq!(position, Position { line: 3, character: 0 });
+        assert!(text.starts_with("\n#[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n"));
+        assert!(text.ends_with("    }\n}\n"));
+
+        let source = "fn add() {}\n\n#[cfg(test)]\nmod tests {\n    use super::*;\n}\n";
+        let (position, text) = rust_tests_insertion(source, tests);
+        assert_eq!(position, Position { line: 5, character: 0 });
+        assert!(text.starts_with("\n    #[test]\n    fn adds() {\n"));
+    }
+}
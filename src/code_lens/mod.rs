@@ -7,7 +7,7 @@
 
 use anyhow::Result;
 use tower_lsp::lsp_types::*;
-use crate::tree_sitter::TreeSitterParser;
+use crate::tree_sitter::{byte_to_position, TreeSitterParser};
 
 /// Code lens provider
 #[derive(Debug)]
@@ -379,33 +379,11 @@ impl CodeLensProvider {
 
     /// Convert tree-sitter node to LSP Range
     fn node_to_range(&self, node: tree_sitter::Node, content: &str) -> Range {
-        let start = self.byte_to_position(content, node.start_byte());
-        let end = self.byte_to_position(content, node.end_byte());
+        let start = byte_to_position(content, node.start_byte());
+        let end = byte_to_position(content, node.end_byte());
         Range { start, end }
     }
 
-    /// Convert byte offset to LSP Position
-    fn byte_to_position(&self, content: &str, byte_offset: usize) -> Position {
-        let mut line = 0;
-        let mut character = 0;
-
-        for (i, ch) in content.char_indices() {
-            if i >= byte_offset {
-                break;
-            }
-            if ch == '\n' {
-                line += 1;
-                character = 0;
-            } else {
-                character += 1;
-            }
-        }
-
-        Position {
-            line: line as u32,
-            character: character as u32,
-        }
-    }
 }
 
 #[cfg(test)]
@@ -618,14 +596,13 @@ def test_something():
 
     #[test]
     fn test_position_conversion() {
-        let provider = CodeLensProvider::new();
         let content = "line1\nline2\nline3";
 
-        let pos1 = provider.byte_to_position(content, 0);
+        let pos1 = byte_to_position(content, 0);
         assert_eq!(pos1.line, 0);
         assert_eq!(pos1.character, 0);
 
-        let pos2 = provider.byte_to_position(content, 6);
+        let pos2 = byte_to_position(content, 6);
         assert_eq!(pos2.line, 1);
         assert_eq!(pos2.character, 0);
     }
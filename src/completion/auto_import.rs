@@ -0,0 +1,212 @@
+//! Imports for symbols defined in other workspace files
+//!
+//! A completion for a symbol of another file carries the import it needs as an
+//! additional edit, placed after the document's leading imports (or where the first
+//! import would go). Statements are derived from the files' paths: Python modules
+//! relative to the workspace root, relative JavaScript/TypeScript specifiers, Rust
+//! `crate::` paths within the same crate, and Java packages. Go and Java files in
+//! the same directory share a package and need no import.
+
+use std::path::{Component, Path, PathBuf};
+use tower_lsp::lsp_types::{Position, Range, TextEdit};
+use tree_sitter::{Node, Tree};
+
+use crate::code_actions::imports::import_block;
+
+/// Edits importing `name` from `target` into the document at `from`: empty when no
+/// import is needed, `None` when the symbol cannot be imported
+pub fn import_edits(
+    tree: &Tree,
+    source: &str,
+    lang: &str,
+    from: &Path,
+    target: &Path,
+    name: &str,
+    root: Option<&Path>,
+) -> Option<Vec<TextEdit>> {
+    let statement = match lang {
+        "python" => python_import(target, name, root.or(from.parent())?)?,
+        "javascript" | "typescript" | "tsx" => js_import(source, from, target, name)?,
+        "rust" => rust_import(from, target, name)?,
+        "go" => return (from.parent() == target.parent()).then(Vec::new),
+        "java" => {
+            if from.parent() == target.parent() {
+                return Some(Vec::new());
+            }
+            java_import(target, name)?
+        }
+        _ => return None,
+    };
+
+    // Already imported under that name
+    if source.lines().any(|line| line.trim() == statement) {
+        return Some(Vec::new());
+    }
+
+    let root_node = tree.root_node();
+    let (position, new_text) = match import_block(root_node, lang) {
+        Some(block) => (end_position(block[block.len() - 1]), format!("\n{}", statement)),
+        None => match preamble_end(root_node) {
+            Some(node) => (end_position(node), format!("\n\n{}", statement)),
+            None => (Position::new(0, 0), format!("{}\n\n", statement)),
+        },
+    };
+    Some(vec![TextEdit { range: Range { start: position, end: position }, new_text }])
+}
+
+/// `from package.module import name`
+fn python_import(target: &Path, name: &str, root: &Path) -> Option<String> {
+    let relative = target.strip_prefix(root).ok()?.with_extension("");
+    let mut module: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+    if module.last().is_some_and(|last| last == "__init__") {
+        module.pop();
+    }
+    (!module.is_empty()).then(|| format!("from {} import {}", module.join("."), name))
+}
+
+/// `import { name } from './relative/path';`, quoted like the document's imports
+fn js_import(source: &str, from: &Path, target: &Path, name: &str) -> Option<String> {
+    let specifier = js_specifier(from, target)?;
+    let quote = if source.matches("from \"").count() > source.matches("from '").count() { '"' } else { '\'' };
+    Some(format!("import {{ {} }} from {}{}{};", name, quote, specifier, quote))
+}
+
+/// Relative module specifier of `target` in the file at `from`: `./widget`,
+/// `../lib` for `../lib/index.ts`
+pub(super) fn js_specifier(from: &Path, target: &Path) -> Option<String> {
+    let mut specifier = relative_path(from.parent()?, &target.with_extension(""))
+        .to_string_lossy()
+        .replace('\\', "/");
+    if let Some(directory) = specifier.strip_suffix("/index") {
+        specifier = directory.to_string();
+    }
+    if !specifier.starts_with("../") {
+        specifier = format!("./{}", specifier);
+    }
+    Some(specifier)
+}
+
+/// `use crate::module::name;` for files of the same crate
+fn rust_import(from: &Path, target: &Path, name: &str) -> Option<String> {
+    let crate_root = target.ancestors().find(|dir| dir.join("Cargo.toml").is_file())?;
+    if !from.starts_with(crate_root) || from.ancestors().find(|dir| dir.join("Cargo.toml").is_file()) != Some(crate_root) {
+        return None;
+    }
+
+    let relative = target.strip_prefix(crate_root.join("src")).ok()?.with_extension("");
+    let mut path = vec!["crate".to_string()];
+    path.extend(relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()));
+    if path.iter().any(|segment| segment == "bin") {
+        return None;
+    }
+    if (path.len() == 2 && matches!(path[1].as_str(), "lib" | "main")) || path.last().is_some_and(|last| last == "mod") {
+        path.pop();
+    }
+    path.push(name.to_string());
+    Some(format!("use {};", path.join("::")))
+}
+
+/// `import package.name;` with the package the target file declares
+fn java_import(target: &Path, name: &str) -> Option<String> {
+    let source = std::fs::read_to_string(target).ok()?;
+    let package = source
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("package "))?
+        .trim_end_matches(';')
+        .trim();
+    Some(format!("import {}.{};", package, name))
+}
+
+/// `target` relative to the directory `base`
+pub(super) fn relative_path(base: &Path, target: &Path) -> PathBuf {
+    let base: Vec<Component> = base.components().collect();
+    let target: Vec<Component> = target.components().collect();
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let mut path = PathBuf::new();
+    for _ in common..base.len() {
+        path.push("..");
+    }
+    for component in &target[common..] {
+        path.push(component);
+    }
+    path
+}
+
+/// Last top-level node before the first import would go: a package clause, module
+/// docstring or `"use strict"` directive, or leading comments
+fn preamble_end(root: Node) -> Option<Node> {
+    let mut last = None;
+    for child in root.named_children(&mut root.walk()) {
+        let preamble = match child.kind() {
+            "package_clause" | "package_declaration" | "inner_attribute_item" => true,
+            kind if kind.contains("comment") => true,
+            // Docstrings and directives
+            "expression_statement" => {
+                child.named_child_count() == 1 && child.named_child(0).is_some_and(|n| n.kind() == "string")
+            }
+            _ => false,
+        };
+        if !preamble {
+            break;
+        }
+        last = Some(child);
+    }
+    last
+}
+
+fn end_position(node: Node) -> Position {
+    let end = node.end_position();
+    Position::new(end.row as u32, end.column as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_actions::edits::apply_text_edits;
+    use crate::tree_sitter::TreeSitterParser;
+
+    fn import(source: &str, lang: &str, from: &str, target: &str, root: Option<&Path>) -> Option<String> {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        let edits = import_edits(&tree, source, lang, Path::new(from), Path::new(target), "Widget", root)?;
+        Some(apply_text_edits(source, &edits))
+    }
+
+    #[test]
+    fn test_import_statements() {
+        let root = Path::new("/ws");
+        assert_eq!(
+            import("import os\n\nos.getcwd()\n", "python", "/ws/app/main.py", "/ws/app/ui/widgets.py", Some(root)).unwrap(),
+            "import os\nfrom app.ui.widgets import Widget\n\nos.getcwd()\n"
+        );
+        assert_eq!(
+            import("\"\"\"Docs.\"\"\"\nx = 1\n", "python", "/ws/a.py", "/ws/pkg/__init__.py", Some(root)).unwrap(),
+            "\"\"\"Docs.\"\"\"\n\nfrom pkg import Widget\nx = 1\n"
+        );
+        assert_eq!(
+            import("import { a } from \"./a\";\n", "typescript", "/ws/src/app/main.ts", "/ws/src/ui/index.ts", None).unwrap(),
+            "import { a } from \"./a\";\nimport { Widget } from \"../ui\";\n"
+        );
+        assert_eq!(
+            import("package main\n\nfunc main() {}\n", "go", "/ws/main.go", "/ws/widget.go", None).unwrap(),
+            "package main\n\nfunc main() {}\n"
+        );
+        assert_eq!(import("package main\n", "go", "/ws/main.go", "/ws/ui/widget.go", None), None);
+    }
+
+    #[test]
+    fn test_rust_import_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/ui")).unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+
+        let source = "//! Entry point\n\nfn main() {}\n";
+        let from = dir.path().join("src/main.rs");
+        let imported = import(source, "rust", from.to_str().unwrap(), dir.path().join("src/ui/mod.rs").to_str().unwrap(), None);
+        assert_eq!(imported.unwrap(), "//! Entry point\n\nuse crate::ui::Widget;\n\nfn main() {}\n");
+        let imported = import(source, "rust", from.to_str().unwrap(), dir.path().join("src/ui/button.rs").to_str().unwrap(), None);
+        assert!(imported.unwrap().contains("use crate::ui::button::Widget;"));
+    }
+}
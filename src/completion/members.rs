@@ -0,0 +1,642 @@
+//! Members of the receiver of a member access
+//!
+//! The receiver before `.` or `::` is read back from the text as a path of names
+//! (`self.engine`), since the expression being typed rarely parses cleanly. Its first
+//! name resolves to a type through the enclosing class (`self`, `this`), through the
+//! binding it refers to (a type annotation, or the constructor call, literal or `new`
+//! expression it was assigned from), or by naming a type itself. Each further name
+//! resolves through the declared type of that field.
+//!
+//! Members are collected from the type's definitions in the document and in the
+//! workspace files defining it, then from its supertypes, plus the methods the
+//! workspace index records for the type elsewhere (Rust impls, Go receivers).
+
+use std::collections::HashSet;
+use tower_lsp::lsp_types::{CompletionItemKind, Url};
+use tree_sitter::{Node, Tree};
+
+use crate::diagnostics::usage::names_in_scope;
+use crate::language::grammar_name;
+use crate::tree_sitter::TreeSitterParser;
+use crate::type_hierarchy::extract_types;
+use crate::workspace_index::WorkspaceIndex;
+
+/// How many levels of supertypes contribute members
+const MAX_SUPERTYPE_DEPTH: usize = 3;
+
+/// Smart pointers whose members are those of their content
+const POINTER_TYPES: &[&str] = &["Box", "Rc", "Arc"];
+
+/// A field, method or variant of a type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    pub kind: CompletionItemKind,
+    /// Declared type of a field, signature of a method
+    pub detail: Option<String>,
+    /// File and line of the declaration
+    pub uri: Option<Url>,
+    pub line: u32,
+}
+
+/// The document a completion is requested in
+pub struct Document<'a> {
+    pub tree: &'a Tree,
+    pub source: &'a str,
+    pub lang: &'a str,
+    pub uri: &'a Url,
+}
+
+/// Members of the receiver ending before `access` at byte `end`, or `None` when the
+/// receiver's type cannot be resolved
+pub fn receiver_members(doc: &Document, index: &WorkspaceIndex, end: usize, access: &str) -> Option<Vec<Member>> {
+    let path = receiver_path(&doc.source[..end]);
+    let (first, start) = path.first()?.clone();
+
+    // `a::b::Type::` names the type directly
+    let mut ty = if access == "::" {
+        let (name, start) = path.last()?;
+        match name.as_str() {
+            "Self" => enclosing_type(doc, *start)?,
+            _ => name.clone(),
+        }
+    } else {
+        let mut ty = first_type(doc, index, &first, start)?;
+        for (name, _) in &path[1..] {
+            let field = members_of(doc, index, &ty).into_iter().find(|m| &m.name == name)?;
+            ty = type_name(field.detail.as_deref()?)?;
+        }
+        ty
+    };
+    if ty == "Self" {
+        ty = enclosing_type(doc, start)?;
+    }
+
+    let members: Vec<Member> = members_of(doc, index, &ty)
+        .into_iter()
+        .filter(|member| match access {
+            // Fields need a value, variants a path
+            "::" => member.kind != CompletionItemKind::FIELD,
+            _ => !(doc.lang == "rust" && member.kind == CompletionItemKind::ENUM_MEMBER),
+        })
+        .collect();
+    (!members.is_empty()).then_some(members)
+}
+
+/// Names of the receiver path ending the text, with their start bytes
+fn receiver_path(text: &str) -> Vec<(String, usize)> {
+    let mut path = Vec::new();
+    let mut end = text.len();
+    loop {
+        let start = text[..end]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map_or(end, |(i, _)| i);
+        if start == end || text[start..].starts_with(|c: char| c.is_ascii_digit()) {
+            // Calls, indexing and literals are not followed
+            return Vec::new();
+        }
+        path.push((text[start..end].to_string(), start));
+
+        let before = &text[..start];
+        end = match [".", "::", "->"].iter().find(|sep| before.ends_with(**sep)) {
+            Some(sep) => start - sep.len(),
+            None => break,
+        };
+    }
+    path.reverse();
+    path
+}
+
+/// Type of the first name of a receiver path
+fn first_type(doc: &Document, index: &WorkspaceIndex, name: &str, byte: usize) -> Option<String> {
+    if matches!(name, "self" | "this" | "cls" | "Self") && !(doc.lang == "python" && name == "this") {
+        return enclosing_type(doc, byte);
+    }
+
+    let Some(definition) = names_in_scope(doc.tree, doc.source, doc.lang, byte)
+        .into_iter()
+        .find(|definition| definition.name == name)
+    else {
+        return is_type(doc, index, name).then(|| name.to_string());
+    };
+
+    let node = doc.tree.root_node().named_descendant_for_byte_range(definition.start, definition.end)?;
+    if node.parent().is_some_and(|parent| is_type_definition(parent, doc.lang)) {
+        return Some(name.to_string());
+    }
+    let ty = binding_type(node, doc.source)?;
+    match ty.as_str() {
+        "Self" => enclosing_type(doc, definition.start),
+        _ => Some(ty),
+    }
+}
+
+/// Type of a binding from its annotation or the value it is initialized with
+fn binding_type(ident: Node, source: &str) -> Option<String> {
+    let inside = |node: Node| node.start_byte() <= ident.start_byte() && ident.end_byte() <= node.end_byte();
+
+    let mut current = ident.parent();
+    for _ in 0..4 {
+        let node = current?;
+        if node.kind().contains("block") || node.kind().ends_with("body") || node.parent().is_none() {
+            return None;
+        }
+        if let Some(ty) = node.child_by_field_name("type").filter(|ty| !inside(*ty)) {
+            return type_name(&source[ty.byte_range()]);
+        }
+        for field in ["value", "right"] {
+            if let Some(value) = node.child_by_field_name(field).filter(|value| !inside(*value)) {
+                // `a, b := x, y` pairs the names with the values by position
+                let value = match value.kind() {
+                    "expression_list" => value.named_child(0)?,
+                    _ => value,
+                };
+                return value_type(value, source);
+            }
+        }
+        current = node.parent();
+    }
+    None
+}
+
+/// Type an expression evidently constructs
+fn value_type(value: Node, source: &str) -> Option<String> {
+    let text = |node: Node| &source[node.byte_range()];
+    let capitalized = |name: &str| name.starts_with(|c: char| c.is_uppercase()).then(|| name.to_string());
+
+    match value.kind() {
+        // `Foo()`, `models.Foo()`
+        "call" => {
+            let function = value.child_by_field_name("function")?;
+            let name = match function.kind() {
+                "attribute" => text(function.child_by_field_name("attribute")?),
+                _ => text(function),
+            };
+            capitalized(name)
+        }
+        "call_expression" => {
+            let function = value.child_by_field_name("function")?;
+            match function.kind() {
+                // `Foo::new()`
+                "scoped_identifier" => type_name(text(function.child_by_field_name("path")?)),
+                // `NewFoo()`, `pkg.NewFoo()`
+                "identifier" | "selector_expression" => {
+                    let name = text(function).rsplit('.').next()?;
+                    name.strip_prefix("New").and_then(capitalized)
+                }
+                _ => None,
+            }
+        }
+        "new_expression" => type_name(text(value.child_by_field_name("constructor")?)),
+        "object_creation_expression" | "composite_literal" => type_name(text(value.child_by_field_name("type")?)),
+        "struct_expression" => type_name(text(value.child_by_field_name("name")?)),
+        "unary_expression" => value_type(value.child_by_field_name("operand")?, source),
+        "reference_expression" => value_type(value.child_by_field_name("value")?, source),
+        "parenthesized_expression" | "try_expression" => value_type(value.named_child(0)?, source),
+        _ => None,
+    }
+}
+
+/// Bare name of a type written in source (`&mut foo::Bar<T>` is `Bar`)
+pub fn type_name(text: &str) -> Option<String> {
+    let mut ty = text.trim().trim_start_matches(':').trim();
+    loop {
+        let stripped = ty.trim_start_matches(['&', '*']).trim_start();
+        let stripped = stripped.strip_prefix("mut ").unwrap_or(stripped);
+        let stripped = match stripped.strip_prefix('\'') {
+            Some(lifetime) => lifetime.split_once(' ').map_or(stripped, |(_, rest)| rest),
+            None => stripped,
+        };
+        if stripped == ty {
+            break;
+        }
+        ty = stripped.trim_start();
+    }
+
+    for pointer in POINTER_TYPES {
+        if let Some(inner) = ty.strip_prefix(pointer).and_then(|rest| rest.strip_prefix('<')?.strip_suffix('>')) {
+            return type_name(inner);
+        }
+    }
+
+    let ty = ty.split(['<', '[', '(', '|']).next()?.trim();
+    let ty = ty.rsplit("::").next()?.rsplit('.').next()?;
+    let valid = ty.starts_with(|c: char| c.is_alphabetic() || c == '_') && ty.chars().all(|c| c.is_alphanumeric() || c == '_');
+    valid.then(|| ty.to_string())
+}
+
+/// Class or impl type around `byte`
+fn enclosing_type(doc: &Document, byte: usize) -> Option<String> {
+    let mut current = doc.tree.root_node().descendant_for_byte_range(byte, byte);
+    while let Some(node) = current {
+        let name = match (doc.lang, node.kind()) {
+            ("python", "class_definition")
+            | ("javascript" | "typescript" | "tsx", "class_declaration" | "class" | "abstract_class_declaration")
+            | ("java", "class_declaration" | "interface_declaration" | "enum_declaration" | "record_declaration") => {
+                node.child_by_field_name("name")
+            }
+            ("rust", "impl_item") => node.child_by_field_name("type"),
+            ("go", "method_declaration") => node
+                .child_by_field_name("receiver")
+                .and_then(|receiver| receiver.named_child(0))
+                .and_then(|parameter| parameter.child_by_field_name("type")),
+            _ => None,
+        };
+        if let Some(name) = name {
+            return type_name(&doc.source[name.byte_range()]);
+        }
+        current = node.parent();
+    }
+    None
+}
+
+fn is_type_definition(node: Node, lang: &str) -> bool {
+    matches!(
+        (lang, node.kind()),
+        ("python", "class_definition")
+            | ("javascript" | "typescript" | "tsx", "class_declaration" | "abstract_class_declaration" | "interface_declaration" | "enum_declaration")
+            | ("rust", "struct_item" | "enum_item" | "trait_item" | "union_item")
+            | ("go", "type_spec")
+            | ("java", "class_declaration" | "interface_declaration" | "enum_declaration" | "record_declaration")
+    )
+}
+
+/// Whether a type named `name` is defined in the document or the workspace
+fn is_type(doc: &Document, index: &WorkspaceIndex, name: &str) -> bool {
+    let mut stack = vec![doc.tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if is_type_definition(node, doc.lang)
+            && node.child_by_field_name("name").is_some_and(|n| &doc.source[n.byte_range()] == name)
+        {
+            return true;
+        }
+        stack.extend(node.named_children(&mut node.walk()));
+    }
+    !index.types_named(name).is_empty()
+}
+
+/// Members of `ty` and its supertypes, own members first
+fn members_of(doc: &Document, index: &WorkspaceIndex, ty: &str) -> Vec<Member> {
+    let local_relations = extract_types(doc.source, doc.lang).map(|types| types.relations).unwrap_or_default();
+
+    let mut members = Vec::new();
+    let mut visited = HashSet::new();
+    let mut level = vec![ty.to_string()];
+    for _ in 0..=MAX_SUPERTYPE_DEPTH {
+        let mut supertypes = Vec::new();
+        for ty in level.drain(..) {
+            if !visited.insert(ty.clone()) {
+                continue;
+            }
+            members.extend(type_members(doc, index, &ty));
+            supertypes.extend(local_relations.iter().filter(|r| r.subtype == ty).map(|r| r.supertype.clone()));
+            supertypes.extend(
+                index
+                    .relations_where(|r| r.subtype == ty)
+                    .into_iter()
+                    .filter(|(uri, _)| uri != doc.uri)
+                    .map(|(_, r)| r.supertype),
+            );
+        }
+        level = supertypes;
+    }
+
+    let mut seen = HashSet::new();
+    members.retain(|member: &Member| seen.insert(member.name.clone()));
+    members
+}
+
+/// Members `ty` itself declares, in the document and across the workspace
+fn type_members(doc: &Document, index: &WorkspaceIndex, ty: &str) -> Vec<Member> {
+    let mut members = declared_members(doc.tree.root_node(), doc.source, doc.lang, ty, doc.uri);
+
+    // Definitions in other files of the same language, read from disk
+    let mut files = HashSet::new();
+    for (uri, _) in index.types_named(ty) {
+        if &uri == doc.uri || !files.insert(uri.clone()) || grammar_name(uri.path()) != doc.lang {
+            continue;
+        }
+        let Some(source) = uri.to_file_path().ok().and_then(|path| std::fs::read_to_string(path).ok()) else {
+            continue;
+        };
+        let Ok(mut parser) = TreeSitterParser::new() else {
+            continue;
+        };
+        if parser.set_language(doc.lang).is_err() {
+            continue;
+        }
+        if let Ok(tree) = parser.parse(&source, uri.as_str()) {
+            members.extend(declared_members(tree.root_node(), &source, doc.lang, ty, &uri));
+        }
+    }
+
+    // Methods declared away from the type (impl blocks, Go receivers)
+    members.extend(
+        index
+            .callables_in(ty)
+            .into_iter()
+            .filter(|(uri, _)| uri != doc.uri && grammar_name(uri.path()) == doc.lang)
+            .map(|(uri, callable)| Member {
+                name: callable.name,
+                kind: CompletionItemKind::METHOD,
+                detail: None,
+                uri: Some(uri),
+                line: callable.selection_range.start.line,
+            }),
+    );
+    members
+}
+
+/// Members declared by the definitions of `ty` in a syntax tree
+fn declared_members(root: Node, source: &str, lang: &str, ty: &str, uri: &Url) -> Vec<Member> {
+    let text = |node: Node| &source[node.byte_range()];
+    let named = |node: Node, field: &str| node.child_by_field_name(field).is_some_and(|name| text(name) == ty);
+
+    let mut members = Vec::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let body = node.child_by_field_name("body");
+        match (lang, node.kind()) {
+            ("python", "class_definition") if named(node, "name") => {
+                python_members(body, source, &mut members);
+            }
+            ("javascript" | "typescript" | "tsx", "class_declaration" | "class" | "abstract_class_declaration" | "interface_declaration")
+                if named(node, "name") =>
+            {
+                js_members(body, source, &mut members);
+            }
+            ("rust", "struct_item" | "enum_item" | "trait_item") if named(node, "name") => {
+                rust_members(body, source, &mut members);
+            }
+            ("rust", "impl_item") => {
+                let implemented = node.child_by_field_name("type").and_then(|t| type_name(text(t)));
+                if implemented.as_deref() == Some(ty) {
+                    rust_members(body, source, &mut members);
+                }
+            }
+            ("go", "type_spec") if named(node, "name") => {
+                go_members(node.child_by_field_name("type"), source, &mut members);
+            }
+            ("go", "method_declaration") => {
+                let receiver = node
+                    .child_by_field_name("receiver")
+                    .and_then(|receiver| receiver.named_child(0))
+                    .and_then(|parameter| parameter.child_by_field_name("type"))
+                    .and_then(|t| type_name(text(t)));
+                if receiver.as_deref() == Some(ty) {
+                    push_named(node, source, CompletionItemKind::METHOD, &mut members);
+                }
+            }
+            ("java", "class_declaration" | "interface_declaration" | "enum_declaration" | "record_declaration")
+                if named(node, "name") =>
+            {
+                java_members(body, source, &mut members);
+            }
+            _ => {}
+        }
+        // Definitions anywhere, in document order
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+    for member in &mut members {
+        member.uri = Some(uri.clone());
+    }
+    members
+}
+
+fn python_members(body: Option<Node>, source: &str, members: &mut Vec<Member>) {
+    let Some(body) = body else {
+        return;
+    };
+    for child in body.named_children(&mut body.walk()) {
+        let definition = match child.kind() {
+            "decorated_definition" => child.child_by_field_name("definition"),
+            _ => Some(child),
+        };
+        match definition {
+            Some(function) if function.kind() == "function_definition" => {
+                push_named(function, source, CompletionItemKind::METHOD, members);
+            }
+            _ => {
+                // Class attributes
+                if let Some(assignment) = child.named_child(0).filter(|n| n.kind() == "assignment") {
+                    if let Some(left) = assignment.child_by_field_name("left").filter(|l| l.kind() == "identifier") {
+                        push_field(left, assignment, source, members);
+                    }
+                }
+            }
+        }
+    }
+
+    // Instance attributes assigned through `self`
+    let mut stack = vec![body];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "assignment" {
+            let attribute = node.child_by_field_name("left").filter(|left| left.kind() == "attribute");
+            if let Some(attribute) = attribute.filter(|a| {
+                a.child_by_field_name("object").is_some_and(|object| &source[object.byte_range()] == "self")
+            }) {
+                if let Some(name) = attribute.child_by_field_name("attribute") {
+                    push_field(name, node, source, members);
+                }
+            }
+        }
+        if node.kind() != "class_definition" || node == body {
+            stack.extend(node.named_children(&mut node.walk()));
+        }
+    }
+}
+
+fn js_members(body: Option<Node>, source: &str, members: &mut Vec<Member>) {
+    let Some(body) = body else {
+        return;
+    };
+    for child in body.named_children(&mut body.walk()) {
+        match child.kind() {
+            "method_definition" | "method_signature" | "abstract_method_signature"
+                if child.child_by_field_name("name").is_some_and(|name| &source[name.byte_range()] != "constructor") =>
+            {
+                push_named(child, source, CompletionItemKind::METHOD, members);
+            }
+            "field_definition" | "public_field_definition" | "property_signature" => {
+                let name = child.child_by_field_name("name").or_else(|| child.child_by_field_name("property"));
+                if let Some(name) = name {
+                    push_field(name, child, source, members);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Properties assigned through `this`
+    let mut stack = vec![body];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "assignment_expression" {
+            let member = node.child_by_field_name("left").filter(|left| left.kind() == "member_expression");
+            if let Some(member) = member.filter(|m| m.child_by_field_name("object").is_some_and(|o| o.kind() == "this")) {
+                if let Some(name) = member.child_by_field_name("property") {
+                    let value = node.child_by_field_name("right");
+                    push_member(name, value.and_then(|v| value_type(v, source)), CompletionItemKind::FIELD, source, members);
+                }
+            }
+        }
+        stack.extend(node.named_children(&mut node.walk()));
+    }
+}
+
+fn rust_members(body: Option<Node>, source: &str, members: &mut Vec<Member>) {
+    let Some(body) = body else {
+        return;
+    };
+    for child in body.named_children(&mut body.walk()) {
+        match child.kind() {
+            "field_declaration" => {
+                if let Some(name) = child.child_by_field_name("name") {
+                    push_field(name, child, source, members);
+                }
+            }
+            "enum_variant" => push_named(child, source, CompletionItemKind::ENUM_MEMBER, members),
+            "function_item" | "function_signature_item" => push_named(child, source, CompletionItemKind::METHOD, members),
+            "const_item" => push_named(child, source, CompletionItemKind::CONSTANT, members),
+            _ => {}
+        }
+    }
+}
+
+fn go_members(ty: Option<Node>, source: &str, members: &mut Vec<Member>) {
+    let Some(ty) = ty else {
+        return;
+    };
+    let mut stack = vec![ty];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "field_declaration" => {
+                let names: Vec<Node> = node.children_by_field_name("name", &mut node.walk()).collect();
+                for name in names {
+                    push_field(name, node, source, members);
+                }
+            }
+            "method_spec" | "method_elem" => push_named(node, source, CompletionItemKind::METHOD, members),
+            _ => stack.extend(node.named_children(&mut node.walk())),
+        }
+    }
+}
+
+fn java_members(body: Option<Node>, source: &str, members: &mut Vec<Member>) {
+    let Some(body) = body else {
+        return;
+    };
+    let mut children: Vec<Node> = body.named_children(&mut body.walk()).collect();
+    // Enum bodies nest their fields and methods after the constants
+    if let Some(declarations) = children.iter().find(|c| c.kind() == "enum_body_declarations").copied() {
+        children.extend(declarations.named_children(&mut declarations.walk()));
+    }
+    for child in children {
+        match child.kind() {
+            "method_declaration" => push_named(child, source, CompletionItemKind::METHOD, members),
+            "enum_constant" => push_named(child, source, CompletionItemKind::ENUM_MEMBER, members),
+            "field_declaration" | "constant_declaration" => {
+                let declarators: Vec<Node> = child.children_by_field_name("declarator", &mut child.walk()).collect();
+                for declarator in declarators {
+                    if let Some(name) = declarator.child_by_field_name("name") {
+                        push_field(name, child, source, members);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Push the definition `node` under its `name`, described by its signature
+fn push_named(node: Node, source: &str, kind: CompletionItemKind, members: &mut Vec<Member>) {
+    if let Some(name) = node.child_by_field_name("name") {
+        let detail = (kind == CompletionItemKind::METHOD).then(|| signature(node, source));
+        push_member(name, detail, kind, source, members);
+    }
+}
+
+/// Push the field `name`, typed by the annotation or value of `declaration`
+fn push_field(name: Node, declaration: Node, source: &str, members: &mut Vec<Member>) {
+    let ty = match declaration.child_by_field_name("type") {
+        Some(ty) => Some(source[ty.byte_range()].trim_start_matches(':').trim().to_string()),
+        None => ["value", "right"]
+            .iter()
+            .find_map(|field| declaration.child_by_field_name(field))
+            .and_then(|value| value_type(value, source)),
+    };
+    push_member(name, ty, CompletionItemKind::FIELD, source, members);
+}
+
+fn push_member(name: Node, detail: Option<String>, kind: CompletionItemKind, source: &str, members: &mut Vec<Member>) {
+    members.push(Member {
+        name: source[name.byte_range()].to_string(),
+        kind,
+        detail,
+        uri: None,
+        line: name.start_position().row as u32,
+    });
+}
+
+/// Header of a definition up to its body, on one line
+pub(super) fn signature(node: Node, source: &str) -> String {
+    let end = node.child_by_field_name("body").map_or(node.end_byte(), |body| body.start_byte());
+    let header = source[node.start_byte()..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    header.trim_end_matches([':', '{', ';', ' ']).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(source: &str, lang: &str, marker: &str, access: &str) -> Option<Vec<String>> {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        let uri = Url::parse("file:///tmp/members_test").unwrap();
+        let doc = Document { tree: &tree, source, lang, uri: &uri };
+        let end = source.find(marker).unwrap() + marker.trim_end().len() - access.len();
+        receiver_members(&doc, &WorkspaceIndex::new(), end, access)
+            .map(|members| members.into_iter().map(|m| m.name).collect())
+    }
+
+    #[test]
+    fn test_type_name() {
+        assert_eq!(type_name(": Engine").as_deref(), Some("Engine"));
+        assert_eq!(type_name("&'a mut crate::car::Engine<T>").as_deref(), Some("Engine"));
+        assert_eq!(type_name("Arc<Mutex<State>>").as_deref(), Some("Mutex"));
+        assert_eq!(type_name("*pkg.Server").as_deref(), Some("Server"));
+        assert_eq!(type_name("(i32, i32)"), None);
+    }
+
+    #[test]
+    fn test_python_members() {
+        let source = "class Engine:\n    def start(self):\n        pass\n\nclass Car(Base):\n    wheels = 4\n\n    def __init__(self):\n        self.engine = Engine()\n\n    def drive(self):\n        self.\n        self.engine.\n\nclass Base:\n    def honk(self):\n        pass\n\ncar = Car()\ncar.\nunknown.\n";
+        assert_eq!(members(source, "python", "self.\n", ".").unwrap(), vec!["wheels", "__init__", "drive", "engine", "honk"]);
+        assert_eq!(members(source, "python", "self.engine.\n", ".").unwrap(), vec!["start"]);
+        assert_eq!(members(source, "python", "car.\n", ".").unwrap()[0], "wheels");
+        assert_eq!(members(source, "python", "unknown.\n", "."), None);
+    }
+
+    #[test]
+    fn test_rust_members() {
+        let source = "struct Point { x: i32, y: i32 }\n\nenum Shape { Circle, Square }\n\nimpl Point {\n    fn new() -> Self { Point { x: 0, y: 0 } }\n    fn norm(&self) -> i32 { self.x }\n}\n\nfn main() {\n    let p = Point::new();\n    let q: &Point = &p;\n    p.\n    q.\n    Shape::\n}\n";
+        assert_eq!(members(source, "rust", "    p.", ".").unwrap(), vec!["x", "y", "new", "norm"]);
+        assert_eq!(members(source, "rust", "    q.", ".").unwrap(), vec!["x", "y", "new", "norm"]);
+        assert_eq!(members(source, "rust", "Shape::", "::").unwrap(), vec!["Circle", "Square"]);
+    }
+
+    #[test]
+    fn test_js_and_go_members() {
+        let source = "class Store {\n  constructor() {\n    this.items = [];\n  }\n  add(item) {}\n}\n\nconst store = new Store();\nstore.\n";
+        assert_eq!(members(source, "javascript", "store.\n", ".").unwrap(), vec!["add", "items"]);
+
+        let source = "package main\n\ntype Server struct {\n\tAddr string\n}\n\nfunc (s *Server) Start() {\n\ts.\n}\n\nfunc main() {\n\tsrv := &Server{}\n\tsrv.\n}\n";
+        assert_eq!(members(source, "go", "\ts.\n", ".").unwrap(), vec!["Addr", "Start"]);
+        assert_eq!(members(source, "go", "srv.\n", ".").unwrap(), vec!["Addr", "Start"]);
+    }
+}
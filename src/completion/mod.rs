@@ -0,0 +1,766 @@
+//! Completion Module
+//!
+//! Candidates depend on the cursor's context. After a member access (`.`, `::`,
+//! `->`) only the members of the receiver's type are offered, and nothing when that
+//! type cannot be resolved (see [`members`]). Elsewhere the names in scope come first,
+//! innermost scope and nearest declaration first, then the language's keywords, then
+//! matching symbols defined in other workspace files together with the import they
+//! need (see [`auto_import`]), and the language's snippets (see [`snippets`]).
+//! Typing `<` or a quote opens include and import paths or markup tags (see
+//! [`triggers`]).
+//!
+//! Items are documented lazily: they carry the location of their definition, and
+//! [`CompletionProvider::resolve`] adds its signature, doc comment and, when the
+//! workspace enables it, a one-line AI summary (see [`resolve`]).
+//!
+//! [`CompletionProvider::rank`] filters the candidates by the word under the cursor
+//! and orders them by fuzzy score, locality and how often and recently they were
+//! accepted in the workspace (see [`ranking`]).
+
+pub mod auto_import;
+pub mod members;
+pub mod ranking;
+pub mod resolve;
+pub mod snippets;
+pub mod triggers;
+
+use anyhow::Result;
+use dashmap::DashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tower_lsp::lsp_types::*;
+use tree_sitter::{Node, Tree};
+
+use crate::ai::claude::Message;
+use crate::ai::AiProvider;
+use crate::diagnostics::usage::{names_in_scope, Definition};
+use crate::language::{grammar_name, keywords};
+use crate::signature_help::SignatureHelpProvider;
+use crate::tree_sitter::{position_to_byte, TreeSitterParser};
+use crate::workspace_index::{IndexedSymbol, WorkspaceIndex};
+
+use auto_import::import_edits;
+use members::{receiver_members, Document};
+use ranking::{fuzzy_score, AcceptanceHistory, AcceptedItem, Ranking};
+use resolve::{docstring, find_definition, CompletionData};
+use snippets::variables::Variables;
+use snippets::SnippetLibrary;
+
+/// Operators after which members are completed
+const ACCESS_OPERATORS: &[&str] = &[".", "::", "->"];
+
+/// Most workspace symbols offered per request
+const MAX_WORKSPACE_ITEMS: usize = 50;
+
+/// Longest definition sent to be summarized, in bytes
+const MAX_SUMMARY_SOURCE: usize = 4000;
+
+/// How long resolving waits for a summary before documenting the item without it
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Completion provider
+#[derive(Debug)]
+pub struct CompletionProvider {
+    snippets: RwLock<SnippetLibrary>,
+    /// Whether the client expands snippets
+    snippet_support: AtomicBool,
+    signature_help: SignatureHelpProvider,
+    ai_provider: Option<Arc<dyn AiProvider>>,
+    /// AI summaries by hash of the summarized definition
+    summaries: DashMap<u64, String>,
+    /// Items accepted by workspace folder; `None` holds those accepted outside any
+    /// folder, which are not saved
+    histories: DashMap<Option<PathBuf>, Arc<WorkspaceHistory>>,
+}
+
+/// Items accepted in a workspace folder
+#[derive(Debug)]
+struct WorkspaceHistory {
+    history: RwLock<AcceptanceHistory>,
+    /// Held while saving, so that saves are written in order
+    saving: Mutex<()>,
+}
+
+impl WorkspaceHistory {
+    fn save(&self) -> Result<()> {
+        let _saving = self.saving.lock().map_err(|_| anyhow::anyhow!("Completion history poisoned"))?;
+        let to_save = self.history.read().map_err(|_| anyhow::anyhow!("Completion history poisoned"))?.to_save()?;
+        if let Some((path, contents)) = to_save {
+            ranking::save_history(&path, &contents)?;
+        }
+        Ok(())
+    }
+}
+
+impl CompletionProvider {
+    pub fn new() -> Self {
+        Self::with_ai(None)
+    }
+
+    pub fn with_ai(ai_provider: Option<Arc<dyn AiProvider>>) -> Self {
+        Self {
+            snippets: RwLock::new(SnippetLibrary::with_defaults()),
+            snippet_support: AtomicBool::new(true),
+            signature_help: SignatureHelpProvider::new(),
+            ai_provider,
+            summaries: DashMap::new(),
+            histories: DashMap::new(),
+        }
+    }
+
+    pub fn set_snippet_support(&self, enabled: bool) {
+        self.snippet_support.store(enabled, Ordering::Relaxed);
+    }
+
+    /// (Re)load the built-in, user and workspace snippets, returning warnings about
+    /// the snippets that were skipped
+    pub fn load_snippets(&self, workspace_root: Option<&Path>) -> Vec<String> {
+        let (library, warnings) = SnippetLibrary::load(workspace_root);
+        if let Ok(mut snippets) = self.snippets.write() {
+            *snippets = library;
+        }
+        warnings
+    }
+
+    /// Load the items accepted in a workspace folder ahead of its first completion
+    pub fn load_history(&self, workspace_root: &Path) {
+        self.history(Some(workspace_root));
+    }
+
+    /// History of a workspace folder, loaded on first use
+    fn history(&self, workspace_root: Option<&Path>) -> Arc<WorkspaceHistory> {
+        self.histories
+            .entry(workspace_root.map(Path::to_path_buf))
+            .or_insert_with(|| {
+                let history = workspace_root.map_or_else(AcceptanceHistory::default, AcceptanceHistory::load);
+                Arc::new(WorkspaceHistory { history: RwLock::new(history), saving: Mutex::new(()) })
+            })
+            .clone()
+    }
+
+    /// Record the acceptance reported by the arguments of a
+    /// [`ranking::ACCEPTED_COMMAND`] in the history of the folder `workspace_root`
+    /// finds for its document. The history is saved on a blocking thread.
+    pub fn record_acceptance(
+        &self,
+        arguments: &[serde_json::Value],
+        workspace_root: impl FnOnce(&Url) -> Option<PathBuf>,
+    ) -> Result<()> {
+        let argument = arguments.first().cloned().ok_or_else(|| anyhow::anyhow!("No accepted item"))?;
+        let item: AcceptedItem = serde_json::from_value(argument)?;
+        let workspace = self.history(item.uri.as_ref().and_then(workspace_root).as_deref());
+        workspace
+            .history
+            .write()
+            .map_err(|_| anyhow::anyhow!("Completion history poisoned"))?
+            .record(&item, now());
+
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = workspace.save() {
+                tracing::debug!("Failed to save completion history: {}", e);
+            }
+        });
+        Ok(())
+    }
+
+    /// Filter the items of a list by the word before `position` and order them for
+    /// the client, boosting those accepted in the document's workspace folder
+    pub fn rank(
+        &self,
+        list: CompletionList,
+        content: &str,
+        uri: &Url,
+        position: Position,
+        lang: &str,
+        workspace_root: Option<&Path>,
+    ) -> CompletionList {
+        let byte = position_to_byte(content, position);
+        let prefix = &content[word_start(content, byte)..byte];
+        let workspace = self.history(workspace_root);
+        let Ok(history) = workspace.history.read() else {
+            return list;
+        };
+        ranking::rank(list, &Ranking { prefix, uri, lang, history: &history, now: now() })
+    }
+
+    /// Completion candidates at a position, unranked; `trigger` is the character
+    /// that triggered the request, if any. The list is incomplete when typing on
+    /// may offer items it lacks: with no word typed yet, or when workspace symbols
+    /// were cut.
+    pub fn completions(
+        &self,
+        content: &str,
+        uri: &Url,
+        position: Position,
+        lang: &str,
+        trigger: Option<&str>,
+        index: &WorkspaceIndex,
+    ) -> Result<CompletionList> {
+        let byte = position_to_byte(content, position);
+        let start = word_start(content, byte);
+        let prefix = &content[start..byte];
+        let access = ACCESS_OPERATORS.iter().find(|op| content[..start].ends_with(**op)).copied();
+
+        // Other trigger characters only open paths and tags; the rest (`:` of a type
+        // annotation, `>` of a comparison) have nothing to offer
+        let opener = trigger.filter(|_| access.is_none());
+        if opener.is_some_and(|trigger| !matches!(trigger, "\"" | "'" | "<")) {
+            return Ok(CompletionList::default());
+        }
+
+        let mut parser = TreeSitterParser::new()?;
+        let tree = match parser.set_language(lang) {
+            Ok(()) => Some(parser.parse(content, uri.as_str())?),
+            Err(_) => None,
+        };
+
+        if let Some(opener) = opener {
+            let line_start = content[..byte].rfind('\n').map_or(0, |i| i + 1);
+            let line = &content[line_start..byte - opener.len()];
+            if opener == "<" && triggers::opens_element(line, lang) {
+                let mut items = triggers::tag_items(content);
+                if let Some(tree) = tree.as_ref().filter(|_| matches!(lang, "javascript" | "tsx")) {
+                    // Components are the capitalized names in scope, listed before tags
+                    let mut seen = HashSet::new();
+                    let components: Vec<CompletionItem> = self
+                        .scope_items(tree, content, uri, lang, byte, &mut seen)
+                        .into_iter()
+                        .filter(|item| item.label.starts_with(char::is_uppercase))
+                        .collect();
+                    items.retain(|tag| !components.iter().any(|component| component.label == tag.label));
+                    items.extend(components);
+                }
+                return Ok(complete(items));
+            }
+            let Ok(from) = uri.to_file_path() else {
+                return Ok(CompletionList::default());
+            };
+            let root = index.workspace_root();
+            return Ok(complete(triggers::path_items(line, opener, lang, &from, root.as_deref(), &index.indexed_files())));
+        }
+
+        if let Some(access) = access {
+            let Some(tree) = &tree else {
+                return Ok(CompletionList::default());
+            };
+            let doc = Document { tree, source: content, lang, uri };
+            let members = receiver_members(&doc, index, start - access.len(), access).unwrap_or_default();
+            return Ok(complete(
+                members
+                    .into_iter()
+                    .enumerate()
+                    .map(|(rank, member)| CompletionItem {
+                        data: member.uri.and_then(|uri| CompletionData { uri, line: member.line }.to_value()),
+                        label: member.name,
+                        kind: Some(member.kind),
+                        detail: member.detail,
+                        sort_text: Some(format!("{:04}", rank)),
+                        ..Default::default()
+                    })
+                    .collect(),
+            ));
+        }
+
+        let mut items = Vec::new();
+        let mut seen = HashSet::new();
+        if let Some(tree) = &tree {
+            items.extend(self.scope_items(tree, content, uri, lang, byte, &mut seen));
+        }
+
+        for keyword in keywords(uri.path()) {
+            if seen.insert(keyword.to_string()) {
+                items.push(CompletionItem {
+                    label: keyword.to_string(),
+                    kind: Some(CompletionItemKind::KEYWORD),
+                    sort_text: Some(format!("1_{}", keyword)),
+                    ..Default::default()
+                });
+            }
+        }
+
+        items.extend(self.snippet_items(content, uri, lang, byte, prefix, index));
+
+        // Workspace symbols wait for a word to narrow them down
+        let mut is_incomplete = prefix.is_empty();
+        if let Some(tree) = &tree {
+            if !prefix.is_empty() {
+                let workspace = self.workspace_items(tree, content, uri, lang, prefix, index, &seen);
+                is_incomplete = workspace.len() == MAX_WORKSPACE_ITEMS;
+                items.extend(workspace);
+            }
+        }
+
+        Ok(CompletionList { is_incomplete, items })
+    }
+
+    /// Names in scope at `byte`, or every symbol of the document for languages
+    /// without scope analysis
+    fn scope_items(
+        &self,
+        tree: &Tree,
+        content: &str,
+        uri: &Url,
+        lang: &str,
+        byte: usize,
+        seen: &mut HashSet<String>,
+    ) -> Vec<CompletionItem> {
+        let definitions = names_in_scope(tree, content, lang, byte);
+        if definitions.is_empty() {
+            let Ok(parser) = TreeSitterParser::new() else {
+                return Vec::new();
+            };
+            let symbols = parser.extract_symbols(tree, content, lang).unwrap_or_default();
+            return symbols
+                .into_iter()
+                .filter(|symbol| seen.insert(symbol.name.clone()))
+                .map(|symbol| CompletionItem {
+                    kind: Some(symbol_completion_kind(symbol.kind).unwrap_or(CompletionItemKind::TEXT)),
+                    detail: symbol.detail,
+                    data: CompletionData { uri: uri.clone(), line: symbol.selection_range.start.line }.to_value(),
+                    sort_text: Some(format!("0_{}", symbol.name)),
+                    label: symbol.name,
+                    ..Default::default()
+                })
+                .collect();
+        }
+
+        definitions
+            .into_iter()
+            .filter(|definition| seen.insert(definition.name.clone()))
+            .enumerate()
+            .map(|(rank, definition)| {
+                let (kind, detail) = describe(tree, content, &definition);
+                let declared = !definition.parameter && !definition.import;
+                let line = content[..definition.start].matches('\n').count() as u32;
+                CompletionItem {
+                    data: declared.then(|| CompletionData { uri: uri.clone(), line }.to_value()).flatten(),
+                    label: definition.name,
+                    kind: Some(kind),
+                    detail,
+                    sort_text: Some(format!("0_{:04}", rank)),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// Symbols of other workspace files matching `prefix`, best matches first, with
+    /// their imports
+    #[allow(clippy::too_many_arguments)]
+    fn workspace_items(
+        &self,
+        tree: &Tree,
+        content: &str,
+        uri: &Url,
+        lang: &str,
+        prefix: &str,
+        index: &WorkspaceIndex,
+        seen: &HashSet<String>,
+    ) -> Vec<CompletionItem> {
+        let Ok(from) = uri.to_file_path() else {
+            return Vec::new();
+        };
+        let root = index.workspace_root();
+        let mut symbols: Vec<(i32, IndexedSymbol)> = index
+            .symbols_where(|name| fuzzy_score(prefix, name).is_some())
+            .into_iter()
+            .filter_map(|symbol| Some((fuzzy_score(prefix, &symbol.name)?, symbol)))
+            .collect();
+        symbols.sort_by(|(a, x), (b, y)| b.cmp(a).then_with(|| x.name.cmp(&y.name)));
+
+        let mut offered = HashSet::new();
+        let mut items = Vec::new();
+        for (_, symbol) in symbols {
+            let location = &symbol.location;
+            if &location.uri == uri
+                || seen.contains(&symbol.name)
+                || grammar_name(location.uri.path()) != lang
+                || !offered.insert((symbol.name.clone(), location.uri.clone()))
+            {
+                continue;
+            }
+            let Some(kind) = symbol_completion_kind(symbol.kind).filter(|kind| *kind != CompletionItemKind::VARIABLE) else {
+                continue;
+            };
+            let Ok(target) = location.uri.to_file_path() else {
+                continue;
+            };
+            let Some(edits) = import_edits(tree, content, lang, &from, &target, &symbol.name, root.as_deref()) else {
+                continue;
+            };
+
+            let description = root
+                .as_deref()
+                .and_then(|root| target.strip_prefix(root).ok())
+                .unwrap_or(&target)
+                .display()
+                .to_string();
+            items.push(CompletionItem {
+                label: symbol.name.clone(),
+                label_details: Some(CompletionItemLabelDetails { detail: None, description: Some(description) }),
+                kind: Some(kind),
+                detail: symbol.signature.clone(),
+                sort_text: Some(format!("2_{}", symbol.name)),
+                additional_text_edits: (!edits.is_empty()).then_some(edits),
+                data: CompletionData { uri: location.uri.clone(), line: location.range.start.line }.to_value(),
+                ..Default::default()
+            });
+            if items.len() == MAX_WORKSPACE_ITEMS {
+                break;
+            }
+        }
+        items
+    }
+
+    /// Document an item with its definition in `source`, the contents of the file
+    /// its data names: the signature, a one-line AI summary when `summarize` is set
+    /// and the doc comment or docstring
+    pub async fn resolve(&self, mut item: CompletionItem, source: &str, summarize: bool) -> CompletionItem {
+        let Some(data) = CompletionData::of(&item) else {
+            return item;
+        };
+        let lang = grammar_name(data.uri.path());
+        let Some((signature, docs, definition)) = self.describe_definition(source, &lang, &item.label, data.line) else {
+            return item;
+        };
+        let summary = if summarize { self.summarize(&source[definition], &lang).await } else { None };
+
+        let mut value = format!("```{}\n{}\n```", lang, signature);
+        for paragraph in [summary.map(|summary| format!("*{}*", summary)), docs].into_iter().flatten() {
+            value.push_str("\n\n");
+            value.push_str(&paragraph);
+        }
+        item.detail.get_or_insert(signature);
+        item.documentation = Some(Documentation::MarkupContent(MarkupContent { kind: MarkupKind::Markdown, value }));
+        item
+    }
+
+    /// Signature, doc comment and byte range of the definition of `name` on `line`
+    fn describe_definition(
+        &self,
+        source: &str,
+        lang: &str,
+        name: &str,
+        line: u32,
+    ) -> Option<(String, Option<String>, std::ops::Range<usize>)> {
+        let mut parser = TreeSitterParser::new().ok()?;
+        parser.set_language(lang).ok()?;
+        let tree = parser.parse(source, "resolve").ok()?;
+        let definition = find_definition(&tree, source, name, line)?;
+
+        let signature = match self.signature_help.definition_signature(definition, source, lang) {
+            Some(signature) => signature.label,
+            None => members::signature(definition, source),
+        };
+        Some((signature, docstring(definition, source, lang), definition.byte_range()))
+    }
+
+    /// One-line AI summary of a definition, cached by its text
+    async fn summarize(&self, definition: &str, lang: &str) -> Option<String> {
+        let client = self.ai_provider.as_ref()?;
+        let mut hasher = DefaultHasher::new();
+        (lang, definition).hash(&mut hasher);
+        let key = hasher.finish();
+        if let Some(summary) = self.summaries.get(&key) {
+            return Some(summary.clone());
+        }
+
+        let mut end = definition.len().min(MAX_SUMMARY_SOURCE);
+        while !definition.is_char_boundary(end) {
+            end -= 1;
+        }
+        let prompt = format!(
+            "Summarize in one short sentence what this {} definition does. Reply with only the sentence:\n\n```{}\n{}\n```",
+            lang,
+            lang,
+            &definition[..end]
+        );
+        let message = Message { role: "user".to_string(), content: prompt };
+        let response = tokio::time::timeout(SUMMARY_TIMEOUT, client.send_message(&[message])).await.ok()?.ok()?;
+        let summary = response.lines().map(str::trim).find(|line| !line.is_empty())?.to_string();
+        self.summaries.insert(key, summary.clone());
+        Some(summary)
+    }
+
+    /// Snippets of the language, with their variables resolved for the cursor
+    fn snippet_items(
+        &self,
+        content: &str,
+        uri: &Url,
+        lang: &str,
+        byte: usize,
+        prefix: &str,
+        index: &WorkspaceIndex,
+    ) -> Vec<CompletionItem> {
+        let Ok(snippets) = self.snippets.read() else {
+            return Vec::new();
+        };
+        let path = uri.to_file_path().ok();
+        let workspace_root = index.workspace_root();
+        let line_start = content[..byte].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[byte..].find('\n').map_or(content.len(), |i| byte + i);
+        let variables = Variables {
+            path: path.as_deref(),
+            workspace_root: workspace_root.as_deref(),
+            lang,
+            line: &content[line_start..line_end],
+            line_index: content[..line_start].matches('\n').count() as u32,
+            word: prefix,
+            now: std::time::SystemTime::now(),
+        };
+        snippets.items(lang, &variables, self.snippet_support.load(Ordering::Relaxed))
+    }
+}
+
+impl Default for CompletionProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A list the client can filter as the user types
+fn complete(items: Vec<CompletionItem>) -> CompletionList {
+    CompletionList { is_incomplete: false, items }
+}
+
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Start of the identifier ending at `byte`
+fn word_start(content: &str, byte: usize) -> usize {
+    content[..byte]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map_or(byte, |(i, _)| i)
+}
+
+/// Completion kind and detail of a name in scope, from the node declaring it
+fn describe(tree: &Tree, content: &str, definition: &Definition) -> (CompletionItemKind, Option<String>) {
+    if definition.parameter {
+        return (CompletionItemKind::VARIABLE, Some("parameter".to_string()));
+    }
+    let capitalized = definition.name.starts_with(|c: char| c.is_uppercase());
+    if definition.import {
+        let kind = if capitalized { CompletionItemKind::CLASS } else { CompletionItemKind::MODULE };
+        return (kind, Some("import".to_string()));
+    }
+
+    let declaration: Option<Node> = tree
+        .root_node()
+        .named_descendant_for_byte_range(definition.start, definition.end)
+        .and_then(|ident| ident.parent());
+    let kind = match declaration.map(|node| node.kind()) {
+        Some("class_definition" | "class_declaration" | "class" | "abstract_class_declaration" | "type_spec") => {
+            CompletionItemKind::CLASS
+        }
+        Some("struct_item" | "union_item") => CompletionItemKind::STRUCT,
+        Some("enum_item" | "enum_declaration") => CompletionItemKind::ENUM,
+        Some("trait_item" | "interface_declaration") => CompletionItemKind::INTERFACE,
+        Some("const_item" | "static_item" | "const_spec") => CompletionItemKind::CONSTANT,
+        Some("mod_item") => CompletionItemKind::MODULE,
+        Some(kind) if kind.contains("function") || kind.contains("method") => CompletionItemKind::FUNCTION,
+        _ if definition.name.len() > 1 && definition.name.chars().all(|c| c.is_uppercase() || c == '_' || c.is_ascii_digit()) => {
+            CompletionItemKind::CONSTANT
+        }
+        _ => CompletionItemKind::VARIABLE,
+    };
+
+    let detail = match kind {
+        CompletionItemKind::FUNCTION => declaration.map(|node| members::signature(node, content)),
+        _ => None,
+    };
+    (kind, detail)
+}
+
+fn symbol_completion_kind(kind: SymbolKind) -> Option<CompletionItemKind> {
+    match kind {
+        SymbolKind::FUNCTION | SymbolKind::METHOD => Some(CompletionItemKind::FUNCTION),
+        SymbolKind::CLASS => Some(CompletionItemKind::CLASS),
+        SymbolKind::STRUCT => Some(CompletionItemKind::STRUCT),
+        SymbolKind::INTERFACE => Some(CompletionItemKind::INTERFACE),
+        SymbolKind::ENUM => Some(CompletionItemKind::ENUM),
+        SymbolKind::CONSTANT => Some(CompletionItemKind::CONSTANT),
+        SymbolKind::MODULE => Some(CompletionItemKind::MODULE),
+        SymbolKind::VARIABLE => Some(CompletionItemKind::VARIABLE),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(items: &[CompletionItem]) -> Vec<&str> {
+        items.iter().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_scope_then_keywords() {
+        let content = "import os\n\ndef run(limit):\n    total = 0\n    \n";
+        let uri = Url::parse("file:///tmp/app.py").unwrap();
+        let provider = CompletionProvider::new();
+        let items = provider
+            .completions(content, &uri, Position::new(4, 4), "python", None, &WorkspaceIndex::new())
+            .unwrap()
+            .items;
+
+        assert_eq!(&labels(&items)[..4], ["total", "limit", "run", "os"]);
+        assert!(items.iter().any(|item| item.label == "def" && item.kind == Some(CompletionItemKind::KEYWORD)));
+        assert!(!items.iter().any(|item| item.label == "function"));
+        assert_eq!(items[2].kind, Some(CompletionItemKind::FUNCTION));
+        assert_eq!(items[2].detail.as_deref(), Some("def run(limit)"));
+
+        // A bare `:` trigger is not an access
+        let items = provider
+            .completions(content, &uri, Position::new(4, 4), "python", Some(":"), &WorkspaceIndex::new())
+            .unwrap()
+            .items;
+        assert!(items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ranking() {
+        let content = "def to_csv(rows):\n    pass\n\ndef total_count(rows):\n    pass\n\ntc\n";
+        let uri = Url::parse("file:///tmp/report.py").unwrap();
+        let provider = CompletionProvider::new();
+        let position = Position::new(6, 2);
+        let list = provider.completions(content, &uri, position, "python", None, &WorkspaceIndex::new()).unwrap();
+        assert!(!list.is_incomplete);
+
+        let ranked = provider.rank(list.clone(), content, &uri, position, "python", None);
+        assert_eq!(&labels(&ranked.items)[..2], ["to_csv", "total_count"]);
+        assert!(!ranked.items.iter().any(|item| item.label == "def"));
+        let command = ranked.items[1].command.clone().unwrap();
+        assert_eq!(command.command, ranking::ACCEPTED_COMMAND);
+
+        // Accepted items rise
+        for _ in 0..3 {
+            provider.record_acceptance(command.arguments.as_deref().unwrap(), |_| None).unwrap();
+        }
+        let ranked = provider.rank(list.clone(), content, &uri, position, "python", None);
+        assert_eq!(&labels(&ranked.items)[..2], ["total_count", "to_csv"]);
+
+        // Histories are kept per workspace folder
+        let ranked = provider.rank(list, content, &uri, position, "python", Some(Path::new("/nonexistent/ws")));
+        assert_eq!(&labels(&ranked.items)[..2], ["to_csv", "total_count"]);
+
+        // Without a word, workspace symbols are left for the next request
+        let list = provider.completions(content, &uri, Position::new(5, 0), "python", None, &WorkspaceIndex::new()).unwrap();
+        assert!(list.is_incomplete);
+    }
+
+    #[test]
+    fn test_workspace_symbol_with_import() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("shapes")).unwrap();
+        let library = "class Circle:\n    pass\n";
+        std::fs::write(dir.path().join("shapes/round.py"), library).unwrap();
+
+        let index = WorkspaceIndex::new();
+        index.set_workspace_root(dir.path().to_path_buf());
+        let library_uri = Url::from_file_path(dir.path().join("shapes/round.py")).unwrap();
+        index.index_content(library_uri.as_str(), library).unwrap();
+
+        let content = "import math\n\nshape = Cir\n";
+        let uri = Url::from_file_path(dir.path().join("main.py")).unwrap();
+        let items = CompletionProvider::new()
+            .completions(content, &uri, Position::new(2, 11), "python", None, &index)
+            .unwrap()
+            .items;
+        let circle = items.iter().find(|item| item.label == "Circle").unwrap();
+        let edits = circle.additional_text_edits.as_ref().unwrap();
+        assert_eq!(edits[0].new_text, "\nfrom shapes.round import Circle");
+        assert_eq!(edits[0].range.start, Position::new(0, 11));
+    }
+
+    #[test]
+    fn test_member_access() {
+        let content = "struct Point { x: i32 }\n\nfn main() {\n    let p = Point { x: 1 };\n    p.\n}\n";
+        let uri = Url::parse("file:///tmp/main.rs").unwrap();
+        let items = CompletionProvider::new()
+            .completions(content, &uri, Position::new(4, 6), "rust", Some("."), &WorkspaceIndex::new())
+            .unwrap()
+            .items;
+        assert_eq!(labels(&items), ["x"]);
+        assert_eq!(items[0].detail.as_deref(), Some("i32"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_documentation() {
+        let content = "def area(r):\n    \"\"\"Area of a circle.\"\"\"\n    return 3.14 * r * r\n\nar\n";
+        let uri = Url::parse("file:///tmp/shapes.py").unwrap();
+        let provider = CompletionProvider::new();
+        let items = provider
+            .completions(content, &uri, Position::new(4, 2), "python", None, &WorkspaceIndex::new())
+            .unwrap()
+            .items;
+        let area = items.iter().find(|item| item.label == "area").unwrap().clone();
+        assert!(area.documentation.is_none());
+        assert_eq!(CompletionData::of(&area), Some(CompletionData { uri, line: 0 }));
+
+        let resolved = provider.resolve(area, content, false).await;
+        let Some(Documentation::MarkupContent(docs)) = resolved.documentation else {
+            panic!("no documentation");
+        };
+        assert_eq!(docs.value, "```python\narea(r)\n```\n\nArea of a circle.");
+
+        // Keywords and snippets carry no definition
+        let keyword = items.iter().find(|item| item.kind == Some(CompletionItemKind::KEYWORD)).unwrap().clone();
+        assert_eq!(provider.resolve(keyword.clone(), content, false).await, keyword);
+    }
+
+    #[test]
+    fn test_opening_triggers() {
+        let content = "function Card() {}\n\nconst App = () => (\n  <\n);\n";
+        let uri = Url::parse("file:///tmp/app.jsx").unwrap();
+        let provider = CompletionProvider::new();
+        let items = provider
+            .completions(content, &uri, Position::new(3, 3), "javascript", Some("<"), &WorkspaceIndex::new())
+            .unwrap()
+            .items;
+        assert!(items.iter().any(|item| item.label == "div" && item.kind == Some(CompletionItemKind::PROPERTY)));
+        assert!(items.iter().any(|item| item.label == "Card" && item.kind == Some(CompletionItemKind::FUNCTION)));
+
+        // `<` of a comparison and quotes of plain strings open nothing
+        let content = "if (a <\nconst s = '";
+        for (position, trigger) in [(Position::new(0, 7), "<"), (Position::new(1, 11), "'")] {
+            let items = provider
+                .completions(content, &uri, position, "javascript", Some(trigger), &WorkspaceIndex::new())
+                .unwrap()
+                .items;
+            assert!(items.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_snippets() {
+        let content = "fn main() {\n    ma\n}\n";
+        let uri = Url::parse("file:///tmp/main.rs").unwrap();
+        let provider = CompletionProvider::new();
+        let items = provider
+            .completions(content, &uri, Position::new(1, 6), "rust", None, &WorkspaceIndex::new())
+            .unwrap()
+            .items;
+        // The `match` snippet is offered next to the keyword
+        let snippet = items.iter().find(|item| item.label == "match" && item.kind == Some(CompletionItemKind::SNIPPET)).unwrap();
+        assert_eq!(snippet.insert_text_format, Some(InsertTextFormat::SNIPPET));
+        assert_eq!(snippet.insert_text.as_deref(), Some("match ${1:value} {\n\t${2:_} => ${0:todo!()},\n}"));
+
+        provider.set_snippet_support(false);
+        let items = provider
+            .completions(content, &uri, Position::new(1, 6), "rust", None, &WorkspaceIndex::new())
+            .unwrap()
+            .items;
+        let snippet = items.iter().find(|item| item.label == "match" && item.kind == Some(CompletionItemKind::SNIPPET)).unwrap();
+        assert_eq!(snippet.insert_text.as_deref(), Some("match value {\n\t_ => todo!(),\n}"));
+    }
+}
@@ -0,0 +1,378 @@
+//! Completion ranking
+//!
+//! Items are filtered by the word under the cursor with a fuzzy matcher: the query's
+//! characters must appear in order, the first at the start of a word of the
+//! candidate, and matches at camelCase or snake_case word starts and runs of
+//! consecutive characters score higher. Names defined in the document and items
+//! accepted recently and often are boosted; ties keep the provider's order.
+//!
+//! Every item runs [`ACCEPTED_COMMAND`] when accepted. Acceptances are kept per
+//! workspace folder in `~/.universal-lsp/completion-history/`.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::*;
+
+use super::resolve::CompletionData;
+
+/// Command the client runs when an item is accepted, with an [`AcceptedItem`]
+pub const ACCEPTED_COMMAND: &str = "universal-lsp.completionAccepted";
+
+/// Most items returned; longer lists are marked incomplete
+pub const MAX_ITEMS: usize = 200;
+
+const MATCH: i32 = 1;
+const WORD_START: i32 = 8;
+const CANDIDATE_START: i32 = 4;
+const CONSECUTIVE: i32 = 5;
+const EXACT_CASE: i32 = 1;
+/// Penalty per candidate character skipped between two matches
+const GAP: i32 = 1;
+
+/// Boost of names defined in the document being completed
+const LOCAL_BOOST: i32 = 20;
+
+/// Largest boost of an often accepted item, before decay
+const MAX_FRECENCY_BOOST: f64 = 30.0;
+
+/// Days after which the boost of an accepted item halves
+const HALF_LIFE_DAYS: f64 = 14.0;
+
+/// Most acceptances remembered per workspace
+const MAX_HISTORY: usize = 1000;
+
+/// Argument of [`ACCEPTED_COMMAND`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcceptedItem {
+    pub language: String,
+    pub label: String,
+    /// Document the item was accepted in, which picks the workspace history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<Url>,
+}
+
+/// How often and when an item was last accepted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+struct Usage {
+    count: u32,
+    /// Seconds since the Unix epoch
+    last: u64,
+}
+
+/// Items accepted in a workspace, by language and label
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AcceptanceHistory {
+    #[serde(default)]
+    workspace: Option<PathBuf>,
+    #[serde(default)]
+    accepted: HashMap<String, Usage>,
+    /// File the history is saved to; in memory only when unset
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl AcceptanceHistory {
+    /// The saved history of a workspace, empty when there is none
+    pub fn load(workspace_root: &Path) -> Self {
+        match history_path(workspace_root) {
+            Some(path) => Self::load_from(path, workspace_root),
+            None => Self::default(),
+        }
+    }
+
+    /// The history saved at `path`, which acceptances are saved back to
+    pub fn load_from(path: PathBuf, workspace_root: &Path) -> Self {
+        let mut history: Self = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        history.workspace = Some(workspace_root.to_path_buf());
+        history.path = Some(path);
+        history
+    }
+
+    /// Record an accepted item at `now` (seconds since the Unix epoch); the history
+    /// is saved with [`save_history`]
+    pub fn record(&mut self, item: &AcceptedItem, now: u64) {
+        let usage = self.accepted.entry(key(&item.language, &item.label)).or_default();
+        usage.count = usage.count.saturating_add(1);
+        usage.last = now;
+
+        if self.accepted.len() > MAX_HISTORY {
+            let mut entries: Vec<(String, Usage)> = self.accepted.drain().collect();
+            entries.sort_by(|(_, a), (_, b)| frecency(b, now).total_cmp(&frecency(a, now)));
+            entries.truncate(MAX_HISTORY);
+            self.accepted = entries.into_iter().collect();
+        }
+    }
+
+    /// Boost of an item for how often and how recently it was accepted
+    pub fn boost(&self, lang: &str, label: &str, now: u64) -> i32 {
+        self.accepted.get(&key(lang, label)).map_or(0, |usage| frecency(usage, now).round() as i32)
+    }
+
+    /// The file the history is saved to and what to write there, `None` when it is
+    /// kept in memory only
+    pub fn to_save(&self) -> Result<Option<(PathBuf, String)>> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        Ok(Some((path.clone(), serde_json::to_string_pretty(self)?)))
+    }
+}
+
+/// Write a history returned by [`AcceptanceHistory::to_save`]
+///
+/// The history goes to a temporary file next to `path` that is then renamed over
+/// it, so a crash or a concurrent load never sees a partly written history.
+pub fn save_history(path: &Path, contents: &str) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(contents.as_bytes())?;
+    file.persist(path)?;
+    Ok(())
+}
+
+/// What an item is ranked against
+pub struct Ranking<'a> {
+    /// Word before the cursor
+    pub prefix: &'a str,
+    /// Document being completed
+    pub uri: &'a Url,
+    pub lang: &'a str,
+    pub history: &'a AcceptanceHistory,
+    /// Seconds since the Unix epoch
+    pub now: u64,
+}
+
+/// Drop the items not matching the prefix and order the rest by score, keeping at
+/// most [`MAX_ITEMS`]
+pub fn rank(list: CompletionList, ranking: &Ranking) -> CompletionList {
+    let mut scored: Vec<(i32, CompletionItem)> = list
+        .items
+        .into_iter()
+        .filter_map(|item| {
+            let text = item.filter_text.as_deref().unwrap_or(&item.label);
+            let mut score = fuzzy_score(ranking.prefix, text)?;
+            if CompletionData::of(&item).is_some_and(|data| &data.uri == ranking.uri) {
+                score += LOCAL_BOOST;
+            }
+            score += ranking.history.boost(ranking.lang, &item.label, ranking.now);
+            Some((score, item))
+        })
+        .collect();
+    // Stable, so equal scores keep the order the items were offered in
+    scored.sort_by_key(|(score, _)| -score);
+
+    let is_incomplete = list.is_incomplete || scored.len() > MAX_ITEMS;
+    let items = scored
+        .into_iter()
+        .take(MAX_ITEMS)
+        .enumerate()
+        .map(|(rank, (_, mut item))| {
+            item.sort_text = Some(format!("{:04}", rank));
+            if item.command.is_none() {
+                let accepted = AcceptedItem {
+                    language: ranking.lang.to_string(),
+                    label: item.label.clone(),
+                    uri: Some(ranking.uri.clone()),
+                };
+                item.command = Some(Command {
+                    title: String::new(),
+                    command: ACCEPTED_COMMAND.to_string(),
+                    arguments: serde_json::to_value(accepted).ok().map(|argument| vec![argument]),
+                });
+            }
+            item
+        })
+        .collect();
+    CompletionList { is_incomplete, items }
+}
+
+/// Score of `candidate` for the typed `query`, or `None` when it does not match.
+/// Case is ignored, except for a small bonus.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let query: Vec<char> = query.chars().collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    if query.len() > chars.len() {
+        return None;
+    }
+
+    // best[i]: best score of the query so far with its last character matched at i
+    let mut best: Vec<Option<i32>> = vec![None; chars.len()];
+    for (j, &q) in query.iter().enumerate() {
+        let mut next = vec![None; chars.len()];
+        // Best score of an earlier match, less the gap up to i
+        let mut carried: Option<i32> = None;
+        for i in 0..chars.len() {
+            let adjacent = i.checked_sub(1).and_then(|previous| best[previous]);
+            carried = carried.map(|score| score - GAP).max(adjacent);
+            let reach = carried.max(adjacent.map(|score| score + CONSECUTIVE));
+            if !q.to_lowercase().eq(chars[i].to_lowercase()) {
+                continue;
+            }
+            let before = if j == 0 { word_start(&chars, i).then_some(0) } else { reach };
+            let Some(before) = before else {
+                continue;
+            };
+            let mut score = before + MATCH;
+            if word_start(&chars, i) {
+                score += WORD_START;
+            }
+            if i == 0 {
+                score += CANDIDATE_START;
+            }
+            if q == chars[i] {
+                score += EXACT_CASE;
+            }
+            next[i] = Some(score);
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Whether a word starts at `i`: after a separator, at a camelCase hump, at the
+/// last capital of an acronym followed by lowercase (`HTTPServer`) or at a digit
+fn word_start(chars: &[char], i: usize) -> bool {
+    let Some(&previous) = i.checked_sub(1).and_then(|p| chars.get(p)) else {
+        return true;
+    };
+    let c = chars[i];
+    (!previous.is_alphanumeric() && c.is_alphanumeric())
+        || (previous.is_lowercase() && c.is_uppercase())
+        || (!previous.is_ascii_digit() && c.is_ascii_digit())
+        || (previous.is_uppercase() && c.is_uppercase() && chars.get(i + 1).is_some_and(|n| n.is_lowercase()))
+}
+
+/// Boost for `count` acceptances, halving every [`HALF_LIFE_DAYS`] since the last
+fn frecency(usage: &Usage, now: u64) -> f64 {
+    let days = now.saturating_sub(usage.last) as f64 / 86_400.0;
+    let boost = (10.0 * (1.0 + usage.count as f64).ln()).min(MAX_FRECENCY_BOOST);
+    boost * 0.5f64.powf(days / HALF_LIFE_DAYS)
+}
+
+fn key(lang: &str, label: &str) -> String {
+    format!("{}:{}", lang, label)
+}
+
+/// File of a workspace's history, named by a hash of its root that stays the same
+/// across builds
+fn history_path(workspace_root: &Path) -> Option<PathBuf> {
+    let hash = workspace_root
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+    let dir = dirs::home_dir()?.join(".universal-lsp").join("completion-history");
+    Some(dir.join(format!("{:016x}.json", hash)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+
+    fn item(label: &str, data: Option<CompletionData>) -> CompletionItem {
+        CompletionItem { label: label.to_string(), data: data.and_then(|data| data.to_value()), ..Default::default() }
+    }
+
+    fn labels(list: &CompletionList) -> Vec<&str> {
+        list.items.iter().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("names", "name"), None);
+
+        // Word starts of camelCase and snake_case names
+        assert!(fuzzy_score("gsn", "getServerName").is_some());
+        assert!(fuzzy_score("ufn", "user_first_name").is_some());
+        assert!(fuzzy_score("serv", "HTTPServer").is_some());
+        assert!(fuzzy_score("gsn", "getServerName") > fuzzy_score("gsn", "gasiness"));
+
+        // The first character must start a word
+        assert_eq!(fuzzy_score("ser", "user"), None);
+        assert!(fuzzy_score("name", "getServerName").is_some());
+
+        // Prefixes beat later words, consecutive beats scattered
+        assert!(fuzzy_score("na", "name") > fuzzy_score("na", "fullName"));
+        assert!(fuzzy_score("get", "getter") > fuzzy_score("get", "gadget"));
+        assert!(fuzzy_score("Na", "Name") > fuzzy_score("Na", "name"));
+    }
+
+    #[test]
+    fn test_rank() {
+        let uri = Url::parse("file:///ws/app.ts").unwrap();
+        let other = Url::parse("file:///ws/lib.ts").unwrap();
+        let mut history = AcceptanceHistory::default();
+        let now = 100 * DAY;
+        let list = CompletionList {
+            is_incomplete: false,
+            items: vec![
+                item("setValue", Some(CompletionData { uri: other.clone(), line: 0 })),
+                item("setVisible", Some(CompletionData { uri: uri.clone(), line: 3 })),
+                item("settings", None),
+                item("reset", None),
+            ],
+        };
+
+        // Non-matching items are dropped and local definitions come first
+        let ranked = rank(list.clone(), &Ranking { prefix: "sv", uri: &uri, lang: "typescript", history: &history, now });
+        assert_eq!(labels(&ranked), ["setVisible", "setValue"]);
+        assert_eq!(ranked.items[0].sort_text.as_deref(), Some("0000"));
+        let command = ranked.items[1].command.as_ref().unwrap();
+        assert_eq!(command.command, ACCEPTED_COMMAND);
+        let accepted: AcceptedItem = serde_json::from_value(command.arguments.as_ref().unwrap()[0].clone()).unwrap();
+        let expected =
+            AcceptedItem { language: "typescript".to_string(), label: "setValue".to_string(), uri: Some(uri.clone()) };
+        assert_eq!(accepted, expected);
+
+        // Accepting an item often enough outranks the local boost
+        for _ in 0..10 {
+            history.record(&accepted, now);
+        }
+        let ranked = rank(list, &Ranking { prefix: "sv", uri: &uri, lang: "typescript", history: &history, now });
+        assert_eq!(labels(&ranked), ["setValue", "setVisible"]);
+
+        // Long lists are cut and marked incomplete
+        let items = (0..MAX_ITEMS + 1).map(|i| item(&format!("item{}", i), None)).collect();
+        let ranking = Ranking { prefix: "", uri: &uri, lang: "typescript", history: &history, now };
+        let ranked = rank(CompletionList { is_incomplete: false, items }, &ranking);
+        assert_eq!(ranked.items.len(), MAX_ITEMS);
+        assert!(ranked.is_incomplete);
+    }
+
+    #[test]
+    fn test_history_persists_and_decays() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let root = Path::new("/ws");
+        let accepted = AcceptedItem { language: "rust".to_string(), label: "unwrap_or_default".to_string(), uri: None };
+
+        let mut history = AcceptanceHistory::load_from(path.clone(), root);
+        history.record(&accepted, 10 * DAY);
+        history.record(&accepted, 10 * DAY);
+        let (saved_to, contents) = history.to_save().unwrap().unwrap();
+        assert_eq!(saved_to, path);
+        save_history(&saved_to, &contents).unwrap();
+        // Saving again replaces the file, leaving no temporary files behind
+        save_history(&saved_to, &contents).unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(AcceptanceHistory::default().to_save().unwrap().is_none());
+
+        let history = AcceptanceHistory::load_from(path, root);
+        let fresh = history.boost("rust", "unwrap_or_default", 10 * DAY);
+        assert!(fresh > 0);
+        assert!(history.boost("rust", "unwrap_or_default", 40 * DAY) < fresh);
+        assert_eq!(history.boost("python", "unwrap_or_default", 10 * DAY), 0);
+    }
+}
@@ -0,0 +1,211 @@
+//! Lazy completion documentation
+//!
+//! Items naming a definition carry the file and line declaring it in their `data`.
+//! `completionItem/resolve` parses that file again, finds the definition and
+//! documents the item with its signature and its doc comment or docstring.
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::*;
+use tree_sitter::{Node, Tree};
+
+/// Lines below the recorded line searched for the name, for definitions whose
+/// reported range starts at a decorator or attribute
+const SEARCH_LINES: u32 = 3;
+
+/// Nodes wrapping a definition; its doc comment precedes the wrapper
+const WRAPPERS: &[&str] = &[
+    "decorated_definition",
+    "export_statement",
+    "expression_statement",
+    "lexical_declaration",
+    "variable_declaration",
+    "type_declaration",
+    "const_declaration",
+    "var_declaration",
+    "template_declaration",
+];
+
+/// `data` of a completion item naming a definition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletionData {
+    pub uri: Url,
+    pub line: u32,
+}
+
+impl CompletionData {
+    pub fn of(item: &CompletionItem) -> Option<Self> {
+        serde_json::from_value(item.data.clone()?).ok()
+    }
+
+    pub fn to_value(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self).ok()
+    }
+}
+
+/// The definition of `name` declared on `line`, or on one of the lines just below
+pub fn find_definition<'t>(tree: &'t Tree, source: &str, name: &str, line: u32) -> Option<Node<'t>> {
+    let mut line_start = source.split_inclusive('\n').take(line as usize).map(str::len).sum::<usize>();
+    for text in source[line_start..].split_inclusive('\n').take(SEARCH_LINES as usize + 1) {
+        for (offset, _) in text.match_indices(name) {
+            let start = line_start + offset;
+            let Some(node) = tree.root_node().descendant_for_byte_range(start, start + name.len()) else {
+                continue;
+            };
+            if node.byte_range() == (start..start + name.len()) && node.child_count() == 0 {
+                let definition = declaration(node);
+                if definition.id() != node.id() {
+                    return Some(definition);
+                }
+            }
+        }
+        line_start += text.len();
+    }
+    None
+}
+
+/// The node a name node declares: the chain of parents holding it as their name,
+/// declarator or assignment target
+fn declaration(name: Node) -> Node {
+    let mut node = name;
+    while let Some(parent) = node.parent() {
+        let declares = ["name", "declarator", "left", "pattern"]
+            .iter()
+            .any(|field| parent.child_by_field_name(field).is_some_and(|child| child.id() == node.id()));
+        if !declares {
+            break;
+        }
+        node = parent;
+    }
+    node
+}
+
+/// Doc comment or docstring of a definition, without its comment markers
+pub fn docstring(definition: Node, source: &str, lang: &str) -> Option<String> {
+    if lang == "python" {
+        if let Some(docstring) = python_docstring(definition, source) {
+            return Some(docstring);
+        }
+    }
+
+    let mut node = definition;
+    while let Some(parent) = node.parent().filter(|parent| WRAPPERS.contains(&parent.kind())) {
+        node = parent;
+    }
+
+    // Comments directly above, skipping attributes and annotations in between
+    let mut comments = Vec::new();
+    let mut top = node.start_position().row;
+    let mut sibling = node.prev_named_sibling();
+    while let Some(previous) = sibling {
+        if previous.end_position().row + 1 < top {
+            break;
+        }
+        if previous.kind().contains("comment") {
+            comments.push(clean_comment(&source[previous.byte_range()]));
+        } else if !matches!(previous.kind(), "attribute_item" | "decorator" | "annotation" | "marker_annotation") {
+            break;
+        }
+        top = previous.start_position().row;
+        sibling = previous.prev_named_sibling();
+    }
+    comments.reverse();
+    let text = comments.join("\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// The string opening the body of a Python function or class
+fn python_docstring(definition: Node, source: &str) -> Option<String> {
+    let first = definition.child_by_field_name("body")?.named_child(0)?;
+    let string = first.named_child(0).filter(|node| first.kind() == "expression_statement" && node.kind() == "string")?;
+    let text = source[string.byte_range()].trim_start_matches(|c: char| "rRbBuUfF".contains(c));
+    let quote = ["\"\"\"", "'''", "\"", "'"].into_iter().find(|quote| text.starts_with(quote))?;
+    let text = text.strip_prefix(quote)?.strip_suffix(quote)?;
+    let text = dedent(text);
+    (!text.is_empty()).then_some(text)
+}
+
+/// Text of a comment without its delimiters and leading `*`s
+fn clean_comment(comment: &str) -> String {
+    if let Some(block) = comment.strip_prefix("/*") {
+        let block = block.trim_start_matches(['*', '!']).trim_end_matches("*/");
+        let lines: Vec<&str> = block
+            .lines()
+            .map(|line| {
+                let line = line.trim_start();
+                line.strip_prefix('*').map_or(line, |rest| rest.strip_prefix(' ').unwrap_or(rest))
+            })
+            .collect();
+        return lines.join("\n").trim().to_string();
+    }
+    comment
+        .lines()
+        .map(|line| {
+            let line = line.trim_start();
+            let rest = ["///", "//!", "//", "#", "--", ";"]
+                .iter()
+                .find_map(|marker| line.strip_prefix(marker))
+                .unwrap_or(line);
+            rest.strip_prefix(' ').unwrap_or(rest).trim_end()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Docstring lines without the indentation they share after the first
+fn dedent(text: &str) -> String {
+    let mut lines = text.lines();
+    let first = lines.next().unwrap_or_default().trim();
+    let rest: Vec<&str> = lines.collect();
+    let indent = rest
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    std::iter::once(first)
+        .chain(rest.iter().map(|line| line.get(indent..).unwrap_or("").trim_end()))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_sitter::TreeSitterParser;
+
+    fn docs(source: &str, lang: &str, name: &str) -> Option<String> {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        let line = source.lines().position(|line| line.contains(name)).unwrap() as u32;
+        let definition = find_definition(&tree, source, name, line)?;
+        docstring(definition, source, lang)
+    }
+
+    #[test]
+    fn test_docstrings() {
+        let python = "def area(r):\n    \"\"\"Area of a circle.\n\n    Uses pi.\n    \"\"\"\n    return 3.14 * r * r\n";
+        assert_eq!(docs(python, "python", "area").as_deref(), Some("Area of a circle.\n\nUses pi."));
+
+        let rust = "/// Adds two numbers.\n/// Saturates.\n#[inline]\npub fn add(a: u8, b: u8) -> u8 {\n    a.saturating_add(b)\n}\n";
+        assert_eq!(docs(rust, "rust", "add").as_deref(), Some("Adds two numbers.\nSaturates."));
+
+        let js = "// unrelated\n\n/**\n * Greets someone.\n * @param name who\n */\nexport const greet = (name) => name;\n";
+        assert_eq!(docs(js, "javascript", "greet").as_deref(), Some("Greets someone.\n@param name who"));
+
+        let go = "package main\n\n// Server serves.\ntype Server struct{}\n";
+        assert_eq!(docs(go, "go", "Server").as_deref(), Some("Server serves."));
+
+        assert_eq!(docs("fn bare() {}\n", "rust", "bare"), None);
+    }
+
+    #[test]
+    fn test_completion_data() {
+        let data = CompletionData { uri: Url::parse("file:///ws/a.py").unwrap(), line: 3 };
+        let item = CompletionItem { data: data.to_value(), ..Default::default() };
+        assert_eq!(CompletionData::of(&item), Some(data));
+        assert_eq!(CompletionData::of(&CompletionItem::default()), None);
+    }
+}
@@ -0,0 +1,467 @@
+//! Snippet library
+//!
+//! Snippets are VS Code snippet files: `<language>.json` files with snippets for one
+//! language, and `.code-snippets` files whose entries name their languages in a
+//! `scope` field (every language when absent). Comments and trailing commas are
+//! allowed, as VS Code allows them.
+//!
+//! Built-in snippets for the tree-sitter languages are loaded first, then user
+//! snippets from `~/.universal-lsp/snippets` and `<config dir>/universal-lsp/snippets`,
+//! then workspace snippets from `.universal-lsp/snippets` and `.vscode`. A snippet
+//! replaces an earlier one of the same name and language.
+//!
+//! Bodies are checked when loaded: they must follow the snippet syntax and, with
+//! every placeholder filled in, parse with the language's grammar, either on their
+//! own or inside one of the constructs they are written for (a function body, a
+//! class body, after an `if`). Snippets failing either check are skipped with a
+//! warning.
+
+pub mod syntax;
+pub mod variables;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::*;
+
+use crate::tree_sitter::TreeSitterParser;
+use syntax::{Fill, Marker};
+use variables::Variables;
+
+/// Key of the snippets offered for every language
+const ANY_LANGUAGE: &str = "*";
+
+/// Built-in snippet files and the languages they serve
+const DEFAULTS: &[(&[&str], &str)] = &[
+    (&["python"], include_str!("defaults/python.json")),
+    (&["javascript", "typescript", "tsx"], include_str!("defaults/javascript.json")),
+    (&["typescript", "tsx"], include_str!("defaults/typescript.json")),
+    (&["rust"], include_str!("defaults/rust.json")),
+    (&["go"], include_str!("defaults/go.json")),
+    (&["java"], include_str!("defaults/java.json")),
+    (&["c", "cpp"], include_str!("defaults/c.json")),
+    (&["cpp"], include_str!("defaults/cpp.json")),
+    (&["ruby"], include_str!("defaults/ruby.json")),
+    (&["php"], include_str!("defaults/php.json")),
+];
+
+/// A snippet of the library
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    pub name: String,
+    pub prefixes: Vec<String>,
+    pub description: Option<String>,
+    pub body: Vec<Marker>,
+}
+
+/// Snippets by tree-sitter language name
+#[derive(Debug, Default)]
+pub struct SnippetLibrary {
+    snippets: HashMap<String, Vec<Snippet>>,
+}
+
+impl SnippetLibrary {
+    /// The built-in snippets (all valid, see the tests)
+    pub fn with_defaults() -> Self {
+        let mut library = Self::default();
+        for (languages, json) in DEFAULTS {
+            for lang in *languages {
+                library.add_json(json, &[lang], "built-in snippets");
+            }
+        }
+        library
+    }
+
+    /// Built-in, user and workspace snippets, with warnings about the files and
+    /// snippets that could not be loaded
+    pub fn load(workspace_root: Option<&Path>) -> (Self, Vec<String>) {
+        let mut library = Self::with_defaults();
+        let mut warnings = Vec::new();
+        for dir in snippet_dirs(workspace_root) {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            let mut files: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).filter(|p| is_snippet_file(p)).collect();
+            files.sort();
+            for file in files {
+                warnings.extend(library.load_file(&file));
+            }
+        }
+        (library, warnings)
+    }
+
+    /// Load a snippet file, returning warnings
+    pub fn load_file(&mut self, path: &Path) -> Vec<String> {
+        let origin = path.display().to_string();
+        let languages: Vec<&str> = match path.extension().and_then(|e| e.to_str()) {
+            Some("code-snippets") => Vec::new(),
+            _ => match path.file_stem().and_then(|s| s.to_str()).and_then(grammar_for_language_id) {
+                Some(lang) => vec![lang],
+                None => return vec![format!("{}: unknown language", origin)],
+            },
+        };
+        match std::fs::read_to_string(path) {
+            Ok(json) => self.add_json(&json, &languages, &origin),
+            Err(e) => vec![format!("{}: {}", origin, e)],
+        }
+    }
+
+    /// Add the snippets of a snippet file's contents for `languages`, or for the
+    /// languages each entry's `scope` names when empty
+    pub fn add_json(&mut self, json: &str, languages: &[&str], origin: &str) -> Vec<String> {
+        let entries = match serde_json::from_str::<Value>(&strip_jsonc(json)) {
+            Ok(Value::Object(entries)) => entries,
+            Ok(_) => return vec![format!("{}: expected an object of snippets", origin)],
+            Err(e) => return vec![format!("{}: {}", origin, e)],
+        };
+
+        let mut warnings = Vec::new();
+        for (name, entry) in entries {
+            let scoped: Vec<&str>;
+            let targets = if languages.is_empty() {
+                scoped = match entry.get("scope").and_then(Value::as_str) {
+                    Some(scope) => scope.split(',').filter_map(|id| grammar_for_language_id(id.trim())).collect(),
+                    None => vec![ANY_LANGUAGE],
+                };
+                &scoped[..]
+            } else {
+                languages
+            };
+
+            for lang in targets {
+                match parse_entry(&name, &entry).and_then(|snippet| validate(&snippet.body, lang).map(|_| snippet)) {
+                    Ok(snippet) => self.insert(lang, snippet),
+                    Err(e) => warnings.push(format!("{}: snippet '{}' ({}): {}", origin, name, lang, e)),
+                }
+            }
+        }
+        warnings
+    }
+
+    fn insert(&mut self, lang: &str, snippet: Snippet) {
+        let snippets = self.snippets.entry(lang.to_string()).or_default();
+        match snippets.iter_mut().find(|existing| existing.name == snippet.name) {
+            Some(existing) => *existing = snippet,
+            None => snippets.push(snippet),
+        }
+    }
+
+    /// Snippets offered in documents of `lang`
+    pub fn snippets<'a>(&'a self, lang: &str) -> impl Iterator<Item = &'a Snippet> {
+        let own = self.snippets.get(lang).into_iter().flatten();
+        let any = self.snippets.get(ANY_LANGUAGE).into_iter().flatten();
+        own.chain(any)
+    }
+
+    /// Completion items for the snippets of `lang`, as snippets when the client
+    /// supports them and as their plain text otherwise
+    pub fn items(&self, lang: &str, variables: &Variables, snippet_support: bool) -> Vec<CompletionItem> {
+        let resolve = |name: &str| variables.resolve(name);
+        let mut items = Vec::new();
+        for snippet in self.snippets(lang) {
+            let plain = syntax::to_plain(&snippet.body, &resolve, Fill::Empty);
+            let (insert_text, format) = if snippet_support {
+                (syntax::to_snippet(&snippet.body, &resolve), InsertTextFormat::SNIPPET)
+            } else {
+                (plain.clone(), InsertTextFormat::PLAIN_TEXT)
+            };
+            for prefix in &snippet.prefixes {
+                items.push(CompletionItem {
+                    label: prefix.clone(),
+                    label_details: Some(CompletionItemLabelDetails { detail: None, description: Some(snippet.name.clone()) }),
+                    kind: Some(CompletionItemKind::SNIPPET),
+                    detail: snippet.description.clone().or_else(|| Some(snippet.name.clone())),
+                    documentation: Some(Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: format!("```{}\n{}\n```", lang, plain),
+                    })),
+                    insert_text: Some(insert_text.clone()),
+                    insert_text_format: Some(format),
+                    insert_text_mode: Some(InsertTextMode::ADJUST_INDENTATION),
+                    sort_text: Some(format!("1_{}", prefix)),
+                    ..Default::default()
+                });
+            }
+        }
+        items
+    }
+}
+
+/// Directories snippet files are loaded from, in loading order
+pub fn snippet_dirs(workspace_root: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".universal-lsp").join("snippets"));
+    }
+    if let Some(config) = dirs::config_dir() {
+        dirs.push(config.join("universal-lsp").join("snippets"));
+    }
+    if let Some(root) = workspace_root {
+        dirs.push(root.join(".universal-lsp").join("snippets"));
+        dirs.push(root.join(".vscode"));
+    }
+    dirs.dedup();
+    dirs
+}
+
+/// Whether `path` is a snippet file by name (`.vscode` also holds other JSON files)
+pub fn is_snippet_file(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("code-snippets") => true,
+        Some("json") => path.parent().is_some_and(|dir| dir.ends_with("snippets")),
+        _ => false,
+    }
+}
+
+/// Tree-sitter grammar of a VS Code language identifier
+fn grammar_for_language_id(id: &str) -> Option<&'static str> {
+    Some(match id {
+        "python" => "python",
+        "javascript" | "javascriptreact" => "javascript",
+        "typescript" => "typescript",
+        "typescriptreact" => "tsx",
+        "rust" => "rust",
+        "go" => "go",
+        "java" => "java",
+        "c" => "c",
+        "cpp" => "cpp",
+        "csharp" => "csharp",
+        "ruby" => "ruby",
+        "php" => "php",
+        "shellscript" | "bash" => "bash",
+        "scala" => "scala",
+        "kotlin" => "kotlin",
+        "html" => "html",
+        "css" => "css",
+        "json" => "json",
+        "svelte" => "svelte",
+        _ => return None,
+    })
+}
+
+fn parse_entry(name: &str, entry: &Value) -> Result<Snippet> {
+    let strings = |value: Option<&Value>| -> Option<Vec<String>> {
+        match value? {
+            Value::String(s) => Some(vec![s.clone()]),
+            Value::Array(items) => items.iter().map(|item| item.as_str().map(str::to_string)).collect(),
+            _ => None,
+        }
+    };
+
+    let prefixes = strings(entry.get("prefix")).filter(|p| !p.is_empty()).ok_or_else(|| anyhow!("missing prefix"))?;
+    let body = strings(entry.get("body")).ok_or_else(|| anyhow!("missing body"))?.join("\n");
+    Ok(Snippet {
+        name: name.to_string(),
+        prefixes,
+        description: entry.get("description").and_then(Value::as_str).map(str::to_string),
+        body: syntax::parse(&body)?,
+    })
+}
+
+/// Constructs a snippet may be written for, as text before and after its body.
+/// Bodies following a line ending in `:` are indented one level.
+fn contexts(lang: &str) -> &'static [(&'static str, &'static str)] {
+    match lang {
+        "python" => &[("", ""), ("def _():\n", ""), ("class _:\n", ""), ("if _:\n\tpass\n", ""), ("try:\n\tpass\n", "")],
+        "javascript" | "typescript" | "tsx" => &[
+            ("", ""),
+            ("function _() {\n", "\n}"),
+            ("class _ {\n", "\n}"),
+            ("if (_) {}\n", ""),
+            ("switch (_) {\n", "\n}"),
+        ],
+        "rust" => &[("", ""), ("fn _() {\n", "\n}"), ("impl _ {\n", "\n}"), ("match _ {\n", "\n}")],
+        "go" => &[
+            ("package _\n", ""),
+            ("package _\nfunc _() {\n", "\n}"),
+            ("package _\nfunc _() {\nswitch {\n", "\n}\n}"),
+        ],
+        "java" => &[
+            ("", ""),
+            ("class _ {\n", "\n}"),
+            ("class _ {\nvoid _() {\n", "\n}\n}"),
+            ("class _ {\nvoid _() {\nswitch (_) {\n", "\n}\n}\n}"),
+        ],
+        "c" => &[("", ""), ("void _() {\n", "\n}"), ("void _() {\nswitch (_) {\n", "\n}\n}")],
+        "cpp" => &[("", ""), ("void _() {\n", "\n}"), ("void _() {\nswitch (_) {\n", "\n}\n}"), ("class _ {\n", "\n};")],
+        "ruby" => &[("", ""), ("def _\n", "\nend"), ("class _\n", "\nend"), ("if _\n", "\nend"), ("case _\n", "\nend")],
+        "php" => &[("<?php\n", ""), ("<?php\nfunction _() {\n", "\n}"), ("<?php\nclass _ {\n", "\n}")],
+        _ => &[("", "")],
+    }
+}
+
+/// Check that a body parses with the grammar of `lang` in one of its contexts
+fn validate(body: &[Marker], lang: &str) -> Result<()> {
+    if lang == ANY_LANGUAGE {
+        return Ok(());
+    }
+    let mut parser = TreeSitterParser::new()?;
+    if parser.set_language(lang).is_err() {
+        // Languages without a grammar cannot be checked
+        return Ok(());
+    }
+
+    let sample = Path::new("Example.txt");
+    let variables = Variables {
+        path: Some(sample),
+        workspace_root: None,
+        lang,
+        line: "",
+        line_index: 0,
+        word: "",
+        now: std::time::SystemTime::now(),
+    };
+    let resolve = |name: &str| variables.resolve(name);
+
+    for fill in [Fill::Empty, Fill::Identifier] {
+        let text = syntax::to_plain(body, &resolve, fill);
+        for (before, after) in contexts(lang) {
+            let text = if before.ends_with(":\n") { indent(&text) } else { text.clone() };
+            let source = format!("{}{}{}\n", before, text, after);
+            if parser.parse(&source, "snippet").is_ok_and(|tree| !tree.root_node().has_error()) {
+                return Ok(());
+            }
+        }
+    }
+    bail!("does not parse as {}", lang)
+}
+
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("\t{}", line)).collect::<Vec<_>>().join("\n")
+}
+
+/// JSON with the `//` and `/* */` comments and trailing commas of JSONC removed
+fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            (']' | '}', _) => {
+                // Drop a comma left dangling before the closing bracket
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.truncate(trimmed - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(lang: &str) -> Variables<'_> {
+        Variables {
+            path: Some(Path::new("/ws/widget.py")),
+            workspace_root: Some(Path::new("/ws")),
+            lang,
+            line: "",
+            line_index: 0,
+            word: "",
+            now: std::time::SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        for (languages, json) in DEFAULTS {
+            for lang in *languages {
+                let mut library = SnippetLibrary::default();
+                assert_eq!(library.add_json(json, &[lang], "built-in"), Vec::<String>::new());
+                assert!(library.snippets(lang).count() >= 5, "{}", lang);
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_and_override() {
+        let mut library = SnippetLibrary::with_defaults();
+        let json = r#"{
+            // Replaces the built-in snippet
+            "For Loop": { "prefix": "for", "body": ["for ${1:x} in ${2:xs}:", "\t$0"] },
+            "Broken": { "prefix": "broken", "body": "def (:" },
+            "Unclosed": { "prefix": "unclosed", "body": "${1:oops" },
+            "No prefix": { "body": "pass" },
+        }"#;
+        let warnings = library.add_json(json, &["python"], "python.json");
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings.iter().any(|w| w.contains("'Broken'") && w.contains("does not parse as python")));
+
+        let fors: Vec<&Snippet> = library.snippets("python").filter(|s| s.name == "For Loop").collect();
+        assert_eq!(fors.len(), 1);
+        assert_eq!(fors[0].prefixes, vec!["for"]);
+        assert!(!library.snippets("python").any(|s| s.name == "Broken"));
+
+        // `.code-snippets` entries are scoped by VS Code language identifiers
+        let scoped = r#"{
+            "Header": { "scope": "javascript,typescriptreact", "prefix": "hdr", "body": "// $TM_FILENAME" },
+            "Everywhere": { "prefix": "todo", "body": "TODO: $0" }
+        }"#;
+        assert!(library.add_json(scoped, &[], "team.code-snippets").is_empty());
+        assert!(library.snippets("tsx").any(|s| s.name == "Header"));
+        assert!(!library.snippets("typescript").any(|s| s.name == "Header"));
+        assert!(library.snippets("go").any(|s| s.name == "Everywhere"));
+    }
+
+    #[test]
+    fn test_snippet_items() {
+        let mut library = SnippetLibrary::default();
+        let json = r#"{ "Class": { "prefix": ["cls", "class"], "body": "class ${1:$TM_FILENAME_BASE}:\n\t${2|pass,...|}", "description": "A class" } }"#;
+        assert!(library.add_json(json, &["python"], "python.json").is_empty());
+
+        let items = library.items("python", &variables("python"), true);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].insert_text.as_deref(), Some("class ${1:widget}:\n\t${2|pass,...|}"));
+        assert_eq!(items[0].insert_text_format, Some(InsertTextFormat::SNIPPET));
+        assert_eq!(items[1].label, "class");
+
+        let plain = library.items("python", &variables("python"), false);
+        assert_eq!(plain[0].insert_text.as_deref(), Some("class widget:\n\tpass"));
+        assert_eq!(plain[0].insert_text_format, Some(InsertTextFormat::PLAIN_TEXT));
+    }
+
+    #[test]
+    fn test_strip_jsonc() {
+        let json = "{\n  // comment\n  \"a\": \"http://x\", /* block */\n  \"b\": [1, 2,],\n}";
+        let value: Value = serde_json::from_str(&strip_jsonc(json)).unwrap();
+        assert_eq!(value["a"], "http://x");
+        assert_eq!(value["b"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,415 @@
+//! Snippet syntax
+//!
+//! Parses the TextMate/LSP snippet grammar (tabstops, placeholders, choices and
+//! variables, with optional transforms) and renders it back, either as a snippet
+//! with the variables the server knows already substituted, or as plain text with
+//! every placeholder filled in.
+//!
+//! Transforms are kept verbatim for the client to apply: rendering a variable with a
+//! transform as plain text inserts its value untransformed.
+
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
+
+/// A piece of a snippet body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Marker {
+    Text(String),
+    /// `$1`, `${1:placeholder}` or `${1/regex/format/options}`
+    Tabstop { index: u32, placeholder: Vec<Marker>, transform: Option<String> },
+    /// `${1|one,two|}`
+    Choice { index: u32, options: Vec<String> },
+    /// `$NAME`, `${NAME:default}` or `${NAME/regex/format/options}`
+    Variable { name: String, default: Vec<Marker>, transform: Option<String> },
+}
+
+/// How tabstops without a placeholder are filled in plain text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fill {
+    Empty,
+    /// A bare identifier, for tabstops standing where an expression is expected
+    Identifier,
+}
+
+/// Parse a snippet body
+pub fn parse(body: &str) -> Result<Vec<Marker>> {
+    let mut parser = Parser { chars: body.chars().collect(), pos: 0 };
+    let markers = parser.markers(&[])?;
+    match parser.peek() {
+        None => Ok(markers),
+        Some(c) => bail!("unexpected '{}' at offset {}", c, parser.pos),
+    }
+}
+
+/// Render as a snippet, substituting the variables `resolve` knows. Variables
+/// resolving to nothing fall back to their default; unknown variables without one
+/// become placeholders holding their name, as in VS Code.
+pub fn to_snippet(markers: &[Marker], resolve: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut next_index = max_index(markers) + 1;
+    let mut out = String::new();
+    write_snippet(markers, resolve, false, &mut next_index, &mut out);
+    out
+}
+
+/// Render as the text the snippet inserts when every placeholder is accepted
+pub fn to_plain(markers: &[Marker], resolve: &dyn Fn(&str) -> Option<String>, fill: Fill) -> String {
+    let mut defaults = HashMap::new();
+    collect_defaults(markers, resolve, &mut defaults);
+    let mut out = String::new();
+    write_plain(markers, resolve, fill, &defaults, &mut out);
+    out
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(anyhow!("expected '{}' at offset {}", c, self.pos))
+        }
+    }
+
+    /// Markers up to (not including) the first unescaped character of `stop`
+    fn markers(&mut self, stop: &[char]) -> Result<Vec<Marker>> {
+        let mut markers = Vec::new();
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if stop.contains(&c) {
+                break;
+            }
+            match c {
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(escaped @ ('$' | '}' | '\\')) => {
+                            self.pos += 1;
+                            text.push(escaped);
+                        }
+                        _ => text.push('\\'),
+                    }
+                }
+                '$' => match self.dollar()? {
+                    Some(marker) => {
+                        if !text.is_empty() {
+                            markers.push(Marker::Text(std::mem::take(&mut text)));
+                        }
+                        markers.push(marker);
+                    }
+                    None => text.push('$'),
+                },
+                _ => {
+                    self.pos += 1;
+                    text.push(c);
+                }
+            }
+        }
+        if !text.is_empty() {
+            markers.push(Marker::Text(text));
+        }
+        Ok(markers)
+    }
+
+    /// The construct starting at a `$`, `None` for a literal dollar sign
+    fn dollar(&mut self) -> Result<Option<Marker>> {
+        let start = self.pos;
+        self.pos += 1;
+
+        if let Some(index) = self.int() {
+            return Ok(Some(Marker::Tabstop { index, placeholder: Vec::new(), transform: None }));
+        }
+        if let Some(name) = self.var() {
+            return Ok(Some(Marker::Variable { name, default: Vec::new(), transform: None }));
+        }
+        if !self.eat('{') {
+            return Ok(None);
+        }
+
+        if let Some(index) = self.int() {
+            let marker = if self.eat(':') {
+                let placeholder = self.markers(&['}'])?;
+                Marker::Tabstop { index, placeholder, transform: None }
+            } else if self.eat('|') {
+                Marker::Choice { index, options: self.choices()? }
+            } else if self.peek() == Some('/') {
+                Marker::Tabstop { index, placeholder: Vec::new(), transform: Some(self.transform()?) }
+            } else {
+                Marker::Tabstop { index, placeholder: Vec::new(), transform: None }
+            };
+            self.expect('}')?;
+            return Ok(Some(marker));
+        }
+
+        let name = self.var().ok_or_else(|| anyhow!("expected a tabstop or variable at offset {}", start))?;
+        let marker = if self.eat(':') {
+            Marker::Variable { name, default: self.markers(&['}'])?, transform: None }
+        } else if self.peek() == Some('/') {
+            Marker::Variable { name, default: Vec::new(), transform: Some(self.transform()?) }
+        } else {
+            Marker::Variable { name, default: Vec::new(), transform: None }
+        };
+        self.expect('}')?;
+        Ok(Some(marker))
+    }
+
+    fn int(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().ok()
+    }
+
+    fn var(&mut self) -> Option<String> {
+        if !self.peek().is_some_and(|c| c == '_' || c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c == '_' || c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        Some(self.chars[start..self.pos].iter().collect())
+    }
+
+    /// Options of a choice, after the opening `|` and through the closing `|`
+    fn choices(&mut self) -> Result<Vec<String>> {
+        let mut options = vec![String::new()];
+        loop {
+            match self.peek() {
+                None => bail!("unterminated choice"),
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(escaped @ (',' | '|' | '\\' | '$' | '}')) => {
+                            self.pos += 1;
+                            options.last_mut().unwrap().push(escaped);
+                        }
+                        _ => options.last_mut().unwrap().push('\\'),
+                    }
+                }
+                Some(',') => {
+                    self.pos += 1;
+                    options.push(String::new());
+                }
+                Some('|') => {
+                    self.pos += 1;
+                    return Ok(options);
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    options.last_mut().unwrap().push(c);
+                }
+            }
+        }
+    }
+
+    /// `/regex/format/options`, returned verbatim
+    fn transform(&mut self) -> Result<String> {
+        let start = self.pos;
+        self.expect('/')?;
+        for _ in 0..2 {
+            let mut depth = 0;
+            loop {
+                match self.peek() {
+                    None => bail!("unterminated transform"),
+                    Some('\\') => self.pos += 2,
+                    Some('/') if depth == 0 => break,
+                    Some(c) => {
+                        // Format groups like `${1:/upcase}` contain slashes of their own
+                        if c == '{' && self.pos > 0 && self.chars[self.pos - 1] == '$' {
+                            depth += 1;
+                        } else if c == '}' && depth > 0 {
+                            depth -= 1;
+                        }
+                        self.pos += 1;
+                    }
+                }
+            }
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+}
+
+fn max_index(markers: &[Marker]) -> u32 {
+    markers
+        .iter()
+        .map(|marker| match marker {
+            Marker::Text(_) => 0,
+            Marker::Tabstop { index, placeholder, .. } => (*index).max(max_index(placeholder)),
+            Marker::Choice { index, .. } => *index,
+            Marker::Variable { default, .. } => max_index(default),
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Escape text for a snippet; `}` only needs escaping inside a placeholder
+fn escape(text: &str, nested: bool) -> String {
+    let text = text.replace('\\', "\\\\").replace('$', "\\$");
+    if nested {
+        text.replace('}', "\\}")
+    } else {
+        text
+    }
+}
+
+fn write_snippet(
+    markers: &[Marker],
+    resolve: &dyn Fn(&str) -> Option<String>,
+    nested: bool,
+    next_index: &mut u32,
+    out: &mut String,
+) {
+    for marker in markers {
+        match marker {
+            Marker::Text(text) => out.push_str(&escape(text, nested)),
+            Marker::Tabstop { index, placeholder, transform } => {
+                if let Some(transform) = transform {
+                    out.push_str(&format!("${{{}{}}}", index, transform));
+                } else if placeholder.is_empty() {
+                    out.push_str(&format!("${}", index));
+                } else {
+                    out.push_str(&format!("${{{}:", index));
+                    write_snippet(placeholder, resolve, true, next_index, out);
+                    out.push('}');
+                }
+            }
+            Marker::Choice { index, options } => {
+                let options: Vec<String> = options
+                    .iter()
+                    .map(|option| option.replace('\\', "\\\\").replace(',', "\\,").replace('|', "\\|"))
+                    .collect();
+                out.push_str(&format!("${{{}|{}|}}", index, options.join(",")));
+            }
+            Marker::Variable { name, default, transform } => match (resolve(name), transform) {
+                // The client applies transforms to the variables it knows
+                (Some(_), Some(transform)) => out.push_str(&format!("${{{}{}}}", name, transform)),
+                (Some(value), None) if !value.is_empty() => out.push_str(&escape(&value, nested)),
+                (value, _) if !default.is_empty() || value.is_some() => {
+                    write_snippet(default, resolve, nested, next_index, out);
+                }
+                _ => {
+                    out.push_str(&format!("${{{}:{}}}", next_index, name));
+                    *next_index += 1;
+                }
+            },
+        }
+    }
+}
+
+/// Plain text of the first placeholder or choice of each tabstop, for its mirrors
+fn collect_defaults(markers: &[Marker], resolve: &dyn Fn(&str) -> Option<String>, defaults: &mut HashMap<u32, String>) {
+    for marker in markers {
+        match marker {
+            Marker::Tabstop { index, placeholder, .. } if !placeholder.is_empty() => {
+                // Nested placeholders first, so they appear in this one's text
+                collect_defaults(placeholder, resolve, defaults);
+                if !defaults.contains_key(index) {
+                    let mut text = String::new();
+                    write_plain(placeholder, resolve, Fill::Empty, defaults, &mut text);
+                    defaults.insert(*index, text);
+                }
+            }
+            Marker::Choice { index, options } => {
+                defaults.entry(*index).or_insert_with(|| options.first().cloned().unwrap_or_default());
+            }
+            Marker::Variable { default, .. } => collect_defaults(default, resolve, defaults),
+            _ => {}
+        }
+    }
+}
+
+fn write_plain(
+    markers: &[Marker],
+    resolve: &dyn Fn(&str) -> Option<String>,
+    fill: Fill,
+    defaults: &HashMap<u32, String>,
+    out: &mut String,
+) {
+    for marker in markers {
+        match marker {
+            Marker::Text(text) => out.push_str(text),
+            Marker::Tabstop { index, .. } | Marker::Choice { index, .. } => match defaults.get(index) {
+                Some(text) => out.push_str(text),
+                None if fill == Fill::Identifier => out.push('x'),
+                None => {}
+            },
+            Marker::Variable { name, default, .. } => match resolve(name) {
+                Some(value) if !value.is_empty() => out.push_str(&value),
+                Some(_) => write_plain(default, resolve, fill, defaults, out),
+                None if default.is_empty() => out.push_str(name),
+                None => write_plain(default, resolve, fill, defaults, out),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(name: &str) -> Option<String> {
+        match name {
+            "TM_FILENAME" => Some("app.py".to_string()),
+            "TM_SELECTED_TEXT" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_markers() {
+        let markers = parse("for ${1:i} in ${2|a,b\\,c|}: $0 \\$x ${TM_FILENAME/(.*)/${1:/upcase}/g}").unwrap();
+        assert_eq!(markers[1], Marker::Tabstop { index: 1, placeholder: vec![Marker::Text("i".into())], transform: None });
+        assert_eq!(markers[3], Marker::Choice { index: 2, options: vec!["a".into(), "b,c".into()] });
+        assert_eq!(markers[5], Marker::Tabstop { index: 0, placeholder: Vec::new(), transform: None });
+        assert_eq!(markers[6], Marker::Text(" $x ".into()));
+        assert_eq!(
+            markers[7],
+            Marker::Variable { name: "TM_FILENAME".into(), default: Vec::new(), transform: Some("/(.*)/${1:/upcase}/g".into()) }
+        );
+
+        // Nested placeholders, and a lone dollar sign is text
+        let nested = parse("${1:outer ${2:inner}} costs $ 5").unwrap();
+        assert!(matches!(&nested[0], Marker::Tabstop { placeholder, .. } if placeholder.len() == 2));
+        assert_eq!(nested[1], Marker::Text(" costs $ 5".into()));
+
+        assert!(parse("${1:unclosed").is_err());
+        assert!(parse("${1|a,b").is_err());
+        assert!(parse("${:x}").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let markers = parse("# $TM_FILENAME ${TM_SELECTED_TEXT:sel} $UNKNOWN ${1:a\\}b} $1 ${2|x,y|} $0").unwrap();
+        assert_eq!(to_snippet(&markers, &resolve), "# app.py sel ${3:UNKNOWN} ${1:a\\}b} $1 ${2|x,y|} $0");
+        assert_eq!(to_plain(&markers, &resolve, Fill::Empty), "# app.py sel UNKNOWN a}b a}b x ");
+        assert_eq!(to_plain(&markers, &resolve, Fill::Identifier), "# app.py sel UNKNOWN a}b a}b x x");
+
+        // Resolved values are escaped, `}` only within placeholders
+        let dollar = |_: &str| Some("$HOME}".to_string());
+        assert_eq!(to_snippet(&parse("$TM_FILENAME").unwrap(), &dollar), "\\$HOME}");
+        assert_eq!(to_snippet(&parse("${1:$TM_FILENAME}").unwrap(), &dollar), "${1:\\$HOME\\}}");
+    }
+}
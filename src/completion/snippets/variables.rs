@@ -0,0 +1,188 @@
+//! Snippet variables
+//!
+//! The VS Code variables the server can resolve: file and workspace names, the
+//! current line and word, the date and time (in UTC), random values and the
+//! language's comment tokens. The selection and clipboard are unknown to a
+//! completion request and resolve to nothing, so their defaults apply.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MONTHS: &[&str] = &[
+    "January", "February", "March", "April", "May", "June", "July", "August", "September",
+    "October", "November", "December",
+];
+
+const DAYS: &[&str] = &["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+/// Context of a snippet expansion
+#[derive(Debug, Clone)]
+pub struct Variables<'a> {
+    pub path: Option<&'a Path>,
+    pub workspace_root: Option<&'a Path>,
+    pub lang: &'a str,
+    /// Text of the line the snippet is inserted on, and its index
+    pub line: &'a str,
+    pub line_index: u32,
+    /// Word being completed
+    pub word: &'a str,
+    pub now: SystemTime,
+}
+
+impl Variables<'_> {
+    /// Value of a variable, `None` for unknown names
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        let file_name = || self.path.and_then(|p| p.file_name()).map(|n| n.to_string_lossy().into_owned());
+        let value = match name {
+            "TM_SELECTED_TEXT" | "CLIPBOARD" => String::new(),
+            "TM_CURRENT_LINE" => self.line.to_string(),
+            "TM_CURRENT_WORD" => self.word.to_string(),
+            "TM_LINE_INDEX" => self.line_index.to_string(),
+            "TM_LINE_NUMBER" => (self.line_index + 1).to_string(),
+            "CURSOR_INDEX" => "0".to_string(),
+            "CURSOR_NUMBER" => "1".to_string(),
+            "TM_FILENAME" => file_name()?,
+            "TM_FILENAME_BASE" => {
+                let name = file_name()?;
+                name.split('.').next().unwrap_or(&name).to_string()
+            }
+            "TM_DIRECTORY" => self.path?.parent()?.display().to_string(),
+            "TM_FILEPATH" => self.path?.display().to_string(),
+            "RELATIVE_FILEPATH" => {
+                let path = self.path?;
+                self.workspace_root
+                    .and_then(|root| path.strip_prefix(root).ok())
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            }
+            "WORKSPACE_NAME" => self.workspace_root?.file_name()?.to_string_lossy().into_owned(),
+            "WORKSPACE_FOLDER" => self.workspace_root?.display().to_string(),
+            "LINE_COMMENT" => comment_tokens(self.lang).0?.to_string(),
+            "BLOCK_COMMENT_START" => comment_tokens(self.lang).1?.0.to_string(),
+            "BLOCK_COMMENT_END" => comment_tokens(self.lang).1?.1.to_string(),
+            "RANDOM" => format!("{:06}", random() % 1_000_000),
+            "RANDOM_HEX" => format!("{:06x}", random() & 0xff_ffff),
+            "UUID" => uuid(),
+            _ => return self.date(name),
+        };
+        Some(value)
+    }
+
+    fn date(&self, name: &str) -> Option<String> {
+        let seconds = self.now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let days = (seconds / 86_400) as i64;
+        let (year, month, day) = civil_from_days(days);
+        let weekday = DAYS[((days + 4).rem_euclid(7)) as usize];
+        let month_name = MONTHS[month as usize - 1];
+        let time = seconds % 86_400;
+
+        let value = match name {
+            "CURRENT_YEAR" => year.to_string(),
+            "CURRENT_YEAR_SHORT" => format!("{:02}", year % 100),
+            "CURRENT_MONTH" => format!("{:02}", month),
+            "CURRENT_MONTH_NAME" => month_name.to_string(),
+            "CURRENT_MONTH_NAME_SHORT" => month_name[..3].to_string(),
+            "CURRENT_DATE" => format!("{:02}", day),
+            "CURRENT_DAY_NAME" => weekday.to_string(),
+            "CURRENT_DAY_NAME_SHORT" => weekday[..3].to_string(),
+            "CURRENT_HOUR" => format!("{:02}", time / 3600),
+            "CURRENT_MINUTE" => format!("{:02}", time % 3600 / 60),
+            "CURRENT_SECOND" => format!("{:02}", time % 60),
+            "CURRENT_SECONDS_UNIX" => seconds.to_string(),
+            "CURRENT_TIMEZONE_OFFSET" => "+00:00".to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Line comment and block comment delimiters of a language
+fn comment_tokens(lang: &str) -> (Option<&'static str>, Option<(&'static str, &'static str)>) {
+    match lang {
+        "python" => (Some("#"), Some(("\"\"\"", "\"\"\""))),
+        "ruby" => (Some("#"), Some(("=begin", "=end"))),
+        "bash" | "yaml" | "toml" => (Some("#"), None),
+        "html" | "xml" | "svelte" => (None, Some(("<!--", "-->"))),
+        "css" => (None, Some(("/*", "*/"))),
+        "lua" => (Some("--"), Some(("--[[", "]]"))),
+        "json" => (None, None),
+        _ => (Some("//"), Some(("/*", "*/"))),
+    }
+}
+
+/// Year, month and day of a day count since the Unix epoch
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos()));
+    hasher.finish()
+}
+
+/// A version 4 UUID
+fn uuid() -> String {
+    let high = random();
+    let low = random();
+    let time_high = ((high & 0x0fff) | 0x4000) as u16;
+    let clock = (((low >> 48) & 0x3fff) | 0x8000) as u16;
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        time_high,
+        clock,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_resolve_variables() {
+        let variables = Variables {
+            path: Some(Path::new("/ws/src/app.test.ts")),
+            workspace_root: Some(Path::new("/ws")),
+            lang: "typescript",
+            line: "  con",
+            line_index: 4,
+            word: "con",
+            // 2024-02-29 13:05:09 UTC, a Thursday
+            now: UNIX_EPOCH + Duration::from_secs(1_709_211_909),
+        };
+        let get = |name: &str| variables.resolve(name);
+
+        assert_eq!(get("TM_FILENAME").as_deref(), Some("app.test.ts"));
+        assert_eq!(get("TM_FILENAME_BASE").as_deref(), Some("app"));
+        assert_eq!(get("RELATIVE_FILEPATH").as_deref(), Some("src/app.test.ts"));
+        assert_eq!(get("WORKSPACE_NAME").as_deref(), Some("ws"));
+        assert_eq!(get("TM_LINE_NUMBER").as_deref(), Some("5"));
+        assert_eq!(get("TM_SELECTED_TEXT").as_deref(), Some(""));
+        assert_eq!(get("LINE_COMMENT").as_deref(), Some("//"));
+        assert_eq!(
+            ["CURRENT_YEAR", "CURRENT_MONTH", "CURRENT_DATE", "CURRENT_DAY_NAME_SHORT", "CURRENT_HOUR", "CURRENT_MINUTE"]
+                .map(|name| get(name).unwrap()),
+            ["2024", "02", "29", "Thu", "13", "05"]
+        );
+        assert_eq!(get("RANDOM").unwrap().len(), 6);
+        let uuid = get("UUID").unwrap();
+        assert_eq!((uuid.len(), &uuid[14..15]), (36, "4"));
+        assert_eq!(get("NOT_A_VARIABLE"), None);
+    }
+}
@@ -0,0 +1,154 @@
+//! Completions opened by `<` and quotes
+//!
+//! The quote of a C/C++ `#include` or of a JavaScript/TypeScript import or
+//! `require` offers the workspace's headers or modules: relative to the document,
+//! or to the workspace root for `#include <`. A `<` opening an element in markup or
+//! JSX offers the tags used in the document, common HTML elements and, in JSX, the
+//! components in scope. Other `<` and quotes (comparisons, generics, strings) open
+//! nothing.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use tower_lsp::lsp_types::*;
+
+use super::auto_import::{js_specifier, relative_path};
+
+const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx"];
+
+const MODULE_EXTENSIONS: &[&str] = &["js", "jsx", "mjs", "cjs", "ts", "tsx"];
+
+const MARKUP_LANGUAGES: &[&str] = &["html", "xml", "vue", "svelte", "astro"];
+
+const HTML_ELEMENTS: &[&str] = &[
+    "a", "article", "aside", "body", "button", "canvas", "code", "div", "footer", "form", "h1", "h2", "h3", "head",
+    "header", "img", "input", "label", "li", "link", "main", "meta", "nav", "ol", "option", "p", "pre", "script",
+    "section", "select", "span", "strong", "style", "table", "tbody", "td", "textarea", "th", "thead", "title", "tr",
+    "ul",
+];
+
+/// Characters before a `<` that opens a JSX element rather than comparing
+const JSX_OPENERS: &[char] = &['(', '=', '>', '{', ',', '?', ':', '&', '|', '['];
+
+/// Include or import paths for a quote (or `<`) typed after `line`, the text of the
+/// line before it
+pub fn path_items(line: &str, trigger: &str, lang: &str, from: &Path, root: Option<&Path>, files: &[String]) -> Vec<CompletionItem> {
+    let before = line.trim_end();
+    let last_word = before.rsplit(|c: char| c.is_whitespace()).next().unwrap_or_default();
+    let (extensions, base) = match (lang, trigger) {
+        ("c" | "cpp", "\"") if before.ends_with("#include") => (HEADER_EXTENSIONS, from.parent()),
+        ("c" | "cpp", "<") if before.ends_with("#include") => (HEADER_EXTENSIONS, root),
+        ("javascript" | "typescript" | "tsx", "\"" | "'")
+            if matches!(last_word, "from" | "import") || before.ends_with("require(") || before.ends_with("import(") =>
+        {
+            (MODULE_EXTENSIONS, from.parent())
+        }
+        _ => return Vec::new(),
+    };
+    let Some(base) = base else {
+        return Vec::new();
+    };
+
+    let mut paths = BTreeSet::new();
+    for file in files {
+        let Some(target) = Url::parse(file).ok().and_then(|uri| uri.to_file_path().ok()) else {
+            continue;
+        };
+        if target == from || !target.extension().and_then(|e| e.to_str()).is_some_and(|e| extensions.contains(&e)) {
+            continue;
+        }
+        if extensions == MODULE_EXTENSIONS {
+            paths.extend(js_specifier(from, &target));
+        } else {
+            paths.insert(relative_path(base, &target).to_string_lossy().replace('\\', "/"));
+        }
+    }
+
+    paths
+        .into_iter()
+        .map(|path| CompletionItem {
+            sort_text: Some(format!("0_{}", path)),
+            label: path,
+            kind: Some(CompletionItemKind::FILE),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Whether a `<` typed after `line` opens an element
+pub fn opens_element(line: &str, lang: &str) -> bool {
+    if MARKUP_LANGUAGES.contains(&lang) {
+        return true;
+    }
+    if !matches!(lang, "javascript" | "tsx") {
+        return false;
+    }
+    let before = line.trim_end();
+    before.is_empty() || before.ends_with(JSX_OPENERS) || before.ends_with("return")
+}
+
+/// Tag names for a `<` opening an element: tags of the document, then HTML elements
+pub fn tag_items(content: &str) -> Vec<CompletionItem> {
+    let mut tags = Vec::new();
+    for (i, _) in content.match_indices('<') {
+        let name: String = content[i + 1..]
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '.' | '_' | ':'))
+            .collect();
+        if name.starts_with(|c: char| c.is_alphabetic()) && !tags.contains(&name) {
+            tags.push(name);
+        }
+    }
+    for element in HTML_ELEMENTS {
+        if !tags.iter().any(|tag| tag == element) {
+            tags.push(element.to_string());
+        }
+    }
+
+    tags.into_iter()
+        .enumerate()
+        .map(|(rank, tag)| CompletionItem {
+            label: tag,
+            kind: Some(CompletionItemKind::PROPERTY),
+            sort_text: Some(format!("1_{:04}", rank)),
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(items: &[CompletionItem]) -> Vec<&str> {
+        items.iter().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_path_items() {
+        let files = ["file:///ws/src/app.ts", "file:///ws/src/ui/button.tsx", "file:///ws/lib/index.js", "file:///ws/include/net.h"]
+            .map(String::from);
+        let from = Path::new("/ws/src/app.ts");
+        let root = Some(Path::new("/ws"));
+
+        let items = path_items("import { Button } from ", "'", "typescript", from, root, &files);
+        assert_eq!(labels(&items), ["../lib", "./ui/button"]);
+        assert!(path_items("const s = ", "'", "typescript", from, root, &files).is_empty());
+
+        let from = Path::new("/ws/src/main.c");
+        assert_eq!(labels(&path_items("#include ", "\"", "c", from, root, &files)), ["../include/net.h"]);
+        assert_eq!(labels(&path_items("#include ", "<", "c", from, root, &files)), ["include/net.h"]);
+    }
+
+    #[test]
+    fn test_tags() {
+        assert!(opens_element("  return (", "tsx"));
+        assert!(opens_element("", "javascript"));
+        assert!(!opens_element("if (a ", "javascript"));
+        assert!(!opens_element("let v: Vec", "rust"));
+        assert!(opens_element("<p>text", "html"));
+
+        let items = tag_items("<Layout>\n  <my-card></my-card>\n  <div>");
+        assert_eq!(labels(&items)[..3], ["Layout", "my-card", "div"]);
+        assert_eq!(items.iter().filter(|item| item.label == "div").count(), 1);
+    }
+}
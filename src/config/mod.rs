@@ -4,12 +4,15 @@
 //! - MCP pipeline (pre/post-processing)
 //! - LSP proxy servers
 //! - Server settings
+//! - AI providers per feature (the `[ai]` table of the `--config` file)
+//! - Workspaces trusted to run their own commands (the `[trust]` table)
 //! - Multi-command CLI (LSP, ACP, Zed init)
 
-use anyhow::Result;
+use crate::ai::config::AiConfig;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "ulsp")]
@@ -127,6 +130,10 @@ pub struct Config {
     pub server: ServerConfig,
     pub mcp: McpConfig,
     pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub ai: AiConfig,
+    #[serde(default)]
+    pub trust: TrustConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -158,6 +165,48 @@ pub struct ProxyConfig {
     pub servers: std::collections::HashMap<String, String>,
 }
 
+/// Workspaces trusted to run their own commands
+///
+/// A workspace's `.universal-lsp` file comes with the repository, so only the
+/// workspaces listed here may configure linters and formatter commands, which run on
+/// every save or format. Others get no linters, since even the built-in ones run
+/// code from the repository (`cargo clippy` builds it, `eslint` loads its
+/// configuration), and only the built-in formatters.
+///
+/// ```toml
+/// [trust]
+/// workspaces = ["/home/me/src/app"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustConfig {
+    /// Trusted folders, subfolders included
+    #[serde(default)]
+    pub workspaces: Vec<PathBuf>,
+}
+
+impl TrustConfig {
+    /// The `[trust]` table of `path`, or of `~/.universal-lsp/config.toml`
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let default_path = dirs::home_dir().map(|home| home.join(".universal-lsp").join("config.toml"));
+        let Some(path) = path.map(Path::to_path_buf).or(default_path.filter(|path| path.exists())) else {
+            return Ok(Self::default());
+        };
+
+        #[derive(Deserialize)]
+        struct File {
+            #[serde(default)]
+            trust: TrustConfig,
+        }
+        let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let file: File = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text)?
+        } else {
+            toml::from_str(&text)?
+        };
+        Ok(file.trust)
+    }
+}
+
 /// Runtime command mode
 #[derive(Debug, Clone)]
 pub enum CommandMode {
@@ -221,6 +270,8 @@ impl Config {
             proxy: ProxyConfig {
                 servers: proxy_servers,
             },
+            ai: AiConfig::default(),
+            trust: TrustConfig::default(),
         }
     }
 
@@ -260,6 +311,8 @@ impl Config {
             proxy: ProxyConfig {
                 servers: std::collections::HashMap::new(), // ACP doesn't use LSP proxies
             },
+            ai: AiConfig::default(),
+            trust: TrustConfig::default(),
         }
     }
 
@@ -267,6 +320,7 @@ impl Config {
     /// Returns tuple: (Config, CommandMode)
     pub fn from_args() -> Result<(Self, CommandMode)> {
         let args = CliArgs::parse();
+        let config_path = args.config.clone();
 
         match &args.command {
             // Default command: LSP server
@@ -299,7 +353,7 @@ impl Config {
                     (vec![], 5000, true, vec![], 100, false)
                 };
 
-                let config = Self::from_lsp_args(
+                let mut config = Self::from_lsp_args(
                     args.log_level,
                     mcp_server,
                     mcp_timeout,
@@ -308,6 +362,8 @@ impl Config {
                     max_concurrent,
                     log_requests,
                 );
+                config.ai = AiConfig::load(config_path.as_deref())?;
+                config.trust = TrustConfig::load(config_path.as_deref())?;
                 Ok((config, CommandMode::Lsp))
             }
 
@@ -319,7 +375,7 @@ impl Config {
                 max_concurrent,
                 log_requests,
             }) => {
-                let config = Self::from_acp_args(
+                let mut config = Self::from_acp_args(
                     args.log_level,
                     mcp_server.clone(),
                     *mcp_timeout,
@@ -327,6 +383,7 @@ impl Config {
                     *max_concurrent,
                     *log_requests,
                 );
+                config.ai = AiConfig::load(config_path.as_deref())?;
                 Ok((config, CommandMode::Acp))
             }
 
@@ -373,6 +430,8 @@ impl Config {
             proxy: ProxyConfig {
                 servers: std::collections::HashMap::new(),
             },
+            ai: AiConfig::default(),
+            trust: TrustConfig::default(),
         }
     }
 
@@ -454,6 +513,8 @@ mod tests {
             proxy: ProxyConfig {
                 servers: std::collections::HashMap::new(),
             },
+            ai: AiConfig::default(),
+            trust: TrustConfig::default(),
         };
 
         assert!(config.has_mcp_pipeline());
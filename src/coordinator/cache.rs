@@ -136,6 +136,7 @@ mod tests {
             suggestions: vec!["test".to_string()],
             documentation: Some("Test doc".to_string()),
             confidence: Some(0.9),
+            diagnostics: Vec::new(),
         }
     }
 
@@ -317,6 +317,8 @@ mod tests {
             proxy: crate::config::ProxyConfig {
                 servers: std::collections::HashMap::new(),
             },
+            ai: Default::default(),
+            trust: Default::default(),
         };
 
         let coordinator = Coordinator::new(&config);
@@ -0,0 +1,527 @@
+//! Opt-in AI lint pass
+//!
+//! Documents are split into top-level functions (methods included) and each function
+//! is sent to the AI provider on its own. Findings are cached by a hash of the
+//! function text, so after a save only functions whose subtree changed are
+//! re-analysed, and every request is charged against the token budget of the
+//! workspace it came from.
+//!
+//! Findings come back as diagnostics with the [`AI_LINT_SOURCE`] source, the rule id
+//! as code and the suggested fix (a `TextEdit`) under `data.fix`.
+
+use anyhow::Result;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range, TextEdit};
+use tree_sitter::{Node, Tree};
+
+use crate::ai::claude::Message;
+use crate::ai::AiProvider;
+use crate::workspace::AiLintConfig;
+
+/// Diagnostic source of AI lint findings
+pub const AI_LINT_SOURCE: &str = "universal-lsp-ai";
+
+/// Tokens reserved for each reply, as much as a reply is billed for here
+const REPLY_TOKENS: u64 = 512;
+
+/// Cached functions are dropped wholesale past this size
+const MAX_CACHED_FUNCTIONS: usize = 4096;
+
+/// Node kinds analysed as one unit
+const FUNCTION_KINDS: &[&str] = &[
+    "function_definition",
+    "function_declaration",
+    "function_item",
+    "method_definition",
+    "method_declaration",
+    "constructor_declaration",
+    "generator_function_declaration",
+];
+
+const SYSTEM_PROMPT: &str = "You are a code reviewer. Report bugs, risky patterns and clear \
+style problems in the given function. Answer with a JSON array only, no prose. Each element \
+is {\"line\": <1-based line>, \"end_line\": <1-based line>, \"severity\": \
+\"error\"|\"warning\"|\"info\"|\"hint\", \"rule\": \"<kebab-case-rule-id>\", \"message\": \
+\"<one sentence>\", \"fix\": \"<replacement text for lines line..=end_line>\" or null}. \
+Answer [] when there is nothing worth reporting.";
+
+/// A structured finding, with lines relative to the start of its function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AiFinding {
+    pub line: u32,
+    #[serde(default)]
+    pub end_line: Option<u32>,
+    #[serde(default)]
+    pub severity: Option<String>,
+    pub rule: String,
+    pub message: String,
+    #[serde(default)]
+    pub fix: Option<String>,
+}
+
+/// A function of the document, the unit of analysis and caching
+#[derive(Debug, Clone)]
+pub struct LintUnit<'a> {
+    pub name: String,
+    pub start_line: u32,
+    pub text: &'a str,
+    pub hash: u64,
+}
+
+/// AI lint pass with its finding cache, token budgets and save debouncing
+#[derive(Debug)]
+pub struct AiLinter {
+    client: std::sync::Arc<dyn AiProvider>,
+    findings: DashMap<u64, Vec<AiFinding>>,
+    tokens_used: DashMap<String, u64>,
+    generations: DashMap<String, u64>,
+    next_generation: AtomicU64,
+}
+
+impl AiLinter {
+    pub fn new(client: std::sync::Arc<dyn AiProvider>) -> Self {
+        Self {
+            client,
+            findings: DashMap::new(),
+            tokens_used: DashMap::new(),
+            generations: DashMap::new(),
+            next_generation: AtomicU64::new(1),
+        }
+    }
+
+    /// Register a save of `uri` and return its generation
+    ///
+    /// A debounced pass only runs when its generation is still the latest one.
+    pub fn schedule(&self, uri: &str) -> u64 {
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        self.generations.insert(uri.to_string(), generation);
+        generation
+    }
+
+    /// Whether no save of `uri` happened after the one that produced `generation`
+    pub fn is_latest(&self, uri: &str, generation: u64) -> bool {
+        self.generations.get(uri).map(|g| *g == generation).unwrap_or(false)
+    }
+
+    /// Tokens left in a workspace's budget
+    pub fn remaining_budget(&self, workspace: &str, config: &AiLintConfig) -> u64 {
+        let used = self.tokens_used.get(workspace).map(|used| *used).unwrap_or(0);
+        config.token_budget.saturating_sub(used)
+    }
+
+    /// Findings already known for the functions of a document, without any requests
+    pub fn cached_diagnostics(&self, tree: &Tree, source: &str, lang: &str) -> Vec<Diagnostic> {
+        lint_units(tree, source, lang)
+            .iter()
+            .filter_map(|unit| self.findings.get(&unit.hash).map(|f| to_diagnostics(unit, &f, source)))
+            .flatten()
+            .collect()
+    }
+
+    /// Analyse the functions without cached findings and return all findings
+    ///
+    /// Each request reserves its prompt and [`REPLY_TOKENS`] up front, so the budget
+    /// is never exceeded; functions whose reservation doesn't fit are skipped, and
+    /// smaller ones may still be analysed. Requests that never reach the provider are
+    /// refunded; completed ones stay charged even when the reply can't be parsed.
+    /// Either way the function is retried on the next save.
+    pub async fn lint(
+        &self,
+        tree: &Tree,
+        source: &str,
+        lang: &str,
+        workspace: &str,
+        config: &AiLintConfig,
+    ) -> Vec<Diagnostic> {
+        let units = lint_units(tree, source, lang);
+
+        for unit in units.iter().filter(|unit| !self.findings.contains_key(&unit.hash)) {
+            let prompt = build_prompt(unit, lang);
+            let reserved = estimate_tokens(SYSTEM_PROMPT) + estimate_tokens(&prompt) + REPLY_TOKENS;
+            if !self.charge(workspace, reserved, config) {
+                tracing::info!("AI lint budget of {} doesn't fit {}, skipping it", workspace, unit.name);
+                continue;
+            }
+
+            let reply = match self.request(&prompt).await {
+                Ok(reply) => reply,
+                Err(e) => {
+                    self.refund(workspace, reserved);
+                    tracing::debug!("AI lint request for {} failed: {}", unit.name, e);
+                    continue;
+                }
+            };
+            // Keep the reply's share of the reservation, up to what was reserved
+            self.refund(workspace, REPLY_TOKENS.saturating_sub(estimate_tokens(&reply)));
+            match parse_findings(&reply) {
+                Ok(findings) => self.insert(unit.hash, findings),
+                Err(e) => tracing::debug!("Unparseable AI lint reply for {}: {}", unit.name, e),
+            }
+        }
+
+        units
+            .iter()
+            .filter_map(|unit| self.findings.get(&unit.hash).map(|f| to_diagnostics(unit, &f, source)))
+            .flatten()
+            .collect()
+    }
+
+    async fn request(&self, prompt: &str) -> Result<String> {
+        let messages = [Message {
+            role: "user".to_string(),
+            content: prompt.to_string(),
+        }];
+        let response = self
+            .client
+            .send_message_with_tools(&messages, None, Some(SYSTEM_PROMPT.to_string()))
+            .await?;
+        Ok(response.text_blocks.join("\n"))
+    }
+
+    /// Reserve `tokens` from a workspace's budget, failing when they don't fit
+    fn charge(&self, workspace: &str, tokens: u64, config: &AiLintConfig) -> bool {
+        let mut used = self.tokens_used.entry(workspace.to_string()).or_insert(0);
+        if *used + tokens > config.token_budget {
+            return false;
+        }
+        *used += tokens;
+        true
+    }
+
+    /// Give back reserved tokens that weren't spent
+    fn refund(&self, workspace: &str, tokens: u64) {
+        if let Some(mut used) = self.tokens_used.get_mut(workspace) {
+            *used = used.saturating_sub(tokens);
+        }
+    }
+
+    fn insert(&self, hash: u64, findings: Vec<AiFinding>) {
+        if self.findings.len() >= MAX_CACHED_FUNCTIONS {
+            self.findings.clear();
+        }
+        self.findings.insert(hash, findings);
+    }
+}
+
+/// Split a document into the functions analysed by the AI lint pass
+///
+/// Only the outermost functions are returned; nested functions and closures belong to
+/// their enclosing function, so editing them invalidates it.
+pub fn lint_units<'a>(tree: &Tree, source: &'a str, lang: &str) -> Vec<LintUnit<'a>> {
+    let mut units = Vec::new();
+    collect_units(tree.root_node(), source, lang, &mut units);
+    units
+}
+
+fn collect_units<'a>(node: Node, source: &'a str, lang: &str, units: &mut Vec<LintUnit<'a>>) {
+    if FUNCTION_KINDS.contains(&node.kind()) {
+        let Some(text) = source.get(node.start_byte()..node.end_byte()) else {
+            return;
+        };
+        let name = node
+            .child_by_field_name("name")
+            .or_else(|| node.child_by_field_name("declarator"))
+            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+            .unwrap_or("<anonymous>")
+            .to_string();
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        lang.hash(&mut hasher);
+        text.hash(&mut hasher);
+
+        units.push(LintUnit {
+            name,
+            start_line: node.start_position().row as u32,
+            text,
+            hash: hasher.finish(),
+        });
+        return;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_units(child, source, lang, units);
+    }
+}
+
+fn build_prompt(unit: &LintUnit, lang: &str) -> String {
+    let numbered: Vec<String> = unit
+        .text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| format!("{:>4} | {}", i + 1, line))
+        .collect();
+    format!("Language: {}\n\n{}", lang, numbered.join("\n"))
+}
+
+/// Rough token estimate used for budgeting (about four characters per token)
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Parse the JSON array of findings out of a model reply
+///
+/// Surrounding prose or code fences are ignored.
+pub fn parse_findings(reply: &str) -> Result<Vec<AiFinding>> {
+    let start = reply.find('[').ok_or_else(|| anyhow::anyhow!("No JSON array in reply"))?;
+    let end = reply.rfind(']').ok_or_else(|| anyhow::anyhow!("Unterminated JSON array in reply"))?;
+    if end < start {
+        return Err(anyhow::anyhow!("Malformed JSON array in reply"));
+    }
+    Ok(serde_json::from_str(&reply[start..=end])?)
+}
+
+/// Place the findings of a function in the document
+fn to_diagnostics(unit: &LintUnit, findings: &[AiFinding], source: &str) -> Vec<Diagnostic> {
+    let unit_lines = unit.text.lines().count().max(1) as u32;
+    let lines: Vec<&str> = source.lines().collect();
+
+    findings
+        .iter()
+        .map(|finding| {
+            let first = finding.line.clamp(1, unit_lines);
+            let last = finding.end_line.unwrap_or(first).clamp(first, unit_lines);
+            let start_line = unit.start_line + first - 1;
+            let end_line = unit.start_line + last - 1;
+
+            let line_text = |line: u32| lines.get(line as usize).copied().unwrap_or("");
+            let indent = line_text(start_line).chars().take_while(|c| c.is_whitespace()).count() as u32;
+            let line_end = line_text(end_line).chars().count() as u32;
+
+            let fix = finding.fix.as_ref().map(|new_text| TextEdit {
+                range: Range {
+                    start: Position { line: start_line, character: 0 },
+                    end: Position { line: end_line, character: line_end },
+                },
+                new_text: new_text.clone(),
+            });
+
+            Diagnostic {
+                range: Range {
+                    start: Position { line: start_line, character: indent },
+                    end: Position { line: end_line, character: line_end },
+                },
+                severity: Some(severity(finding.severity.as_deref())),
+                code: Some(NumberOrString::String(finding.rule.clone())),
+                source: Some(AI_LINT_SOURCE.to_string()),
+                message: finding.message.clone(),
+                related_information: None,
+                tags: None,
+                code_description: None,
+                data: fix.map(|fix| serde_json::json!({ "fix": fix })),
+            }
+        })
+        .collect()
+}
+
+fn severity(severity: Option<&str>) -> DiagnosticSeverity {
+    match severity.map(|s| s.to_ascii_lowercase()).as_deref() {
+        Some("error") => DiagnosticSeverity::ERROR,
+        Some("info") | Some("information") => DiagnosticSeverity::INFORMATION,
+        Some("hint") => DiagnosticSeverity::HINT,
+        _ => DiagnosticSeverity::WARNING,
+    }
+}
+
+/// Suggested fix attached to an AI lint diagnostic
+pub fn suggested_fix(diagnostic: &Diagnostic) -> Option<TextEdit> {
+    if diagnostic.source.as_deref() != Some(AI_LINT_SOURCE) {
+        return None;
+    }
+    let fix = diagnostic.data.as_ref()?.get("fix")?.clone();
+    serde_json::from_value(fix).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::claude::{ClaudeClient, ClaudeConfig};
+    use crate::tree_sitter::TreeSitterParser;
+    use std::sync::Arc;
+
+    fn parse(source: &str, lang: &str) -> Arc<Tree> {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        parser.parse(source, "test").unwrap()
+    }
+
+    fn linter() -> AiLinter {
+        let client = ClaudeClient::new(ClaudeConfig {
+            api_key: "test-key".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        AiLinter::new(Arc::new(client))
+    }
+
+    #[test]
+    fn test_only_changed_functions_get_new_hashes() {
+        let before = "def a():\n    return 1\n\ndef b():\n    return 2\n";
+        let after = "def a():\n    return 1\n\ndef b():\n    return 3\n";
+
+        let old_tree = parse(before, "python");
+        let new_tree = parse(after, "python");
+        let old_units = lint_units(&old_tree, before, "python");
+        let new_units = lint_units(&new_tree, after, "python");
+
+        assert_eq!(new_units.len(), 2);
+        assert_eq!(new_units[0].name, "a");
+        assert_eq!(old_units[0].hash, new_units[0].hash);
+        assert_ne!(old_units[1].hash, new_units[1].hash);
+    }
+
+    #[test]
+    fn test_parse_findings_ignores_surrounding_prose() {
+        let reply = "Here you go:\n```json\n[{\"line\": 2, \"severity\": \"error\", \"rule\": \"division-by-zero\", \"message\": \"Divides by zero\", \"fix\": null}]\n```";
+        let findings = parse_findings(reply).unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "division-by-zero");
+        assert!(parse_findings("nothing to report").is_err());
+    }
+
+    #[test]
+    fn test_cached_findings_follow_moved_function() {
+        let linter = linter();
+        let source = "def check(x):\n    return x / 0\n";
+        let tree = parse(source, "python");
+        let unit = lint_units(&tree, source, "python").remove(0);
+        linter.insert(
+            unit.hash,
+            vec![AiFinding {
+                line: 2,
+                end_line: None,
+                severity: Some("error".to_string()),
+                rule: "division-by-zero".to_string(),
+                message: "Divides by zero".to_string(),
+                fix: Some("    return 0".to_string()),
+            }],
+        );
+
+        // Unchanged function text, two lines further down
+        let moved = "import os\n\ndef check(x):\n    return x / 0\n";
+        let diagnostics = linter.cached_diagnostics(&parse(moved, "python"), moved, "python");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position { line: 3, character: 4 });
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].code, Some(NumberOrString::String("division-by-zero".to_string())));
+        assert_eq!(diagnostics[0].source.as_deref(), Some(AI_LINT_SOURCE));
+
+        let fix = suggested_fix(&diagnostics[0]).unwrap();
+        assert_eq!(fix.range.start, Position { line: 3, character: 0 });
+        assert_eq!(fix.new_text, "    return 0");
+    }
+
+    #[test]
+    fn test_token_budget_is_per_workspace() {
+        let linter = linter();
+        let config = AiLintConfig {
+            enabled: true,
+            token_budget: 100,
+            ..Default::default()
+        };
+
+        assert!(linter.charge("file:///a", 80, &config));
+        assert!(!linter.charge("file:///a", 30, &config));
+        assert!(linter.charge("file:///b", 30, &config));
+        assert_eq!(linter.remaining_budget("file:///a", &config), 20);
+    }
+
+    #[tokio::test]
+    async fn test_lint_skips_requests_beyond_budget() {
+        let linter = linter();
+        let source = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        let tree = parse(source, "rust");
+        let config = AiLintConfig {
+            enabled: true,
+            token_budget: 1,
+            ..Default::default()
+        };
+
+        let diagnostics = linter.lint(&tree, source, "rust", "file:///ws", &config).await;
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(linter.remaining_budget("file:///ws", &config), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_requests_are_refunded() {
+        use crate::ai::ollama::{OllamaClient, OllamaConfig};
+
+        // Nothing listens on the discard port, so every request fails
+        let client = OllamaClient::new(OllamaConfig {
+            base_url: "http://127.0.0.1:9".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let linter = AiLinter::new(Arc::new(client));
+        let source = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        let tree = parse(source, "rust");
+        let config = AiLintConfig {
+            enabled: true,
+            token_budget: 10_000,
+            ..Default::default()
+        };
+
+        let diagnostics = linter.lint(&tree, source, "rust", "file:///ws", &config).await;
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(linter.remaining_budget("file:///ws", &config), 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_replies_stay_charged() {
+        use crate::ai::ollama::{OllamaClient, OllamaConfig};
+        use std::sync::atomic::AtomicUsize;
+        use warp::Filter;
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        let chat = warp::path!("api" / "chat").map(move || {
+            counted.fetch_add(1, Ordering::Relaxed);
+            warp::reply::json(&serde_json::json!({
+                "model": "local-model",
+                "message": { "role": "assistant", "content": "Looks fine to me" },
+                "done": true
+            }))
+        });
+        let (addr, server) = warp::serve(chat).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = OllamaClient::new(OllamaConfig { base_url: format!("http://{}", addr), ..Default::default() }).unwrap();
+        let linter = AiLinter::new(Arc::new(client));
+        let source = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        let tree = parse(source, "rust");
+        let unit = lint_units(&tree, source, "rust").remove(0);
+        // Room for exactly one reservation
+        let reserved = estimate_tokens(SYSTEM_PROMPT) + estimate_tokens(&build_prompt(&unit, "rust")) + REPLY_TOKENS;
+        let config = AiLintConfig {
+            enabled: true,
+            token_budget: reserved,
+            ..Default::default()
+        };
+
+        // Bad replies are retried on the next save, until the budget runs out
+        for _ in 0..3 {
+            assert!(linter.lint(&tree, source, "rust", "file:///ws", &config).await.is_empty());
+        }
+
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+        let remaining = linter.remaining_budget("file:///ws", &config);
+        assert_eq!(remaining, REPLY_TOKENS - estimate_tokens("Looks fine to me"));
+    }
+
+    #[test]
+    fn test_debounce_generations() {
+        let linter = linter();
+        let first = linter.schedule("file:///a.py");
+        let second = linter.schedule("file:///a.py");
+
+        assert!(!linter.is_latest("file:///a.py", first));
+        assert!(linter.is_latest("file:///a.py", second));
+    }
+}
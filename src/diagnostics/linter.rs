@@ -0,0 +1,749 @@
+//! External linter integration
+//!
+//! Runs the linter configured in `LanguageConfig.linter` on a saved file and turns its
+//! JSON or SARIF output into diagnostics. Built-in invocations exist for ruff, eslint,
+//! shellcheck, clippy and golangci-lint; any other value is run as a command line,
+//! with `{file}` replaced by the file path (or the path appended when absent). Only
+//! trusted workspaces may configure command lines (see `workspace`).
+//!
+//! The output format is recognised from its shape, so custom commands only need to
+//! print one of the supported formats. Autofixes are kept under `data.fix` of each
+//! diagnostic and offered as quick fixes.
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range, TextEdit, Url};
+
+/// Linters taking longer than this are killed
+const LINTER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs external linters and keeps their latest diagnostics per document
+#[derive(Debug, Default)]
+pub struct LinterRunner {
+    results: DashMap<String, Vec<Diagnostic>>,
+}
+
+impl LinterRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lint the file behind `uri` and remember the diagnostics for it
+    pub async fn run(&self, uri: &Url, linter: &str) -> Result<Vec<Diagnostic>> {
+        let path = uri
+            .to_file_path()
+            .map_err(|_| anyhow::anyhow!("Linting needs a file URI: {}", uri))?;
+        let content = tokio::fs::read_to_string(&path).await?;
+
+        let (source, program, args) = command_line(linter, &path);
+        let mut command = tokio::process::Command::new(&program);
+        command.args(&args).kill_on_drop(true);
+        if let Some(dir) = path.parent() {
+            command.current_dir(dir);
+        }
+
+        let output = tokio::time::timeout(LINTER_TIMEOUT, command.output())
+            .await
+            .with_context(|| format!("{} timed out", source))?
+            .with_context(|| format!("Failed to run {}", program))?;
+
+        // Linters exit non-zero when they report problems, so only empty output is a failure
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() && !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "{} failed: {}",
+                source,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let diagnostics = parse_output(&stdout, &source, &path, &content)?;
+        self.results.insert(uri.to_string(), diagnostics.clone());
+        Ok(diagnostics)
+    }
+
+    /// Diagnostics from the last run for a document
+    pub fn diagnostics(&self, uri: &str) -> Vec<Diagnostic> {
+        self.results.get(uri).map(|d| d.clone()).unwrap_or_default()
+    }
+
+    /// Forget the results of a document
+    pub fn clear(&self, uri: &str) {
+        self.results.remove(uri);
+    }
+}
+
+/// Diagnostic source name, program and arguments for a configured linter
+fn command_line(linter: &str, file: &Path) -> (String, String, Vec<String>) {
+    let file_arg = file.to_string_lossy().to_string();
+    let preset = |source: &str, program: &str, args: &[&str], with_file: bool| {
+        let mut args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        if with_file {
+            args.push(file_arg.clone());
+        }
+        (source.to_string(), program.to_string(), args)
+    };
+
+    match linter.trim() {
+        "ruff" => preset("ruff", "ruff", &["check", "--output-format", "json", "--quiet"], true),
+        "eslint" => preset("eslint", "eslint", &["--format", "json"], true),
+        "shellcheck" => preset("shellcheck", "shellcheck", &["--format", "json1"], true),
+        "clippy" => preset("clippy", "cargo", &["clippy", "--message-format", "json", "--quiet"], false),
+        "golangci-lint" => preset("golangci-lint", "golangci-lint", &["run", "--out-format", "json"], false),
+        command => {
+            let mut parts: Vec<String> = command.split_whitespace().map(|p| p.to_string()).collect();
+            let program = if parts.is_empty() { String::new() } else { parts.remove(0) };
+            if parts.iter().any(|p| p.contains("{file}")) {
+                for part in &mut parts {
+                    *part = part.replace("{file}", &file_arg);
+                }
+            } else {
+                parts.push(file_arg.clone());
+            }
+            let source = Path::new(&program)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| program.clone());
+            (source, program, parts)
+        }
+    }
+}
+
+/// Parse linter output for `file` into diagnostics
+///
+/// Recognises SARIF, ruff, eslint, shellcheck (`json` and `json1`), golangci-lint and
+/// cargo's JSON messages. Entries for other files are dropped.
+pub fn parse_output(output: &str, source: &str, file: &Path, content: &str) -> Result<Vec<Diagnostic>> {
+    if output.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if let Ok(value) = serde_json::from_str::<Value>(output) {
+        if value.get("runs").is_some() {
+            return Ok(parse_sarif(&value, source, file, content));
+        }
+        if value.get("Issues").is_some() {
+            return Ok(parse_golangci(&value, source, file, content));
+        }
+        if let Some(comments) = value.get("comments").and_then(|c| c.as_array()) {
+            return Ok(parse_shellcheck(comments, source, file));
+        }
+        if let Some(items) = value.as_array() {
+            let Some(first) = items.first() else {
+                return Ok(Vec::new());
+            };
+            if first.get("filePath").is_some() {
+                return Ok(parse_eslint(items, source, file, content));
+            }
+            if first.get("location").is_some() && first.get("filename").is_some() {
+                return Ok(parse_ruff(items, source, file));
+            }
+            if first.get("level").is_some() && first.get("file").is_some() {
+                return Ok(parse_shellcheck(items, source, file));
+            }
+        }
+        if value.get("reason").is_none() {
+            return Err(anyhow::anyhow!("Unrecognised {} output", source));
+        }
+    }
+
+    // cargo prints one JSON message per line
+    let messages: Vec<Value> = output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| message.get("reason").and_then(|r| r.as_str()) == Some("compiler-message"))
+        .collect();
+    if messages.is_empty() && !output.lines().any(|line| line.trim_start().starts_with('{')) {
+        return Err(anyhow::anyhow!("Unrecognised {} output", source));
+    }
+    Ok(parse_cargo(&messages, source, file))
+}
+
+/// Title and edits of a linter diagnostic's autofix
+pub fn autofix(diagnostic: &Diagnostic) -> Option<(String, Vec<TextEdit>)> {
+    let data = diagnostic.data.as_ref()?;
+    data.get("linter")?;
+    let fix = data.get("fix")?;
+    let title = fix.get("title")?.as_str()?.to_string();
+    let edits: Vec<TextEdit> = serde_json::from_value(fix.get("edits")?.clone()).ok()?;
+    if edits.is_empty() {
+        return None;
+    }
+    Some((title, edits))
+}
+
+fn diagnostic(
+    range: Range,
+    severity: DiagnosticSeverity,
+    code: Option<String>,
+    source: &str,
+    message: String,
+    fix: Option<(String, Vec<TextEdit>)>,
+) -> Diagnostic {
+    let fix = fix.filter(|(_, edits)| !edits.is_empty());
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        code: code.map(NumberOrString::String),
+        source: Some(source.to_string()),
+        message,
+        related_information: None,
+        tags: None,
+        code_description: None,
+        data: Some(match fix {
+            Some((title, edits)) => json!({ "linter": source, "fix": { "title": title, "edits": edits } }),
+            None => json!({ "linter": source }),
+        }),
+    }
+}
+
+fn is_same_file(file: &Path, reported: &str) -> bool {
+    let reported = match Url::parse(reported).ok().and_then(|u| u.to_file_path().ok()) {
+        Some(path) => path,
+        None => Path::new(reported).to_path_buf(),
+    };
+    file.ends_with(reported.strip_prefix("./").unwrap_or(&reported))
+}
+
+/// Position from 1-based line and column numbers (0 or missing columns mean line start)
+fn position(line: Option<u64>, column: Option<u64>) -> Position {
+    Position {
+        line: line.unwrap_or(1).saturating_sub(1) as u32,
+        character: column.unwrap_or(1).saturating_sub(1) as u32,
+    }
+}
+
+fn line_end(content: &str, line: u32) -> Position {
+    let length = content.lines().nth(line as usize).map(|l| l.chars().count()).unwrap_or(0);
+    Position { line, character: length as u32 }
+}
+
+/// Position of a UTF-16 offset, as used by eslint fix ranges
+fn utf16_offset_to_position(content: &str, offset: u64) -> Position {
+    let mut position = Position { line: 0, character: 0 };
+    let mut units = 0;
+    for ch in content.chars() {
+        if units >= offset {
+            break;
+        }
+        units += ch.len_utf16() as u64;
+        if ch == '\n' {
+            position.line += 1;
+            position.character = 0;
+        } else {
+            position.character += 1;
+        }
+    }
+    position
+}
+
+fn u64_field(value: &Value, key: &str) -> Option<u64> {
+    value.get(key).and_then(|v| v.as_u64())
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(|v| v.as_str())
+}
+
+fn parse_ruff(items: &[Value], source: &str, file: &Path) -> Vec<Diagnostic> {
+    let location = |value: Option<&Value>| {
+        value
+            .map(|loc| position(u64_field(loc, "row"), u64_field(loc, "column")))
+            .unwrap_or(Position { line: 0, character: 0 })
+    };
+
+    items
+        .iter()
+        .filter(|item| str_field(item, "filename").map(|f| is_same_file(file, f)).unwrap_or(true))
+        .map(|item| {
+            let code = str_field(item, "code").map(|c| c.to_string());
+            let severity = match code.as_deref() {
+                None | Some("E999") => DiagnosticSeverity::ERROR,
+                _ => DiagnosticSeverity::WARNING,
+            };
+            let fix = item.get("fix").filter(|f| !f.is_null()).map(|fix| {
+                let edits = fix
+                    .get("edits")
+                    .and_then(|e| e.as_array())
+                    .map(|edits| {
+                        edits
+                            .iter()
+                            .map(|edit| TextEdit {
+                                range: Range {
+                                    start: location(edit.get("location")),
+                                    end: location(edit.get("end_location")),
+                                },
+                                new_text: str_field(edit, "content").unwrap_or("").to_string(),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let title = str_field(fix, "message").unwrap_or("Apply ruff fix").to_string();
+                (title, edits)
+            });
+
+            diagnostic(
+                Range {
+                    start: location(item.get("location")),
+                    end: location(item.get("end_location")),
+                },
+                severity,
+                code,
+                source,
+                str_field(item, "message").unwrap_or("").to_string(),
+                fix,
+            )
+        })
+        .collect()
+}
+
+fn parse_eslint(items: &[Value], source: &str, file: &Path, content: &str) -> Vec<Diagnostic> {
+    let fix_edit = |fix: &Value| {
+        let range = fix.get("range")?.as_array()?;
+        Some(TextEdit {
+            range: Range {
+                start: utf16_offset_to_position(content, range.first()?.as_u64()?),
+                end: utf16_offset_to_position(content, range.get(1)?.as_u64()?),
+            },
+            new_text: str_field(fix, "text").unwrap_or("").to_string(),
+        })
+    };
+
+    items
+        .iter()
+        .filter(|item| str_field(item, "filePath").map(|f| is_same_file(file, f)).unwrap_or(true))
+        .filter_map(|item| item.get("messages").and_then(|m| m.as_array()))
+        .flatten()
+        .map(|message| {
+            let start = position(u64_field(message, "line"), u64_field(message, "column"));
+            let end = match u64_field(message, "endLine") {
+                Some(_) => position(u64_field(message, "endLine"), u64_field(message, "endColumn")),
+                None => line_end(content, start.line),
+            };
+            let severity = match u64_field(message, "severity") {
+                Some(2) => DiagnosticSeverity::ERROR,
+                _ => DiagnosticSeverity::WARNING,
+            };
+            let rule = str_field(message, "ruleId").map(|r| r.to_string());
+            let fix = message.get("fix").and_then(fix_edit).map(|edit| {
+                let title = match &rule {
+                    Some(rule) => format!("Fix {}", rule),
+                    None => "Apply eslint fix".to_string(),
+                };
+                (title, vec![edit])
+            });
+
+            diagnostic(
+                Range { start, end },
+                severity,
+                rule,
+                source,
+                str_field(message, "message").unwrap_or("").to_string(),
+                fix,
+            )
+        })
+        .collect()
+}
+
+fn parse_shellcheck(comments: &[Value], source: &str, file: &Path) -> Vec<Diagnostic> {
+    comments
+        .iter()
+        .filter(|comment| str_field(comment, "file").map(|f| is_same_file(file, f)).unwrap_or(true))
+        .map(|comment| {
+            let severity = match str_field(comment, "level") {
+                Some("error") => DiagnosticSeverity::ERROR,
+                Some("warning") => DiagnosticSeverity::WARNING,
+                Some("info") => DiagnosticSeverity::INFORMATION,
+                _ => DiagnosticSeverity::HINT,
+            };
+            let code = u64_field(comment, "code").map(|c| format!("SC{}", c));
+            let fix = comment
+                .get("fix")
+                .and_then(|f| f.get("replacements"))
+                .and_then(|r| r.as_array())
+                .map(|replacements| {
+                    let edits = replacements
+                        .iter()
+                        .map(|r| TextEdit {
+                            range: Range {
+                                start: position(u64_field(r, "line"), u64_field(r, "column")),
+                                end: position(u64_field(r, "endLine"), u64_field(r, "endColumn")),
+                            },
+                            new_text: str_field(r, "replacement").unwrap_or("").to_string(),
+                        })
+                        .collect();
+                    let title = match &code {
+                        Some(code) => format!("Fix {}", code),
+                        None => "Apply shellcheck fix".to_string(),
+                    };
+                    (title, edits)
+                });
+
+            diagnostic(
+                Range {
+                    start: position(u64_field(comment, "line"), u64_field(comment, "column")),
+                    end: position(u64_field(comment, "endLine"), u64_field(comment, "endColumn")),
+                },
+                severity,
+                code,
+                source,
+                str_field(comment, "message").unwrap_or("").to_string(),
+                fix,
+            )
+        })
+        .collect()
+}
+
+fn parse_golangci(value: &Value, source: &str, file: &Path, content: &str) -> Vec<Diagnostic> {
+    let Some(issues) = value.get("Issues").and_then(|i| i.as_array()) else {
+        return Vec::new();
+    };
+
+    issues
+        .iter()
+        .filter(|issue| {
+            issue
+                .get("Pos")
+                .and_then(|pos| str_field(pos, "Filename"))
+                .map(|f| is_same_file(file, f))
+                .unwrap_or(false)
+        })
+        .map(|issue| {
+            let pos = &issue["Pos"];
+            let start = position(u64_field(pos, "Line"), u64_field(pos, "Column"));
+            let severity = match str_field(issue, "Severity") {
+                Some("error") => DiagnosticSeverity::ERROR,
+                Some("info") => DiagnosticSeverity::INFORMATION,
+                _ => DiagnosticSeverity::WARNING,
+            };
+
+            let fix = issue.get("Replacement").filter(|r| !r.is_null()).map(|replacement| {
+                let from = issue
+                    .get("LineRange")
+                    .and_then(|r| u64_field(r, "From"))
+                    .unwrap_or(start.line as u64 + 1);
+                let to = issue.get("LineRange").and_then(|r| u64_field(r, "To")).unwrap_or(from);
+                let lines = Range {
+                    start: position(Some(from), None),
+                    end: position(Some(to + 1), None),
+                };
+
+                let edit = if let Some(inline) = replacement.get("Inline").filter(|i| !i.is_null()) {
+                    let column = u64_field(inline, "StartCol").unwrap_or(0) as u32;
+                    let length = u64_field(inline, "Length").unwrap_or(0) as u32;
+                    TextEdit {
+                        range: Range {
+                            start: Position { line: start.line, character: column },
+                            end: Position { line: start.line, character: column + length },
+                        },
+                        new_text: str_field(inline, "NewString").unwrap_or("").to_string(),
+                    }
+                } else if replacement.get("NeedOnlyDelete").and_then(|d| d.as_bool()) == Some(true) {
+                    TextEdit { range: lines, new_text: String::new() }
+                } else {
+                    let new_lines: Vec<&str> = replacement
+                        .get("NewLines")
+                        .and_then(|l| l.as_array())
+                        .map(|l| l.iter().filter_map(|line| line.as_str()).collect())
+                        .unwrap_or_default();
+                    TextEdit {
+                        range: lines,
+                        new_text: new_lines.iter().map(|line| format!("{}\n", line)).collect(),
+                    }
+                };
+                ("Apply golangci-lint fix".to_string(), vec![edit])
+            });
+
+            diagnostic(
+                Range { start, end: line_end(content, start.line) },
+                severity,
+                str_field(issue, "FromLinter").map(|l| l.to_string()),
+                source,
+                str_field(issue, "Text").unwrap_or("").to_string(),
+                fix,
+            )
+        })
+        .collect()
+}
+
+fn parse_cargo(messages: &[Value], source: &str, file: &Path) -> Vec<Diagnostic> {
+    let span_range = |span: &Value| Range {
+        start: position(u64_field(span, "line_start"), u64_field(span, "column_start")),
+        end: position(u64_field(span, "line_end"), u64_field(span, "column_end")),
+    };
+
+    messages
+        .iter()
+        .filter_map(|message| message.get("message"))
+        .filter_map(|message| {
+            let spans = message.get("spans")?.as_array()?;
+            let primary = spans
+                .iter()
+                .find(|span| span.get("is_primary").and_then(|p| p.as_bool()) == Some(true))?;
+            if !str_field(primary, "file_name").map(|f| is_same_file(file, f)).unwrap_or(false) {
+                return None;
+            }
+
+            let severity = match str_field(message, "level") {
+                Some("error") => DiagnosticSeverity::ERROR,
+                Some("warning") => DiagnosticSeverity::WARNING,
+                Some("note") => DiagnosticSeverity::INFORMATION,
+                _ => DiagnosticSeverity::HINT,
+            };
+            let code = message.get("code").and_then(|c| str_field(c, "code")).map(|c| c.to_string());
+
+            // Machine-applicable suggestions are attached to child messages
+            let fix = message
+                .get("children")
+                .and_then(|c| c.as_array())
+                .into_iter()
+                .flatten()
+                .find_map(|child| {
+                    let edits: Vec<TextEdit> = child
+                        .get("spans")?
+                        .as_array()?
+                        .iter()
+                        .filter(|span| str_field(span, "suggestion_applicability") == Some("MachineApplicable"))
+                        .filter_map(|span| {
+                            Some(TextEdit {
+                                range: span_range(span),
+                                new_text: str_field(span, "suggested_replacement")?.to_string(),
+                            })
+                        })
+                        .collect();
+                    if edits.is_empty() {
+                        return None;
+                    }
+                    Some((str_field(child, "message").unwrap_or("Apply suggestion").to_string(), edits))
+                });
+
+            Some(diagnostic(
+                span_range(primary),
+                severity,
+                code,
+                source,
+                str_field(message, "message").unwrap_or("").to_string(),
+                fix,
+            ))
+        })
+        .collect()
+}
+
+fn parse_sarif(value: &Value, source: &str, file: &Path, content: &str) -> Vec<Diagnostic> {
+    let region_range = |region: &Value| {
+        let start = position(u64_field(region, "startLine"), u64_field(region, "startColumn"));
+        let end_line = u64_field(region, "endLine").or(u64_field(region, "startLine"));
+        let end = match u64_field(region, "endColumn") {
+            Some(column) => position(end_line, Some(column)),
+            None => line_end(content, end_line.unwrap_or(1).saturating_sub(1) as u32),
+        };
+        Range { start, end }
+    };
+    let artifact_uri = |location: &Value| -> Option<String> {
+        location
+            .get("artifactLocation")
+            .and_then(|a| str_field(a, "uri"))
+            .map(|u| u.to_string())
+    };
+
+    let mut diagnostics = Vec::new();
+    for run in value.get("runs").and_then(|r| r.as_array()).into_iter().flatten() {
+        for result in run.get("results").and_then(|r| r.as_array()).into_iter().flatten() {
+            let Some(location) = result
+                .get("locations")
+                .and_then(|l| l.as_array())
+                .and_then(|l| l.first())
+                .and_then(|l| l.get("physicalLocation"))
+            else {
+                continue;
+            };
+            if let Some(uri) = artifact_uri(location) {
+                if !is_same_file(file, &uri) {
+                    continue;
+                }
+            }
+
+            let severity = match str_field(result, "level") {
+                Some("error") => DiagnosticSeverity::ERROR,
+                Some("note") => DiagnosticSeverity::INFORMATION,
+                Some("none") => DiagnosticSeverity::HINT,
+                _ => DiagnosticSeverity::WARNING,
+            };
+            let range = location.get("region").map(&region_range).unwrap_or_default();
+
+            let fix = result
+                .get("fixes")
+                .and_then(|f| f.as_array())
+                .and_then(|f| f.first())
+                .map(|fix| {
+                    let edits = fix
+                        .get("artifactChanges")
+                        .and_then(|c| c.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter(|change| artifact_uri(change).map(|u| is_same_file(file, &u)).unwrap_or(true))
+                        .filter_map(|change| change.get("replacements").and_then(|r| r.as_array()))
+                        .flatten()
+                        .filter_map(|replacement| {
+                            Some(TextEdit {
+                                range: region_range(replacement.get("deletedRegion")?),
+                                new_text: replacement
+                                    .get("insertedContent")
+                                    .and_then(|c| str_field(c, "text"))
+                                    .unwrap_or("")
+                                    .to_string(),
+                            })
+                        })
+                        .collect();
+                    let title = fix
+                        .get("description")
+                        .and_then(|d| str_field(d, "text"))
+                        .unwrap_or("Apply fix")
+                        .to_string();
+                    (title, edits)
+                });
+
+            diagnostics.push(diagnostic(
+                range,
+                severity,
+                str_field(result, "ruleId").map(|r| r.to_string()),
+                source,
+                result
+                    .get("message")
+                    .and_then(|m| str_field(m, "text"))
+                    .unwrap_or("")
+                    .to_string(),
+                fix,
+            ));
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ruff_with_fix() {
+        let output = r#"[{"code": "F401", "filename": "/ws/app.py", "message": "`os` imported but unused",
+            "location": {"row": 1, "column": 8}, "end_location": {"row": 1, "column": 10},
+            "fix": {"message": "Remove unused import: `os`", "edits": [{"content": "",
+                "location": {"row": 1, "column": 1}, "end_location": {"row": 2, "column": 1}}]}}]"#;
+        let diagnostics = parse_output(output, "ruff", Path::new("/ws/app.py"), "import os\n").unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some(NumberOrString::String("F401".to_string())));
+        assert_eq!(diagnostics[0].range.start, Position { line: 0, character: 7 });
+
+        let (title, edits) = autofix(&diagnostics[0]).unwrap();
+        assert_eq!(title, "Remove unused import: `os`");
+        assert_eq!(edits[0].range.end, Position { line: 1, character: 0 });
+    }
+
+    #[test]
+    fn test_parse_eslint_fix_offsets() {
+        let content = "let a = 1;;\n";
+        let output = r#"[{"filePath": "/ws/app.js", "messages": [{"ruleId": "no-extra-semi", "severity": 2,
+            "message": "Unnecessary semicolon.", "line": 1, "column": 11, "endLine": 1, "endColumn": 12,
+            "fix": {"range": [9, 11], "text": ";"}}]}]"#;
+        let diagnostics = parse_output(output, "eslint", Path::new("/ws/app.js"), content).unwrap();
+
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        let (_, edits) = autofix(&diagnostics[0]).unwrap();
+        assert_eq!(edits[0].range.start, Position { line: 0, character: 9 });
+        assert_eq!(edits[0].range.end, Position { line: 0, character: 11 });
+    }
+
+    #[test]
+    fn test_parse_shellcheck_json1() {
+        let output = r#"{"comments": [{"file": "run.sh", "line": 2, "endLine": 2, "column": 6, "endColumn": 10,
+            "level": "info", "code": 2086, "message": "Double quote to prevent globbing.",
+            "fix": {"replacements": [{"line": 2, "endLine": 2, "column": 6, "endColumn": 6, "replacement": "\""},
+                                      {"line": 2, "endLine": 2, "column": 10, "endColumn": 10, "replacement": "\""}]}}]}"#;
+        let diagnostics = parse_output(output, "shellcheck", Path::new("/ws/run.sh"), "").unwrap();
+
+        assert_eq!(diagnostics[0].code, Some(NumberOrString::String("SC2086".to_string())));
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::INFORMATION));
+        assert_eq!(autofix(&diagnostics[0]).unwrap().1.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_cargo_messages_for_file_only() {
+        let own = json!({"reason": "compiler-message", "message": {
+            "level": "warning", "message": "unneeded `return` statement",
+            "code": {"code": "clippy::needless_return"},
+            "spans": [{"file_name": "src/lib.rs", "line_start": 2, "line_end": 2,
+                       "column_start": 5, "column_end": 14, "is_primary": true}],
+            "children": [{"message": "remove `return`", "spans": [{
+                "file_name": "src/lib.rs", "line_start": 2, "line_end": 2, "column_start": 5, "column_end": 14,
+                "suggested_replacement": "x", "suggestion_applicability": "MachineApplicable"}]}]}});
+        let other = json!({"reason": "compiler-message", "message": {
+            "level": "warning", "message": "elsewhere", "code": null,
+            "spans": [{"file_name": "src/main.rs", "line_start": 1, "line_end": 1,
+                       "column_start": 1, "column_end": 2, "is_primary": true}],
+            "children": []}});
+        let output = format!("{}\n{}\n{}\n", json!({"reason": "compiler-artifact"}), own, other);
+        let diagnostics = parse_output(&output, "clippy", Path::new("/ws/src/lib.rs"), "").unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some(NumberOrString::String("clippy::needless_return".to_string())));
+        let (title, edits) = autofix(&diagnostics[0]).unwrap();
+        assert_eq!(title, "remove `return`");
+        assert_eq!(edits[0].new_text, "x");
+    }
+
+    #[test]
+    fn test_parse_golangci_and_sarif() {
+        let content = "package main\n\nfunc main() {\n\tos.Open(\"x\")\n}\n";
+        let golangci = r#"{"Issues": [{"FromLinter": "errcheck", "Text": "Error return value is not checked",
+            "Severity": "", "Pos": {"Filename": "main.go", "Line": 4, "Column": 9}, "Replacement": null}]}"#;
+        let diagnostics = parse_output(golangci, "golangci-lint", Path::new("/ws/main.go"), content).unwrap();
+        assert_eq!(diagnostics[0].code, Some(NumberOrString::String("errcheck".to_string())));
+        assert_eq!(diagnostics[0].range.end, Position { line: 3, character: 13 });
+
+        let sarif = r#"{"version": "2.1.0", "runs": [{"results": [{"ruleId": "G104", "level": "error",
+            "message": {"text": "Errors unhandled."},
+            "locations": [{"physicalLocation": {"artifactLocation": {"uri": "file:///ws/main.go"},
+                "region": {"startLine": 4, "startColumn": 2}}}]}]}]}"#;
+        let diagnostics = parse_output(sarif, "gosec", Path::new("/ws/main.go"), content).unwrap();
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].range.start, Position { line: 3, character: 1 });
+        assert!(autofix(&diagnostics[0]).is_none());
+
+        assert!(parse_output("not json", "gosec", Path::new("/ws/main.go"), content).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_fake_linter_script() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.py");
+        std::fs::write(&file, "import os\n").unwrap();
+
+        let script = dir.path().join("fake-linter.sh");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\necho '[{\"code\": \"F401\", \"filename\": \"'\"$1\"'\", \"message\": \"unused\", \
+             \"location\": {\"row\": 1, \"column\": 8}, \"end_location\": {\"row\": 1, \"column\": 10}, \"fix\": null}]'\nexit 1\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let runner = LinterRunner::new();
+        let uri = Url::from_file_path(&file).unwrap();
+        let diagnostics = runner.run(&uri, &format!("{} {{file}}", script.display())).await.unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].source.as_deref(), Some("fake-linter"));
+        assert_eq!(runner.diagnostics(uri.as_str()).len(), 1);
+
+        runner.clear(uri.as_str());
+        assert!(runner.diagnostics(uri.as_str()).is_empty());
+    }
+}
@@ -3,21 +3,262 @@
 //! Provides real-time error detection and code quality analysis through:
 //! - Syntax errors from tree-sitter error nodes
 //! - Semantic analysis (undefined symbols, type errors)
-//! - AI-enhanced diagnostics via Claude
+//! - Unused symbols, shadowing and unreachable code (see [`usage`])
+//! - AI-enhanced diagnostics via Claude (opt-in, see [`ai_lint`])
+//!
+//! Reports are cached per document together with a hash of the analysed content and
+//! a result id, which backs the pull model (`textDocument/diagnostic`,
+//! `workspace/diagnostic`) with unchanged reports. Reports of files that aren't open
+//! are brought up to date in the background by [`DiagnosticProvider::update_files`],
+//! so workspace pulls are answered from the cache.
 
 use anyhow::Result;
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::SystemTime;
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentDiagnosticReportKind, FullDocumentDiagnosticReport, Position,
+    Range, UnchangedDocumentDiagnosticReport, Url, WorkspaceDocumentDiagnosticReport,
+    WorkspaceFullDocumentDiagnosticReport, WorkspaceUnchangedDocumentDiagnosticReport,
+};
 use tree_sitter::Tree;
 
-use crate::ai::claude::ClaudeClient;
+use crate::language::grammar_name;
+use crate::tree_sitter::{byte_to_position, TreeSitterParser};
+use crate::workspace::WorkspaceManager;
+
+pub mod ai_lint;
+pub mod linter;
+pub mod scheduler;
+pub mod usage;
+
+use ai_lint::AiLinter;
+use linter::LinterRunner;
+
+/// Diagnostics produced outside of the syntax tree analysis
+///
+/// External linter results are looked up by document URI, so they are only merged in
+/// when `uri` is set. AI lint findings additionally need the document's workspace to
+/// have `ai_lint.enabled`, which is looked up in `workspaces`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiagnosticSources<'a> {
+    pub uri: Option<&'a str>,
+    pub ai_linter: Option<&'a AiLinter>,
+    pub linters: Option<&'a LinterRunner>,
+    pub workspaces: Option<&'a WorkspaceManager>,
+}
+
+/// Last diagnostics computed for a document
+#[derive(Debug, Clone)]
+struct CachedReport {
+    result_id: String,
+    content_hash: u64,
+    diagnostics: Vec<Diagnostic>,
+    /// Modification time of the file the report was computed from, for reports of
+    /// files read from disk
+    modified: Option<SystemTime>,
+}
 
 /// Diagnostic provider for computing diagnostics
-pub struct DiagnosticProvider {}
+///
+/// Keeps the latest report per document URI so pull requests for unchanged content
+/// can be answered without re-analysing it.
+#[derive(Debug)]
+pub struct DiagnosticProvider {
+    reports: DashMap<String, CachedReport>,
+    next_result_id: AtomicU64,
+    /// Set while [`Self::update_files`] runs
+    updating_files: AtomicBool,
+}
 
 impl DiagnosticProvider {
     /// Create a new diagnostic provider
     pub fn new() -> Self {
-        Self {}
+        Self {
+            reports: DashMap::new(),
+            next_result_id: AtomicU64::new(1),
+            updating_files: AtomicBool::new(false),
+        }
+    }
+
+    /// Record diagnostics computed for `content` and return their result id
+    pub fn store(&self, uri: &str, content: &str, diagnostics: Vec<Diagnostic>) -> String {
+        let result_id = self.next_result_id.fetch_add(1, Ordering::Relaxed).to_string();
+        self.reports.insert(
+            uri.to_string(),
+            CachedReport {
+                result_id: result_id.clone(),
+                content_hash: content_hash(content),
+                diagnostics,
+                modified: None,
+            },
+        );
+        result_id
+    }
+
+    /// Record diagnostics computed for `content`, returning whether they differ from
+    /// the cached ones; unchanged diagnostics keep their result id
+    pub fn update(&self, uri: &str, content: &str, diagnostics: Vec<Diagnostic>) -> bool {
+        self.put(uri, content_hash(content), diagnostics, None)
+    }
+
+    fn put(&self, uri: &str, content_hash: u64, diagnostics: Vec<Diagnostic>, modified: Option<SystemTime>) -> bool {
+        let mut report = self.reports.entry(uri.to_string()).or_insert_with(|| CachedReport {
+            result_id: String::new(),
+            content_hash,
+            diagnostics: Vec::new(),
+            modified,
+        });
+        let changed = report.result_id.is_empty() || report.diagnostics != diagnostics;
+        if changed {
+            report.result_id = self.next_result_id.fetch_add(1, Ordering::Relaxed).to_string();
+            report.diagnostics = diagnostics;
+        }
+        report.content_hash = content_hash;
+        report.modified = modified;
+        changed
+    }
+
+    /// Cached diagnostics for a document, if they were computed for exactly `content`
+    pub fn cached(&self, uri: &str, content: &str) -> Option<Vec<Diagnostic>> {
+        self.reports
+            .get(uri)
+            .filter(|report| report.content_hash == content_hash(content))
+            .map(|report| report.diagnostics.clone())
+    }
+
+    /// Forget the cached report of a document (e.g. after a configuration change)
+    pub fn invalidate(&self, uri: &str) {
+        self.reports.remove(uri);
+    }
+
+    /// Pull report for a document
+    ///
+    /// Answers `Unchanged` when the content matches the cached report and the client
+    /// already has its result id; otherwise returns the cached or freshly computed
+    /// diagnostics in full.
+    pub async fn document_report(
+        &self,
+        uri: &str,
+        content: &str,
+        lang: &str,
+        previous_result_id: Option<&str>,
+        sources: DiagnosticSources<'_>,
+    ) -> Result<DocumentDiagnosticReportKind> {
+        let hash = content_hash(content);
+        if let Some(report) = self.reports.get(uri).filter(|r| r.content_hash == hash) {
+            if previous_result_id == Some(report.result_id.as_str()) {
+                return Ok(DocumentDiagnosticReportKind::Unchanged(UnchangedDocumentDiagnosticReport {
+                    result_id: report.result_id.clone(),
+                }));
+            }
+            return Ok(DocumentDiagnosticReportKind::Full(FullDocumentDiagnosticReport {
+                result_id: Some(report.result_id.clone()),
+                items: report.diagnostics.clone(),
+            }));
+        }
+
+        let sources = DiagnosticSources { uri: Some(uri), ..sources };
+        let diagnostics = analyze_document(content, lang, sources).await?;
+        let result_id = self.store(uri, content, diagnostics.clone());
+
+        Ok(DocumentDiagnosticReportKind::Full(FullDocumentDiagnosticReport {
+            result_id: Some(result_id),
+            items: diagnostics,
+        }))
+    }
+
+    /// Cached pull reports for files that aren't open in the editor
+    ///
+    /// `previous_result_ids` maps URIs to the result ids the client already has.
+    /// Files without a report yet are left out until [`Self::update_files`] analysed
+    /// them.
+    pub fn workspace_reports(
+        &self,
+        files: &[Url],
+        previous_result_ids: &HashMap<Url, String>,
+    ) -> Vec<WorkspaceDocumentDiagnosticReport> {
+        files
+            .iter()
+            .filter_map(|uri| {
+                let report = self.reports.get(uri.as_str())?;
+                let report = if previous_result_ids.get(uri) == Some(&report.result_id) {
+                    WorkspaceDocumentDiagnosticReport::Unchanged(WorkspaceUnchangedDocumentDiagnosticReport {
+                        uri: uri.clone(),
+                        version: None,
+                        unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                            result_id: report.result_id.clone(),
+                        },
+                    })
+                } else {
+                    WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+                        uri: uri.clone(),
+                        version: None,
+                        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                            result_id: Some(report.result_id.clone()),
+                            items: report.diagnostics.clone(),
+                        },
+                    })
+                };
+                Some(report)
+            })
+            .collect()
+    }
+
+    /// Bring the reports of files that aren't open in the editor up to date with
+    /// their content on disk, returning whether any report changed
+    ///
+    /// Files whose modification time matches their report are not read again, and
+    /// reports of files outside `files` (no longer indexed, deleted or closed) are
+    /// dropped unless `keep` holds for them. Returns `false` right away while another
+    /// update is running.
+    pub async fn update_files(
+        &self,
+        files: &[Url],
+        keep: impl Fn(&str) -> bool,
+        sources: DiagnosticSources<'_>,
+    ) -> bool {
+        if self.updating_files.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        let listed: std::collections::HashSet<&str> = files.iter().map(|uri| uri.as_str()).collect();
+        let before = self.reports.len();
+        self.reports.retain(|uri, _| listed.contains(uri.as_str()) || keep(uri));
+        let mut changed = self.reports.len() != before;
+
+        for uri in files {
+            let Ok(path) = uri.to_file_path() else {
+                continue;
+            };
+            let Ok(modified) = tokio::fs::metadata(&path).await.and_then(|metadata| metadata.modified()) else {
+                changed |= self.reports.remove(uri.as_str()).is_some();
+                continue;
+            };
+            if self.reports.get(uri.as_str()).is_some_and(|report| report.modified == Some(modified)) {
+                continue;
+            }
+
+            let Ok(content) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            let hash = content_hash(&content);
+            if let Some(mut report) = self.reports.get_mut(uri.as_str()).filter(|report| report.content_hash == hash) {
+                report.modified = Some(modified);
+                continue;
+            }
+
+            let sources = DiagnosticSources { uri: Some(uri.as_str()), ..sources };
+            match analyze_document(&content, &grammar_name(uri.path()), sources).await {
+                Ok(diagnostics) => changed |= self.put(uri.as_str(), hash, diagnostics, Some(modified)),
+                Err(e) => tracing::debug!("Workspace diagnostics failed for {}: {}", uri, e),
+            }
+        }
+
+        self.updating_files.store(false, Ordering::Release);
+        changed
     }
 }
 
@@ -27,29 +268,87 @@ impl Default for DiagnosticProvider {
     }
 }
 
+/// Parse a document and compute its diagnostics
+///
+/// Languages without a tree-sitter grammar have no diagnostics.
+pub async fn analyze_document(
+    content: &str,
+    lang: &str,
+    sources: DiagnosticSources<'_>,
+) -> Result<Vec<Diagnostic>> {
+    let mut parser = TreeSitterParser::new()?;
+    if parser.set_language(lang).is_err() {
+        return Ok(Vec::new());
+    }
+
+    let tree = parser.parse(content, "temp")?;
+    compute_diagnostics(&tree, content, lang, sources).await
+}
+
+/// Hash identifying the analysed content of a document
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
 /// Compute all diagnostics for a document
 pub async fn compute_diagnostics(
     tree: &Tree,
     source: &str,
     lang: &str,
-    _claude_client: Option<&ClaudeClient>,
+    sources: DiagnosticSources<'_>,
 ) -> Result<Vec<Diagnostic>> {
-    let mut diagnostics = Vec::new();
+    let mut diagnostics = syntax_diagnostics(tree, source);
+    diagnostics.extend(background_diagnostics(tree, source, lang, sources).await?);
+    Ok(diagnostics)
+}
 
-    // 1. Extract syntax errors from tree-sitter
-    diagnostics.extend(extract_syntax_errors(tree, source));
+/// Syntax errors from tree-sitter, cheap enough to publish on every edit
+pub fn syntax_diagnostics(tree: &Tree, source: &str) -> Vec<Diagnostic> {
+    extract_syntax_errors(tree, source)
+}
+
+/// Diagnostics beyond syntax errors, computed once edits settle
+pub async fn background_diagnostics(
+    tree: &Tree,
+    source: &str,
+    lang: &str,
+    sources: DiagnosticSources<'_>,
+) -> Result<Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
 
-    // 2. Semantic analysis (undefined symbols, etc.)
+    // 1. Semantic analysis (undefined symbols, etc.)
     diagnostics.extend(analyze_semantic_errors(tree, source, lang)?);
 
-    // 3. AI-enhanced diagnostics (future)
-    // if let Some(claude) = claude_client {
-    //     diagnostics.extend(ai_analyze(source, lang, claude).await?);
-    // }
+    // 2. External linter results from the last save
+    if let (Some(linters), Some(uri)) = (sources.linters, sources.uri) {
+        diagnostics.extend(linters.diagnostics(uri));
+    }
+
+    // 3. AI lint findings for functions analysed on an earlier save; requests
+    //    themselves only happen in the debounced pass after a save
+    if let Some(linter) = sources.ai_linter.filter(|_| ai_lint_enabled(sources, lang)) {
+        diagnostics.extend(linter.cached_diagnostics(tree, source, lang));
+    }
 
     Ok(diagnostics)
 }
 
+/// Whether the workspace of the document in `sources` has the AI lint pass enabled
+fn ai_lint_enabled(sources: DiagnosticSources<'_>, lang: &str) -> bool {
+    let (Some(uri), Some(workspaces)) = (sources.uri, sources.workspaces) else {
+        return false;
+    };
+    let Ok(uri) = Url::parse(uri) else {
+        return false;
+    };
+    workspaces
+        .get_config_for_document(&uri, lang)
+        .ai_lint
+        .is_some_and(|config| config.enabled)
+}
+
 /// Extract syntax errors from tree-sitter error nodes
 fn extract_syntax_errors(tree: &Tree, source: &str) -> Vec<Diagnostic> {
     let mut diagnostics = Vec::new();
@@ -156,6 +455,9 @@ fn analyze_semantic_errors(tree: &Tree, source: &str, lang: &str) -> Result<Vec<
         }
     }
 
+    // Unused symbols and unreachable code (Python, JS/TS, Rust, Go, Java)
+    diagnostics.extend(usage::analyze_usage(tree, source, lang));
+
     Ok(diagnostics)
 }
 
@@ -724,32 +1026,9 @@ fn is_rust_builtin(name: &str) -> bool {
     )
 }
 
-/// Convert byte offset to LSP Position
-fn byte_to_position(source: &str, byte_offset: usize) -> Position {
-    let mut line = 0;
-    let mut character = 0;
-    let mut current_byte = 0;
-
-    for ch in source.chars() {
-        if current_byte >= byte_offset {
-            break;
-        }
-        current_byte += ch.len_utf8();
-        if ch == '\n' {
-            line += 1;
-            character = 0;
-        } else {
-            character += 1;
-        }
-    }
-
-    Position { line, character }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tree_sitter::TreeSitterParser;
 
     #[test]
     fn test_byte_to_position() {
@@ -778,7 +1057,7 @@ def broken_function():
         parser.set_language("python").unwrap();
         let tree = parser.parse(source, "test.py").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "python", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "python", DiagnosticSources::default()).await.unwrap();
 
         // Should detect syntax error
         assert!(!diagnostics.is_empty(), "Should detect syntax error for unclosed parenthesis");
@@ -797,7 +1076,7 @@ def test_function():
         parser.set_language("python").unwrap();
         let tree = parser.parse(source, "test.py").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "python", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "python", DiagnosticSources::default()).await.unwrap();
 
         // Should detect undefined variable
         assert!(diagnostics.iter().any(|d| {
@@ -819,7 +1098,7 @@ def test_builtins():
         parser.set_language("python").unwrap();
         let tree = parser.parse(source, "test.py").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "python", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "python", DiagnosticSources::default()).await.unwrap();
 
         // Should NOT report print, len, or str as undefined
         assert!(!diagnostics.iter().any(|d| d.message.contains("print")));
@@ -842,7 +1121,7 @@ print(x)
         parser.set_language("python").unwrap();
         let tree = parser.parse(source, "test.py").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "python", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "python", DiagnosticSources::default()).await.unwrap();
 
         // Should NOT report any undefined variables
         assert!(!diagnostics.iter().any(|d| d.message.contains("Undefined name")));
@@ -861,7 +1140,7 @@ def test():
         parser.set_language("python").unwrap();
         let tree = parser.parse(source, "test.py").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "python", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "python", DiagnosticSources::default()).await.unwrap();
 
         // Should detect both undefined variables
         assert!(diagnostics.iter().any(|d| d.message.contains("undefined_var1")));
@@ -883,7 +1162,7 @@ function test() {
         parser.set_language("javascript").unwrap();
         let tree = parser.parse(source, "test.js").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "javascript", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "javascript", DiagnosticSources::default()).await.unwrap();
 
         // Should detect undefined variable
         assert!(diagnostics.iter().any(|d| {
@@ -906,7 +1185,7 @@ function test() {
         parser.set_language("javascript").unwrap();
         let tree = parser.parse(source, "test.js").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "javascript", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "javascript", DiagnosticSources::default()).await.unwrap();
 
         // Should NOT report console, Array, JSON, Promise as undefined
         assert!(!diagnostics.iter().any(|d| d.message.contains("console")));
@@ -931,7 +1210,7 @@ console.log(x);
         parser.set_language("javascript").unwrap();
         let tree = parser.parse(source, "test.js").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "javascript", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "javascript", DiagnosticSources::default()).await.unwrap();
 
         // Should NOT report any undefined variables
         assert!(!diagnostics.iter().any(|d| d.message.contains("Undefined name")));
@@ -952,7 +1231,7 @@ fn test() {
         parser.set_language("rust").unwrap();
         let tree = parser.parse(source, "test.rs").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "rust", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "rust", DiagnosticSources::default()).await.unwrap();
 
         // Should detect undefined variable
         assert!(diagnostics.iter().any(|d| {
@@ -976,7 +1255,7 @@ fn test() {
         parser.set_language("rust").unwrap();
         let tree = parser.parse(source, "test.rs").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "rust", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "rust", DiagnosticSources::default()).await.unwrap();
 
         // Should NOT report println, Vec, Some, Ok as undefined
         assert!(!diagnostics.iter().any(|d| d.message.contains("println")));
@@ -1003,9 +1282,127 @@ fn main() {
         parser.set_language("rust").unwrap();
         let tree = parser.parse(source, "test.rs").unwrap();
 
-        let diagnostics = compute_diagnostics(&tree, source, "rust", None).await.unwrap();
+        let diagnostics = compute_diagnostics(&tree, source, "rust", DiagnosticSources::default()).await.unwrap();
 
         // Should NOT report any undefined variables
         assert!(!diagnostics.iter().any(|d| d.message.contains("Undefined name")));
     }
+
+    #[tokio::test]
+    async fn test_document_report_result_ids() {
+        let provider = DiagnosticProvider::new();
+        let uri = "file:///ws/app.py";
+        let content = "print(undefined_name)\n";
+
+        let first = provider.document_report(uri, content, "python", None, DiagnosticSources::default()).await.unwrap();
+        let DocumentDiagnosticReportKind::Full(full) = first else {
+            panic!("first pull must be a full report");
+        };
+        assert_eq!(full.items.len(), 1);
+        let result_id = full.result_id.unwrap();
+
+        // Same content and the client has the result id: unchanged
+        let second = provider
+            .document_report(uri, content, "python", Some(&result_id), DiagnosticSources::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            second,
+            DocumentDiagnosticReportKind::Unchanged(ref unchanged) if unchanged.result_id == result_id
+        ));
+
+        // Edited content: new full report with a new result id
+        let third = provider
+            .document_report(uri, "x = 1\nprint(x)\n", "python", Some(&result_id), DiagnosticSources::default())
+            .await
+            .unwrap();
+        let DocumentDiagnosticReportKind::Full(full) = third else {
+            panic!("changed content must produce a full report");
+        };
+        assert!(full.items.is_empty());
+        assert_ne!(full.result_id.as_deref(), Some(result_id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_store_feeds_pull_reports() {
+        let provider = DiagnosticProvider::new();
+        let uri = "file:///ws/lib.rs";
+        let content = "fn main() {}\n";
+
+        let pushed = vec![Diagnostic {
+            message: "from push".to_string(),
+            ..Default::default()
+        }];
+        let result_id = provider.store(uri, content, pushed);
+
+        assert_eq!(provider.cached(uri, content).unwrap()[0].message, "from push");
+        assert!(provider.cached(uri, "fn other() {}\n").is_none());
+
+        let report = provider.document_report(uri, content, "rust", None, DiagnosticSources::default()).await.unwrap();
+        assert!(matches!(
+            report,
+            DocumentDiagnosticReportKind::Full(ref full) if full.result_id.as_deref() == Some(result_id.as_str())
+        ));
+
+        provider.invalidate(uri);
+        assert!(provider.cached(uri, content).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_workspace_reports_read_unopened_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.py");
+        std::fs::write(&path, "print(missing)\n").unwrap();
+        let uri = Url::from_file_path(&path).unwrap();
+
+        let provider = DiagnosticProvider::new();
+        let files = vec![uri.clone(), Url::parse("file:///does/not/exist.py").unwrap()];
+
+        // Nothing is analysed on a pull
+        assert!(provider.workspace_reports(&files, &HashMap::new()).is_empty());
+        assert!(provider.update_files(&files, |_| false, DiagnosticSources::default()).await);
+        let reports = provider.workspace_reports(&files, &HashMap::new());
+        assert_eq!(reports.len(), 1);
+        let WorkspaceDocumentDiagnosticReport::Full(full) = &reports[0] else {
+            panic!("first workspace pull must be a full report");
+        };
+        assert_eq!(full.uri, uri);
+        assert_eq!(full.full_document_diagnostic_report.items.len(), 1);
+
+        let previous = HashMap::from([(
+            uri.clone(),
+            full.full_document_diagnostic_report.result_id.clone().unwrap(),
+        )]);
+
+        // Unmodified files are not analysed again
+        assert!(!provider.update_files(&files, |_| false, DiagnosticSources::default()).await);
+        let reports = provider.workspace_reports(&files, &previous);
+        assert!(matches!(reports[0], WorkspaceDocumentDiagnosticReport::Unchanged(_)));
+
+        // Files that are no longer listed are dropped, unless kept
+        let open = "file:///ws/open.py";
+        provider.store(open, "x = 1\n", Vec::new());
+        assert!(provider.update_files(&[], |uri| uri == open, DiagnosticSources::default()).await);
+        assert!(provider.workspace_reports(&files, &HashMap::new()).is_empty());
+        assert!(provider.cached(open, "x = 1\n").is_some());
+    }
+
+    #[test]
+    fn test_update_keeps_result_id_of_unchanged_diagnostics() {
+        let provider = DiagnosticProvider::new();
+        let uri = "file:///ws/app.py";
+        let diagnostic = Diagnostic {
+            message: "unused".to_string(),
+            ..Default::default()
+        };
+        let result_id = provider.store(uri, "x = 1\n", vec![diagnostic.clone()]);
+
+        assert!(!provider.update(uri, "x = 1\n", vec![diagnostic.clone()]));
+        let files = [Url::parse(uri).unwrap()];
+        let reports = provider.workspace_reports(&files, &HashMap::from([(files[0].clone(), result_id)]));
+        assert!(matches!(reports[0], WorkspaceDocumentDiagnosticReport::Unchanged(_)));
+
+        assert!(provider.update(uri, "x = 1\n", Vec::new()));
+        assert!(provider.cached(uri, "x = 1\n").unwrap().is_empty());
+    }
 }
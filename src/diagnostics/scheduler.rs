@@ -0,0 +1,155 @@
+//! Per-document background diagnostics scheduling
+//!
+//! Edits only trigger the cheap syntax pass inline; semantic analysis, external
+//! linters, AI findings and MCP queries run in a background task per document. A new
+//! edit aborts the document's pending or running task, so only the latest version is
+//! analysed and superseded MCP queries are dropped mid-flight.
+
+use dashmap::DashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tower_lsp::lsp_types::Diagnostic;
+
+/// Quiet period after an edit before background diagnostics start
+pub const EDIT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Debounces and cancels background diagnostics runs per document
+#[derive(Debug, Default)]
+pub struct DiagnosticsScheduler {
+    tasks: DashMap<String, JoinHandle<()>>,
+    background: DashMap<String, Vec<Diagnostic>>,
+}
+
+impl DiagnosticsScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `analysis` for `uri` after `delay`, aborting the document's previous run
+    pub fn schedule<F>(&self, uri: &str, delay: Duration, analysis: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            analysis.await;
+        });
+
+        if let Some(previous) = self.tasks.insert(uri.to_string(), handle) {
+            previous.abort();
+        }
+    }
+
+    /// Abort the pending run of a document and forget its results
+    pub fn cancel(&self, uri: &str) {
+        if let Some((_, handle)) = self.tasks.remove(uri) {
+            handle.abort();
+        }
+        self.background.remove(uri);
+    }
+
+    /// Record the background results of a run over `analysed`, unless the document
+    /// has since changed to `current` (`None` once closed); returns whether they were
+    /// kept
+    ///
+    /// Results of a superseded run have ranges for the old content, so they must not
+    /// be shown alongside the next version's syntax errors.
+    pub fn remember_background(
+        &self,
+        uri: &str,
+        analysed: &str,
+        current: Option<&str>,
+        diagnostics: Vec<Diagnostic>,
+    ) -> bool {
+        if current != Some(analysed) {
+            return false;
+        }
+        self.background.insert(uri.to_string(), diagnostics);
+        true
+    }
+
+    /// Latest background results of a document, shown until the next run completes
+    pub fn background(&self, uri: &str) -> Vec<Diagnostic> {
+        self.background.get(uri).map(|d| d.clone()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_newer_edit_supersedes_pending_run() {
+        let scheduler = DiagnosticsScheduler::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        for version in 1..=3 {
+            let runs = runs.clone();
+            scheduler.schedule("file:///a.py", Duration::from_millis(50), async move {
+                runs.fetch_add(version, Ordering::SeqCst);
+            });
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // Only the third version ran
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_running_analysis() {
+        let scheduler = DiagnosticsScheduler::new();
+        let finished = Arc::new(AtomicUsize::new(0));
+
+        let flag = finished.clone();
+        scheduler.schedule("file:///a.py", Duration::ZERO, async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            flag.fetch_add(1, Ordering::SeqCst);
+        });
+        scheduler.remember_background("file:///a.py", "x = 1\n", Some("x = 1\n"), vec![Diagnostic::default()]);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        scheduler.cancel("file:///a.py");
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert_eq!(finished.load(Ordering::SeqCst), 0);
+        assert!(scheduler.background("file:///a.py").is_empty());
+    }
+
+    #[test]
+    fn test_superseded_results_are_not_remembered() {
+        let scheduler = DiagnosticsScheduler::new();
+        let uri = "file:///a.py";
+        let stale = Diagnostic {
+            message: "stale".to_string(),
+            ..Default::default()
+        };
+
+        assert!(scheduler.remember_background(uri, "x = 1\n", Some("x = 1\n"), vec![Diagnostic::default()]));
+
+        // The document was edited, or closed, while the run analysed the old content
+        assert!(!scheduler.remember_background(uri, "x = 1\n", Some("x = 12\n"), vec![stale.clone()]));
+        assert!(!scheduler.remember_background(uri, "x = 1\n", None, vec![stale]));
+        assert_eq!(scheduler.background(uri), vec![Diagnostic::default()]);
+    }
+
+    #[tokio::test]
+    async fn test_documents_are_scheduled_independently() {
+        let scheduler = DiagnosticsScheduler::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        for uri in ["file:///a.py", "file:///b.py"] {
+            let runs = runs.clone();
+            scheduler.schedule(uri, Duration::from_millis(10), async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,1800 @@
+//! Unused symbols, shadowing and unreachable code
+//!
+//! A lightweight scope analysis over the syntax tree. Declarations are bound to the
+//! innermost scope (a block forming the body of a function, loop or arm shares that
+//! construct's scope), and every other identifier resolves to the nearest binding
+//! visible at that point. Bindings nothing resolves to are reported as unused.
+//!
+//! Findings carry [`DiagnosticTag::UNNECESSARY`] so editors render them faded, and
+//! their `data` describes the edit behind the quick fix (see [`usage_fix`]).
+//!
+//! The same analysis is exposed through [`resolve_names`] for refactorings that need
+//! to tell a function's locals from names bound elsewhere.
+
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString, Range, TextEdit,
+};
+use tree_sitter::{Node, Tree};
+
+use super::byte_to_position;
+
+pub const UNUSED_IMPORT: &str = "unused-import";
+pub const UNUSED_VARIABLE: &str = "unused-variable";
+pub const UNUSED_PARAMETER: &str = "unused-parameter";
+pub const SHADOWED_VARIABLE: &str = "shadowed-variable";
+pub const UNREACHABLE_CODE: &str = "unreachable-code";
+
+/// Traits commonly imported only for their methods, which the tree cannot see
+const RUST_TRAIT_IMPORTS: &[&str] = &[
+    "Read", "Write", "BufRead", "Seek", "FromStr", "Hash", "Hasher", "Iterator",
+    "IntoIterator", "DoubleEndedIterator", "ExactSizeIterator", "Context", "Borrow",
+    "BorrowMut", "Deref", "DerefMut", "AsRef", "AsMut", "Display", "Debug", "Future",
+    "Stream", "Sink", "AsyncRead", "AsyncWrite", "AsyncBufRead", "AsyncSeek", "Rng",
+    "Digest", "Parser", "FromIterator", "TryFrom", "TryInto", "Any",
+];
+
+/// Macros that never return
+const RUST_DIVERGING_MACROS: &[&str] = &["panic", "unreachable", "todo", "unimplemented"];
+
+const COMMENT_KINDS: &[&str] = &["comment", "line_comment", "block_comment"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    Import,
+    Variable,
+    Parameter,
+    /// Bound names that are never reported (functions, patterns, loop variables)
+    Other,
+}
+
+#[derive(Debug)]
+struct Scope {
+    parent: Option<usize>,
+    /// Whether unused locals and shadowing are reported (not for module or class scope)
+    reportable: bool,
+    /// Function-like scope, the target of hoisted `var` declarations
+    function: bool,
+    /// Byte range of the node opening the scope
+    start: usize,
+    end: usize,
+}
+
+/// Byte ranges needed to remove an import
+#[derive(Debug, Clone, Copy)]
+struct ImportSite {
+    statement: (usize, usize),
+    item: (usize, usize),
+}
+
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    kind: BindingKind,
+    scope: usize,
+    start: usize,
+    end: usize,
+    /// First byte references can see this binding from
+    visible_from: usize,
+    import: Option<ImportSite>,
+    /// Parameter list this binding belongs to
+    group: Option<usize>,
+    used: bool,
+}
+
+impl Binding {
+    fn definition(&self) -> Definition {
+        Definition {
+            name: self.name.clone(),
+            start: self.start,
+            end: self.end,
+            scope: self.scope,
+            parameter: self.kind == BindingKind::Parameter,
+            import: self.kind == BindingKind::Import,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ParamGroup {
+    params: Vec<usize>,
+    /// Signatures dictated from elsewhere (overrides, trait impls) or stub bodies
+    skip: bool,
+}
+
+struct Reference {
+    name: String,
+    scope: usize,
+    byte: usize,
+}
+
+/// Run the usage checks for a document
+pub fn analyze_usage(tree: &Tree, source: &str, lang: &str) -> Vec<Diagnostic> {
+    if scope_kinds(lang).is_empty() {
+        return Vec::new();
+    }
+
+    let mut collector = Collector::new(source, lang);
+    collector.walk(tree.root_node());
+    collector.resolve();
+
+    let mut diagnostics = collector.report(tree.root_node());
+    diagnostics.extend(unreachable_code(tree.root_node(), source, lang));
+    diagnostics
+}
+
+/// A name bound in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    /// Scope the name is bound in; definitions sharing a scope rebind the same variable
+    pub scope: usize,
+    pub parameter: bool,
+    pub import: bool,
+}
+
+/// A use of a name and the definition it resolves to, if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameReference {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    /// Index into [`Names::definitions`]
+    pub definition: Option<usize>,
+}
+
+/// Definitions and resolved references of a document
+#[derive(Debug, Default)]
+pub struct Names {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<NameReference>,
+}
+
+/// Resolve the names of a document with the scope analysis behind the usage checks
+pub fn resolve_names(tree: &Tree, source: &str, lang: &str) -> Names {
+    if scope_kinds(lang).is_empty() {
+        return Names::default();
+    }
+
+    let mut collector = Collector::new(source, lang);
+    collector.walk(tree.root_node());
+
+    let index = collector.index();
+    let references = collector
+        .references
+        .iter()
+        .map(|reference| {
+            let targets = collector.targets(&index, reference);
+            NameReference {
+                name: reference.name.clone(),
+                start: reference.byte,
+                end: reference.byte + reference.name.len(),
+                definition: (targets.len() == 1).then(|| targets[0]),
+            }
+        })
+        .collect();
+    let definitions = collector.bindings.iter().map(Binding::definition).collect();
+
+    Names { definitions, references }
+}
+
+/// Names visible at `byte`: innermost scope first and, within a scope, the latest
+/// binding first. Each name appears once, shadowed bindings are left out.
+pub fn names_in_scope(tree: &Tree, source: &str, lang: &str, byte: usize) -> Vec<Definition> {
+    if scope_kinds(lang).is_empty() {
+        return Vec::new();
+    }
+
+    let mut collector = Collector::new(source, lang);
+    collector.walk(tree.root_node());
+
+    // Scopes are opened in document order, so the last one around `byte` is innermost
+    let column = |byte: usize| byte - source[..byte].rfind('\n').map_or(0, |i| i + 1);
+    let innermost = collector
+        .scopes
+        .iter()
+        .rposition(|scope| {
+            scope.start <= byte
+                && (byte <= scope.end
+                    // A Python block goes on while blank lines are indented past its header
+                    || lang == "python"
+                        && source[scope.end..byte].trim().is_empty()
+                        && column(byte) > column(scope.start))
+        })
+        .unwrap_or(0);
+
+    let mut seen = HashSet::new();
+    let mut names = Vec::new();
+    for (depth, id) in scope_chain(&collector.scopes, innermost).into_iter().enumerate() {
+        let scope = &collector.scopes[id];
+        // Python class bodies are not visible from their methods
+        if depth > 0 && id != 0 && lang == "python" && !scope.reportable {
+            continue;
+        }
+
+        let mut bindings: Vec<&Binding> = collector
+            .bindings
+            .iter()
+            .filter(|binding| binding.scope == id && !(binding.start <= byte && byte <= binding.end))
+            // Locals once declared, module and class members from anywhere
+            .filter(|binding| binding.visible_from <= byte || !scope.reportable)
+            .collect();
+        // Closest declarations first, then names declared further down
+        bindings.sort_by_key(|binding| match binding.visible_from <= byte {
+            true => (0, byte - binding.visible_from, std::cmp::Reverse(binding.start)),
+            false => (1, binding.visible_from, std::cmp::Reverse(0)),
+        });
+        for binding in bindings {
+            if seen.insert(binding.name.as_str()) {
+                names.push(binding.definition());
+            }
+        }
+    }
+    names
+}
+
+/// Title and edits of the quick fix for a usage finding
+pub fn usage_fix(diagnostic: &Diagnostic) -> Option<(String, Vec<TextEdit>)> {
+    let code = match diagnostic.code.as_ref()? {
+        NumberOrString::String(code) => code.as_str(),
+        NumberOrString::Number(_) => return None,
+    };
+    let data = diagnostic.data.as_ref()?;
+
+    match code {
+        UNUSED_IMPORT => {
+            let range: Range = serde_json::from_value(data.get("remove")?.clone()).ok()?;
+            Some((
+                "Remove unused import".to_string(),
+                vec![TextEdit { range, new_text: String::new() }],
+            ))
+        }
+        UNUSED_VARIABLE | UNUSED_PARAMETER => {
+            let new_name = data.get("rename")?.as_str()?;
+            let title = if new_name == "_" {
+                "Replace with '_'".to_string()
+            } else {
+                format!("Rename to '{}'", new_name)
+            };
+            Some((
+                title,
+                vec![TextEdit { range: diagnostic.range, new_text: new_name.to_string() }],
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Node kinds opening a scope of their own
+fn scope_kinds(lang: &str) -> &'static [&'static str] {
+    match lang {
+        "python" => &[
+            "function_definition", "lambda", "class_definition", "list_comprehension",
+            "set_comprehension", "dictionary_comprehension", "generator_expression",
+        ],
+        "javascript" | "typescript" | "tsx" => &[
+            "function_declaration", "generator_function_declaration", "function_expression",
+            "function", "generator_function", "arrow_function", "method_definition",
+            "for_statement", "for_in_statement", "catch_clause",
+        ],
+        "rust" => &[
+            "function_item", "closure_expression", "for_expression", "while_expression",
+            "if_expression", "while_let_expression", "match_arm", "mod_item",
+        ],
+        "go" => &[
+            "function_declaration", "method_declaration", "func_literal", "if_statement",
+            "for_statement", "expression_switch_statement", "type_switch_statement",
+            "select_statement",
+        ],
+        "java" => &[
+            "class_body", "interface_body", "enum_body", "method_declaration",
+            "constructor_declaration", "lambda_expression", "for_statement",
+            "enhanced_for_statement", "catch_clause", "try_with_resources_statement",
+        ],
+        _ => &[],
+    }
+}
+
+/// Block kinds, which open a scope unless they are the body of a scope node
+fn block_kinds(lang: &str) -> &'static [&'static str] {
+    match lang {
+        "javascript" | "typescript" | "tsx" => &["statement_block"],
+        "rust" | "go" => &["block"],
+        "java" => &["block", "constructor_body"],
+        _ => &[],
+    }
+}
+
+/// Node kinds counted as references to a binding
+fn reference_kinds(lang: &str) -> &'static [&'static str] {
+    match lang {
+        "python" => &["identifier"],
+        "javascript" | "typescript" | "tsx" => {
+            &["identifier", "type_identifier", "shorthand_property_identifier"]
+        }
+        "rust" | "java" => &["identifier", "type_identifier"],
+        "go" => &["identifier", "type_identifier", "package_identifier"],
+        _ => &[],
+    }
+}
+
+fn is_function_scope(kind: &str) -> bool {
+    matches!(
+        kind,
+        "function_definition"
+            | "lambda"
+            | "function_declaration"
+            | "generator_function_declaration"
+            | "function_expression"
+            | "function"
+            | "generator_function"
+            | "arrow_function"
+            | "method_definition"
+            | "function_item"
+            | "closure_expression"
+            | "method_declaration"
+            | "func_literal"
+            | "constructor_declaration"
+            | "lambda_expression"
+    )
+}
+
+fn is_container_scope(kind: &str) -> bool {
+    matches!(kind, "class_definition" | "class_body" | "interface_body" | "enum_body" | "mod_item")
+}
+
+struct Collector<'a> {
+    source: &'a str,
+    lang: &'a str,
+    scopes: Vec<Scope>,
+    stack: Vec<usize>,
+    bindings: Vec<Binding>,
+    references: Vec<Reference>,
+    /// Identifier nodes in declaration position, which are not references
+    declared: HashSet<usize>,
+    groups: Vec<ParamGroup>,
+    /// Names never reported (Python `global`/`nonlocal` and `__all__` entries)
+    ignored: HashSet<String>,
+}
+
+impl<'a> Collector<'a> {
+    fn new(source: &'a str, lang: &'a str) -> Self {
+        Self {
+            source,
+            lang,
+            scopes: vec![Scope { parent: None, reportable: false, function: true, start: 0, end: source.len() }],
+            stack: vec![0],
+            bindings: Vec::new(),
+            references: Vec::new(),
+            declared: HashSet::new(),
+            groups: Vec::new(),
+            ignored: HashSet::new(),
+        }
+    }
+
+    fn text(&self, node: Node) -> &'a str {
+        node.utf8_text(self.source.as_bytes()).unwrap_or("")
+    }
+
+    fn current(&self) -> usize {
+        *self.stack.last().unwrap_or(&0)
+    }
+
+    fn opens_scope(&self, node: Node) -> bool {
+        let scopes = scope_kinds(self.lang);
+        if scopes.contains(&node.kind()) {
+            return node.parent().is_some();
+        }
+        // Branches of an `if` get scopes of their own so they do not nest in each other
+        block_kinds(self.lang).contains(&node.kind())
+            && !node.parent().is_some_and(|parent| {
+                scopes.contains(&parent.kind()) && !matches!(parent.kind(), "if_statement" | "if_expression")
+            })
+    }
+
+    fn walk(&mut self, node: Node) {
+        let outer = self.current();
+        let opens = self.opens_scope(node);
+        if opens {
+            self.scopes.push(Scope {
+                parent: Some(outer),
+                reportable: !is_container_scope(node.kind()),
+                function: is_function_scope(node.kind()),
+                start: node.start_byte(),
+                end: node.end_byte(),
+            });
+            self.stack.push(self.scopes.len() - 1);
+        }
+
+        if self.declare(node, outer) {
+            if reference_kinds(self.lang).contains(&node.kind()) && !self.declared.contains(&node.id()) {
+                self.references.push(Reference {
+                    name: self.text(node).to_string(),
+                    scope: self.current(),
+                    byte: node.start_byte(),
+                });
+            }
+
+            let mut cursor = node.walk();
+            let children: Vec<Node> = node.children(&mut cursor).collect();
+            for child in children {
+                self.walk(child);
+            }
+        }
+
+        if opens {
+            self.stack.pop();
+        }
+    }
+
+    /// Register the declarations made by `node`; returns whether to descend into it
+    fn declare(&mut self, node: Node, outer: usize) -> bool {
+        match self.lang {
+            "python" => self.declare_python(node, outer),
+            "javascript" | "typescript" | "tsx" => self.declare_js(node, outer),
+            "rust" => self.declare_rust(node, outer),
+            "go" => self.declare_go(node, outer),
+            "java" => self.declare_java(node),
+            _ => true,
+        }
+    }
+
+    fn bind(&mut self, ident: Node, kind: BindingKind, scope: usize, visible_from: usize) -> usize {
+        self.declared.insert(ident.id());
+        self.bindings.push(Binding {
+            name: self.text(ident).to_string(),
+            kind,
+            scope,
+            start: ident.start_byte(),
+            end: ident.end_byte(),
+            visible_from,
+            import: None,
+            group: None,
+            used: false,
+        });
+        self.bindings.len() - 1
+    }
+
+    fn bind_import(&mut self, ident: Node, name: &str, statement: Node, item: (usize, usize)) {
+        let index = self.bind(ident, BindingKind::Import, self.current(), 0);
+        let binding = &mut self.bindings[index];
+        binding.name = name.to_string();
+        binding.import = Some(ImportSite {
+            statement: line_extent(self.source, statement.start_byte(), statement.end_byte()),
+            item,
+        });
+    }
+
+    fn bind_param(&mut self, ident: Node, group: usize) {
+        let scope = self.current();
+        let index = self.bind(ident, BindingKind::Parameter, scope, 0);
+        self.bindings[index].group = Some(group);
+        self.groups[group].params.push(index);
+    }
+
+    /// Destructured parameters are never reported, but still count as used positions
+    fn bind_param_pattern(&mut self, pattern: Node, group: usize) {
+        let first = self.bindings.len();
+        self.bind_pattern(pattern, BindingKind::Other, self.current(), 0);
+        self.groups[group].params.extend(first..self.bindings.len());
+    }
+
+    fn new_group(&mut self, skip: bool) -> usize {
+        self.groups.push(ParamGroup { params: Vec::new(), skip });
+        self.groups.len() - 1
+    }
+
+    /// Bind every name introduced by a (possibly destructuring) pattern
+    fn bind_pattern(&mut self, pattern: Node, kind: BindingKind, scope: usize, visible_from: usize) {
+        match pattern.kind() {
+            "identifier" | "shorthand_property_identifier_pattern" | "shorthand_field_identifier" => {
+                let name = self.text(pattern);
+                // Rust patterns name constants and unit variants with capitals
+                if self.lang == "rust" && name.starts_with(|c: char| c.is_uppercase()) {
+                    return;
+                }
+                self.bind(pattern, kind, scope, visible_from);
+            }
+            // Assigning to an element or attribute reads the object
+            "scoped_identifier" | "field_identifier" | "property_identifier" | "subscript" | "attribute"
+            | "member_expression" | "subscript_expression" => {}
+            _ => {
+                let mut cursor = pattern.walk();
+                let children: Vec<(Node, Option<&str>)> = pattern
+                    .children(&mut cursor)
+                    .enumerate()
+                    .map(|(i, child)| (child, pattern.field_name_for_child(i as u32)))
+                    .collect();
+                for (child, field) in children {
+                    // Types, struct paths and default values are references
+                    if matches!(field, Some("type" | "right" | "default_value")) {
+                        continue;
+                    }
+                    self.bind_pattern(child, BindingKind::Other, scope, visible_from);
+                }
+            }
+        }
+    }
+
+    fn mark_field(&mut self, node: Node, field: &str) {
+        if let Some(child) = node.child_by_field_name(field) {
+            self.declared.insert(child.id());
+        }
+    }
+
+    fn declare_python(&mut self, node: Node, outer: usize) -> bool {
+        let scope = self.current();
+        match node.kind() {
+            "function_definition" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.bind(name, BindingKind::Other, outer, node.start_byte());
+                }
+                let stub = node.child_by_field_name("body").is_none_or(|body| self.is_stub_body(body));
+                if let Some(params) = node.child_by_field_name("parameters") {
+                    let group = self.new_group(stub || overrides_base_method(node));
+                    self.python_params(params, group);
+                }
+            }
+            "lambda" => {
+                if let Some(params) = node.child_by_field_name("parameters") {
+                    let group = self.new_group(false);
+                    self.python_params(params, group);
+                }
+            }
+            "class_definition" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.bind(name, BindingKind::Other, outer, node.start_byte());
+                }
+            }
+            "assignment" => {
+                let Some(left) = node.child_by_field_name("left") else {
+                    return true;
+                };
+                if self.text(left) == "__all__" {
+                    if let Some(right) = node.child_by_field_name("right") {
+                        self.ignore_strings(right);
+                    }
+                }
+                // A bare annotation (`x: int`) declares without assigning
+                let kind = if left.kind() == "identifier" && node.child_by_field_name("right").is_some() {
+                    BindingKind::Variable
+                } else {
+                    BindingKind::Other
+                };
+                self.bind_pattern(left, kind, scope, node.end_byte());
+            }
+            "for_statement" | "for_in_clause" => {
+                if let Some(left) = node.child_by_field_name("left") {
+                    self.bind_pattern(left, BindingKind::Other, scope, after_field(node, "right", left));
+                }
+            }
+            "named_expression" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.bind(name, BindingKind::Other, scope, node.end_byte());
+                }
+            }
+            "as_pattern_target" => {
+                self.bind_pattern(node, BindingKind::Other, scope, node.end_byte());
+                return false;
+            }
+            "import_statement" | "import_from_statement" => {
+                self.python_imports(node);
+                return false;
+            }
+            "global_statement" | "nonlocal_statement" => {
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor) {
+                    self.ignored.insert(self.text(child).to_string());
+                }
+                return false;
+            }
+            "augmented_assignment"
+                if node.child_by_field_name("left").is_some_and(|left| self.text(left) == "__all__") =>
+            {
+                self.ignore_strings(node);
+            }
+            // `__all__.extend([...])`
+            "call" if node.child_by_field_name("function").is_some_and(|f| self.text(f).starts_with("__all__.")) => {
+                self.ignore_strings(node);
+            }
+            "attribute" => self.mark_field(node, "attribute"),
+            "keyword_argument" => self.mark_field(node, "name"),
+            _ => {}
+        }
+        true
+    }
+
+    fn python_params(&mut self, params: Node, group: usize) {
+        let mut cursor = params.walk();
+        let children: Vec<Node> = params.named_children(&mut cursor).collect();
+        for param in children {
+            let ident = match param.kind() {
+                "identifier" => Some(param),
+                "default_parameter" | "typed_default_parameter" => param.child_by_field_name("name"),
+                _ => first_named_of_kind(param, "identifier"),
+            };
+            match ident {
+                Some(ident) if ident.kind() == "identifier" => self.bind_param(ident, group),
+                _ => {}
+            }
+        }
+    }
+
+    fn python_imports(&mut self, node: Node) {
+        if let Some(module) = node.child_by_field_name("module_name") {
+            if self.text(module) == "__future__" {
+                return;
+            }
+        }
+
+        let mut cursor = node.walk();
+        let names: Vec<Node> = node.children_by_field_name("name", &mut cursor).collect();
+        for item in names {
+            let (ident, name) = match item.kind() {
+                "aliased_import" => match item.child_by_field_name("alias") {
+                    Some(alias) => (alias, self.text(alias)),
+                    None => continue,
+                },
+                // `import os.path` binds `os`
+                _ => (item, self.text(item).split('.').next().unwrap_or("")),
+            };
+            let removal = list_item_extent(self.source, item);
+            self.bind_import(ident, name, node, removal);
+        }
+    }
+
+    fn ignore_strings(&mut self, node: Node) {
+        if node.kind() == "string" {
+            let name = self.text(node).trim_matches(|c| c == '"' || c == '\'');
+            self.ignored.insert(name.to_string());
+            return;
+        }
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            self.ignore_strings(child);
+        }
+    }
+
+    fn declare_js(&mut self, node: Node, outer: usize) -> bool {
+        let scope = self.current();
+        match node.kind() {
+            "function_declaration" | "generator_function_declaration" | "class_declaration" => {
+                // Declarations are hoisted to the top of the enclosing scope
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.bind(name, BindingKind::Other, outer, 0);
+                }
+                self.js_function(node);
+            }
+            "function_expression" | "function" | "generator_function" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.bind(name, BindingKind::Other, scope, 0);
+                }
+                self.js_function(node);
+            }
+            "arrow_function" | "method_definition" => self.js_function(node),
+            "variable_declarator" => {
+                let Some(name) = node.child_by_field_name("name") else {
+                    return true;
+                };
+                let declaration = node.parent().unwrap_or(node);
+                let target = if declaration.kind() == "variable_declaration" {
+                    self.function_scope()
+                } else {
+                    scope
+                };
+                let kind = if name.kind() == "identifier" { BindingKind::Variable } else { BindingKind::Other };
+                self.bind_pattern(name, kind, target, declaration.end_byte());
+            }
+            "catch_clause" => {
+                if let Some(param) = node.child_by_field_name("parameter") {
+                    self.bind_pattern(param, BindingKind::Other, scope, 0);
+                }
+            }
+            "for_in_statement" => {
+                if let Some(left) = node.child_by_field_name("left") {
+                    self.bind_pattern(left, BindingKind::Other, scope, after_field(node, "right", left));
+                }
+            }
+            "import_statement" => {
+                self.js_imports(node);
+                return false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn function_scope(&self) -> usize {
+        self.stack
+            .iter()
+            .rev()
+            .copied()
+            .find(|&id| self.scopes[id].function)
+            .unwrap_or(0)
+    }
+
+    fn js_function(&mut self, node: Node) {
+        let stub = node.child_by_field_name("body").is_none_or(|body| body.named_child_count() == 0);
+        let group = self.new_group(stub || overrides_base_method(node));
+
+        if let Some(param) = node.child_by_field_name("parameter") {
+            self.bind_param(param, group);
+            return;
+        }
+        let Some(params) = node.child_by_field_name("parameters") else {
+            return;
+        };
+
+        let mut cursor = params.walk();
+        let children: Vec<Node> = params.named_children(&mut cursor).collect();
+        for param in children {
+            // TypeScript parameter properties are fields, not locals
+            if has_child_of_kind(param, &["accessibility_modifier", "readonly"]) {
+                continue;
+            }
+            let pattern = match param.kind() {
+                "required_parameter" | "optional_parameter" => param.child_by_field_name("pattern"),
+                "assignment_pattern" => param.child_by_field_name("left"),
+                "rest_pattern" => param.named_child(0),
+                _ => Some(param),
+            };
+            let Some(pattern) = pattern else { continue };
+            let pattern = if pattern.kind() == "rest_pattern" { pattern.named_child(0).unwrap_or(pattern) } else { pattern };
+            match pattern.kind() {
+                "identifier" => self.bind_param(pattern, group),
+                "this" | "comment" => {}
+                _ => self.bind_param_pattern(pattern, group),
+            }
+        }
+    }
+
+    fn js_imports(&mut self, node: Node) {
+        let Some(clause) = first_named_of_kind(node, "import_clause") else {
+            return;
+        };
+
+        let mut cursor = clause.walk();
+        let children: Vec<Node> = clause.named_children(&mut cursor).collect();
+        for child in children {
+            match child.kind() {
+                "identifier" => {
+                    let removal = list_item_extent(self.source, child);
+                    self.bind_import(child, self.text(child), node, removal);
+                }
+                "namespace_import" => {
+                    if let Some(ident) = first_named_of_kind(child, "identifier") {
+                        let removal = list_item_extent(self.source, child);
+                        self.bind_import(ident, self.text(ident), node, removal);
+                    }
+                }
+                "named_imports" => {
+                    let mut specifiers = child.walk();
+                    let specifiers: Vec<Node> = child.named_children(&mut specifiers).collect();
+                    for specifier in specifiers.into_iter().filter(|s| s.kind() == "import_specifier") {
+                        let ident = specifier
+                            .child_by_field_name("alias")
+                            .or_else(|| specifier.child_by_field_name("name"));
+                        if let Some(ident) = ident {
+                            let removal = list_item_extent(self.source, specifier);
+                            self.bind_import(ident, self.text(ident), node, removal);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_rust(&mut self, node: Node, outer: usize) -> bool {
+        let scope = self.current();
+        match node.kind() {
+            "function_item" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.bind(name, BindingKind::Other, outer, 0);
+                }
+                // Trait impls and trait defaults must keep the trait's signature
+                let in_trait = node.parent().and_then(|list| list.parent()).is_some_and(|owner| {
+                    owner.kind() == "trait_item"
+                        || (owner.kind() == "impl_item" && owner.child_by_field_name("trait").is_some())
+                });
+                let stub = in_trait || node.child_by_field_name("body").is_none_or(|body| self.is_stub_body(body));
+                if let Some(params) = node.child_by_field_name("parameters") {
+                    let group = self.new_group(stub);
+                    self.rust_params(params, group);
+                }
+            }
+            "function_signature_item" | "extern_crate_declaration" => return false,
+            "closure_expression" => {
+                if let Some(params) = node.child_by_field_name("parameters") {
+                    let group = self.new_group(false);
+                    self.rust_params(params, group);
+                }
+            }
+            "let_declaration" => {
+                if let Some(pattern) = node.child_by_field_name("pattern") {
+                    let kind = if pattern.kind() == "identifier" { BindingKind::Variable } else { BindingKind::Other };
+                    self.bind_pattern(pattern, kind, scope, node.end_byte());
+                }
+            }
+            "for_expression" | "let_condition" | "if_let_expression" | "while_let_expression" | "match_arm" => {
+                if let Some(pattern) = node.child_by_field_name("pattern") {
+                    self.bind_pattern(pattern, BindingKind::Other, scope, after_field(node, "value", pattern));
+                }
+            }
+            "use_declaration" => {
+                if !has_child_of_kind(node, &["visibility_modifier"]) {
+                    if let Some(argument) = node.child_by_field_name("argument") {
+                        self.rust_use(argument, node);
+                    }
+                }
+                return false;
+            }
+            "macro_invocation" => self.rust_format_captures(node),
+            _ => {}
+        }
+        true
+    }
+
+    fn rust_params(&mut self, params: Node, group: usize) {
+        let mut cursor = params.walk();
+        let children: Vec<Node> = params.named_children(&mut cursor).collect();
+        for param in children {
+            let pattern = match param.kind() {
+                "parameter" => param.child_by_field_name("pattern"),
+                "identifier" => Some(param),
+                _ => None,
+            };
+            match pattern {
+                Some(ident) if ident.kind() == "identifier" => self.bind_param(ident, group),
+                Some(pattern) => self.bind_param_pattern(pattern, group),
+                None => {}
+            }
+        }
+    }
+
+    fn rust_use(&mut self, node: Node, statement: Node) {
+        let ident = match node.kind() {
+            "identifier" => Some(node),
+            "scoped_identifier" => node.child_by_field_name("name"),
+            "use_as_clause" => node.child_by_field_name("alias"),
+            "scoped_use_list" => {
+                if let Some(list) = node.child_by_field_name("list") {
+                    self.rust_use(list, statement);
+                }
+                None
+            }
+            "use_list" => {
+                let mut cursor = node.walk();
+                let items: Vec<Node> = node.named_children(&mut cursor).collect();
+                for item in items {
+                    self.rust_use(item, statement);
+                }
+                None
+            }
+            _ => None,
+        };
+
+        let Some(ident) = ident else { return };
+        let name = self.text(ident);
+        if name == "_" || name == "self" {
+            return;
+        }
+        let removal = if node.parent().is_some_and(|p| p.kind() == "use_list") {
+            list_item_extent(self.source, node)
+        } else {
+            line_extent(self.source, statement.start_byte(), statement.end_byte())
+        };
+        self.bind_import(ident, name, statement, removal);
+    }
+
+    /// Names captured by format strings (`println!("{name}")`)
+    fn rust_format_captures(&mut self, node: Node) {
+        let scope = self.current();
+        let mut stack = vec![node];
+        while let Some(current) = stack.pop() {
+            if current.kind() == "string_literal" {
+                let text = self.text(current);
+                for (offset, capture) in format_captures(text) {
+                    self.references.push(Reference {
+                        name: capture.to_string(),
+                        scope,
+                        byte: current.start_byte() + offset,
+                    });
+                }
+                continue;
+            }
+            let mut cursor = current.walk();
+            stack.extend(current.named_children(&mut cursor));
+        }
+    }
+
+    fn declare_go(&mut self, node: Node, outer: usize) -> bool {
+        let scope = self.current();
+        match node.kind() {
+            "function_declaration" | "func_literal" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.bind(name, BindingKind::Other, outer, 0);
+                }
+                let stub = node.child_by_field_name("body").is_none_or(|body| body.named_child_count() == 0);
+                if let Some(params) = node.child_by_field_name("parameters") {
+                    let group = self.new_group(stub);
+                    self.go_params(params, Some(group));
+                }
+                if let Some(result) = node.child_by_field_name("result") {
+                    self.go_params(result, None);
+                }
+            }
+            "method_declaration" => {
+                // Methods usually satisfy an interface, whose signature they must keep
+                self.mark_field(node, "name");
+                for field in ["receiver", "parameters", "result"] {
+                    if let Some(list) = node.child_by_field_name(field) {
+                        self.go_params(list, None);
+                    }
+                }
+            }
+            "short_var_declaration" => {
+                if let Some(left) = node.child_by_field_name("left") {
+                    self.go_declare_list(left, scope, node.end_byte());
+                }
+            }
+            "var_spec" => {
+                let mut cursor = node.walk();
+                let names: Vec<Node> = node.children_by_field_name("name", &mut cursor).collect();
+                for name in names {
+                    if self.text(name) != "_" {
+                        self.bind(name, BindingKind::Variable, scope, node.end_byte());
+                    }
+                }
+            }
+            "const_spec" => {
+                let mut cursor = node.walk();
+                let names: Vec<Node> = node.children_by_field_name("name", &mut cursor).collect();
+                for name in names {
+                    self.bind(name, BindingKind::Other, scope, 0);
+                }
+            }
+            "range_clause" if has_child_of_kind(node, &[":="]) => {
+                if let Some(left) = node.child_by_field_name("left") {
+                    self.bind_pattern(left, BindingKind::Other, scope, after_field(node, "right", left));
+                }
+            }
+            "type_switch_statement" => {
+                if let Some(alias) = node.child_by_field_name("alias") {
+                    self.bind_pattern(alias, BindingKind::Other, scope, alias.end_byte());
+                }
+            }
+            "assignment_statement" => {
+                // Assigning to a variable does not use it
+                if let Some(left) = node.child_by_field_name("left") {
+                    let mut cursor = left.walk();
+                    let targets: Vec<Node> = left.named_children(&mut cursor).collect();
+                    for target in targets.into_iter().filter(|t| t.kind() == "identifier") {
+                        if has_child_of_kind(node, &["="]) {
+                            self.declared.insert(target.id());
+                        }
+                    }
+                }
+            }
+            "import_declaration" => {
+                self.go_imports(node);
+                return false;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn go_params(&mut self, list: Node, group: Option<usize>) {
+        let mut cursor = list.walk();
+        let params: Vec<Node> = list.named_children(&mut cursor).collect();
+        for param in params {
+            let mut names = param.walk();
+            let names: Vec<Node> = param.children_by_field_name("name", &mut names).collect();
+            for name in names {
+                match group {
+                    Some(group) if self.text(name) != "_" => self.bind_param(name, group),
+                    _ => {
+                        self.bind(name, BindingKind::Other, self.current(), 0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `:=` declares the names not yet bound in the scope and assigns the others
+    fn go_declare_list(&mut self, left: Node, scope: usize, visible_from: usize) {
+        let mut cursor = left.walk();
+        let names: Vec<Node> = left.named_children(&mut cursor).collect();
+        for name in names.into_iter().filter(|n| n.kind() == "identifier") {
+            let text = self.text(name);
+            let redeclared = self.bindings.iter().any(|b| b.scope == scope && b.name == text);
+            if text == "_" || redeclared {
+                self.declared.insert(name.id());
+            } else {
+                self.bind(name, BindingKind::Variable, scope, visible_from);
+            }
+        }
+    }
+
+    fn go_imports(&mut self, node: Node) {
+        let mut specs = Vec::new();
+        let mut stack = vec![node];
+        while let Some(current) = stack.pop() {
+            if current.kind() == "import_spec" {
+                specs.push(current);
+            } else {
+                let mut cursor = current.walk();
+                stack.extend(current.named_children(&mut cursor));
+            }
+        }
+
+        let single = specs.len() == 1;
+        for spec in specs {
+            let Some(path) = spec.child_by_field_name("path") else { continue };
+            let (ident, name) = match spec.child_by_field_name("name") {
+                Some(alias) => (alias, self.text(alias).to_string()),
+                None => match go_package_name(self.text(path)) {
+                    Some(name) => (path, name),
+                    None => continue,
+                },
+            };
+            if name == "_" || name == "." {
+                continue;
+            }
+            let removal = if single {
+                line_extent(self.source, node.start_byte(), node.end_byte())
+            } else {
+                line_extent(self.source, spec.start_byte(), spec.end_byte())
+            };
+            self.bind_import(ident, &name, node, removal);
+        }
+    }
+
+    fn declare_java(&mut self, node: Node) -> bool {
+        let scope = self.current();
+        match node.kind() {
+            "method_declaration" | "constructor_declaration" => {
+                self.mark_field(node, "name");
+                let overrides = node.children(&mut node.walk()).any(|child| {
+                    child.kind() == "modifiers"
+                        && self.text(child).split_whitespace().any(|m| {
+                            matches!(m, "@Override" | "abstract" | "native")
+                        })
+                });
+                let in_interface = node.parent().is_some_and(|p| p.kind() == "interface_body");
+                let stub = node.child_by_field_name("body").is_none_or(|body| body.named_child_count() == 0);
+                if let Some(params) = node.child_by_field_name("parameters") {
+                    let group = self.new_group(overrides || in_interface || stub);
+                    self.java_params(params, group);
+                }
+            }
+            "lambda_expression" => {
+                let group = self.new_group(false);
+                if let Some(params) = node.child_by_field_name("parameters") {
+                    match params.kind() {
+                        "identifier" => self.bind_param(params, group),
+                        "inferred_parameters" => {
+                            let mut cursor = params.walk();
+                            let names: Vec<Node> = params.named_children(&mut cursor).collect();
+                            for name in names {
+                                self.bind_param(name, group);
+                            }
+                        }
+                        _ => self.java_params(params, group),
+                    }
+                }
+            }
+            "local_variable_declaration" | "field_declaration" => {
+                let kind = if node.kind() == "field_declaration" { BindingKind::Other } else { BindingKind::Variable };
+                let mut cursor = node.walk();
+                let declarators: Vec<Node> = node.children_by_field_name("declarator", &mut cursor).collect();
+                for declarator in declarators {
+                    if let Some(name) = declarator.child_by_field_name("name") {
+                        self.bind(name, kind, scope, node.end_byte());
+                    }
+                }
+            }
+            "enhanced_for_statement" | "catch_formal_parameter" | "resource" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    self.bind(name, BindingKind::Other, scope, 0);
+                }
+            }
+            "class_declaration" | "interface_declaration" | "enum_declaration" => self.mark_field(node, "name"),
+            "field_access" => self.mark_field(node, "field"),
+            "import_declaration" => {
+                self.java_import(node);
+                return false;
+            }
+            "package_declaration" => return false,
+            _ => {}
+        }
+        true
+    }
+
+    fn java_params(&mut self, params: Node, group: usize) {
+        let mut cursor = params.walk();
+        let children: Vec<Node> = params.named_children(&mut cursor).collect();
+        for param in children {
+            let name = match param.kind() {
+                "formal_parameter" => param.child_by_field_name("name"),
+                "spread_parameter" => first_named_of_kind(param, "variable_declarator")
+                    .and_then(|declarator| declarator.child_by_field_name("name")),
+                _ => None,
+            };
+            if let Some(name) = name {
+                self.bind_param(name, group);
+            }
+        }
+    }
+
+    fn java_import(&mut self, node: Node) {
+        if has_child_of_kind(node, &["asterisk"]) {
+            return;
+        }
+        let mut cursor = node.walk();
+        let path = node
+            .named_children(&mut cursor)
+            .find(|child| matches!(child.kind(), "scoped_identifier" | "identifier"));
+        let Some(path) = path else { return };
+        let ident = path.child_by_field_name("name").unwrap_or(path);
+        let removal = line_extent(self.source, node.start_byte(), node.end_byte());
+        self.bind_import(ident, self.text(ident), node, removal);
+    }
+
+    /// Bodies that only hold `pass`, `...`, a docstring or a `todo!()`-like placeholder
+    fn is_stub_body(&self, body: Node) -> bool {
+        let mut cursor = body.walk();
+        let statements: Vec<Node> = body
+            .named_children(&mut cursor)
+            .filter(|s| !COMMENT_KINDS.contains(&s.kind()))
+            .collect();
+
+        statements.iter().all(|statement| match statement.kind() {
+            "pass_statement" | "raise_statement" => true,
+            "expression_statement" => statement
+                .named_child(0)
+                .is_some_and(|expr| match expr.kind() {
+                    "string" | "ellipsis" => true,
+                    "macro_invocation" => self.is_diverging_macro(expr),
+                    _ => false,
+                }),
+            "macro_invocation" => self.is_diverging_macro(*statement),
+            _ => false,
+        })
+    }
+
+    fn is_diverging_macro(&self, node: Node) -> bool {
+        node.child_by_field_name("macro")
+            .is_some_and(|name| RUST_DIVERGING_MACROS.contains(&self.text(name)))
+    }
+
+    /// Bindings of each scope by name
+    fn index(&self) -> HashMap<(usize, &str), Vec<usize>> {
+        let mut index: HashMap<(usize, &str), Vec<usize>> = HashMap::new();
+        for (i, binding) in self.bindings.iter().enumerate() {
+            index.entry((binding.scope, binding.name.as_str())).or_default().push(i);
+        }
+        index
+    }
+
+    /// Bindings `reference` may resolve to: the nearest one visible at the reference,
+    /// or every binding of the name in the nearest scope declaring it when none is
+    /// visible yet (hoisted or later-defined names, like functions and module globals)
+    fn targets(&self, index: &HashMap<(usize, &str), Vec<usize>>, reference: &Reference) -> Vec<usize> {
+        let chain = scope_chain(&self.scopes, reference.scope);
+        let visible = chain.iter().find_map(|&scope| {
+            index.get(&(scope, reference.name.as_str()))?.iter().copied()
+                .filter(|&i| self.bindings[i].visible_from <= reference.byte)
+                .max_by_key(|&i| self.bindings[i].visible_from)
+        });
+
+        match visible {
+            Some(i) => vec![i],
+            None => chain
+                .iter()
+                .find_map(|&scope| index.get(&(scope, reference.name.as_str())))
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Mark every binding some reference resolves to as used
+    fn resolve(&mut self) {
+        let index = self.index();
+
+        let mut used = vec![false; self.bindings.len()];
+        for reference in &self.references {
+            for i in self.targets(&index, reference) {
+                used[i] = true;
+            }
+        }
+
+        // An import redefined in the same scope (a fallback in `except ImportError`)
+        // is used when any of its definitions is
+        for (i, binding) in self.bindings.iter().enumerate() {
+            if binding.kind == BindingKind::Import && !used[i] {
+                used[i] = index[&(binding.scope, binding.name.as_str())].iter().any(|&j| used[j]);
+            }
+        }
+
+        for (binding, used) in self.bindings.iter_mut().zip(used) {
+            binding.used = used;
+        }
+    }
+
+    fn report(&self, root: Node) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        diagnostics.extend(self.unused_imports(root));
+        diagnostics.extend(self.unused_variables());
+        diagnostics.extend(self.unused_parameters());
+        diagnostics.extend(self.shadowed_variables());
+        diagnostics
+    }
+
+    fn unused_imports(&self, root: Node) -> Vec<Diagnostic> {
+        let has_jsx = self.lang != "python" && contains_kind(root, &["jsx_element", "jsx_self_closing_element"]);
+        let is_unused = |binding: &Binding| {
+            let implicit = (has_jsx && binding.name == "React")
+                || (self.lang == "rust" && is_rust_trait_import(&binding.name));
+            binding.kind == BindingKind::Import && !binding.used && !implicit && !self.ignored.contains(&binding.name)
+        };
+
+        // Imports of a statement that all go unused remove the whole statement
+        let mut statement_used: HashMap<(usize, usize), bool> = HashMap::new();
+        for binding in &self.bindings {
+            if let Some(site) = binding.import {
+                *statement_used.entry(site.statement).or_default() |= !is_unused(binding);
+            }
+        }
+
+        self.bindings
+            .iter()
+            .filter(|binding| is_unused(binding))
+            .filter_map(|binding| {
+                let site = binding.import?;
+                let (start, end) = if statement_used[&site.statement] { site.item } else { site.statement };
+                let remove = self.range(start, end);
+                Some(self.finding(
+                    binding,
+                    DiagnosticSeverity::WARNING,
+                    UNUSED_IMPORT,
+                    format!("Unused import '{}'", binding.name),
+                    Some(json!({ "remove": remove })),
+                ))
+            })
+            .collect()
+    }
+
+    fn unused_variables(&self) -> Vec<Diagnostic> {
+        // Rebinding a name counts as the same variable, so report a name once per
+        // scope and only when none of its bindings is read
+        let mut by_name: HashMap<(usize, &str), Vec<&Binding>> = HashMap::new();
+        for binding in &self.bindings {
+            if self.scopes[binding.scope].reportable {
+                by_name.entry((binding.scope, binding.name.as_str())).or_default().push(binding);
+            }
+        }
+
+        let mut unused: Vec<&Binding> = by_name
+            .into_iter()
+            .filter(|((_, name), bindings)| !self.is_exempt(name) && bindings.iter().all(|b| !b.used))
+            .filter_map(|(_, bindings)| {
+                bindings.into_iter().filter(|b| b.kind == BindingKind::Variable).min_by_key(|b| b.start)
+            })
+            .collect();
+        unused.sort_by_key(|b| b.start);
+
+        unused
+            .into_iter()
+            .map(|binding| {
+                // `_x := ...` is still an unused variable in Go
+                let data = (self.lang != "go").then(|| json!({ "rename": format!("_{}", binding.name) }));
+                self.finding(
+                    binding,
+                    DiagnosticSeverity::WARNING,
+                    UNUSED_VARIABLE,
+                    format!("Unused variable '{}'", binding.name),
+                    data,
+                )
+            })
+            .collect()
+    }
+
+    fn unused_parameters(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for group in self.groups.iter().filter(|g| !g.skip) {
+            // Parameters before a used one are required by position
+            let last_used = group.params.iter().rposition(|&i| self.bindings[i].used);
+            let trailing = match last_used {
+                Some(position) => &group.params[position + 1..],
+                None => &group.params[..],
+            };
+
+            for &i in trailing {
+                let binding = &self.bindings[i];
+                if binding.kind != BindingKind::Parameter
+                    || self.is_exempt(&binding.name)
+                    || matches!(binding.name.as_str(), "self" | "cls" | "this")
+                {
+                    continue;
+                }
+                let rename = if self.lang == "go" { "_".to_string() } else { format!("_{}", binding.name) };
+                diagnostics.push(self.finding(
+                    binding,
+                    DiagnosticSeverity::HINT,
+                    UNUSED_PARAMETER,
+                    format!("Unused parameter '{}'", binding.name),
+                    Some(json!({ "rename": rename })),
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    fn shadowed_variables(&self) -> Vec<Diagnostic> {
+        let is_local = |b: &Binding| matches!(b.kind, BindingKind::Variable | BindingKind::Parameter);
+
+        self.bindings
+            .iter()
+            .filter(|b| is_local(b) && self.scopes[b.scope].reportable && !self.is_exempt(&b.name))
+            // Go rebinds `err` and `ok` in nearly every nested statement
+            .filter(|b| !(self.lang == "go" && matches!(b.name.as_str(), "err" | "ok")))
+            // Rebinding from the outer value (`let x = x.clone()`) is deliberate
+            .filter(|inner| {
+                !self.references.iter().any(|r| {
+                    r.name == inner.name && r.byte > inner.start && r.byte < inner.visible_from
+                })
+            })
+            .filter(|inner| {
+                let outer_scopes = &scope_chain(&self.scopes, inner.scope)[1..];
+                self.bindings.iter().any(|outer| {
+                    is_local(outer)
+                        && outer.name == inner.name
+                        && outer.visible_from <= inner.start
+                        && outer_scopes.contains(&outer.scope)
+                        && self.scopes[outer.scope].reportable
+                })
+            })
+            .map(|binding| Diagnostic {
+                tags: None,
+                ..self.finding(
+                    binding,
+                    DiagnosticSeverity::HINT,
+                    SHADOWED_VARIABLE,
+                    format!("'{}' shadows a variable from an outer scope", binding.name),
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn is_exempt(&self, name: &str) -> bool {
+        name.starts_with('_') || self.ignored.contains(name)
+    }
+
+    fn range(&self, start: usize, end: usize) -> Range {
+        Range {
+            start: byte_to_position(self.source, start),
+            end: byte_to_position(self.source, end),
+        }
+    }
+
+    fn finding(
+        &self,
+        binding: &Binding,
+        severity: DiagnosticSeverity,
+        code: &str,
+        message: String,
+        data: Option<Value>,
+    ) -> Diagnostic {
+        Diagnostic {
+            range: self.range(binding.start, binding.end),
+            severity: Some(severity),
+            code: Some(NumberOrString::String(code.to_string())),
+            code_description: None,
+            source: Some("universal-lsp".to_string()),
+            message,
+            related_information: None,
+            tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+            data,
+        }
+    }
+}
+
+/// End of the `field` child of `node` (the iterated or matched value), which the
+/// names bound by `pattern` only become visible after
+fn after_field(node: Node, field: &str, pattern: Node) -> usize {
+    node.child_by_field_name(field).map_or(pattern.end_byte(), |value| value.end_byte().max(pattern.end_byte()))
+}
+
+/// `scope` followed by its enclosing scopes
+fn scope_chain(scopes: &[Scope], scope: usize) -> Vec<usize> {
+    let mut chain = vec![scope];
+    let mut current = scopes[scope].parent;
+    while let Some(id) = current {
+        chain.push(id);
+        current = scopes[id].parent;
+    }
+    chain
+}
+
+/// Methods of a class deriving from another, whose signatures usually come from the base
+fn overrides_base_method(function: Node) -> bool {
+    let Some(class) = function.parent().and_then(|body| body.parent()) else {
+        return false;
+    };
+    match class.kind() {
+        "class_definition" => class
+            .child_by_field_name("superclasses")
+            .is_some_and(|bases| bases.named_child_count() > 0),
+        "class_declaration" | "class" => has_child_of_kind(class, &["class_heritage"]),
+        _ => false,
+    }
+}
+
+fn is_rust_trait_import(name: &str) -> bool {
+    RUST_TRAIT_IMPORTS.contains(&name) || (name.ends_with("Ext") && name.starts_with(|c: char| c.is_uppercase()))
+}
+
+/// Package name of a Go import path, when it can be inferred from the path
+fn go_package_name(path: &str) -> Option<String> {
+    let path = path.trim_matches(|c| c == '"' || c == '`');
+    let mut segments = path.rsplit('/');
+    let mut name = segments.next()?;
+    // Major version suffixes (`example.com/mod/v2`) are not part of the name
+    if name.len() > 1 && name.starts_with('v') && name[1..].chars().all(|c| c.is_ascii_digit()) {
+        name = segments.next()?;
+    }
+    let plain = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    plain.then(|| name.to_string())
+}
+
+/// `{name}` and `{name:?}` captures of a format string, with their byte offsets
+fn format_captures(literal: &str) -> Vec<(usize, &str)> {
+    let mut captures = Vec::new();
+    let bytes = literal.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'{' {
+            if bytes.get(i + 1) == Some(&b'{') {
+                i += 2;
+                continue;
+            }
+            let start = i + 1;
+            let len = literal[start..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(literal.len() - start);
+            let name = &literal[start..start + len];
+            if name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                captures.push((start, name));
+            }
+            i = start + len;
+        } else {
+            i += 1;
+        }
+    }
+    captures
+}
+
+fn first_named_of_kind<'t>(node: Node<'t>, kind: &str) -> Option<Node<'t>> {
+    let mut cursor = node.walk();
+    let found = node.named_children(&mut cursor).find(|child| child.kind() == kind);
+    found
+}
+
+fn has_child_of_kind(node: Node, kinds: &[&str]) -> bool {
+    let mut cursor = node.walk();
+    let found = node.children(&mut cursor).any(|child| kinds.contains(&child.kind()));
+    found
+}
+
+fn contains_kind(node: Node, kinds: &[&str]) -> bool {
+    if kinds.contains(&node.kind()) {
+        return true;
+    }
+    let mut cursor = node.walk();
+    let found = node.children(&mut cursor).any(|child| contains_kind(child, kinds));
+    found
+}
+
+/// Extent of an item in a comma separated list, including one adjacent comma
+fn list_item_extent(source: &str, item: Node) -> (usize, usize) {
+    if let Some(next) = item.next_sibling().filter(|n| n.kind() == ",") {
+        let end = next.next_sibling().map_or(next.end_byte(), |n| n.start_byte());
+        return (item.start_byte(), end);
+    }
+    if let Some(previous) = item.prev_sibling().filter(|n| n.kind() == ",") {
+        let start = previous.prev_sibling().map_or(previous.start_byte(), |n| n.end_byte());
+        return (start, item.end_byte());
+    }
+    line_extent(source, item.start_byte(), item.end_byte())
+}
+
+/// Widen a span to whole lines when nothing else shares them
+fn line_extent(source: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[end..].find('\n').map_or(source.len(), |i| end + i + 1);
+
+    if source[line_start..start].trim().is_empty() && source[end..line_end].trim().is_empty() {
+        (line_start, line_end)
+    } else {
+        (start, end)
+    }
+}
+
+/// Statements following a `return`, `raise`/`throw`, `break`, `continue` or diverging call
+fn unreachable_code(root: Node, source: &str, lang: &str) -> Vec<Diagnostic> {
+    let blocks: &[&str] = match lang {
+        "python" => &["block"],
+        "javascript" | "typescript" | "tsx" => &["statement_block", "switch_case", "switch_default"],
+        "rust" => &["block"],
+        "go" => &["block", "expression_case", "default_case", "type_case"],
+        "java" => &["block", "constructor_body", "switch_block_statement_group"],
+        _ => return Vec::new(),
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        stack.extend(children.iter().copied());
+
+        if !blocks.contains(&node.kind()) {
+            continue;
+        }
+
+        let statements: Vec<Node> = children
+            .into_iter()
+            .filter(|s| !COMMENT_KINDS.contains(&s.kind()))
+            .collect();
+        let Some(exit) = statements.iter().position(|s| is_terminator(*s, source, lang)) else {
+            continue;
+        };
+
+        let rest: Vec<Node> = statements[exit + 1..]
+            .iter()
+            .copied()
+            .filter(|s| !is_hoisted(s.kind(), lang))
+            .collect();
+        // Labels may still be reached through `goto`
+        if rest.is_empty() || rest.iter().any(|s| s.kind() == "labeled_statement" && lang == "go") {
+            continue;
+        }
+
+        let (first, last) = (rest[0], rest[rest.len() - 1]);
+        diagnostics.push(Diagnostic {
+            range: Range {
+                start: byte_to_position(source, first.start_byte()),
+                end: byte_to_position(source, last.end_byte()),
+            },
+            severity: Some(DiagnosticSeverity::HINT),
+            code: Some(NumberOrString::String(UNREACHABLE_CODE.to_string())),
+            code_description: None,
+            source: Some("universal-lsp".to_string()),
+            message: "Unreachable code".to_string(),
+            related_information: None,
+            tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+            data: None,
+        });
+    }
+
+    diagnostics.sort_by_key(|d| (d.range.start.line, d.range.start.character));
+    diagnostics
+}
+
+fn is_terminator(statement: Node, source: &str, lang: &str) -> bool {
+    let text = |node: Node| node.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+
+    match statement.kind() {
+        "return_statement" | "break_statement" | "continue_statement" => true,
+        "raise_statement" => lang == "python",
+        "throw_statement" => lang != "python",
+        "expression_statement" => {
+            let Some(expr) = statement.named_child(0) else {
+                return false;
+            };
+            match expr.kind() {
+                "return_expression" | "break_expression" | "continue_expression" => true,
+                "macro_invocation" => expr
+                    .child_by_field_name("macro")
+                    .is_some_and(|name| RUST_DIVERGING_MACROS.contains(&text(name).as_str())),
+                "call_expression" if lang == "go" => expr
+                    .child_by_field_name("function")
+                    .is_some_and(|function| text(function) == "panic"),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Declarations that take effect regardless of where they appear in a block
+fn is_hoisted(kind: &str, lang: &str) -> bool {
+    match lang {
+        "javascript" | "typescript" | "tsx" => {
+            matches!(kind, "function_declaration" | "generator_function_declaration")
+        }
+        "rust" => kind.ends_with("_item") || matches!(kind, "use_declaration" | "macro_definition"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_sitter::TreeSitterParser;
+    use tower_lsp::lsp_types::Position;
+
+    fn analyze(source: &str, lang: &str) -> Vec<Diagnostic> {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        analyze_usage(&tree, source, lang)
+    }
+
+    fn codes<'d>(diagnostics: &'d [Diagnostic], code: &str) -> Vec<&'d Diagnostic> {
+        diagnostics
+            .iter()
+            .filter(|d| d.code == Some(NumberOrString::String(code.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_python_unused_import_and_fix() {
+        let source = "import os\nimport sys\n\nprint(sys.argv)\n";
+        let diagnostics = analyze(source, "python");
+
+        let unused = codes(&diagnostics, UNUSED_IMPORT);
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].message, "Unused import 'os'");
+        assert_eq!(unused[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+
+        // The whole line goes away
+        let (_, edits) = usage_fix(unused[0]).unwrap();
+        assert_eq!(edits[0].range.start, Position { line: 0, character: 0 });
+        assert_eq!(edits[0].range.end, Position { line: 1, character: 0 });
+    }
+
+    #[test]
+    fn test_python_unused_names_in_list_import() {
+        let source = "from typing import Dict, List, Optional\n\nx: List[int] = []\n";
+        let diagnostics = analyze(source, "python");
+
+        let unused: Vec<&str> = codes(&diagnostics, UNUSED_IMPORT).iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(unused, vec!["Unused import 'Dict'", "Unused import 'Optional'"]);
+
+        let (_, edits) = usage_fix(codes(&diagnostics, UNUSED_IMPORT)[0]).unwrap();
+        assert_eq!(edits[0].range.start, Position { line: 0, character: 19 });
+        assert_eq!(edits[0].range.end, Position { line: 0, character: 25 });
+    }
+
+    #[test]
+    fn test_python_unused_variable_and_parameter() {
+        let source = "def f(a, b, unused):\n    temp = a * 2\n    total = 0\n    for i in range(b):\n        total = total + i\n    return total\n";
+        let diagnostics = analyze(source, "python");
+
+        let variables = codes(&diagnostics, UNUSED_VARIABLE);
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables[0].message, "Unused variable 'temp'");
+        let (title, edits) = usage_fix(variables[0]).unwrap();
+        assert_eq!(title, "Rename to '_temp'");
+        assert_eq!(edits[0].new_text, "_temp");
+
+        let params = codes(&diagnostics, UNUSED_PARAMETER);
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].message, "Unused parameter 'unused'");
+    }
+
+    #[test]
+    fn test_stub_and_leading_parameters_not_reported() {
+        let source = "def hook(event, context):\n    pass\n\ndef handler(request, response):\n    return response\n";
+        let diagnostics = analyze(source, "python");
+        assert!(codes(&diagnostics, UNUSED_PARAMETER).is_empty());
+    }
+
+    #[test]
+    fn test_python_unreachable_after_raise() {
+        let source = "def f():\n    raise ValueError()\n    print('never')\n    return 1\n";
+        let diagnostics = analyze(source, "python");
+
+        let unreachable = codes(&diagnostics, UNREACHABLE_CODE);
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable[0].range.start, Position { line: 2, character: 4 });
+        assert_eq!(unreachable[0].range.end, Position { line: 3, character: 12 });
+        assert_eq!(unreachable[0].tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+    }
+
+    #[test]
+    fn test_js_unused_import_specifier_and_shadowing() {
+        let source = "import { a, b } from './m';\nfunction f(x) {\n  let y = a(x);\n  if (y) {\n    let y = 2;\n    return y;\n  }\n  return 0;\n}\nf(1);\n";
+        let diagnostics = analyze(source, "javascript");
+
+        let unused = codes(&diagnostics, UNUSED_IMPORT);
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].message, "Unused import 'b'");
+
+        let shadowed = codes(&diagnostics, SHADOWED_VARIABLE);
+        assert_eq!(shadowed.len(), 1);
+        assert_eq!(shadowed[0].range.start.line, 4);
+        assert!(shadowed[0].tags.is_none());
+    }
+
+    #[test]
+    fn test_rust_unused_and_unreachable() {
+        let source = "use std::collections::{HashMap, HashSet};\nuse std::io::Read;\n\nfn f(x: i32, y: i32) -> i32 {\n    let set: HashSet<i32> = HashSet::new();\n    let name = \"n\";\n    println!(\"{name} {}\", set.len());\n    let x = x * 2;\n    let unused = 1;\n    return x;\n    panic!(\"unreachable\");\n}\n";
+        let diagnostics = analyze(source, "rust");
+
+        let imports: Vec<&str> = codes(&diagnostics, UNUSED_IMPORT).iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(imports, vec!["Unused import 'HashMap'"]);
+
+        let variables: Vec<&str> = codes(&diagnostics, UNUSED_VARIABLE).iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(variables, vec!["Unused variable 'unused'"]);
+
+        let params: Vec<&str> = codes(&diagnostics, UNUSED_PARAMETER).iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(params, vec!["Unused parameter 'y'"]);
+
+        // Same-scope rebinding is not shadowing
+        assert!(codes(&diagnostics, SHADOWED_VARIABLE).is_empty());
+
+        let unreachable = codes(&diagnostics, UNREACHABLE_CODE);
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable[0].range.start.line, 10);
+    }
+
+    #[test]
+    fn test_go_unused_import_and_local() {
+        let source = "package main\n\nimport (\n\t\"fmt\"\n\t\"os\"\n)\n\nfunc main() {\n\tcount := 1\n\tvalue, err := run()\n\tfmt.Println(value)\n\terr = nil\n\treturn\n\tfmt.Println(count)\n}\n";
+        let diagnostics = analyze(source, "go");
+
+        let imports = codes(&diagnostics, UNUSED_IMPORT);
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].message, "Unused import 'os'");
+        let (_, edits) = usage_fix(imports[0]).unwrap();
+        assert_eq!(edits[0].range.start, Position { line: 4, character: 0 });
+        assert_eq!(edits[0].range.end, Position { line: 5, character: 0 });
+
+        // Assigning to `err` does not use it
+        let variables: Vec<&str> = codes(&diagnostics, UNUSED_VARIABLE).iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(variables, vec!["Unused variable 'err'"]);
+        assert_eq!(codes(&diagnostics, UNREACHABLE_CODE).len(), 1);
+    }
+
+    #[test]
+    fn test_java_unused_import_local_and_override() {
+        let source = "import java.util.List;\nimport java.util.Map;\n\nclass A implements Runnable {\n    @Override\n    public void accept(String event) {}\n\n    int size(List<String> items, int limit) {\n        int unused = 0;\n        return items.size();\n    }\n}\n";
+        let diagnostics = analyze(source, "java");
+
+        let imports: Vec<&str> = codes(&diagnostics, UNUSED_IMPORT).iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(imports, vec!["Unused import 'Map'"]);
+
+        let variables: Vec<&str> = codes(&diagnostics, UNUSED_VARIABLE).iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(variables, vec!["Unused variable 'unused'"]);
+
+        let params: Vec<&str> = codes(&diagnostics, UNUSED_PARAMETER).iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(params, vec!["Unused parameter 'limit'"]);
+    }
+
+    #[test]
+    fn test_names_in_scope_order() {
+        let source = "import os\n\nclass Box:\n    size = 1\n\n    def grow(self, step):\n        total = step\n        count = 2\n        \n        later = 3\n\ndef helper():\n    pass\n";
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language("python").unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+
+        let byte = source.find("        \n").unwrap() + 8;
+        let names: Vec<String> = names_in_scope(&tree, source, "python", byte).into_iter().map(|d| d.name).collect();
+        // Locals nearest first, then parameters, then module names; class attributes
+        // and locals declared further down are not visible
+        assert_eq!(names, vec!["count", "total", "step", "self", "Box", "os", "helper"]);
+    }
+
+    #[test]
+    fn test_format_captures() {
+        assert_eq!(format_captures("\"{a} {{b}} {c:?} {0}\""), vec![(2, "a"), (12, "c")]);
+    }
+}
@@ -0,0 +1,122 @@
+//! Document Highlight Module
+//!
+//! Highlights every occurrence of the symbol under the cursor within the scope that
+//! declares it. Declarations and assignments are reported as writes, everything else
+//! as reads.
+
+use anyhow::Result;
+use tower_lsp::lsp_types::*;
+use crate::tree_sitter::TreeSitterParser;
+
+/// Document highlight provider
+#[derive(Debug)]
+pub struct DocumentHighlightProvider {}
+
+impl DocumentHighlightProvider {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Get highlights for the symbol at position
+    pub fn get_highlights(
+        &self,
+        content: &str,
+        position: Position,
+        lang: &str,
+    ) -> Result<Vec<DocumentHighlight>> {
+        let mut parser = TreeSitterParser::new()?;
+        if parser.set_language(lang).is_err() {
+            return Ok(Vec::new());
+        }
+
+        let tree = parser.parse(content, "temp")?;
+        let references = parser.find_scoped_references(&tree, content, position, lang)?;
+
+        Ok(references
+            .into_iter()
+            .map(|reference| DocumentHighlight {
+                range: reference.range,
+                kind: Some(if reference.is_write {
+                    DocumentHighlightKind::WRITE
+                } else {
+                    DocumentHighlightKind::READ
+                }),
+            })
+            .collect())
+    }
+}
+
+impl Default for DocumentHighlightProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(highlights: &[DocumentHighlight]) -> Vec<(u32, u32, DocumentHighlightKind)> {
+        highlights
+            .iter()
+            .map(|h| (h.range.start.line, h.range.start.character, h.kind.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_python_read_write() {
+        let provider = DocumentHighlightProvider::new();
+        let content = "count = 0\ncount += 1\nprint(count)\n";
+
+        let highlights = provider
+            .get_highlights(content, Position { line: 2, character: 8 }, "python")
+            .unwrap();
+
+        assert_eq!(
+            kinds(&highlights),
+            vec![
+                (0, 0, DocumentHighlightKind::WRITE),
+                (1, 0, DocumentHighlightKind::WRITE),
+                (2, 6, DocumentHighlightKind::READ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_short_var_and_assignment() {
+        let provider = DocumentHighlightProvider::new();
+        let content = "package main\n\nfunc main() {\n\tn := 1\n\tn = n + 1\n\tn++\n}\n";
+
+        let highlights = provider
+            .get_highlights(content, Position { line: 3, character: 1 }, "go")
+            .unwrap();
+
+        assert_eq!(
+            kinds(&highlights),
+            vec![
+                (3, 1, DocumentHighlightKind::WRITE),
+                (4, 1, DocumentHighlightKind::WRITE),
+                (4, 5, DocumentHighlightKind::READ),
+                (5, 1, DocumentHighlightKind::WRITE),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_symbol_under_cursor() {
+        let provider = DocumentHighlightProvider::new();
+        let highlights = provider
+            .get_highlights("x = 1\n", Position { line: 0, character: 3 }, "python")
+            .unwrap();
+        assert!(highlights.is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_language() {
+        let provider = DocumentHighlightProvider::new();
+        let highlights = provider
+            .get_highlights("text", Position { line: 0, character: 0 }, "unsupported")
+            .unwrap();
+        assert!(highlights.is_empty());
+    }
+}
@@ -0,0 +1,485 @@
+//! Folding Range Module
+//!
+//! Computes folding ranges from the tree-sitter syntax tree. Bracketed blocks and
+//! comments are folded generically for every registered grammar; language-specific
+//! constructs (imports, indentation-based bodies, markup elements) are picked up by
+//! small per-language queries. `#region` / `#endregion` comments fold as regions.
+
+use anyhow::Result;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+use tower_lsp::lsp_types::*;
+use tree_sitter::{Query, QueryCursor};
+use crate::tree_sitter::TreeSitterParser;
+
+/// Per-language folding queries
+///
+/// Captures:
+/// - `@fold` - fold the whole node
+/// - `@fold.body` - fold from the line that introduces the node (e.g. a Python `def` header)
+/// - `@fold.imports` - adjacent captures are merged into a single imports fold
+/// - `@fold.comment` - fold as a comment (docstrings and the like)
+const FOLD_QUERIES: &[(&str, &str)] = &[
+    ("python", r#"
+        (block) @fold.body
+        (import_statement) @fold.imports
+        (import_from_statement) @fold.imports
+        (future_import_statement) @fold.imports
+        (expression_statement (string) @fold.comment)
+    "#),
+    ("rust", r#"
+        (use_declaration) @fold.imports
+        (extern_crate_declaration) @fold.imports
+    "#),
+    ("javascript", r#"
+        (import_statement) @fold.imports
+        (jsx_element) @fold
+    "#),
+    ("typescript", r#"
+        (import_statement) @fold.imports
+    "#),
+    ("tsx", r#"
+        (import_statement) @fold.imports
+        (jsx_element) @fold
+    "#),
+    ("go", r#"
+        (import_declaration) @fold.imports
+    "#),
+    ("java", r#"
+        (import_declaration) @fold.imports
+    "#),
+    ("c", r#"
+        (preproc_include) @fold.imports
+        (preproc_if) @fold
+        (preproc_ifdef) @fold
+    "#),
+    ("cpp", r#"
+        (preproc_include) @fold.imports
+        (using_declaration) @fold.imports
+        (preproc_if) @fold
+        (preproc_ifdef) @fold
+    "#),
+    ("kotlin", r#"
+        (import_header) @fold.imports
+    "#),
+    ("scala", r#"
+        (import_declaration) @fold.imports
+    "#),
+    ("csharp", r#"
+        (using_directive) @fold.imports
+    "#),
+    ("cs", r#"
+        (using_directive) @fold.imports
+    "#),
+    ("html", r#"
+        (element) @fold
+        (script_element) @fold
+        (style_element) @fold
+    "#),
+    ("svelte", r#"
+        (element) @fold
+        (script_element) @fold
+        (style_element) @fold
+    "#),
+    ("bash", r#"
+        (if_statement) @fold
+        (case_statement) @fold
+        (do_group) @fold.body
+    "#),
+    ("sh", r#"
+        (if_statement) @fold
+        (case_statement) @fold
+        (do_group) @fold.body
+    "#),
+];
+
+/// Compiled folding queries, keyed by language (`None` if the query failed to compile)
+static QUERY_CACHE: Lazy<DashMap<String, Option<Arc<Query>>>> = Lazy::new(DashMap::new);
+
+/// Folding range provider
+#[derive(Debug)]
+pub struct FoldingRangeProvider {}
+
+impl FoldingRangeProvider {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Get folding ranges for the whole document
+    pub fn get_folding_ranges(&self, content: &str, lang: &str) -> Result<Vec<FoldingRange>> {
+        let mut parser = TreeSitterParser::new()?;
+        if parser.set_language(lang).is_err() {
+            return Ok(Vec::new());
+        }
+
+        let tree = parser.parse(content, "temp")?;
+        let root = tree.root_node();
+
+        let mut ranges = Vec::new();
+        let mut comments = Vec::new();
+        self.collect_generic_folds(root, content, &mut ranges, &mut comments);
+
+        self.add_comment_folds(&comments, content, &mut ranges);
+        self.add_region_folds(&comments, content, &mut ranges);
+
+        if let Some(query) = self.query_for(&parser, lang) {
+            self.add_query_folds(&query, root, content, &mut ranges);
+        }
+
+        Ok(self.normalize(ranges))
+    }
+
+    /// Fold bracketed nodes and gather comments for later passes
+    fn collect_generic_folds<'a>(
+        &self,
+        node: tree_sitter::Node<'a>,
+        content: &str,
+        ranges: &mut Vec<FoldingRange>,
+        comments: &mut Vec<tree_sitter::Node<'a>>,
+    ) {
+        if node.kind().contains("comment") {
+            comments.push(node);
+            return;
+        }
+
+        if self.is_bracketed(&node) {
+            self.push_fold(node.start_position().row, self.fold_end_row(&node, content), None, ranges);
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.collect_generic_folds(child, content, ranges, comments);
+        }
+    }
+
+    /// Whether a node is delimited by a matching pair of brackets
+    fn is_bracketed(&self, node: &tree_sitter::Node) -> bool {
+        if node.child_count() < 2 || node.start_position().row == node.end_position().row {
+            return false;
+        }
+
+        let first = node.child(0).map(|c| c.kind());
+        let last = node.child(node.child_count() - 1).map(|c| c.kind());
+
+        matches!(
+            (first, last),
+            (Some("{"), Some("}")) | (Some("["), Some("]")) | (Some("("), Some(")"))
+        )
+    }
+
+    /// Last line to hide, keeping a closing delimiter on its own line visible
+    fn fold_end_row(&self, node: &tree_sitter::Node, content: &str) -> usize {
+        let end_row = node.end_position().row;
+
+        let last = match node.child(node.child_count().saturating_sub(1)) {
+            Some(last) => last,
+            None => return end_row,
+        };
+
+        let is_closer = matches!(last.kind(), "}" | "]" | ")" | "end_tag" | "done" | "fi" | "esac" | "end");
+        if !is_closer || last.start_position().row == node.start_position().row {
+            return end_row;
+        }
+
+        let line_start = content[..last.start_byte()].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        if content[line_start..last.start_byte()].trim().is_empty() {
+            last.start_position().row.saturating_sub(1)
+        } else {
+            end_row
+        }
+    }
+
+    /// Fold multi-line comments and runs of single-line comments
+    fn add_comment_folds(&self, comments: &[tree_sitter::Node], content: &str, ranges: &mut Vec<FoldingRange>) {
+        let mut run: Option<(usize, usize)> = None;
+
+        for comment in comments {
+            let text = &content[comment.start_byte()..comment.end_byte()];
+            let start = comment.start_position().row;
+            let end = comment.end_position().row;
+
+            // Region markers get their own folds
+            if self.region_marker(text).is_some() {
+                if let Some((run_start, run_end)) = run.take() {
+                    self.push_fold(run_start, run_end, Some(FoldingRangeKind::Comment), ranges);
+                }
+                continue;
+            }
+
+            if start != end {
+                if let Some((run_start, run_end)) = run.take() {
+                    self.push_fold(run_start, run_end, Some(FoldingRangeKind::Comment), ranges);
+                }
+                self.push_fold(start, end, Some(FoldingRangeKind::Comment), ranges);
+                continue;
+            }
+
+            // Trailing comments after code don't start or extend a run
+            let line_start = content[..comment.start_byte()].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let own_line = content[line_start..comment.start_byte()].trim().is_empty();
+
+            run = match run {
+                Some((run_start, run_end)) if own_line && start == run_end + 1 => Some((run_start, start)),
+                Some((run_start, run_end)) => {
+                    self.push_fold(run_start, run_end, Some(FoldingRangeKind::Comment), ranges);
+                    if own_line { Some((start, start)) } else { None }
+                }
+                None if own_line => Some((start, start)),
+                None => None,
+            };
+        }
+
+        if let Some((run_start, run_end)) = run {
+            self.push_fold(run_start, run_end, Some(FoldingRangeKind::Comment), ranges);
+        }
+    }
+
+    /// Fold `#region` ... `#endregion` comment pairs
+    fn add_region_folds(&self, comments: &[tree_sitter::Node], content: &str, ranges: &mut Vec<FoldingRange>) {
+        let mut open = Vec::new();
+
+        for comment in comments {
+            let text = &content[comment.start_byte()..comment.end_byte()];
+            match self.region_marker(text) {
+                Some(true) => open.push(comment.start_position().row),
+                Some(false) => {
+                    if let Some(start) = open.pop() {
+                        self.push_fold(start, comment.start_position().row, Some(FoldingRangeKind::Region), ranges);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// `Some(true)` for a region start marker, `Some(false)` for an end marker
+    fn region_marker(&self, comment: &str) -> Option<bool> {
+        let text = comment
+            .trim_start_matches(|c: char| matches!(c, '/' | '*' | '#' | '-' | '!' | '<' | ';' | '%') || c.is_whitespace())
+            .to_lowercase();
+
+        let is_marker = |keyword: &str| {
+            text.strip_prefix(keyword)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace() || c == '-' || c == '*'))
+        };
+
+        if is_marker("region") {
+            Some(true)
+        } else if is_marker("endregion") {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Apply the language's folding query, if any
+    fn add_query_folds(&self, query: &Query, root: tree_sitter::Node, content: &str, ranges: &mut Vec<FoldingRange>) {
+        let mut cursor = QueryCursor::new();
+        let mut imports: Vec<(usize, usize)> = Vec::new();
+
+        for query_match in cursor.matches(query, root, content.as_bytes()) {
+            for capture in query_match.captures {
+                let node = capture.node;
+                let start = node.start_position().row;
+                let end = self.fold_end_row(&node, content);
+
+                match query.capture_names()[capture.index as usize].as_str() {
+                    "fold" => self.push_fold(start, end, None, ranges),
+                    "fold.body" => {
+                        // Start on the line of the header that owns this body
+                        let header_row = node
+                            .prev_sibling()
+                            .map(|s| s.end_position().row)
+                            .unwrap_or(start);
+                        self.push_fold(header_row, end, None, ranges);
+                    }
+                    "fold.comment" => self.push_fold(start, end, Some(FoldingRangeKind::Comment), ranges),
+                    "fold.imports" => imports.push((start, node.end_position().row)),
+                    _ => {}
+                }
+            }
+        }
+
+        // Merge adjacent import statements into one fold
+        imports.sort();
+        let mut group: Option<(usize, usize)> = None;
+        for (start, end) in imports {
+            group = match group {
+                Some((group_start, group_end)) if start <= group_end + 1 => Some((group_start, group_end.max(end))),
+                Some((group_start, group_end)) => {
+                    self.push_fold(group_start, group_end, Some(FoldingRangeKind::Imports), ranges);
+                    Some((start, end))
+                }
+                None => Some((start, end)),
+            };
+        }
+        if let Some((group_start, group_end)) = group {
+            self.push_fold(group_start, group_end, Some(FoldingRangeKind::Imports), ranges);
+        }
+    }
+
+    /// Compile (once) and return the folding query for a language
+    fn query_for(&self, parser: &TreeSitterParser, lang: &str) -> Option<Arc<Query>> {
+        if let Some(cached) = QUERY_CACHE.get(lang) {
+            return cached.clone();
+        }
+
+        let source = FOLD_QUERIES.iter().find(|(name, _)| *name == lang).map(|(_, q)| *q)?;
+        let language = parser.language()?;
+
+        let compiled = match Query::new(language, source) {
+            Ok(query) => Some(Arc::new(query)),
+            Err(e) => {
+                tracing::warn!("Invalid folding query for {}: {:?}", lang, e);
+                None
+            }
+        };
+
+        QUERY_CACHE.insert(lang.to_string(), compiled.clone());
+        compiled
+    }
+
+    fn push_fold(&self, start: usize, end: usize, kind: Option<FoldingRangeKind>, ranges: &mut Vec<FoldingRange>) {
+        if end <= start {
+            return;
+        }
+
+        ranges.push(FoldingRange {
+            start_line: start as u32,
+            start_character: None,
+            end_line: end as u32,
+            end_character: None,
+            kind,
+            collapsed_text: None,
+        });
+    }
+
+    /// Sort and keep one fold per start line, preferring kinded and larger folds
+    fn normalize(&self, mut ranges: Vec<FoldingRange>) -> Vec<FoldingRange> {
+        ranges.sort_by(|a, b| {
+            a.start_line
+                .cmp(&b.start_line)
+                .then(b.kind.is_some().cmp(&a.kind.is_some()))
+                .then(b.end_line.cmp(&a.end_line))
+        });
+        ranges.dedup_by(|later, earlier| later.start_line == earlier.start_line);
+        ranges
+    }
+}
+
+impl Default for FoldingRangeProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(ranges: &[FoldingRange], start: u32) -> &FoldingRange {
+        ranges
+            .iter()
+            .find(|r| r.start_line == start)
+            .unwrap_or_else(|| panic!("no fold starting at line {}: {:?}", start, ranges))
+    }
+
+    #[test]
+    fn test_rust_block_folding() {
+        let provider = FoldingRangeProvider::new();
+        let content = "fn main() {\n    let x = 1;\n    let y = 2;\n}\n";
+
+        let ranges = provider.get_folding_ranges(content, "rust").unwrap();
+        let fold = find(&ranges, 0);
+        assert_eq!(fold.end_line, 2, "closing brace stays visible");
+        assert_eq!(fold.kind, None);
+    }
+
+    #[test]
+    fn test_rust_imports_and_comments() {
+        let provider = FoldingRangeProvider::new();
+        let content = r#"use std::fs;
+use std::io;
+use std::path::Path;
+
+// First line of a comment
+// Second line of a comment
+fn main() {}
+"#;
+
+        let ranges = provider.get_folding_ranges(content, "rust").unwrap();
+        let imports = find(&ranges, 0);
+        assert_eq!(imports.end_line, 2);
+        assert_eq!(imports.kind, Some(FoldingRangeKind::Imports));
+
+        let comment = find(&ranges, 4);
+        assert_eq!(comment.end_line, 5);
+        assert_eq!(comment.kind, Some(FoldingRangeKind::Comment));
+    }
+
+    #[test]
+    fn test_region_folding() {
+        let provider = FoldingRangeProvider::new();
+        let content = r#"// #region helpers
+function a() {}
+function b() {}
+// #endregion
+"#;
+
+        let ranges = provider.get_folding_ranges(content, "javascript").unwrap();
+        let region = find(&ranges, 0);
+        assert_eq!(region.end_line, 3);
+        assert_eq!(region.kind, Some(FoldingRangeKind::Region));
+    }
+
+    #[test]
+    fn test_python_folding() {
+        let provider = FoldingRangeProvider::new();
+        let content = r#"import os
+import sys
+
+def greet(name):
+    message = "hello"
+    return message + name
+
+class Greeter:
+    def run(self):
+        pass
+"#;
+
+        let ranges = provider.get_folding_ranges(content, "python").unwrap();
+        assert_eq!(find(&ranges, 0).kind, Some(FoldingRangeKind::Imports));
+        assert_eq!(find(&ranges, 3).end_line, 5);
+        assert_eq!(find(&ranges, 7).end_line, 9);
+    }
+
+    #[test]
+    fn test_python_region_comments() {
+        let provider = FoldingRangeProvider::new();
+        let content = "# region setup\nx = 1\ny = 2\n# endregion\n";
+
+        let ranges = provider.get_folding_ranges(content, "python").unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].kind, Some(FoldingRangeKind::Region));
+    }
+
+    #[test]
+    fn test_all_queries_compile() {
+        let provider = FoldingRangeProvider::new();
+        for (lang, _) in FOLD_QUERIES {
+            let mut parser = TreeSitterParser::new().unwrap();
+            if parser.set_language(lang).is_err() {
+                continue;
+            }
+            assert!(provider.query_for(&parser, lang).is_some(), "query for {} failed to compile", lang);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_language() {
+        let provider = FoldingRangeProvider::new();
+        let ranges = provider.get_folding_ranges("anything", "unsupported").unwrap();
+        assert!(ranges.is_empty());
+    }
+}
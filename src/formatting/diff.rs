@@ -0,0 +1,211 @@
+//! Line diff of a document against its formatted text
+//!
+//! Formatter output is turned into one edit per changed line, or per run of lines
+//! where lines were added or removed (Myers' algorithm), so the client keeps
+//! cursors, marks and folds on untouched lines instead of replacing the whole
+//! document.
+
+use std::ops::Range as Span;
+use tower_lsp::lsp_types::*;
+
+/// Edit distance beyond which the changed lines are replaced in one edit, bounding
+/// the diff's memory on documents that are rewritten wholesale
+const MAX_EDIT_DISTANCE: usize = 1000;
+
+/// Minimal line-level edits turning `old` into `new`
+pub fn line_edits(old: &str, new: &str) -> Vec<TextEdit> {
+    if old == new {
+        return Vec::new();
+    }
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+
+    hunks(&old_lines, &new_lines)
+        .into_iter()
+        .flat_map(|(removed, inserted)| {
+            // Lines rewritten one for one are edited separately, so range formatting
+            // can keep the ones inside its range
+            if removed.len() == inserted.len() && removed.len() > 1 {
+                removed.zip(inserted).map(|(old, new)| (old..old + 1, new..new + 1)).collect()
+            } else {
+                vec![(removed, inserted)]
+            }
+        })
+        .map(|(removed, inserted)| {
+            // Replacing the last line of a document without a trailing newline
+            // ends at the end of that line, not on a line past it
+            let end = if removed.end == old_lines.len() && removed.end > removed.start && !old.ends_with('\n') {
+                let last = old_lines[removed.end - 1];
+                Position { line: removed.end as u32 - 1, character: last.encode_utf16().count() as u32 }
+            } else {
+                Position { line: removed.end as u32, character: 0 }
+            };
+            TextEdit {
+                range: Range { start: Position { line: removed.start as u32, character: 0 }, end },
+                new_text: new_lines[inserted].concat(),
+            }
+        })
+        .collect()
+}
+
+/// Runs of differing lines as `(old lines, new lines)` spans
+fn hunks(old: &[&str], new: &[&str]) -> Vec<(Span<usize>, Span<usize>)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let Some(script) = edit_script(old_middle, new_middle) else {
+        return vec![(prefix..old.len() - suffix, prefix..new.len() - suffix)];
+    };
+
+    // Group consecutive deletions and insertions between runs of equal lines
+    let mut hunks: Vec<(Span<usize>, Span<usize>)> = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for op in script {
+        let open = hunks.last().is_some_and(|(removed, inserted)| removed.end == x + prefix && inserted.end == y + prefix);
+        match op {
+            Op::Equal => {
+                x += 1;
+                y += 1;
+                continue;
+            }
+            Op::Delete => x += 1,
+            Op::Insert => y += 1,
+        }
+        match hunks.last_mut() {
+            Some((removed, inserted)) if open => {
+                removed.end = x + prefix;
+                inserted.end = y + prefix;
+            }
+            _ => {
+                let (start_x, start_y) = match op {
+                    Op::Delete => (x - 1, y),
+                    _ => (x, y - 1),
+                };
+                hunks.push((start_x + prefix..x + prefix, start_y + prefix..y + prefix));
+            }
+        }
+    }
+    hunks
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Shortest edit script from `a` to `b`, `None` past [`MAX_EDIT_DISTANCE`]
+fn edit_script(a: &[&str], b: &[&str]) -> Option<Vec<Op>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDIT_DISTANCE) as isize;
+    let offset = max + 1;
+    let index = |k: isize| (k + offset) as usize;
+
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut distance = None;
+
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                distance = Some(d);
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back from the end through the furthest points of each step
+    let mut script = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..=distance?).rev() {
+        let v = &trace[d as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) { k + 1 } else { k - 1 };
+        let previous_x = if d == 0 { 0 } else { v[index(previous_k)] };
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            script.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            script.push(if x == previous_x { Op::Insert } else { Op::Delete });
+            x = previous_x;
+            y = previous_y;
+        }
+    }
+    script.reverse();
+    Some(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(source: &str, edits: &[TextEdit]) -> String {
+        let lines: Vec<&str> = source.split_inclusive('\n').collect();
+        let offset = |position: Position| {
+            let line_start: usize = lines.iter().take(position.line as usize).map(|l| l.len()).sum();
+            line_start + position.character as usize
+        };
+        let mut result = source.to_string();
+        for edit in edits.iter().rev() {
+            result.replace_range(offset(edit.range.start)..offset(edit.range.end), &edit.new_text);
+        }
+        result
+    }
+
+    #[test]
+    fn test_edits_only_changed_lines() {
+        let old = "def f( a ):\n    return a\n\n\nx=1\ny = 2\n";
+        let new = "def f(a):\n    return a\n\n\nx = 1\ny = 2\n";
+        let edits = line_edits(old, new);
+
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range.start, Position { line: 0, character: 0 });
+        assert_eq!(edits[0].range.end, Position { line: 1, character: 0 });
+        assert_eq!(edits[0].new_text, "def f(a):\n");
+        assert_eq!(edits[1].range.start, Position { line: 4, character: 0 });
+        assert_eq!(edits[1].new_text, "x = 1\n");
+        assert_eq!(apply(old, &edits), new);
+    }
+
+    #[test]
+    fn test_inserted_and_removed_lines() {
+        let old = "a\nb\nc\nd\n";
+        let new = "a\nx\nb\nd\n";
+        let edits = line_edits(old, new);
+        assert_eq!(edits.len(), 2);
+        assert_eq!(apply(old, &edits), new);
+
+        // Missing final newline added by the formatter
+        let edits = line_edits("a\nb", "a\nb\n");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, Position { line: 1, character: 0 });
+        assert_eq!(apply("a\nb", &edits), "a\nb\n");
+
+        assert!(line_edits(old, old).is_empty());
+        assert_eq!(apply("", &line_edits("", "x\n")), "x\n");
+        assert_eq!(apply("x\n", &line_edits("x\n", "")), "");
+    }
+}
@@ -0,0 +1,243 @@
+//! `.editorconfig` support
+//!
+//! Collects the properties that apply to a file: `.editorconfig` files are read from
+//! the file's directory upwards until one declares `root = true`. Closer files take
+//! precedence over files further up, and later sections over earlier ones. Section
+//! globs follow the EditorConfig rules: `*`, `**`, `?`, `[...]`, `{a,b}` and
+//! `{1..3}`; a glob without `/` matches the file name in any subdirectory. Numeric
+//! ranges are matched by comparing the number in the path to their bounds, so huge
+//! ranges cost nothing.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Properties (lowercased keys) of the `.editorconfig` sections matching `file`
+pub fn properties(file: &Path) -> HashMap<String, String> {
+    let mut files = Vec::new();
+    let mut dir = file.parent();
+    while let Some(current) = dir {
+        if let Ok(content) = std::fs::read_to_string(current.join(".editorconfig")) {
+            let parsed = parse(&content);
+            let root = parsed.root;
+            files.push((current.to_path_buf(), parsed));
+            if root {
+                break;
+            }
+        }
+        dir = current.parent();
+    }
+
+    // Apply from the outermost file inwards so closer files win
+    let mut properties = HashMap::new();
+    for (dir, config) in files.iter().rev() {
+        let Ok(relative) = file.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        for section in &config.sections {
+            if section_matches(&section.glob, &relative) {
+                for (key, value) in &section.properties {
+                    properties.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    properties
+}
+
+#[derive(Debug, Default)]
+struct EditorConfig {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug)]
+struct Section {
+    glob: String,
+    properties: Vec<(String, String)>,
+}
+
+fn parse(content: &str) -> EditorConfig {
+    let mut config = EditorConfig::default();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            config.sections.push(Section { glob: glob.to_string(), properties: Vec::new() });
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_string();
+        match config.sections.last_mut() {
+            Some(section) => section.properties.push((key, value.to_lowercase())),
+            None if key == "root" => config.root = value.eq_ignore_ascii_case("true"),
+            None => {}
+        }
+    }
+    config
+}
+
+/// Whether a section glob matches `path`, relative to the `.editorconfig` directory
+fn section_matches(glob: &str, path: &str) -> bool {
+    let glob = if let Some(anchored) = glob.strip_prefix('/') {
+        anchored.to_string()
+    } else if glob.contains('/') {
+        glob.to_string()
+    } else {
+        format!("**/{}", glob)
+    };
+    expand_braces(&glob).iter().any(|pattern| {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let path: Vec<char> = path.chars().collect();
+        glob_match(&pattern, &path) || (pattern.starts_with(&['*', '*', '/']) && glob_match(&pattern[3..], &path))
+    })
+}
+
+/// Alternatives of the first `{a,b}` group, expanded recursively; `{1..3}` ranges
+/// are left to [`glob_match`]
+fn expand_braces(glob: &str) -> Vec<String> {
+    let Some(open) = glob.find('{') else {
+        return vec![glob.to_string()];
+    };
+    let Some(close) = glob[open..].find('}').map(|i| open + i) else {
+        return vec![glob.to_string()];
+    };
+    let (prefix, inner, suffix) = (&glob[..open], &glob[open + 1..close], &glob[close + 1..]);
+
+    if !inner.contains(',') {
+        // A numeric range or a single word, which is literal
+        return expand_braces(suffix)
+            .into_iter()
+            .map(|rest| format!("{}{{{}}}{}", prefix, inner, rest))
+            .collect();
+    }
+    inner
+        .split(',')
+        .flat_map(|alternative| expand_braces(&format!("{}{}{}", prefix, alternative, suffix)))
+        .collect()
+}
+
+fn glob_match(pattern: &[char], path: &[char]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            (0..=path.len()).any(|skip| glob_match(rest, &path[skip..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for skip in 0..=path.len() {
+                if glob_match(rest, &path[skip..]) {
+                    return true;
+                }
+                if path.get(skip) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => path.first().is_some_and(|c| *c != '/') && glob_match(&pattern[1..], &path[1..]),
+        Some('[') => {
+            let Some(close) = pattern.iter().position(|c| *c == ']') else {
+                return path.first() == Some(&'[') && glob_match(&pattern[1..], &path[1..]);
+            };
+            let Some(c) = path.first() else {
+                return false;
+            };
+            let class = &pattern[1..close];
+            let (negated, class) = match class.first() {
+                Some('!') | Some('^') => (true, &class[1..]),
+                _ => (false, class),
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= (class[i]..=class[i + 2]).contains(c);
+                    i += 3;
+                } else {
+                    matched |= class[i] == *c;
+                    i += 1;
+                }
+            }
+            matched != negated && glob_match(&pattern[close + 1..], &path[1..])
+        }
+        Some('{') => {
+            let Some((from, to, close)) = numeric_range(pattern) else {
+                return path.first() == Some(&'{') && glob_match(&pattern[1..], &path[1..]);
+            };
+            let digits = path
+                .iter()
+                .enumerate()
+                .take_while(|(i, c)| c.is_ascii_digit() || (*i == 0 && **c == '-'))
+                .count();
+            (1..=digits).any(|len| {
+                let number: String = path[..len].iter().collect();
+                number.parse::<i64>().is_ok_and(|n| (from..=to).contains(&n))
+                    && glob_match(&pattern[close + 1..], &path[len..])
+            })
+        }
+        Some('\\') if pattern.len() > 1 => path.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &path[1..]),
+        Some(c) => path.first() == Some(c) && glob_match(&pattern[1..], &path[1..]),
+    }
+}
+
+/// Bounds of a `{from..to}` range at the start of `pattern`, lowest first, and the
+/// index of its closing brace
+fn numeric_range(pattern: &[char]) -> Option<(i64, i64, usize)> {
+    let close = pattern.iter().position(|c| *c == '}')?;
+    let inner: String = pattern[1..close].iter().collect();
+    let (from, to) = inner.split_once("..")?;
+    let (from, to) = (from.parse::<i64>().ok()?, to.parse::<i64>().ok()?);
+    Some((from.min(to), from.max(to), close))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_section_globs() {
+        assert!(section_matches("*", "src/main.rs"));
+        assert!(section_matches("*.py", "pkg/module.py"));
+        assert!(!section_matches("*.py", "pkg/module.pyc"));
+        assert!(section_matches("*.{js,ts}", "web/app.ts"));
+        assert!(section_matches("src/*.rs", "src/main.rs"));
+        assert!(!section_matches("src/*.rs", "src/bin/main.rs"));
+        assert!(section_matches("src/**.rs", "src/bin/main.rs"));
+        assert!(section_matches("/Makefile", "Makefile"));
+        assert!(!section_matches("/Makefile", "sub/Makefile"));
+        assert!(section_matches("file[0-9].txt", "file3.txt"));
+        assert!(section_matches("v{1..3}.md", "docs/v2.md"));
+        assert!(!section_matches("v{1..3}.md", "docs/v4.md"));
+        assert!(section_matches("v{3..1}.md", "v1.md"));
+        assert!(section_matches("v{-2..2}.md", "v-1.md"));
+        assert!(section_matches("{word}.md", "{word}.md"));
+        // Not expanded, so huge ranges are cheap
+        assert!(section_matches("log{0..999999999999}.txt", "log123456.txt"));
+        assert!(!section_matches("log{0..999999999999}.txt", "log.txt"));
+    }
+
+    #[test]
+    fn test_closer_files_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("web")).unwrap();
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 4\n\n[Makefile]\nindent_style = tab\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("web/.editorconfig"), "[*.{js,ts}]\nindent_size = 2\n").unwrap();
+
+        let props = properties(&dir.path().join("web/app.ts"));
+        assert_eq!(props.get("indent_size").map(String::as_str), Some("2"));
+        assert_eq!(props.get("indent_style").map(String::as_str), Some("space"));
+        assert_eq!(properties(&dir.path().join("Makefile")).get("indent_style").map(String::as_str), Some("tab"));
+        assert_eq!(properties(&dir.path().join("main.py")).get("indent_size").map(String::as_str), Some("4"));
+    }
+}
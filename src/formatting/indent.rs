@@ -0,0 +1,359 @@
+//! Indentation from the syntax tree
+//!
+//! Each language describes its indentation with a small query:
+//! - `@indent` - lines inside the node, after its first line, are indented one level
+//!   deeper than the line the node starts on
+//! - `@outdent` - a line starting with the node is aligned with the line its
+//!   enclosing `@indent` node starts on (closing brackets, `else`, `case`)
+//!
+//! The indentation of a line is derived from the innermost `@indent` node around its
+//! first token. Lines continuing an expression or inside a multi-line string are left
+//! alone. A blank line takes the indentation it would have if code were typed there,
+//! so indentation-based bodies (Python) continue after their last line.
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tower_lsp::lsp_types::*;
+use tree_sitter::{Node, Query, QueryCursor, Tree};
+
+use crate::tree_sitter::TreeSitterParser;
+
+/// Per-language indent queries
+const INDENT_QUERIES: &[(&str, &str)] = &[
+    ("python", r#"
+        [
+          (function_definition) (class_definition) (if_statement) (elif_clause) (else_clause)
+          (for_statement) (while_statement) (with_statement) (try_statement) (except_clause)
+          (finally_clause) (match_statement) (case_clause)
+          (argument_list) (parameters) (parenthesized_expression) (list) (dictionary) (set)
+          (tuple) (list_comprehension) (dictionary_comprehension) (set_comprehension)
+          (generator_expression) (import_from_statement)
+        ] @indent
+        [(elif_clause) (else_clause) (except_clause) (finally_clause) (case_clause) ")" "]" "}"] @outdent
+        ((identifier) @outdent (#match? @outdent "^(else|elif|except|finally)$"))
+    "#),
+    ("javascript", JS_INDENT),
+    ("typescript", TS_INDENT),
+    ("tsx", TS_INDENT),
+    ("rust", r#"
+        [
+          (block) (declaration_list) (field_declaration_list) (enum_variant_list) (match_block)
+          (arguments) (parameters) (token_tree) (field_initializer_list) (array_expression)
+          (tuple_expression) (use_list) (type_arguments) (type_parameters) (where_clause)
+        ] @indent
+        ["}" "]" ")" ">"] @outdent
+    "#),
+    ("go", r#"
+        [
+          (block) (literal_value) (field_declaration_list) (interface_type) (argument_list)
+          (parameter_list) (import_spec_list) (const_declaration) (var_declaration)
+          (expression_switch_statement) (type_switch_statement) (select_statement)
+          (expression_case) (type_case) (default_case) (communication_case)
+        ] @indent
+        [(expression_case) (type_case) (default_case) (communication_case) "}" "]" ")"] @outdent
+    "#),
+    ("c", C_INDENT),
+    ("cpp", C_INDENT),
+    ("java", r#"
+        [
+          (block) (class_body) (interface_body) (enum_body) (constructor_body) (switch_block)
+          (switch_block_statement_group) (array_initializer) (argument_list) (formal_parameters)
+          (annotation_argument_list)
+        ] @indent
+        ["}" "]" ")"] @outdent
+    "#),
+];
+
+const JS_INDENT: &str = r#"
+    [
+      (statement_block) (class_body) (object) (array) (arguments) (formal_parameters)
+      (switch_body) (switch_case) (switch_default) (object_pattern) (array_pattern)
+      (parenthesized_expression) (named_imports) (export_clause)
+    ] @indent
+    ["}" "]" ")"] @outdent
+"#;
+
+const TS_INDENT: &str = r#"
+    [
+      (statement_block) (class_body) (object) (array) (arguments) (formal_parameters)
+      (switch_body) (switch_case) (switch_default) (object_pattern) (array_pattern)
+      (parenthesized_expression) (named_imports) (export_clause) (object_type) (enum_body)
+      (type_arguments) (type_parameters)
+    ] @indent
+    ["}" "]" ")" ">"] @outdent
+"#;
+
+const C_INDENT: &str = r#"
+    [
+      (compound_statement) (field_declaration_list) (enumerator_list) (initializer_list)
+      (argument_list) (parameter_list) (case_statement)
+    ] @indent
+    ["}" "]" ")"] @outdent
+"#;
+
+/// Compiled indent queries, `None` for languages without one
+static QUERY_CACHE: Lazy<DashMap<String, Option<Arc<Query>>>> = Lazy::new(DashMap::new);
+
+fn query_for(lang: &str) -> Option<Arc<Query>> {
+    if let Some(cached) = QUERY_CACHE.get(lang) {
+        return cached.clone();
+    }
+
+    let source = INDENT_QUERIES.iter().find(|(name, _)| *name == lang).map(|(_, q)| *q)?;
+    let mut parser = TreeSitterParser::new().ok()?;
+    parser.set_language(lang).ok()?;
+    let language = parser.language()?;
+
+    let compiled = match Query::new(language, source) {
+        Ok(query) => Some(Arc::new(query)),
+        Err(e) => {
+            tracing::warn!("Invalid indent query for {}: {:?}", lang, e);
+            None
+        }
+    };
+    QUERY_CACHE.insert(lang.to_string(), compiled.clone());
+    compiled
+}
+
+/// Computes the indentation of lines from a document's syntax tree
+pub struct Indenter<'a> {
+    root: Node<'a>,
+    source: &'a str,
+    line_starts: Vec<usize>,
+    indent: HashSet<usize>,
+    outdent: HashSet<usize>,
+}
+
+/// Where a line's indentation comes from
+enum Anchor {
+    /// Top level: no indentation
+    None,
+    /// One level deeper than the line `row` starts on, or aligned with it
+    Line { row: usize, outdent: bool },
+}
+
+impl<'a> Indenter<'a> {
+    pub fn new(tree: &'a Tree, source: &'a str, lang: &str) -> Option<Self> {
+        let query = query_for(lang)?;
+        let root = tree.root_node();
+
+        let mut indent = HashSet::new();
+        let mut outdent = HashSet::new();
+        let mut cursor = QueryCursor::new();
+        for query_match in cursor.matches(&query, root, source.as_bytes()) {
+            for capture in query_match.captures {
+                match query.capture_names()[capture.index as usize].as_str() {
+                    "indent" => indent.insert(capture.node.id()),
+                    "outdent" => outdent.insert(capture.node.id()),
+                    _ => false,
+                };
+            }
+        }
+
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Some(Self { root, source, line_starts, indent, outdent })
+    }
+
+    fn line(&self, row: usize) -> &'a str {
+        let Some(&start) = self.line_starts.get(row) else {
+            return "";
+        };
+        let end = self.line_starts.get(row + 1).map_or(self.source.len(), |next| next - 1);
+        self.source[start..end.max(start)].trim_end_matches('\r')
+    }
+
+    fn leading_whitespace(&self, row: usize) -> &'a str {
+        let line = self.line(row);
+        &line[..line.len() - line.trim_start().len()]
+    }
+
+    /// Indentation for line `row` given the current indentation of the lines above,
+    /// `None` when the line should keep its own
+    pub fn indent_for_line(&self, row: usize, unit: &str) -> Option<String> {
+        self.indent_with(row, unit, |anchor_row| self.leading_whitespace(anchor_row).to_string())
+    }
+
+    fn indent_with(&self, row: usize, unit: &str, base: impl Fn(usize) -> String) -> Option<String> {
+        match self.anchor(row)? {
+            Anchor::None => Some(String::new()),
+            Anchor::Line { row, outdent: true } => Some(base(row)),
+            Anchor::Line { row, outdent: false } => Some(base(row) + unit),
+        }
+    }
+
+    /// Edits reindenting every line of the document
+    pub fn reindent(&self, unit: &str) -> Vec<TextEdit> {
+        let mut indents: HashMap<usize, String> = HashMap::new();
+        let mut edits = Vec::new();
+        for row in 0..self.line_starts.len() {
+            let current = self.leading_whitespace(row);
+            if self.line(row).trim().is_empty() {
+                continue;
+            }
+            let base = |anchor_row: usize| {
+                indents.get(&anchor_row).cloned().unwrap_or_else(|| self.leading_whitespace(anchor_row).to_string())
+            };
+            let indent = self.indent_with(row, unit, base).unwrap_or_else(|| current.to_string());
+            if indent != current {
+                edits.push(TextEdit {
+                    range: Range {
+                        start: Position { line: row as u32, character: 0 },
+                        end: Position { line: row as u32, character: current.encode_utf16().count() as u32 },
+                    },
+                    new_text: indent.clone(),
+                });
+            }
+            indents.insert(row, indent);
+        }
+        edits
+    }
+
+    fn anchor(&self, row: usize) -> Option<Anchor> {
+        let text = self.line(row);
+        let line_start = *self.line_starts.get(row)?;
+
+        if text.trim().is_empty() {
+            return Some(self.blank_line_anchor(row));
+        }
+
+        let first = line_start + (text.len() - text.trim_start().len());
+        let leaf = self.root.descendant_for_byte_range(first, first + 1)?;
+        // Inside a token that started earlier (multi-line string or comment)
+        if leaf.start_byte() < first {
+            return None;
+        }
+
+        // Outermost node starting with this line's first token
+        let mut start = leaf;
+        let mut outdent = self.outdent.contains(&leaf.id());
+        while let Some(parent) = start.parent().filter(|p| p.start_byte() == first) {
+            start = parent;
+            outdent |= self.outdent.contains(&start.id());
+        }
+
+        // Climb through sequences (statements of a block) to the enclosing indent node;
+        // anything else starting on an earlier line makes this a continuation line
+        let mut current = start;
+        loop {
+            let Some(parent) = current.parent() else {
+                return Some(Anchor::None);
+            };
+            if self.indent.contains(&parent.id()) {
+                return Some(Anchor::Line { row: parent.start_position().row, outdent });
+            }
+            if parent.parent().is_none() {
+                return Some(Anchor::None);
+            }
+            let in_sequence = current.is_named()
+                && current
+                    .prev_sibling()
+                    .is_some_and(|previous| previous.is_named() && previous.end_position().row < row);
+            if !in_sequence && current.start_byte() != parent.start_byte() {
+                return None;
+            }
+            current = parent;
+        }
+    }
+
+    /// Anchor of a blank line: the innermost indent node still open after the
+    /// previous line
+    fn blank_line_anchor(&self, row: usize) -> Anchor {
+        let Some(previous) = (0..row).rev().find(|&r| !self.line(r).trim().is_empty()) else {
+            return Anchor::None;
+        };
+        let text = self.line(previous).trim_end();
+        let last = self.line_starts[previous] + text.len() - 1;
+        let Some(leaf) = self.root.descendant_for_byte_range(last, last + 1) else {
+            return Anchor::None;
+        };
+
+        let mut node = Some(leaf);
+        while let Some(current) = node {
+            let open = current.end_position().row >= row || !is_closed(current);
+            if self.indent.contains(&current.id()) && open {
+                return Anchor::Line { row: current.start_position().row, outdent: false };
+            }
+            node = current.parent();
+        }
+        Anchor::None
+    }
+}
+
+/// Whether a node ends with its closing bracket (missing when the code is incomplete)
+fn is_closed(node: Node) -> bool {
+    node.child(node.child_count().saturating_sub(1))
+        .is_some_and(|last| matches!(last.kind(), "}" | "]" | ")" | ">") && !last.is_missing())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reindented(source: &str, lang: &str) -> String {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        let indenter = Indenter::new(&tree, source, lang).unwrap();
+        let edits = indenter.reindent("    ");
+
+        let mut lines: Vec<String> = source.split('\n').map(str::to_string).collect();
+        for edit in edits {
+            let line = &mut lines[edit.range.start.line as usize];
+            line.replace_range(..edit.range.end.character as usize, &edit.new_text);
+        }
+        lines.join("\n")
+    }
+
+    fn indent_at(source: &str, lang: &str, row: usize) -> Option<String> {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        Indenter::new(&tree, source, lang).unwrap().indent_for_line(row, "    ")
+    }
+
+    #[test]
+    fn test_queries_compile() {
+        for (lang, _) in INDENT_QUERIES {
+            let mut parser = TreeSitterParser::new().unwrap();
+            if parser.set_language(lang).is_ok() {
+                assert!(query_for(lang).is_some(), "indent query for {} does not compile", lang);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reindent_braces() {
+        let source = "function f(a) {\nif (a) {\n  return [\n1,\n        2,\n  ];\n      }\nconst x = a\n  + 1;\n}\n";
+        assert_eq!(
+            reindented(source, "javascript"),
+            "function f(a) {\n    if (a) {\n        return [\n            1,\n            2,\n        ];\n    }\n    const x = a\n  + 1;\n}\n"
+        );
+
+        let source = "fn main() {\nmatch x {\n1 => {}\n_ => {}\n}\n}\n";
+        assert_eq!(reindented(source, "rust"), "fn main() {\n    match x {\n        1 => {}\n        _ => {}\n    }\n}\n");
+
+        let source = "func f(x int) {\nswitch x {\ncase 1:\nfmt.Println(x)\n}\n}\n";
+        assert_eq!(reindented(source, "go"), "func f(x int) {\n    switch x {\n    case 1:\n        fmt.Println(x)\n    }\n}\n");
+    }
+
+    #[test]
+    fn test_python_indentation() {
+        let source = "class A:\n  def f(self):\n    if x:\n      return [\n        1]\n    else:\n      pass\n";
+        assert_eq!(
+            reindented(source, "python"),
+            "class A:\n    def f(self):\n        if x:\n            return [\n                1]\n        else:\n            pass\n"
+        );
+
+        // A new line after a colon opens the body, after the body's last line stays in it
+        assert_eq!(indent_at("def f():\n\n", "python", 1).as_deref(), Some("    "));
+        assert_eq!(indent_at("def f():\n    x = 1\n\n", "python", 2).as_deref(), Some("    "));
+        // `else:` typed at the body's indentation dedents
+        assert_eq!(indent_at("if x:\n    a\n    else:\n", "python", 2).as_deref(), Some(""));
+        // Multi-line strings are left alone
+        assert_eq!(indent_at("x = \"\"\"\n  text\n\"\"\"\n", "python", 1), None);
+    }
+}
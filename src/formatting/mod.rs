@@ -1,12 +1,29 @@
 //! Code Formatting Module
 //!
 //! Provides formatting capabilities using tree-sitter and external formatters
-
-use anyhow::{Context, Result};
-use std::process::{Command, Stdio};
-use std::io::Write;
+//!
+//! External formatters come from the [`registry`]; their output is diffed against
+//! the document ([`diff`]) so only the changed lines are edited. Range formatting
+//! formats the whole document and keeps the edits in the range ([`range`]).
+//! Without an external formatter, and while typing, lines are reindented from the
+//! syntax tree ([`indent`]). Indentation and whitespace follow the document's
+//! [`FormatSettings`] ([`options`], [`whitespace`]).
+
+use anyhow::Result;
+use std::path::PathBuf;
 use tower_lsp::lsp_types::*;
 use crate::tree_sitter::TreeSitterParser;
+use crate::workspace::{FormatterConfig, WorkspaceConfig};
+
+pub mod diff;
+pub mod editorconfig;
+pub mod indent;
+pub mod options;
+pub mod range;
+pub mod registry;
+pub mod whitespace;
+
+pub use options::FormatSettings;
 
 /// Formatting provider for code formatting
 #[derive(Debug)]
@@ -30,355 +47,161 @@ impl FormattingProvider {
         self
     }
 
+    /// Settings of a document in `workspace`, with the `client`'s request options on
+    /// top and the configured indentation as the last resort
+    pub fn settings(
+        &self,
+        uri: &Url,
+        lang: &str,
+        workspace: &WorkspaceConfig,
+        client: Option<&FormattingOptions>,
+    ) -> FormatSettings {
+        let fallback = FormatSettings { indent_size: self.indent_size, use_tabs: self.use_tabs, ..Default::default() };
+        FormatSettings::resolve(&document_path(uri), lang, workspace, client, fallback)
+    }
+
     /// Format entire document
-    pub fn format_document(
+    ///
+    /// Uses the `configured` formatter, or the language's conventional one. A
+    /// configured formatter that fails is an error; a missing default formatter falls
+    /// back to reindenting. Trailing whitespace and final newlines are handled
+    /// according to `settings` either way.
+    pub async fn format_document(
         &self,
         content: &str,
         lang: &str,
         uri: &Url,
+        configured: Option<&FormatterConfig>,
+        settings: FormatSettings,
     ) -> Result<Vec<TextEdit>> {
-        // Try external formatter first
-        if let Ok(formatted) = self.try_external_formatter(content, lang) {
-            return Ok(vec![TextEdit {
-                range: Range {
-                    start: Position { line: 0, character: 0 },
-                    end: self.get_end_position(content),
-                },
-                new_text: formatted,
-            }]);
+        if let Some(formatter) = registry::resolve(lang, configured) {
+            match formatter.run(content, &document_path(uri)).await {
+                Ok(formatted) => return Ok(diff::line_edits(content, &whitespace::tidy(&formatted, &settings))),
+                Err(e) if configured.is_some() => return Err(e),
+                Err(e) => tracing::debug!("{}; using tree-sitter formatting", e),
+            }
         }
 
         // Fall back to tree-sitter based formatting
-        self.format_with_tree_sitter(content, lang, uri)
+        let mut edits = self.reindent(content, lang, uri, &settings);
+        edits.extend(whitespace::document_edits(content, &settings));
+        Ok(edits)
     }
 
-    /// Format a specific range
-    pub fn format_range(
+    /// Format the line where `ch` was just typed at `position`
+    ///
+    /// `}`, `;` and `:` (Python) reindent the current line, a newline the new line
+    /// and trims the line it ended. Only the line's indentation is edited, and only
+    /// when the syntax tree determines it.
+    pub fn format_on_type(
         &self,
         content: &str,
-        range: Range,
+        position: Position,
+        ch: &str,
         lang: &str,
-        _uri: &Url,
+        uri: &Url,
+        settings: FormatSettings,
     ) -> Result<Vec<TextEdit>> {
-        // Extract range text
-        let start_byte = self.position_to_byte(content, range.start);
-        let end_byte = self.position_to_byte(content, range.end);
-        let range_text = &content[start_byte..end_byte];
-
-        // Format just the range
-        if let Ok(formatted) = self.try_external_formatter(range_text, lang) {
-            return Ok(vec![TextEdit {
-                range,
-                new_text: formatted,
-            }]);
+        match ch {
+            "}" | ";" | "\n" => {}
+            ":" if lang == "python" => {}
+            _ => return Ok(Vec::new()),
         }
 
-        // Fall back to basic formatting
-        self.format_basic(range_text, range)
-    }
-
-    /// Try to use external formatter
-    fn try_external_formatter(&self, content: &str, lang: &str) -> Result<String> {
-        match lang {
-            "javascript" | "typescript" | "tsx" | "jsx" => {
-                self.format_with_prettier(content, lang)
-            }
-            "json" => {
-                self.format_with_prettier(content, "json")
-            }
-            "python" => {
-                self.format_with_black(content)
-            }
-            "rust" => {
-                self.format_with_rustfmt(content)
-            }
-            "go" => {
-                self.format_with_gofmt(content)
-            }
-            _ => Err(anyhow::anyhow!("No external formatter for {}", lang)),
-        }
-    }
-
-    /// Format Python code with black
-    fn format_with_black(&self, content: &str) -> Result<String> {
-        let mut child = Command::new("black")
-            .arg("-")
-            .arg("--quiet")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("Failed to spawn black. Is it installed? Try: pip install black")?;
-
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(content.as_bytes())
-                .context("Failed to write to black stdin")?;
-        }
-
-        let output = child.wait_with_output()
-            .context("Failed to wait for black")?;
-
-        if output.status.success() {
-            String::from_utf8(output.stdout)
-                .context("Black output was not valid UTF-8")
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!("Black formatting failed: {}", stderr))
-        }
-    }
-
-    /// Format JavaScript/TypeScript with prettier
-    fn format_with_prettier(&self, content: &str, lang: &str) -> Result<String> {
-        let parser = match lang {
-            "javascript" | "jsx" => "babel",
-            "typescript" => "typescript",
-            "tsx" => "typescript",
-            "json" => "json",
-            _ => "babel",
+        let mut parser = TreeSitterParser::new()?;
+        parser.set_language(lang)?;
+        let tree = parser.parse(content, uri.as_str())?;
+        let Some(indenter) = indent::Indenter::new(&tree, content, lang) else {
+            return Ok(Vec::new());
         };
 
-        let mut child = Command::new("prettier")
-            .arg("--stdin-filepath")
-            .arg(format!("file.{}", self.get_extension(lang)))
-            .arg("--parser")
-            .arg(parser)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("Failed to spawn prettier. Is it installed? Try: npm install -g prettier")?;
-
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(content.as_bytes())
-                .context("Failed to write to prettier stdin")?;
-        }
-
-        let output = child.wait_with_output()
-            .context("Failed to wait for prettier")?;
-
-        if output.status.success() {
-            String::from_utf8(output.stdout)
-                .context("Prettier output was not valid UTF-8")
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!("Prettier formatting failed: {}", stderr))
-        }
-    }
-
-    /// Format Rust code with rustfmt
-    fn format_with_rustfmt(&self, content: &str) -> Result<String> {
-        let mut child = Command::new("rustfmt")
-            .arg("--emit")
-            .arg("stdout")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("Failed to spawn rustfmt. Is it installed? Try: rustup component add rustfmt")?;
-
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(content.as_bytes())
-                .context("Failed to write to rustfmt stdin")?;
-        }
-
-        let output = child.wait_with_output()
-            .context("Failed to wait for rustfmt")?;
-
-        if output.status.success() {
-            String::from_utf8(output.stdout)
-                .context("Rustfmt output was not valid UTF-8")
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!("Rustfmt formatting failed: {}", stderr))
-        }
-    }
-
-    /// Format Go code with gofmt
-    fn format_with_gofmt(&self, content: &str) -> Result<String> {
-        let mut child = Command::new("gofmt")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("Failed to spawn gofmt. Is Go installed?")?;
-
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(content.as_bytes())
-                .context("Failed to write to gofmt stdin")?;
-        }
-
-        let output = child.wait_with_output()
-            .context("Failed to wait for gofmt")?;
-
-        if output.status.success() {
-            String::from_utf8(output.stdout)
-                .context("Gofmt output was not valid UTF-8")
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow::anyhow!("Gofmt formatting failed: {}", stderr))
+        let mut edits = Vec::new();
+        let row = position.line as usize;
+        if ch == "\n" && row > 0 && settings.trim_trailing_whitespace {
+            edits.extend(whitespace::trailing_whitespace_edits(content, (row - 1, row - 1)));
         }
-    }
 
-    /// Get file extension for language
-    fn get_extension(&self, lang: &str) -> &str {
-        match lang {
-            "javascript" => "js",
-            "typescript" => "ts",
-            "tsx" => "tsx",
-            "jsx" => "jsx",
-            "json" => "json",
-            "python" => "py",
-            "rust" => "rs",
-            "go" => "go",
-            _ => "txt",
+        let line = content.split('\n').nth(row).unwrap_or("");
+        let current = &line[..line.len() - line.trim_start().len()];
+        if let Some(new_indent) = indenter.indent_for_line(row, &settings.unit()).filter(|indent| indent != current) {
+            edits.push(TextEdit {
+                range: Range {
+                    start: Position { line: position.line, character: 0 },
+                    end: Position { line: position.line, character: current.encode_utf16().count() as u32 },
+                },
+                new_text: new_indent,
+            });
         }
+        Ok(edits)
     }
 
-    /// Format using tree-sitter
-    fn format_with_tree_sitter(
+    /// Format a specific range
+    ///
+    /// The range is widened to the complete statements it touches and the whole
+    /// document is formatted for context; only edits on the requested lines are
+    /// returned (see [`range`]). Without a formatter, trailing whitespace on those
+    /// lines is removed unless `settings` keep it.
+    pub async fn format_range(
         &self,
         content: &str,
+        range: Range,
         lang: &str,
         uri: &Url,
+        configured: Option<&FormatterConfig>,
+        settings: FormatSettings,
     ) -> Result<Vec<TextEdit>> {
-        let mut parser = TreeSitterParser::new()?;
-        if parser.set_language(lang).is_ok() {
-            if let Ok(tree) = parser.parse(content, uri.as_str()) {
-                return self.format_tree(&tree, content);
+        let requested = range::requested_lines(range);
+        let expanded = self.expand_range(content, lang, uri, requested);
+
+        if let Some(formatter) = registry::resolve(lang, configured) {
+            match formatter.run_range(content, &document_path(uri), expanded).await {
+                Ok(formatted) => {
+                    let edits = diff::line_edits(content, &whitespace::tidy(&formatted, &settings));
+                    return Ok(range::edits_in_lines(edits, requested, expanded));
+                }
+                Err(e) if configured.is_some() => return Err(e),
+                Err(e) => tracing::debug!("{}; only trimming trailing whitespace", e),
             }
         }
 
-        // Fall back to basic formatting
-        let end_pos = self.get_end_position(content);
-        self.format_basic(content, Range {
-            start: Position { line: 0, character: 0 },
-            end: end_pos,
-        })
-    }
-
-    /// Format based on AST structure
-    fn format_tree(&self, tree: &tree_sitter::Tree, source: &str) -> Result<Vec<TextEdit>> {
-        let mut edits = Vec::new();
-        let root = tree.root_node();
-
-        // Walk the tree and collect formatting edits
-        self.format_node_recursive(root, source, 0, &mut edits)?;
-
-        Ok(edits)
-    }
-
-    /// Recursively format nodes
-    fn format_node_recursive(
-        &self,
-        node: tree_sitter::Node,
-        source: &str,
-        depth: usize,
-        edits: &mut Vec<TextEdit>,
-    ) -> Result<()> {
-        // Check if node needs indentation fix
-        if node.start_position().column != depth * self.indent_size {
-            let indent = if self.use_tabs {
-                "\t".repeat(depth)
-            } else {
-                " ".repeat(depth * self.indent_size)
-            };
-
-            let start_pos = Position {
-                line: node.start_position().row as u32,
-                character: 0,
-            };
-            let end_pos = Position {
-                line: node.start_position().row as u32,
-                character: node.start_position().column as u32,
-            };
-
-            edits.push(TextEdit {
-                range: Range {
-                    start: start_pos,
-                    end: end_pos,
-                },
-                new_text: indent,
-            });
+        if !settings.trim_trailing_whitespace {
+            return Ok(Vec::new());
         }
+        Ok(whitespace::trailing_whitespace_edits(content, requested))
+    }
 
-        // Process children
-        let mut child_depth = depth;
-        if self.should_increase_indent(&node) {
-            child_depth += 1;
+    /// `lines` widened to complete syntax nodes, unchanged without a grammar
+    fn expand_range(&self, content: &str, lang: &str, uri: &Url, lines: range::Lines) -> range::Lines {
+        let Ok(mut parser) = TreeSitterParser::new() else {
+            return lines;
+        };
+        if parser.set_language(lang).is_err() {
+            return lines;
         }
-
-        for child in node.children(&mut node.walk()) {
-            self.format_node_recursive(child, source, child_depth, edits)?;
+        match parser.parse(content, uri.as_str()) {
+            Ok(tree) => range::expand_to_complete_nodes(&tree, content, lines),
+            Err(_) => lines,
         }
-
-        Ok(())
     }
 
-    /// Check if node should increase indentation for children
-    fn should_increase_indent(&self, node: &tree_sitter::Node) -> bool {
-        matches!(
-            node.kind(),
-            "block" | "statement_block" | "function_definition" | "class_definition" |
-            "function_declaration" | "class_declaration" | "object" | "array"
-        )
-    }
-
-    /// Basic formatting (whitespace normalization)
-    fn format_basic(&self, content: &str, range: Range) -> Result<Vec<TextEdit>> {
-        let mut formatted = String::new();
-        let mut in_whitespace = false;
-
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if !trimmed.is_empty() {
-                if in_whitespace {
-                    formatted.push('\n');
-                }
-                formatted.push_str(trimmed);
-                formatted.push('\n');
-                in_whitespace = false;
-            } else {
-                in_whitespace = true;
-            }
+    /// Reindent using the language's indent query, nothing without one
+    fn reindent(&self, content: &str, lang: &str, uri: &Url, settings: &FormatSettings) -> Vec<TextEdit> {
+        let Ok(mut parser) = TreeSitterParser::new() else {
+            return Vec::new();
+        };
+        if parser.set_language(lang).is_err() {
+            return Vec::new();
         }
-
-        Ok(vec![TextEdit {
-            range,
-            new_text: formatted,
-        }])
-    }
-
-    /// Get end position of content
-    fn get_end_position(&self, content: &str) -> Position {
-        let lines: Vec<&str> = content.lines().collect();
-        let line = lines.len().saturating_sub(1) as u32;
-        let character = lines.last().map(|l| l.len()).unwrap_or(0) as u32;
-        Position { line, character }
+        let Ok(tree) = parser.parse(content, uri.as_str()) else {
+            return Vec::new();
+        };
+        indent::Indenter::new(&tree, content, lang).map_or_else(Vec::new, |indenter| indenter.reindent(&settings.unit()))
     }
+}
 
-    /// Convert LSP position to byte offset
-    fn position_to_byte(&self, source: &str, position: Position) -> usize {
-        let mut byte_offset = 0;
-        let mut current_line = 0;
-        let mut current_char = 0;
-
-        for ch in source.chars() {
-            if current_line == position.line && current_char == position.character {
-                return byte_offset;
-            }
-
-            if ch == '\n' {
-                current_line += 1;
-                current_char = 0;
-            } else {
-                current_char += 1;
-            }
-
-            byte_offset += ch.len_utf8();
-        }
-
-        byte_offset
-    }
+/// Path handed to formatters, also for documents that are not saved files
+fn document_path(uri: &Url) -> PathBuf {
+    uri.to_file_path().unwrap_or_else(|_| PathBuf::from(uri.path()))
 }
 
 impl Default for FormattingProvider {
@@ -390,33 +213,25 @@ impl Default for FormattingProvider {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::code_actions::edits::apply_text_edits;
+    use std::path::Path;
 
-    #[test]
-    fn test_basic_formatting() {
+    #[tokio::test]
+    async fn test_basic_formatting() {
         let formatter = FormattingProvider::new();
         let content = "fn main()   {   \n\n\n    println!(\"hello\");    \n\n}";
         let uri = Url::parse("file:///test.rs").unwrap();
 
-        let edits = formatter.format_document(content, "rust", &uri);
+        let edits = formatter.format_document(content, "rust", &uri, None, FormatSettings::default()).await;
         assert!(edits.is_ok());
     }
 
-    #[test]
-    fn test_get_end_position() {
-        let formatter = FormattingProvider::new();
-        let content = "line1\nline2\nline3";
-        let pos = formatter.get_end_position(content);
-        assert_eq!(pos.line, 2);
-        assert_eq!(pos.character, 5);
-    }
-
-    #[test]
-    fn test_python_formatting_with_black() {
-        let formatter = FormattingProvider::new();
+    #[tokio::test]
+    async fn test_python_formatting_with_black() {
         let content = "def   hello(  ):  print('world')";
 
         // Try formatting with black
-        let result = formatter.format_with_black(content);
+        let result = registry::preset("black").unwrap().run(content, Path::new("/tmp/test.py")).await;
 
         // If black is installed, should succeed and format correctly
         // If not installed, should error gracefully
@@ -433,12 +248,11 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_javascript_formatting_with_prettier() {
-        let formatter = FormattingProvider::new();
+    #[tokio::test]
+    async fn test_javascript_formatting_with_prettier() {
         let content = "function   hello(  ) {  console.log('world')  }";
 
-        let result = formatter.format_with_prettier(content, "javascript");
+        let result = registry::preset("prettier").unwrap().run(content, Path::new("/tmp/test.js")).await;
 
         match result {
             Ok(formatted) => {
@@ -453,12 +267,11 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_rust_formatting_with_rustfmt() {
-        let formatter = FormattingProvider::new();
+    #[tokio::test]
+    async fn test_rust_formatting_with_rustfmt() {
         let content = "fn   main(  ) {  println!(\"hello\");  }";
 
-        let result = formatter.format_with_rustfmt(content);
+        let result = registry::preset("rustfmt").unwrap().run(content, Path::new("/tmp/test.rs")).await;
 
         match result {
             Ok(formatted) => {
@@ -474,12 +287,11 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_go_formatting_with_gofmt() {
-        let formatter = FormattingProvider::new();
+    #[tokio::test]
+    async fn test_go_formatting_with_gofmt() {
         let content = "package main\nfunc   main(  ) {  println(\"hello\")  }";
 
-        let result = formatter.format_with_gofmt(content);
+        let result = registry::preset("gofmt").unwrap().run(content, Path::new("/tmp/test.go")).await;
 
         match result {
             Ok(formatted) => {
@@ -494,10 +306,10 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_format_range() {
+    #[tokio::test]
+    async fn test_format_range() {
         let formatter = FormattingProvider::new();
-        let content = "def hello():\n    print('world')\n\ndef goodbye():\n    print('bye')";
+        let content = "def hello():\n    print('world')   \n\ndef goodbye():\n    print('bye')  ";
         let uri = Url::parse("file:///test.py").unwrap();
 
         // Format just the first function
@@ -506,56 +318,81 @@ mod tests {
             end: Position { line: 1, character: 20 },
         };
 
-        let edits = formatter.format_range(content, range, "python", &uri);
-        assert!(edits.is_ok());
+        // Without a formatter only trailing whitespace in the range goes
+        let unavailable = FormatterConfig::Command("no-such-formatter".to_string());
+        assert!(formatter.format_range(content, range, "python", &uri, Some(&unavailable), FormatSettings::default()).await.is_err());
+        let edits = formatter.format_range(content, range, "unsupported", &uri, None, FormatSettings::default()).await.unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, Position { line: 1, character: 18 });
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_format_range_with_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("fake-fmt.sh");
+        // Records its arguments, then collapses runs of spaces after the indentation
+        std::fs::write(&script, format!("echo \"$@\" > {}/args\nsed 's/\\([^ ]\\)  */\\1 /g'\n", dir.path().display())).unwrap();
 
-        let edits = edits.unwrap();
+        let formatter = FormattingProvider::new();
+        let uri = Url::from_file_path(dir.path().join("main.py")).unwrap();
+        let content = "def hello():\n    x  =  1\n\ndef goodbye():\n    y  =  call(\n        1,  2)\n    return  y\n";
+        let configured = FormatterConfig::Custom {
+            command: "sh".to_string(),
+            args: vec![script.to_string_lossy().to_string(), "-".to_string()],
+            mode: crate::workspace::FormatterMode::Stdin,
+            timeout_ms: None,
+            range_args: vec!["--lines={start_line}-{end_line}".to_string()],
+        };
+
+        // The continuation line is formatted in the context of its statement,
+        // keeping its indentation; nothing outside the range changes
+        let range = Range {
+            start: Position { line: 5, character: 8 },
+            end: Position { line: 5, character: 10 },
+        };
+        let edits = formatter.format_range(content, range, "python", &uri, Some(&configured), FormatSettings::default()).await.unwrap();
         assert_eq!(edits.len(), 1);
-        assert_eq!(edits[0].range, range);
+        assert_eq!(edits[0].range.start, Position { line: 5, character: 0 });
+        assert_eq!(edits[0].new_text, "        1, 2)\n");
+
+        // Native range arguments cover the expanded statement, before the `-`
+        let args = std::fs::read_to_string(dir.path().join("args")).unwrap();
+        assert!(args.trim_end().ends_with("--lines=5-6 -"), "{}", args);
     }
 
-    #[test]
-    fn test_format_document_fallback() {
+    #[tokio::test]
+    async fn test_format_document_fallback() {
         let formatter = FormattingProvider::new();
         // Use an unsupported language to test fallback
         let content = "some random text\n  with   weird spacing";
         let uri = Url::parse("file:///test.txt").unwrap();
 
-        let edits = formatter.format_document(content, "unsupported", &uri);
+        let edits = formatter.format_document(content, "unsupported", &uri, None, FormatSettings::default()).await;
         assert!(edits.is_ok());
     }
 
-    #[test]
-    fn test_position_to_byte() {
-        let formatter = FormattingProvider::new();
-        let content = "hello\nworld\ntest";
-
-        // Position at start
-        assert_eq!(formatter.position_to_byte(content, Position { line: 0, character: 0 }), 0);
-
-        // Position at end of first line
-        assert_eq!(formatter.position_to_byte(content, Position { line: 0, character: 5 }), 5);
-
-        // Position at start of second line (after newline)
-        assert_eq!(formatter.position_to_byte(content, Position { line: 1, character: 0 }), 6);
-
-        // Position in middle of second line
-        assert_eq!(formatter.position_to_byte(content, Position { line: 1, character: 3 }), 9);
-    }
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_configured_formatter_edits_changed_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("fake-fmt.sh");
+        std::fs::write(&script, "sed 's/  */ /g'\n").unwrap();
 
-    #[test]
-    fn test_get_extension() {
         let formatter = FormattingProvider::new();
+        let uri = Url::from_file_path(dir.path().join("main.py")).unwrap();
+        let content = "import os\n\nx  =  1\nprint(x)\n";
+        let configured = FormatterConfig::Command(format!("sh {}", script.display()));
 
-        assert_eq!(formatter.get_extension("javascript"), "js");
-        assert_eq!(formatter.get_extension("typescript"), "ts");
-        assert_eq!(formatter.get_extension("tsx"), "tsx");
-        assert_eq!(formatter.get_extension("jsx"), "jsx");
-        assert_eq!(formatter.get_extension("python"), "py");
-        assert_eq!(formatter.get_extension("rust"), "rs");
-        assert_eq!(formatter.get_extension("go"), "go");
-        assert_eq!(formatter.get_extension("json"), "json");
-        assert_eq!(formatter.get_extension("unknown"), "txt");
+        let edits = formatter.format_document(content, "python", &uri, Some(&configured), FormatSettings::default()).await.unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range.start, Position { line: 2, character: 0 });
+        assert_eq!(edits[0].range.end, Position { line: 3, character: 0 });
+        assert_eq!(edits[0].new_text, "x = 1\n");
+
+        // A configured formatter that fails is reported instead of silently replaced
+        let failing = FormatterConfig::Command("false".to_string());
+        assert!(formatter.format_document(content, "python", &uri, Some(&failing), FormatSettings::default()).await.is_err());
     }
 
     #[test]
@@ -568,17 +405,65 @@ mod tests {
     }
 
     #[test]
-    fn test_empty_content_formatting() {
+    fn test_format_on_type() {
+        let formatter = FormattingProvider::new();
+        let uri = Url::parse("file:///test.js").unwrap();
+        let two = FormatSettings { indent_size: 2, ..Default::default() };
+
+        // A closing brace typed at the body's indentation moves out
+        let content = "function f() {\n  if (x) {\n    y();\n    }\n}\n";
+        let position = Position { line: 3, character: 5 };
+        let edits = formatter.format_on_type(content, position, "}", "javascript", &uri, two).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, "  ");
+        assert_eq!(edits[0].range.end, Position { line: 3, character: 4 });
+
+        // Enter inside a block indents the new line
+        let content = "function f() {\n  if (x) {  \n\n  }\n}\n";
+        let position = Position { line: 2, character: 0 };
+        let edits = formatter.format_on_type(content, position, "\n", "javascript", &uri, two).unwrap();
+        assert_eq!(edits.len(), 2);
+        // The line the newline ended loses its trailing whitespace
+        assert_eq!(edits[0].range.start, Position { line: 1, character: 10 });
+        assert_eq!(edits[1].new_text, "    ");
+
+        // `:` only formats Python, where it dedents `else`
+        let uri = Url::parse("file:///test.py").unwrap();
+        let content = "if x:\n    a\n    else:\n";
+        let position = Position { line: 2, character: 9 };
+        let four = FormatSettings::default();
+        let edits = formatter.format_on_type(content, position, ":", "python", &uri, four).unwrap();
+        assert_eq!(edits[0].new_text, "");
+        assert!(formatter.format_on_type(content, position, ":", "javascript", &uri, four).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_format_document_settings() {
+        let formatter = FormattingProvider::new();
+        let uri = Url::parse("file:///test.c").unwrap();
+        let content = "int main() {\nreturn 0;   \n}\n\n\n";
+
+        let settings = FormatSettings { use_tabs: true, trim_final_newlines: true, ..Default::default() };
+        let edits = formatter.format_document(content, "c", &uri, None, settings).await.unwrap();
+        assert_eq!(apply_text_edits(content, &edits), "int main() {\n\treturn 0;\n}\n");
+
+        let settings = FormatSettings { trim_trailing_whitespace: false, ..Default::default() };
+        let edits = formatter.format_document(content, "c", &uri, None, settings).await.unwrap();
+        assert_eq!(apply_text_edits(content, &edits), "int main() {\n    return 0;   \n}\n\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_empty_content_formatting() {
         let formatter = FormattingProvider::new();
         let content = "";
         let uri = Url::parse("file:///test.rs").unwrap();
 
-        let edits = formatter.format_document(content, "rust", &uri);
+        let edits = formatter.format_document(content, "rust", &uri, None, FormatSettings::default()).await;
         assert!(edits.is_ok());
     }
 
-    #[test]
-    fn test_multiline_python_formatting() {
+    #[tokio::test]
+    async fn test_multiline_python_formatting() {
         let formatter = FormattingProvider::new();
         let content = r#"
 def calculate(a,b,c):
@@ -591,12 +476,12 @@ class MyClass:
 "#;
         let uri = Url::parse("file:///test.py").unwrap();
 
-        let edits = formatter.format_document(content, "python", &uri);
+        let edits = formatter.format_document(content, "python", &uri, None, FormatSettings::default()).await;
         assert!(edits.is_ok());
     }
 
-    #[test]
-    fn test_multiline_javascript_formatting() {
+    #[tokio::test]
+    async fn test_multiline_javascript_formatting() {
         let formatter = FormattingProvider::new();
         let content = r#"
 function calculate(a,b,c){
@@ -612,7 +497,7 @@ class MyClass{
 "#;
         let uri = Url::parse("file:///test.js").unwrap();
 
-        let edits = formatter.format_document(content, "javascript", &uri);
+        let edits = formatter.format_document(content, "javascript", &uri, None, FormatSettings::default()).await;
         assert!(edits.is_ok());
     }
 }
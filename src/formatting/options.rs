@@ -0,0 +1,186 @@
+//! Formatting settings of a document
+//!
+//! Resolved per document in layers, most specific first: the `FormattingOptions` of
+//! the client's request, `.editorconfig` sections matching the file, the workspace
+//! configuration (with its language overrides), the language's convention and
+//! finally the provider's default.
+
+use std::path::Path;
+use tower_lsp::lsp_types::FormattingOptions;
+
+use crate::workspace::WorkspaceConfig;
+
+use super::editorconfig;
+
+/// Indentation and whitespace settings of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatSettings {
+    pub indent_size: usize,
+    pub use_tabs: bool,
+    pub trim_trailing_whitespace: bool,
+    pub insert_final_newline: bool,
+    pub trim_final_newlines: bool,
+}
+
+impl FormatSettings {
+    /// One level of indentation
+    pub fn unit(&self) -> String {
+        if self.use_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.indent_size)
+        }
+    }
+
+    /// Settings of `path` in language `lang`
+    pub fn resolve(
+        path: &Path,
+        lang: &str,
+        workspace: &WorkspaceConfig,
+        client: Option<&FormattingOptions>,
+        fallback: FormatSettings,
+    ) -> Self {
+        let mut settings = fallback;
+        if let Some((indent_size, use_tabs)) = language_default(lang) {
+            settings.indent_size = indent_size;
+            settings.use_tabs = use_tabs;
+        }
+
+        let layer = |settings: &mut Self, layer: Layer| {
+            if let Some(size) = layer.indent_size {
+                settings.indent_size = size;
+            }
+            if let Some(use_tabs) = layer.use_tabs {
+                settings.use_tabs = use_tabs;
+            }
+            if let Some(trim) = layer.trim_trailing_whitespace {
+                settings.trim_trailing_whitespace = trim;
+            }
+            if let Some(insert) = layer.insert_final_newline {
+                settings.insert_final_newline = insert;
+            }
+            if let Some(trim) = layer.trim_final_newlines {
+                settings.trim_final_newlines = trim;
+            }
+        };
+
+        layer(&mut settings, Layer {
+            indent_size: workspace.indent_size,
+            use_tabs: workspace.use_tabs,
+            trim_trailing_whitespace: workspace.trim_trailing_whitespace,
+            insert_final_newline: workspace.insert_final_newline,
+            trim_final_newlines: workspace.trim_final_newlines,
+        });
+        layer(&mut settings, editorconfig_layer(path));
+        if let Some(options) = client {
+            layer(&mut settings, Layer {
+                indent_size: (options.tab_size > 0).then_some(options.tab_size as usize),
+                use_tabs: Some(!options.insert_spaces),
+                trim_trailing_whitespace: options.trim_trailing_whitespace,
+                insert_final_newline: options.insert_final_newline,
+                trim_final_newlines: options.trim_final_newlines,
+            });
+        }
+        settings
+    }
+}
+
+impl Default for FormatSettings {
+    fn default() -> Self {
+        Self {
+            indent_size: 4,
+            use_tabs: false,
+            trim_trailing_whitespace: true,
+            insert_final_newline: false,
+            trim_final_newlines: false,
+        }
+    }
+}
+
+/// Settings one source defines
+struct Layer {
+    indent_size: Option<usize>,
+    use_tabs: Option<bool>,
+    trim_trailing_whitespace: Option<bool>,
+    insert_final_newline: Option<bool>,
+    trim_final_newlines: Option<bool>,
+}
+
+fn editorconfig_layer(path: &Path) -> Layer {
+    let properties = editorconfig::properties(path);
+    let flag = |key: &str| match properties.get(key).map(String::as_str) {
+        Some("true") => Some(true),
+        Some("false") => Some(false),
+        _ => None,
+    };
+    // `indent_size = tab` defers to `tab_width`
+    let indent_size = properties
+        .get("indent_size")
+        .filter(|size| size.as_str() != "tab")
+        .or_else(|| properties.get("tab_width"))
+        .and_then(|size| size.parse().ok());
+
+    Layer {
+        indent_size,
+        use_tabs: match properties.get("indent_style").map(String::as_str) {
+            Some("tab") => Some(true),
+            Some("space") => Some(false),
+            _ => None,
+        },
+        trim_trailing_whitespace: flag("trim_trailing_whitespace"),
+        // `false` means "leave the end alone" rather than removing the newline
+        insert_final_newline: flag("insert_final_newline"),
+        trim_final_newlines: None,
+    }
+}
+
+/// Conventional indentation of a language as `(indent_size, use_tabs)`
+fn language_default(lang: &str) -> Option<(usize, bool)> {
+    match lang {
+        "go" => Some((4, true)),
+        "javascript" | "typescript" | "tsx" | "json" | "html" | "css" | "yaml" | "ruby" | "lua" => Some((2, false)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_layers() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = WorkspaceConfig::default();
+        let resolve = |file: &str, lang: &str, workspace: &WorkspaceConfig, client: Option<&FormattingOptions>| {
+            FormatSettings::resolve(&dir.path().join(file), lang, workspace, client, FormatSettings::default())
+        };
+
+        // Language convention, then the workspace configuration
+        assert_eq!(resolve("a.ts", "typescript", &workspace, None).unit(), "  ");
+        assert_eq!(resolve("a.go", "go", &workspace, None).unit(), "\t");
+        let configured = WorkspaceConfig { indent_size: Some(3), insert_final_newline: Some(true), ..Default::default() };
+        assert_eq!(resolve("a.ts", "typescript", &configured, None).unit(), "   ");
+        assert!(resolve("a.ts", "typescript", &configured, None).insert_final_newline);
+
+        // `.editorconfig` wins over both
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n[*.ts]\nindent_style = tab\ntrim_trailing_whitespace = false\n[*.py]\nindent_size = tab\ntab_width = 8\n",
+        )
+        .unwrap();
+        let settings = resolve("a.ts", "typescript", &configured, None);
+        assert!(settings.use_tabs && !settings.trim_trailing_whitespace && settings.insert_final_newline);
+        assert_eq!(resolve("a.py", "python", &configured, None).indent_size, 8);
+
+        // And the client's request over everything
+        let options = FormattingOptions {
+            tab_size: 2,
+            insert_spaces: true,
+            trim_final_newlines: Some(true),
+            ..Default::default()
+        };
+        let settings = resolve("a.ts", "typescript", &configured, Some(&options));
+        assert_eq!(settings.unit(), "  ");
+        assert!(settings.trim_final_newlines && !settings.trim_trailing_whitespace);
+    }
+}
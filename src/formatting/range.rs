@@ -0,0 +1,158 @@
+//! Range formatting
+//!
+//! Formatters only produce sensible output for complete syntax, so a requested range
+//! is first widened to the statements, declarations and comments it touches. The
+//! whole document is then formatted (with the formatter's native range flags when it
+//! has them) and only the resulting edits on the requested lines are kept.
+
+use tower_lsp::lsp_types::*;
+use tree_sitter::{Node, Tree};
+
+/// Inclusive span of zero-based line numbers
+pub type Lines = (usize, usize);
+
+/// Lines covered by `range`; a range ending at the start of a line leaves it out
+pub fn requested_lines(range: Range) -> Lines {
+    let (first, end) = (range.start.line as usize, range.end.line as usize);
+    let last = if range.end.character == 0 && end > first { end - 1 } else { end };
+    (first, last.max(first))
+}
+
+/// `lines` widened to the complete syntax nodes starting or ending on them
+pub fn expand_to_complete_nodes(tree: &Tree, source: &str, lines: Lines) -> Lines {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_text = |line: usize| {
+        let start = line_starts.get(line).copied().unwrap_or(source.len());
+        let end = line_starts.get(line + 1).map_or(source.len(), |next| next - 1);
+        (start, &source[start..end.max(start)])
+    };
+
+    // First and last non-blank characters of the span
+    let first_code = (lines.0..=lines.1).find_map(|line| {
+        let (start, text) = line_text(line);
+        let indent = text.len() - text.trim_start().len();
+        (!text.trim().is_empty()).then_some(start + indent)
+    });
+    let last_code = (lines.0..=lines.1).rev().find_map(|line| {
+        let (start, text) = line_text(line);
+        (!text.trim().is_empty()).then(|| start + text.trim_end().len() - 1)
+    });
+    let (Some(first_code), Some(last_code)) = (first_code, last_code) else {
+        return lines;
+    };
+
+    let root = tree.root_node();
+    let first_node = complete_node(root, first_code);
+    let last_node = complete_node(root, last_code);
+    let first = first_node.map_or(lines.0, |node| node.start_position().row.min(lines.0));
+    let last = last_node.map_or(lines.1, |node| node.end_position().row.max(lines.1));
+    (first, last)
+}
+
+/// Innermost statement, declaration or comment around `byte`
+fn complete_node(root: Node, byte: usize) -> Option<Node> {
+    let mut node = root.descendant_for_byte_range(byte, byte + 1)?;
+    loop {
+        let parent = node.parent()?;
+        if is_complete(node.kind()) || parent.parent().is_none() {
+            return Some(node);
+        }
+        node = parent;
+    }
+}
+
+fn is_complete(kind: &str) -> bool {
+    kind.ends_with("statement")
+        || kind.ends_with("declaration")
+        || kind.ends_with("definition")
+        || kind.ends_with("_item")
+        || kind.ends_with("comment")
+        || matches!(kind, "decorated_definition" | "method_definition" | "element" | "rule_set" | "pair")
+}
+
+/// Old lines replaced by an edit, as an inclusive span; an insertion is the line it
+/// is inserted before
+fn edited_lines(edit: &TextEdit) -> Lines {
+    let (start, end) = (edit.range.start, edit.range.end);
+    let last = if end.character == 0 && end.line > start.line { end.line - 1 } else { end.line };
+    (start.line as usize, last as usize)
+}
+
+/// Edits within `expanded` that touch the `requested` lines
+pub fn edits_in_lines(edits: Vec<TextEdit>, requested: Lines, expanded: Lines) -> Vec<TextEdit> {
+    edits
+        .into_iter()
+        .filter(|edit| {
+            let (first, last) = edited_lines(edit);
+            first >= expanded.0 && last <= expanded.1 && first <= requested.1 && last >= requested.0
+        })
+        .collect()
+}
+
+/// UTF-16 offsets of the start of line `first` and end of line `last`, as formatters
+/// taking character ranges (prettier) expect them
+pub fn line_offsets(source: &str, (first, last): Lines) -> (usize, usize) {
+    let mut offset = 0;
+    let mut start = None;
+    for (line, text) in source.split_inclusive('\n').enumerate() {
+        if line == first {
+            start = Some(offset);
+        }
+        offset += text.trim_end_matches('\n').encode_utf16().count();
+        if line == last {
+            return (start.unwrap_or(offset), offset);
+        }
+        offset += usize::from(text.ends_with('\n'));
+    }
+    (start.unwrap_or(offset), offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_sitter::TreeSitterParser;
+
+    fn expand(source: &str, lang: &str, lines: Lines) -> Lines {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        expand_to_complete_nodes(&tree, source, lines)
+    }
+
+    #[test]
+    fn test_expand_to_complete_nodes() {
+        let source = "def f():\n    x = call(\n        1,\n        2)\n    y = 3\n    return x\n";
+
+        // A continuation line widens to its statement, a whole statement stays as is
+        assert_eq!(expand(source, "python", (2, 2)), (1, 3));
+        assert_eq!(expand(source, "python", (4, 4)), (4, 4));
+        assert_eq!(expand(source, "python", (3, 4)), (1, 4));
+        // The header line takes the whole function
+        assert_eq!(expand(source, "python", (0, 0)), (0, 5));
+
+        let source = "fn main() {\n    let v = vec![\n        1,\n    ];\n}\n";
+        assert_eq!(expand(source, "rust", (2, 2)), (1, 3));
+    }
+
+    #[test]
+    fn test_edits_in_lines() {
+        let edit = |first: u32, last: u32| TextEdit {
+            range: Range {
+                start: Position { line: first, character: 0 },
+                end: Position { line: last + 1, character: 0 },
+            },
+            new_text: String::new(),
+        };
+        let edits = vec![edit(0, 0), edit(2, 3), edit(4, 4), edit(5, 7)];
+        let kept = edits_in_lines(edits, (3, 4), (2, 5));
+        assert_eq!(kept, vec![edit(2, 3), edit(4, 4)]);
+
+        assert_eq!(requested_lines(Range {
+            start: Position { line: 1, character: 4 },
+            end: Position { line: 3, character: 0 },
+        }), (1, 2));
+        assert_eq!(line_offsets("ab\ncd\nef\n", (1, 1)), (3, 5));
+    }
+}
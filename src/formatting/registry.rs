@@ -0,0 +1,322 @@
+//! External formatters
+//!
+//! Each language can declare its formatter in `LanguageConfig.formatter`, either by
+//! preset name (`black`, `ruff`, `prettier`, `rustfmt`, `gofmt`, `clang-format`) or as
+//! a command; commands are only kept for trusted workspaces (see `workspace`).
+//! Languages without one use their conventional formatter when it is installed.
+//! Formatters that can restrict themselves to a range of lines declare the arguments
+//! for it (`range_args`), used by range formatting.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use super::range::{line_offsets, Lines};
+use crate::workspace::{FormatterConfig, FormatterMode};
+
+/// Time a formatter may run unless configured otherwise
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A formatter command ready to run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formatter {
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub mode: FormatterMode,
+    pub timeout: Duration,
+    /// Arguments limiting formatting to a range, with `{start_line}` and `{end_line}`
+    /// (one-based, inclusive) and `{start_offset}` and `{end_offset}` placeholders
+    pub range_args: Vec<String>,
+}
+
+impl Formatter {
+    fn stdin(name: &str, program: &str, args: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            mode: FormatterMode::Stdin,
+            timeout: DEFAULT_TIMEOUT,
+            range_args: Vec::new(),
+        }
+    }
+
+    fn with_range_args(mut self, range_args: &[&str]) -> Self {
+        self.range_args = range_args.iter().map(|a| a.to_string()).collect();
+        self
+    }
+
+    /// Format the whole document at `path`, asking the formatter to only touch
+    /// `lines` when it supports ranges
+    ///
+    /// Formatter versions without range support reject the arguments; they format the
+    /// whole document instead, the caller keeps only the edits on `lines`.
+    pub async fn run_range(&self, content: &str, path: &Path, lines: Lines) -> Result<String> {
+        if self.range_args.is_empty() {
+            return self.run(content, path).await;
+        }
+
+        let (start_offset, end_offset) = line_offsets(content, lines);
+        let placeholders = [
+            ("{start_line}", (lines.0 + 1).to_string()),
+            ("{end_line}", (lines.1 + 1).to_string()),
+            ("{start_offset}", start_offset.to_string()),
+            ("{end_offset}", end_offset.to_string()),
+        ];
+        let range_args = self.range_args.iter().map(|arg| {
+            placeholders.iter().fold(arg.clone(), |arg, (placeholder, value)| arg.replace(placeholder, value))
+        });
+
+        // Options go before a trailing `-` (read stdin) argument
+        let mut ranged = self.clone();
+        let stdin_marker = ranged.args.last().is_some_and(|arg| arg == "-");
+        let position = if stdin_marker { ranged.args.len() - 1 } else { ranged.args.len() };
+        ranged.args.splice(position..position, range_args);
+
+        match ranged.run(content, path).await {
+            Ok(formatted) => Ok(formatted),
+            Err(e) => {
+                tracing::debug!("{} without range support: {}", self.name, e);
+                self.run(content, path).await
+            }
+        }
+    }
+
+    /// Format `content` of the document at `path`
+    ///
+    /// The formatter runs in the document's directory so it picks up the project's
+    /// own configuration (`pyproject.toml`, `.prettierrc`, `rustfmt.toml`).
+    pub async fn run(&self, content: &str, path: &Path) -> Result<String> {
+        tokio::time::timeout(self.timeout, self.execute(content, path))
+            .await
+            .with_context(|| format!("{} timed out after {:?}", self.name, self.timeout))?
+    }
+
+    async fn execute(&self, content: &str, path: &Path) -> Result<String> {
+        let temp_file = match self.mode {
+            FormatterMode::Stdin => None,
+            FormatterMode::TempFile => Some(TempFile::new(path, content)?),
+        };
+        let file_arg = temp_file.as_ref().map_or(path, |temp| temp.path.as_path()).to_string_lossy().to_string();
+
+        let mut args: Vec<String> = self.args.iter().map(|arg| arg.replace("{file}", &file_arg)).collect();
+        if temp_file.is_some() && !self.args.iter().any(|arg| arg.contains("{file}")) {
+            args.push(file_arg.clone());
+        }
+
+        let mut command = tokio::process::Command::new(&self.program);
+        command
+            .args(&args)
+            .stdin(if temp_file.is_some() { Stdio::null() } else { Stdio::piped() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(dir) = path.parent().filter(|dir| dir.is_dir()) {
+            command.current_dir(dir);
+        }
+
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to spawn {}. Is it installed?", self.program))?;
+        // Feed stdin while reading stdout, so large documents cannot fill both pipes
+        let stdin = child.stdin.take();
+        let write = async move {
+            match stdin {
+                Some(mut stdin) => stdin.write_all(content.as_bytes()).await,
+                None => Ok(()),
+            }
+        };
+        let (written, output) = tokio::join!(write, child.wait_with_output());
+        let output = output.with_context(|| format!("Failed to wait for {}", self.name))?;
+
+        if !output.status.success() {
+            bail!("{} formatting failed: {}", self.name, String::from_utf8_lossy(&output.stderr).trim());
+        }
+        written.with_context(|| format!("Failed to write to {} stdin", self.name))?;
+        let formatted = match &temp_file {
+            Some(temp) => std::fs::read_to_string(&temp.path)
+                .with_context(|| format!("Failed to read the file formatted by {}", self.name))?,
+            None => String::from_utf8(output.stdout).with_context(|| format!("{} output was not valid UTF-8", self.name))?,
+        };
+        // An empty result for a non-empty document is a failure, not a deletion
+        if formatted.trim().is_empty() && !content.trim().is_empty() {
+            bail!("{} returned no output", self.name);
+        }
+        Ok(formatted)
+    }
+}
+
+/// Formatter for `lang`: the configured one, else the language's preset
+pub fn resolve(lang: &str, configured: Option<&FormatterConfig>) -> Option<Formatter> {
+    match configured {
+        Some(FormatterConfig::Command(command)) => {
+            preset(command.trim()).or_else(|| {
+                let mut parts = command.split_whitespace();
+                let program = parts.next()?;
+                let args: Vec<&str> = parts.collect();
+                Some(Formatter::stdin(&program_name(program), program, &args))
+            })
+        }
+        Some(FormatterConfig::Custom { command, args, mode, timeout_ms, range_args }) => Some(Formatter {
+            name: program_name(command),
+            program: command.clone(),
+            args: args.clone(),
+            mode: *mode,
+            timeout: timeout_ms.map_or(DEFAULT_TIMEOUT, Duration::from_millis),
+            range_args: range_args.clone(),
+        }),
+        None => default_preset(lang).and_then(preset),
+    }
+}
+
+/// Built-in formatter by name
+pub fn preset(name: &str) -> Option<Formatter> {
+    Some(match name {
+        "black" => Formatter::stdin("black", "black", &["--quiet", "--stdin-filename", "{file}", "-"])
+            .with_range_args(&["--line-ranges={start_line}-{end_line}"]),
+        "ruff" => Formatter::stdin("ruff", "ruff", &["format", "--quiet", "--stdin-filename", "{file}", "-"])
+            .with_range_args(&["--range={start_line}-{end_line}"]),
+        "prettier" => Formatter::stdin("prettier", "prettier", &["--stdin-filepath", "{file}"])
+            .with_range_args(&["--range-start={start_offset}", "--range-end={end_offset}"]),
+        "rustfmt" => Formatter::stdin("rustfmt", "rustfmt", &["--emit", "stdout"]),
+        "gofmt" => Formatter::stdin("gofmt", "gofmt", &[]),
+        "clang-format" => Formatter::stdin("clang-format", "clang-format", &["--assume-filename={file}"])
+            .with_range_args(&["--lines={start_line}:{end_line}"]),
+        _ => return None,
+    })
+}
+
+/// Conventional formatter of a language
+fn default_preset(lang: &str) -> Option<&'static str> {
+    match lang {
+        "python" => Some("black"),
+        "javascript" | "typescript" | "tsx" | "jsx" | "json" => Some("prettier"),
+        "rust" => Some("rustfmt"),
+        "go" => Some("gofmt"),
+        _ => None,
+    }
+}
+
+fn program_name(program: &str) -> String {
+    Path::new(program)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| program.to_string())
+}
+
+/// Copy of a document for formatters that rewrite files in place, in a temporary
+/// directory removed on drop
+///
+/// Keeps the document's file name so formatters still infer the language from its
+/// extension.
+struct TempFile {
+    path: PathBuf,
+    _dir: tempfile::TempDir,
+}
+
+impl TempFile {
+    fn new(document: &Path, content: &str) -> Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("universal-lsp-")
+            .tempdir()
+            .context("Failed to create a temporary directory")?;
+        let path = dir.path().join(document.file_name().unwrap_or("document".as_ref()));
+        std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(Self { path, _dir: dir })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fake formatter collapsing runs of spaces, from stdin or in place
+    fn fake_formatter(dir: &Path) -> PathBuf {
+        let script = dir.join("fake-fmt.sh");
+        std::fs::write(
+            &script,
+            "if [ -n \"$1\" ]; then sed 's/  */ /g' \"$1\" > \"$1.out\" && mv \"$1.out\" \"$1\"; else sed 's/  */ /g'; fi\n",
+        )
+        .unwrap();
+        script
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("python", None).unwrap().name, "black");
+        assert!(resolve("markdown", None).is_none());
+
+        let ruff = FormatterConfig::Command("ruff".to_string());
+        assert_eq!(resolve("python", Some(&ruff)).unwrap().program, "ruff");
+
+        let command = FormatterConfig::Command("/usr/bin/yapf --style pep8".to_string());
+        let formatter = resolve("python", Some(&command)).unwrap();
+        assert_eq!((formatter.name.as_str(), formatter.args.len()), ("yapf", 2));
+
+        let custom = FormatterConfig::Custom {
+            command: "sqlfmt".to_string(),
+            args: vec![],
+            mode: FormatterMode::TempFile,
+            timeout_ms: Some(500),
+            range_args: vec![],
+        };
+        let formatter = resolve("sql", Some(&custom)).unwrap();
+        assert_eq!(formatter.timeout, Duration::from_millis(500));
+        assert_eq!(formatter.mode, FormatterMode::TempFile);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_stdin_and_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = fake_formatter(dir.path()).to_string_lossy().to_string();
+        let document = dir.path().join("main.py");
+
+        let stdin = FormatterConfig::Custom {
+            command: "sh".to_string(),
+            args: vec![script.clone()],
+            mode: FormatterMode::Stdin,
+            timeout_ms: None,
+            range_args: vec![],
+        };
+        let formatter = resolve("python", Some(&stdin)).unwrap();
+        assert_eq!(formatter.run("x  =   1\n", &document).await.unwrap(), "x = 1\n");
+
+        let temp_file = FormatterConfig::Custom {
+            command: "sh".to_string(),
+            args: vec![script],
+            mode: FormatterMode::TempFile,
+            timeout_ms: None,
+            range_args: vec![],
+        };
+        let formatter = resolve("python", Some(&temp_file)).unwrap();
+        assert_eq!(formatter.run("y  =   2\n", &document).await.unwrap(), "y = 2\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let document = dir.path().join("main.py");
+
+        let slow = FormatterConfig::Custom {
+            command: "sleep".to_string(),
+            args: vec!["5".to_string()],
+            mode: FormatterMode::Stdin,
+            timeout_ms: Some(100),
+            range_args: vec![],
+        };
+        let error = resolve("python", Some(&slow)).unwrap().run("x = 1\n", &document).await.unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+
+        let failing = FormatterConfig::Command("false".to_string());
+        assert!(resolve("python", Some(&failing)).unwrap().run("x = 1\n", &document).await.is_err());
+
+        let missing = FormatterConfig::Command("no-such-formatter".to_string());
+        let error = resolve("python", Some(&missing)).unwrap().run("x = 1\n", &document).await.unwrap_err();
+        assert!(error.to_string().contains("Failed to spawn"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Whitespace cleanup
+//!
+//! Trailing whitespace and the newlines at the end of a document, handled after
+//! (or instead of) formatting according to the document's [`FormatSettings`].
+
+use tower_lsp::lsp_types::*;
+
+use super::range::Lines;
+use super::FormatSettings;
+
+/// Edits removing trailing whitespace from `lines`
+pub fn trailing_whitespace_edits(content: &str, (first, last): Lines) -> Vec<TextEdit> {
+    content
+        .lines()
+        .enumerate()
+        .skip(first)
+        .take(last + 1 - first)
+        .filter_map(|(line, text)| {
+            let trimmed = text.trim_end();
+            (trimmed.len() < text.len()).then(|| TextEdit {
+                range: Range {
+                    start: Position { line: line as u32, character: trimmed.encode_utf16().count() as u32 },
+                    end: Position { line: line as u32, character: text.encode_utf16().count() as u32 },
+                },
+                new_text: String::new(),
+            })
+        })
+        .collect()
+}
+
+/// Edits applying the whitespace settings to a whole document
+pub fn document_edits(content: &str, settings: &FormatSettings) -> Vec<TextEdit> {
+    let (body, tail) = split_tail(content, settings);
+    let mut edits = Vec::new();
+    if settings.trim_trailing_whitespace && !body.is_empty() {
+        let last = body.lines().count().saturating_sub(1);
+        edits.extend(trailing_whitespace_edits(body, (0, last)));
+    }
+    if tail != content[body.len()..] {
+        edits.push(TextEdit {
+            range: Range { start: position_at(content, body.len()), end: position_at(content, content.len()) },
+            new_text: tail,
+        });
+    }
+    edits
+}
+
+/// `text` with the whitespace settings applied, for formatter output
+pub fn tidy(text: &str, settings: &FormatSettings) -> String {
+    let (body, tail) = split_tail(text, settings);
+    let mut tidied = if settings.trim_trailing_whitespace {
+        body.split('\n').map(str::trim_end).collect::<Vec<_>>().join("\n")
+    } else {
+        body.to_string()
+    };
+    tidied.push_str(&tail);
+    tidied
+}
+
+/// The document up to its end (without the blank lines to trim) and the text that
+/// should follow it
+fn split_tail<'a>(content: &'a str, settings: &FormatSettings) -> (&'a str, String) {
+    if content.is_empty() {
+        return (content, String::new());
+    }
+
+    let mut end = content.len();
+    if settings.trim_final_newlines {
+        loop {
+            let before = content[..end].trim_end_matches([' ', '\t']);
+            match before.strip_suffix('\n') {
+                Some(rest) => end = rest.strip_suffix('\r').unwrap_or(rest).len(),
+                None => break,
+            }
+        }
+        if end == content.len() {
+            return (content, final_newline(content, settings));
+        }
+        // Keep the document's own final newline
+        let newline = if content[end..].starts_with("\r\n") { "\r\n" } else { "\n" };
+        return (&content[..end], newline.to_string());
+    }
+    (content, final_newline(content, settings))
+}
+
+fn final_newline(content: &str, settings: &FormatSettings) -> String {
+    if settings.insert_final_newline && !content.ends_with('\n') {
+        let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+        newline.to_string()
+    } else {
+        String::new()
+    }
+}
+
+fn position_at(content: &str, byte: usize) -> Position {
+    let before = &content[..byte];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::code_actions::edits::apply_text_edits;
+
+    #[test]
+    fn test_whitespace_settings() {
+        let content = "a  \nb\t\n\n  \n";
+        let trim = FormatSettings { trim_trailing_whitespace: true, ..Default::default() };
+        let final_lines = FormatSettings { trim_final_newlines: true, insert_final_newline: true, ..Default::default() };
+        let none = FormatSettings { trim_trailing_whitespace: false, ..Default::default() };
+
+        assert_eq!(apply_text_edits(content, &document_edits(content, &trim)), "a\nb\n\n\n");
+        assert_eq!(apply_text_edits(content, &document_edits(content, &final_lines)), "a\nb\n");
+        assert!(document_edits(content, &none).is_empty());
+        assert_eq!(tidy(content, &final_lines), "a\nb\n");
+
+        // A missing final newline is only added when asked for
+        assert_eq!(apply_text_edits("x", &document_edits("x", &final_lines)), "x\n");
+        assert_eq!(tidy("x", &trim), "x");
+    }
+}
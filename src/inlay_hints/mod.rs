@@ -1,10 +1,19 @@
 //! Inlay Hints Module
 //!
-//! Provides inline annotations for parameter names, types, and other contextual information
+//! Provides inline annotations for parameter names, types, and other contextual information.
+//!
+//! Types come from a lightweight, file-local inference pass: declared return types of
+//! functions and methods, constructor calls and struct/composite literals are collected
+//! first, then propagated through variable bindings and method chains.
 
 use anyhow::Result;
+use serde_json::json;
+use std::collections::HashMap;
 use tower_lsp::lsp_types::*;
-use crate::tree_sitter::TreeSitterParser;
+use crate::tree_sitter::{byte_to_position, position_to_byte, TreeSitterParser};
+
+/// Minimum number of lines a block must span before it gets a closing brace hint
+const DEFAULT_CLOSING_BRACE_MIN_LINES: u32 = 25;
 
 /// Type of inlay hint
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -13,6 +22,21 @@ pub enum HintKind {
     Parameter,
     /// Type annotation hint
     Type,
+    /// Type of an intermediate step in a multi-line method chain
+    Chaining,
+    /// Label after the closing brace of a long block
+    ClosingBrace,
+}
+
+impl HintKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HintKind::Parameter => "parameter",
+            HintKind::Type => "type",
+            HintKind::Chaining => "chaining",
+            HintKind::ClosingBrace => "closing_brace",
+        }
+    }
 }
 
 /// Inlay hints provider
@@ -22,6 +46,73 @@ pub struct InlayHintsProvider {
     show_parameter_hints: bool,
     /// Show type hints
     show_type_hints: bool,
+    /// Show type hints on intermediate method chain calls
+    show_chaining_hints: bool,
+    /// Minimum block length for closing brace hints (`None` disables them)
+    closing_brace_min_lines: Option<u32>,
+}
+
+/// Types and signatures declared in the current document
+#[derive(Debug, Default)]
+struct TypeEnv {
+    /// Declared return types of free functions, keyed by name
+    functions: HashMap<String, String>,
+    /// Declared return types of methods, keyed by `Owner::name` and by bare name
+    methods: HashMap<String, String>,
+    /// Parameter names, keyed like `functions` and `methods`
+    parameters: HashMap<String, Vec<String>>,
+    /// Signature text used for tooltips, keyed like `parameters`
+    signatures: HashMap<String, String>,
+    /// Inferred or declared types of variable bindings, keyed by name
+    variables: HashMap<String, Vec<VariableType>>,
+}
+
+/// Type of one binding, visible from the end of its declaration to the end of
+/// the enclosing scope
+#[derive(Debug)]
+struct VariableType {
+    scope: std::ops::Range<usize>,
+    defined_at: usize,
+    ty: String,
+}
+
+impl TypeEnv {
+    /// Type of the binding of `name` visible at `byte`: the one of the innermost
+    /// scope, the latest when it shadows another in the same scope
+    fn variable(&self, name: &str, byte: usize) -> Option<&str> {
+        self.variables
+            .get(name)?
+            .iter()
+            .filter(|binding| binding.scope.contains(&byte) && binding.defined_at <= byte)
+            .min_by_key(|binding| (binding.scope.len(), std::cmp::Reverse(binding.defined_at)))
+            .map(|binding| binding.ty.as_str())
+    }
+}
+
+/// A type inferred for an expression, with a note on where it came from
+#[derive(Debug, Clone)]
+struct Inferred {
+    ty: String,
+    origin: String,
+}
+
+impl Inferred {
+    fn new(ty: impl Into<String>, origin: impl Into<String>) -> Self {
+        Self {
+            ty: ty.into(),
+            origin: origin.into(),
+        }
+    }
+}
+
+/// A name introduced by a declaration, with the expression it is initialised from
+struct Binding<'a> {
+    name: tree_sitter::Node<'a>,
+    value: Option<tree_sitter::Node<'a>>,
+    /// Explicitly declared type, if any
+    declared: Option<String>,
+    /// Position within a multi-value assignment such as Go's `v, err := f()`
+    tuple_index: Option<usize>,
 }
 
 impl InlayHintsProvider {
@@ -29,6 +120,8 @@ impl InlayHintsProvider {
         Self {
             show_parameter_hints: true,
             show_type_hints: true,
+            show_chaining_hints: true,
+            closing_brace_min_lines: Some(DEFAULT_CLOSING_BRACE_MIN_LINES),
         }
     }
 
@@ -39,6 +132,18 @@ impl InlayHintsProvider {
         self
     }
 
+    /// Enable or disable method chain hints
+    pub fn with_chaining_hints(mut self, enabled: bool) -> Self {
+        self.show_chaining_hints = enabled;
+        self
+    }
+
+    /// Set the minimum block length for closing brace hints (`None` disables them)
+    pub fn with_closing_brace_hints(mut self, min_lines: Option<u32>) -> Self {
+        self.closing_brace_min_lines = min_lines;
+        self
+    }
+
     /// Get inlay hints for a range in the document
     pub fn get_inlay_hints(
         &self,
@@ -54,24 +159,81 @@ impl InlayHintsProvider {
         let tree = parser.parse(content, "temp")?;
         let root = tree.root_node();
 
+        // Inference runs over the whole file so that declarations outside the
+        // requested range still contribute types
+        let mut env = TypeEnv::default();
+        self.collect_declarations(root, content, lang, None, &mut env);
+        self.collect_variable_types(root, content, lang, &mut env);
+
         let mut hints = Vec::new();
 
         // Find all nodes in the range
-        let start_byte = self.position_to_byte(content, range.start);
-        let end_byte = self.position_to_byte(content, range.end);
+        let start_byte = position_to_byte(content, range.start);
+        let end_byte = position_to_byte(content, range.end);
 
         let mut cursor = root.walk();
-        self.collect_hints_recursive(root, content, lang, start_byte, end_byte, &mut hints, &mut cursor)?;
+        self.collect_hints_recursive(root, content, lang, &env, start_byte, end_byte, &mut hints, &mut cursor)?;
 
         Ok(hints)
     }
 
+    /// Fill in the tooltip of a hint returned by `get_inlay_hints`
+    pub fn resolve_inlay_hint(&self, mut hint: InlayHint) -> InlayHint {
+        if hint.tooltip.is_some() {
+            return hint;
+        }
+
+        let data = match &hint.data {
+            Some(data) => data,
+            None => return hint,
+        };
+        let field = |name: &str| data.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let lang = field("lang");
+
+        let value = match field("kind").as_str() {
+            "parameter" => {
+                let mut value = format!("Parameter `{}` of `{}`", field("parameter"), field("callee"));
+                let signature = field("signature");
+                if !signature.is_empty() {
+                    value.push_str(&format!("\n\n```{}\n{}\n```", lang, signature));
+                }
+                value
+            }
+            "type" => format!(
+                "```{}\n{}: {}\n```\n\nInferred from {}",
+                lang,
+                field("name"),
+                field("type"),
+                field("origin")
+            ),
+            "chaining" => format!(
+                "```{}\n{}\n```\n\nInferred from {}",
+                lang,
+                field("type"),
+                field("origin")
+            ),
+            "closing_brace" => {
+                let line = data.get("line").and_then(|v| v.as_u64()).unwrap_or(0);
+                format!("Closes `{}` opened on line {}", field("label"), line + 1)
+            }
+            _ => return hint,
+        };
+
+        hint.tooltip = Some(InlayHintTooltip::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }));
+        hint
+    }
+
     /// Recursively collect hints from tree
+    #[allow(clippy::too_many_arguments)]
     fn collect_hints_recursive(
         &self,
         node: tree_sitter::Node,
         content: &str,
         lang: &str,
+        env: &TypeEnv,
         start_byte: usize,
         end_byte: usize,
         hints: &mut Vec<InlayHint>,
@@ -84,17 +246,30 @@ impl InlayHintsProvider {
 
         // Collect hints for this node
         match lang {
-            "python" => self.collect_python_hints(&node, content, hints),
-            "javascript" | "typescript" | "tsx" | "jsx" => self.collect_js_hints(&node, content, hints),
-            "rust" => self.collect_rust_hints(&node, content, hints),
+            "python" => self.collect_python_hints(&node, content, env, hints),
+            "javascript" | "typescript" | "tsx" | "jsx" => self.collect_js_hints(&node, content, lang, env, hints),
+            "rust" => self.collect_rust_hints(&node, content, env, hints),
+            "go" => self.collect_go_hints(&node, content, env, hints),
+            "java" => self.collect_java_hints(&node, content, env, hints),
+            "kotlin" => self.collect_kotlin_hints(&node, content, env, hints),
             _ => {}
         }
 
+        if self.show_type_hints && self.show_chaining_hints {
+            self.add_chaining_hint(&node, content, lang, env, hints);
+        }
+
+        if let Some(min_lines) = self.closing_brace_min_lines {
+            if node.end_byte() <= end_byte {
+                self.add_closing_brace_hint(&node, content, lang, min_lines, hints);
+            }
+        }
+
         // Process children
         if cursor.goto_first_child() {
             loop {
                 let child = cursor.node();
-                self.collect_hints_recursive(child, content, lang, start_byte, end_byte, hints, cursor)?;
+                self.collect_hints_recursive(child, content, lang, env, start_byte, end_byte, hints, cursor)?;
 
                 if !cursor.goto_next_sibling() {
                     break;
@@ -111,17 +286,18 @@ impl InlayHintsProvider {
         &self,
         node: &tree_sitter::Node,
         content: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
         match node.kind() {
             "call" => {
                 if self.show_parameter_hints {
-                    self.add_python_parameter_hints(node, content, hints);
+                    self.add_python_parameter_hints(node, content, env, hints);
                 }
             }
             "assignment" => {
                 if self.show_type_hints {
-                    self.add_python_type_hints(node, content, hints);
+                    self.add_type_hints(node, content, "python", env, hints);
                 }
             }
             _ => {}
@@ -133,375 +309,1197 @@ impl InlayHintsProvider {
         &self,
         node: &tree_sitter::Node,
         content: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
         // Get the arguments node
         if let Some(args_node) = node.child_by_field_name("arguments") {
             let mut cursor = args_node.walk();
-            let mut param_index = 0;
-
-            for child in args_node.children(&mut cursor) {
-                // Skip keyword arguments (they already have names)
-                if child.kind() == "keyword_argument" {
-                    continue;
-                }
+            let arguments: Vec<_> = args_node
+                .named_children(&mut cursor)
+                // Keyword arguments already have names, splats have no fixed position
+                .filter(|c| !matches!(c.kind(), "keyword_argument" | "list_splat" | "dictionary_splat" | "comment"))
+                .collect();
 
-                // Add hint for positional arguments
-                if child.is_named() && child.kind() != "(" && child.kind() != ")" && child.kind() != "," {
-                    let position = self.byte_to_position(content, child.start_byte());
-
-                    hints.push(InlayHint {
-                        position,
-                        label: InlayHintLabel::String(format!("param{}:", param_index)),
-                        kind: Some(InlayHintKind::PARAMETER),
-                        text_edits: None,
-                        tooltip: None,
-                        padding_left: Some(false),
-                        padding_right: Some(true),
-                        data: None,
-                    });
-
-                    param_index += 1;
-                }
-            }
+            self.add_parameter_hints(node, &arguments, content, "python", env, hints);
         }
     }
 
-    /// Add type hints for Python variables
-    fn add_python_type_hints(
+    /// Collect hints for JavaScript/TypeScript code
+    fn collect_js_hints(
         &self,
         node: &tree_sitter::Node,
         content: &str,
+        lang: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
-        // Look for assignments without type annotations
-        if let Some(left) = node.child_by_field_name("left") {
-            if left.kind() == "identifier" {
-                // Check if there's already a type annotation
-                let has_annotation = node.children(&mut node.walk())
-                    .any(|c| c.kind() == "type");
-
-                if !has_annotation {
-                    if let Some(right) = node.child_by_field_name("right") {
-                        let inferred_type = self.infer_python_type(&right, content);
-                        if let Some(type_str) = inferred_type {
-                            let position = self.byte_to_position(content, left.end_byte());
-
-                            hints.push(InlayHint {
-                                position,
-                                label: InlayHintLabel::String(format!(": {}", type_str)),
-                                kind: Some(InlayHintKind::TYPE),
-                                text_edits: None,
-                                tooltip: None,
-                                padding_left: Some(false),
-                                padding_right: Some(false),
-                                data: None,
-                            });
-                        }
-                    }
+        match node.kind() {
+            "call_expression" | "new_expression" => {
+                if self.show_parameter_hints {
+                    self.add_js_parameter_hints(node, content, lang, env, hints);
+                }
+            }
+            "variable_declarator" => {
+                if self.show_type_hints {
+                    self.add_type_hints(node, content, lang, env, hints);
                 }
             }
+            _ => {}
         }
     }
 
-    /// Infer Python type from expression
-    fn infer_python_type(&self, node: &tree_sitter::Node, content: &str) -> Option<String> {
-        match node.kind() {
-            "integer" => Some("int".to_string()),
-            "float" => Some("float".to_string()),
-            "string" | "string_content" => Some("str".to_string()),
-            "true" | "false" => Some("bool".to_string()),
-            "list" => Some("list".to_string()),
-            "dictionary" => Some("dict".to_string()),
-            "tuple" => Some("tuple".to_string()),
-            "set" => Some("set".to_string()),
-            "call" => {
-                // Try to infer from function name
-                if let Some(func) = node.child_by_field_name("function") {
-                    let func_name = &content[func.start_byte()..func.end_byte()];
-                    match func_name {
-                        "int" => Some("int".to_string()),
-                        "float" => Some("float".to_string()),
-                        "str" => Some("str".to_string()),
-                        "list" => Some("list".to_string()),
-                        "dict" => Some("dict".to_string()),
-                        "set" => Some("set".to_string()),
-                        "tuple" => Some("tuple".to_string()),
-                        _ => None,
-                    }
-                } else {
-                    None
-                }
-            }
-            _ => None,
+    /// Add parameter name hints for JavaScript function calls
+    fn add_js_parameter_hints(
+        &self,
+        node: &tree_sitter::Node,
+        content: &str,
+        lang: &str,
+        env: &TypeEnv,
+        hints: &mut Vec<InlayHint>,
+    ) {
+        if let Some(args_node) = node.child_by_field_name("arguments") {
+            let mut cursor = args_node.walk();
+            let arguments: Vec<_> = args_node
+                .named_children(&mut cursor)
+                .filter(|c| !matches!(c.kind(), "spread_element" | "comment"))
+                .collect();
+
+            self.add_parameter_hints(node, &arguments, content, lang, env, hints);
         }
     }
 
-    /// Collect hints for JavaScript/TypeScript code
-    fn collect_js_hints(
+    /// Collect hints for Rust code
+    fn collect_rust_hints(
         &self,
         node: &tree_sitter::Node,
         content: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
         match node.kind() {
             "call_expression" => {
                 if self.show_parameter_hints {
-                    self.add_js_parameter_hints(node, content, hints);
+                    self.add_rust_parameter_hints(node, content, env, hints);
                 }
             }
-            "variable_declarator" => {
+            "let_declaration" => {
                 if self.show_type_hints {
-                    self.add_js_type_hints(node, content, hints);
+                    self.add_type_hints(node, content, "rust", env, hints);
                 }
             }
             _ => {}
         }
     }
 
-    /// Add parameter name hints for JavaScript function calls
-    fn add_js_parameter_hints(
+    /// Add parameter name hints for Rust function calls
+    fn add_rust_parameter_hints(
         &self,
         node: &tree_sitter::Node,
         content: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
         if let Some(args_node) = node.child_by_field_name("arguments") {
             let mut cursor = args_node.walk();
-            let mut param_index = 0;
-
-            for child in args_node.children(&mut cursor) {
-                if child.is_named() && child.kind() != "(" && child.kind() != ")" && child.kind() != "," {
-                    let position = self.byte_to_position(content, child.start_byte());
-
-                    hints.push(InlayHint {
-                        position,
-                        label: InlayHintLabel::String(format!("param{}:", param_index)),
-                        kind: Some(InlayHintKind::PARAMETER),
-                        text_edits: None,
-                        tooltip: None,
-                        padding_left: Some(false),
-                        padding_right: Some(true),
-                        data: None,
-                    });
+            let arguments: Vec<_> = args_node
+                .named_children(&mut cursor)
+                .filter(|c| !matches!(c.kind(), "line_comment" | "block_comment" | "attribute_item"))
+                .collect();
 
-                    param_index += 1;
-                }
-            }
+            self.add_parameter_hints(node, &arguments, content, "rust", env, hints);
         }
     }
 
-    /// Add type hints for JavaScript variables
-    fn add_js_type_hints(
+    /// Collect hints for Go code
+    fn collect_go_hints(
         &self,
         node: &tree_sitter::Node,
         content: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
-        if let Some(name) = node.child_by_field_name("name") {
-            // Check if there's already a type annotation
-            let has_annotation = node.children(&mut node.walk())
-                .any(|c| c.kind() == "type_annotation");
-
-            if !has_annotation {
-                if let Some(value) = node.child_by_field_name("value") {
-                    let inferred_type = self.infer_js_type(&value, content);
-                    if let Some(type_str) = inferred_type {
-                        let position = self.byte_to_position(content, name.end_byte());
-
-                        hints.push(InlayHint {
-                            position,
-                            label: InlayHintLabel::String(format!(": {}", type_str)),
-                            kind: Some(InlayHintKind::TYPE),
-                            text_edits: None,
-                            tooltip: None,
-                            padding_left: Some(false),
-                            padding_right: Some(false),
-                            data: None,
-                        });
+        match node.kind() {
+            "call_expression" => {
+                if self.show_parameter_hints {
+                    if let Some(args_node) = node.child_by_field_name("arguments") {
+                        let mut cursor = args_node.walk();
+                        let arguments: Vec<_> = args_node
+                            .named_children(&mut cursor)
+                            .filter(|c| c.kind() != "comment")
+                            .collect();
+                        self.add_parameter_hints(node, &arguments, content, "go", env, hints);
                     }
                 }
             }
+            "short_var_declaration" | "var_spec" => {
+                if self.show_type_hints {
+                    self.add_type_hints(node, content, "go", env, hints);
+                }
+            }
+            _ => {}
         }
     }
 
-    /// Infer JavaScript type from expression
-    fn infer_js_type(&self, node: &tree_sitter::Node, _content: &str) -> Option<String> {
+    /// Collect hints for Java code
+    fn collect_java_hints(
+        &self,
+        node: &tree_sitter::Node,
+        content: &str,
+        env: &TypeEnv,
+        hints: &mut Vec<InlayHint>,
+    ) {
         match node.kind() {
-            "number" => Some("number".to_string()),
-            "string" | "template_string" => Some("string".to_string()),
-            "true" | "false" => Some("boolean".to_string()),
-            "array" => Some("Array".to_string()),
-            "object" => Some("Object".to_string()),
-            "arrow_function" | "function" => Some("Function".to_string()),
-            "null" => Some("null".to_string()),
-            "undefined" => Some("undefined".to_string()),
-            _ => None,
+            "method_invocation" | "object_creation_expression" => {
+                if self.show_parameter_hints {
+                    if let Some(args_node) = node.child_by_field_name("arguments") {
+                        let mut cursor = args_node.walk();
+                        let arguments: Vec<_> = args_node
+                            .named_children(&mut cursor)
+                            .filter(|c| !matches!(c.kind(), "line_comment" | "block_comment"))
+                            .collect();
+                        self.add_parameter_hints(node, &arguments, content, "java", env, hints);
+                    }
+                }
+            }
+            // Only `var` declarations lack an explicit type
+            "local_variable_declaration" => {
+                if self.show_type_hints {
+                    self.add_type_hints(node, content, "java", env, hints);
+                }
+            }
+            _ => {}
         }
     }
 
-    /// Collect hints for Rust code
-    fn collect_rust_hints(
+    /// Collect hints for Kotlin code
+    fn collect_kotlin_hints(
         &self,
         node: &tree_sitter::Node,
         content: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
         match node.kind() {
             "call_expression" => {
                 if self.show_parameter_hints {
-                    self.add_rust_parameter_hints(node, content, hints);
+                    let value_arguments = self
+                        .child_of_kind(node, "call_suffix")
+                        .and_then(|suffix| self.child_of_kind(&suffix, "value_arguments"));
+
+                    if let Some(args_node) = value_arguments {
+                        let mut cursor = args_node.walk();
+                        let arguments: Vec<_> = args_node
+                            .named_children(&mut cursor)
+                            // Named arguments (`name = value`) already carry their label
+                            .filter(|c| c.kind() == "value_argument" && self.child_of_kind(c, "=").is_none())
+                            .collect();
+                        self.add_parameter_hints(node, &arguments, content, "kotlin", env, hints);
+                    }
                 }
             }
-            "let_declaration" => {
+            "property_declaration" => {
                 if self.show_type_hints {
-                    self.add_rust_type_hints(node, content, hints);
+                    self.add_type_hints(node, content, "kotlin", env, hints);
                 }
             }
             _ => {}
         }
     }
 
-    /// Add parameter name hints for Rust function calls
-    fn add_rust_parameter_hints(
+    /// Add parameter name hints for the positional arguments of a call
+    fn add_parameter_hints(
         &self,
-        node: &tree_sitter::Node,
+        call: &tree_sitter::Node,
+        arguments: &[tree_sitter::Node],
         content: &str,
+        lang: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
-        if let Some(args_node) = node.child_by_field_name("arguments") {
-            let mut cursor = args_node.walk();
-            let mut param_index = 0;
-
-            for child in args_node.children(&mut cursor) {
-                if child.is_named() && child.kind() != "(" && child.kind() != ")" && child.kind() != "," {
-                    let position = self.byte_to_position(content, child.start_byte());
-
-                    hints.push(InlayHint {
-                        position,
-                        label: InlayHintLabel::String(format!("param{}:", param_index)),
-                        kind: Some(InlayHintKind::PARAMETER),
-                        text_edits: None,
-                        tooltip: None,
-                        padding_left: Some(false),
-                        padding_right: Some(true),
-                        data: None,
-                    });
+        let callee = self.callee_key(call, content, lang, env);
+        let names = callee.as_ref().and_then(|key| env.parameters.get(key));
+        let signature = callee.as_ref().and_then(|key| env.signatures.get(key));
+
+        for (index, argument) in arguments.iter().enumerate() {
+            let parameter = match names {
+                Some(names) => match names.get(index) {
+                    Some(name) => name.clone(),
+                    // More arguments than declared parameters (varargs)
+                    None => break,
+                },
+                None => format!("param{}", index),
+            };
+
+            // `foo(bar)` where the parameter is also called `bar` needs no hint
+            if content[argument.start_byte()..argument.end_byte()] == parameter {
+                continue;
+            }
 
-                    param_index += 1;
-                }
+            hints.push(InlayHint {
+                position: byte_to_position(content, argument.start_byte()),
+                label: InlayHintLabel::String(format!("{}:", parameter)),
+                kind: Some(InlayHintKind::PARAMETER),
+                text_edits: None,
+                tooltip: None,
+                padding_left: Some(false),
+                padding_right: Some(true),
+                data: Some(json!({
+                    "kind": HintKind::Parameter.as_str(),
+                    "lang": lang,
+                    "callee": callee.clone().unwrap_or_default(),
+                    "parameter": parameter,
+                    "signature": signature.cloned().unwrap_or_default(),
+                })),
+            });
+        }
+    }
+
+    /// Add type hints for the unannotated bindings introduced by a declaration
+    fn add_type_hints(
+        &self,
+        node: &tree_sitter::Node,
+        content: &str,
+        lang: &str,
+        env: &TypeEnv,
+        hints: &mut Vec<InlayHint>,
+    ) {
+        for binding in self.bindings(node, content, lang) {
+            if binding.declared.is_some() {
+                continue;
             }
+
+            let inferred = match binding
+                .value
+                .and_then(|value| self.infer_binding_type(&value, binding.tuple_index, content, lang, env))
+            {
+                Some(inferred) => inferred,
+                None => continue,
+            };
+
+            let name = &content[binding.name.start_byte()..binding.name.end_byte()];
+            hints.push(InlayHint {
+                position: byte_to_position(content, binding.name.end_byte()),
+                label: InlayHintLabel::String(format!(": {}", inferred.ty)),
+                kind: Some(InlayHintKind::TYPE),
+                text_edits: None,
+                tooltip: None,
+                padding_left: Some(false),
+                padding_right: Some(false),
+                data: Some(json!({
+                    "kind": HintKind::Type.as_str(),
+                    "lang": lang,
+                    "name": name,
+                    "type": inferred.ty,
+                    "origin": inferred.origin,
+                })),
+            });
         }
     }
 
-    /// Add type hints for Rust variables
-    fn add_rust_type_hints(
+    /// Add a type hint after a call whose result is chained on the next line
+    fn add_chaining_hint(
         &self,
         node: &tree_sitter::Node,
         content: &str,
+        lang: &str,
+        env: &TypeEnv,
         hints: &mut Vec<InlayHint>,
     ) {
-        // Check if there's already a type annotation
-        let has_type = node.child_by_field_name("type").is_some();
-
-        if !has_type {
-            if let Some(pattern) = node.child_by_field_name("pattern") {
-                if let Some(value) = node.child_by_field_name("value") {
-                    let inferred_type = self.infer_rust_type(&value, content);
-                    if let Some(type_str) = inferred_type {
-                        let position = self.byte_to_position(content, pattern.end_byte());
-
-                        hints.push(InlayHint {
-                            position,
-                            label: InlayHintLabel::String(format!(": {}", type_str)),
-                            kind: Some(InlayHintKind::TYPE),
-                            text_edits: None,
-                            tooltip: None,
-                            padding_left: Some(false),
-                            padding_right: Some(false),
-                            data: None,
-                        });
-                    }
-                }
+        if !matches!(node.kind(), "call" | "call_expression" | "method_invocation") {
+            return;
+        }
+
+        // The call must be the receiver of a method call...
+        let parent = match node.parent() {
+            Some(parent) => parent,
+            None => return,
+        };
+        let continues_chain = match parent.kind() {
+            "attribute" | "member_expression" | "field_expression" | "selector_expression" | "navigation_expression" => {
+                parent.named_child(0).map(|c| c.id()) == Some(node.id())
             }
+            "method_invocation" => parent.child_by_field_name("object").map(|c| c.id()) == Some(node.id()),
+            _ => false,
+        };
+        if !continues_chain {
+            return;
+        }
+
+        // ...which starts on the following line
+        let rest_of_line = content[node.end_byte()..].split('\n').next().unwrap_or("");
+        if !rest_of_line.trim().is_empty() || node.end_byte() == content.len() {
+            return;
+        }
+
+        if let Some(inferred) = self.infer_expression(node, content, lang, env) {
+            hints.push(InlayHint {
+                position: byte_to_position(content, node.end_byte()),
+                label: InlayHintLabel::String(inferred.ty.clone()),
+                kind: Some(InlayHintKind::TYPE),
+                text_edits: None,
+                tooltip: None,
+                padding_left: Some(true),
+                padding_right: Some(false),
+                data: Some(json!({
+                    "kind": HintKind::Chaining.as_str(),
+                    "lang": lang,
+                    "type": inferred.ty,
+                    "origin": inferred.origin,
+                })),
+            });
         }
     }
 
-    /// Infer Rust type from expression
-    fn infer_rust_type(&self, node: &tree_sitter::Node, content: &str) -> Option<String> {
-        match node.kind() {
-            "integer_literal" => {
-                let text = &content[node.start_byte()..node.end_byte()];
-                if text.ends_with("u32") || text.ends_with("i32") || text.ends_with("u64") || text.ends_with("i64") {
-                    None // Already has type suffix
+    /// Add a `// fn name` style hint after the closing brace of a long block
+    fn add_closing_brace_hint(
+        &self,
+        node: &tree_sitter::Node,
+        content: &str,
+        lang: &str,
+        min_lines: u32,
+        hints: &mut Vec<InlayHint>,
+    ) {
+        let label = match self.block_label(node, content, lang) {
+            Some(label) => label,
+            None => return,
+        };
+
+        let start_line = node.start_position().row;
+        let end_line = node.end_position().row;
+        if ((end_line - start_line + 1) as u32) < min_lines {
+            return;
+        }
+
+        let text = &content[node.start_byte()..node.end_byte()];
+        if !text.ends_with('}') {
+            return;
+        }
+
+        // Don't crowd lines where more code follows the brace
+        let rest_of_line = content[node.end_byte()..].split('\n').next().unwrap_or("");
+        if !rest_of_line.trim_start_matches([';', ',']).trim().is_empty() {
+            return;
+        }
+
+        hints.push(InlayHint {
+            position: byte_to_position(content, node.end_byte()),
+            label: InlayHintLabel::String(format!("// {}", label)),
+            kind: None,
+            text_edits: None,
+            tooltip: None,
+            padding_left: Some(true),
+            padding_right: Some(false),
+            data: Some(json!({
+                "kind": HintKind::ClosingBrace.as_str(),
+                "lang": lang,
+                "label": label,
+                "line": start_line,
+            })),
+        });
+    }
+
+    /// Describe a named block (`fn foo`, `class Bar`, ...) for closing brace hints
+    fn block_label(&self, node: &tree_sitter::Node, content: &str, lang: &str) -> Option<String> {
+        let text = |n: tree_sitter::Node| content[n.start_byte()..n.end_byte()].to_string();
+        let named = |keyword: &str, field: &str| {
+            node.child_by_field_name(field).map(|n| {
+                if keyword.is_empty() {
+                    text(n)
                 } else {
-                    Some("i32".to_string())
+                    format!("{} {}", keyword, text(n))
+                }
+            })
+        };
+
+        match (lang, node.kind()) {
+            ("rust", "function_item") => named("fn", "name"),
+            ("rust", "impl_item") => {
+                let ty = node.child_by_field_name("type").map(text)?;
+                match node.child_by_field_name("trait").map(text) {
+                    Some(trait_name) => Some(format!("impl {} for {}", trait_name, ty)),
+                    None => Some(format!("impl {}", ty)),
                 }
             }
-            "float_literal" => Some("f64".to_string()),
-            "string_literal" | "raw_string_literal" => Some("&str".to_string()),
-            "char_literal" => Some("char".to_string()),
-            "true" | "false" => Some("bool".to_string()),
-            "array_expression" => Some("Vec".to_string()),
+            ("rust", "trait_item") => named("trait", "name"),
+            ("rust", "struct_item") => named("struct", "name"),
+            ("rust", "enum_item") => named("enum", "name"),
+            ("rust", "mod_item") => named("mod", "name"),
+            ("javascript" | "typescript" | "tsx" | "jsx", "function_declaration") => named("function", "name"),
+            ("javascript" | "typescript" | "tsx" | "jsx", "class_declaration") => named("class", "name"),
+            ("javascript" | "typescript" | "tsx" | "jsx", "method_definition") => named("", "name"),
+            ("typescript" | "tsx", "interface_declaration") => named("interface", "name"),
+            ("go", "function_declaration" | "method_declaration") => named("func", "name"),
+            ("java", "class_declaration") => named("class", "name"),
+            ("java", "interface_declaration") => named("interface", "name"),
+            ("java", "enum_declaration") => named("enum", "name"),
+            ("java", "method_declaration" | "constructor_declaration") => named("", "name"),
+            ("kotlin", "function_declaration") => self
+                .child_of_kind(node, "simple_identifier")
+                .map(|n| format!("fun {}", text(n))),
+            ("kotlin", "class_declaration" | "object_declaration") => {
+                let keyword = if node.kind() == "class_declaration" { "class" } else { "object" };
+                self.child_of_kind(node, "type_identifier")
+                    .map(|n| format!("{} {}", keyword, text(n)))
+            }
             _ => None,
         }
     }
 
-    /// Convert LSP position to byte offset
-    fn position_to_byte(&self, source: &str, position: Position) -> usize {
-        let mut byte_offset = 0;
-        let mut current_line = 0;
-        let mut current_char = 0;
+    // ========================================================================
+    // Local type inference
+    // ========================================================================
 
-        for ch in source.chars() {
-            if current_line == position.line && current_char == position.character {
-                return byte_offset;
-            }
+    /// Record declared function/method return types and parameter names
+    fn collect_declarations(
+        &self,
+        node: tree_sitter::Node,
+        content: &str,
+        lang: &str,
+        owner: Option<&str>,
+        env: &mut TypeEnv,
+    ) {
+        let text = |n: tree_sitter::Node| content[n.start_byte()..n.end_byte()].to_string();
+        let mut child_owner: Option<String> = owner.map(|o| o.to_string());
+
+        match (lang, node.kind()) {
+            ("python", "function_definition")
+            | ("javascript" | "typescript" | "tsx" | "jsx", "function_declaration" | "method_definition")
+            | ("rust", "function_item")
+            | ("go", "function_declaration")
+            | ("java", "method_declaration") => {
+                if let Some(name) = node.child_by_field_name("name").map(text) {
+                    let return_type = match lang {
+                        "java" => node.child_by_field_name("type").map(text).filter(|t| t != "void"),
+                        "go" => node.child_by_field_name("result").map(|r| self.go_result_type(&r, content)),
+                        _ => node.child_by_field_name("return_type").map(|r| self.clean_type(&text(r))),
+                    }
+                    .map(|ty| match (ty.as_str(), owner) {
+                        ("Self", Some(owner)) => owner.to_string(),
+                        _ => ty,
+                    });
 
-            if ch == '\n' {
-                current_line += 1;
-                current_char = 0;
-            } else {
-                current_char += 1;
+                    self.register_callable(node, &name, owner, return_type, content, lang, env);
+                }
             }
-
-            byte_offset += ch.len_utf8();
+            ("go", "method_declaration") => {
+                let receiver = node
+                    .child_by_field_name("receiver")
+                    .and_then(|r| r.named_child(0))
+                    .and_then(|p| p.child_by_field_name("type"))
+                    .map(|t| self.base_type(&text(t)));
+
+                if let Some(name) = node.child_by_field_name("name").map(text) {
+                    let return_type = node.child_by_field_name("result").map(|r| self.go_result_type(&r, content));
+                    self.register_callable(node, &name, receiver.as_deref(), return_type, content, lang, env);
+                }
+            }
+            ("kotlin", "function_declaration") => {
+                if let Some(name) = self.child_of_kind(&node, "simple_identifier").map(text) {
+                    let return_type = self.kotlin_declared_type(&node, content);
+                    self.register_callable(node, &name, owner, return_type, content, lang, env);
+                }
+            }
+            ("python", "class_definition")
+            | ("javascript" | "typescript" | "tsx" | "jsx", "class_declaration" | "class")
+            | ("java", "class_declaration" | "interface_declaration" | "enum_declaration")
+            | ("rust", "trait_item") => {
+                child_owner = node.child_by_field_name("name").map(|n| self.base_type(&text(n)));
+            }
+            ("rust", "impl_item") => {
+                child_owner = node.child_by_field_name("type").map(|n| self.base_type(&text(n)));
+            }
+            ("kotlin", "class_declaration" | "object_declaration") => {
+                child_owner = self.child_of_kind(&node, "type_identifier").map(text);
+            }
+            _ => {}
         }
 
-        byte_offset
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.collect_declarations(child, content, lang, child_owner.as_deref(), env);
+        }
     }
 
-    /// Convert byte offset to LSP position
-    fn byte_to_position(&self, source: &str, byte_offset: usize) -> Position {
-        let mut line = 0;
-        let mut character = 0;
-        let mut current_offset = 0;
+    /// Store the return type, parameters and signature of a function or method
+    #[allow(clippy::too_many_arguments)]
+    fn register_callable(
+        &self,
+        node: tree_sitter::Node,
+        name: &str,
+        owner: Option<&str>,
+        return_type: Option<String>,
+        content: &str,
+        lang: &str,
+        env: &mut TypeEnv,
+    ) {
+        let mut keys = vec![name.to_string()];
+        if let Some(owner) = owner {
+            keys.insert(0, format!("{}::{}", owner, name));
+        }
 
-        for ch in source.chars() {
-            if current_offset >= byte_offset {
-                break;
+        // Signature is everything up to the body, on one line
+        let body_start = node
+            .child_by_field_name("body")
+            .or_else(|| self.child_of_kind(&node, "function_body"))
+            .map(|b| b.start_byte())
+            .unwrap_or_else(|| node.end_byte());
+        let signature = content[node.start_byte()..body_start]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end_matches(':')
+            .to_string();
+
+        let parameters = self.parameter_names(&node, content, lang, owner.is_some());
+
+        for key in keys {
+            if let Some(ty) = &return_type {
+                if owner.is_some() {
+                    env.methods.entry(key.clone()).or_insert_with(|| ty.clone());
+                } else {
+                    env.functions.entry(key.clone()).or_insert_with(|| ty.clone());
+                }
             }
+            env.parameters.entry(key.clone()).or_insert_with(|| parameters.clone());
+            env.signatures.entry(key).or_insert_with(|| signature.clone());
+        }
+    }
 
-            if ch == '\n' {
-                line += 1;
-                character = 0;
-            } else {
-                character += 1;
+    /// Names of the declared parameters of a function node
+    fn parameter_names(&self, node: &tree_sitter::Node, content: &str, lang: &str, is_method: bool) -> Vec<String> {
+        let params = match node
+            .child_by_field_name("parameters")
+            .or_else(|| self.child_of_kind(node, "function_value_parameters"))
+        {
+            Some(params) => params,
+            None => return Vec::new(),
+        };
+
+        let mut names = Vec::new();
+        let mut cursor = params.walk();
+        for param in params.named_children(&mut cursor) {
+            match param.kind() {
+                "self_parameter" | "comment" | "line_comment" | "block_comment" => continue,
+                // `func f(a, b int)` declares several names at once
+                "parameter_declaration" if lang == "go" => {
+                    let mut names_cursor = param.walk();
+                    for name in param.children_by_field_name("name", &mut names_cursor) {
+                        names.push(content[name.start_byte()..name.end_byte()].to_string());
+                    }
+                }
+                _ => {
+                    if let Some(name) = self.first_identifier(&param) {
+                        names.push(content[name.start_byte()..name.end_byte()].to_string());
+                    }
+                }
             }
+        }
 
-            current_offset += ch.len_utf8();
+        // Python methods receive the instance implicitly
+        if lang == "python" && is_method && matches!(names.first().map(String::as_str), Some("self" | "cls")) {
+            names.remove(0);
         }
 
-        Position { line, character }
+        names
     }
-}
+
+    /// Record the types of variables bound anywhere in the document
+    fn collect_variable_types(&self, node: tree_sitter::Node, content: &str, lang: &str, env: &mut TypeEnv) {
+        for binding in self.bindings(&node, content, lang) {
+            let name = content[binding.name.start_byte()..binding.name.end_byte()].to_string();
+            let ty = binding.declared.clone().or_else(|| {
+                binding
+                    .value
+                    .and_then(|value| self.infer_binding_type(&value, binding.tuple_index, content, lang, env))
+                    .map(|inferred| inferred.ty)
+            });
+
+            if let Some(ty) = ty {
+                let scope = self.binding_scope(node, lang);
+                let binding = VariableType { scope: scope.byte_range(), defined_at: node.end_byte(), ty };
+                env.variables.entry(name).or_default().push(binding);
+            }
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.collect_variable_types(child, content, lang, env);
+        }
+    }
+
+    /// Node whose extent a declaration's bindings are visible in
+    fn binding_scope<'a>(&self, declaration: tree_sitter::Node<'a>, lang: &str) -> tree_sitter::Node<'a> {
+        let scope_kinds: &[&str] = match lang {
+            // Python scopes by function, not by block
+            "python" => &["function_definition", "lambda", "class_definition"],
+            "javascript" | "typescript" | "tsx" | "jsx" => {
+                &["statement_block", "arrow_function", "function_declaration", "method_definition"]
+            }
+            "rust" => &["block", "closure_expression"],
+            _ => &["block"],
+        };
+        let mut scope = declaration;
+        while let Some(parent) = scope.parent() {
+            scope = parent;
+            if scope_kinds.contains(&scope.kind()) {
+                break;
+            }
+        }
+        scope
+    }
+
+    /// Names introduced by a declaration node
+    fn bindings<'a>(&self, node: &tree_sitter::Node<'a>, content: &str, lang: &str) -> Vec<Binding<'a>> {
+        let text = |n: tree_sitter::Node| content[n.start_byte()..n.end_byte()].to_string();
+        let mut bindings = Vec::new();
+
+        match (lang, node.kind()) {
+            ("python", "assignment") => {
+                if let Some(left) = node.child_by_field_name("left").filter(|l| l.kind() == "identifier") {
+                    bindings.push(Binding {
+                        name: left,
+                        value: node.child_by_field_name("right"),
+                        declared: node.child_by_field_name("type").map(text),
+                        tuple_index: None,
+                    });
+                }
+            }
+            ("javascript" | "typescript" | "tsx" | "jsx", "variable_declarator") => {
+                if let Some(name) = node.child_by_field_name("name").filter(|n| n.kind() == "identifier") {
+                    bindings.push(Binding {
+                        name,
+                        value: node.child_by_field_name("value"),
+                        declared: node.child_by_field_name("type").map(|t| self.clean_type(&text(t))),
+                        tuple_index: None,
+                    });
+                }
+            }
+            ("rust", "let_declaration") => {
+                if let Some(pattern) = node.child_by_field_name("pattern").filter(|p| p.kind() == "identifier") {
+                    bindings.push(Binding {
+                        name: pattern,
+                        value: node.child_by_field_name("value"),
+                        declared: node.child_by_field_name("type").map(text),
+                        tuple_index: None,
+                    });
+                }
+            }
+            ("go", "short_var_declaration" | "var_spec") => {
+                let mut cursor = node.walk();
+                let names: Vec<_> = if node.kind() == "short_var_declaration" {
+                    node.child_by_field_name("left")
+                        .map(|left| left.named_children(&mut cursor).collect())
+                        .unwrap_or_default()
+                } else {
+                    node.children_by_field_name("name", &mut cursor).collect()
+                };
+
+                let value_list = node
+                    .child_by_field_name("right")
+                    .or_else(|| node.child_by_field_name("value"));
+                let mut value_cursor = node.walk();
+                let values: Vec<_> = value_list
+                    .map(|list| list.named_children(&mut value_cursor).collect())
+                    .unwrap_or_default();
+                let declared = node.child_by_field_name("type").map(text);
+                let spread = values.len() == 1 && names.len() > 1;
+
+                for (i, name) in names.into_iter().enumerate() {
+                    if name.kind() != "identifier" || text(name) == "_" {
+                        continue;
+                    }
+
+                    // `a, err := f()` spreads one call over several names
+                    let (value, tuple_index) = if spread {
+                        (values.first().copied(), Some(i))
+                    } else {
+                        (values.get(i).copied(), None)
+                    };
+
+                    bindings.push(Binding {
+                        name,
+                        value,
+                        declared: declared.clone(),
+                        tuple_index,
+                    });
+                }
+            }
+            ("java", "local_variable_declaration") => {
+                let declared = node
+                    .child_by_field_name("type")
+                    .map(text)
+                    .filter(|ty| ty != "var");
+
+                let mut cursor = node.walk();
+                for declarator in node.children_by_field_name("declarator", &mut cursor) {
+                    if let Some(name) = declarator.child_by_field_name("name") {
+                        bindings.push(Binding {
+                            name,
+                            value: declarator.child_by_field_name("value"),
+                            declared: declared.clone(),
+                            tuple_index: None,
+                        });
+                    }
+                }
+            }
+            ("kotlin", "property_declaration") => {
+                if let Some(variable) = self.child_of_kind(node, "variable_declaration") {
+                    if let Some(name) = self.child_of_kind(&variable, "simple_identifier") {
+                        let mut cursor = node.walk();
+                        let value = node
+                            .children(&mut cursor)
+                            .skip_while(|c| c.kind() != "=")
+                            .find(|c| c.is_named());
+
+                        bindings.push(Binding {
+                            name,
+                            value,
+                            declared: self.kotlin_declared_type(&variable, content),
+                            tuple_index: None,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        bindings
+    }
+
+    /// Infer the type a binding receives from its initialiser
+    fn infer_binding_type(
+        &self,
+        value: &tree_sitter::Node,
+        tuple_index: Option<usize>,
+        content: &str,
+        lang: &str,
+        env: &TypeEnv,
+    ) -> Option<Inferred> {
+        let inferred = self.infer_expression(value, content, lang, env)?;
+
+        match tuple_index {
+            Some(index) => {
+                let parts = self.split_tuple_type(&inferred.ty);
+                parts.get(index).map(|ty| Inferred::new(ty.clone(), inferred.origin.clone()))
+            }
+            None => Some(inferred),
+        }
+    }
+
+    /// Infer the type of an expression from literals and the declarations in `env`
+    fn infer_expression(
+        &self,
+        node: &tree_sitter::Node,
+        content: &str,
+        lang: &str,
+        env: &TypeEnv,
+    ) -> Option<Inferred> {
+        let literal = match lang {
+            "python" => self.infer_python_type(node, content),
+            "javascript" | "typescript" | "tsx" | "jsx" => self.infer_js_type(node, content),
+            "rust" => self.infer_rust_type(node, content),
+            "go" => self.infer_go_type(node, content),
+            "java" => self.infer_java_type(node, content),
+            "kotlin" => self.infer_kotlin_type(node, content),
+            _ => None,
+        };
+        if let Some(ty) = literal {
+            return Some(Inferred::new(ty, "the assigned value"));
+        }
+
+        let text = |n: tree_sitter::Node| content[n.start_byte()..n.end_byte()].to_string();
+
+        match node.kind() {
+            "identifier" | "simple_identifier" => {
+                let name = text(*node);
+                return env
+                    .variable(&name, node.start_byte())
+                    .map(|ty| Inferred::new(ty, format!("`{}`", name)));
+            }
+            "parenthesized_expression" => {
+                return node
+                    .named_child(0)
+                    .and_then(|inner| self.infer_expression(&inner, content, lang, env));
+            }
+            "new_expression" => {
+                let constructor = node.child_by_field_name("constructor").map(text)?;
+                let type_arguments = node.child_by_field_name("type_arguments").map(text).unwrap_or_default();
+                return Some(Inferred::new(
+                    format!("{}{}", constructor, type_arguments),
+                    format!("constructor `{}`", constructor),
+                ));
+            }
+            "object_creation_expression" => {
+                let ty = node.child_by_field_name("type").map(text)?;
+                return Some(Inferred::new(ty.clone(), format!("constructor `{}`", ty)));
+            }
+            "struct_expression" => {
+                let ty = node.child_by_field_name("name").map(text)?;
+                return Some(Inferred::new(ty.clone(), format!("struct literal `{}`", ty)));
+            }
+            "composite_literal" => {
+                let ty = node.child_by_field_name("type").map(text)?;
+                return Some(Inferred::new(ty.clone(), format!("composite literal `{}`", ty)));
+            }
+            "unary_expression" if lang == "go" => {
+                let operator = node.child_by_field_name("operator").map(text);
+                let operand = node.child_by_field_name("operand")?;
+                if operator.as_deref() == Some("&") && operand.kind() == "composite_literal" {
+                    let inner = self.infer_expression(&operand, content, lang, env)?;
+                    return Some(Inferred::new(format!("*{}", inner.ty), inner.origin));
+                }
+                return None;
+            }
+            _ => {}
+        }
+
+        if let Some((receiver, method)) = self.method_call_parts(node, content, lang) {
+            let receiver_type = self
+                .infer_expression(&receiver, content, lang, env)
+                .map(|inferred| inferred.ty);
+            let qualified = receiver_type.map(|ty| format!("{}::{}", self.base_type(&ty), method));
+
+            return qualified
+                .and_then(|key| env.methods.get(&key))
+                .or_else(|| env.methods.get(&method))
+                .map(|ty| Inferred::new(ty.clone(), format!("return type of `{}`", method)));
+        }
+
+        if let Some(callee) = self.plain_callee(node, content, lang) {
+            if let Some(ty) = env.functions.get(&callee).or_else(|| env.methods.get(&callee)) {
+                return Some(Inferred::new(ty.clone(), format!("return type of `{}`", callee)));
+            }
+
+            // `Foo::new()`, `String::from(..)` and friends construct their path type
+            if let Some((path, name)) = callee.rsplit_once("::") {
+                let is_constructor = matches!(name, "new" | "default" | "from" | "with_capacity")
+                    || name.starts_with("new_");
+                if is_constructor && path.chars().next().is_some_and(|c| c.is_uppercase()) {
+                    return Some(Inferred::new(path.to_string(), format!("constructor `{}`", callee)));
+                }
+            }
+
+            // Python and Kotlin construct classes by calling them
+            if matches!(lang, "python" | "kotlin") && callee.chars().next().is_some_and(|c| c.is_uppercase()) {
+                return Some(Inferred::new(callee.clone(), format!("constructor `{}`", callee)));
+            }
+        }
+
+        None
+    }
+
+    /// Split a method call into its receiver expression and method name
+    fn method_call_parts<'a>(
+        &self,
+        node: &tree_sitter::Node<'a>,
+        content: &str,
+        lang: &str,
+    ) -> Option<(tree_sitter::Node<'a>, String)> {
+        let text = |n: tree_sitter::Node| content[n.start_byte()..n.end_byte()].to_string();
+
+        match (lang, node.kind()) {
+            ("python", "call") => {
+                let function = node.child_by_field_name("function").filter(|f| f.kind() == "attribute")?;
+                Some((function.child_by_field_name("object")?, text(function.child_by_field_name("attribute")?)))
+            }
+            ("javascript" | "typescript" | "tsx" | "jsx", "call_expression") => {
+                let function = node.child_by_field_name("function").filter(|f| f.kind() == "member_expression")?;
+                Some((function.child_by_field_name("object")?, text(function.child_by_field_name("property")?)))
+            }
+            ("rust", "call_expression") => {
+                let function = node.child_by_field_name("function").filter(|f| f.kind() == "field_expression")?;
+                Some((function.child_by_field_name("value")?, text(function.child_by_field_name("field")?)))
+            }
+            ("go", "call_expression") => {
+                let function = node.child_by_field_name("function").filter(|f| f.kind() == "selector_expression")?;
+                Some((function.child_by_field_name("operand")?, text(function.child_by_field_name("field")?)))
+            }
+            ("java", "method_invocation") => {
+                Some((node.child_by_field_name("object")?, text(node.child_by_field_name("name")?)))
+            }
+            ("kotlin", "call_expression") => {
+                let navigation = node.named_child(0).filter(|n| n.kind() == "navigation_expression")?;
+                let method = self
+                    .child_of_kind(&navigation, "navigation_suffix")
+                    .and_then(|suffix| self.child_of_kind(&suffix, "simple_identifier"))?;
+                Some((navigation.named_child(0)?, text(method)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Name of the free function, associated function or constructor invoked by a call
+    fn plain_callee(&self, node: &tree_sitter::Node, content: &str, lang: &str) -> Option<String> {
+        let text = |n: tree_sitter::Node| content[n.start_byte()..n.end_byte()].to_string();
+
+        match (lang, node.kind()) {
+            ("python", "call")
+            | ("javascript" | "typescript" | "tsx" | "jsx", "call_expression")
+            | ("go", "call_expression") => node
+                .child_by_field_name("function")
+                .filter(|f| f.kind() == "identifier")
+                .map(text),
+            ("javascript" | "typescript" | "tsx" | "jsx", "new_expression") => {
+                node.child_by_field_name("constructor").map(text)
+            }
+            ("rust", "call_expression") => node
+                .child_by_field_name("function")
+                .filter(|f| matches!(f.kind(), "identifier" | "scoped_identifier"))
+                .map(text),
+            ("java", "method_invocation") if node.child_by_field_name("object").is_none() => {
+                node.child_by_field_name("name").map(text)
+            }
+            ("java", "object_creation_expression") => node.child_by_field_name("type").map(text),
+            ("kotlin", "call_expression") => node
+                .named_child(0)
+                .filter(|n| n.kind() == "simple_identifier")
+                .map(text),
+            _ => None,
+        }
+    }
+
+    /// Key under which the callee of a call is stored in `env`
+    fn callee_key(&self, node: &tree_sitter::Node, content: &str, lang: &str, env: &TypeEnv) -> Option<String> {
+        if let Some((receiver, method)) = self.method_call_parts(node, content, lang) {
+            let qualified = self
+                .infer_expression(&receiver, content, lang, env)
+                .map(|inferred| format!("{}::{}", self.base_type(&inferred.ty), method));
+
+            return match qualified {
+                Some(key) if env.parameters.contains_key(&key) => Some(key),
+                _ => Some(method),
+            };
+        }
+
+        self.plain_callee(node, content, lang)
+    }
+
+    /// Return type of a Go function; multiple results become `(A, B)`
+    fn go_result_type(&self, result: &tree_sitter::Node, content: &str) -> String {
+        if result.kind() != "parameter_list" {
+            return content[result.start_byte()..result.end_byte()].to_string();
+        }
+
+        let mut cursor = result.walk();
+        let types: Vec<String> = result
+            .named_children(&mut cursor)
+            .filter_map(|p| p.child_by_field_name("type"))
+            .map(|t| content[t.start_byte()..t.end_byte()].to_string())
+            .collect();
+
+        if types.len() == 1 {
+            types[0].clone()
+        } else {
+            format!("({})", types.join(", "))
+        }
+    }
+
+    /// Type written after the `:` of a Kotlin declaration
+    fn kotlin_declared_type(&self, node: &tree_sitter::Node, content: &str) -> Option<String> {
+        let mut cursor = node.walk();
+        let declared = node
+            .children(&mut cursor)
+            .skip_while(|c| c.kind() != ":")
+            .find(|c| c.is_named() && !matches!(c.kind(), "function_body" | "type_constraints"))
+            .map(|t| content[t.start_byte()..t.end_byte()].to_string());
+        declared
+    }
+
+    /// Split `(A, B<C, D>)` into its top-level elements
+    fn split_tuple_type(&self, ty: &str) -> Vec<String> {
+        let inner = match ty.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+            Some(inner) => inner,
+            None => return vec![ty.to_string()],
+        };
+
+        let mut parts = Vec::new();
+        let mut depth = 0i32;
+        let mut current = String::new();
+        for ch in inner.chars() {
+            match ch {
+                '(' | '<' | '[' | '{' => depth += 1,
+                ')' | '>' | ']' | '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(current.trim().to_string());
+                    current.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(ch);
+        }
+        if !current.trim().is_empty() {
+            parts.push(current.trim().to_string());
+        }
+
+        parts
+    }
+
+    /// Strip annotation punctuation and quotes (`: Foo`, `"Foo"`)
+    fn clean_type(&self, ty: &str) -> String {
+        ty.trim_start_matches(':')
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_string()
+    }
+
+    /// Reduce a type to the name methods are registered under (`&mut Foo<T>` -> `Foo`)
+    fn base_type(&self, ty: &str) -> String {
+        let ty = ty
+            .trim_start_matches(['&', '*'])
+            .trim_start_matches("mut ")
+            .trim();
+        ty.split(['<', '[', '(']).next().unwrap_or(ty).trim().to_string()
+    }
+
+    /// First direct child with the given kind
+    fn child_of_kind<'a>(&self, node: &tree_sitter::Node<'a>, kind: &str) -> Option<tree_sitter::Node<'a>> {
+        let mut cursor = node.walk();
+        let found = node.children(&mut cursor).find(|c| c.kind() == kind);
+        found
+    }
+
+    /// First identifier in a parameter declaration, depth-first
+    fn first_identifier<'a>(&self, node: &tree_sitter::Node<'a>) -> Option<tree_sitter::Node<'a>> {
+        if matches!(node.kind(), "identifier" | "simple_identifier") {
+            return Some(*node);
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<_> = node.named_children(&mut cursor).collect();
+        children.iter().find_map(|child| self.first_identifier(child))
+    }
+
+    /// Infer Python type from expression
+    fn infer_python_type(&self, node: &tree_sitter::Node, content: &str) -> Option<String> {
+        match node.kind() {
+            "integer" => Some("int".to_string()),
+            "float" => Some("float".to_string()),
+            "string" | "string_content" => Some("str".to_string()),
+            "true" | "false" => Some("bool".to_string()),
+            "list" => Some("list".to_string()),
+            "dictionary" => Some("dict".to_string()),
+            "tuple" => Some("tuple".to_string()),
+            "set" => Some("set".to_string()),
+            "call" => {
+                // Try to infer from function name
+                if let Some(func) = node.child_by_field_name("function") {
+                    let func_name = &content[func.start_byte()..func.end_byte()];
+                    match func_name {
+                        "int" => Some("int".to_string()),
+                        "float" => Some("float".to_string()),
+                        "str" => Some("str".to_string()),
+                        "list" => Some("list".to_string()),
+                        "dict" => Some("dict".to_string()),
+                        "set" => Some("set".to_string()),
+                        "tuple" => Some("tuple".to_string()),
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Infer JavaScript type from expression
+    fn infer_js_type(&self, node: &tree_sitter::Node, _content: &str) -> Option<String> {
+        match node.kind() {
+            "number" => Some("number".to_string()),
+            "string" | "template_string" => Some("string".to_string()),
+            "true" | "false" => Some("boolean".to_string()),
+            "array" => Some("Array".to_string()),
+            "object" => Some("Object".to_string()),
+            "arrow_function" | "function" => Some("Function".to_string()),
+            "null" => Some("null".to_string()),
+            "undefined" => Some("undefined".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Infer Rust type from expression
+    fn infer_rust_type(&self, node: &tree_sitter::Node, content: &str) -> Option<String> {
+        match node.kind() {
+            "integer_literal" => {
+                let text = &content[node.start_byte()..node.end_byte()];
+                if text.ends_with("u32") || text.ends_with("i32") || text.ends_with("u64") || text.ends_with("i64") {
+                    None // Already has type suffix
+                } else {
+                    Some("i32".to_string())
+                }
+            }
+            "float_literal" => Some("f64".to_string()),
+            "string_literal" | "raw_string_literal" => Some("&str".to_string()),
+            "char_literal" => Some("char".to_string()),
+            "true" | "false" => Some("bool".to_string()),
+            "array_expression" => Some("Vec".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Infer Go type from expression
+    fn infer_go_type(&self, node: &tree_sitter::Node, _content: &str) -> Option<String> {
+        match node.kind() {
+            "int_literal" => Some("int".to_string()),
+            "float_literal" => Some("float64".to_string()),
+            "imaginary_literal" => Some("complex128".to_string()),
+            "interpreted_string_literal" | "raw_string_literal" => Some("string".to_string()),
+            "rune_literal" => Some("rune".to_string()),
+            "true" | "false" => Some("bool".to_string()),
+            "func_literal" => Some("func".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Infer Java type from expression
+    fn infer_java_type(&self, node: &tree_sitter::Node, content: &str) -> Option<String> {
+        match node.kind() {
+            "decimal_integer_literal" | "hex_integer_literal" | "octal_integer_literal" | "binary_integer_literal" => {
+                let text = &content[node.start_byte()..node.end_byte()];
+                if text.ends_with(['l', 'L']) {
+                    Some("long".to_string())
+                } else {
+                    Some("int".to_string())
+                }
+            }
+            "decimal_floating_point_literal" | "hex_floating_point_literal" => {
+                let text = &content[node.start_byte()..node.end_byte()];
+                if text.ends_with(['f', 'F']) {
+                    Some("float".to_string())
+                } else {
+                    Some("double".to_string())
+                }
+            }
+            "string_literal" | "text_block" => Some("String".to_string()),
+            "character_literal" => Some("char".to_string()),
+            "true" | "false" => Some("boolean".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Infer Kotlin type from expression
+    fn infer_kotlin_type(&self, node: &tree_sitter::Node, _content: &str) -> Option<String> {
+        match node.kind() {
+            "integer_literal" | "hex_literal" | "bin_literal" => Some("Int".to_string()),
+            "long_literal" => Some("Long".to_string()),
+            "real_literal" => Some("Double".to_string()),
+            "string_literal" | "line_string_literal" | "multi_line_string_literal" => Some("String".to_string()),
+            "character_literal" => Some("Char".to_string()),
+            "boolean_literal" => Some("Boolean".to_string()),
+            "lambda_literal" => Some("Function".to_string()),
+            _ => None,
+        }
+    }
+
+}
 
 impl Default for InlayHintsProvider {
     fn default() -> Self {
         Self::new()
     }
 }
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -637,13 +1635,234 @@ fn main() {
 
     #[test]
     fn test_position_conversion() {
-        let provider = InlayHintsProvider::new();
         let content = "line1\nline2\nline3";
 
-        let byte_offset = provider.position_to_byte(content, Position { line: 1, character: 3 });
-        let position = provider.byte_to_position(content, byte_offset);
+        let byte_offset = position_to_byte(content, Position { line: 1, character: 3 });
+        let position = byte_to_position(content, byte_offset);
 
         assert_eq!(position.line, 1);
         assert_eq!(position.character, 3);
     }
+
+    fn full_range() -> Range {
+        Range {
+            start: Position { line: 0, character: 0 },
+            end: Position { line: 100, character: 0 },
+        }
+    }
+
+    fn labels(hints: &[InlayHint]) -> Vec<String> {
+        hints
+            .iter()
+            .map(|h| match &h.label {
+                InlayHintLabel::String(s) => s.clone(),
+                InlayHintLabel::LabelParts(parts) => parts.iter().map(|p| p.value.as_str()).collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_return_type_propagation() {
+        let provider = InlayHintsProvider::new();
+        let content = r#"
+struct Config { debug: bool }
+
+impl Config {
+    fn new() -> Self { Config { debug: false } }
+}
+
+fn load() -> Config { Config::new() }
+
+fn main() {
+    let a = load();
+    let b = Config::new();
+    let c = Config { debug: true };
+}
+"#;
+
+        let hints = provider.get_inlay_hints(content, full_range(), "rust").unwrap();
+        let labels = labels(&hints);
+        assert_eq!(labels.iter().filter(|l| *l == ": Config").count(), 3, "{:?}", labels);
+    }
+
+    #[test]
+    fn test_shadowed_bindings() {
+        let provider = InlayHintsProvider::new().with_config(false, true);
+        let content = r#"
+struct Config;
+struct Request;
+
+fn load() -> Config { Config }
+fn fetch() -> Request { Request }
+
+fn main() {
+    let a = load();
+    {
+        let a = fetch();
+        let inner = a;
+    }
+    let outer = a;
+    let a = fetch();
+    let last = a;
+}
+"#;
+
+        let hints = provider.get_inlay_hints(content, full_range(), "rust").unwrap();
+        assert_eq!(
+            labels(&hints),
+            vec![": Config", ": Request", ": Request", ": Config", ": Request", ": Request"]
+        );
+    }
+
+    #[test]
+    fn test_python_constructor_and_return_type() {
+        let provider = InlayHintsProvider::new().with_config(false, true);
+        let content = r#"
+class Point:
+    def scale(self, factor) -> "Point":
+        return self
+
+def origin() -> Point:
+    return Point()
+
+p = Point(1, 2)
+q = origin()
+r = p.scale(2)
+"#;
+
+        let hints = provider.get_inlay_hints(content, full_range(), "python").unwrap();
+        let labels = labels(&hints);
+        assert_eq!(labels.iter().filter(|l| *l == ": Point").count(), 3, "{:?}", labels);
+    }
+
+    #[test]
+    fn test_parameter_names_from_declaration() {
+        let provider = InlayHintsProvider::new().with_config(true, false);
+        let content = r#"
+def connect(host, port):
+    pass
+
+connect("localhost", 8080)
+"#;
+
+        let hints = provider.get_inlay_hints(content, full_range(), "python").unwrap();
+        assert_eq!(labels(&hints), vec!["host:", "port:"]);
+    }
+
+    #[test]
+    fn test_typescript_hints() {
+        let provider = InlayHintsProvider::new().with_config(false, true);
+        let content = r#"
+function makeUser(name: string): User { return new User(name); }
+const a = makeUser("x");
+const b = new Map<string, number>();
+const c: User = makeUser("y");
+"#;
+
+        let hints = provider.get_inlay_hints(content, full_range(), "typescript").unwrap();
+        assert_eq!(labels(&hints), vec![": User", ": Map<string, number>"]);
+    }
+
+    #[test]
+    fn test_go_hints() {
+        let provider = InlayHintsProvider::new().with_config(false, true);
+        let content = r#"
+package main
+
+func NewServer(addr string) (*Server, error) { return &Server{}, nil }
+
+func main() {
+	srv, err := NewServer(":80")
+	cfg := Config{Port: 80}
+	ptr := &Config{}
+	n := 42
+}
+"#;
+
+        let hints = provider.get_inlay_hints(content, full_range(), "go").unwrap();
+        assert_eq!(labels(&hints), vec![": *Server", ": error", ": Config", ": *Config", ": int"]);
+    }
+
+    #[test]
+    fn test_java_var_hints() {
+        let provider = InlayHintsProvider::new().with_config(false, true);
+        let content = r#"
+class App {
+    Report build() { return new Report(); }
+
+    void run() {
+        var list = new ArrayList<String>();
+        var report = build();
+        var count = 3;
+        int explicit = 4;
+    }
+}
+"#;
+
+        let hints = provider.get_inlay_hints(content, full_range(), "java").unwrap();
+        assert_eq!(labels(&hints), vec![": ArrayList<String>", ": Report", ": int"]);
+    }
+
+    #[test]
+    fn test_chaining_hints() {
+        let provider = InlayHintsProvider::new().with_config(false, true);
+        let content = r#"
+struct Builder;
+struct Request;
+
+impl Builder {
+    fn new() -> Self { Builder }
+    fn header(self) -> Builder { self }
+    fn build(self) -> Request { Request }
+}
+
+fn main() {
+    let req = Builder::new()
+        .header()
+        .header()
+        .build();
+}
+"#;
+
+        let hints = provider.get_inlay_hints(content, full_range(), "rust").unwrap();
+        let labels = labels(&hints);
+        assert_eq!(labels.iter().filter(|l| *l == "Builder").count(), 3, "{:?}", labels);
+        assert!(labels.contains(&": Request".to_string()), "{:?}", labels);
+    }
+
+    #[test]
+    fn test_closing_brace_hints() {
+        let provider = InlayHintsProvider::new()
+            .with_config(false, false)
+            .with_closing_brace_hints(Some(3));
+        let content = "fn short() {}\n\nfn long_function() {\n    let a = 1;\n    let b = 2;\n}\n";
+
+        let hints = provider.get_inlay_hints(content, full_range(), "rust").unwrap();
+        assert_eq!(labels(&hints), vec!["// fn long_function"]);
+        assert_eq!(hints[0].position, Position { line: 5, character: 1 });
+
+        let disabled = InlayHintsProvider::new()
+            .with_config(false, false)
+            .with_closing_brace_hints(None);
+        assert!(disabled.get_inlay_hints(content, full_range(), "rust").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_adds_tooltip() {
+        let provider = InlayHintsProvider::new().with_config(false, true);
+        let content = "fn make() -> Widget { Widget }\nfn main() { let w = make(); }\n";
+
+        let hints = provider.get_inlay_hints(content, full_range(), "rust").unwrap();
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].tooltip.is_none());
+
+        let resolved = provider.resolve_inlay_hint(hints[0].clone());
+        match resolved.tooltip {
+            Some(InlayHintTooltip::MarkupContent(markup)) => {
+                assert!(markup.value.contains("w: Widget"));
+                assert!(markup.value.contains("return type of `make`"));
+            }
+            other => panic!("expected markdown tooltip, got {:?}", other),
+        }
+    }
 }
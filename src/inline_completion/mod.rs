@@ -2,12 +2,23 @@
 //!
 //! This module provides enhanced completion functionality for ghost text/inline
 //! completions, with debouncing, caching, and request cancellation.
+//!
+//! Suggestions are served through `textDocument/inlineCompletion` ([`protocol`]).
+//! A new request for a document supersedes the pending one ([`PendingRequest`]), and
+//! text typed or partially accepted from the last shown suggestion is answered from
+//! it without asking the model again ([`InlineCompletionManager::continuation`]).
 
 use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 use tokio::time::sleep;
+use tower_lsp::lsp_types::{Position, Range};
+
+pub mod protocol;
+
+use protocol::{InlineCompletionItem, SelectedCompletionInfo};
 
 /// Debounce delay for completion requests (milliseconds)
 const DEBOUNCE_DELAY_MS: u64 = 300;
@@ -56,6 +67,17 @@ impl CompletionKey {
     }
 }
 
+/// Suggestions last shown for a document
+#[derive(Debug, Clone)]
+struct Shown {
+    prefix: String,
+    suffix: Option<String>,
+    /// Text each suggestion inserts after the cursor
+    remainders: Vec<String>,
+}
+
+type CancellationTokens = Arc<DashMap<String, (u64, watch::Sender<bool>)>>;
+
 /// Inline completion manager with debouncing and caching
 #[derive(Clone)]
 pub struct InlineCompletionManager {
@@ -64,7 +86,35 @@ pub struct InlineCompletionManager {
     /// Last request timestamp per URI (for debouncing)
     last_request: Arc<RwLock<DashMap<String, Instant>>>,
     /// Pending request cancellation tokens
-    cancellation_tokens: Arc<DashMap<String, tokio::sync::watch::Sender<bool>>>,
+    cancellation_tokens: CancellationTokens,
+    next_request_id: Arc<AtomicU64>,
+    /// Suggestions last shown per URI
+    shown: Arc<DashMap<String, Shown>>,
+}
+
+/// The pending inline completion request of a document
+///
+/// Cancelled when a newer request for the document begins. Dropping it (when the
+/// request completes, or when the client cancels it and the handler future is
+/// dropped) releases the token.
+pub struct PendingRequest {
+    tokens: CancellationTokens,
+    uri: String,
+    id: u64,
+    cancel_rx: watch::Receiver<bool>,
+}
+
+impl PendingRequest {
+    /// Resolves once a newer request superseded this one
+    pub async fn cancelled(&mut self) {
+        let _ = self.cancel_rx.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+impl Drop for PendingRequest {
+    fn drop(&mut self) {
+        self.tokens.remove_if(&self.uri, |_, (id, _)| *id == self.id);
+    }
 }
 
 impl InlineCompletionManager {
@@ -74,7 +124,51 @@ impl InlineCompletionManager {
             cache: Arc::new(DashMap::new()),
             last_request: Arc::new(RwLock::new(DashMap::new())),
             cancellation_tokens: Arc::new(DashMap::new()),
+            next_request_id: Arc::new(AtomicU64::new(0)),
+            shown: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Start a request for `uri`, cancelling the pending one
+    pub fn begin_request(&self, uri: &str) -> PendingRequest {
+        let (id, cancel_rx) = self.new_token(uri.to_string());
+        PendingRequest {
+            tokens: self.cancellation_tokens.clone(),
+            uri: uri.to_string(),
+            id,
+            cancel_rx,
+        }
+    }
+
+    /// Remember the suggestions shown at the end of `prefix`; `remainders` are the
+    /// texts they insert after the cursor
+    pub fn remember_shown(&self, uri: &str, prefix: &str, suffix: &Option<String>, remainders: Vec<String>) {
+        self.shown.insert(
+            uri.to_string(),
+            Shown {
+                prefix: prefix.to_string(),
+                suffix: suffix.clone(),
+                remainders,
+            },
+        );
+    }
+
+    /// What is left of the last shown suggestions after the user typed, or partially
+    /// accepted, their beginning
+    pub fn continuation(&self, uri: &str, prefix: &str, suffix: &Option<String>) -> Option<Vec<String>> {
+        let shown = self.shown.get(uri)?;
+        if &shown.suffix != suffix {
+            return None;
         }
+        let typed = prefix.strip_prefix(shown.prefix.as_str()).filter(|typed| !typed.is_empty())?;
+        let rest: Vec<String> = shown
+            .remainders
+            .iter()
+            .filter_map(|remainder| remainder.strip_prefix(typed))
+            .filter(|rest| !rest.is_empty())
+            .map(str::to_string)
+            .collect();
+        (!rest.is_empty()).then_some(rest)
     }
 
     /// Check if we should debounce this completion request
@@ -149,21 +243,26 @@ impl InlineCompletionManager {
     /// Create a cancellation token for a request
     ///
     /// Returns a receiver that will be notified when the request should be cancelled
-    pub fn create_cancellation_token(&self, uri: String) -> tokio::sync::watch::Receiver<bool> {
+    pub fn create_cancellation_token(&self, uri: String) -> watch::Receiver<bool> {
+        self.new_token(uri).1
+    }
+
+    fn new_token(&self, uri: String) -> (u64, watch::Receiver<bool>) {
         // Cancel any existing request for this URI
-        if let Some((_, sender)) = self.cancellation_tokens.remove(&uri) {
+        if let Some((_, (_, sender))) = self.cancellation_tokens.remove(&uri) {
             let _ = sender.send(true);
         }
 
         // Create new cancellation token
-        let (tx, rx) = tokio::sync::watch::channel(false);
-        self.cancellation_tokens.insert(uri, tx);
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = watch::channel(false);
+        self.cancellation_tokens.insert(uri, (id, tx));
 
-        rx
+        (id, rx)
     }
 
     /// Check if a request was cancelled
-    pub fn is_cancelled(&self, _uri: &str, rx: &tokio::sync::watch::Receiver<bool>) -> bool {
+    pub fn is_cancelled(&self, _uri: &str, rx: &watch::Receiver<bool>) -> bool {
         *rx.borrow()
     }
 
@@ -189,6 +288,67 @@ impl Default for InlineCompletionManager {
     }
 }
 
+/// Ghost text item for a suggestion `text` continuing the code at `position`
+///
+/// A suggestion that repeats what is already typed on the line replaces it, one
+/// ending with the rest of the line (closing brackets the editor inserted) replaces
+/// that too, and with an item `selected` in the completion widget the ghost text
+/// starts with that item.
+pub fn inline_item(content: &str, position: Position, text: &str, selected: Option<&SelectedCompletionInfo>) -> InlineCompletionItem {
+    let (before, after) = split_line(content, position);
+    let line_end = Position {
+        line: position.line,
+        character: position.character + after.encode_utf16().count() as u32,
+    };
+
+    let typed = before.trim_start();
+    let (start, insert_text) = match selected {
+        Some(selected) => (selected.range.start, format!("{}{}", selected.text, text)),
+        None if !typed.is_empty() && text.starts_with(typed) => {
+            let indent = before.len() - typed.len();
+            (Position { line: position.line, character: before[..indent].encode_utf16().count() as u32 }, text.to_string())
+        }
+        None => (position, text.to_string()),
+    };
+
+    let rest_of_line = after.trim_end();
+    let first_line = insert_text.lines().next().unwrap_or("").trim_end();
+    let end = if !rest_of_line.is_empty() && first_line.ends_with(rest_of_line) { line_end } else { position };
+
+    InlineCompletionItem {
+        insert_text,
+        filter_text: None,
+        range: Some(Range { start, end }),
+        command: None,
+    }
+}
+
+/// Text a suggestion inserts after the cursor, without the part of the line it repeats
+pub fn remainder(content: &str, position: Position, text: &str) -> String {
+    let (before, _) = split_line(content, position);
+    let typed = before.trim_start();
+    match text.strip_prefix(typed) {
+        Some(rest) if !typed.is_empty() => rest.to_string(),
+        _ => text.to_string(),
+    }
+}
+
+/// The line of `position` split at it, without the line break
+fn split_line(content: &str, position: Position) -> (&str, &str) {
+    let line = content.split('\n').nth(position.line as usize).unwrap_or("");
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut units = 0;
+    let split = line
+        .char_indices()
+        .find(|(_, c)| {
+            let reached = units >= position.character as usize;
+            units += c.len_utf16();
+            reached
+        })
+        .map_or(line.len(), |(i, _)| i);
+    line.split_at(split)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,6 +417,61 @@ mod tests {
         assert!(manager.get_cached(&uri, prefix, &suffix).is_none());
     }
 
+    #[tokio::test]
+    async fn test_superseded_request() {
+        let manager = InlineCompletionManager::new();
+        let uri = "file:///test.rs";
+
+        let mut first = manager.begin_request(uri);
+        let second = manager.begin_request(uri);
+        tokio::time::timeout(Duration::from_secs(1), first.cancelled()).await.unwrap();
+
+        // Finishing the superseded request leaves the newer token in place
+        drop(first);
+        assert!(manager.cancellation_tokens.contains_key(uri));
+        drop(second);
+        assert!(!manager.cancellation_tokens.contains_key(uri));
+    }
+
+    #[test]
+    fn test_continuation() {
+        let manager = InlineCompletionManager::new();
+        let uri = "file:///test.py";
+        let suffix = Some("\n".to_string());
+        manager.remember_shown(uri, "def f(", &suffix, vec!["a, b):".to_string(), "x):".to_string()]);
+
+        // Typing (or accepting a word of) the ghost text keeps the rest on offer
+        assert_eq!(manager.continuation(uri, "def f(a, ", &suffix), Some(vec!["b):".to_string()]));
+        assert_eq!(manager.continuation(uri, "def f(y", &suffix), None);
+        assert_eq!(manager.continuation(uri, "def f(", &suffix), None);
+        assert_eq!(manager.continuation(uri, "def f(a, ", &None), None);
+    }
+
+    #[test]
+    fn test_inline_item_ranges() {
+        let content = "fn main() {\n    let total = \n    foo()\n}\n";
+        let at = |line, character| Position { line, character };
+
+        // Plain continuation inserts at the cursor
+        let item = inline_item(content, at(1, 16), "items.len();", None);
+        assert_eq!(item.range, Some(Range { start: at(1, 16), end: at(1, 16) }));
+
+        // A suggestion repeating the typed line replaces it from the indentation
+        let item = inline_item(content, at(1, 16), "let total = items.len();", None);
+        assert_eq!(item.range, Some(Range { start: at(1, 4), end: at(1, 16) }));
+        assert_eq!(remainder(content, at(1, 16), "let total = items.len();"), "items.len();");
+
+        // A suggestion ending with the auto-inserted `)` replaces it
+        let item = inline_item(content, at(2, 8), "bar, baz)", None);
+        assert_eq!(item.range, Some(Range { start: at(2, 8), end: at(2, 9) }));
+
+        // The selected completion item leads the ghost text
+        let selected = SelectedCompletionInfo { range: Range { start: at(2, 4), end: at(2, 7) }, text: "foobar".to_string() };
+        let item = inline_item(content, at(2, 7), "(1)", Some(&selected));
+        assert_eq!(item.insert_text, "foobar(1)");
+        assert_eq!(item.range.unwrap().start, at(2, 4));
+    }
+
     #[tokio::test]
     async fn test_cancellation() {
         let manager = InlineCompletionManager::new();
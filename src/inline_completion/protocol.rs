@@ -0,0 +1,96 @@
+//! `textDocument/inlineCompletion` protocol types (LSP 3.18)
+//!
+//! lsp-types 0.94 predates inline completions, so the request is served as a custom
+//! method with these definitions, and the client capability is read from the raw
+//! `initialize` parameters.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower_lsp::lsp_types::{Command, Range, TextDocumentPositionParams, WorkDoneProgressParams};
+
+/// Method name of the request
+pub const METHOD: &str = "textDocument/inlineCompletion";
+
+/// Whether the client's `initialize` parameters allow registering the request
+/// dynamically (`textDocument.inlineCompletion.dynamicRegistration`)
+pub fn dynamic_registration(initialize_params: Option<&Value>) -> bool {
+    initialize_params
+        .and_then(|params| params.pointer("/capabilities/textDocument/inlineCompletion/dynamicRegistration"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineCompletionParams {
+    #[serde(flatten)]
+    pub text_document_position: TextDocumentPositionParams,
+    pub context: InlineCompletionContext,
+    #[serde(flatten)]
+    pub work_done_progress_params: WorkDoneProgressParams,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineCompletionContext {
+    pub trigger_kind: InlineCompletionTriggerKind,
+    /// Item selected in the completion widget, which the ghost text should extend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected_completion_info: Option<SelectedCompletionInfo>,
+}
+
+/// How an inline completion was triggered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct InlineCompletionTriggerKind(i32);
+
+impl InlineCompletionTriggerKind {
+    /// Explicitly requested by the user
+    pub const INVOKED: Self = Self(1);
+    /// Requested while typing
+    pub const AUTOMATIC: Self = Self(2);
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectedCompletionInfo {
+    /// Range the selected item replaces
+    pub range: Range,
+    /// Text the selected item inserts
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineCompletionItem {
+    pub insert_text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_text: Option<String>,
+    /// Range replaced by `insert_text`, the cursor position when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct InlineCompletionList {
+    pub items: Vec<InlineCompletionItem>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_dynamic_registration() {
+        let params = json!({
+            "processId": null,
+            "capabilities": { "textDocument": { "inlineCompletion": { "dynamicRegistration": true } } }
+        });
+        assert!(dynamic_registration(Some(&params)));
+        assert!(!dynamic_registration(Some(&json!({ "capabilities": {} }))));
+        assert!(!dynamic_registration(None));
+    }
+}
@@ -376,6 +376,46 @@ pub fn detect_language(path: &str) -> &'static str {
     "Unknown"
 }
 
+/// Keywords of the language of a file path
+pub fn keywords(path: &str) -> &'static [&'static str] {
+    let name = detect_language(path);
+    LANGUAGES.iter().find(|lang| lang.name == name).map_or(&[], |lang| lang.keywords)
+}
+
+impl Language {
+    /// Characters that trigger completion: the last character of the language's
+    /// member access operators (`.`, `::`, `->`), `<` of markup tags and `"` (or `<`)
+    /// opening an include or import path
+    pub fn trigger_characters(&self) -> &'static [&'static str] {
+        match self.name {
+            "C" | "Objective-C" => &[".", ">", "\"", "<"],
+            "C++" | "Objective-C++" => &[".", ":", ">", "\"", "<"],
+            "Rust" => &[".", ":"],
+            "PHP" | "Hack" => &[">", ":"],
+            "Ruby" | "Crystal" => &[".", ":"],
+            "JavaScript" | "TypeScript" => &[".", "\"", "'", "<"],
+            "HTML" | "XML" | "Vue" | "Svelte" | "Astro" => &["<"],
+            "CSS" | "SCSS" | "SASS" | "Less" | "Stylus" | "JSON" | "YAML" | "TOML" | "INI" | "Markdown" | "LaTeX"
+            | "AsciiDoc" | "reStructuredText" | "Bash" | "Zsh" | "Fish" | "Makefile" | "Dockerfile" | "CMake" => &[],
+            _ => &["."],
+        }
+    }
+}
+
+/// Tree-sitter grammar name for a file path ("main.rs" -> "rust", "app.cpp" -> "cpp")
+pub fn grammar_name(path: &str) -> String {
+    // `.tsx` is detected as TypeScript, but needs the TSX grammar for JSX
+    if path.ends_with(".tsx") {
+        return "tsx".to_string();
+    }
+
+    match detect_language(path) {
+        "C++" => "cpp".to_string(),
+        "C#" => "csharp".to_string(),
+        other => other.to_lowercase(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -389,6 +429,28 @@ mod tests {
         assert_eq!(detect_language("unknown.xyz"), "Unknown");
     }
 
+    #[test]
+    fn test_grammar_name() {
+        assert_eq!(grammar_name("main.rs"), "rust");
+        assert_eq!(grammar_name("widget.cpp"), "cpp");
+        assert_eq!(grammar_name("Program.cs"), "csharp");
+        assert_eq!(grammar_name("App.tsx"), "tsx");
+        assert_eq!(grammar_name("index.ts"), "typescript");
+    }
+
+    #[test]
+    fn test_trigger_characters() {
+        let trigger_characters = |path: &str| {
+            let name = detect_language(path);
+            LANGUAGES.iter().find(|lang| lang.name == name).unwrap().trigger_characters()
+        };
+        assert_eq!(trigger_characters("main.rs"), [".", ":"]);
+        assert_eq!(trigger_characters("widget.cpp"), [".", ":", ">", "\"", "<"]);
+        assert_eq!(trigger_characters("index.html"), ["<"]);
+        assert_eq!(trigger_characters("server.py"), ["."]);
+        assert!(trigger_characters("config.yaml").is_empty());
+    }
+
     #[test]
     fn test_all_languages_have_extensions() {
         for lang in LANGUAGES.iter() {
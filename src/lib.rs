@@ -31,6 +31,7 @@
 //!
 //! - **Claude Integration**: Context-aware AI completions using Anthropic's Claude
 //! - **GitHub Copilot Support**: Compatible with Copilot-style completions
+//! - **Local Models**: Ollama and OpenAI-compatible servers, chosen per feature
 //! - **Intelligent Suggestions**: Machine learning-enhanced code suggestions
 //! - **Natural Language Code Generation**: Convert comments to code
 //!
@@ -158,7 +159,7 @@
 //! ### Core Modules
 //!
 //! - [`acp`] - Agent Client Protocol implementation for editor-to-AI communication
-//! - [`ai`] - AI provider integrations (Claude, Copilot) with intelligent completion engines
+//! - [`ai`] - AI provider integrations (Claude, Copilot, OpenAI-compatible, Ollama) behind the [`ai::AiProvider`] trait
 //! - [`config`] - Configuration management and validation
 //! - [`tree_sitter`] - Multi-language parsing using Tree-sitter grammars
 //!
@@ -168,6 +169,12 @@
 //! - [`diagnostics`] - Error detection, validation, and diagnostic reporting
 //! - [`code_actions`] - Quick fixes, refactorings, and code transformations
 //! - [`formatting`] - Code formatting and style enforcement
+//! - [`folding_range`] - Folding of blocks, comments, imports and regions
+//! - [`selection_range`] - Syntax-aware expand/shrink selection
+//! - [`document_highlight`] - Scope-aware read/write highlighting of the symbol under the cursor
+//! - [`linked_editing`] - Linked renaming of HTML, Svelte and JSX tag pairs
+//! - [`call_hierarchy`] - Incoming/outgoing call graphs across the workspace index
+//! - [`type_hierarchy`] - Supertypes and subtypes from extends/implements and impl clauses
 //! - [`workspace`] - Workspace management and file operations
 //!
 //! ### Advanced Features
@@ -249,15 +256,20 @@
 //! version = "0.1.0"
 //! log_level = "info"
 //!
-//! [ai.claude]
+//! [ai]
+//! default = "claude"
+//!
+//! [ai.providers.claude]
+//! kind = "anthropic"
 //! model = "claude-sonnet-4-20250514"
 //! max_tokens = 4096
-//! temperature = 0.7
-//! timeout_ms = 30000
 //!
-//! [ai.copilot]
-//! enable = true
-//! debounce_ms = 75
+//! [ai.providers.local]
+//! kind = "ollama"
+//! model = "qwen2.5-coder"
+//!
+//! [ai.features]
+//! completion = ["local", "claude"]
 //!
 //! [mcp]
 //! enable = true
@@ -321,22 +333,29 @@
 
 pub mod acp;
 pub mod ai;
+pub mod call_hierarchy;
 pub mod code_actions;
 pub mod code_lens;
+pub mod completion;
 pub mod config;
 pub mod coordinator;
 pub mod diagnostics;
+pub mod document_highlight;
+pub mod folding_range;
 pub mod formatting;
 pub mod inline_completion;
 pub mod inlay_hints;
 pub mod language;
+pub mod linked_editing;
 pub mod mcp;
 pub mod pipeline;
 pub mod proxy;
+pub mod selection_range;
 pub mod semantic_tokens;
 pub mod signature_help;
 pub mod text_sync;
 pub mod tree_sitter;
+pub mod type_hierarchy;
 pub mod workspace;
 pub mod workspace_index;
 
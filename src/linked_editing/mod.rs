@@ -0,0 +1,225 @@
+//! Linked Editing Range Module
+//!
+//! Renames the matching open/close tag while the user edits one of them. Supports
+//! HTML and Svelte elements and JSX elements (including `<>` fragments) in
+//! JavaScript and TSX.
+
+use anyhow::Result;
+use tower_lsp::lsp_types::*;
+use crate::tree_sitter::{byte_to_position, position_to_byte, TreeSitterParser};
+
+/// Characters allowed in a tag name while linked editing is active
+const TAG_WORD_PATTERN: &str = r"[A-Za-z_$][\w$:.\-]*";
+
+/// Linked editing range provider
+#[derive(Debug)]
+pub struct LinkedEditingProvider {}
+
+impl LinkedEditingProvider {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Get the linked open/close tag name ranges for the tag at position
+    pub fn get_linked_ranges(
+        &self,
+        content: &str,
+        position: Position,
+        lang: &str,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        let mut parser = TreeSitterParser::new()?;
+        if parser.set_language(lang).is_err() {
+            return Ok(None);
+        }
+
+        let tree = parser.parse(content, "temp")?;
+        let root = tree.root_node();
+        let offset = position_to_byte(content, position);
+
+        let Some(leaf) = root.descendant_for_byte_range(offset, offset) else {
+            return Ok(None);
+        };
+
+        let pair = match lang {
+            "html" | "svelte" => self.markup_tag_pair(leaf, offset),
+            "javascript" | "tsx" => self.jsx_tag_pair(leaf, offset),
+            _ => None,
+        };
+
+        Ok(pair.map(|(open, close)| LinkedEditingRanges {
+            ranges: vec![
+                self.byte_range(content, open),
+                self.byte_range(content, close),
+            ],
+            word_pattern: Some(TAG_WORD_PATTERN.to_string()),
+        }))
+    }
+
+    /// Tag name ranges of an HTML/Svelte element whose start or end tag contains `offset`
+    fn markup_tag_pair(&self, leaf: tree_sitter::Node, offset: usize) -> Option<((usize, usize), (usize, usize))> {
+        let tag = self.ancestor_of_kind(leaf, &["start_tag", "end_tag"])?;
+        let element = tag.parent().filter(|p| p.kind() == "element")?;
+
+        let name_of = |kind: &str| {
+            let mut cursor = element.walk();
+            let tag = element.children(&mut cursor).find(|c| c.kind() == kind)?;
+            let mut cursor = tag.walk();
+            let name = tag.children(&mut cursor).find(|c| c.kind() == "tag_name")?;
+            Some((name.start_byte(), name.end_byte()))
+        };
+
+        let open = name_of("start_tag")?;
+        let close = name_of("end_tag")?;
+        self.cursor_on_name(tag.kind() == "start_tag", open, close, offset)
+    }
+
+    /// Tag name ranges of a JSX element whose opening or closing tag contains `offset`
+    fn jsx_tag_pair(&self, leaf: tree_sitter::Node, offset: usize) -> Option<((usize, usize), (usize, usize))> {
+        let tag = self.ancestor_of_kind(leaf, &["jsx_opening_element", "jsx_closing_element"])?;
+        let element = tag.parent().filter(|p| p.kind() == "jsx_element")?;
+
+        let open = self.jsx_name_range(&element.child_by_field_name("open_tag")?)?;
+        let close = self.jsx_name_range(&element.child_by_field_name("close_tag")?)?;
+        self.cursor_on_name(tag.kind() == "jsx_opening_element", open, close, offset)
+    }
+
+    /// Byte range of a JSX tag's name; empty right after `<`/`</` for fragments
+    fn jsx_name_range(&self, tag: &tree_sitter::Node) -> Option<(usize, usize)> {
+        if let Some(name) = tag.child_by_field_name("name") {
+            return Some((name.start_byte(), name.end_byte()));
+        }
+
+        let opener = tag.child(0).filter(|c| matches!(c.kind(), "<" | "</"))?;
+        Some((opener.end_byte(), opener.end_byte()))
+    }
+
+    /// Keep the pair only if the cursor sits on (or at the edge of) the name being edited
+    fn cursor_on_name(
+        &self,
+        on_open: bool,
+        open: (usize, usize),
+        close: (usize, usize),
+        offset: usize,
+    ) -> Option<((usize, usize), (usize, usize))> {
+        let (start, end) = if on_open { open } else { close };
+        if offset >= start && offset <= end {
+            Some((open, close))
+        } else {
+            None
+        }
+    }
+
+    fn ancestor_of_kind<'a>(&self, node: tree_sitter::Node<'a>, kinds: &[&str]) -> Option<tree_sitter::Node<'a>> {
+        let mut current = Some(node);
+        while let Some(candidate) = current {
+            if kinds.contains(&candidate.kind()) {
+                return Some(candidate);
+            }
+            current = candidate.parent();
+        }
+        None
+    }
+
+    fn byte_range(&self, content: &str, (start, end): (usize, usize)) -> Range {
+        Range {
+            start: byte_to_position(content, start),
+            end: byte_to_position(content, end),
+        }
+    }
+
+}
+
+impl Default for LinkedEditingProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(line: u32, start: u32, end: u32) -> Range {
+        Range {
+            start: Position { line, character: start },
+            end: Position { line, character: end },
+        }
+    }
+
+    #[test]
+    fn test_jsx_tag_pair() {
+        let provider = LinkedEditingProvider::new();
+        let content = "const a = <div className=\"x\">\n  <span>hi</span>\n</div>;\n";
+
+        // Cursor in the opening `div`
+        let linked = provider
+            .get_linked_ranges(content, Position { line: 0, character: 12 }, "javascript")
+            .unwrap()
+            .unwrap();
+        assert_eq!(linked.ranges, vec![range(0, 11, 14), range(2, 2, 5)]);
+
+        // Cursor at the end of the closing `span`
+        let linked = provider
+            .get_linked_ranges(content, Position { line: 1, character: 16 }, "javascript")
+            .unwrap()
+            .unwrap();
+        assert_eq!(linked.ranges, vec![range(1, 3, 7), range(1, 12, 16)]);
+    }
+
+    #[test]
+    fn test_jsx_member_tag_and_fragment() {
+        let provider = LinkedEditingProvider::new();
+        let content = "const a = <Foo.Bar>x</Foo.Bar>;\nconst b = <>y</>;\n";
+
+        let linked = provider
+            .get_linked_ranges(content, Position { line: 0, character: 15 }, "tsx")
+            .unwrap()
+            .unwrap();
+        assert_eq!(linked.ranges, vec![range(0, 11, 18), range(0, 22, 29)]);
+
+        let linked = provider
+            .get_linked_ranges(content, Position { line: 1, character: 11 }, "javascript")
+            .unwrap()
+            .unwrap();
+        assert_eq!(linked.ranges, vec![range(1, 11, 11), range(1, 15, 15)]);
+    }
+
+    #[test]
+    fn test_cursor_outside_tag_name() {
+        let provider = LinkedEditingProvider::new();
+        let content = "const a = <div className=\"x\">text</div>;\n";
+
+        // Inside the attribute value
+        let linked = provider
+            .get_linked_ranges(content, Position { line: 0, character: 26 }, "javascript")
+            .unwrap();
+        assert!(linked.is_none());
+
+        // Plain JavaScript identifier
+        let linked = provider
+            .get_linked_ranges(content, Position { line: 0, character: 6 }, "javascript")
+            .unwrap();
+        assert!(linked.is_none());
+    }
+
+    #[test]
+    fn test_html_tag_pair() {
+        let provider = LinkedEditingProvider::new();
+        let content = "<ul>\n  <li>one</li>\n</ul>\n";
+
+        let linked = provider
+            .get_linked_ranges(content, Position { line: 2, character: 3 }, "html")
+            .unwrap()
+            .unwrap();
+        assert_eq!(linked.ranges, vec![range(0, 1, 3), range(2, 2, 4)]);
+    }
+
+    #[test]
+    fn test_unsupported_language() {
+        let provider = LinkedEditingProvider::new();
+        let linked = provider
+            .get_linked_ranges("fn main() {}", Position { line: 0, character: 3 }, "rust")
+            .unwrap();
+        assert!(linked.is_none());
+    }
+}
@@ -8,19 +8,26 @@
 
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
+use tower::ServiceExt;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 use std::sync::Arc;
 
-mod ai;
+// Shared with the library so that its completion provider and ACP agent take the
+// same `AiProvider` trait objects
+use universal_lsp::ai;
 mod code_actions;
 mod code_lens;
 mod config;
 mod diagnostics;
+mod document_highlight;
+mod folding_range;
 mod formatting;
 mod language;
+mod linked_editing;
 mod mcp;
 mod pipeline;
 mod proxy;
+mod selection_range;
 mod semantic_tokens;
 mod inlay_hints;
 mod signature_help;
@@ -29,23 +36,35 @@ mod tree_sitter;
 mod workspace;
 mod coordinator;
 
-use ai::{ClaudeClient, ClaudeConfig, CopilotClient, CopilotConfig, CompletionContext};
-use code_actions::CodeActionProvider;
+use ai::{AiFeature, AiProviders, CompletionContext};
+use code_actions::{CodeActionProvider, DeferredEdit, ResolveData};
 use code_lens::CodeLensProvider;
 use config::{Config, CommandMode};
 use coordinator::CoordinatorClient;
-use diagnostics::DiagnosticProvider;
-use formatting::FormattingProvider;
+use diagnostics::{DiagnosticProvider, DiagnosticSources};
+use diagnostics::ai_lint::{AiLinter, AI_LINT_SOURCE};
+use diagnostics::linter::LinterRunner;
+use diagnostics::scheduler::{DiagnosticsScheduler, EDIT_DEBOUNCE};
+use document_highlight::DocumentHighlightProvider;
+use folding_range::FoldingRangeProvider;
+use formatting::{FormatSettings, FormattingProvider};
 use inlay_hints::InlayHintsProvider;
-use language::detect_language;
+use language::{detect_language, grammar_name, LANGUAGES};
+use linked_editing::LinkedEditingProvider;
 use mcp::McpRequest;
-use pipeline::{McpPipeline, merge_mcp_responses, lsp_position_to_mcp};
+use pipeline::{McpPipeline, merge_mcp_responses, lsp_position_to_mcp, mcp_response_to_diagnostics};
 use proxy::{ProxyConfig, ProxyManager};
+use selection_range::SelectionRangeProvider;
 use semantic_tokens::SemanticTokensProvider;
 use signature_help::SignatureHelpProvider;
 use text_sync::TextSyncManager;
-use tree_sitter::TreeSitterParser;
-use workspace::WorkspaceManager;
+use tree_sitter::{position_to_byte, TreeSitterParser};
+use workspace::{FormatterConfig, WorkspaceManager};
+use universal_lsp::completion::ranking::ACCEPTED_COMMAND;
+use universal_lsp::inline_completion::{
+    self,
+    protocol::{InlineCompletionList, InlineCompletionParams, InlineCompletionTriggerKind},
+};
 
 struct UniversalLsp {
     client: Client,
@@ -53,25 +72,46 @@ struct UniversalLsp {
     coordinator_client: Option<Arc<CoordinatorClient>>,
     pipeline: Option<Arc<McpPipeline>>,
     proxy_manager: Option<Arc<ProxyManager>>,
-    claude_client: Option<Arc<ClaudeClient>>,
-    copilot_client: Option<Arc<CopilotClient>>,
+    /// AI providers of each feature
+    ai: AiProviders,
+    ai_linter: Option<Arc<AiLinter>>,
     parser: Arc<dashmap::DashMap<String, TreeSitterParser>>,
     documents: Arc<dashmap::DashMap<String, String>>,
     diagnostic_provider: Arc<DiagnosticProvider>,
+    linter_runner: Arc<LinterRunner>,
+    diagnostics_scheduler: Arc<DiagnosticsScheduler>,
     code_action_provider: Arc<CodeActionProvider>,
     formatting_provider: Arc<FormattingProvider>,
     semantic_tokens_provider: Arc<SemanticTokensProvider>,
     signature_help_provider: Arc<SignatureHelpProvider>,
     inlay_hints_provider: Arc<InlayHintsProvider>,
     code_lens_provider: Arc<CodeLensProvider>,
+    folding_range_provider: Arc<FoldingRangeProvider>,
+    selection_range_provider: Arc<SelectionRangeProvider>,
+    document_highlight_provider: Arc<DocumentHighlightProvider>,
+    linked_editing_provider: Arc<LinkedEditingProvider>,
     workspace_manager: Arc<WorkspaceManager>,
     text_sync_manager: Arc<TextSyncManager>,
     inline_completion_manager: Arc<universal_lsp::inline_completion::InlineCompletionManager>,
     workspace_index: Arc<universal_lsp::workspace_index::WorkspaceIndex>,
+    completion_provider: Arc<universal_lsp::completion::CompletionProvider>,
+    call_hierarchy_provider: Arc<universal_lsp::call_hierarchy::CallHierarchyProvider>,
+    type_hierarchy_provider: Arc<universal_lsp::type_hierarchy::TypeHierarchyProvider>,
+    /// Client accepts dynamic registration of `textDocument/prepareTypeHierarchy`
+    /// (lsp-types has no static `typeHierarchyProvider` capability)
+    type_hierarchy_registration: std::sync::atomic::AtomicBool,
+    /// Client accepts dynamic registration of `textDocument/completion`, which lets
+    /// trigger characters differ per language
+    completion_registration: std::sync::atomic::AtomicBool,
+    /// Client accepts dynamic registration of `textDocument/inlineCompletion`; set by
+    /// `run_lsp_server` from the raw `initialize` request, which lsp-types can't hold
+    inline_completion_registration: Arc<std::sync::atomic::AtomicBool>,
+    /// Client pulls diagnostics (`textDocument/diagnostic`) instead of relying on pushes
+    pull_diagnostics: std::sync::atomic::AtomicBool,
 }
 
 impl UniversalLsp {
-    fn new(client: Client, config: Config) -> Self {
+    fn new(client: Client, config: Config, inline_completion_registration: Arc<std::sync::atomic::AtomicBool>) -> Self {
         // Create MCP pipeline if configured
         let pipeline = if config.has_mcp_pipeline() {
             Some(Arc::new(McpPipeline::new(&config)))
@@ -96,39 +136,9 @@ impl UniversalLsp {
             None
         };
 
-        // Create Claude client if API key is available
-        let claude_client = if let Ok(api_key) = std::env::var("ANTHROPIC_API_KEY") {
-            let claude_config = ClaudeConfig {
-                api_key,
-                ..Default::default()
-            };
-            match ClaudeClient::new(claude_config) {
-                Ok(client) => Some(Arc::new(client)),
-                Err(e) => {
-                    tracing::warn!("Failed to initialize Claude client: {}", e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
-
-        // Create Copilot client if API key is available
-        let copilot_client = if let Ok(api_key) = std::env::var("GITHUB_TOKEN") {
-            let copilot_config = CopilotConfig {
-                api_key,
-                ..Default::default()
-            };
-            match CopilotClient::new(copilot_config) {
-                Ok(client) => Some(Arc::new(client)),
-                Err(e) => {
-                    tracing::warn!("Failed to initialize Copilot client: {}", e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
+        // AI providers chosen per feature in the configuration, or from API keys
+        let ai = config.ai.build();
+        let trusted_folders = config.trust.workspaces.clone();
 
         // Try to connect to MCP Coordinator daemon (optional, graceful fallback)
         let coordinator_client = tokio::task::block_in_place(|| {
@@ -152,48 +162,604 @@ impl UniversalLsp {
             coordinator_client,
             pipeline,
             proxy_manager,
-            claude_client: claude_client.clone(),
-            copilot_client,
+            ai_linter: ai.get(AiFeature::Lint).map(|provider| Arc::new(AiLinter::new(provider))),
             parser: Arc::new(dashmap::DashMap::new()),
             documents: Arc::new(dashmap::DashMap::new()),
             diagnostic_provider: Arc::new(DiagnosticProvider::new()),
-            code_action_provider: Arc::new(CodeActionProvider::with_claude(claude_client)),
+            linter_runner: Arc::new(LinterRunner::new()),
+            diagnostics_scheduler: Arc::new(DiagnosticsScheduler::new()),
+            code_action_provider: Arc::new(CodeActionProvider::with_ai(ai.get(AiFeature::CodeActions))),
             formatting_provider: Arc::new(FormattingProvider::new()),
             semantic_tokens_provider: Arc::new(SemanticTokensProvider::new()),
             signature_help_provider: Arc::new(SignatureHelpProvider::new()),
             inlay_hints_provider: Arc::new(InlayHintsProvider::new()),
             code_lens_provider: Arc::new(CodeLensProvider::new()),
-            workspace_manager: Arc::new(WorkspaceManager::new()),
+            folding_range_provider: Arc::new(FoldingRangeProvider::new()),
+            selection_range_provider: Arc::new(SelectionRangeProvider::new()),
+            document_highlight_provider: Arc::new(DocumentHighlightProvider::new()),
+            linked_editing_provider: Arc::new(LinkedEditingProvider::new()),
+            workspace_manager: Arc::new(WorkspaceManager::new().with_trusted_folders(trusted_folders)),
             text_sync_manager: Arc::new(TextSyncManager::new()),
             inline_completion_manager: Arc::new(universal_lsp::inline_completion::InlineCompletionManager::new()),
             workspace_index: Arc::new(universal_lsp::workspace_index::WorkspaceIndex::new()),
+            completion_provider: Arc::new(universal_lsp::completion::CompletionProvider::with_ai(ai.get(AiFeature::Summaries))),
+            call_hierarchy_provider: Arc::new(universal_lsp::call_hierarchy::CallHierarchyProvider::new()),
+            type_hierarchy_provider: Arc::new(universal_lsp::type_hierarchy::TypeHierarchyProvider::new()),
+            type_hierarchy_registration: std::sync::atomic::AtomicBool::new(false),
+            completion_registration: std::sync::atomic::AtomicBool::new(false),
+            inline_completion_registration,
+            pull_diagnostics: std::sync::atomic::AtomicBool::new(false),
+            ai,
+        }
+    }
+
+    /// Publish syntax errors for a new document version right away and schedule the
+    /// slower semantic, linter, AI and MCP diagnostics after `delay`
+    ///
+    /// Until the background run completes, the previous background results are shown
+    /// alongside the new syntax errors. A newer version cancels the pending run.
+    async fn refresh_diagnostics(&self, uri: Url, content: String, delay: std::time::Duration) {
+        let lang = grammar_name(uri.path());
+        let Ok(mut parser) = TreeSitterParser::new() else {
+            return;
+        };
+        if parser.set_language(&lang).is_err() {
+            return;
+        }
+        let Ok(tree) = parser.parse(&content, uri.as_str()) else {
+            return;
+        };
+
+        let syntax = diagnostics::syntax_diagnostics(&tree, &content);
+        let mut diags = syntax.clone();
+        diags.extend(self.diagnostics_scheduler.background(uri.as_str()));
+        self.diagnostic_provider.store(uri.as_str(), &content, diags.clone());
+
+        let pull = self.pull_diagnostics.load(std::sync::atomic::Ordering::Relaxed);
+        if !pull {
+            self.client.publish_diagnostics(uri.clone(), diags, None).await;
+        }
+
+        let scheduler = self.diagnostics_scheduler.clone();
+        let diagnostic_provider = self.diagnostic_provider.clone();
+        let documents = self.documents.clone();
+        let ai_linter = self.ai_linter.clone();
+        let linter_runner = self.linter_runner.clone();
+        let workspace_manager = self.workspace_manager.clone();
+        let coordinator = self.coordinator_client.clone();
+        let servers: Vec<String> = self.config.mcp.servers.keys().cloned().collect();
+        let client = self.client.clone();
+        let uri_str = uri.to_string();
+
+        self.diagnostics_scheduler.schedule(&uri_str, delay, async move {
+            let sources = DiagnosticSources {
+                uri: Some(uri.as_str()),
+                ai_linter: ai_linter.as_deref(),
+                linters: Some(&linter_runner),
+                workspaces: Some(&workspace_manager),
+            };
+            let mut background = match diagnostics::background_diagnostics(&tree, &content, &lang, sources).await {
+                Ok(background) => background,
+                Err(e) => {
+                    tracing::warn!("Failed to compute diagnostics for {}: {}", uri, e);
+                    Vec::new()
+                }
+            };
+            if let Some(coordinator) = &coordinator {
+                background.extend(Self::mcp_diagnostics(coordinator, &servers, &uri, &content).await);
+            }
+            // A newer version has its own run scheduled
+            let remembered = {
+                let current = documents.get(uri.as_str());
+                let current = current.as_ref().map(|current| current.as_str());
+                scheduler.remember_background(uri.as_str(), &content, current, background.clone())
+            };
+            if !remembered {
+                return;
+            }
+
+            let mut diags = syntax;
+            diags.extend(background);
+            if !diagnostic_provider.update(uri.as_str(), &content, diags.clone()) {
+                return;
+            }
+            if pull {
+                if let Err(e) = client.workspace_diagnostic_refresh().await {
+                    tracing::debug!("Diagnostic refresh failed: {}", e);
+                }
+            } else {
+                client.publish_diagnostics(uri, diags, None).await;
+            }
+        });
+    }
+
+    /// Diagnostics suggested by the configured MCP servers, queried concurrently
+    async fn mcp_diagnostics(
+        coordinator: &CoordinatorClient,
+        servers: &[String],
+        uri: &Url,
+        content: &str,
+    ) -> Vec<Diagnostic> {
+        let mcp_request = McpRequest {
+            request_type: "diagnostics".to_string(),
+            uri: uri.to_string(),
+            position: lsp_position_to_mcp(0, 0),
+            context: Some(content.to_string()),
+        };
+
+        let queries = servers.iter().map(|server_name| {
+            let mcp_request = mcp_request.clone();
+            async move { (server_name, coordinator.query(server_name, mcp_request).await) }
+        });
+
+        let mut diags = Vec::new();
+        for (server_name, result) in futures::future::join_all(queries).await {
+            match result {
+                Ok(response) => diags.extend(mcp_response_to_diagnostics(&response, uri, server_name)),
+                Err(e) => {
+                    tracing::debug!("MCP diagnostics query to {} failed: {}", server_name, e);
+                }
+            }
+        }
+        diags
+    }
+
+    /// Run the AI lint pass for a saved document once saves have been quiet for the
+    /// workspace's debounce interval, then merge its findings into the diagnostics
+    ///
+    /// Only workspaces with `ai_lint.enabled` in their configuration are linted.
+    fn schedule_ai_lint(&self, uri: Url) {
+        let Some(linter) = self.ai_linter.clone() else {
+            return;
+        };
+        let lang = grammar_name(uri.path());
+        let Some(config) = self
+            .workspace_manager
+            .get_config_for_document(&uri, &lang)
+            .ai_lint
+            .filter(|config| config.enabled)
+        else {
+            return;
+        };
+        let workspace = self
+            .workspace_manager
+            .get_workspace_for_document(&uri)
+            .map(|folder| folder.uri.to_string())
+            .unwrap_or_default();
+        if linter.remaining_budget(&workspace, &config) == 0 {
+            return;
+        }
+
+        let generation = linter.schedule(uri.as_str());
+        let documents = self.documents.clone();
+        let diagnostic_provider = self.diagnostic_provider.clone();
+        let client = self.client.clone();
+        let pull = self.pull_diagnostics.load(std::sync::atomic::Ordering::Relaxed);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(config.debounce_ms)).await;
+            if !linter.is_latest(uri.as_str(), generation) {
+                return;
+            }
+
+            let Some(content) = documents.get(uri.as_str()).map(|c| c.clone()) else {
+                return;
+            };
+            let Ok(mut parser) = TreeSitterParser::new() else {
+                return;
+            };
+            if parser.set_language(&lang).is_err() {
+                return;
+            }
+            let Ok(tree) = parser.parse(&content, uri.as_str()) else {
+                return;
+            };
+            drop(parser);
+
+            let findings = linter.lint(&tree, &content, &lang, &workspace, &config).await;
+
+            let is_ai_finding = |d: &Diagnostic| d.source.as_deref() == Some(AI_LINT_SOURCE);
+            Self::replace_diagnostics(&client, &diagnostic_provider, pull, uri, &content, is_ai_finding, findings).await;
+        });
+    }
+
+    /// `textDocument/inlineCompletion`: AI suggestions as ghost text
+    ///
+    /// Served from the last shown suggestion when the user typed into it, then from
+    /// the cache, then from the completion providers. Automatic requests are
+    /// debounced; a newer request for the document, or the client cancelling this
+    /// one, abandons the model request.
+    async fn inline_completion(&self, params: InlineCompletionParams) -> Result<Option<InlineCompletionList>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let selected = params.context.selected_completion_info.as_ref();
+        let manager = &self.inline_completion_manager;
+
+        let Some(content) = self.documents.get(uri.as_str()).map(|c| c.clone()) else {
+            return Ok(None);
+        };
+        let byte_offset = position_to_byte(&content, position);
+        let mut prefix = content[..byte_offset].to_string();
+        let suffix = (byte_offset < content.len()).then(|| content[byte_offset..].to_string());
+        // Ghost text extends the item selected in the completion widget
+        if let Some(selected) = selected {
+            prefix.truncate(position_to_byte(&content, selected.range.start));
+            prefix.push_str(&selected.text);
+        }
+
+        let continued = match selected {
+            None => manager.continuation(uri.as_str(), &prefix, &suffix),
+            Some(_) => None,
+        };
+        let texts = match continued.or_else(|| manager.get_cached(uri.as_str(), &prefix, &suffix)) {
+            Some(texts) => texts,
+            None => {
+                let mut request = manager.begin_request(uri.as_str());
+                if params.context.trigger_kind == InlineCompletionTriggerKind::AUTOMATIC
+                    && manager.should_debounce(uri.as_str()).await
+                {
+                    tokio::select! {
+                        _ = manager.wait_debounce() => {}
+                        _ = request.cancelled() => return Ok(None),
+                    }
+                }
+                manager.update_last_request(uri.to_string()).await;
+
+                let completion_context = CompletionContext {
+                    language: detect_language(uri.path()).to_string(),
+                    file_path: uri.path().to_string(),
+                    prefix: prefix.clone(),
+                    suffix: suffix.clone(),
+                    context: None,
+                };
+                let texts = tokio::select! {
+                    texts = self.ai_inline_completions(&completion_context) => texts,
+                    _ = request.cancelled() => return Ok(None),
+                };
+                if !texts.is_empty() {
+                    manager.cache_completion(uri.to_string(), &prefix, &suffix, texts.clone());
+                }
+                texts
+            }
+        };
+
+        let texts: Vec<&String> = texts.iter().filter(|text| !text.trim().is_empty()).collect();
+        if selected.is_none() {
+            let remainders = texts.iter().map(|text| inline_completion::remainder(&content, position, text)).collect();
+            manager.remember_shown(uri.as_str(), &prefix, &suffix, remainders);
+        }
+        let items = texts
+            .into_iter()
+            .map(|text| inline_completion::inline_item(&content, position, text, selected))
+            .collect();
+        Ok(Some(InlineCompletionList { items }))
+    }
+
+    /// Suggestions of the completion providers, in order, without duplicates
+    async fn ai_inline_completions(&self, context: &CompletionContext) -> Vec<String> {
+        let mut texts: Vec<String> = Vec::new();
+        for provider in self.ai.for_feature(AiFeature::Completion) {
+            match provider.get_completions(context).await {
+                Ok(suggestions) => {
+                    for suggestion in suggestions {
+                        if !texts.contains(&suggestion.text) {
+                            texts.push(suggestion.text);
+                        }
+                    }
+                }
+                Err(e) => tracing::debug!("{} completion failed: {}", provider.name(), e),
+            }
+        }
+        texts
+    }
+
+    /// Formatter configured for a document's language in its workspace
+    fn formatter_config(&self, uri: &Url, lang: &str) -> Option<FormatterConfig> {
+        self.workspace_manager
+            .get_config_for_document(uri, lang)
+            .language_overrides
+            .get(lang)
+            .and_then(|config| config.formatter.clone())
+    }
+
+    /// Formatting settings of a document: the request's options, `.editorconfig`,
+    /// then its workspace's configuration
+    fn format_settings(&self, uri: &Url, lang: &str, options: Option<&FormattingOptions>) -> FormatSettings {
+        let workspace = self.workspace_manager.get_config_for_document(uri, lang);
+        self.formatting_provider.settings(uri, lang, &workspace, options)
+    }
+
+    /// Trigger characters of every language, for clients registering completion once
+    fn all_trigger_characters() -> Vec<String> {
+        let characters: std::collections::BTreeSet<&str> = LANGUAGES
+            .iter()
+            .flat_map(|lang| lang.trigger_characters().iter().copied())
+            .collect();
+        characters.into_iter().map(str::to_string).collect()
+    }
+
+    /// One completion registration per set of trigger characters, selecting the
+    /// files of the languages sharing it by extension
+    fn completion_registrations() -> Vec<Registration> {
+        let mut groups: std::collections::BTreeMap<&[&str], Vec<&str>> = std::collections::BTreeMap::new();
+        for lang in LANGUAGES.iter() {
+            groups.entry(lang.trigger_characters()).or_default().extend(lang.extensions.iter().copied());
+        }
+
+        groups
+            .into_iter()
+            .enumerate()
+            .map(|(i, (characters, extensions))| {
+                let options = CompletionRegistrationOptions {
+                    text_document_registration_options: TextDocumentRegistrationOptions {
+                        document_selector: Some(vec![DocumentFilter {
+                            language: None,
+                            scheme: Some("file".to_string()),
+                            pattern: Some(format!("**/*.{{{}}}", extensions.join(","))),
+                        }]),
+                    },
+                    completion_options: CompletionOptions {
+                        resolve_provider: Some(true),
+                        trigger_characters: Some(characters.iter().map(|c| c.to_string()).collect()),
+                        ..Default::default()
+                    },
+                };
+                Registration {
+                    id: format!("universal-lsp-completion-{}", i),
+                    method: "textDocument/completion".to_string(),
+                    register_options: serde_json::to_value(options).ok(),
+                }
+            })
+            .collect()
+    }
+
+    /// Load the built-in, user and workspace snippets, reporting the ones skipped
+    async fn load_snippets(&self) {
+        let root = self.workspace_index.workspace_root();
+        for warning in self.completion_provider.load_snippets(root.as_deref()) {
+            self.client.log_message(MessageType::WARNING, warning).await;
+        }
+    }
+
+    /// Run the external linter configured for a saved document's language and merge
+    /// its results into the diagnostics
+    fn run_linter(&self, uri: Url) {
+        let lang = grammar_name(uri.path());
+        let Some(linter) = self
+            .workspace_manager
+            .get_config_for_document(&uri, &lang)
+            .language_overrides
+            .get(&lang)
+            .and_then(|config| config.linter.clone())
+        else {
+            return;
+        };
+
+        let linter_runner = self.linter_runner.clone();
+        let documents = self.documents.clone();
+        let diagnostic_provider = self.diagnostic_provider.clone();
+        let client = self.client.clone();
+        let pull = self.pull_diagnostics.load(std::sync::atomic::Ordering::Relaxed);
+
+        tokio::spawn(async move {
+            let results = match linter_runner.run(&uri, &linter).await {
+                Ok(results) => results,
+                Err(e) => {
+                    tracing::warn!("Linter {} failed for {}: {}", linter, uri, e);
+                    return;
+                }
+            };
+
+            let Some(content) = documents.get(uri.as_str()).map(|c| c.clone()) else {
+                return;
+            };
+            let is_linter_result = |d: &Diagnostic| d.data.as_ref().and_then(|data| data.get("linter")).is_some();
+            Self::replace_diagnostics(&client, &diagnostic_provider, pull, uri, &content, is_linter_result, results).await;
+        });
+    }
+
+    /// Swap the diagnostics matching `replaced` in a document's stored report for
+    /// `diagnostics`, then publish the report (or ask pull clients to re-pull) if it
+    /// changed
+    ///
+    /// Nothing happens when the document changed since the report was stored; its next
+    /// analysis picks the new results up from their caches.
+    async fn replace_diagnostics(
+        client: &Client,
+        diagnostic_provider: &DiagnosticProvider,
+        pull: bool,
+        uri: Url,
+        content: &str,
+        replaced: impl Fn(&Diagnostic) -> bool,
+        diagnostics: Vec<Diagnostic>,
+    ) {
+        let Some(previous) = diagnostic_provider.cached(uri.as_str(), content) else {
+            return;
+        };
+        let mut diags: Vec<Diagnostic> = previous.into_iter().filter(|d| !replaced(d)).collect();
+        diags.extend(diagnostics);
+        if !diagnostic_provider.update(uri.as_str(), content, diags.clone()) {
+            return;
+        }
+
+        if pull {
+            if let Err(e) = client.workspace_diagnostic_refresh().await {
+                tracing::debug!("Diagnostic refresh failed: {}", e);
+            }
+        } else {
+            client.publish_diagnostics(uri, diags, None).await;
+        }
+    }
+
+    /// Explain the code in `range` in a message, leaving the document untouched
+    async fn show_explanation(&self, uri: &str, range: Range, content: &str) -> Option<serde_json::Value> {
+        let lang = grammar_name(uri);
+        match self.code_action_provider.explain(range, &lang, content).await {
+            Ok(explanation) => {
+                self.client.show_message(MessageType::INFO, &explanation).await;
+                Some(serde_json::json!({ "explanation": explanation }))
+            }
+            Err(e) => {
+                self.client
+                    .show_message(MessageType::ERROR, format!("universal-lsp.explainCode: {}", e))
+                    .await;
+                None
+            }
+        }
+    }
+
+    /// Extra diagnostic sources for a document
+    fn diagnostic_sources<'a>(&'a self, uri: Option<&'a str>) -> DiagnosticSources<'a> {
+        DiagnosticSources {
+            uri,
+            ai_linter: self.ai_linter.as_deref(),
+            linters: Some(&self.linter_runner),
+            workspaces: Some(&self.workspace_manager),
+        }
+    }
+
+    /// Root of the workspace folder a document is in; the index root when the
+    /// client sent no folders
+    fn workspace_root(&self, uri: &Url) -> Option<std::path::PathBuf> {
+        if self.workspace_manager.count() == 0 {
+            return self.workspace_index.workspace_root();
+        }
+        self.workspace_manager.get_workspace_for_document(uri).and_then(|folder| folder.uri.to_file_path().ok())
+    }
+
+    /// Bring the reports of indexed files that aren't open up to date in the
+    /// background (see `DiagnosticProvider::update_files`)
+    fn spawn_workspace_reports_update(&self) {
+        let client = self.client.clone();
+        let diagnostic_provider = self.diagnostic_provider.clone();
+        let workspace_index = self.workspace_index.clone();
+        let documents = self.documents.clone();
+        let ai_linter = self.ai_linter.clone();
+        let linter_runner = self.linter_runner.clone();
+        let workspace_manager = self.workspace_manager.clone();
+        let pull = self.pull_diagnostics.load(std::sync::atomic::Ordering::Relaxed);
+
+        tokio::spawn(async move {
+            let sources = DiagnosticSources {
+                uri: None,
+                ai_linter: ai_linter.as_deref(),
+                linters: Some(&linter_runner),
+                workspaces: Some(&workspace_manager),
+            };
+            Self::update_workspace_reports(&client, &diagnostic_provider, &workspace_index, &documents, sources, pull)
+                .await;
+        });
+    }
+
+    /// Bring the reports of indexed files that aren't open up to date, asking pull
+    /// clients to re-pull when one changed
+    async fn update_workspace_reports(
+        client: &Client,
+        diagnostic_provider: &DiagnosticProvider,
+        workspace_index: &universal_lsp::workspace_index::WorkspaceIndex,
+        documents: &dashmap::DashMap<String, String>,
+        sources: DiagnosticSources<'_>,
+        pull: bool,
+    ) {
+        let files = Self::unopened_indexed_files(workspace_index, documents);
+        let changed = diagnostic_provider.update_files(&files, |uri| documents.contains_key(uri), sources).await;
+        if changed && pull {
+            if let Err(e) = client.workspace_diagnostic_refresh().await {
+                tracing::debug!("Diagnostic refresh failed: {}", e);
+            }
         }
     }
+
+    /// Indexed files that aren't open in the editor
+    fn unopened_indexed_files(
+        index: &universal_lsp::workspace_index::WorkspaceIndex,
+        documents: &dashmap::DashMap<String, String>,
+    ) -> Vec<Url> {
+        index
+            .indexed_files()
+            .into_iter()
+            .filter(|uri| !documents.contains_key(uri))
+            .filter_map(|uri| Url::parse(&uri).ok())
+            .collect()
+    }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for UniversalLsp {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let type_hierarchy_dynamic = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.type_hierarchy.as_ref())
+            .and_then(|t| t.dynamic_registration)
+            .unwrap_or(false);
+        self.type_hierarchy_registration
+            .store(type_hierarchy_dynamic, std::sync::atomic::Ordering::Relaxed);
+
+        let completion_dynamic = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.completion.as_ref())
+            .and_then(|c| c.dynamic_registration)
+            .unwrap_or(false);
+        self.completion_registration
+            .store(completion_dynamic, std::sync::atomic::Ordering::Relaxed);
+
+        let pull_diagnostics = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.diagnostic.as_ref())
+            .is_some();
+        self.pull_diagnostics
+            .store(pull_diagnostics, std::sync::atomic::Ordering::Relaxed);
+
+        // Defer expensive code action edits to codeAction/resolve when the client can resolve them
+        let code_action_resolve = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.code_action.as_ref())
+            .is_some_and(|c| {
+                c.data_support == Some(true)
+                    && c.resolve_support
+                        .as_ref()
+                        .is_some_and(|r| r.properties.iter().any(|p| p == "edit"))
+            });
+        self.code_action_provider.set_resolve_support(code_action_resolve);
+
+        // Offer snippets as plain text to clients that cannot expand them
+        let snippet_support = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.completion.as_ref())
+            .and_then(|c| c.completion_item.as_ref())
+            .and_then(|i| i.snippet_support)
+            .unwrap_or(false);
+        self.completion_provider.set_snippet_support(snippet_support);
+
         // Initialize workspace folders if provided
         if let Some(folders) = params.workspace_folders {
             for folder in folders {
-                // Store in workspace manager
-                if let Err(e) = self.workspace_manager.add_folder(folder.clone()) {
-                    tracing::warn!("Failed to add workspace folder: {}", e);
+                // Set workspace root in index (use first folder)
+                if self.workspace_index.workspace_root().is_none() {
+                    if let Ok(path) = folder.uri.to_file_path() {
+                        self.workspace_index.set_workspace_root(path);
+                    }
                 }
 
-                // Set workspace root in index (use first folder)
-                if let Ok(path) = folder.uri.to_file_path() {
-                    let mut index = Arc::as_ref(&self.workspace_index).clone();
-                    index.set_workspace_root(path);
-                    break;
+                // Store in workspace manager
+                if let Err(e) = self.workspace_manager.add_folder(folder) {
+                    tracing::warn!("Failed to add workspace folder: {}", e);
                 }
             }
         } else if let Some(root_uri) = params.root_uri {
             // Fallback to root_uri if no workspace_folders
             if let Ok(path) = root_uri.to_file_path() {
-                let mut index = Arc::as_ref(&self.workspace_index).clone();
-                index.set_workspace_root(path);
+                self.workspace_index.set_workspace_root(path);
             }
         }
 
@@ -211,7 +777,12 @@ impl LanguageServer for UniversalLsp {
                     },
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
-                completion_provider: Some(CompletionOptions::default()),
+                // Registered per language once initialized when the client allows it
+                completion_provider: (!completion_dynamic).then(|| CompletionOptions {
+                    resolve_provider: Some(true),
+                    trigger_characters: Some(Self::all_trigger_characters()),
+                    ..Default::default()
+                }),
                 signature_help_provider: Some(SignatureHelpOptions {
                     trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
                     retrigger_characters: None,
@@ -220,18 +791,35 @@ impl LanguageServer for UniversalLsp {
                 document_symbol_provider: Some(OneOf::Left(true)),
                 definition_provider: Some(OneOf::Left(true)),
                 references_provider: Some(OneOf::Left(true)),
-                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                    code_action_kinds: Some(vec![
+                        CodeActionKind::QUICKFIX,
+                        CodeActionKind::REFACTOR,
+                        CodeActionKind::REFACTOR_EXTRACT,
+                        CodeActionKind::REFACTOR_REWRITE,
+                        CodeActionKind::SOURCE,
+                        CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                        CodeActionKind::SOURCE_FIX_ALL,
+                    ]),
+                    resolve_provider: Some(true),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                })),
                 execute_command_provider: Some(ExecuteCommandOptions {
                     commands: vec![
                         "universal-lsp.explainCode".to_string(),
                         "universal-lsp.optimizeCode".to_string(),
                         "universal-lsp.generateTests".to_string(),
                         "universal-lsp.generateDocs".to_string(),
+                        ACCEPTED_COMMAND.to_string(),
                     ],
                     work_done_progress_options: WorkDoneProgressOptions::default(),
                 }),
                 document_formatting_provider: Some(OneOf::Left(true)),
                 document_range_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: "}".to_string(),
+                    more_trigger_character: Some(vec![";".to_string(), "\n".to_string(), ":".to_string()]),
+                }),
                 semantic_tokens_provider: Some(
                     SemanticTokensServerCapabilities::SemanticTokensOptions(
                         SemanticTokensOptions {
@@ -242,10 +830,26 @@ impl LanguageServer for UniversalLsp {
                         }
                     )
                 ),
-                inlay_hint_provider: Some(OneOf::Left(true)),
+                inlay_hint_provider: Some(OneOf::Right(InlayHintServerCapabilities::Options(
+                    InlayHintOptions {
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                        resolve_provider: Some(true),
+                    },
+                ))),
                 code_lens_provider: Some(CodeLensOptions {
                     resolve_provider: Some(false),
                 }),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                linked_editing_range_provider: Some(LinkedEditingRangeServerCapabilities::Simple(true)),
+                call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
+                    identifier: Some("universal-lsp".to_string()),
+                    inter_file_dependencies: false,
+                    workspace_diagnostics: true,
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                })),
                 workspace: Some(WorkspaceServerCapabilities {
                     workspace_folders: Some(WorkspaceFoldersServerCapabilities {
                         supported: Some(true),
@@ -253,6 +857,10 @@ impl LanguageServer for UniversalLsp {
                     }),
                     file_operations: None,
                 }),
+                // `inlineCompletionProvider` (LSP 3.18) has no field in lsp-types 0.94, so it
+                // is registered in `initialized`, or announced here for clients that can't
+                experimental: (!self.inline_completion_registration.load(std::sync::atomic::Ordering::Relaxed))
+                    .then(|| serde_json::json!({ "inlineCompletionProvider": true })),
                 ..Default::default()
             },
             server_info: Some(ServerInfo {
@@ -267,9 +875,61 @@ impl LanguageServer for UniversalLsp {
             .log_message(MessageType::INFO, "Universal LSP initialized!")
             .await;
 
+        self.load_snippets().await;
+        let folders = self.workspace_manager.list_folders();
+        let roots: Vec<_> = if folders.is_empty() {
+            self.workspace_index.workspace_root().into_iter().collect()
+        } else {
+            folders.iter().filter_map(|folder| folder.uri.to_file_path().ok()).collect()
+        };
+        for root in roots {
+            self.completion_provider.load_history(&root);
+        }
+
+        if self.completion_registration.load(std::sync::atomic::Ordering::Relaxed) {
+            if let Err(e) = self.client.register_capability(Self::completion_registrations()).await {
+                tracing::warn!("Failed to register completion: {}", e);
+            }
+        }
+
+        if self.type_hierarchy_registration.load(std::sync::atomic::Ordering::Relaxed) {
+            let options = TypeHierarchyRegistrationOptions {
+                text_document_registration_options: TextDocumentRegistrationOptions {
+                    document_selector: None,
+                },
+                type_hierarchy_options: TypeHierarchyOptions::default(),
+                static_registration_options: StaticRegistrationOptions::default(),
+            };
+            let registration = Registration {
+                id: "universal-lsp-type-hierarchy".to_string(),
+                method: "textDocument/prepareTypeHierarchy".to_string(),
+                register_options: serde_json::to_value(options).ok(),
+            };
+            if let Err(e) = self.client.register_capability(vec![registration]).await {
+                tracing::warn!("Failed to register type hierarchy: {}", e);
+            }
+        }
+
+        if self.inline_completion_registration.load(std::sync::atomic::Ordering::Relaxed) {
+            let registration = Registration {
+                id: "universal-lsp-inline-completion".to_string(),
+                method: inline_completion::protocol::METHOD.to_string(),
+                register_options: Some(serde_json::json!({ "documentSelector": null })),
+            };
+            if let Err(e) = self.client.register_capability(vec![registration]).await {
+                tracing::warn!("Failed to register inline completion: {}", e);
+            }
+        }
+
         // Trigger workspace indexing in the background
         let workspace_index = self.workspace_index.clone();
+        let diagnostic_provider = self.diagnostic_provider.clone();
+        let documents = self.documents.clone();
+        let ai_linter = self.ai_linter.clone();
+        let linter_runner = self.linter_runner.clone();
+        let workspace_manager = self.workspace_manager.clone();
         let client = self.client.clone();
+        let pull = self.pull_diagnostics.load(std::sync::atomic::Ordering::Relaxed);
         tokio::spawn(async move {
             client
                 .log_message(MessageType::INFO, "Starting workspace indexing...")
@@ -283,6 +943,16 @@ impl LanguageServer for UniversalLsp {
                             format!("Workspace indexing complete: {} symbols indexed", count),
                         )
                         .await;
+
+                    // Analyse unopened files so workspace pulls have reports to serve
+                    let sources = DiagnosticSources {
+                        uri: None,
+                        ai_linter: ai_linter.as_deref(),
+                        linters: Some(&linter_runner),
+                        workspaces: Some(&workspace_manager),
+                    };
+                    Self::update_workspace_reports(&client, &diagnostic_provider, &workspace_index, &documents, sources, pull)
+                        .await;
                 }
                 Err(e) => {
                     client
@@ -414,7 +1084,7 @@ impl LanguageServer for UniversalLsp {
                 );
             }
 
-            // AI Enhancement: Send combined context to Claude/Copilot for enrichment
+            // AI Enhancement: Send combined context to the hover providers for enrichment
             use crate::ai::claude::CompletionContext;
 
             let ai_ctx = CompletionContext {
@@ -432,24 +1102,16 @@ impl LanguageServer for UniversalLsp {
                 )),
             };
 
-            // Try Claude first, then Copilot
-            let ai_enhancement = if let Some(ref claude) = self.claude_client {
-                match claude.get_completions(&ai_ctx).await {
-                    Ok(suggestions) if !suggestions.is_empty() => {
-                        Some(suggestions[0].text.clone())
+            // First provider with an answer
+            let mut ai_enhancement = None;
+            for provider in self.ai.for_feature(AiFeature::Hover) {
+                if let Ok(suggestions) = provider.get_completions(&ai_ctx).await {
+                    if let Some(suggestion) = suggestions.into_iter().next() {
+                        ai_enhancement = Some(suggestion.text);
+                        break;
                     }
-                    _ => None,
                 }
-            } else if let Some(ref copilot) = self.copilot_client {
-                match copilot.get_completions(&ai_ctx).await {
-                    Ok(suggestions) if !suggestions.is_empty() => {
-                        Some(suggestions[0].text.clone())
-                    }
-                    _ => None,
-                }
-            } else {
-                None
-            };
+            }
 
             // Add AI-enhanced explanation
             if let Some(enhancement) = ai_enhancement {
@@ -503,206 +1165,46 @@ impl LanguageServer for UniversalLsp {
         let position = params.text_document_position.position;
         let lang = detect_language(uri.path());
 
-        let mut items: Vec<CompletionItem> = vec![
-            CompletionItem {
-                label: "function".to_string(),
-                kind: Some(CompletionItemKind::KEYWORD),
-                ..Default::default()
-            },
-            CompletionItem {
-                label: "class".to_string(),
-                kind: Some(CompletionItemKind::KEYWORD),
-                ..Default::default()
-            },
-        ];
-
-        // Try AI-powered completions from Claude (highest priority)
-        if let Some(claude_client) = &self.claude_client {
-            if let Some(content) = self.documents.get(uri.as_str()) {
-                let byte_offset = position_to_byte(&content, position);
-                let prefix = content[..byte_offset].to_string();
-                let suffix = if byte_offset < content.len() {
-                    Some(content[byte_offset..].to_string())
-                } else {
-                    None
-                };
-
-                // Check cache first
-                if let Some(cached_completions) = self.inline_completion_manager.get_cached(uri.as_str(), &prefix, &suffix) {
-                    tracing::debug!("Using cached completions for {}", uri);
-                    for (idx, text) in cached_completions.iter().enumerate() {
-                        items.push(CompletionItem {
-                            label: text.clone(),
-                            kind: Some(CompletionItemKind::TEXT),
-                            detail: Some("Claude AI (cached)".to_string()),
-                            insert_text: Some(text.clone()),
-                            insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
-                            sort_text: Some(format!("0_claude_{}", idx)),
-                            ..Default::default()
-                        });
-                    }
-                } else {
-                    // Check if we should debounce
-                    if self.inline_completion_manager.should_debounce(uri.as_str()).await {
-                        tracing::debug!("Debouncing completion request for {}", uri);
-                        self.inline_completion_manager.wait_debounce().await;
-                    }
-
-                    // Update last request timestamp
-                    self.inline_completion_manager.update_last_request(uri.to_string()).await;
-
-                    // Create cancellation token
-                    let cancel_rx = self.inline_completion_manager.create_cancellation_token(uri.to_string());
+        let trigger = params.context.as_ref().and_then(|context| context.trigger_character.as_deref());
+        let grammar = grammar_name(uri.path());
+        let content = self.documents.get(uri.as_str()).map(|content| content.clone()).unwrap_or_default();
+        let mut list = self
+            .completion_provider
+            .completions(&content, uri, position, &grammar, trigger, &self.workspace_index)
+            .unwrap_or_else(|e| {
+                tracing::debug!("Completion failed for {}: {}", uri, e);
+                CompletionList::default()
+            });
+        let items = &mut list.items;
 
-                    let completion_context = CompletionContext {
-                        language: lang.to_string(),
-                        file_path: uri.path().to_string(),
-                        prefix: prefix.clone(),
-                        suffix: suffix.clone(),
-                        context: None,
-                    };
-
-                    // Check cancellation before making request
-                    if !self.inline_completion_manager.is_cancelled(uri.as_str(), &cancel_rx) {
-                        match claude_client.get_completions(&completion_context).await {
-                            Ok(suggestions) => {
-                                // Check cancellation after request
-                                if !self.inline_completion_manager.is_cancelled(uri.as_str(), &cancel_rx) {
-                                    let mut cached_texts = Vec::new();
-
-                                    for suggestion in suggestions {
-                                        items.push(CompletionItem {
-                                            label: suggestion.text.clone(),
-                                            kind: Some(CompletionItemKind::TEXT),
-                                            detail: suggestion.detail.or(Some("Claude AI".to_string())),
-                                            insert_text: Some(suggestion.text.clone()),
-                                            insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
-                                            sort_text: Some(format!("0_claude_{}", suggestion.confidence)),
-                                            ..Default::default()
-                                        });
-                                        cached_texts.push(suggestion.text);
-                                    }
+        // Query MCP servers via Coordinator (if available)
+        if let Some(coordinator) = &self.coordinator_client {
+            // Try querying configured MCP servers for this language
+            // For now, query a generic "completion" server if it exists
+            let mcp_request = McpRequest {
+                request_type: "completion".to_string(),
+                uri: uri.to_string(),
+                position: lsp_position_to_mcp(position.line, position.character),
+                context: None,
+            };
 
-                                    // Cache the completions
-                                    if !cached_texts.is_empty() {
-                                        self.inline_completion_manager.cache_completion(
-                                            uri.to_string(),
-                                            &prefix,
-                                            &suffix,
-                                            cached_texts,
-                                        );
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                tracing::debug!("Claude completion failed: {}", e);
-                            }
+            // Query all configured MCP servers via coordinator
+            for server_name in self.config.mcp.servers.keys() {
+                match coordinator.query(server_name, mcp_request.clone()).await {
+                    Ok(response) => {
+                        for suggestion in response.suggestions {
+                            items.push(CompletionItem {
+                                label: suggestion.clone(),
+                                kind: Some(CompletionItemKind::TEXT),
+                                detail: Some(format!("MCP: {}", server_name)),
+                                sort_text: Some(format!("2_mcp_{}", suggestion)),
+                                ..Default::default()
+                            });
                         }
                     }
-
-                    // Clean up cancellation token
-                    self.inline_completion_manager.remove_cancellation_token(uri.as_str());
-                }
-            }
-        }
-
-        // Try AI-powered completions from GitHub Copilot
-        if let Some(copilot_client) = &self.copilot_client {
-            if let Some(content) = self.documents.get(uri.as_str()) {
-                let byte_offset = position_to_byte(&content, position);
-                let prefix = content[..byte_offset].to_string();
-                let suffix = if byte_offset < content.len() {
-                    Some(content[byte_offset..].to_string())
-                } else {
-                    None
-                };
-
-                let completion_context = CompletionContext {
-                    language: lang.to_string(),
-                    file_path: uri.path().to_string(),
-                    prefix,
-                    suffix,
-                    context: None,
-                };
-
-                match copilot_client.get_completions(&completion_context).await {
-                    Ok(suggestions) => {
-                        for suggestion in suggestions {
-                            items.push(CompletionItem {
-                                label: suggestion.text.clone(),
-                                kind: Some(CompletionItemKind::TEXT),
-                                detail: suggestion.detail.or(Some("GitHub Copilot".to_string())),
-                                insert_text: Some(suggestion.text),
-                                sort_text: Some(format!("0_copilot_{}", suggestion.confidence)),
-                                ..Default::default()
-                            });
-                        }
-                    }
-                    Err(e) => {
-                        tracing::debug!("Copilot completion failed: {}", e);
-                    }
-                }
-            }
-        }
-
-        // Try tree-sitter symbol-based completions
-        let lang_lowercase = lang.to_lowercase();
-        if let Some(content) = self.documents.get(uri.as_str()) {
-            if let Ok(mut parser) = TreeSitterParser::new() {
-                if parser.set_language(&lang_lowercase).is_ok() {
-                    if let Ok(tree) = parser.parse(&content, uri.as_str()) {
-                        if let Ok(symbols) = parser.extract_symbols(&tree, &content, &lang_lowercase) {
-                            for symbol in symbols {
-                                let completion_kind = match symbol.kind {
-                                    SymbolKind::FUNCTION | SymbolKind::METHOD => CompletionItemKind::FUNCTION,
-                                    SymbolKind::CLASS => CompletionItemKind::CLASS,
-                                    SymbolKind::VARIABLE => CompletionItemKind::VARIABLE,
-                                    SymbolKind::CONSTANT => CompletionItemKind::CONSTANT,
-                                    _ => CompletionItemKind::TEXT,
-                                };
-
-                                items.push(CompletionItem {
-                                    label: symbol.name.clone(),
-                                    kind: Some(completion_kind),
-                                    detail: symbol.detail.or(Some(format!("{:?}", symbol.kind))),
-                                    sort_text: Some(format!("1_{}", symbol.name)),
-                                    ..Default::default()
-                                });
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        // Query MCP servers via Coordinator (if available)
-        if let Some(coordinator) = &self.coordinator_client {
-            // Try querying configured MCP servers for this language
-            // For now, query a generic "completion" server if it exists
-            let mcp_request = McpRequest {
-                request_type: "completion".to_string(),
-                uri: uri.to_string(),
-                position: lsp_position_to_mcp(position.line, position.character),
-                context: None,
-            };
-
-            // Query all configured MCP servers via coordinator
-            for server_name in self.config.mcp.servers.keys() {
-                match coordinator.query(server_name, mcp_request.clone()).await {
-                    Ok(response) => {
-                        for suggestion in response.suggestions {
-                            items.push(CompletionItem {
-                                label: suggestion.clone(),
-                                kind: Some(CompletionItemKind::TEXT),
-                                detail: Some(format!("MCP: {}", server_name)),
-                                sort_text: Some(format!("2_mcp_{}", suggestion)),
-                                ..Default::default()
-                            });
-                        }
-                    }
-                    Err(e) => {
-                        tracing::debug!("MCP query to {} failed: {}", server_name, e);
-                    }
+                    Err(e) => {
+                        tracing::debug!("MCP query to {} failed: {}", server_name, e);
+                    }
                 }
             }
         }
@@ -743,7 +1245,28 @@ impl LanguageServer for UniversalLsp {
             }
         }
 
-        Ok(Some(CompletionResponse::Array(items)))
+        let workspace_root = self.workspace_root(uri);
+        let list = self.completion_provider.rank(list, &content, uri, position, &grammar, workspace_root.as_deref());
+        Ok(Some(CompletionResponse::List(list)))
+    }
+
+    async fn completion_resolve(&self, item: CompletionItem) -> Result<CompletionItem> {
+        let Some(data) = universal_lsp::completion::resolve::CompletionData::of(&item) else {
+            return Ok(item);
+        };
+        let source = match self.documents.get(data.uri.as_str()) {
+            Some(content) => content.clone(),
+            None => match data.uri.to_file_path().ok().and_then(|path| std::fs::read_to_string(path).ok()) {
+                Some(content) => content,
+                None => return Ok(item),
+            },
+        };
+        let summarize = self
+            .workspace_manager
+            .get_config_for_document(&data.uri, &grammar_name(data.uri.path()))
+            .completion
+            .is_some_and(|config| config.ai_summaries);
+        Ok(self.completion_provider.resolve(item, &source, summarize).await)
     }
 
     async fn goto_definition(
@@ -851,81 +1374,12 @@ impl LanguageServer for UniversalLsp {
         self.text_sync_manager.did_open(params);
         self.documents.insert(uri_str.clone(), content.clone());
 
-        // Compute and publish initial diagnostics
-        let lang = detect_language(uri.path());
-        let lang_lowercase = lang.to_lowercase();
-
-        if let Ok(mut parser) = TreeSitterParser::new() {
-            if parser.set_language(&lang_lowercase).is_ok() {
-                if let Ok(tree) = parser.parse(&content, uri.as_str()) {
-                    let claude_client = self.claude_client.as_deref();
-                    match diagnostics::compute_diagnostics(&tree, &content, &lang_lowercase, claude_client).await {
-                        Ok(mut diags) => {
-                            // Enhance diagnostics with MCP validation
-                            if let Some(coordinator) = &self.coordinator_client {
-                                let mcp_request = McpRequest {
-                                    request_type: "diagnostics".to_string(),
-                                    uri: uri.to_string(),
-                                    position: lsp_position_to_mcp(0, 0),
-                                    context: Some(content.clone()),
-                                };
-
-                                // Query all configured MCP servers for diagnostic suggestions
-                                for server_name in self.config.mcp.servers.keys() {
-                                    match coordinator.query(server_name, mcp_request.clone()).await {
-                                        Ok(response) => {
-                                            // Convert MCP suggestions to LSP diagnostics
-                                            for suggestion in response.suggestions {
-                                                diags.push(Diagnostic {
-                                                    range: Range {
-                                                        start: tower_lsp::lsp_types::Position { line: 0, character: 0 },
-                                                        end: tower_lsp::lsp_types::Position { line: 0, character: 0 },
-                                                    },
-                                                    severity: Some(DiagnosticSeverity::HINT),
-                                                    code: None,
-                                                    source: Some(format!("mcp:{}", server_name)),
-                                                    message: suggestion,
-                                                    related_information: None,
-                                                    tags: None,
-                                                    code_description: None,
-                                                    data: None,
-                                                });
-                                            }
-
-                                            // Add documentation as info diagnostic if present
-                                            if let Some(doc) = response.documentation {
-                                                diags.push(Diagnostic {
-                                                    range: Range {
-                                                        start: tower_lsp::lsp_types::Position { line: 0, character: 0 },
-                                                        end: tower_lsp::lsp_types::Position { line: 0, character: 0 },
-                                                    },
-                                                    severity: Some(DiagnosticSeverity::INFORMATION),
-                                                    code: None,
-                                                    source: Some(format!("mcp:{}", server_name)),
-                                                    message: doc,
-                                                    related_information: None,
-                                                    tags: None,
-                                                    code_description: None,
-                                                    data: None,
-                                                });
-                                            }
-                                        }
-                                        Err(e) => {
-                                            tracing::debug!("MCP diagnostics query to {} failed: {}", server_name, e);
-                                        }
-                                    }
-                                }
-                            }
-
-                            self.client.publish_diagnostics(uri, diags, None).await;
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to compute initial diagnostics: {}", e);
-                        }
-                    }
-                }
-            }
+        // Keep call/type hierarchy data in sync with the open buffer
+        if let Err(e) = self.workspace_index.index_content(&uri_str, &content) {
+            tracing::debug!("Failed to index {}: {}", uri_str, e);
         }
+
+        self.refresh_diagnostics(uri, content, std::time::Duration::ZERO).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -939,85 +1393,27 @@ impl LanguageServer for UniversalLsp {
         if let Some(content) = self.text_sync_manager.get_content(&uri_str) {
             self.documents.insert(uri_str.clone(), content.clone());
 
-            // Compute and publish diagnostics in real-time
-            let lang = detect_language(uri.path());
-            let lang_lowercase = lang.to_lowercase();
-
-            // Parse with tree-sitter
-            if let Ok(mut parser) = TreeSitterParser::new() {
-                if parser.set_language(&lang_lowercase).is_ok() {
-                    if let Ok(tree) = parser.parse(&content, uri.as_str()) {
-                        // Compute diagnostics
-                        let claude_client = self.claude_client.as_deref();
-                        match diagnostics::compute_diagnostics(&tree, &content, &lang_lowercase, claude_client).await {
-                            Ok(mut diags) => {
-                                // Enhance diagnostics with MCP validation
-                                if let Some(coordinator) = &self.coordinator_client {
-                                    let mcp_request = McpRequest {
-                                        request_type: "diagnostics".to_string(),
-                                        uri: uri.to_string(),
-                                        position: lsp_position_to_mcp(0, 0),
-                                        context: Some(content.clone()),
-                                    };
-
-                                    // Query all configured MCP servers for diagnostic suggestions
-                                    for server_name in self.config.mcp.servers.keys() {
-                                        match coordinator.query(server_name, mcp_request.clone()).await {
-                                            Ok(response) => {
-                                                // Convert MCP suggestions to LSP diagnostics
-                                                for suggestion in response.suggestions {
-                                                    diags.push(Diagnostic {
-                                                        range: Range {
-                                                            start: tower_lsp::lsp_types::Position { line: 0, character: 0 },
-                                                            end: tower_lsp::lsp_types::Position { line: 0, character: 0 },
-                                                        },
-                                                        severity: Some(DiagnosticSeverity::HINT),
-                                                        code: None,
-                                                        source: Some(format!("mcp:{}", server_name)),
-                                                        message: suggestion,
-                                                        related_information: None,
-                                                        tags: None,
-                                                        code_description: None,
-                                                        data: None,
-                                                    });
-                                                }
-
-                                                // Add documentation as info diagnostic if present
-                                                if let Some(doc) = response.documentation {
-                                                    diags.push(Diagnostic {
-                                                        range: Range {
-                                                            start: tower_lsp::lsp_types::Position { line: 0, character: 0 },
-                                                            end: tower_lsp::lsp_types::Position { line: 0, character: 0 },
-                                                        },
-                                                        severity: Some(DiagnosticSeverity::INFORMATION),
-                                                        code: None,
-                                                        source: Some(format!("mcp:{}", server_name)),
-                                                        message: doc,
-                                                        related_information: None,
-                                                        tags: None,
-                                                        code_description: None,
-                                                        data: None,
-                                                    });
-                                                }
-                                            }
-                                            Err(e) => {
-                                                tracing::debug!("MCP diagnostics query to {} failed: {}", server_name, e);
-                                            }
-                                        }
-                                    }
-                                }
+            self.refresh_diagnostics(uri, content, EDIT_DEBOUNCE).await;
+        }
+    }
 
-                                // Publish diagnostics to client
-                                self.client.publish_diagnostics(uri, diags, None).await;
-                            }
-                            Err(e) => {
-                                tracing::warn!("Failed to compute diagnostics: {}", e);
-                            }
-                        }
-                    }
-                }
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        let uri = params.text_document.uri.to_string();
+
+        if let Some(content) = self.documents.get(&uri) {
+            if let Err(e) = self.workspace_index.index_content(&uri, &content) {
+                tracing::debug!("Failed to index {}: {}", uri, e);
             }
         }
+
+        if let Ok(path) = params.text_document.uri.to_file_path() {
+            if universal_lsp::completion::snippets::is_snippet_file(&path) {
+                self.load_snippets().await;
+            }
+        }
+
+        self.run_linter(params.text_document.uri.clone());
+        self.schedule_ai_lint(params.text_document.uri);
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
@@ -1025,10 +1421,17 @@ impl LanguageServer for UniversalLsp {
 
         self.text_sync_manager.did_close(params);
         self.documents.remove(&uri);
+        self.linter_runner.clear(&uri);
+        self.diagnostics_scheduler.cancel(&uri);
+        // The report was for the editor's buffer; the saved file gets its own
+        self.diagnostic_provider.invalidate(&uri);
     }
 
     async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
         for added in params.event.added {
+            if let Ok(root) = added.uri.to_file_path() {
+                self.completion_provider.load_history(&root);
+            }
             if let Err(e) = self.workspace_manager.add_folder(added) {
                 tracing::error!("Failed to add workspace folder: {}", e);
             }
@@ -1080,11 +1483,22 @@ impl LanguageServer for UniversalLsp {
     async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
         let uri = &params.text_document.uri;
         let range = params.range;
-        let diagnostics = params.context.diagnostics;
-        let lang = detect_language(uri.path());
+        let mut diagnostics = params.context.diagnostics;
+        let only = params.context.only.as_deref();
+        let lang = grammar_name(uri.path());
+        let indent = self.format_settings(uri, &lang, None).unit();
 
         if let Some(content) = self.documents.get(uri.as_str()) {
-            match self.code_action_provider.get_actions(uri, range, &content, diagnostics, lang) {
+            // Clients only send the diagnostics of the requested range; fix-all needs
+            // the linter findings of the whole document
+            if only.is_some_and(|only| only.iter().any(|kind| *kind == CodeActionKind::SOURCE || *kind == CodeActionKind::SOURCE_FIX_ALL)) {
+                for diagnostic in self.diagnostic_provider.cached(uri.as_str(), &content).unwrap_or_default() {
+                    if !diagnostics.contains(&diagnostic) {
+                        diagnostics.push(diagnostic);
+                    }
+                }
+            }
+            match self.code_action_provider.get_actions(uri, range, &content, diagnostics, &lang, only, &indent) {
                 Ok(actions) => Ok(Some(actions)),
                 Err(_) => Ok(None),
             }
@@ -1093,9 +1507,40 @@ impl LanguageServer for UniversalLsp {
         }
     }
 
+    async fn code_action_resolve(&self, params: CodeAction) -> Result<CodeAction> {
+        let Some(data) = params
+            .data
+            .clone()
+            .and_then(|d| serde_json::from_value::<ResolveData>(d).ok())
+        else {
+            return Ok(params);
+        };
+
+        let Some(content) = self.documents.get(data.uri.as_str()).map(|c| c.clone()) else {
+            return Ok(params);
+        };
+
+        self.code_action_provider
+            .resolve(params, &content)
+            .await
+            .map_err(|e| tower_lsp::jsonrpc::Error {
+                code: tower_lsp::jsonrpc::ErrorCode::InternalError,
+                message: e.to_string().into(),
+                data: None,
+            })
+    }
+
     async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<serde_json::Value>> {
         let command = params.command.as_str();
 
+        // Sent on every accepted completion, so handled before logging
+        if command == ACCEPTED_COMMAND {
+            if let Err(e) = self.completion_provider.record_acceptance(&params.arguments, |uri| self.workspace_root(uri)) {
+                tracing::debug!("Failed to record completion acceptance: {}", e);
+            }
+            return Ok(None);
+        }
+
         self.client
             .log_message(MessageType::INFO, format!("Executing command: {}", command))
             .await;
@@ -1122,27 +1567,45 @@ impl LanguageServer for UniversalLsp {
             }
         };
 
-        // Extract selected text
-        let selected_text = Self::extract_text_from_range(&content, range);
-
-        // Execute the appropriate command
-        match command {
-            "universal-lsp.explainCode" => {
-                self.execute_explain_code(&uri_str, &selected_text, &content).await
-            }
-            "universal-lsp.optimizeCode" => {
-                self.execute_optimize_code(&uri_str, &selected_text, &content, range).await
-            }
-            "universal-lsp.generateTests" => {
-                self.execute_generate_tests(&uri_str, &selected_text, &content).await
-            }
-            "universal-lsp.generateDocs" => {
-                self.execute_generate_docs(&uri_str, &selected_text, &content, range).await
-            }
+        let edit = match command {
+            "universal-lsp.explainCode" => return Ok(self.show_explanation(&uri_str, range, &content).await),
+            "universal-lsp.optimizeCode" => DeferredEdit::AiOptimize,
+            "universal-lsp.generateTests" => DeferredEdit::AiTests,
+            "universal-lsp.generateDocs" => DeferredEdit::AiDocumentation,
             _ => {
                 self.client
                     .log_message(MessageType::ERROR, format!("Unknown command: {}", command))
                     .await;
+                return Ok(None);
+            }
+        };
+
+        let uri = match Url::parse(&uri_str) {
+            Ok(uri) => uri,
+            Err(e) => {
+                self.client
+                    .show_message(MessageType::ERROR, format!("Invalid URI: {}", e))
+                    .await;
+                return Ok(None);
+            }
+        };
+
+        // Claude's answer comes back as a validated edit the client previews before applying
+        let lang = grammar_name(uri.path());
+        match self.code_action_provider.ai_edit(edit, &uri, range, &lang, &content).await {
+            Ok(workspace_edit) => match self.client.apply_edit(workspace_edit).await {
+                Ok(response) => Ok(Some(serde_json::json!({ "success": true, "applied": response.applied }))),
+                Err(e) => {
+                    self.client
+                        .show_message(MessageType::ERROR, format!("Failed to apply edit: {}", e))
+                        .await;
+                    Ok(None)
+                }
+            },
+            Err(e) => {
+                self.client
+                    .show_message(MessageType::ERROR, format!("{}: {}", command, e))
+                    .await;
                 Ok(None)
             }
         }
@@ -1169,7 +1632,7 @@ impl LanguageServer for UniversalLsp {
         let lang = detect_language(uri.path());
 
         if let Some(content) = self.documents.get(uri.as_str()) {
-            match self.inlay_hints_provider.get_inlay_hints(&content, range, lang) {
+            match self.inlay_hints_provider.get_inlay_hints(&content, range, &lang.to_lowercase()) {
                 Ok(hints) => Ok(Some(hints)),
                 Err(_) => Ok(None),
             }
@@ -1178,6 +1641,10 @@ impl LanguageServer for UniversalLsp {
         }
     }
 
+    async fn inlay_hint_resolve(&self, params: InlayHint) -> Result<InlayHint> {
+        Ok(self.inlay_hints_provider.resolve_inlay_hint(params))
+    }
+
     async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
         let uri = &params.text_document.uri;
         let lang = detect_language(uri.path());
@@ -1192,13 +1659,13 @@ impl LanguageServer for UniversalLsp {
         }
     }
 
-    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
         let uri = &params.text_document.uri;
         let lang = detect_language(uri.path());
 
         if let Some(content) = self.documents.get(uri.as_str()) {
-            match self.formatting_provider.format_document(&content, lang, uri) {
-                Ok(edits) => Ok(Some(edits)),
+            match self.folding_range_provider.get_folding_ranges(&content, &lang.to_lowercase()) {
+                Ok(ranges) => Ok(Some(ranges)),
                 Err(_) => Ok(None),
             }
         } else {
@@ -1206,319 +1673,243 @@ impl LanguageServer for UniversalLsp {
         }
     }
 
-    async fn range_formatting(
-        &self,
-        params: DocumentRangeFormattingParams,
-    ) -> Result<Option<Vec<TextEdit>>> {
+    async fn selection_range(&self, params: SelectionRangeParams) -> Result<Option<Vec<SelectionRange>>> {
         let uri = &params.text_document.uri;
-        let range = params.range;
         let lang = detect_language(uri.path());
 
         if let Some(content) = self.documents.get(uri.as_str()) {
-            match self.formatting_provider.format_range(&content, range, lang, uri) {
-                Ok(edits) => Ok(Some(edits)),
-                Err(_) => Ok(None),
+            match self.selection_range_provider.get_selection_ranges(&content, &params.positions, &lang.to_lowercase()) {
+                Ok(ranges) if !ranges.is_empty() => Ok(Some(ranges)),
+                _ => Ok(None),
             }
         } else {
             Ok(None)
         }
     }
 
-    async fn semantic_tokens_full(
-        &self,
-        params: SemanticTokensParams,
-    ) -> Result<Option<SemanticTokensResult>> {
-        let uri = &params.text_document.uri;
+    async fn document_highlight(&self, params: DocumentHighlightParams) -> Result<Option<Vec<DocumentHighlight>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
         let lang = detect_language(uri.path());
 
         if let Some(content) = self.documents.get(uri.as_str()) {
-            match self.semantic_tokens_provider.get_semantic_tokens(&content, lang) {
-                Ok(Some(tokens)) => Ok(Some(SemanticTokensResult::Tokens(tokens))),
-                Ok(None) => Ok(None),
+            match self.document_highlight_provider.get_highlights(&content, position, &lang.to_lowercase()) {
+                Ok(highlights) if !highlights.is_empty() => Ok(Some(highlights)),
+                _ => Ok(None),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn linked_editing_range(&self, params: LinkedEditingRangeParams) -> Result<Option<LinkedEditingRanges>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let lang = grammar_name(uri.path());
+
+        if let Some(content) = self.documents.get(uri.as_str()) {
+            match self.linked_editing_provider.get_linked_ranges(&content, position, &lang) {
+                Ok(ranges) => Ok(ranges),
                 Err(_) => Ok(None),
             }
         } else {
             Ok(None)
         }
     }
-}
 
-// ========================================================================
-// AI Command Execution Helpers (Phase 5.2)
-// ========================================================================
+    async fn diagnostic(&self, params: DocumentDiagnosticParams) -> Result<DocumentDiagnosticReportResult> {
+        let uri = &params.text_document.uri;
+        let content = self.documents.get(uri.as_str()).map(|c| c.clone());
+
+        let report = match content {
+            Some(content) => self
+                .diagnostic_provider
+                .document_report(
+                    uri.as_str(),
+                    &content,
+                    &grammar_name(uri.path()),
+                    params.previous_result_id.as_deref(),
+                    self.diagnostic_sources(None),
+                )
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to compute diagnostics for {}: {}", uri, e);
+                    DocumentDiagnosticReportKind::Full(FullDocumentDiagnosticReport::default())
+                }),
+            None => DocumentDiagnosticReportKind::Full(FullDocumentDiagnosticReport::default()),
+        };
 
-impl UniversalLsp {
-    /// Parse command arguments to extract URI and Range
-    fn parse_command_args(args: &Vec<serde_json::Value>) -> Result<(String, Range)> {
-        use tower_lsp::jsonrpc::Error as JsonRpcError;
+        let report = match report {
+            DocumentDiagnosticReportKind::Full(full) => DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: full,
+            }),
+            DocumentDiagnosticReportKind::Unchanged(unchanged) => {
+                DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                    related_documents: None,
+                    unchanged_document_diagnostic_report: unchanged,
+                })
+            }
+        };
 
-        if args.len() < 2 {
-            return Err(JsonRpcError::invalid_params("Expected at least 2 arguments (uri, range)"));
-        }
+        Ok(DocumentDiagnosticReportResult::Report(report))
+    }
 
-        let uri_str = args[0]
-            .as_str()
-            .ok_or_else(|| JsonRpcError::invalid_params("First argument must be a string (URI)"))?
-            .to_string();
+    async fn workspace_diagnostic(&self, params: WorkspaceDiagnosticParams) -> Result<WorkspaceDiagnosticReportResult> {
+        // Open documents are covered by `textDocument/diagnostic`
+        let files = Self::unopened_indexed_files(&self.workspace_index, &self.documents);
+        let previous_result_ids = params
+            .previous_result_ids
+            .into_iter()
+            .map(|previous| (previous.uri, previous.value))
+            .collect();
 
-        let range: Range = serde_json::from_value(args[1].clone())
-            .map_err(|e| JsonRpcError::invalid_params(format!("Failed to parse range: {}", e)))?;
+        // Served from the cache; files changed on disk are re-analysed afterwards and
+        // a refresh asks for another pull
+        let items = self.diagnostic_provider.workspace_reports(&files, &previous_result_ids);
+        self.spawn_workspace_reports_update();
 
-        Ok((uri_str, range))
+        Ok(WorkspaceDiagnosticReportResult::Report(WorkspaceDiagnosticReport { items }))
     }
 
-    /// Extract text from a range in the document
-    fn extract_text_from_range(content: &str, range: Range) -> String {
-        let start_byte = position_to_byte(content, range.start);
-        let end_byte = position_to_byte(content, range.end);
+    async fn prepare_call_hierarchy(&self, params: CallHierarchyPrepareParams) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
 
-        if start_byte < end_byte && end_byte <= content.len() {
-            content[start_byte..end_byte].to_string()
+        if let Some(content) = self.documents.get(uri.as_str()) {
+            match self.call_hierarchy_provider.prepare(&content, uri, position, &grammar_name(uri.path()), &self.workspace_index) {
+                Ok(items) if !items.is_empty() => Ok(Some(items)),
+                _ => Ok(None),
+            }
         } else {
-            String::new()
+            Ok(None)
         }
     }
 
-    /// Execute "Explain Code" command
-    async fn execute_explain_code(
-        &self,
-        uri: &str,
-        selected_text: &str,
-        _full_content: &str,
-    ) -> Result<Option<serde_json::Value>> {
-        if selected_text.trim().is_empty() {
-            self.client
-                .show_message(MessageType::WARNING, "No code selected to explain")
-                .await;
-            return Ok(None);
-        }
-
-        let lang = detect_language(uri);
-        let prompt = format!(
-            "Explain this {} code in detail. Focus on what it does, how it works, and any important patterns or gotchas:\n\n```{}\n{}\n```",
-            lang, lang, selected_text
-        );
-
-        self.call_claude_and_show_result("Code Explanation", &prompt).await
+    async fn incoming_calls(&self, params: CallHierarchyIncomingCallsParams) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        Ok(Some(self.call_hierarchy_provider.incoming_calls(&params.item, &self.workspace_index)))
     }
 
-    /// Execute "Optimize Code" command
-    async fn execute_optimize_code(
-        &self,
-        uri: &str,
-        selected_text: &str,
-        full_content: &str,
-        range: Range,
-    ) -> Result<Option<serde_json::Value>> {
-        if selected_text.trim().is_empty() {
-            self.client
-                .show_message(MessageType::WARNING, "No code selected to optimize")
-                .await;
-            return Ok(None);
-        }
+    async fn outgoing_calls(&self, params: CallHierarchyOutgoingCallsParams) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        Ok(Some(self.call_hierarchy_provider.outgoing_calls(&params.item, &self.workspace_index)))
+    }
 
-        let lang = detect_language(uri);
-        let prompt = format!(
-            "Optimize this {} code for performance, readability, and best practices. Provide the optimized version and explain the improvements:\n\n```{}\n{}\n```",
-            lang, lang, selected_text
-        );
+    async fn prepare_type_hierarchy(&self, params: TypeHierarchyPrepareParams) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
 
-        // Get optimized code from Claude
-        match self.call_claude_for_edit(&prompt).await {
-            Ok(optimized_code) => {
-                // Apply the edit automatically
-                let uri_parsed = match Url::parse(uri) {
-                    Ok(u) => u,
-                    Err(e) => {
-                        self.client
-                            .show_message(MessageType::ERROR, format!("Invalid URI: {}", e))
-                            .await;
-                        return Ok(None);
-                    }
-                };
-                self.apply_text_edit(&uri_parsed, range, &optimized_code).await;
-                Ok(Some(serde_json::json!({ "success": true, "optimized": true })))
-            }
-            Err(e) => {
-                self.client
-                    .show_message(MessageType::ERROR, format!("Failed to optimize code: {}", e))
-                    .await;
-                Ok(None)
+        if let Some(content) = self.documents.get(uri.as_str()) {
+            match self.type_hierarchy_provider.prepare(&content, uri, position, &grammar_name(uri.path()), &self.workspace_index) {
+                Ok(items) if !items.is_empty() => Ok(Some(items)),
+                _ => Ok(None),
             }
+        } else {
+            Ok(None)
         }
     }
 
-    /// Execute "Generate Tests" command
-    async fn execute_generate_tests(
-        &self,
-        uri: &str,
-        selected_text: &str,
-        _full_content: &str,
-    ) -> Result<Option<serde_json::Value>> {
-        if selected_text.trim().is_empty() {
-            self.client
-                .show_message(MessageType::WARNING, "No code selected to generate tests for")
-                .await;
-            return Ok(None);
-        }
-
-        let lang = detect_language(uri);
-        let prompt = format!(
-            "Generate comprehensive unit tests for this {} code. Include edge cases, error cases, and document each test:\n\n```{}\n{}\n```",
-            lang, lang, selected_text
-        );
-
-        self.call_claude_and_show_result("Generated Tests", &prompt).await
+    async fn supertypes(&self, params: TypeHierarchySupertypesParams) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        Ok(Some(self.type_hierarchy_provider.supertypes(&params.item, &self.workspace_index)))
     }
 
-    /// Execute "Generate Documentation" command
-    async fn execute_generate_docs(
-        &self,
-        uri: &str,
-        selected_text: &str,
-        full_content: &str,
-        range: Range,
-    ) -> Result<Option<serde_json::Value>> {
-        if selected_text.trim().is_empty() {
-            self.client
-                .show_message(MessageType::WARNING, "No code selected to document")
-                .await;
-            return Ok(None);
-        }
+    async fn subtypes(&self, params: TypeHierarchySubtypesParams) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        Ok(Some(self.type_hierarchy_provider.subtypes(&params.item, &self.workspace_index)))
+    }
 
-        let lang = detect_language(uri);
-        let prompt = format!(
-            "Generate comprehensive documentation for this {} code. Use appropriate doc comment syntax for the language:\n\n```{}\n{}\n```",
-            lang, lang, selected_text
-        );
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document.uri;
+        let lang = grammar_name(uri.path());
+        let formatter = self.formatter_config(uri, &lang);
+        let settings = self.format_settings(uri, &lang, Some(&params.options));
 
-        // Get documentation from Claude
-        match self.call_claude_for_edit(&prompt).await {
-            Ok(doc_text) => {
-                // Insert documentation before the selected code
-                let uri_parsed = match Url::parse(uri) {
-                    Ok(u) => u,
-                    Err(e) => {
-                        self.client
-                            .show_message(MessageType::ERROR, format!("Invalid URI: {}", e))
-                            .await;
-                        return Ok(None);
-                    }
-                };
-                let insert_range = Range {
-                    start: range.start,
-                    end: range.start,
-                };
-                self.apply_text_edit(&uri_parsed, insert_range, &format!("{}\n", doc_text)).await;
-                Ok(Some(serde_json::json!({ "success": true, "documented": true })))
-            }
-            Err(e) => {
-                self.client
-                    .show_message(MessageType::ERROR, format!("Failed to generate docs: {}", e))
-                    .await;
-                Ok(None)
+        if let Some(content) = self.documents.get(uri.as_str()).map(|c| c.clone()) {
+            match self.formatting_provider.format_document(&content, &lang, uri, formatter.as_ref(), settings).await {
+                Ok(edits) => Ok(Some(edits)),
+                Err(_) => Ok(None),
             }
+        } else {
+            Ok(None)
         }
     }
 
-    /// Call Claude API and show result as a message
-    async fn call_claude_and_show_result(
+    async fn range_formatting(
         &self,
-        title: &str,
-        prompt: &str,
-    ) -> Result<Option<serde_json::Value>> {
-        if let Some(client) = &self.claude_client {
-            match client.send_message(&[ai::claude::Message {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            }]).await {
-                Ok(response) => {
-                    // Show result to user
-                    self.client
-                        .show_message(MessageType::INFO, format!("{}\n\n{}", title, response))
-                        .await;
-                    Ok(Some(serde_json::json!({ "success": true, "result": response })))
-                }
-                Err(e) => {
-                    self.client
-                        .show_message(MessageType::ERROR, format!("Claude API error: {}", e))
-                        .await;
-                    Ok(None)
-                }
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document.uri;
+        let range = params.range;
+        let lang = grammar_name(uri.path());
+        let formatter = self.formatter_config(uri, &lang);
+        let settings = self.format_settings(uri, &lang, Some(&params.options));
+
+        if let Some(content) = self.documents.get(uri.as_str()).map(|c| c.clone()) {
+            match self.formatting_provider.format_range(&content, range, &lang, uri, formatter.as_ref(), settings).await {
+                Ok(edits) => Ok(Some(edits)),
+                Err(_) => Ok(None),
             }
         } else {
-            self.client
-                .show_message(MessageType::WARNING, "Claude API not available. Set ANTHROPIC_API_KEY to enable AI features.")
-                .await;
             Ok(None)
         }
     }
 
-    /// Call Claude API for code edit/generation
-    async fn call_claude_for_edit(&self, prompt: &str) -> std::result::Result<String, String> {
-        if let Some(client) = &self.claude_client {
-            client.send_message(&[ai::claude::Message {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            }]).await.map_err(|e| format!("Claude API error: {}", e))
+    async fn on_type_formatting(&self, params: DocumentOnTypeFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = &params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let lang = grammar_name(uri.path());
+        let settings = self.format_settings(uri, &lang, Some(&params.options));
+
+        if let Some(content) = self.documents.get(uri.as_str()).map(|c| c.clone()) {
+            match self.formatting_provider.format_on_type(&content, position, &params.ch, &lang, uri, settings) {
+                Ok(edits) if !edits.is_empty() => Ok(Some(edits)),
+                _ => Ok(None),
+            }
         } else {
-            Err("Claude API not available. Set ANTHROPIC_API_KEY to enable AI features.".to_string())
+            Ok(None)
         }
     }
 
-    /// Apply a text edit to a document
-    async fn apply_text_edit(&self, uri: &Url, range: Range, new_text: &str) {
-        let edit = WorkspaceEdit {
-            changes: Some(std::collections::HashMap::from([(
-                uri.clone(),
-                vec![TextEdit {
-                    range,
-                    new_text: new_text.to_string(),
-                }],
-            )])),
-            ..Default::default()
-        };
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let uri = &params.text_document.uri;
+        let lang = detect_language(uri.path());
 
-        match self.client.apply_edit(edit).await {
-            Ok(response) => {
-                if response.applied {
-                    self.client
-                        .show_message(MessageType::INFO, "Code updated successfully!")
-                        .await;
-                } else {
-                    self.client
-                        .show_message(MessageType::WARNING, "Edit was not applied")
-                        .await;
-                }
-            }
-            Err(e) => {
-                self.client
-                    .show_message(MessageType::ERROR, format!("Failed to apply edit: {}", e))
-                    .await;
+        if let Some(content) = self.documents.get(uri.as_str()) {
+            match self.semantic_tokens_provider.get_semantic_tokens(&content, lang) {
+                Ok(Some(tokens)) => Ok(Some(SemanticTokensResult::Tokens(tokens))),
+                Ok(None) => Ok(None),
+                Err(_) => Ok(None),
             }
+        } else {
+            Ok(None)
         }
     }
 }
 
-/// Helper function to convert LSP position to byte offset
-fn position_to_byte(source: &str, position: Position) -> usize {
-    let mut byte_offset = 0;
-    let mut current_line = 0;
-    let mut current_char = 0;
+// ========================================================================
+// AI Command Execution Helpers (Phase 5.2)
+// ========================================================================
+
+impl UniversalLsp {
+    /// Parse command arguments to extract URI and Range
+    fn parse_command_args(args: &Vec<serde_json::Value>) -> Result<(String, Range)> {
+        use tower_lsp::jsonrpc::Error as JsonRpcError;
 
-    for ch in source.chars() {
-        if current_line == position.line && current_char == position.character {
-            break;
-        }
-        byte_offset += ch.len_utf8();
-        current_char += 1;
-        if ch == '\n' {
-            current_line += 1;
-            current_char = 0;
+        if args.len() < 2 {
+            return Err(JsonRpcError::invalid_params("Expected at least 2 arguments (uri, range)"));
         }
-    }
 
-    byte_offset
+        let uri_str = args[0]
+            .as_str()
+            .ok_or_else(|| JsonRpcError::invalid_params("First argument must be a string (URI)"))?
+            .to_string();
+
+        let range: Range = serde_json::from_value(args[1].clone())
+            .map_err(|e| JsonRpcError::invalid_params(format!("Failed to parse range: {}", e)))?;
+
+        Ok((uri_str, range))
+    }
 }
 
 /// Start LSP server mode
@@ -1546,8 +1937,19 @@ async fn run_lsp_server(config: Config) {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| {
-        UniversalLsp::new(client, config.clone())
+    let inline_completion_registration = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let registration = inline_completion_registration.clone();
+    let (service, socket) = LspService::build(|client| UniversalLsp::new(client, config.clone(), registration))
+        .custom_method(inline_completion::protocol::METHOD, UniversalLsp::inline_completion)
+        .finish();
+    // lsp-types drops the inline completion client capabilities, so they are read from
+    // the `initialize` request before it is deserialized
+    let service = service.map_request(move |request: tower_lsp::jsonrpc::Request| {
+        if request.method() == "initialize" {
+            let dynamic = inline_completion::protocol::dynamic_registration(request.params());
+            inline_completion_registration.store(dynamic, std::sync::atomic::Ordering::Relaxed);
+        }
+        request
     });
 
     Server::new(stdin, stdout, socket).serve(service).await;
@@ -1576,7 +1978,7 @@ async fn run_acp_agent(config: Config) {
     println!("\n🚀 Starting ACP agent on stdio...\n");
 
     // Run the ACP agent
-    if let Err(e) = acp::run_agent().await {
+    if let Err(e) = acp::run_agent_with_config(std::path::PathBuf::from("."), config.ai.clone()).await {
         eprintln!("❌ ACP agent error: {}", e);
         std::process::exit(1);
     }
@@ -105,7 +105,7 @@ pub struct McpRequest {
     pub context: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Position {
     pub line: u32,
     pub character: u32,
@@ -120,6 +120,54 @@ pub struct McpResponse {
     pub documentation: Option<String>,
     /// Confidence score (0.0 - 1.0)
     pub confidence: Option<f32>,
+    /// Structured diagnostics; servers that only send `suggestions` leave this empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<McpDiagnostic>,
+}
+
+/// Range in the requested document (zero-based, like LSP)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpRange {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Diagnostic reported by an MCP server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpDiagnostic {
+    pub range: McpRange,
+    /// `error`, `warning`, `information` or `hint` (defaults to `warning`)
+    #[serde(default)]
+    pub severity: Option<String>,
+    #[serde(default)]
+    pub code: Option<String>,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_information: Vec<McpRelatedInformation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<McpFix>,
+}
+
+/// Location related to a diagnostic; `uri` defaults to the requested document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpRelatedInformation {
+    #[serde(default)]
+    pub uri: Option<String>,
+    pub range: McpRange,
+    pub message: String,
+}
+
+/// Fix for a diagnostic, as edits to the requested document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpFix {
+    pub title: String,
+    pub edits: Vec<McpTextEdit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpTextEdit {
+    pub range: McpRange,
+    pub new_text: String,
 }
 
 /// MCP Configuration
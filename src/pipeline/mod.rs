@@ -5,8 +5,12 @@
 
 use anyhow::Result;
 use std::sync::Arc;
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Range, TextEdit, Url,
+};
 
 use crate::config::Config;
+use crate::mcp::protocol::{McpDiagnostic, McpRange};
 use crate::mcp::{McpClient, McpConfig, McpRequest, McpResponse, Position, TransportType};
 
 /// Pipeline for MCP-enhanced LSP processing
@@ -97,6 +101,7 @@ pub fn merge_mcp_responses(responses: Vec<McpResponse>) -> McpResponse {
             suggestions: Vec::new(),
             documentation: None,
             confidence: None,
+            diagnostics: Vec::new(),
         };
     }
 
@@ -104,9 +109,15 @@ pub fn merge_mcp_responses(responses: Vec<McpResponse>) -> McpResponse {
     let mut all_suggestions = Vec::new();
     let mut all_docs = Vec::new();
     let mut confidences = Vec::new();
+    let mut all_diagnostics = Vec::new();
 
     for response in responses {
         all_suggestions.extend(response.suggestions);
+        for diagnostic in response.diagnostics {
+            if !all_diagnostics.contains(&diagnostic) {
+                all_diagnostics.push(diagnostic);
+            }
+        }
         if let Some(doc) = response.documentation {
             all_docs.push(doc);
         }
@@ -134,6 +145,7 @@ pub fn merge_mcp_responses(responses: Vec<McpResponse>) -> McpResponse {
             Some(all_docs.join("\n\n"))
         },
         confidence: avg_confidence,
+        diagnostics: all_diagnostics,
     }
 }
 
@@ -142,6 +154,126 @@ pub fn lsp_position_to_mcp(line: u32, character: u32) -> Position {
     Position { line, character }
 }
 
+/// Convert MCP range to LSP range
+pub fn mcp_range_to_lsp(range: &McpRange) -> Range {
+    Range {
+        start: tower_lsp::lsp_types::Position { line: range.start.line, character: range.start.character },
+        end: tower_lsp::lsp_types::Position { line: range.end.line, character: range.end.character },
+    }
+}
+
+/// Convert a diagnostics response of an MCP server for `uri` into LSP diagnostics
+///
+/// Structured diagnostics keep their ranges, and their fixes are stored under
+/// `data.mcp_fixes` for [`mcp_fixes`]. Legacy servers that only send plain suggestions
+/// get them as hints (and their documentation as information) at the start of the file.
+pub fn mcp_response_to_diagnostics(response: &McpResponse, uri: &Url, server_name: &str) -> Vec<Diagnostic> {
+    let source = format!("mcp:{}", server_name);
+
+    if !response.diagnostics.is_empty() {
+        return response
+            .diagnostics
+            .iter()
+            .map(|diagnostic| mcp_diagnostic_to_lsp(diagnostic, uri, &source))
+            .collect();
+    }
+
+    let legacy = |message: &String, severity| Diagnostic {
+        range: Range::default(),
+        severity: Some(severity),
+        code: None,
+        source: Some(source.clone()),
+        message: message.clone(),
+        related_information: None,
+        tags: None,
+        code_description: None,
+        data: None,
+    };
+
+    response
+        .suggestions
+        .iter()
+        .map(|suggestion| legacy(suggestion, DiagnosticSeverity::HINT))
+        .chain(response.documentation.iter().map(|doc| legacy(doc, DiagnosticSeverity::INFORMATION)))
+        .collect()
+}
+
+fn mcp_diagnostic_to_lsp(diagnostic: &McpDiagnostic, uri: &Url, source: &str) -> Diagnostic {
+    let severity = match diagnostic.severity.as_deref().map(|s| s.to_ascii_lowercase()).as_deref() {
+        Some("error") => DiagnosticSeverity::ERROR,
+        Some("information") | Some("info") => DiagnosticSeverity::INFORMATION,
+        Some("hint") => DiagnosticSeverity::HINT,
+        _ => DiagnosticSeverity::WARNING,
+    };
+
+    let related_information: Vec<DiagnosticRelatedInformation> = diagnostic
+        .related_information
+        .iter()
+        .filter_map(|related| {
+            let related_uri = match &related.uri {
+                Some(related_uri) => Url::parse(related_uri).ok()?,
+                None => uri.clone(),
+            };
+            Some(DiagnosticRelatedInformation {
+                location: Location {
+                    uri: related_uri,
+                    range: mcp_range_to_lsp(&related.range),
+                },
+                message: related.message.clone(),
+            })
+        })
+        .collect();
+
+    let fixes: Vec<serde_json::Value> = diagnostic
+        .fixes
+        .iter()
+        .map(|fix| {
+            let edits: Vec<TextEdit> = fix
+                .edits
+                .iter()
+                .map(|edit| TextEdit {
+                    range: mcp_range_to_lsp(&edit.range),
+                    new_text: edit.new_text.clone(),
+                })
+                .collect();
+            serde_json::json!({ "title": fix.title, "edits": edits })
+        })
+        .collect();
+
+    Diagnostic {
+        range: mcp_range_to_lsp(&diagnostic.range),
+        severity: Some(severity),
+        code: diagnostic.code.clone().map(NumberOrString::String),
+        source: Some(source.to_string()),
+        message: diagnostic.message.clone(),
+        related_information: if related_information.is_empty() { None } else { Some(related_information) },
+        tags: None,
+        code_description: None,
+        data: if fixes.is_empty() { None } else { Some(serde_json::json!({ "mcp_fixes": fixes })) },
+    }
+}
+
+/// Fixes attached to a diagnostic by [`mcp_response_to_diagnostics`], as titles and edits
+pub fn mcp_fixes(diagnostic: &Diagnostic) -> Vec<(String, Vec<TextEdit>)> {
+    let Some(fixes) = diagnostic
+        .data
+        .as_ref()
+        .and_then(|data| data.get("mcp_fixes"))
+        .and_then(|fixes| fixes.as_array())
+    else {
+        return Vec::new();
+    };
+
+    fixes
+        .iter()
+        .filter_map(|fix| {
+            let title = fix.get("title")?.as_str()?.to_string();
+            let edits: Vec<TextEdit> = serde_json::from_value(fix.get("edits")?.clone()).ok()?;
+            Some((title, edits))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,11 +293,13 @@ mod tests {
                 suggestions: vec!["fn".to_string(), "class".to_string()],
                 documentation: Some("First doc".to_string()),
                 confidence: Some(0.8),
+                diagnostics: Vec::new(),
             },
             McpResponse {
                 suggestions: vec!["fn".to_string(), "struct".to_string()],
                 documentation: Some("Second doc".to_string()),
                 confidence: Some(0.9),
+                diagnostics: Vec::new(),
             },
         ];
 
@@ -174,4 +308,66 @@ mod tests {
         assert!(merged.documentation.unwrap().contains("First doc"));
         assert_eq!(merged.confidence, Some(0.85)); // Average of 0.8 and 0.9
     }
+
+    #[test]
+    fn test_structured_mcp_diagnostics() {
+        let response: McpResponse = serde_json::from_value(serde_json::json!({
+            "suggestions": ["ignored when diagnostics are present"],
+            "documentation": null,
+            "confidence": null,
+            "diagnostics": [{
+                "range": {"start": {"line": 3, "character": 4}, "end": {"line": 3, "character": 9}},
+                "severity": "error",
+                "code": "SEC001",
+                "message": "Hard-coded secret",
+                "related_information": [{
+                    "range": {"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 6}},
+                    "message": "Declared here"
+                }],
+                "fixes": [{
+                    "title": "Read from environment",
+                    "edits": [{
+                        "range": {"start": {"line": 3, "character": 4}, "end": {"line": 3, "character": 9}},
+                        "new_text": "os.environ['TOKEN']"
+                    }]
+                }]
+            }]
+        }))
+        .unwrap();
+        let uri = Url::parse("file:///app.py").unwrap();
+
+        let diagnostics = mcp_response_to_diagnostics(&response, &uri, "security");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 3);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].code, Some(NumberOrString::String("SEC001".to_string())));
+        assert_eq!(diagnostics[0].source.as_deref(), Some("mcp:security"));
+        assert_eq!(diagnostics[0].related_information.as_ref().unwrap()[0].location.uri, uri);
+
+        let fixes = mcp_fixes(&diagnostics[0]);
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].0, "Read from environment");
+        assert_eq!(fixes[0].1[0].new_text, "os.environ['TOKEN']");
+    }
+
+    #[test]
+    fn test_legacy_mcp_suggestions_fallback() {
+        // Legacy servers send no `diagnostics` field at all
+        let response: McpResponse = serde_json::from_value(serde_json::json!({
+            "suggestions": ["Consider adding type hints"],
+            "documentation": "Style guide",
+            "confidence": 0.5
+        }))
+        .unwrap();
+        let uri = Url::parse("file:///app.py").unwrap();
+
+        let diagnostics = mcp_response_to_diagnostics(&response, &uri, "style");
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::HINT));
+        assert_eq!(diagnostics[0].range, Range::default());
+        assert_eq!(diagnostics[1].severity, Some(DiagnosticSeverity::INFORMATION));
+        assert!(mcp_fixes(&diagnostics[0]).is_empty());
+    }
 }
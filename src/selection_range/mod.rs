@@ -0,0 +1,194 @@
+//! Selection Range Module
+//!
+//! Provides expand/shrink selection by walking up the tree-sitter ancestors of the
+//! node under the cursor. The contents of bracketed nodes are offered as an extra
+//! step between the inner expression and the brackets themselves.
+
+use anyhow::Result;
+use tower_lsp::lsp_types::*;
+use crate::tree_sitter::{byte_to_position, position_to_byte, TreeSitterParser};
+
+/// Selection range provider
+#[derive(Debug)]
+pub struct SelectionRangeProvider {}
+
+impl SelectionRangeProvider {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Get one selection range chain per requested position
+    pub fn get_selection_ranges(
+        &self,
+        content: &str,
+        positions: &[Position],
+        lang: &str,
+    ) -> Result<Vec<SelectionRange>> {
+        let mut parser = TreeSitterParser::new()?;
+        if parser.set_language(lang).is_err() {
+            return Ok(Vec::new());
+        }
+
+        let tree = parser.parse(content, "temp")?;
+        let root = tree.root_node();
+
+        let mut selections = Vec::new();
+        for position in positions {
+            let offset = position_to_byte(content, *position);
+            let leaf = root
+                .descendant_for_byte_range(offset, offset)
+                .unwrap_or(root);
+
+            let ranges = self.ancestor_ranges(leaf, content);
+            selections.push(self.build_chain(content, ranges, *position));
+        }
+
+        Ok(selections)
+    }
+
+    /// Ranges from the innermost node outwards, each strictly containing the previous one
+    fn ancestor_ranges(&self, leaf: tree_sitter::Node, content: &str) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut push = |range: (usize, usize)| {
+            let grows = match ranges.last() {
+                Some(&(start, end)) => range != (start, end) && range.0 <= start && range.1 >= end,
+                None => true,
+            };
+            if grows {
+                ranges.push(range);
+            }
+        };
+
+        let mut current = Some(leaf);
+        while let Some(node) = current {
+            if let Some(inner) = self.bracket_contents(&node, content) {
+                push(inner);
+            }
+            push((node.start_byte(), node.end_byte()));
+            current = node.parent();
+        }
+
+        ranges
+    }
+
+    /// Byte range between a node's opening and closing bracket, without surrounding whitespace
+    fn bracket_contents(&self, node: &tree_sitter::Node, content: &str) -> Option<(usize, usize)> {
+        if node.child_count() < 3 {
+            return None;
+        }
+
+        let first = node.child(0)?;
+        let last = node.child(node.child_count() - 1)?;
+        let paired = matches!(
+            (first.kind(), last.kind()),
+            ("{", "}") | ("[", "]") | ("(", ")") | ("<", ">")
+        );
+        if !paired {
+            return None;
+        }
+
+        let inner = &content[first.end_byte()..last.start_byte()];
+        let start = first.end_byte() + (inner.len() - inner.trim_start().len());
+        let end = last.start_byte() - (inner.len() - inner.trim_end().len());
+
+        if start < end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    /// Nest ranges so that each one's parent is the next larger range
+    fn build_chain(&self, content: &str, ranges: Vec<(usize, usize)>, position: Position) -> SelectionRange {
+        let mut selection: Option<SelectionRange> = None;
+        for (start, end) in ranges.into_iter().rev() {
+            selection = Some(SelectionRange {
+                range: Range {
+                    start: byte_to_position(content, start),
+                    end: byte_to_position(content, end),
+                },
+                parent: selection.map(Box::new),
+            });
+        }
+
+        selection.unwrap_or(SelectionRange {
+            range: Range { start: position, end: position },
+            parent: None,
+        })
+    }
+
+}
+
+impl Default for SelectionRangeProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(selection: &SelectionRange) -> Vec<Range> {
+        let mut ranges = vec![selection.range];
+        let mut current = &selection.parent;
+        while let Some(parent) = current {
+            ranges.push(parent.range);
+            current = &parent.parent;
+        }
+        ranges
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range {
+            start: Position { line: start.0, character: start.1 },
+            end: Position { line: end.0, character: end.1 },
+        }
+    }
+
+    #[test]
+    fn test_rust_selection_expands_outwards() {
+        let provider = SelectionRangeProvider::new();
+        let content = "fn main() {\n    let total = add(first, second);\n}\n";
+
+        // Cursor inside `first`
+        let selections = provider
+            .get_selection_ranges(content, &[Position { line: 1, character: 22 }], "rust")
+            .unwrap();
+        assert_eq!(selections.len(), 1);
+
+        let ranges = chain(&selections[0]);
+        assert_eq!(ranges[0], range((1, 20), (1, 25)), "identifier first");
+        assert!(ranges.contains(&range((1, 20), (1, 33))), "argument list contents: {:?}", ranges);
+        assert!(ranges.contains(&range((1, 19), (1, 34))), "argument list with parens: {:?}", ranges);
+        assert!(ranges.contains(&range((1, 4), (1, 35))), "whole statement: {:?}", ranges);
+
+        // Every step strictly contains the previous one
+        for pair in ranges.windows(2) {
+            assert!(pair[0] != pair[1]);
+            assert!(pair[1].start <= pair[0].start && pair[1].end >= pair[0].end);
+        }
+    }
+
+    #[test]
+    fn test_multiple_positions() {
+        let provider = SelectionRangeProvider::new();
+        let content = "x = [1, 2, 3]\ny = x\n";
+
+        let positions = [Position { line: 0, character: 5 }, Position { line: 1, character: 4 }];
+        let selections = provider.get_selection_ranges(content, &positions, "python").unwrap();
+
+        assert_eq!(selections.len(), 2);
+        assert_eq!(selections[0].range, range((0, 5), (0, 6)));
+        assert_eq!(selections[1].range, range((1, 4), (1, 5)));
+    }
+
+    #[test]
+    fn test_unsupported_language() {
+        let provider = SelectionRangeProvider::new();
+        let selections = provider
+            .get_selection_ranges("text", &[Position { line: 0, character: 0 }], "unsupported")
+            .unwrap();
+        assert!(selections.is_empty());
+    }
+}
@@ -4,7 +4,7 @@
 
 use anyhow::Result;
 use tower_lsp::lsp_types::*;
-use crate::tree_sitter::TreeSitterParser;
+use crate::tree_sitter::{position_to_byte, TreeSitterParser};
 
 /// Signature help provider
 #[derive(Debug)]
@@ -28,7 +28,7 @@ impl SignatureHelpProvider {
         }
 
         let tree = parser.parse(content, "temp")?;
-        let byte_offset = self.position_to_byte(content, position);
+        let byte_offset = position_to_byte(content, position);
 
         // Find the function call node at the cursor position
         let root = tree.root_node();
@@ -199,6 +199,23 @@ impl SignatureHelpProvider {
         None
     }
 
+    /// Signature of a function definition node, for the languages with signature help
+    pub fn definition_signature(
+        &self,
+        node: tree_sitter::Node,
+        content: &str,
+        lang: &str,
+    ) -> Option<SignatureInformation> {
+        match (lang, node.kind()) {
+            ("python", "function_definition") => self.extract_python_signature(node, content),
+            ("javascript" | "typescript" | "tsx", "function_declaration" | "function" | "method_definition") => {
+                self.extract_js_signature(node, content)
+            }
+            ("rust", "function_item" | "function_signature_item") => self.extract_rust_signature(node, content),
+            _ => None,
+        }
+    }
+
     /// Extract Python function signature
     fn extract_python_signature(
         &self,
@@ -310,29 +327,6 @@ impl SignatureHelpProvider {
         })
     }
 
-    /// Helper: Convert LSP position to byte offset
-    fn position_to_byte(&self, source: &str, position: Position) -> usize {
-        let mut byte_offset = 0;
-        let mut current_line = 0;
-        let mut current_char = 0;
-
-        for ch in source.chars() {
-            if current_line == position.line && current_char == position.character {
-                return byte_offset;
-            }
-
-            if ch == '\n' {
-                current_line += 1;
-                current_char = 0;
-            } else {
-                current_char += 1;
-            }
-
-            byte_offset += ch.len_utf8();
-        }
-
-        byte_offset
-    }
 }
 
 impl Default for SignatureHelpProvider {
@@ -459,12 +453,11 @@ func(1, 2, 3, 4)
 
     #[test]
     fn test_position_to_byte_conversion() {
-        let provider = SignatureHelpProvider::new();
         let source = "hello\nworld\n";
 
-        assert_eq!(provider.position_to_byte(source, Position { line: 0, character: 0 }), 0);
-        assert_eq!(provider.position_to_byte(source, Position { line: 0, character: 5 }), 5);
-        assert_eq!(provider.position_to_byte(source, Position { line: 1, character: 0 }), 6);
-        assert_eq!(provider.position_to_byte(source, Position { line: 1, character: 5 }), 11);
+        assert_eq!(position_to_byte(source, Position { line: 0, character: 0 }), 0);
+        assert_eq!(position_to_byte(source, Position { line: 0, character: 5 }), 5);
+        assert_eq!(position_to_byte(source, Position { line: 1, character: 0 }), 6);
+        assert_eq!(position_to_byte(source, Position { line: 1, character: 5 }), 11);
     }
 }
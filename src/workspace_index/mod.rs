@@ -12,8 +12,10 @@ use tokio::fs;
 use tower_lsp::lsp_types::{Location, SymbolKind, Url};
 use tracing::{debug, info, warn};
 
-use crate::language::detect_language;
+use crate::call_hierarchy::{extract_calls, CallSite, CallableDefinition, FileCalls};
+use crate::language::{detect_language, grammar_name};
 use crate::tree_sitter::TreeSitterParser;
+use crate::type_hierarchy::{extract_types, FileTypes, TypeDefinition, TypeRelation};
 
 /// Maximum number of files to index
 const MAX_INDEXED_FILES: usize = 10_000;
@@ -79,8 +81,12 @@ pub struct WorkspaceIndex {
     symbols_by_file: Arc<DashMap<String, Vec<IndexedSymbol>>>,
     /// Global symbol lookup by name (for fast search)
     symbols_by_name: Arc<DashMap<String, Vec<IndexedSymbol>>>,
+    /// Call sites and callable definitions by file URI
+    calls_by_file: Arc<DashMap<String, FileCalls>>,
+    /// Type definitions and supertype relations by file URI
+    types_by_file: Arc<DashMap<String, FileTypes>>,
     /// Workspace root path
-    workspace_root: Arc<Option<PathBuf>>,
+    workspace_root: Arc<std::sync::RwLock<Option<PathBuf>>>,
     /// Last full index timestamp
     last_indexed: Arc<std::sync::RwLock<Option<SystemTime>>>,
     /// Parser cache
@@ -93,21 +99,30 @@ impl WorkspaceIndex {
         Self {
             symbols_by_file: Arc::new(DashMap::new()),
             symbols_by_name: Arc::new(DashMap::new()),
-            workspace_root: Arc::new(None),
+            calls_by_file: Arc::new(DashMap::new()),
+            types_by_file: Arc::new(DashMap::new()),
+            workspace_root: Arc::new(std::sync::RwLock::new(None)),
             last_indexed: Arc::new(std::sync::RwLock::new(None)),
             parsers: Arc::new(DashMap::new()),
         }
     }
 
     /// Set workspace root
-    pub fn set_workspace_root(&mut self, root: PathBuf) {
-        self.workspace_root = Arc::new(Some(root));
+    ///
+    /// Shared by all clones of the index.
+    pub fn set_workspace_root(&self, root: PathBuf) {
+        *self.workspace_root.write().unwrap() = Some(root);
+    }
+
+    /// Workspace root, once set
+    pub fn workspace_root(&self) -> Option<PathBuf> {
+        self.workspace_root.read().unwrap().clone()
     }
 
     /// Index the entire workspace
     pub async fn index_workspace(&self) -> Result<usize> {
-        let root = match &*self.workspace_root {
-            Some(root) => root.clone(),
+        let root = match self.workspace_root.read().unwrap().clone() {
+            Some(root) => root,
             None => {
                 warn!("No workspace root set, skipping index");
                 return Ok(0);
@@ -120,6 +135,8 @@ impl WorkspaceIndex {
         // Clear existing index
         self.symbols_by_file.clear();
         self.symbols_by_name.clear();
+        self.calls_by_file.clear();
+        self.types_by_file.clear();
 
         // Scan workspace for files
         let files = self.scan_workspace(&root).await?;
@@ -188,7 +205,7 @@ impl WorkspaceIndex {
                     // Check if file is a source code file
                     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
                         let lang = detect_language(&format!("file.{}", ext));
-                        if lang != "plaintext" && lang != "unknown" {
+                        if lang != "Unknown" {
                             files.push(path);
                         }
                     }
@@ -206,41 +223,53 @@ impl WorkspaceIndex {
             .await
             .context("Failed to read file")?;
 
+        // Same form as the URIs editors send, so open documents replace their entries
+        let uri_str = Url::from_file_path(file_path)
+            .map(|u| u.to_string())
+            .unwrap_or_else(|_| format!("file://{}", file_path.display()));
+        self.index_content(&uri_str, &content)
+    }
+
+    /// Index a document from its content, replacing any previous entries for the URI
+    ///
+    /// Used for files read from disk and for open documents, whose buffer may differ
+    /// from what is saved.
+    pub fn index_content(&self, uri_str: &str, content: &str) -> Result<usize> {
         // Detect language
-        let lang = detect_language(file_path.to_str().unwrap_or(""));
+        let lang = grammar_name(uri_str);
 
         // Get or create parser
-        if !self.parsers.contains_key(&lang.to_string()) {
+        if !self.parsers.contains_key(&lang) {
             match TreeSitterParser::new() {
                 Ok(mut p) => {
                     let _ = p.set_language(&lang);
-                    self.parsers.insert(lang.to_string(), p);
+                    self.parsers.insert(lang.clone(), p);
                 }
                 Err(_) => {
                     // Fallback: create a parser without setting language
                     if let Ok(p) = TreeSitterParser::new() {
-                        self.parsers.insert(lang.to_string(), p);
+                        self.parsers.insert(lang.clone(), p);
                     }
                 }
             }
         }
 
         // Get a mutable reference to the parser
-        let mut parser = match self.parsers.get_mut(&lang.to_string()) {
+        let mut parser = match self.parsers.get_mut(&lang) {
             Some(p) => p,
             None => return Ok(0), // Skip if parser creation failed
         };
 
         // Parse file
-        let uri_str = format!("file://{}", file_path.display());
-        let tree = parser.parse(&content, &uri_str)?;
+        let tree = parser.parse(content, uri_str)?;
 
         // Extract symbols
-        let symbols = parser.extract_symbols(&tree, &content, &lang)?;
+        let symbols = parser.extract_symbols(&tree, content, &lang)?;
+        drop(parser);
 
         // Convert to indexed symbols
         let mut indexed_symbols = Vec::new();
-        let uri = Url::parse(&uri_str).context("Failed to parse URI")?;
+        let uri = Url::parse(uri_str).context("Failed to parse URI")?;
 
         for symbol in symbols {
             let indexed = IndexedSymbol {
@@ -260,9 +289,12 @@ impl WorkspaceIndex {
 
         let symbol_count = indexed_symbols.len();
 
+        // Drop stale entries from a previous index of this file
+        self.remove_file(uri_str);
+
         // Store in index
         self.symbols_by_file
-            .insert(uri_str.clone(), indexed_symbols.clone());
+            .insert(uri_str.to_string(), indexed_symbols.clone());
 
         // Index by name for fast lookup
         for symbol in indexed_symbols {
@@ -272,9 +304,28 @@ impl WorkspaceIndex {
                 .push(symbol);
         }
 
+        // Call graph and type hierarchy data
+        self.calls_by_file.insert(uri_str.to_string(), extract_calls(content, &lang)?);
+        self.types_by_file.insert(uri_str.to_string(), extract_types(content, &lang)?);
+
         Ok(symbol_count)
     }
 
+    /// Remove everything indexed for a file
+    pub fn remove_file(&self, uri: &str) {
+        if let Some((_, old_symbols)) = self.symbols_by_file.remove(uri) {
+            for symbol in old_symbols {
+                if let Some(mut entries) = self.symbols_by_name.get_mut(&symbol.name) {
+                    entries.retain(|s| s.location.uri.as_str() != uri);
+                }
+            }
+            self.symbols_by_name.retain(|_, entries| !entries.is_empty());
+        }
+
+        self.calls_by_file.remove(uri);
+        self.types_by_file.remove(uri);
+    }
+
     /// Search symbols by name or pattern
     pub fn search_symbols(&self, query: &str) -> Vec<IndexedSymbol> {
         let query_lower = query.to_lowercase();
@@ -300,11 +351,101 @@ impl WorkspaceIndex {
         results
     }
 
+    /// Symbols whose name matches a predicate
+    pub fn symbols_where<F>(&self, predicate: F) -> Vec<IndexedSymbol>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.symbols_by_name
+            .iter()
+            .filter(|entry| predicate(entry.key()))
+            .flat_map(|entry| entry.value().clone())
+            .collect()
+    }
+
     /// Get all symbols in a file
     pub fn get_file_symbols(&self, uri: &str) -> Option<Vec<IndexedSymbol>> {
         self.symbols_by_file.get(uri).map(|s| s.clone())
     }
 
+    /// Callables and call sites indexed for a file
+    pub fn file_calls(&self, uri: &str) -> Option<FileCalls> {
+        self.calls_by_file.get(uri).map(|c| c.clone())
+    }
+
+    /// Every indexed call site of `name`, with the URI of the calling file
+    pub fn calls_to(&self, name: &str) -> Vec<(Url, CallSite)> {
+        let mut calls = Vec::new();
+        for entry in self.calls_by_file.iter() {
+            let Ok(uri) = Url::parse(entry.key()) else {
+                continue;
+            };
+            for call in entry.value().calls.iter().filter(|c| c.callee == name) {
+                calls.push((uri.clone(), call.clone()));
+            }
+        }
+        calls
+    }
+
+    /// Callable definitions named `name` across the workspace
+    pub fn callables_named(&self, name: &str) -> Vec<(Url, CallableDefinition)> {
+        let mut callables = Vec::new();
+        for entry in self.calls_by_file.iter() {
+            let Ok(uri) = Url::parse(entry.key()) else {
+                continue;
+            };
+            for callable in entry.value().callables.iter().filter(|c| c.name == name) {
+                callables.push((uri.clone(), callable.clone()));
+            }
+        }
+        callables
+    }
+
+    /// Methods and functions whose enclosing class, impl or receiver type is `container`
+    pub fn callables_in(&self, container: &str) -> Vec<(Url, CallableDefinition)> {
+        let mut callables = Vec::new();
+        for entry in self.calls_by_file.iter() {
+            let Ok(uri) = Url::parse(entry.key()) else {
+                continue;
+            };
+            for callable in entry.value().callables.iter().filter(|c| c.container.as_deref() == Some(container)) {
+                callables.push((uri.clone(), callable.clone()));
+            }
+        }
+        callables
+    }
+
+    /// Type definitions named `name` across the workspace
+    pub fn types_named(&self, name: &str) -> Vec<(Url, TypeDefinition)> {
+        let mut types = Vec::new();
+        for entry in self.types_by_file.iter() {
+            let Ok(uri) = Url::parse(entry.key()) else {
+                continue;
+            };
+            for definition in entry.value().definitions.iter().filter(|d| d.name == name) {
+                types.push((uri.clone(), definition.clone()));
+            }
+        }
+        types
+    }
+
+    /// Type relations matching a predicate, with the URI of the declaring file
+    pub fn relations_where<F>(&self, predicate: F) -> Vec<(Url, TypeRelation)>
+    where
+        F: Fn(&TypeRelation) -> bool,
+    {
+        let mut relations = Vec::new();
+        for entry in self.types_by_file.iter() {
+            let Ok(uri) = Url::parse(entry.key()) else {
+                continue;
+            };
+            for relation in entry.value().relations.iter().filter(|r| predicate(r)) {
+                relations.push((uri.clone(), relation.clone()));
+            }
+        }
+        relations
+    }
+
     /// Get workspace context for Claude
     ///
     /// Returns a formatted string with symbol information suitable for AI context
@@ -360,6 +501,11 @@ impl WorkspaceIndex {
         context
     }
 
+    /// URIs of all indexed files
+    pub fn indexed_files(&self) -> Vec<String> {
+        self.symbols_by_file.iter().map(|e| e.key().clone()).collect()
+    }
+
     /// Get statistics about the index
     pub fn get_statistics(&self) -> IndexStatistics {
         let total_files = self.symbols_by_file.len();
@@ -377,6 +523,8 @@ impl WorkspaceIndex {
     pub fn clear(&self) {
         self.symbols_by_file.clear();
         self.symbols_by_name.clear();
+        self.calls_by_file.clear();
+        self.types_by_file.clear();
         *self.last_indexed.write().unwrap() = None;
     }
 }
@@ -462,6 +610,39 @@ mod tests {
         assert!(text.contains("MathUtils"));
     }
 
+    #[test]
+    fn test_index_content_replaces_previous_entries() {
+        let index = WorkspaceIndex::new();
+        let uri = "file:///ws/lib.py";
+
+        index.index_content(uri, "def old_name():\n    pass\n").unwrap();
+        assert_eq!(index.search_symbols("old_name").len(), 1);
+        assert_eq!(index.callables_named("old_name").len(), 1);
+
+        index.index_content(uri, "def new_name():\n    pass\n").unwrap();
+        assert!(index.search_symbols("old_name").is_empty());
+        assert!(index.callables_named("old_name").is_empty());
+        assert_eq!(index.callables_named("new_name").len(), 1);
+
+        index.remove_file(uri);
+        assert_eq!(index.get_statistics().total_files, 0);
+        assert!(index.file_calls(uri).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_index_workspace_uses_shared_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() { helper(); }\nfn helper() {}\n").unwrap();
+
+        let index = WorkspaceIndex::new();
+        // Setting the root through a clone must affect the original
+        index.clone().set_workspace_root(dir.path().to_path_buf());
+        let count = index.index_workspace().await.unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(index.calls_to("helper").len(), 1);
+    }
+
     #[test]
     fn test_symbol_kind_name() {
         assert_eq!(symbol_kind_name(SymbolKind::FUNCTION), "Function");
@@ -48,6 +48,8 @@ fn create_test_config() -> Config {
         proxy: ProxyConfig {
             servers: std::collections::HashMap::new(),
         },
+        ai: Default::default(),
+        trust: Default::default(),
     }
 }
 
@@ -309,6 +311,7 @@ async fn test_coordinator_cache_functionality() {
         suggestions: vec!["test".to_string()],
         documentation: Some("Test doc".to_string()),
         confidence: Some(0.9),
+        diagnostics: Vec::new(),
     };
 
     // Send SetCache request
@@ -114,6 +114,7 @@ async fn test_mcp_response_structure() {
         ],
         documentation: Some("Main function documentation".to_string()),
         confidence: Some(0.95),
+        diagnostics: Vec::new(),
     };
 
     assert_eq!(response.suggestions.len(), 2);
@@ -127,6 +128,7 @@ async fn test_mcp_response_minimal() {
         suggestions: vec!["suggestion".to_string()],
         documentation: None,
         confidence: None,
+        diagnostics: Vec::new(),
     };
 
     assert_eq!(response.suggestions.len(), 1);
@@ -411,6 +413,7 @@ async fn test_mcp_response_with_many_suggestions() {
         suggestions: suggestions.clone(),
         documentation: None,
         confidence: Some(0.8),
+        diagnostics: Vec::new(),
     };
 
     assert_eq!(response.suggestions.len(), 100);
@@ -427,6 +430,7 @@ async fn test_mcp_confidence_range() {
             suggestions: vec!["test".to_string()],
             documentation: None,
             confidence: Some(conf),
+            diagnostics: Vec::new(),
         };
 
         assert!(response.confidence.unwrap() >= 0.0);
@@ -475,6 +479,7 @@ async fn test_mcp_empty_suggestions() {
         suggestions: vec![],
         documentation: None,
         confidence: None,
+        diagnostics: Vec::new(),
     };
 
     assert_eq!(response.suggestions.len(), 0);
@@ -494,6 +499,7 @@ async fn test_mcp_documentation_formats() {
             suggestions: vec!["test".to_string()],
             documentation: Some(doc.to_string()),
             confidence: None,
+            diagnostics: Vec::new(),
         };
 
         assert!(response.documentation.is_some());
@@ -48,6 +48,7 @@ async fn test_mcp_response_structure() {
         suggestions: vec!["suggestion1".to_string(), "suggestion2".to_string()],
         documentation: Some("Test documentation".to_string()),
         confidence: None,
+        diagnostics: Vec::new(),
     };
 
     assert_eq!(response.suggestions.len(), 2);
@@ -194,12 +195,14 @@ async fn test_mcp_response_merging() {
         suggestions: vec!["suggestion1".to_string()],
         documentation: Some("Doc from server 1".to_string()),
         confidence: Some(0.8),
+        diagnostics: Vec::new(),
     };
 
     let response2 = McpResponse {
         suggestions: vec!["suggestion2".to_string(), "suggestion3".to_string()],
         documentation: Some("Doc from server 2".to_string()),
         confidence: Some(0.9),
+        diagnostics: Vec::new(),
     };
 
     // Manually merge
@@ -218,6 +221,7 @@ async fn test_mcp_empty_response() {
         suggestions: vec![],
         documentation: None,
         confidence: None,
+        diagnostics: Vec::new(),
     };
 
     assert!(empty_response.suggestions.is_empty());
@@ -231,6 +235,7 @@ async fn test_mcp_response_with_confidence() {
         suggestions: vec!["test".to_string()],
         documentation: None,
         confidence: Some(0.95),
+        diagnostics: Vec::new(),
     };
 
     assert!(response.confidence.is_some());
@@ -342,12 +347,14 @@ async fn test_mcp_suggestion_deduplication() {
         suggestions: vec!["foo".to_string(), "bar".to_string()],
         documentation: None,
         confidence: None,
+        diagnostics: Vec::new(),
     };
 
     let response2 = McpResponse {
         suggestions: vec!["bar".to_string(), "baz".to_string()],
         documentation: None,
         confidence: None,
+        diagnostics: Vec::new(),
     };
 
     // Merge and deduplicate
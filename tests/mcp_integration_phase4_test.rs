@@ -26,6 +26,7 @@ fn test_mcp_response_structure() {
         suggestions: vec!["suggestion1".to_string(), "suggestion2".to_string()],
         documentation: Some("This is documentation".to_string()),
         confidence: Some(0.95),
+        diagnostics: Vec::new(),
     };
 
     assert_eq!(response.suggestions.len(), 2);
@@ -83,6 +84,7 @@ fn test_mcp_response_empty_suggestions() {
         suggestions: Vec::new(),
         documentation: None,
         confidence: None,
+        diagnostics: Vec::new(),
     };
 
     assert!(response.suggestions.is_empty());
@@ -99,6 +101,7 @@ fn test_mcp_response_with_confidence_scores() {
             suggestions: vec!["test".to_string()],
             documentation: None,
             confidence: Some(score),
+            diagnostics: Vec::new(),
         };
 
         assert_eq!(response.confidence, Some(score));
@@ -157,6 +160,7 @@ fn test_mcp_response_multiple_suggestions() {
         suggestions: suggestions.clone(),
         documentation: None,
         confidence: Some(0.8),
+        diagnostics: Vec::new(),
     };
 
     assert_eq!(response.suggestions.len(), 5);
@@ -216,6 +220,7 @@ fn test_mcp_response_with_long_documentation() {
         suggestions: vec![],
         documentation: Some(long_doc.clone()),
         confidence: Some(0.99),
+        diagnostics: Vec::new(),
     };
 
     assert_eq!(response.documentation, Some(long_doc));
@@ -301,6 +306,7 @@ fn test_mcp_response_serialization() {
         suggestions: vec!["test1".to_string(), "test2".to_string()],
         documentation: Some("docs".to_string()),
         confidence: Some(0.95),
+        diagnostics: Vec::new(),
     };
 
     // Test that response can be serialized to JSON
@@ -336,6 +342,7 @@ fn test_mcp_response_high_confidence() {
         suggestions: vec!["highly_confident_suggestion".to_string()],
         documentation: Some("Very reliable information".to_string()),
         confidence: Some(0.99),
+        diagnostics: Vec::new(),
     };
 
     assert!(response.confidence.unwrap() > 0.9);
@@ -347,6 +354,7 @@ fn test_mcp_response_low_confidence() {
         suggestions: vec!["uncertain_suggestion".to_string()],
         documentation: None,
         confidence: Some(0.3),
+        diagnostics: Vec::new(),
     };
 
     assert!(response.confidence.unwrap() < 0.5);